@@ -1,5 +1,3 @@
-use actix_cors::Cors;
-use actix_session::CookieSession;
 use actix_web::{get, App, HttpResponse, HttpServer, Responder};
 use rustls::internal::pemfile::{certs, pkcs8_private_keys};
 use rustls::{NoClientAuth, ServerConfig};
@@ -7,41 +5,91 @@ use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::io::BufReader;
-use time::Duration;
 
 /// A layer that defines data structure.
 pub mod models {
     /// Model related to authentication.
     pub mod auth;
+    /// Model related to category of posts.
+    pub mod category;
     /// Model related to error.
     pub mod error;
+    /// Model related to OAuth.
+    pub mod oauth;
     /// Model related to post.
     pub mod post;
     /// Model related to user.
     pub mod user;
+    /// Model related to user key.
+    pub mod user_key;
+    /// Model related to webhook.
+    pub mod webhook;
 }
 
 /// A presentation layer that makes API public and passes request to back-end service.
 pub mod routes {
     /// API related to authentication.
     pub mod auth;
+    /// API related to category of posts.
+    pub mod category;
+    /// API related to OAuth.
+    pub mod oauth;
     /// API related to post.
     pub mod post;
     /// API related to user.
     pub mod user;
+    /// API related to user key.
+    pub mod user_key;
+    /// API related to webhook.
+    pub mod webhook;
+
+    use actix_web::web;
+
+    /// Initializes the routes of all modules, which are mounted under the version of the API.
+    pub fn init_routes(cfg: &mut web::ServiceConfig) {
+        auth::init_routes(cfg);
+        category::init_routes(cfg);
+        oauth::init_routes(cfg);
+        post::init_routes(cfg);
+        user::init_routes(cfg);
+        user_key::init_routes(cfg);
+        webhook::init_routes(cfg);
+    }
 }
 
 /// Reusable functions for multiple modules.
 pub mod utils {
+    /// Utilities related to API key.
+    pub mod api_key_util;
+    /// Utilities related to compression of responses.
+    pub mod compress_util;
+    /// Utilities related to CORS.
+    pub mod cors_util;
+    /// Utilities related to environment variables.
+    pub mod env_util;
     /// Utilities related to HTTP.
     pub mod http_util;
     /// Utilities related to service.
     pub mod meta_util;
+    /// Utilities related to the body of requests.
+    pub mod payload_util;
     /// Utilities related to session.
     pub mod session_util;
+    /// Fixtures shared by tests of multiple modules.
+    #[cfg(test)]
+    pub mod test_util;
+    /// Utilities related to versioning of the API.
+    pub mod version_util;
 }
 
+use utils::api_key_util::ApiKeyAuthentication;
+use utils::compress_util::{self, CompressionConfig};
+use utils::cors_util::{self, CorsConfig};
 use utils::meta_util::{MetaInfo, ENV};
+use utils::payload_util::{self, PayloadLimits};
+use utils::session_util::{self, SessionCookieConfig};
+use utils::version_util;
+use utils::{env_util, http_util};
 
 /// Health check
 #[get("/")]
@@ -62,30 +110,29 @@ async fn main() -> std::io::Result<()> {
     let port = env::var("PORT").expect("PORT not found");
     let address = format!("{}:{}", host, port);
 
-    let server = HttpServer::new(|| {
-        let client_address = env::var("CLIENT_ADDRESS").expect("CLIENT_ADDRESS not found");
+    let cookie_config = SessionCookieConfig::from_env(meta_info.is_production());
+    let cors_config = CorsConfig::from_env();
+    let compression_config = CompressionConfig::from_env();
+    let payload_limits = PayloadLimits::from_env();
+    // The unversioned routes are kept until `LEGACY_ROUTES_ENABLED` is set to false.
+    let legacy_routes_enabled = env_util::get_env_var_or("LEGACY_ROUTES_ENABLED", true);
+
+    let server = HttpServer::new(move || {
         App::new()
-            .wrap(
-                Cors::default()
-                    .allowed_origin(&client_address)
-                    .allowed_methods(vec!["GET", "POST", "PATCH", "DELETE"])
-                    .allowed_headers(vec![
-                        http::header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
-                        http::header::CONTENT_TYPE,
-                    ])
-                    .supports_credentials()
-                    .max_age(3600),
-            )
-            .wrap(
-                CookieSession::signed(&[0; 64])
-                    .secure(true)
-                    .http_only(true)
-                    .max_age_time(Duration::days(30)),
-            )
+            .wrap_fn(move |req, srv| payload_util::limit_payload(req, srv, payload_limits))
+            .wrap(cors_util::get_cors(&cors_config))
+            .wrap(ApiKeyAuthentication)
+            .wrap(session_util::get_cookie_session(&cookie_config))
+            .wrap(version_util::get_version_headers())
+            .wrap_fn(move |req, srv| compress_util::skip_compression(req, srv, compression_config))
+            .wrap(compress_util::get_compress())
+            .wrap_fn(http_util::forward_client_ip)
+            .app_data(payload_util::get_json_config(&payload_limits))
+            .app_data(payload_util::get_payload_config(&payload_limits))
             .service(health_check)
-            .configure(routes::auth::init_routes)
-            .configure(routes::post::init_routes)
-            .configure(routes::user::init_routes)
+            .configure(|cfg| {
+                version_util::init_versioned_routes(cfg, routes::init_routes, legacy_routes_enabled)
+            })
     });
 
     println!("Server running at {}", address);
@@ -1,3 +1,4 @@
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
 /// Arguments for `GET /auth` API.
@@ -5,6 +6,17 @@ use serde::{Deserialize, Serialize};
 pub struct LoginArgs {
     pub email: String,
     pub password: String,
+    pub totp_code: Option<String>,
+}
+
+/// Arguments for `POST /auth/login` API of the service.
+#[derive(Serialize, Deserialize)]
+pub struct ServiceLoginArgs {
+    pub email: String,
+    pub password: String,
+    pub totp_code: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
 }
 
 /// Arguments for `POST /auth/token` API.
@@ -14,6 +26,14 @@ pub struct SetSignUpTokenArgs {
     pub email: String,
     pub password: String,
     pub avatar_url: Option<String>,
+    pub locale: Option<String>,
+    pub invite_code: Option<String>,
+}
+
+/// Arguments for `POST /auth/token/sign_up/resend` API.
+#[derive(Serialize, Deserialize)]
+pub struct ResendSignUpTokenArgs {
+    pub email: String,
 }
 
 /// Arguments for `POST /auth/token/password` API.
@@ -22,6 +42,19 @@ pub struct SetPasswordTokenArgs {
     pub email: String,
 }
 
+/// Arguments for `POST /auth/magic_link` API.
+#[derive(Serialize, Deserialize)]
+pub struct RequestMagicLinkArgs {
+    pub email: String,
+}
+
+/// Arguments for `POST /auth/magic_link/login` API.
+#[derive(Serialize, Deserialize)]
+pub struct ConsumeMagicLinkArgs {
+    pub token: String,
+    pub totp_code: Option<String>,
+}
+
 /// Session containing information of the logged-in user.
 #[derive(Serialize, Deserialize)]
 pub struct UserSession {
@@ -30,4 +63,145 @@ pub struct UserSession {
     pub user_name: String,
     pub user_public_key: String,
     pub user_avatar_url: Option<String>,
+    pub user_session_epoch: u64,
+    pub user_session_id: String,
+    pub user_last_login_at: Option<NaiveDateTime>,
+    pub user_previous_login_at: Option<NaiveDateTime>,
+    /// A version of the terms of service the user has accepted last.
+    #[serde(default)]
+    pub user_tos_version_accepted: Option<String>,
+    pub user_session_started_at: NaiveDateTime,
+    pub user_session_expires_at: NaiveDateTime,
+}
+
+impl UserSession {
+    /// Returns the profile of the logged-in user in the session.
+    ///
+    /// # Arguments
+    ///
+    /// * `tos_version` - A current version of the terms of service, which the user is asked to
+    ///   accept if the user hasn't accepted it yet.
+    pub fn to_current_user(&self, tos_version: &str) -> CurrentUserDTO {
+        CurrentUserDTO {
+            id: self.user_id,
+            email: self.user_email.clone(),
+            name: self.user_name.clone(),
+            avatar_url: self.user_avatar_url.clone(),
+            public_key: self.user_public_key.clone(),
+            tos_acceptance_required: self.user_tos_version_accepted.as_deref() != Some(tos_version),
+        }
+    }
+}
+
+/// Profile of the logged-in user, which is kept in the session.
+#[derive(Serialize, Deserialize)]
+pub struct CurrentUserDTO {
+    pub id: u64,
+    pub email: String,
+    pub name: String,
+    pub avatar_url: Option<String>,
+    pub public_key: String,
+    /// Whether the user has to accept the current version of the terms of service.
+    pub tos_acceptance_required: bool,
+}
+
+/// Arguments for `POST /auth/sessions/refresh` API of the service.
+#[derive(Serialize, Deserialize)]
+pub struct ServiceRefreshSessionArgs {
+    pub user_id: u64,
+    pub user_session_epoch: u64,
+    pub user_session_id: String,
+    pub user_session_started_at: NaiveDateTime,
+    pub user_session_expires_at: NaiveDateTime,
+}
+
+/// An active session of the user, which can be revoked from another session.
+#[derive(Serialize, Deserialize)]
+pub struct ActiveSession {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Arguments for `POST /auth/sessions/invalidate` API of the service.
+#[derive(Serialize, Deserialize)]
+pub struct ServiceInvalidateSessionsArgs {
+    pub user_id: u64,
+}
+
+/// Arguments for `POST /auth/change-password` API.
+#[derive(Serialize, Deserialize)]
+pub struct ChangePasswordArgs {
+    pub current_password: String,
+    pub new_password: String,
+    pub invalidate_other_sessions: Option<bool>,
+}
+
+/// Arguments for `POST /auth/change-password` API of the service.
+#[derive(Serialize, Deserialize)]
+pub struct ServiceChangePasswordArgs {
+    pub user_id: u64,
+    pub user_session_id: String,
+    pub current_password: String,
+    pub new_password: String,
+    pub invalidate_other_sessions: bool,
+}
+
+/// Arguments for `POST /auth/totp` API of the service.
+#[derive(Serialize, Deserialize)]
+pub struct ServiceEnrollTotpArgs {
+    pub user_id: u64,
+}
+
+/// Arguments for `POST /auth/totp/confirm` API.
+#[derive(Serialize, Deserialize)]
+pub struct ConfirmTotpArgs {
+    pub code: String,
+}
+
+/// Arguments for `POST /auth/totp/confirm` API of the service.
+#[derive(Serialize, Deserialize)]
+pub struct ServiceConfirmTotpArgs {
+    pub user_id: u64,
+    pub code: String,
+}
+
+/// Arguments for `POST /auth/totp/backup_codes` API of the service.
+#[derive(Serialize, Deserialize)]
+pub struct ServiceGenerateBackupCodesArgs {
+    pub user_id: u64,
+}
+
+/// Arguments for `POST /auth/api-keys` API.
+#[derive(Serialize, Deserialize)]
+pub struct CreateApiKeyArgs {
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// Arguments for `POST /auth/api-keys` API of the service.
+#[derive(Serialize, Deserialize)]
+pub struct ServiceCreateApiKeyArgs {
+    pub user_id: u64,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// Arguments for `POST /auth/api-keys/authenticate` API of the service.
+#[derive(Serialize, Deserialize)]
+pub struct ServiceAuthenticateApiKeyArgs {
+    pub key: String,
+}
+
+/// A newly created API key, which is shown only once.
+#[derive(Serialize, Deserialize)]
+pub struct CreatedApiKey {
+    pub id: u64,
+    pub key: String,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// TOTP enrollment containing information to register the secret to authenticator apps.
+#[derive(Serialize, Deserialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub provisioning_uri: String,
 }
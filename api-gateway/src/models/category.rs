@@ -0,0 +1,29 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Arguments for `POST /categories` API.
+#[derive(Serialize, Deserialize)]
+pub struct CreateCategoryArgs {
+    pub name: String,
+}
+
+/// Arguments for `POST /categories` API of back-end service.
+#[derive(Serialize, Deserialize)]
+pub struct ServiceCreateCategoryArgs {
+    pub user_id: u64,
+    pub name: String,
+}
+
+/// Arguments for `PATCH /categories/:id` API.
+#[derive(Serialize, Deserialize)]
+pub struct RenameCategoryArgs {
+    pub name: String,
+}
+
+/// Category of a user grouping posts.
+#[derive(Serialize, Deserialize)]
+pub struct CategoryDTO {
+    pub id: u64,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+}
@@ -11,6 +11,9 @@ pub enum ApiGatewayError {
     #[error("internal server error")]
     InternalServerError,
 
+    #[error("payload too large")]
+    PayloadTooLarge,
+
     #[error("failed to parse structure from service response")]
     ServiceResponseParsingFailure,
 }
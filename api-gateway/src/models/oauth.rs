@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+/// Arguments for `POST /auth/oauth/:provider/callback` API.
+#[derive(Serialize, Deserialize)]
+pub struct CompleteOAuthLoginArgs {
+    pub code: String,
+    pub state: String,
+    pub totp_code: Option<String>,
+    pub invite_code: Option<String>,
+}
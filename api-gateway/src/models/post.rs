@@ -1,5 +1,42 @@
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Arguments for `GET /posts` API.
+/// The `tag` parameter can be repeated, so it is read from the query string separately.
+#[derive(Serialize, Deserialize)]
+pub struct GetListArgs {
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+    pub tag_mode: Option<String>,
+    pub category: Option<u64>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+    pub after: Option<String>,
+}
+
+/// Arguments for `GET /posts/count` API.
+#[derive(Serialize, Deserialize)]
+pub struct CountArgs {
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+    pub tag: Option<String>,
+}
+
+/// Arguments for `GET /posts/mood-stats` API.
+#[derive(Serialize, Deserialize)]
+pub struct MoodStatsArgs {
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
+
+/// Arguments for `GET /posts/search` API.
+#[derive(Serialize, Deserialize)]
+pub struct SearchArgs {
+    pub q: String,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
 
 /// Arguments for `POST /posts` API.
 #[derive(Serialize, Deserialize)]
@@ -7,6 +44,19 @@ pub struct CreateArgs {
     pub title: String,
     pub content: String,
     pub date: NaiveDateTime,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub publish_at: Option<NaiveDateTime>,
+    pub status: Option<PostStatus>,
+    pub mood: Option<String>,
+}
+
+/// Status of a post. A draft is not listed with the published posts until it is published.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostStatus {
+    Draft,
+    Published,
 }
 
 /// Arguments for `POST /posts` API of the service.
@@ -16,34 +66,121 @@ pub struct ServiceCreateArgs {
     pub title: String,
     pub content: String,
     pub date: NaiveDateTime,
+    pub tags: Vec<String>,
+    pub publish_at: Option<NaiveDateTime>,
+    pub status: Option<PostStatus>,
+    pub mood: Option<String>,
+}
+
+/// Arguments for `POST /posts/import` API.
+#[derive(Serialize, Deserialize)]
+pub struct ImportArgs {
+    pub data: Vec<Value>,
+}
+
+/// Arguments for `POST /posts/import` API of the service.
+#[derive(Serialize, Deserialize)]
+pub struct ServiceImportArgs {
+    pub user_id: u64,
+    pub posts: Vec<Value>,
+}
+
+/// Arguments for `POST /posts/bulk-delete` API.
+#[derive(Serialize, Deserialize)]
+pub struct BulkDeleteArgs {
+    pub ids: Vec<u64>,
+}
+
+/// Arguments for `POST /posts/bulk-delete` API of the service.
+#[derive(Serialize, Deserialize)]
+pub struct ServiceBulkDeleteArgs {
+    pub user_id: u64,
+    pub ids: Vec<u64>,
+}
+
+/// Arguments for `PUT /posts/:id/category` API.
+#[derive(Serialize, Deserialize)]
+pub struct SetCategoryArgs {
+    pub category_id: Option<u64>,
 }
 
 /// Arguments for `PATCH /posts/:id` API.
 #[derive(Serialize, Deserialize)]
 pub struct UpdateArgs {
+    pub version: u64,
     pub title: Option<String>,
     pub content: Option<String>,
     pub date: Option<NaiveDateTime>,
+    pub tags: Option<Vec<String>>,
+    pub mood: Option<String>,
 }
 
 /// Arguments for `PATCH /posts/:id` API of the service.
 #[derive(Serialize, Deserialize)]
 pub struct ServiceUpdateArgs {
     pub user_id: u64,
+    pub version: u64,
     pub title: Option<String>,
     pub content: Option<String>,
     pub date: Option<NaiveDateTime>,
+    pub tags: Option<Vec<String>>,
+    pub mood: Option<String>,
 }
 
 /// Post DTO using between api gateway and the service.
+/// The creation time and the update time are serialized in RFC 3339 format with UTC offset.
 #[derive(Serialize, Deserialize)]
 pub struct PostDTO {
     pub id: u64,
     pub title: String,
     pub content: String,
     pub date: NaiveDateTime,
-    pub created_at: NaiveDateTime,
-    pub updated_at: Option<NaiveDateTime>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+    pub version: u64,
+    pub publish_at: Option<NaiveDateTime>,
+    pub status: PostStatus,
+    pub pinned: bool,
+    pub mood: Option<String>,
+    pub category_id: Option<u64>,
+}
+
+/// A page of post DTOs with the total count of the posts.
+#[derive(Serialize, Deserialize)]
+pub struct PostPageDTO {
+    pub posts: Vec<PostDTO>,
+    pub total_count: u64,
+    pub next: Option<String>,
+}
+
+/// Result of importing posts from an exported archive.
+#[derive(Serialize, Deserialize)]
+pub struct PostImportResultDTO {
+    pub imported_count: u64,
+    pub skipped_count: u64,
+}
+
+/// Writing streaks of a user, which are numbers of consecutive days with at least one post.
+#[derive(Serialize, Deserialize)]
+pub struct PostStreakDTO {
+    pub current_streak: u32,
+    pub longest_streak: u32,
+}
+
+/// Result of deleting a post of the id in a batch, which is `deleted`, `not_found` or
+/// `forbidden`.
+#[derive(Serialize, Deserialize)]
+pub struct BulkDeleteResultDTO {
+    pub id: u64,
+    pub status: String,
+}
+
+/// Number of posts tagged with a mood.
+#[derive(Serialize, Deserialize)]
+pub struct MoodCountDTO {
+    pub mood: String,
+    pub count: u64,
 }
 
 /// Summarized post DTO using between api gateway and the service.
@@ -8,16 +8,70 @@ pub struct CreateArgs {
     pub token_key: String,
     pub token_pin: String,
     pub recaptcha_token: String,
+    pub accepted_tos_version: Option<String>,
+}
+
+/// Arguments for `POST /users/me/tos` API.
+#[derive(Serialize, Deserialize)]
+pub struct AcceptTosArgs {
+    pub version: String,
 }
 
 /// Arguments for `PATCH /users/:id` API.
 #[derive(Serialize, Deserialize)]
 pub struct UpdateArgs {
     pub name: Option<String>,
-    pub password: Option<String>,
+    pub avatar_url: Option<String>,
+    pub timezone: Option<String>,
+}
+
+/// Arguments for `PATCH /users/me` API.
+#[derive(Serialize, Deserialize)]
+pub struct UpdateProfileArgs {
+    pub name: Option<String>,
     pub avatar_url: Option<String>,
 }
 
+/// Arguments for `PATCH /users/:id/reminder` API.
+#[derive(Serialize, Deserialize)]
+pub struct UpdateReminderArgs {
+    pub reminder_enabled: bool,
+    pub reminder_hour: u8,
+}
+
+/// Arguments for `PATCH /users/:id/locale` API.
+#[derive(Serialize, Deserialize)]
+pub struct UpdateLocaleArgs {
+    pub locale: Option<String>,
+}
+
+/// Arguments for `POST /users/me/deletion` API.
+#[derive(Serialize, Deserialize)]
+pub struct RequestAccountDeletionArgs {
+    pub password: Option<String>,
+    pub totp_code: Option<String>,
+}
+
+/// Arguments for `DELETE /users/me` API.
+#[derive(Serialize, Deserialize)]
+pub struct DeleteAccountArgs {
+    pub token: String,
+}
+
+/// Arguments for `POST /users/me/email` API.
+#[derive(Serialize, Deserialize)]
+pub struct RequestEmailChangeArgs {
+    pub email: String,
+    pub password: Option<String>,
+    pub totp_code: Option<String>,
+}
+
+/// Arguments for `POST /users/email/confirm` API.
+#[derive(Serialize, Deserialize)]
+pub struct ConfirmEmailChangeArgs {
+    pub token: String,
+}
+
 /// Arguments for `POST /users/password` API.
 #[derive(Serialize, Deserialize)]
 pub struct ResetPasswordArgs {
@@ -27,6 +81,13 @@ pub struct ResetPasswordArgs {
     pub new_password: String,
 }
 
+/// Profile of a user which can be updated by the user.
+#[derive(Serialize, Deserialize)]
+pub struct UserProfileDTO {
+    pub name: String,
+    pub avatar_url: Option<String>,
+}
+
 /// User DTO using between api gateway and the service.
 #[derive(Serialize, Deserialize)]
 pub struct UserDTO {
@@ -36,4 +97,10 @@ pub struct UserDTO {
     pub avatar_url: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: Option<NaiveDateTime>,
+    pub last_login_at: Option<NaiveDateTime>,
+    pub previous_login_at: Option<NaiveDateTime>,
+    pub timezone: Option<String>,
+    pub reminder_enabled: bool,
+    pub reminder_hour: u8,
+    pub locale: Option<String>,
 }
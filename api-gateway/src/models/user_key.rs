@@ -0,0 +1,16 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Arguments for `POST /users/me/key/rotation` API.
+#[derive(Serialize, Deserialize)]
+pub struct RotateArgs {
+    pub public_key: String,
+}
+
+/// Public key of a user.
+#[derive(Serialize, Deserialize)]
+pub struct UserKeyDTO {
+    pub public_key: String,
+    pub previous_public_key: Option<String>,
+    pub rotated_at: Option<NaiveDateTime>,
+}
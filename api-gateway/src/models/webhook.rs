@@ -0,0 +1,44 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Arguments for `POST /webhooks` API.
+#[derive(Serialize, Deserialize)]
+pub struct RegisterWebhookArgs {
+    pub url: String,
+}
+
+/// Arguments for `POST /webhooks` API of back-end service.
+#[derive(Serialize, Deserialize)]
+pub struct ServiceRegisterWebhookArgs {
+    pub user_id: u64,
+    pub url: String,
+}
+
+/// A newly registered webhook with the secret, which is shown only once.
+#[derive(Serialize, Deserialize)]
+pub struct RegisteredWebhook {
+    pub id: u64,
+    pub url: String,
+    pub secret: String,
+}
+
+/// Webhook of a user without the secret.
+#[derive(Serialize, Deserialize)]
+pub struct WebhookDTO {
+    pub id: u64,
+    pub url: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// A log of an attempt to deliver an event to a webhook.
+#[derive(Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: u64,
+    pub webhook_id: u64,
+    pub event: String,
+    pub payload: String,
+    pub attempt: u32,
+    pub status_code: Option<u16>,
+    pub is_success: bool,
+    pub created_at: NaiveDateTime,
+}
@@ -1,12 +1,13 @@
 use actix_session::Session;
-use actix_web::{get, post, web, Responder};
+use actix_web::{delete, get, post, web, HttpRequest, Responder};
+use chrono::NaiveDateTime;
+use http::header::USER_AGENT;
 use http::StatusCode;
-use reqwest::Client;
 
 use crate::models::auth::*;
 use crate::models::error::{get_api_error_message, ApiGatewayError};
 use crate::models::user::UserDTO;
-use crate::utils::{http_util, session_util};
+use crate::utils::{env_util, http_util, session_util};
 
 /// Responds auth information as user session.
 ///
@@ -31,7 +32,7 @@ use crate::utils::{http_util, session_util};
 /// ```
 #[get("/auth")]
 pub async fn get_auth(session: Session) -> impl Responder {
-    let user_session = session_util::get_session(&session);
+    let user_session = session_util::get_browser_session(&session);
 
     if let Some(user_session) = user_session {
         http_util::get_ok_response::<UserSession>(user_session)
@@ -43,7 +44,88 @@ pub async fn get_auth(session: Session) -> impl Responder {
     }
 }
 
-/// Refresh auth information as user session.
+/// Responds the profile of logged-in user.
+/// The name and the avatar are the latest ones as of the last refresh of the session.
+///
+/// # Request
+///
+/// ```text
+/// GET /auth/me
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": {
+///         "id": 0,
+///         "email": "park@email.com",
+///         "name": "park",
+///         "avatar_url": "avatar.jpg",
+///         "public_key": "d63ee429",
+///         "tos_acceptance_required": false
+///     },
+///     "error": null
+/// }
+/// ```
+///
+/// `tos_acceptance_required` is `true` if the user hasn't accepted the current version of the
+/// terms of service, such as after the version is bumped. The client should ask the user to
+/// accept it by `POST /users/me/tos`.
+#[get("/auth/me")]
+pub async fn get_me(session: Session) -> impl Responder {
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let tos_version = env_util::get_env_var_or("TOS_VERSION", String::from("1"));
+        http_util::get_ok_response::<CurrentUserDTO>(user_session.to_current_user(&tos_version))
+    } else {
+        http_util::get_err_response::<CurrentUserDTO>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Responds the CSRF token of user session, which must be sent in `X-CSRF-Token` header of
+/// state-changing requests. A new token is issued if the session doesn't have one.
+///
+/// # Request
+///
+/// ```text
+/// GET /auth/csrf
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": "3hVq8dTz0bXkLw1sRn5yUe7cJa9mGp2f",
+///     "error": null
+/// }
+/// ```
+#[get("/auth/csrf")]
+pub async fn get_csrf_token(mut session: Session) -> impl Responder {
+    if session_util::get_browser_session(&session).is_none() {
+        return http_util::get_err_response::<String>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    let csrf_token = session_util::get_csrf_token(&session)
+        .or_else(|| session_util::set_csrf_token(&mut session));
+    if let Some(csrf_token) = csrf_token {
+        http_util::get_ok_response::<String>(csrf_token)
+    } else {
+        http_util::get_err_response::<String>(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &get_api_error_message(ApiGatewayError::InternalServerError),
+        )
+    }
+}
+
+/// Refresh auth information as user session, and extends the session lifetime.
+/// The session expires if it is not refreshed within the idle timeout, or once it reaches
+/// the maximum lifetime since the login, after which the user has to log in again.
 ///
 /// # Request
 ///
@@ -66,28 +148,65 @@ pub async fn get_auth(session: Session) -> impl Responder {
 /// ```
 #[post("/auth")]
 pub async fn refresh_session(mut session: Session) -> impl Responder {
-    let user_session = session_util::get_session(&session);
+    let user_session = session_util::get_browser_session(&session);
     if let Some(user_session) = user_session {
-        let response = reqwest::get(&http_util::get_url(&format!(
-            "/users/{}",
-            user_session.user_id
-        )))
-        .await
-        .unwrap();
+        let refresh_args = ServiceRefreshSessionArgs {
+            user_id: user_session.user_id,
+            user_session_epoch: user_session.user_session_epoch,
+            user_session_id: user_session.user_session_id.clone(),
+            user_session_started_at: user_session.user_session_started_at,
+            user_session_expires_at: user_session.user_session_expires_at,
+        };
+        let refresh_response = http_util::get_client()
+            .post(&http_util::get_url("/auth/sessions/refresh"))
+            .json(&refresh_args)
+            .send()
+            .await;
+
+        let user_session_expires_at = if let Ok(refresh_response) = refresh_response {
+            http_util::parse_data_from_service_response::<NaiveDateTime>(refresh_response)
+                .await
+                .ok()
+                .flatten()
+        } else {
+            None
+        };
+
+        // The session is expired if it has been idle too long or has reached the maximum lifetime.
+        let user_session_expires_at = if let Some(user_session_expires_at) = user_session_expires_at
+        {
+            user_session_expires_at
+        } else {
+            session_util::unset_session(&mut session);
+            return http_util::get_err_response::<UserSession>(
+                StatusCode::UNAUTHORIZED,
+                &get_api_error_message(ApiGatewayError::Unauthorized),
+            );
+        };
+
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/users/{}",
+                user_session.user_id
+            )))
+            .send()
+            .await
+            .unwrap();
 
         let result = http_util::parse_data_from_service_response::<UserDTO>(response).await;
         if let Ok(user) = result {
             if let Some(user) = user {
                 session_util::set_session(
                     &mut session,
-                    user_session.user_id,
-                    &user_session.user_email,
-                    &user.name,
-                    &user_session.user_public_key,
-                    &user.avatar_url,
+                    &UserSession {
+                        user_name: user.name,
+                        user_avatar_url: user.avatar_url,
+                        user_session_expires_at,
+                        ..user_session
+                    },
                 );
 
-                if let Some(refreshed_user_session) = session_util::get_session(&session) {
+                if let Some(refreshed_user_session) = session_util::get_browser_session(&session) {
                     http_util::get_ok_response::<UserSession>(refreshed_user_session)
                 } else {
                     http_util::get_err_response::<UserSession>(
@@ -129,13 +248,19 @@ pub async fn refresh_session(mut session: Session) -> impl Responder {
 /// * email - A unique email of the user.
 /// * password - A password of the user.
 /// * avatar_url - An avatar image url of the user.
+/// * locale - A locale of the user such as `ko`, in which emails are written. English is used if
+///   it is not given or not supported.
+/// * invite_code - An invite code, which is required while sign up is limited to invited users.
+///   A use of the code is consumed, and an invalid or exhausted code is unauthorized.
 ///
 /// ```json
 /// {
 ///     "name": "park",
 ///     "email": "park@email.com",
 ///     "password": "Ir5c7y8dS3",
-///     "avatar_url": "avatar.jpg"
+///     "avatar_url": "avatar.jpg",
+///     "locale": "ko",
+///     "invite_code": "Kq3Zt8WmPx2c"
 /// }
 /// ```
 ///
@@ -150,7 +275,7 @@ pub async fn refresh_session(mut session: Session) -> impl Responder {
 #[post("/auth/token/sign_up")]
 pub async fn set_sign_up_token(args: web::Json<SetSignUpTokenArgs>) -> impl Responder {
     let args: SetSignUpTokenArgs = args.into_inner();
-    let response = Client::new()
+    let response = http_util::get_client()
         .post(&http_util::get_url("/auth/token/sign_up"))
         .json(&args)
         .send()
@@ -158,7 +283,45 @@ pub async fn set_sign_up_token(args: web::Json<SetSignUpTokenArgs>) -> impl Resp
     http_util::pass_response::<String>(response).await
 }
 
+/// Resends the email of the pending token for creating user.
+///
+/// # Request
+///
+/// ```text
+/// POST /auth/token/sign_up/resend
+/// ```
+///
+/// ## Parameters
+///
+/// * email - An email used for the pending sign up.
+///
+/// ```json
+/// {
+///     "email": "park@email.com"
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[post("/auth/token/sign_up/resend")]
+pub async fn resend_sign_up_token(args: web::Json<ResendSignUpTokenArgs>) -> impl Responder {
+    let args: ResendSignUpTokenArgs = args.into_inner();
+    let response = http_util::get_client()
+        .post(&http_util::get_url("/auth/token/sign_up/resend"))
+        .json(&args)
+        .send()
+        .await;
+    http_util::pass_response::<bool>(response).await
+}
+
 /// Sets token for resetting password.
+/// It responds OK even if the email is not registered.
 ///
 /// # Request
 ///
@@ -187,7 +350,7 @@ pub async fn set_sign_up_token(args: web::Json<SetSignUpTokenArgs>) -> impl Resp
 #[post("/auth/token/password")]
 pub async fn set_password_token(args: web::Json<SetPasswordTokenArgs>) -> impl Responder {
     let args: SetPasswordTokenArgs = args.into_inner();
-    let response = Client::new()
+    let response = http_util::get_client()
         .post(&http_util::get_url("/auth/token/password"))
         .json(&args)
         .send()
@@ -207,11 +370,13 @@ pub async fn set_password_token(args: web::Json<SetPasswordTokenArgs>) -> impl R
 ///
 /// * email - A unique email of the user.
 /// * password - A password of the user.
+/// * totp_code - A TOTP code of the user. It is required only if the user enabled TOTP.
 ///
 /// ```json
 /// {
 ///     "email": "park@email.com",
 ///     "password": "Ir5c7y8dS3",
+///     "totp_code": "287082"
 /// }
 /// ```
 ///
@@ -228,27 +393,46 @@ pub async fn set_password_token(args: web::Json<SetPasswordTokenArgs>) -> impl R
 /// }
 /// ```
 #[post("/auth/login")]
-pub async fn login(mut session: Session, args: web::Json<LoginArgs>) -> impl Responder {
-    let args: LoginArgs = args.into_inner();
-    let response = Client::new()
+pub async fn login(
+    req: HttpRequest,
+    mut session: Session,
+    args: web::Json<LoginArgs>,
+) -> impl Responder {
+    let LoginArgs {
+        email,
+        password,
+        totp_code,
+    } = args.into_inner();
+    // The User-Agent and the address of the client are used to notify login from a new device.
+    let args = ServiceLoginArgs {
+        email,
+        password,
+        totp_code,
+        user_agent: req
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|user_agent| user_agent.to_str().ok())
+            .map(String::from),
+        ip_address: req.connection_info().realip_remote_addr().map(String::from),
+    };
+    let response = http_util::get_client()
         .post(&http_util::get_url("/auth/login"))
         .json(&args)
         .send()
         .await;
 
     if let Ok(response) = response {
+        if !response.status().is_success() {
+            // Passes the error as it is, so that the client can tell whether the TOTP code is required.
+            return http_util::pass_response::<UserSession>(Ok(response)).await;
+        }
+
         let user_session =
             http_util::parse_data_from_service_response::<UserSession>(response).await;
         if let Ok(user_session) = user_session {
             if let Some(user_session) = user_session {
-                session_util::set_session(
-                    &mut session,
-                    user_session.user_id,
-                    &user_session.user_email,
-                    &user_session.user_name,
-                    &user_session.user_public_key,
-                    &user_session.user_avatar_url,
-                );
+                session_util::set_session(&mut session, &user_session);
+                session_util::set_csrf_token(&mut session);
                 http_util::get_ok_response::<UserSession>(user_session)
             } else {
                 http_util::get_err_response::<UserSession>(
@@ -267,7 +451,94 @@ pub async fn login(mut session: Session, args: web::Json<LoginArgs>) -> impl Res
     }
 }
 
-/// Signs out to unset user session.
+/// Requests a magic link to log in without password.
+/// It responds OK even if the email is not registered.
+///
+/// # Request
+///
+/// ```text
+/// POST /auth/magic_link
+/// ```
+///
+/// ## Parameters
+///
+/// * email - A unique email of the user.
+///
+/// ```json
+/// {
+///     "email": "park@email.com"
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[post("/auth/magic_link")]
+pub async fn request_magic_link(args: web::Json<RequestMagicLinkArgs>) -> impl Responder {
+    let args: RequestMagicLinkArgs = args.into_inner();
+    let response = http_util::get_client()
+        .post(&http_util::get_url("/auth/magic_link"))
+        .json(&args)
+        .send()
+        .await;
+    http_util::pass_response::<bool>(response).await
+}
+
+/// Signs in with the token of the magic link to set user session.
+/// The token can be used only once.
+///
+/// # Request
+///
+/// ```text
+/// POST /auth/magic_link/login
+/// ```
+///
+/// ## Parameters
+///
+/// * token - A token in the magic link.
+/// * totp_code - A TOTP code or a backup code of the user. It is required only if the user enabled
+///   TOTP, and the link can be used again with the code after `TOTP_REQUIRED` error.
+///
+/// ```json
+/// {
+///     "token": "Xs8dm2cBkoa1lam9",
+///     "totp_code": "123456"
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": {
+///         "user_id": 0,
+///         "user_email": "park@email.com"
+///         "user_name": "park",
+///     },
+///     "error": null
+/// }
+/// ```
+#[post("/auth/magic_link/login")]
+pub async fn consume_magic_link(
+    mut session: Session,
+    args: web::Json<ConsumeMagicLinkArgs>,
+) -> impl Responder {
+    let args: ConsumeMagicLinkArgs = args.into_inner();
+    let response = http_util::get_client()
+        .post(&http_util::get_url("/auth/magic_link/login"))
+        .json(&args)
+        .send()
+        .await;
+    session_util::set_session_by_service_response(&mut session, response).await
+}
+
+/// Signs out to unset user session, and revokes the session in the service.
+/// It responds OK even if there is no active session.
 ///
 /// # Request
 ///
@@ -285,14 +556,529 @@ pub async fn login(mut session: Session, args: web::Json<LoginArgs>) -> impl Res
 /// ```
 #[post("/auth/logout")]
 pub async fn logout(mut session: Session) -> impl Responder {
-    let is_logged_in = session_util::get_session(&session);
-    if is_logged_in.is_some() {
+    // Revokes the session in the service as well, so that a copy of the session cookie can't be
+    // used for routes checking the active session. It fails if the session is already revoked.
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let _ = http_util::get_client()
+            .delete(&http_util::get_url(&format!(
+                "/auth/sessions/{}/{}",
+                user_session.user_id, user_session.user_session_id
+            )))
+            .send()
+            .await;
+    }
+
+    session_util::unset_session(&mut session);
+    http_util::get_ok_response::<bool>(true)
+}
+
+/// Signs out of all sessions of the logged-in user, including sessions on other devices.
+///
+/// # Request
+///
+/// ```text
+/// POST /auth/logout/all
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[post("/auth/logout/all")]
+pub async fn logout_all(req: HttpRequest, mut session: Session) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let args = ServiceInvalidateSessionsArgs {
+            user_id: user_session.user_id,
+        };
+
+        let response = http_util::get_client()
+            .post(&http_util::get_url("/auth/sessions/invalidate"))
+            .json(&args)
+            .send()
+            .await;
+
         session_util::unset_session(&mut session);
-        http_util::get_ok_response::<bool>(true)
+        http_util::pass_response::<bool>(response).await
     } else {
         http_util::get_err_response::<bool>(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            &format!("{}", ApiGatewayError::InternalServerError),
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Responds the active sessions of the logged-in user from the latest one.
+/// The `id` of the current session is the `user_session_id` of `GET /auth`.
+///
+/// # Request
+///
+/// ```text
+/// GET /auth/sessions
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": [
+///         {
+///             "id": "Q91Mg4yqFdmBRm3poJDYHE9CsuGEPfJT",
+///             "user_agent": "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7)",
+///             "created_at": "2020-10-15T14:00:00"
+///         }
+///     ],
+///     "error": null
+/// }
+/// ```
+#[get("/auth/sessions")]
+pub async fn get_active_sessions(session: Session) -> impl Responder {
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/auth/sessions/{}",
+                user_session.user_id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<Vec<ActiveSession>>(response).await
+    } else {
+        http_util::get_err_response::<Vec<ActiveSession>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Revokes an active session of the logged-in user, which is signed out on its next refresh.
+///
+/// # Request
+///
+/// ```text
+/// DELETE /auth/sessions/:id
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[delete("/auth/sessions/{id}")]
+pub async fn revoke_session(
+    req: HttpRequest,
+    session: Session,
+    id: web::Path<String>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let response = http_util::get_client()
+            .delete(&http_util::get_url(&format!(
+                "/auth/sessions/{}/{}",
+                user_session.user_id, id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<bool>(response).await
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Changes the password of the logged-in user.
+/// The current password is required, and the new password must be strong enough.
+///
+/// # Request
+///
+/// ```text
+/// POST /auth/change-password
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * current_password - A current password of the user.
+/// * new_password - A new password of the user.
+/// * invalidate_other_sessions - Whether to sign out of the other sessions. Defaults to `false`.
+///
+/// ```json
+/// {
+///     "current_password": "Ir5c7y8dS3",
+///     "new_password": "9nWq2xKb7L",
+///     "invalidate_other_sessions": true
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[post("/auth/change-password")]
+pub async fn change_password(
+    req: HttpRequest,
+    session: Session,
+    args: web::Json<ChangePasswordArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_active_session(&session).await {
+        let args = {
+            let ChangePasswordArgs {
+                current_password,
+                new_password,
+                invalidate_other_sessions,
+            } = args.into_inner();
+            ServiceChangePasswordArgs {
+                user_id: user_session.user_id,
+                user_session_id: user_session.user_session_id,
+                current_password,
+                new_password,
+                invalidate_other_sessions: invalidate_other_sessions.unwrap_or(false),
+            }
+        };
+
+        let response = http_util::get_client()
+            .post(&http_util::get_url("/auth/change-password"))
+            .json(&args)
+            .send()
+            .await;
+
+        http_util::pass_response::<bool>(response).await
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Starts TOTP enrollment of the logged-in user.
+///
+/// # Request
+///
+/// ```text
+/// POST /auth/totp
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": {
+///         "secret": "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP",
+///         "provisioning_uri": "otpauth://totp/Darim:park@email.com?secret=JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP&issuer=Darim&algorithm=SHA1&digits=6&period=30"
+///     },
+///     "error": null
+/// }
+/// ```
+#[post("/auth/totp")]
+pub async fn enroll_totp(req: HttpRequest, session: Session) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<TotpEnrollment>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_active_session(&session).await {
+        let args = ServiceEnrollTotpArgs {
+            user_id: user_session.user_id,
+        };
+
+        let response = http_util::get_client()
+            .post(&http_util::get_url("/auth/totp"))
+            .json(&args)
+            .send()
+            .await;
+
+        http_util::pass_response::<TotpEnrollment>(response).await
+    } else {
+        http_util::get_err_response::<TotpEnrollment>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Confirms TOTP enrollment of the logged-in user.
+///
+/// # Request
+///
+/// ```text
+/// POST /auth/totp/confirm
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * code - A 6-digit code generated by the authenticator app.
+///
+/// ```json
+/// {
+///     "code": "287082"
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[post("/auth/totp/confirm")]
+pub async fn confirm_totp(
+    req: HttpRequest,
+    session: Session,
+    args: web::Json<ConfirmTotpArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_active_session(&session).await {
+        let args = {
+            let ConfirmTotpArgs { code } = args.into_inner();
+            ServiceConfirmTotpArgs {
+                user_id: user_session.user_id,
+                code,
+            }
+        };
+
+        let response = http_util::get_client()
+            .post(&http_util::get_url("/auth/totp/confirm"))
+            .json(&args)
+            .send()
+            .await;
+
+        http_util::pass_response::<bool>(response).await
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Generates backup codes of the logged-in user used in place of TOTP codes.
+/// Previous backup codes are invalidated, and the new codes can not be found again.
+///
+/// # Request
+///
+/// ```text
+/// POST /auth/totp/backup_codes
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": ["a1lam9cBko", "P9d82Jc5Qz", "71I3Qz9uWm"],
+///     "error": null
+/// }
+/// ```
+#[post("/auth/totp/backup_codes")]
+pub async fn generate_backup_codes(req: HttpRequest, session: Session) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<Vec<String>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_active_session(&session).await {
+        let args = ServiceGenerateBackupCodesArgs {
+            user_id: user_session.user_id,
+        };
+
+        let response = http_util::get_client()
+            .post(&http_util::get_url("/auth/totp/backup_codes"))
+            .json(&args)
+            .send()
+            .await;
+
+        http_util::pass_response::<Vec<String>>(response).await
+    } else {
+        http_util::get_err_response::<Vec<String>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Creates an API key of the logged-in user for programmatic access, which is sent in
+/// `Authorization: Bearer <key>` header instead of the session cookie.
+/// The key is shown only in the response, and it grants access only to posts and categories, so
+/// it can't manage the account, sessions, user keys or other API keys.
+///
+/// # Request
+///
+/// ```text
+/// POST /auth/api-keys
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * expires_at - An ISO-8601 date and time when the key expires. The key doesn't expire if it is not given.
+///
+/// ```json
+/// {
+///     "expires_at": "2021-01-01T00:00:00"
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": {
+///         "id": 1,
+///         "key": "Tq3yXc8bLw2K.8nVd0a5rUfZpQ1mJ6sWk9eHgT4yBx7Ci",
+///         "expires_at": "2021-01-01T00:00:00"
+///     },
+///     "error": null
+/// }
+/// ```
+#[post("/auth/api-keys")]
+pub async fn create_api_key(
+    req: HttpRequest,
+    session: Session,
+    args: web::Json<CreateApiKeyArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<CreatedApiKey>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_active_session(&session).await {
+        let args = ServiceCreateApiKeyArgs {
+            user_id: user_session.user_id,
+            expires_at: args.into_inner().expires_at,
+        };
+
+        let response = http_util::get_client()
+            .post(&http_util::get_url("/auth/api-keys"))
+            .json(&args)
+            .send()
+            .await;
+
+        http_util::pass_response::<CreatedApiKey>(response).await
+    } else {
+        http_util::get_err_response::<CreatedApiKey>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Revokes an API key of the logged-in user.
+///
+/// # Request
+///
+/// ```text
+/// DELETE /auth/api-keys/:id
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[delete("/auth/api-keys/{id}")]
+pub async fn revoke_api_key(
+    req: HttpRequest,
+    session: Session,
+    id: web::Path<u64>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_active_session(&session).await {
+        let response = http_util::get_client()
+            .delete(&http_util::get_url(&format!(
+                "/auth/api-keys/{}/{}",
+                user_session.user_id, id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<bool>(response).await
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
         )
     }
 }
@@ -300,9 +1086,332 @@ pub async fn logout(mut session: Session) -> impl Responder {
 /// Initializes the auth routes.
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(get_auth);
+    cfg.service(get_me);
+    cfg.service(get_csrf_token);
     cfg.service(refresh_session);
     cfg.service(set_sign_up_token);
+    cfg.service(resend_sign_up_token);
     cfg.service(set_password_token);
     cfg.service(login);
+    cfg.service(request_magic_link);
+    cfg.service(consume_magic_link);
     cfg.service(logout);
+    cfg.service(logout_all);
+    cfg.service(get_active_sessions);
+    cfg.service(revoke_session);
+    cfg.service(change_password);
+    cfg.service(enroll_totp);
+    cfg.service(confirm_totp);
+    cfg.service(generate_backup_codes);
+    cfg.service(create_api_key);
+    cfg.service(revoke_api_key);
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_session::CookieSession;
+    use actix_web::{test, App, HttpResponse};
+    use serde_json::{json, Value};
+    use std::env;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::utils::test_util::{get_user_session, set_test_session};
+
+    static TEST_SESSION_REVOKED: AtomicBool = AtomicBool::new(false);
+
+    async fn get_test_active_sessions(user_id: web::Path<u64>) -> HttpResponse {
+        assert_eq!(user_id.into_inner(), 10);
+        let active_sessions = if TEST_SESSION_REVOKED.load(Ordering::SeqCst) {
+            json!([])
+        } else {
+            json!([{ "id": "c2Vzc2lvbg", "user_agent": null, "created_at": "2020-06-07T07:43:03" }])
+        };
+        HttpResponse::Ok().json(json!({ "data": active_sessions, "error": null }))
+    }
+
+    async fn revoke_test_session(path: web::Path<(u64, String)>) -> HttpResponse {
+        assert_eq!(path.into_inner(), (10, String::from("c2Vzc2lvbg")));
+        TEST_SESSION_REVOKED.store(true, Ordering::SeqCst);
+        HttpResponse::Ok().json(json!({ "data": true, "error": null }))
+    }
+
+    async fn enroll_test_totp() -> HttpResponse {
+        HttpResponse::Ok().json(json!({
+            "data": { "secret": "JBSWY3DPEHPK3PXP", "provisioning_uri": "otpauth://totp/Darim" },
+            "error": null
+        }))
+    }
+
+    #[test]
+    fn test_logout_revokes_session() {
+        http_util::run_back_end_test(async {
+            TEST_SESSION_REVOKED.store(false, Ordering::SeqCst);
+            let service = test::start(|| {
+                App::new()
+                    .route(
+                        "/auth/sessions/{user_id}",
+                        web::get().to(get_test_active_sessions),
+                    )
+                    .route(
+                        "/auth/sessions/{user_id}/{id}",
+                        web::delete().to(revoke_test_session),
+                    )
+                    .route("/auth/totp", web::post().to(enroll_test_totp))
+            });
+            env::set_var(
+                "BACK_END_SERVICE_ADDRESS",
+                format!("http://{}", service.addr()),
+            );
+
+            let mut app = test::init_service(
+                App::new()
+                    .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                    .route("/test/session", web::post().to(set_test_session))
+                    .service(logout)
+                    .service(enroll_totp),
+            )
+            .await;
+
+            let req = test::TestRequest::post().uri("/test/session").to_request();
+            let resp = test::call_service(&mut app, req).await;
+            let session_cookie = resp
+                .response()
+                .cookies()
+                .find(|cookie| cookie.name() == "actix-session")
+                .unwrap()
+                .into_owned();
+            let csrf_token = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+
+            let req = test::TestRequest::post()
+                .uri("/auth/totp")
+                .cookie(session_cookie.clone())
+                .header(session_util::CSRF_TOKEN_HEADER, csrf_token.clone())
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let req = test::TestRequest::post()
+                .uri("/auth/logout")
+                .cookie(session_cookie.clone())
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert!(TEST_SESSION_REVOKED.load(Ordering::SeqCst));
+            assert!(resp
+                .response()
+                .cookies()
+                .any(|cookie| cookie.name() == "actix-session" && cookie.value().is_empty()));
+
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["data"], Value::Bool(true));
+
+            // The copied session cookie is still signed, but the session is no longer active.
+            let req = test::TestRequest::post()
+                .uri("/auth/totp")
+                .cookie(session_cookie)
+                .header(session_util::CSRF_TOKEN_HEADER, csrf_token)
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        });
+    }
+
+    #[actix_rt::test]
+    async fn test_get_me() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .route("/test/session", web::post().to(set_test_session))
+                .service(get_me),
+        )
+        .await;
+
+        let req = test::TestRequest::post().uri("/test/session").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let session_cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "actix-session")
+            .unwrap()
+            .into_owned();
+
+        let req = test::TestRequest::get()
+            .uri("/auth/me")
+            .cookie(session_cookie)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"]["id"], 10);
+        assert_eq!(body["data"]["email"], "user@email.com");
+        assert_eq!(body["data"]["name"], "park");
+        assert_eq!(body["data"]["avatar_url"], Value::Null);
+        assert_eq!(body["data"]["public_key"], "d63ee429");
+        assert_eq!(body["data"]["tos_acceptance_required"], false);
+        assert!(body["data"].get("user_session_id").is_none());
+    }
+
+    #[test]
+    fn test_tos_acceptance_required_after_version_bump() {
+        let user_session = get_user_session();
+        assert!(!user_session.to_current_user("1").tos_acceptance_required);
+        assert!(user_session.to_current_user("2").tos_acceptance_required);
+
+        let user_session = UserSession {
+            user_tos_version_accepted: None,
+            ..get_user_session()
+        };
+        assert!(user_session.to_current_user("1").tos_acceptance_required);
+    }
+
+    #[actix_rt::test]
+    async fn test_get_me_without_session() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .service(get_me),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/auth/me").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"], Value::Null);
+    }
+
+    #[actix_rt::test]
+    async fn test_get_csrf_token() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .route("/test/session", web::post().to(set_test_session))
+                .service(get_csrf_token),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/auth/csrf").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::post().uri("/test/session").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let session_cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "actix-session")
+            .unwrap()
+            .into_owned();
+
+        let req = test::TestRequest::get()
+            .uri("/auth/csrf")
+            .cookie(session_cookie)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"].as_str().unwrap().len(), 32);
+    }
+
+    #[actix_rt::test]
+    async fn test_revoke_session_without_csrf_token() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .route("/test/session", web::post().to(set_test_session))
+                .service(revoke_session),
+        )
+        .await;
+
+        let req = test::TestRequest::post().uri("/test/session").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let session_cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "actix-session")
+            .unwrap()
+            .into_owned();
+
+        let req = test::TestRequest::delete()
+            .uri("/auth/sessions/c2Vzc2lvbg")
+            .cookie(session_cookie)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_account_routes_without_csrf_token() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .route("/test/session", web::post().to(set_test_session))
+                .service(logout_all)
+                .service(enroll_totp)
+                .service(confirm_totp)
+                .service(generate_backup_codes),
+        )
+        .await;
+
+        let req = test::TestRequest::post().uri("/test/session").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let session_cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "actix-session")
+            .unwrap()
+            .into_owned();
+
+        let req = test::TestRequest::post()
+            .uri("/auth/logout/all")
+            .cookie(session_cookie.clone())
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::post()
+            .uri("/auth/totp")
+            .cookie(session_cookie.clone())
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::post()
+            .uri("/auth/totp/confirm")
+            .cookie(session_cookie.clone())
+            .set_json(&json!({ "code": "123456" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::post()
+            .uri("/auth/totp/backup_codes")
+            .cookie(session_cookie)
+            .header(session_util::CSRF_TOKEN_HEADER, "wrong")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_logout_without_session() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .service(logout),
+        )
+        .await;
+
+        let req = test::TestRequest::post().uri("/auth/logout").to_request();
+        let resp = test::call_service(&mut app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"], Value::Bool(true));
+    }
 }
@@ -0,0 +1,245 @@
+use actix_session::Session;
+use actix_web::{delete, get, patch, post, web, HttpRequest, Responder};
+use http::StatusCode;
+
+use crate::models::category::*;
+use crate::models::error::*;
+use crate::utils::{http_util, session_util};
+
+/// Creates a new category of logged-in user, which is a notebook grouping posts.
+///
+/// # Request
+///
+/// ```text
+/// POST /categories
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * name - A name of the category, which is unique among the categories of the user
+///   regardless of case. It must be at most 64 characters.
+///
+/// ```json
+/// {
+///     "name": "Travel"
+/// }
+/// ```
+///
+/// # Response
+///
+/// It responds 409 Conflict if the user already has a category of the name.
+///
+/// ```json
+/// {
+///     "data": {
+///         "id": 1,
+///         "name": "Travel",
+///         "created_at": "2020-04-12T07:43:03"
+///     },
+///     "error": null
+/// }
+/// ```
+#[post("/categories")]
+pub async fn create_category(
+    req: HttpRequest,
+    session: Session,
+    args: web::Json<CreateCategoryArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<CategoryDTO>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_session(&session) {
+        let args = ServiceCreateCategoryArgs {
+            user_id: user_session.user_id,
+            name: args.into_inner().name,
+        };
+
+        let response = http_util::get_client()
+            .post(&http_util::get_url("/categories"))
+            .json(&args)
+            .send()
+            .await;
+
+        http_util::pass_response::<CategoryDTO>(response).await
+    } else {
+        http_util::get_err_response::<CategoryDTO>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Responds categories of logged-in user in name order.
+///
+/// # Request
+///
+/// ```text
+/// GET /categories
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": [
+///         {
+///             "id": 1,
+///             "name": "Travel",
+///             "created_at": "2020-04-12T07:43:03"
+///         }
+///     ],
+///     "error": null
+/// }
+/// ```
+#[get("/categories")]
+pub async fn get_categories(session: Session) -> impl Responder {
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/categories/{}",
+                user_session.user_id
+            )))
+            .send()
+            .await;
+
+        http_util::pass_response::<Vec<CategoryDTO>>(response).await
+    } else {
+        http_util::get_err_response::<Vec<CategoryDTO>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Renames a category of logged-in user.
+///
+/// # Request
+///
+/// ```text
+/// PATCH /categories/:id
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * name - A new name of the category, as in `POST /categories`.
+///
+/// ```json
+/// {
+///     "name": "Journeys"
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[patch("/categories/{id}")]
+pub async fn rename_category(
+    req: HttpRequest,
+    session: Session,
+    id: web::Path<u64>,
+    args: web::Json<RenameCategoryArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .patch(&http_util::get_url(&format!(
+                "/categories/{}/{}",
+                user_session.user_id, id
+            )))
+            .json(&args.into_inner())
+            .send()
+            .await;
+
+        http_util::pass_response::<bool>(response).await
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Deletes a category of logged-in user.
+///
+/// By default, it responds 409 Conflict if the category has posts. If the server is configured
+/// with `CATEGORY_DELETION_POLICY=nullify`, the posts are removed from the category instead.
+///
+/// # Request
+///
+/// ```text
+/// DELETE /categories/:id
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[delete("/categories/{id}")]
+pub async fn delete_category(
+    req: HttpRequest,
+    session: Session,
+    id: web::Path<u64>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .delete(&http_util::get_url(&format!(
+                "/categories/{}/{}",
+                user_session.user_id, id
+            )))
+            .send()
+            .await;
+
+        http_util::pass_response::<bool>(response).await
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Initializes the category routes.
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_category);
+    cfg.service(get_categories);
+    cfg.service(rename_category);
+    cfg.service(delete_category);
+}
@@ -0,0 +1,171 @@
+use actix_session::Session;
+use actix_web::{get, post, web, Responder};
+
+use crate::models::oauth::*;
+use crate::utils::{http_util, session_util};
+
+/// Begins Google login.
+///
+/// # Request
+///
+/// ```text
+/// GET /auth/oauth/google
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": "https://accounts.google.com/o/oauth2/v2/auth?client_id=...&state=Xs8dm2",
+///     "error": null
+/// }
+/// ```
+#[get("/auth/oauth/google")]
+pub async fn begin_google_login() -> impl Responder {
+    let response = http_util::get_client()
+        .post(&http_util::get_url("/oauth/google"))
+        .send()
+        .await;
+    http_util::pass_response::<String>(response).await
+}
+
+/// Completes Google login to set user session.
+///
+/// # Request
+///
+/// ```text
+/// POST /auth/oauth/google/callback
+/// ```
+///
+/// ## Parameters
+///
+/// * code - An authorization code issued by Google.
+/// * state - A state issued when the login began.
+/// * totp_code - A TOTP code or a backup code of the user. It is required only if the user enabled
+///   TOTP. If it responds `TOTP_REQUIRED`, the login must begin again with the code, as the
+///   authorization code can't be used again.
+/// * invite_code - An invite code to sign up, which is required only if the user doesn't exist yet
+///   and invite codes are required. A use of the code is consumed as in
+///   `POST /auth/token/sign_up`.
+///
+/// ```json
+/// {
+///     "code": "4/0AY0e-g7",
+///     "state": "Xs8dm2",
+///     "totp_code": "287082",
+///     "invite_code": null
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": {
+///         "user_id": 0,
+///         "user_email": "park@email.com"
+///         "user_name": "park",
+///     },
+///     "error": null
+/// }
+/// ```
+#[post("/auth/oauth/google/callback")]
+pub async fn complete_google_login(
+    mut session: Session,
+    args: web::Json<CompleteOAuthLoginArgs>,
+) -> impl Responder {
+    let args: CompleteOAuthLoginArgs = args.into_inner();
+    let response = http_util::get_client()
+        .post(&http_util::get_url("/oauth/google/complete"))
+        .json(&args)
+        .send()
+        .await;
+    session_util::set_session_by_service_response(&mut session, response).await
+}
+
+/// Begins GitHub login.
+///
+/// # Request
+///
+/// ```text
+/// GET /auth/oauth/github
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": "https://github.com/login/oauth/authorize?client_id=...&state=Xs8dm2",
+///     "error": null
+/// }
+/// ```
+#[get("/auth/oauth/github")]
+pub async fn begin_github_login() -> impl Responder {
+    let response = http_util::get_client()
+        .post(&http_util::get_url("/oauth/github"))
+        .send()
+        .await;
+    http_util::pass_response::<String>(response).await
+}
+
+/// Completes GitHub login to set user session.
+///
+/// # Request
+///
+/// ```text
+/// POST /auth/oauth/github/callback
+/// ```
+///
+/// ## Parameters
+///
+/// * code - An authorization code issued by GitHub.
+/// * state - A state issued when the login began.
+/// * totp_code - A TOTP code or a backup code of the user. It is required only if the user enabled
+///   TOTP. If it responds `TOTP_REQUIRED`, the login must begin again with the code, as the
+///   authorization code can't be used again.
+/// * invite_code - An invite code to sign up, which is required only if the user doesn't exist yet
+///   and invite codes are required. A use of the code is consumed as in
+///   `POST /auth/token/sign_up`.
+///
+/// ```json
+/// {
+///     "code": "e72e16c7e42f292c6912",
+///     "state": "Xs8dm2",
+///     "totp_code": "287082",
+///     "invite_code": null
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": {
+///         "user_id": 0,
+///         "user_email": "park@email.com"
+///         "user_name": "park",
+///     },
+///     "error": null
+/// }
+/// ```
+#[post("/auth/oauth/github/callback")]
+pub async fn complete_github_login(
+    mut session: Session,
+    args: web::Json<CompleteOAuthLoginArgs>,
+) -> impl Responder {
+    let args: CompleteOAuthLoginArgs = args.into_inner();
+    let response = http_util::get_client()
+        .post(&http_util::get_url("/oauth/github/complete"))
+        .json(&args)
+        .send()
+        .await;
+    session_util::set_session_by_service_response(&mut session, response).await
+}
+
+/// Initializes the OAuth routes.
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(begin_google_login);
+    cfg.service(complete_google_login);
+    cfg.service(begin_github_login);
+    cfg.service(complete_github_login);
+}
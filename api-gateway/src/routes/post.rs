@@ -1,10 +1,13 @@
 use actix_session::Session;
-use actix_web::{delete, get, patch, post, web, Responder};
+use actix_web::{delete, get, patch, post, put, web, HttpRequest, Resource, Responder};
+use chrono::Utc;
+use http::header::{HeaderValue, CONTENT_DISPOSITION};
 use http::StatusCode;
-use reqwest::Client;
+use serde_json::Value;
 
 use crate::models::error::*;
 use crate::models::post::*;
+use crate::utils::payload_util::PayloadLimits;
 use crate::utils::{http_util, session_util};
 
 /// Responds a post written by logged-in user
@@ -25,8 +28,15 @@ use crate::utils::{http_util, session_util};
 ///             "title": "Lorem ipsum",
 ///             "content": "Lorem ipsum dolor sit amet",
 ///             "date": "2020-04-12T07:43:03",
-///             "created_at": "2020-04-13T16:31:09",
-///             "updated_at": null
+///             "created_at": "2020-04-13T16:31:09Z",
+///             "updated_at": null,
+///             "tags": ["travel"],
+///             "version": 1,
+///             "publish_at": null,
+///             "status": "published",
+///             "pinned": false,
+///             "mood": null,
+///             "category_id": null
 ///         },
 ///     ],
 ///     "error": null
@@ -35,131 +45,1206 @@ use crate::utils::{http_util, session_util};
 #[get("/posts/{id}")]
 pub async fn get_post(session: Session, id: web::Path<u64>) -> impl Responder {
     if let Some(user_session) = session_util::get_session(&session) {
-        let response = reqwest::get(&http_util::get_url(&format!(
-            "/posts/{}/{}",
-            user_session.user_id, id
-        )))
-        .await;
-        http_util::pass_response::<PostDTO>(response).await
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/posts/{}/{}",
+                user_session.user_id, id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<PostDTO>(response).await
+    } else {
+        http_util::get_err_response::<PostDTO>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Lists a page of posts written by logged-in user, with the total count of the posts.
+/// Pinned posts come first from the latest pinned one, and the rest are in desc date order.
+///
+/// # Request
+///
+/// ```text
+/// GET /posts?from=2020-04-01T00:00:00&to=2020-04-30T23:59:59&tag=travel&limit=20&offset=40
+/// GET /posts?tag=travel&tag=food&tag_mode=all
+/// GET /posts?category=1&limit=20
+/// GET /posts?limit=20&after=GIYDEMBNGA2C2MJQKQYDOORUGM5DAM27GI
+/// ```
+///
+/// ## Parameters
+///
+/// * from - An ISO-8601 date and time in the timezone of the user. Only posts dated on or after
+///   it are listed, if it is given.
+/// * to - An ISO-8601 date and time in the timezone of the user. Only posts dated on or before
+///   it are listed, if it is given.
+/// * tag - A tag. It can be repeated, and only posts bearing the tags are listed if it is given.
+/// * tag_mode - `any` to list posts bearing any of the tags, or `all` to list posts bearing all of
+///   them. It is `any` by default, and any other value is responded with 400 Bad Request.
+/// * category - An id of a category of the user. Only posts in it are listed, if it is given.
+/// * limit - A maximum number of posts in the page. It is 20 by default, and clamped between 1 and 100.
+/// * offset - A number of posts to skip. It is 0 by default.
+/// * after - A `next` cursor of the previous page. The page starts after it instead of the offset,
+///   and pinned posts are not listed again. Unlike the offset, it doesn't duplicate or skip posts
+///   even if posts are created or deleted between page loads.
+///
+/// ## Headers
+///
+/// * If-None-Match - An `ETag` of the previous response. It responds 304 Not Modified without
+///   body if the page hasn't changed since then.
+///
+/// # Response
+///
+/// The response has `ETag` header, which changes whenever the posts in the page or the total count
+/// change, such as when a post is updated.
+/// `next` is a cursor after the last post of the page if the page is full, or `null` otherwise.
+///
+/// ```json
+/// {
+///     "data": {
+///         "posts": [
+///             {
+///                 "id": 1,
+///                 "title": "Lorem ipsum",
+///                 "content": "Lorem ipsum dolor sit amet",
+///                 "date": "2020-04-12T07:43:03",
+///                 "created_at": "2020-04-13T16:31:09Z",
+///                 "updated_at": null,
+///                 "tags": ["travel"],
+///                 "version": 1,
+///                 "publish_at": null,
+///                 "status": "published",
+///                 "pinned": false,
+///                 "mood": null,
+///                 "category_id": null
+///             },
+///             {
+///                 "id": 2,
+///                 "title": "Lorem ipsum",
+///                 "content": "Lorem ipsum dolor sit amet",
+///                 "date": "2020-04-10T07:43:03",
+///                 "created_at": "2020-05-07T07:43:03Z",
+///                 "updated_at": "2020-05-09T16:07:41Z",
+///                 "tags": ["travel", "food"],
+///                 "version": 1,
+///                 "publish_at": null,
+///                 "status": "published",
+///                 "pinned": false,
+///                 "mood": null,
+///                 "category_id": null
+///             }
+///         ],
+///         "total_count": 42,
+///         "next": "GIYDEMBNGA2C2MJQKQYDOORUGM5DAM27GI"
+///     },
+///     "error": null
+/// }
+/// ```
+#[get("/posts")]
+pub async fn get_posts(
+    req: HttpRequest,
+    session: Session,
+    args: web::Query<GetListArgs>,
+) -> impl Responder {
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/posts/{}",
+                user_session.user_id
+            )))
+            .query(&args.into_inner())
+            .query(&http_util::get_query_pairs(req.query_string(), "tag"))
+            .send()
+            .await;
+        http_util::pass_response_with_etag::<PostPageDTO>(&req, response).await
+    } else {
+        http_util::get_err_response::<PostPageDTO>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Responds the number of posts written by logged-in user.
+///
+/// # Request
+///
+/// ```text
+/// GET /posts/count?from=2020-04-01T00:00:00&to=2020-04-30T23:59:59&tag=travel
+/// ```
+///
+/// ## Parameters
+///
+/// * from - An ISO-8601 date and time in the timezone of the user. Only posts dated on or after
+///   it are counted, if it is given.
+/// * to - An ISO-8601 date and time in the timezone of the user. Only posts dated on or before
+///   it are counted, if it is given.
+/// * tag - A tag. Only posts bearing it are counted, if it is given.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": 42,
+///     "error": null
+/// }
+/// ```
+#[get("/posts/count")]
+pub async fn count_posts(session: Session, args: web::Query<CountArgs>) -> impl Responder {
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/posts/{}/count",
+                user_session.user_id
+            )))
+            .query(&args.into_inner())
+            .send()
+            .await;
+        http_util::pass_response::<u64>(response).await
+    } else {
+        http_util::get_err_response::<u64>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Responds the number of posts written by logged-in user for each mood
+///
+/// Every mood is listed even if no post is tagged with it, and posts without a mood are not
+/// counted. Drafts, posts in the trash and posts scheduled to be published later are not counted.
+///
+/// # Request
+///
+/// ```text
+/// GET /posts/mood-stats?from=2020-04-01T00:00:00&to=2020-04-30T23:59:59
+/// ```
+///
+/// ## Parameters
+///
+/// * from - An ISO-8601 date and time in the timezone of the user. Only posts dated on or after
+///   it are counted, if it is given.
+/// * to - An ISO-8601 date and time in the timezone of the user. Only posts dated on or before
+///   it are counted, if it is given.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": [
+///         { "mood": "happy", "count": 12 },
+///         { "mood": "sad", "count": 3 },
+///         { "mood": "neutral", "count": 7 },
+///         { "mood": "anxious", "count": 1 },
+///         { "mood": "excited", "count": 4 }
+///     ],
+///     "error": null
+/// }
+/// ```
+#[get("/posts/mood-stats")]
+pub async fn get_mood_stats(session: Session, args: web::Query<MoodStatsArgs>) -> impl Responder {
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/posts/{}/mood-stats",
+                user_session.user_id
+            )))
+            .query(&args.into_inner())
+            .send()
+            .await;
+        http_util::pass_response::<Vec<MoodCountDTO>>(response).await
+    } else {
+        http_util::get_err_response::<Vec<MoodCountDTO>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Responds the writing streaks of logged-in user, which are numbers of consecutive days with at
+/// least one post. The current streak is kept until the end of the day after the last post.
+///
+/// Days are divided in the timezone of the user, which is UTC if it is not set.
+/// Posts in the trash and posts scheduled to be published later are not counted.
+///
+/// # Request
+///
+/// ```text
+/// GET /posts/streak
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": {
+///         "current_streak": 3,
+///         "longest_streak": 12
+///     },
+///     "error": null
+/// }
+/// ```
+#[get("/posts/streak")]
+pub async fn get_post_streak(session: Session) -> impl Responder {
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/posts/{}/streak",
+                user_session.user_id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<PostStreakDTO>(response).await
+    } else {
+        http_util::get_err_response::<PostStreakDTO>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Searches posts written by logged-in user, and lists a page of matched posts with the total
+/// count of them.
+///
+/// As the title and the content of a post are encrypted, the query is matched against tags.
+///
+/// # Request
+///
+/// ```text
+/// GET /posts/search?q=tra food&limit=20&offset=0
+/// ```
+///
+/// ## Parameters
+///
+/// * q - Terms separated by whitespace. A post matches if any of its tags starts with any of the
+///   terms, and posts matching more terms come first. It must not be empty.
+/// * limit - A maximum number of posts in the page. It is 20 by default, and clamped between 1 and 100.
+/// * offset - A number of posts to skip. It is 0 by default.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": {
+///         "posts": [
+///             {
+///                 "id": 2,
+///                 "title": "Lorem ipsum",
+///                 "content": "Lorem ipsum dolor sit amet",
+///                 "date": "2020-04-10T07:43:03",
+///                 "created_at": "2020-05-07T07:43:03Z",
+///                 "updated_at": null,
+///                 "tags": ["travel", "food"],
+///                 "version": 1,
+///                 "publish_at": null,
+///                 "status": "published",
+///                 "pinned": false,
+///                 "mood": null,
+///                 "category_id": null
+///             }
+///         ],
+///         "total_count": 1,
+///         "next": null
+///     },
+///     "error": null
+/// }
+/// ```
+#[get("/posts/search")]
+pub async fn search_posts(session: Session, args: web::Query<SearchArgs>) -> impl Responder {
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/posts/{}/search",
+                user_session.user_id
+            )))
+            .query(&args.into_inner())
+            .send()
+            .await;
+        http_util::pass_response::<PostPageDTO>(response).await
+    } else {
+        http_util::get_err_response::<PostPageDTO>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Lists summarized posts written by logged-in user
+///
+/// # Request
+///
+/// ```text
+/// GET /summarized_posts
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": [
+///         {
+///             "id": 1,
+///             "title": "Lorem ipsum",
+///             "date": "2020-04-12T07:43:03",
+///         },
+///         {
+///             "id": 2,
+///             "title": "Lorem ipsum",
+///             "date": "2020-04-10T07:43:03",
+///         },
+///     ],
+///     "error": null
+/// }
+/// ```
+#[get("/summarized_posts")]
+pub async fn get_summarized_posts(session: Session) -> impl Responder {
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/summarized_posts/{}",
+                user_session.user_id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<Vec<SummarizedPostDTO>>(response).await
+    } else {
+        http_util::get_err_response::<Vec<SummarizedPostDTO>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Returns arguments for creating a post in the service.
+/// The owner of the post is always the logged-in user, not the one given by the client.
+///
+/// # Arguments
+///
+/// * `args` - Arguments given by the client
+/// * `user_id` - An id of the logged-in user
+fn get_service_create_args(args: CreateArgs, user_id: u64) -> ServiceCreateArgs {
+    let CreateArgs {
+        title,
+        content,
+        date,
+        tags,
+        publish_at,
+        status,
+        mood,
+    } = args;
+
+    ServiceCreateArgs {
+        title,
+        content,
+        date,
+        tags,
+        publish_at,
+        status,
+        mood,
+        user_id,
+    }
+}
+
+/// Creates a new post
+///
+/// # Request
+///
+/// ```text
+/// POST /posts
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * content - A content of the post.
+/// * tags - Tags of the post. They are trimmed, lowercased and deduplicated, and up to 20 tags are allowed.
+/// * publish_at - A UTC date and time to publish the post. The post is not listed in `GET /posts`
+///   until then, but in `GET /posts/scheduled`. It is published immediately if it is not given.
+/// * status - `draft` or `published`. It is `draft` if it is not given. A draft is listed in
+///   `GET /posts/drafts` instead of `GET /posts` until `POST /posts/:id/publish`, and its content
+///   may be empty.
+/// * mood - `happy`, `sad`, `neutral`, `anxious` or `excited`. It is optional, and is not
+///   encrypted so that it is counted in `GET /posts/mood-stats`. An unknown mood responds
+///   400 Bad Request.
+///
+/// ```json
+/// {
+///     "title": "Lorem ipsum"
+///     "content": "Lorem ipsum dolor sit amet"
+///     "date": "2020-06-07T07:43:03",
+///     "tags": ["travel", "food"],
+///     "publish_at": "2020-06-07T00:00:00",
+///     "status": "published",
+///     "mood": "happy"
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": 1,
+///     "error": null
+/// }
+/// ```
+#[post("/posts")]
+pub async fn create_post(
+    req: HttpRequest,
+    session: Session,
+    args: web::Json<CreateArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<u64>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_session(&session) {
+        let args = get_service_create_args(args.into_inner(), user_session.user_id);
+
+        let response = http_util::get_client()
+            .post(&http_util::get_url("/posts"))
+            .json(&args)
+            .send()
+            .await;
+
+        http_util::pass_response::<u64>(response).await
+    } else {
+        http_util::get_err_response::<u64>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Exports all posts written by logged-in user as a JSON file, including posts in the trash and
+/// posts scheduled to be published later. The title and the content of the posts remain encrypted.
+///
+/// # Request
+///
+/// ```text
+/// GET /posts/export
+/// ```
+///
+/// # Response
+///
+/// It is an attachment named such as `darim-posts-20200607T074303Z.json`.
+///
+/// ```json
+/// {
+///     "data": [
+///         {
+///             "id": 1,
+///             "user_id": 1,
+///             "title": "U2FsdGVkX1+...",
+///             "content": "U2FsdGVkX1+...",
+///             "date": "2020-04-12T07:43:03",
+///             "created_at": "2020-04-13T16:31:09",
+///             "updated_at": null,
+///             "deleted_at": null,
+///             "version": 1,
+///             "publish_at": null,
+///             "status": "published",
+///             "pinned": false,
+///             "pinned_at": null
+///         }
+///     ],
+///     "error": null
+/// }
+/// ```
+#[get("/posts/export")]
+pub async fn export_posts(session: Session) -> impl Responder {
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/posts/{}/export",
+                user_session.user_id
+            )))
+            .send()
+            .await;
+
+        let mut response = http_util::pass_response::<Value>(response).await;
+        if response.status().is_success() {
+            let content_disposition = format!(
+                "attachment; filename=\"darim-posts-{}.json\"",
+                Utc::now().format("%Y%m%dT%H%M%SZ")
+            );
+            if let Ok(content_disposition) = HeaderValue::from_str(&content_disposition) {
+                response
+                    .headers_mut()
+                    .insert(CONTENT_DISPOSITION, content_disposition);
+            }
+        }
+
+        response
+    } else {
+        http_util::get_err_response::<Value>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Lists posts written by logged-in user, which are scheduled to be published later.
+/// The post to be published first comes first.
+///
+/// # Request
+///
+/// ```text
+/// GET /posts/scheduled
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": [
+///         {
+///             "id": 1,
+///             "title": "Lorem ipsum",
+///             "content": "Lorem ipsum dolor sit amet",
+///             "date": "2020-04-12T07:43:03",
+///             "created_at": "2020-04-10T16:31:09Z",
+///             "updated_at": null,
+///             "tags": [],
+///             "version": 1,
+///             "publish_at": "2020-04-12T00:00:00",
+///             "status": "published",
+///             "pinned": false,
+///             "mood": null,
+///             "category_id": null
+///         }
+///     ],
+///     "error": null
+/// }
+/// ```
+#[get("/posts/scheduled")]
+pub async fn get_scheduled_posts(session: Session) -> impl Responder {
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/posts/{}/scheduled",
+                user_session.user_id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<Vec<PostDTO>>(response).await
+    } else {
+        http_util::get_err_response::<Vec<PostDTO>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Imports posts of a JSON file exported by `GET /posts/export` as new posts of logged-in user.
+/// Original timestamps of the posts are preserved, and new ids are assigned.
+/// Malformed posts, or posts with an empty or too long title or content, are skipped and counted.
+///
+/// # Request
+///
+/// ```text
+/// POST /posts/import
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * data - Posts of the exported file. The payload is allowed up to 10 MiB, and up to 1000 posts are
+///   imported at once. An import counts as a creation against the post creation limit.
+///
+/// ```json
+/// {
+///     "data": [
+///         {
+///             "title": "Lorem ipsum",
+///             "content": "Lorem ipsum dolor sit amet",
+///             "date": "2020-04-12T07:43:03",
+///             "created_at": "2020-04-13T16:31:09",
+///             "updated_at": null,
+///             "deleted_at": null,
+///             "publish_at": null
+///         }
+///     ]
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": {
+///         "imported_count": 1,
+///         "skipped_count": 0
+///     },
+///     "error": null
+/// }
+/// ```
+pub async fn import_posts(
+    req: HttpRequest,
+    session: Session,
+    args: web::Json<ImportArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<PostImportResultDTO>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_session(&session) {
+        let args = ServiceImportArgs {
+            user_id: user_session.user_id,
+            posts: args.into_inner().data,
+        };
+
+        let response = http_util::get_client()
+            .post(&http_util::get_url("/posts/import"))
+            .json(&args)
+            .send()
+            .await;
+
+        http_util::pass_response::<PostImportResultDTO>(response).await
+    } else {
+        http_util::get_err_response::<PostImportResultDTO>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Lists posts in the trash written by logged-in user, the most recently deleted first.
+///
+/// # Request
+///
+/// ```text
+/// GET /posts/trash
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": [
+///         {
+///             "id": 1,
+///             "title": "Lorem ipsum",
+///             "content": "Lorem ipsum dolor sit amet",
+///             "date": "2020-04-12T07:43:03",
+///             "created_at": "2020-04-13T16:31:09Z",
+///             "updated_at": null,
+///             "tags": ["travel"],
+///             "version": 1,
+///             "publish_at": null,
+///             "status": "published",
+///             "pinned": false,
+///             "mood": null,
+///             "category_id": null
+///         }
+///     ],
+///     "error": null
+/// }
+/// ```
+#[get("/posts/trash")]
+pub async fn get_trashed_posts(session: Session) -> impl Responder {
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/posts/{}/trash",
+                user_session.user_id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<Vec<PostDTO>>(response).await
+    } else {
+        http_util::get_err_response::<Vec<PostDTO>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Lists drafts written by logged-in user in desc date order
+///
+/// # Request
+///
+/// ```text
+/// GET /posts/drafts
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": [
+///         {
+///             "id": 1,
+///             "title": "Lorem ipsum",
+///             "content": "",
+///             "date": "2020-04-12T07:43:03",
+///             "created_at": "2020-04-10T16:31:09Z",
+///             "updated_at": null,
+///             "tags": [],
+///             "version": 1,
+///             "publish_at": null,
+///             "status": "draft",
+///             "pinned": false,
+///             "mood": null,
+///             "category_id": null
+///         }
+///     ],
+///     "error": null
+/// }
+/// ```
+#[get("/posts/drafts")]
+pub async fn get_draft_posts(session: Session) -> impl Responder {
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/posts/{}/drafts",
+                user_session.user_id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<Vec<PostDTO>>(response).await
+    } else {
+        http_util::get_err_response::<Vec<PostDTO>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Publishes a draft. The title and the content of the draft must not be empty.
+///
+/// # Request
+///
+/// ```text
+/// POST /posts/:id/publish
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[post("/posts/{id}/publish")]
+pub async fn publish_post(
+    req: HttpRequest,
+    session: Session,
+    id: web::Path<u64>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .post(&http_util::get_url(&format!(
+                "/posts/{}/{}/publish",
+                user_session.user_id, id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<bool>(response).await
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Pins a post to the top of `GET /posts`. Up to 5 posts can be pinned.
+///
+/// # Request
+///
+/// ```text
+/// POST /posts/:id/pin
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[post("/posts/{id}/pin")]
+pub async fn pin_post(req: HttpRequest, session: Session, id: web::Path<u64>) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .post(&http_util::get_url(&format!(
+                "/posts/{}/{}/pin",
+                user_session.user_id, id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<bool>(response).await
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Unpins a post
+///
+/// # Request
+///
+/// ```text
+/// POST /posts/:id/unpin
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[post("/posts/{id}/unpin")]
+pub async fn unpin_post(req: HttpRequest, session: Session, id: web::Path<u64>) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .post(&http_util::get_url(&format!(
+                "/posts/{}/{}/unpin",
+                user_session.user_id, id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<bool>(response).await
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Shares a post by a public read-only link, and responds the token of the link.
+/// The same token is responded while the post is shared.
+///
+/// # Request
+///
+/// ```text
+/// POST /posts/:id/share
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": "lDr4FjE7tBkDUqWHXsDwE5sOPDsnzRj2",
+///     "error": null
+/// }
+/// ```
+#[post("/posts/{id}/share")]
+pub async fn share_post(req: HttpRequest, session: Session, id: web::Path<u64>) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<String>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .post(&http_util::get_url(&format!(
+                "/posts/{}/{}/share",
+                user_session.user_id, id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<String>(response).await
+    } else {
+        http_util::get_err_response::<String>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Puts a post into a category of logged-in user, or removes it from its category if the category
+/// is `null`.
+///
+/// # Request
+///
+/// ```text
+/// PUT /posts/:id/category
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * category_id - An id of a category of the user, or `null`.
+///
+/// ```json
+/// {
+///     "category_id": 1
+/// }
+/// ```
+///
+/// # Response
+///
+/// It responds 404 Not Found if the category is not the user's one.
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[put("/posts/{id}/category")]
+pub async fn set_post_category(
+    req: HttpRequest,
+    session: Session,
+    id: web::Path<u64>,
+    args: web::Json<SetCategoryArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .put(&http_util::get_url(&format!(
+                "/posts/{}/{}/category",
+                user_session.user_id, id
+            )))
+            .json(&args.into_inner())
+            .send()
+            .await;
+        http_util::pass_response::<bool>(response).await
     } else {
-        http_util::get_err_response::<PostDTO>(
+        http_util::get_err_response::<bool>(
             StatusCode::UNAUTHORIZED,
             &get_api_error_message(ApiGatewayError::Unauthorized),
         )
     }
 }
 
-/// Lists posts written by logged-in user
+/// Revokes the public link of a post. The revoked link is not found anymore.
 ///
 /// # Request
 ///
 /// ```text
-/// GET /posts
+/// DELETE /posts/:id/share
 /// ```
 ///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
 /// # Response
 ///
 /// ```json
 /// {
-///     "data": [
-///         {
-///             "id": 1,
-///             "title": "Lorem ipsum",
-///             "content": "Lorem ipsum dolor sit amet",
-///             "date": "2020-04-12T07:43:03",
-///             "created_at": "2020-04-13T16:31:09",
-///             "updated_at": null
-///         },
-///         {
-///             "id": 2,
-///             "title": "Lorem ipsum",
-///             "content": "Lorem ipsum dolor sit amet",
-///             "date": "2020-04-10T07:43:03",
-///             "created_at": "2020-05-07T07:43:03",
-///             "updated_at": "2020-05-09T16:07:41"
-///         },
-///     ],
+///     "data": true,
 ///     "error": null
 /// }
 /// ```
-#[get("/posts")]
-pub async fn get_posts(session: Session) -> impl Responder {
+#[delete("/posts/{id}/share")]
+pub async fn unshare_post(
+    req: HttpRequest,
+    session: Session,
+    id: web::Path<u64>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
     if let Some(user_session) = session_util::get_session(&session) {
-        let response = reqwest::get(&http_util::get_url(&format!(
-            "/posts/{}",
-            user_session.user_id
-        )))
-        .await;
-        http_util::pass_response::<Vec<PostDTO>>(response).await
+        let response = http_util::get_client()
+            .delete(&http_util::get_url(&format!(
+                "/posts/{}/{}/share",
+                user_session.user_id, id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<bool>(response).await
     } else {
-        http_util::get_err_response::<Vec<PostDTO>>(
+        http_util::get_err_response::<bool>(
             StatusCode::UNAUTHORIZED,
             &get_api_error_message(ApiGatewayError::Unauthorized),
         )
     }
 }
 
-/// Lists summarized posts written by logged-in user
+/// Responds a post shared by a public link, which is read without logging in
+///
+/// The title and the content remain end-to-end encrypted. The server never sees the plain text,
+/// so the reader's client decrypts them with the key, which the writer gives to the reader
+/// separately from the link (e.g. in the fragment of the URL, which is never sent to the server).
 ///
 /// # Request
 ///
 /// ```text
-/// GET /summarized_posts
+/// GET /public/posts/:token
 /// ```
 ///
 /// # Response
 ///
 /// ```json
 /// {
-///     "data": [
-///         {
-///             "id": 1,
-///             "title": "Lorem ipsum",
-///             "date": "2020-04-12T07:43:03",
-///         },
-///         {
-///             "id": 2,
-///             "title": "Lorem ipsum",
-///             "date": "2020-04-10T07:43:03",
-///         },
-///     ],
+///     "data": {
+///         "id": 1,
+///         "title": "Lorem ipsum",
+///         "content": "Lorem ipsum dolor sit amet",
+///         "date": "2020-04-12T07:43:03",
+///         "created_at": "2020-04-13T16:31:09Z",
+///         "updated_at": null,
+///         "tags": ["travel"],
+///         "version": 1,
+///         "publish_at": null,
+///         "status": "published",
+///         "pinned": false,
+///         "mood": null,
+///         "category_id": null
+///     },
 ///     "error": null
 /// }
 /// ```
-#[get("/summarized_posts")]
-pub async fn get_summarized_posts(session: Session) -> impl Responder {
-    if let Some(user_session) = session_util::get_session(&session) {
-        let response = reqwest::get(&http_util::get_url(&format!(
-            "/summarized_posts/{}",
-            user_session.user_id
+#[get("/public/posts/{token}")]
+pub async fn get_shared_post(token: web::Path<String>) -> impl Responder {
+    let response = http_util::get_client()
+        .get(&http_util::get_url(&format!(
+            "/public/posts/{}",
+            token.into_inner()
         )))
+        .send()
         .await;
-        http_util::pass_response::<Vec<SummarizedPostDTO>>(response).await
+    http_util::pass_response::<PostDTO>(response).await
+}
+
+/// Moves a post to the trash
+///
+/// # Request
+///
+/// ```text
+/// DELETE /posts/:id
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[delete("/posts/{id}")]
+pub async fn delete_post(req: HttpRequest, session: Session, id: web::Path<u64>) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .delete(&http_util::get_url(&format!(
+                "/posts/{}/{}",
+                user_session.user_id, id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<bool>(response).await
     } else {
-        http_util::get_err_response::<Vec<SummarizedPostDTO>>(
+        http_util::get_err_response::<bool>(
             StatusCode::UNAUTHORIZED,
             &get_api_error_message(ApiGatewayError::Unauthorized),
         )
     }
 }
 
-/// Creates a new post
+/// Moves posts to the trash at once, and responds the result of each post in the given order
+///
+/// Up to 100 posts are deleted in a batch, and duplicated ids are handled once. A post which
+/// can't be deleted doesn't fail the whole batch, but is responded with its reason:
+///
+/// * deleted - The post is moved to the trash.
+/// * not_found - The post doesn't exist, or is already in the trash.
+/// * forbidden - The post has been written by another user.
 ///
 /// # Request
 ///
 /// ```text
-/// POST /posts
+/// POST /posts/bulk-delete
 /// ```
 ///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
 /// ## Parameters
 ///
-/// * content - A content of the post.
+/// * ids - Ids of the posts to be deleted.
 ///
 /// ```json
 /// {
-///     "title": "Lorem ipsum"
-///     "content": "Lorem ipsum dolor sit amet"
-///     "date": "2020-06-07T07:43:03",
+///     "ids": [1, 2, 3]
 /// }
 /// ```
 ///
@@ -167,50 +1252,109 @@ pub async fn get_summarized_posts(session: Session) -> impl Responder {
 ///
 /// ```json
 /// {
-///     "data": 1,
+///     "data": [
+///         { "id": 1, "status": "deleted" },
+///         { "id": 2, "status": "forbidden" },
+///         { "id": 3, "status": "not_found" }
+///     ],
 ///     "error": null
 /// }
 /// ```
-#[post("/posts")]
-pub async fn create_post(session: Session, args: web::Json<CreateArgs>) -> impl Responder {
+#[post("/posts/bulk-delete")]
+pub async fn bulk_delete_posts(
+    req: HttpRequest,
+    session: Session,
+    args: web::Json<BulkDeleteArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<Vec<BulkDeleteResultDTO>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
     if let Some(user_session) = session_util::get_session(&session) {
-        let args = {
-            let CreateArgs {
-                title,
-                content,
-                date,
-            } = args.into_inner();
-            ServiceCreateArgs {
-                title,
-                content,
-                date,
-                user_id: user_session.user_id,
-            }
+        let args = ServiceBulkDeleteArgs {
+            user_id: user_session.user_id,
+            ids: args.into_inner().ids,
         };
 
-        let response = Client::new()
-            .post(&http_util::get_url("/posts"))
+        let response = http_util::get_client()
+            .post(&http_util::get_url("/posts/bulk-delete"))
             .json(&args)
             .send()
             .await;
+        http_util::pass_response::<Vec<BulkDeleteResultDTO>>(response).await
+    } else {
+        http_util::get_err_response::<Vec<BulkDeleteResultDTO>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
 
-        http_util::pass_response::<u64>(response).await
+/// Restores a post from the trash
+///
+/// # Request
+///
+/// ```text
+/// POST /posts/:id/restore
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[post("/posts/{id}/restore")]
+pub async fn restore_post(
+    req: HttpRequest,
+    session: Session,
+    id: web::Path<u64>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_session(&session) {
+        let response = http_util::get_client()
+            .post(&http_util::get_url(&format!(
+                "/posts/{}/{}/restore",
+                user_session.user_id, id
+            )))
+            .send()
+            .await;
+        http_util::pass_response::<bool>(response).await
     } else {
-        http_util::get_err_response::<u64>(
+        http_util::get_err_response::<bool>(
             StatusCode::UNAUTHORIZED,
             &get_api_error_message(ApiGatewayError::Unauthorized),
         )
     }
 }
 
-/// Deletes a post
+/// Deletes a post permanently
 ///
 /// # Request
 ///
 /// ```text
-/// DELETE /posts/:id
+/// DELETE /posts/:id/purge
 /// ```
 ///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
 /// # Response
 ///
 /// ```json
@@ -219,19 +1363,26 @@ pub async fn create_post(session: Session, args: web::Json<CreateArgs>) -> impl
 ///     "error": null
 /// }
 /// ```
-#[delete("/posts/{id}")]
-pub async fn delete_post(session: Session, id: web::Path<u64>) -> impl Responder {
+#[delete("/posts/{id}/purge")]
+pub async fn purge_post(req: HttpRequest, session: Session, id: web::Path<u64>) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
     if let Some(user_session) = session_util::get_session(&session) {
-        let response = Client::new()
+        let response = http_util::get_client()
             .delete(&http_util::get_url(&format!(
-                "/posts/{}/{}",
+                "/posts/{}/{}/purge",
                 user_session.user_id, id
             )))
             .send()
             .await;
         http_util::pass_response::<bool>(response).await
     } else {
-        http_util::get_err_response::<u64>(
+        http_util::get_err_response::<bool>(
             StatusCode::UNAUTHORIZED,
             &get_api_error_message(ApiGatewayError::Unauthorized),
         )
@@ -246,13 +1397,28 @@ pub async fn delete_post(session: Session, id: web::Path<u64>) -> impl Responder
 /// PATCH /posts/:id
 /// ```
 ///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
 /// ## Parameters
 ///
+/// Only the given fields are updated, and the others are left untouched. At least one field other
+/// than the version must be given.
+///
+/// * version - A version of the post the client has seen. If the post has been updated since then,
+///   it responds 409 Conflict so the client can merge the changes.
+/// * title - A title of the post.
 /// * content - A content of the post.
+/// * date - A date of the post.
+/// * tags - Tags of the post replacing the existing tags. They are normalized as in `POST /posts`.
+/// * mood - A mood of the post replacing the existing mood, as in `POST /posts`.
 ///
 /// ```json
 /// {
-///     "content": "Lorem ipsum dolor sit amet"
+///     "version": 3,
+///     "content": "Lorem ipsum dolor sit amet",
+///     "tags": ["travel"]
 /// }
 /// ```
 ///
@@ -266,26 +1432,40 @@ pub async fn delete_post(session: Session, id: web::Path<u64>) -> impl Responder
 /// ```
 #[patch("/posts/{id}")]
 pub async fn update_post(
+    req: HttpRequest,
     session: Session,
     id: web::Path<u64>,
     args: web::Json<UpdateArgs>,
 ) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
     if let Some(user_session) = session_util::get_session(&session) {
         let args = {
             let UpdateArgs {
+                version,
                 title,
                 content,
                 date,
+                tags,
+                mood,
             } = args.into_inner();
             ServiceUpdateArgs {
+                version,
                 title,
                 content,
                 date,
+                tags,
+                mood,
                 user_id: user_session.user_id,
             }
         };
 
-        let response = Client::new()
+        let response = http_util::get_client()
             .patch(&http_util::get_url(&format!("/posts/{}", id)))
             .json(&args)
             .send()
@@ -300,12 +1480,308 @@ pub async fn update_post(
     }
 }
 
+/// Returns the resource of `POST /posts/import` API, which allows a larger payload than others.
+/// The limit is `MAX_IMPORT_PAYLOAD_SIZE`, which is 10 MiB by default.
+fn import_resource() -> Resource {
+    web::resource("/posts/import")
+        .app_data(web::JsonConfig::default().limit(PayloadLimits::from_env().import))
+        .route(web::post().to(import_posts))
+}
+
 /// Initializes the post routes.
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(export_posts);
+    cfg.service(get_trashed_posts);
+    cfg.service(get_scheduled_posts);
+    cfg.service(get_draft_posts);
+    cfg.service(search_posts);
+    cfg.service(count_posts);
+    cfg.service(get_post_streak);
+    cfg.service(get_mood_stats);
     cfg.service(get_post);
     cfg.service(get_posts);
     cfg.service(get_summarized_posts);
     cfg.service(create_post);
+    cfg.service(import_resource());
     cfg.service(delete_post);
+    cfg.service(bulk_delete_posts);
+    cfg.service(publish_post);
+    cfg.service(pin_post);
+    cfg.service(unpin_post);
+    cfg.service(share_post);
+    cfg.service(unshare_post);
+    cfg.service(set_post_category);
+    cfg.service(get_shared_post);
+    cfg.service(restore_post);
+    cfg.service(purge_post);
     cfg.service(update_post);
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_session::CookieSession;
+    use actix_web::{test, App, HttpResponse};
+    use serde_json::{json, Value};
+    use std::env;
+
+    use super::*;
+    use crate::utils::test_util::set_test_session;
+
+    /// Moves the post 3 of the user 10 to the trash, and denies any other post of the user, as the
+    /// back-end service denies the post written by another user.
+    async fn delete_test_post(path: web::Path<(u64, u64)>) -> HttpResponse {
+        let (user_id, id) = path.into_inner();
+        assert_eq!(user_id, 10);
+
+        if id == 3 {
+            HttpResponse::Ok().json(json!({ "data": true, "error": null }))
+        } else {
+            HttpResponse::Unauthorized().json(json!({
+                "data": null,
+                "error": { "code": "UNAUTHORIZED", "message": "unauthorized" }
+            }))
+        }
+    }
+
+    /// Deletes the post with the session of the user 10 through the stubbed back-end service, and
+    /// returns the status and the body of the response.
+    async fn delete_post_of_test_user(id: u64) -> (StatusCode, Value) {
+        let service = test::start(|| {
+            App::new().route("/posts/{user_id}/{id}", web::delete().to(delete_test_post))
+        });
+        env::set_var(
+            "BACK_END_SERVICE_ADDRESS",
+            format!("http://{}", service.addr()),
+        );
+
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .route("/test/session", web::post().to(set_test_session))
+                .service(delete_post),
+        )
+        .await;
+
+        let req = test::TestRequest::post().uri("/test/session").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let session_cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "actix-session")
+            .unwrap()
+            .into_owned();
+        let csrf_token = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/posts/{}", id))
+            .cookie(session_cookie)
+            .header(session_util::CSRF_TOKEN_HEADER, csrf_token)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let status = resp.status();
+        (status, test::read_body_json(resp).await)
+    }
+
+    #[test]
+    fn test_delete_post_of_owner() {
+        http_util::run_back_end_test(async {
+            let (status, body) = delete_post_of_test_user(3).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["data"], json!(true));
+        });
+    }
+
+    #[test]
+    fn test_delete_post_of_other_user() {
+        http_util::run_back_end_test(async {
+            let (status, body) = delete_post_of_test_user(4).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            assert_eq!(body["error"]["code"], json!("UNAUTHORIZED"));
+        });
+    }
+
+    #[test]
+    fn test_get_service_create_args_with_spoofed_user_id() {
+        let args: CreateArgs = serde_json::from_value(json!({
+            "user_id": 99,
+            "title": "Lorem ipsum",
+            "content": "Lorem ipsum dolor sit amet",
+            "date": "2020-06-07T07:43:03",
+        }))
+        .unwrap();
+
+        let service_args = get_service_create_args(args, 10);
+
+        assert_eq!(service_args.user_id, 10);
+        assert_eq!(service_args.title, "Lorem ipsum");
+    }
+
+    #[actix_rt::test]
+    async fn test_create_post_without_session() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .service(create_post),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/posts")
+            .set_json(&json!({
+                "user_id": 10,
+                "title": "Lorem ipsum",
+                "content": "Lorem ipsum dolor sit amet",
+                "date": "2020-06-07T07:43:03",
+            }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_delete_post_without_csrf_token() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .route("/test/session", web::post().to(set_test_session))
+                .service(delete_post),
+        )
+        .await;
+
+        let req = test::TestRequest::post().uri("/test/session").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let session_cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "actix-session")
+            .unwrap()
+            .into_owned();
+
+        let req = test::TestRequest::delete()
+            .uri("/posts/3")
+            .cookie(session_cookie.clone())
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::delete()
+            .uri("/posts/3")
+            .cookie(session_cookie)
+            .header(session_util::CSRF_TOKEN_HEADER, "wrong")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_trash_and_import_routes_without_csrf_token() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .route("/test/session", web::post().to(set_test_session))
+                .service(restore_post)
+                .service(purge_post)
+                .service(import_resource()),
+        )
+        .await;
+
+        let req = test::TestRequest::post().uri("/test/session").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let session_cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "actix-session")
+            .unwrap()
+            .into_owned();
+
+        let req = test::TestRequest::post()
+            .uri("/posts/3/restore")
+            .cookie(session_cookie.clone())
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::delete()
+            .uri("/posts/3/purge")
+            .cookie(session_cookie.clone())
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::post()
+            .uri("/posts/import")
+            .cookie(session_cookie)
+            .set_json(&json!({ "data": [] }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_export_posts_without_session() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .service(export_posts),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/posts/export").to_request();
+        let resp = test::call_service(&mut app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert!(resp.headers().get(CONTENT_DISPOSITION).is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_delete_post_without_session() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .service(delete_post),
+        )
+        .await;
+
+        let req = test::TestRequest::delete().uri("/posts/3").to_request();
+        let resp = test::call_service(&mut app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_import_posts_without_session() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .service(import_resource()),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/posts/import")
+            .set_json(&json!({ "data": [] }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_import_posts_with_too_large_payload() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .service(import_resource()),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/posts/import")
+            .set_json(&json!({ "data": ["a".repeat(PayloadLimits::new().import)] }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -1,12 +1,98 @@
 use actix_session::Session;
-use actix_web::{delete, patch, post, web, Responder};
+use actix_web::{delete, get, patch, post, web, HttpRequest, Responder};
+use chrono::Utc;
+use http::header::{HeaderValue, CONTENT_DISPOSITION};
 use http::StatusCode;
-use reqwest::Client;
+use serde_json::Value;
 
 use crate::models::error::*;
 use crate::models::user::*;
 use crate::utils::{http_util, session_util};
 
+/// Exports all personal data of logged-in user as a JSON file: the profile, the public key, and
+/// all posts including posts in the trash and posts scheduled to be published later.
+/// The title and the content of the posts remain encrypted, and sensitive internal fields such as
+/// the password hash are excluded.
+///
+/// # Request
+///
+/// ```text
+/// GET /users/me/export
+/// ```
+///
+/// # Response
+///
+/// It is an attachment named such as `darim-personal-data-20200607T074303Z.json`.
+///
+/// ```json
+/// {
+///     "data": {
+///         "profile": {
+///             "id": 1,
+///             "name": "park",
+///             "email": "park@email.com",
+///             "avatar_url": null,
+///             "created_at": "2020-04-13T16:31:09",
+///             "updated_at": null,
+///             "last_login_at": "2020-06-07T07:43:03",
+///             "previous_login_at": null,
+///             "timezone": "Asia/Seoul",
+///             "reminder_enabled": false,
+///             "reminder_hour": 21,
+///             "locale": "ko"
+///         },
+///         "public_key": "d63ee429",
+///         "posts": [
+///             {
+///                 "id": 1,
+///                 "user_id": 1,
+///                 "title": "U2FsdGVkX1+...",
+///                 "content": "U2FsdGVkX1+...",
+///                 "date": "2020-04-12T07:43:03",
+///                 "created_at": "2020-04-13T16:31:09",
+///                 "updated_at": null,
+///                 "deleted_at": null,
+///                 "version": 1,
+///                 "publish_at": null
+///             }
+///         ]
+///     },
+///     "error": null
+/// }
+/// ```
+#[get("/users/me/export")]
+pub async fn export_personal_data(session: Session) -> impl Responder {
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/users/{}/export",
+                user_session.user_id
+            )))
+            .send()
+            .await;
+
+        let mut response = http_util::pass_response::<Value>(response).await;
+        if response.status().is_success() {
+            let content_disposition = format!(
+                "attachment; filename=\"darim-personal-data-{}.json\"",
+                Utc::now().format("%Y%m%dT%H%M%SZ")
+            );
+            if let Ok(content_disposition) = HeaderValue::from_str(&content_disposition) {
+                response
+                    .headers_mut()
+                    .insert(CONTENT_DISPOSITION, content_disposition);
+            }
+        }
+
+        response
+    } else {
+        http_util::get_err_response::<Value>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
 /// Creates a new user
 ///
 /// # Request
@@ -20,40 +106,252 @@ use crate::utils::{http_util, session_util};
 /// * user_public_key - A user's public key
 /// * token_key - A key for token search
 /// * token_pin - A pin for verifying
+/// * accepted_tos_version - A version of the terms of service accepted by the user, which must
+///   be the current one
 ///
 /// ```json
 /// {
 ///     "user_public_key": "d63ee429"
 ///     "token_key": "71I3Qz9u",
-///     "token_pin": "P9d82Jc5"
+///     "token_pin": "P9d82Jc5",
+///     "accepted_tos_version": "1"
 /// }
 /// ```
 ///
 /// # Response
 ///
+/// The user is logged in with the session of the new user.
+///
 /// ```json
 /// {
-///     "data": true,
+///     "data": {
+///         "user_id": 0,
+///         "user_email": "park@email.com"
+///         "user_name": "park",
+///         "user_public_key": "d63ee429"
+///     },
 ///     "error": null
 /// }
 /// ```
 #[post("/users")]
-pub async fn create_user(args: web::Json<CreateArgs>) -> impl Responder {
-    let response = Client::new()
+pub async fn create_user(mut session: Session, args: web::Json<CreateArgs>) -> impl Responder {
+    let response = http_util::get_client()
         .post(&http_util::get_url("/users"))
         .json(&args.into_inner())
         .send()
         .await;
 
-    http_util::pass_response::<bool>(response).await
+    session_util::set_session_by_service_response(&mut session, response).await
+}
+
+/// Requests to delete the account of logged-in user.
+///
+/// The user is re-authenticated by either the password or the TOTP code, and a link containing
+/// the token to confirm the deletion is sent to the email of the user.
+/// A user who has neither of them, such as a user signed up with OAuth, doesn't need to send them.
+///
+/// # Request
+///
+/// ```text
+/// POST /users/me/deletion
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * password - A password of the user.
+/// * totp_code - A TOTP code of the user, if the user has enabled TOTP.
+///
+/// ```json
+/// {
+///     "password": "71I3Qz9u",
+///     "totp_code": null
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[post("/users/me/deletion")]
+pub async fn request_account_deletion(
+    req: HttpRequest,
+    session: Session,
+    args: web::Json<RequestAccountDeletionArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_active_session(&session).await {
+        let response = http_util::get_client()
+            .post(&http_util::get_url(&format!(
+                "/users/{}/deletion",
+                user_session.user_id
+            )))
+            .json(&args.into_inner())
+            .send()
+            .await;
+
+        http_util::pass_response::<bool>(response).await
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Deletes the account of logged-in user with all data of the user, such as posts, and logs out.
+///
+/// # Request
+///
+/// ```text
+/// DELETE /users/me
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * token - A token in the link sent by `POST /users/me/deletion`.
+///
+/// ```json
+/// {
+///     "token": "VQxjRkO8HhmHkJ1dCHDBmsAqfpmDgy8i"
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[delete("/users/me")]
+pub async fn delete_account(
+    req: HttpRequest,
+    mut session: Session,
+    args: web::Json<DeleteAccountArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_active_session(&session).await {
+        let response = http_util::get_client()
+            .delete(&http_util::get_url(&format!(
+                "/users/{}/account",
+                user_session.user_id
+            )))
+            .json(&args.into_inner())
+            .send()
+            .await;
+
+        let response = http_util::pass_response::<bool>(response).await;
+        if response.status().is_success() {
+            session_util::unset_session(&mut session);
+        }
+
+        response
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Deactivates the account of logged-in user, and logs out.
+/// Posts of the user are kept, and the user cannot log in until the account is reactivated.
+///
+/// # Request
+///
+/// ```text
+/// POST /users/me/deactivate
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[post("/users/me/deactivate")]
+pub async fn deactivate_account(req: HttpRequest, mut session: Session) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let response = http_util::get_client()
+            .post(&http_util::get_url(&format!(
+                "/users/{}/deactivate",
+                user_session.user_id
+            )))
+            .send()
+            .await;
+
+        let response = http_util::pass_response::<bool>(response).await;
+        if response.status().is_success() {
+            session_util::unset_session(&mut session);
+        }
+
+        response
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
 }
 
-/// Deletes a user
+/// Accepts the current version of the terms of service, such as after the version is bumped.
 ///
 /// # Request
 ///
 /// ```text
-/// DELETE /users/:id
+/// POST /users/me/tos
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * version - A version of the terms of service, which must be the current one.
+///
+/// ```json
+/// {
+///     "version": "2"
+/// }
 /// ```
 ///
 /// # Response
@@ -64,13 +362,101 @@ pub async fn create_user(args: web::Json<CreateArgs>) -> impl Responder {
 ///     "error": null
 /// }
 /// ```
-#[delete("/users/{id}")]
-pub async fn delete_user(session: Session, id: web::Path<u64>) -> impl Responder {
-    if let Some(user_session) = session_util::get_session(&session) {
+#[post("/users/me/tos")]
+pub async fn accept_tos(
+    req: HttpRequest,
+    mut session: Session,
+    args: web::Json<AcceptTosArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let args = args.into_inner();
+        let response = http_util::get_client()
+            .post(&http_util::get_url(&format!(
+                "/users/{}/tos",
+                user_session.user_id
+            )))
+            .json(&args)
+            .send()
+            .await;
+
+        let response = http_util::pass_response::<bool>(response).await;
+        if response.status().is_success() {
+            session_util::set_session_tos_version_accepted(&mut session, &args.version);
+        }
+
+        response
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Updates a user
+///
+/// # Request
+///
+/// ```text
+/// PATCH /users/:id
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * name - A name of the user.
+/// * avatar_url - An avatar image url of the user.
+/// * timezone - A name of the IANA time zone database, such as `Asia/Seoul`. Days of the user are
+///   divided in it, and it is UTC if it is not set.
+///
+/// The password is changed by `POST /auth/change-password` with the current password.
+///
+/// ```json
+/// {
+///     "name": "park",
+///     "avatar_url": "avatar.jpg",
+///     "timezone": "Asia/Seoul"
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[patch("/users/{id}")]
+pub async fn update_user(
+    req: HttpRequest,
+    session: Session,
+    id: web::Path<u64>,
+    args: web::Json<UpdateArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_browser_session(&session) {
         let id_in_path = id.into_inner();
         if id_in_path == user_session.user_id {
-            let response = Client::new()
-                .delete(&http_util::get_url(&format!("/users/{}", id_in_path)))
+            let response = http_util::get_client()
+                .patch(&http_util::get_url(&format!("/users/{}", id_in_path)))
+                .json(&args.into_inner())
                 .send()
                 .await;
 
@@ -89,25 +475,120 @@ pub async fn delete_user(session: Session, id: web::Path<u64>) -> impl Responder
     }
 }
 
-/// Updates a user
+/// Updates the profile of logged-in user, and responds the updated profile.
+/// The name and the avatar image url cached in the session are refreshed as well.
 ///
 /// # Request
 ///
 /// ```text
-/// PATCH /users/:id
+/// PATCH /users/me
 /// ```
 ///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
 /// ## Parameters
 ///
-/// * name - A name of the user.
-/// * password - A password of the user.
-/// * avatar_url - An avatar image url of the user.
+/// * name - A name of the user. It must not be empty, and up to 50 characters are allowed.
+/// * avatar_url - An absolute http(s) url of the avatar image of the user.
+///
+/// A field is not changed if it is not given.
 ///
 /// ```json
 /// {
 ///     "name": "park",
-///     "password": "Ir5c7y8dS3",
-///     "avatar_url": "avatar.jpg"
+///     "avatar_url": "https://example.com/avatar.jpg"
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": {
+///         "name": "park",
+///         "avatar_url": "https://example.com/avatar.jpg"
+///     },
+///     "error": null
+/// }
+/// ```
+#[patch("/users/me")]
+pub async fn update_profile(
+    req: HttpRequest,
+    mut session: Session,
+    args: web::Json<UpdateProfileArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<UserProfileDTO>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    let user_session = match session_util::get_browser_session(&session) {
+        Some(user_session) => user_session,
+        None => {
+            return http_util::get_err_response::<UserProfileDTO>(
+                StatusCode::UNAUTHORIZED,
+                &get_api_error_message(ApiGatewayError::Unauthorized),
+            )
+        }
+    };
+
+    let response = http_util::get_client()
+        .patch(&http_util::get_url(&format!(
+            "/users/{}/profile",
+            user_session.user_id
+        )))
+        .json(&args.into_inner())
+        .send()
+        .await;
+    let response = match response {
+        Ok(response) if response.status().is_success() => response,
+        response => return http_util::pass_response::<UserProfileDTO>(response).await,
+    };
+
+    match http_util::parse_data_from_service_response::<UserProfileDTO>(response).await {
+        Ok(Some(profile)) => {
+            session_util::set_session_profile(&mut session, &profile.name, &profile.avatar_url);
+            http_util::get_ok_response::<UserProfileDTO>(profile)
+        }
+        Ok(None) => http_util::get_err_response::<UserProfileDTO>(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &get_api_error_message(ApiGatewayError::ServiceResponseParsingFailure),
+        ),
+        Err(error) => http_util::get_err_response::<UserProfileDTO>(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &get_api_error_message(error),
+        ),
+    }
+}
+
+/// Updates the daily reminder setting of a user.
+///
+/// A reminder email is sent once a day after the hour in the timezone of the user, if the user
+/// hasn't written a post dated the day.
+///
+/// # Request
+///
+/// ```text
+/// PATCH /users/:id/reminder
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * reminder_enabled - Whether the user is reminded daily.
+/// * reminder_hour - An hour to be reminded, between 0 and 23.
+///
+/// ```json
+/// {
+///     "reminder_enabled": true,
+///     "reminder_hour": 21
 /// }
 /// ```
 ///
@@ -119,17 +600,28 @@ pub async fn delete_user(session: Session, id: web::Path<u64>) -> impl Responder
 ///     "error": null
 /// }
 /// ```
-#[patch("/users/{id}")]
-pub async fn update_user(
+#[patch("/users/{id}/reminder")]
+pub async fn update_reminder(
+    req: HttpRequest,
     session: Session,
     id: web::Path<u64>,
-    args: web::Json<UpdateArgs>,
+    args: web::Json<UpdateReminderArgs>,
 ) -> impl Responder {
-    if let Some(user_session) = session_util::get_session(&session) {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_browser_session(&session) {
         let id_in_path = id.into_inner();
         if id_in_path == user_session.user_id {
-            let response = Client::new()
-                .patch(&http_util::get_url(&format!("/users/{}", id_in_path)))
+            let response = http_util::get_client()
+                .patch(&http_util::get_url(&format!(
+                    "/users/{}/reminder",
+                    id_in_path
+                )))
                 .json(&args.into_inner())
                 .send()
                 .await;
@@ -149,6 +641,186 @@ pub async fn update_user(
     }
 }
 
+/// Updates the locale of a user, in which emails are written.
+///
+/// English and Korean are supported, and emails are written in English if the locale is not set.
+///
+/// # Request
+///
+/// ```text
+/// PATCH /users/:id/locale
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * locale - A language tag such as `ko` or `en`. It is removed if it is null.
+///
+/// ```json
+/// {
+///     "locale": "ko"
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[patch("/users/{id}/locale")]
+pub async fn update_locale(
+    req: HttpRequest,
+    session: Session,
+    id: web::Path<u64>,
+    args: web::Json<UpdateLocaleArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let id_in_path = id.into_inner();
+        if id_in_path == user_session.user_id {
+            let response = http_util::get_client()
+                .patch(&http_util::get_url(&format!(
+                    "/users/{}/locale",
+                    id_in_path
+                )))
+                .json(&args.into_inner())
+                .send()
+                .await;
+
+            http_util::pass_response::<bool>(response).await
+        } else {
+            http_util::get_err_response::<bool>(
+                StatusCode::UNAUTHORIZED,
+                &get_api_error_message(ApiGatewayError::Unauthorized),
+            )
+        }
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Requests to change the email of the logged-in user.
+///
+/// The user is re-authenticated by either the password or the TOTP code as in
+/// `POST /users/me/deletion`. A confirmation link is sent to the new email, and the current email
+/// is notified of the request and is still used to log in until the change is confirmed.
+///
+/// # Request
+///
+/// ```text
+/// POST /users/me/email
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * email - A new email of the user.
+/// * password - A password of the user.
+/// * totp_code - A TOTP code of the user, if the user has enabled TOTP.
+///
+/// ```json
+/// {
+///     "email": "park@email.com",
+///     "password": "71I3Qz9u",
+///     "totp_code": null
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[post("/users/me/email")]
+pub async fn request_email_change(
+    req: HttpRequest,
+    session: Session,
+    args: web::Json<RequestEmailChangeArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_active_session(&session).await {
+        let response = http_util::get_client()
+            .post(&http_util::get_url(&format!(
+                "/users/{}/email",
+                user_session.user_id
+            )))
+            .json(&args.into_inner())
+            .send()
+            .await;
+
+        http_util::pass_response::<bool>(response).await
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Confirms the pending email change by the token in the confirmation link.
+///
+/// # Request
+///
+/// ```text
+/// POST /users/email/confirm
+/// ```
+///
+/// ## Parameters
+///
+/// * token - A token in the confirmation link.
+///
+/// ```json
+/// {
+///     "token": "VQxjRkO8HhmHkJ1dCHDBmsAqfpmDgy8i"
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[post("/users/email/confirm")]
+pub async fn confirm_email_change(args: web::Json<ConfirmEmailChangeArgs>) -> impl Responder {
+    let response = http_util::get_client()
+        .post(&http_util::get_url("/users/email/confirm"))
+        .json(&args.into_inner())
+        .send()
+        .await;
+
+    http_util::pass_response::<bool>(response).await
+}
+
 /// Resets the password.
 ///
 /// # Request
@@ -183,7 +855,7 @@ pub async fn update_user(
 /// ```
 #[post("/users/password")]
 pub async fn reset_password(args: web::Json<ResetPasswordArgs>) -> impl Responder {
-    let response = Client::new()
+    let response = http_util::get_client()
         .post(&http_util::get_url("/users/passwword"))
         .json(&args.into_inner())
         .send()
@@ -194,8 +866,196 @@ pub async fn reset_password(args: web::Json<ResetPasswordArgs>) -> impl Responde
 
 /// Initializes the user routes.
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(export_personal_data);
     cfg.service(create_user);
-    cfg.service(delete_user);
+    cfg.service(request_account_deletion);
+    cfg.service(delete_account);
+    cfg.service(deactivate_account);
+    cfg.service(accept_tos);
+    cfg.service(update_profile);
     cfg.service(update_user);
+    cfg.service(update_reminder);
+    cfg.service(update_locale);
+    cfg.service(request_email_change);
+    cfg.service(confirm_email_change);
     cfg.service(reset_password);
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_session::CookieSession;
+    use actix_web::{test, App};
+    use serde_json::json;
+
+    use super::*;
+    use crate::utils::test_util::set_test_session;
+
+    #[actix_rt::test]
+    async fn test_account_routes_without_csrf_token() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .route("/test/session", web::post().to(set_test_session))
+                .service(request_account_deletion)
+                .service(delete_account)
+                .service(deactivate_account)
+                .service(accept_tos)
+                .service(update_profile)
+                .service(update_user)
+                .service(update_reminder)
+                .service(update_locale)
+                .service(request_email_change),
+        )
+        .await;
+
+        let req = test::TestRequest::post().uri("/test/session").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let session_cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "actix-session")
+            .unwrap()
+            .into_owned();
+
+        let req = test::TestRequest::post()
+            .uri("/users/me/deletion")
+            .cookie(session_cookie.clone())
+            .set_json(&json!({ "password": "71I3Qz9u" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::delete()
+            .uri("/users/me")
+            .cookie(session_cookie.clone())
+            .set_json(&json!({ "token": "VQxjRkO8HhmHkJ1dCHDBmsAqfpmDgy8i" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::patch()
+            .uri("/users/me")
+            .cookie(session_cookie.clone())
+            .set_json(&json!({ "name": "park" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::patch()
+            .uri("/users/10")
+            .cookie(session_cookie.clone())
+            .set_json(&json!({ "timezone": "Asia/Seoul" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::patch()
+            .uri("/users/10/reminder")
+            .cookie(session_cookie.clone())
+            .set_json(&json!({ "reminder_enabled": true, "reminder_hour": 21 }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::patch()
+            .uri("/users/10/locale")
+            .cookie(session_cookie.clone())
+            .set_json(&json!({ "locale": "ko" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::post()
+            .uri("/users/me/deactivate")
+            .cookie(session_cookie.clone())
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::post()
+            .uri("/users/me/tos")
+            .cookie(session_cookie.clone())
+            .set_json(&json!({ "version": "2" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::post()
+            .uri("/users/me/email")
+            .cookie(session_cookie)
+            .header(session_util::CSRF_TOKEN_HEADER, "wrong")
+            .set_json(&json!({ "email": "new@email.com", "password": "71I3Qz9u" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_update_profile_without_session() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .service(update_profile)
+                .service(update_user),
+        )
+        .await;
+
+        let req = test::TestRequest::patch()
+            .uri("/users/me")
+            .set_json(&json!({ "name": "park" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+    #[actix_rt::test]
+    async fn test_request_email_change_without_session() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .service(request_email_change),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/users/me/email")
+            .set_json(&json!({ "email": "park@email.com" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+    #[actix_rt::test]
+    async fn test_export_personal_data_without_session() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .service(export_personal_data),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/users/me/export")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+    #[actix_rt::test]
+    async fn test_delete_account_without_session() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .service(delete_account),
+        )
+        .await;
+
+        let req = test::TestRequest::delete()
+            .uri("/users/me")
+            .set_json(&json!({ "token": "VQxjRkO8HhmHkJ1dCHDBmsAqfpmDgy8i" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,213 @@
+use actix_session::Session;
+use actix_web::{delete, get, post, web, HttpRequest, Responder};
+use http::StatusCode;
+
+use crate::models::error::*;
+use crate::models::user_key::*;
+use crate::utils::{http_util, session_util};
+
+/// Responds the public key of logged-in user.
+/// The previous public key is given only during the grace period of rotation.
+///
+/// # Request
+///
+/// ```text
+/// GET /users/me/key
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": {
+///         "public_key": "f0e2b8a1",
+///         "previous_public_key": "d63ee429",
+///         "rotated_at": "2020-06-07T07:43:03"
+///     },
+///     "error": null
+/// }
+/// ```
+#[get("/users/me/key")]
+pub async fn get_user_key(session: Session) -> impl Responder {
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/users/{}/key",
+                user_session.user_id
+            )))
+            .send()
+            .await;
+
+        http_util::pass_response::<UserKeyDTO>(response).await
+    } else {
+        http_util::get_err_response::<UserKeyDTO>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Responds the fingerprint of the public key of logged-in user, which is the SHA-256 of the key
+/// in hex grouped by 4 digits. It can be compared over a separate channel to verify the key.
+///
+/// # Request
+///
+/// ```text
+/// GET /users/me/key-fingerprint
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": "ba78 16bf 8f01 cfea 4141 40de 5dae 2223 b003 61a3 9617 7a9c b410 ff61 f200 15ad",
+///     "error": null
+/// }
+/// ```
+#[get("/users/me/key-fingerprint")]
+pub async fn get_user_key_fingerprint(session: Session) -> impl Responder {
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/users/{}/key-fingerprint",
+                user_session.user_id
+            )))
+            .send()
+            .await;
+
+        http_util::pass_response::<String>(response).await
+    } else {
+        http_util::get_err_response::<String>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Rotates the public key of logged-in user, and responds ids of all posts of the user, which
+/// must be re-encrypted with the new key.
+///
+/// The previous public key is kept until the rotation is completed by
+/// `DELETE /users/me/key/rotation`, or the grace period of 7 days is over.
+///
+/// # Request
+///
+/// ```text
+/// POST /users/me/key/rotation
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * public_key - A new public key of the user.
+///
+/// ```json
+/// {
+///     "public_key": "f0e2b8a1"
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": [1, 2, 3],
+///     "error": null
+/// }
+/// ```
+#[post("/users/me/key/rotation")]
+pub async fn rotate_user_key(
+    req: HttpRequest,
+    mut session: Session,
+    args: web::Json<RotateArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<Vec<u64>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_active_session(&session).await {
+        let args = args.into_inner();
+        let response = http_util::get_client()
+            .post(&http_util::get_url(&format!(
+                "/users/{}/key/rotation",
+                user_session.user_id
+            )))
+            .json(&args)
+            .send()
+            .await;
+
+        let response = http_util::pass_response::<Vec<u64>>(response).await;
+        if response.status().is_success() {
+            session_util::set_session_public_key(&mut session, &args.public_key);
+        }
+
+        response
+    } else {
+        http_util::get_err_response::<Vec<u64>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Completes the rotation of the public key of logged-in user after all posts are re-encrypted,
+/// and deletes the previous public key.
+///
+/// # Request
+///
+/// ```text
+/// DELETE /users/me/key/rotation
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[delete("/users/me/key/rotation")]
+pub async fn complete_user_key_rotation(req: HttpRequest, session: Session) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_active_session(&session).await {
+        let response = http_util::get_client()
+            .delete(&http_util::get_url(&format!(
+                "/users/{}/key/rotation",
+                user_session.user_id
+            )))
+            .send()
+            .await;
+
+        http_util::pass_response::<bool>(response).await
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Initializes the user key routes.
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_user_key);
+    cfg.service(get_user_key_fingerprint);
+    cfg.service(rotate_user_key);
+    cfg.service(complete_user_key_rotation);
+}
@@ -0,0 +1,227 @@
+use actix_session::Session;
+use actix_web::{delete, get, post, web, HttpRequest, Responder};
+use http::StatusCode;
+
+use crate::models::error::*;
+use crate::models::webhook::*;
+use crate::utils::{http_util, session_util};
+
+/// Registers a new webhook of logged-in user, to which `post.created`, `post.updated` and
+/// `post.deleted` events are posted. Each request has the name of the event in `X-Darim-Event`
+/// header, and the HMAC-SHA256 signature of the body with the secret in `X-Darim-Signature`
+/// header as `sha256=<hex>`. The secret is responded only once.
+///
+/// # Request
+///
+/// ```text
+/// POST /webhooks
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// ## Parameters
+///
+/// * url - An HTTP or HTTPS URL to which events are posted.
+///
+/// ```json
+/// {
+///     "url": "https://example.com/hooks/darim"
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": {
+///         "id": 1,
+///         "url": "https://example.com/hooks/darim",
+///         "secret": "Tq3yXc8bLw2KTq3yXc8bLw2KTq3yXc8b"
+///     },
+///     "error": null
+/// }
+/// ```
+#[post("/webhooks")]
+pub async fn register_webhook(
+    req: HttpRequest,
+    session: Session,
+    args: web::Json<RegisterWebhookArgs>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<RegisteredWebhook>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let args = ServiceRegisterWebhookArgs {
+            user_id: user_session.user_id,
+            url: args.into_inner().url,
+        };
+
+        let response = http_util::get_client()
+            .post(&http_util::get_url("/webhooks"))
+            .json(&args)
+            .send()
+            .await;
+
+        http_util::pass_response::<RegisteredWebhook>(response).await
+    } else {
+        http_util::get_err_response::<RegisteredWebhook>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Responds webhooks of logged-in user without their secrets.
+///
+/// # Request
+///
+/// ```text
+/// GET /webhooks
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": [
+///         {
+///             "id": 1,
+///             "url": "https://example.com/hooks/darim",
+///             "created_at": "2020-04-12T07:43:03"
+///         }
+///     ],
+///     "error": null
+/// }
+/// ```
+#[get("/webhooks")]
+pub async fn get_webhooks(session: Session) -> impl Responder {
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/webhooks/{}",
+                user_session.user_id
+            )))
+            .send()
+            .await;
+
+        http_util::pass_response::<Vec<WebhookDTO>>(response).await
+    } else {
+        http_util::get_err_response::<Vec<WebhookDTO>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Deletes a webhook of logged-in user with its deliveries.
+///
+/// # Request
+///
+/// ```text
+/// DELETE /webhooks/:id
+/// ```
+///
+/// ## Headers
+///
+/// * X-CSRF-Token - A CSRF token of the session from `GET /auth/csrf`.
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": true,
+///     "error": null
+/// }
+/// ```
+#[delete("/webhooks/{id}")]
+pub async fn delete_webhook(
+    req: HttpRequest,
+    session: Session,
+    id: web::Path<u64>,
+) -> impl Responder {
+    if !session_util::verify_csrf_token(&session, &req) {
+        return http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        );
+    }
+
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let response = http_util::get_client()
+            .delete(&http_util::get_url(&format!(
+                "/webhooks/{}/{}",
+                user_session.user_id, id
+            )))
+            .send()
+            .await;
+
+        http_util::pass_response::<bool>(response).await
+    } else {
+        http_util::get_err_response::<bool>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Responds recent deliveries of a webhook of logged-in user from the latest one.
+/// Each attempt of a delivery including retries is logged.
+///
+/// # Request
+///
+/// ```text
+/// GET /webhooks/:id/deliveries
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///     "data": [
+///         {
+///             "id": 2,
+///             "webhook_id": 1,
+///             "event": "post.created",
+///             "payload": "{\"event\":\"post.created\",\"post_id\":3,\"occurred_at\":\"2020-04-12T07:43:03\"}",
+///             "attempt": 1,
+///             "status_code": 200,
+///             "is_success": true,
+///             "created_at": "2020-04-12T07:43:04"
+///         }
+///     ],
+///     "error": null
+/// }
+/// ```
+#[get("/webhooks/{id}/deliveries")]
+pub async fn get_webhook_deliveries(session: Session, id: web::Path<u64>) -> impl Responder {
+    if let Some(user_session) = session_util::get_browser_session(&session) {
+        let response = http_util::get_client()
+            .get(&http_util::get_url(&format!(
+                "/webhooks/{}/{}/deliveries",
+                user_session.user_id, id
+            )))
+            .send()
+            .await;
+
+        http_util::pass_response::<Vec<WebhookDelivery>>(response).await
+    } else {
+        http_util::get_err_response::<Vec<WebhookDelivery>>(
+            StatusCode::UNAUTHORIZED,
+            &get_api_error_message(ApiGatewayError::Unauthorized),
+        )
+    }
+}
+
+/// Initializes the webhook routes.
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(register_webhook);
+    cfg.service(get_webhooks);
+    cfg.service(delete_webhook);
+    cfg.service(get_webhook_deliveries);
+}
@@ -0,0 +1,172 @@
+use actix_web::dev::{BodySize, MessageBody, Service, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, CONTENT_ENCODING, CONTENT_TYPE};
+use actix_web::middleware::Compress;
+use actix_web::Error;
+use std::future::Future;
+
+use crate::utils::env_util::get_env_var_or;
+
+/// Options of the compression of responses.
+#[derive(Clone, Copy)]
+pub struct CompressionConfig {
+    pub enabled: bool,
+    /// A minimum number of bytes of a JSON response to be compressed.
+    pub min_size: u64,
+}
+
+impl CompressionConfig {
+    /// Creates the default options compressing JSON responses of 1 KiB or more.
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            min_size: 1024,
+        }
+    }
+
+    /// Creates the default options overridden by `COMPRESSION_ENABLED` and
+    /// `COMPRESSION_MIN_SIZE`.
+    pub fn from_env() -> Self {
+        let default = Self::new();
+        Self {
+            enabled: get_env_var_or("COMPRESSION_ENABLED", default.enabled),
+            min_size: get_env_var_or("COMPRESSION_MIN_SIZE", default.min_size),
+        }
+    }
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the middleware compressing responses with gzip, brotli or deflate negotiated from
+/// `Accept-Encoding` header of the request. Which responses are compressed is decided by
+/// `skip_compression`.
+pub fn get_compress() -> Compress {
+    Compress::default()
+}
+
+/// Returns whether the response is a JSON response large enough to be worth compressing.
+fn is_compressible<B: MessageBody>(res: &ServiceResponse<B>, min_size: u64) -> bool {
+    let content_type = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    let is_json = matches!(content_type, Some(value) if value.starts_with("application/json"));
+    let is_large = match res.response().body().size() {
+        BodySize::Sized(size) => size >= min_size,
+        BodySize::Stream => true,
+        _ => false,
+    };
+
+    is_json && is_large
+}
+
+/// A middleware excluding responses from the compression, except JSON responses of at least the
+/// minimum size. Compressing small responses costs more than it saves.
+/// All responses are excluded if the compression is disabled.
+///
+/// The excluded responses have `Content-Encoding: identity` header, by which the outer `Compress`
+/// middleware leaves them alone. So this middleware must be wrapped before `Compress`.
+///
+/// # Arguments
+///
+/// * `req` - A request
+/// * `srv` - A service processing the request
+/// * `config` - Options of the compression
+pub fn skip_compression<S, B>(
+    req: ServiceRequest,
+    srv: &mut S,
+    config: CompressionConfig,
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    let response = srv.call(req);
+
+    async move {
+        let mut res = response.await?;
+        if !config.enabled || !is_compressible(&res, config.min_size) {
+            res.headers_mut()
+                .insert(CONTENT_ENCODING, HeaderValue::from_static("identity"));
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::header::ACCEPT_ENCODING;
+    use actix_web::{test, web, App, HttpResponse};
+
+    use super::*;
+
+    async fn get_large_list() -> HttpResponse {
+        HttpResponse::Ok().json(vec!["Lorem ipsum dolor sit amet"; 100])
+    }
+
+    async fn get_small_list() -> HttpResponse {
+        HttpResponse::Ok().json(vec!["Lorem ipsum"])
+    }
+
+    #[actix_rt::test]
+    async fn test_compress_large_response() {
+        let config = CompressionConfig::new();
+        let mut app = test::init_service(
+            App::new()
+                .wrap_fn(move |req, srv| skip_compression(req, srv, config))
+                .wrap(get_compress())
+                .route("/posts", web::get().to(get_large_list))
+                .route("/summarized_posts", web::get().to(get_small_list)),
+        )
+        .await;
+
+        for encoding in &["gzip", "br"] {
+            let req = test::TestRequest::get()
+                .uri("/posts")
+                .header(ACCEPT_ENCODING, *encoding)
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            assert_eq!(resp.headers().get(CONTENT_ENCODING).unwrap(), encoding);
+        }
+
+        let req = test::TestRequest::get().uri("/posts").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert!(resp.headers().get(CONTENT_ENCODING).is_none());
+        let body = test::read_body(resp).await;
+        assert!(body.starts_with(b"[\"Lorem ipsum dolor sit amet\""));
+
+        let req = test::TestRequest::get()
+            .uri("/summarized_posts")
+            .header(ACCEPT_ENCODING, "gzip")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.headers().get(CONTENT_ENCODING).unwrap(), "identity");
+        let body = test::read_body(resp).await;
+        assert_eq!(body, "[\"Lorem ipsum\"]");
+    }
+
+    #[actix_rt::test]
+    async fn test_compress_disabled() {
+        let config = CompressionConfig {
+            enabled: false,
+            ..CompressionConfig::new()
+        };
+        let mut app = test::init_service(
+            App::new()
+                .wrap_fn(move |req, srv| skip_compression(req, srv, config))
+                .wrap(get_compress())
+                .route("/posts", web::get().to(get_large_list)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/posts")
+            .header(ACCEPT_ENCODING, "gzip")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.headers().get(CONTENT_ENCODING).unwrap(), "identity");
+    }
+}
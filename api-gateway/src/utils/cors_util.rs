@@ -0,0 +1,177 @@
+use actix_cors::Cors;
+use std::env;
+
+use crate::utils::env_util::{get_env_var_list_or, get_env_var_or};
+use crate::utils::session_util::CSRF_TOKEN_HEADER;
+
+/// Options of CORS for the client served from a different origin.
+#[derive(Clone)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub supports_credentials: bool,
+    pub max_age: usize,
+}
+
+impl CorsConfig {
+    /// Creates the default options allowing only the client address with credentials,
+    /// so that the session cookie is sent by the browser.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_address` - An origin of the client such as `https://darim.vercel.app`
+    pub fn new(client_address: &str) -> Self {
+        Self {
+            allowed_origins: vec![client_address.to_string()],
+            allowed_methods: vec!["GET", "POST", "PATCH", "DELETE"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            allowed_headers: vec![
+                "Access-Control-Allow-Credentials",
+                "Content-Type",
+                CSRF_TOKEN_HEADER,
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            supports_credentials: true,
+            max_age: 3600,
+        }
+    }
+
+    /// Creates the default options overridden by comma-separated `CORS_ALLOWED_ORIGINS`,
+    /// `CORS_ALLOWED_METHODS` and `CORS_ALLOWED_HEADERS`, `CORS_SUPPORTS_CREDENTIALS` and
+    /// `CORS_MAX_AGE_SECONDS`. The origins fall back to `CLIENT_ADDRESS`.
+    ///
+    /// A wildcard origin is not allowed, because browsers don't send credentials to it.
+    pub fn from_env() -> Self {
+        let client_address = env::var("CLIENT_ADDRESS").expect("CLIENT_ADDRESS not found");
+        let default = Self::new(&client_address);
+        Self {
+            allowed_origins: get_env_var_list_or("CORS_ALLOWED_ORIGINS", default.allowed_origins),
+            allowed_methods: get_env_var_list_or("CORS_ALLOWED_METHODS", default.allowed_methods),
+            allowed_headers: get_env_var_list_or("CORS_ALLOWED_HEADERS", default.allowed_headers),
+            supports_credentials: get_env_var_or(
+                "CORS_SUPPORTS_CREDENTIALS",
+                default.supports_credentials,
+            ),
+            max_age: get_env_var_or("CORS_MAX_AGE_SECONDS", default.max_age),
+        }
+    }
+}
+
+/// Returns the CORS middleware with the options. Requests from the other origins are rejected.
+///
+/// # Arguments
+///
+/// * `config` - Options of CORS
+pub fn get_cors(config: &CorsConfig) -> Cors {
+    let cors = config
+        .allowed_origins
+        .iter()
+        .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+        .allowed_methods(config.allowed_methods.iter().map(String::as_str))
+        .allowed_headers(config.allowed_headers.iter().map(String::as_str))
+        .max_age(config.max_age);
+
+    if config.supports_credentials {
+        cors.supports_credentials()
+    } else {
+        cors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, Method, StatusCode};
+    use actix_web::{test, web, App, HttpResponse};
+
+    use super::*;
+
+    fn get_cors_config() -> CorsConfig {
+        CorsConfig {
+            allowed_origins: vec![
+                String::from("https://darim.vercel.app"),
+                String::from("http://localhost:8080"),
+            ],
+            ..CorsConfig::new("https://darim.vercel.app")
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_get_cors_preflight_from_allowed_origin() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(get_cors(&get_cors_config()))
+                .route("/posts", web::post().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = test::TestRequest::with_uri("/posts")
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "http://localhost:8080")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(
+                header::ACCESS_CONTROL_REQUEST_HEADERS,
+                "content-type, x-csrf-token",
+            )
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let headers = resp.headers();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "http://localhost:8080"
+        );
+        assert_eq!(
+            headers
+                .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+                .unwrap(),
+            "true"
+        );
+        assert!(headers
+            .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .contains("POST"));
+        assert!(headers
+            .get(header::ACCESS_CONTROL_ALLOW_HEADERS)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .contains("x-csrf-token"));
+    }
+
+    #[actix_rt::test]
+    async fn test_get_cors_from_disallowed_origin() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(get_cors(&get_cors_config()))
+                .route("/posts", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let preflight_req = test::TestRequest::with_uri("/posts")
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "https://evil.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .to_request();
+        let preflight_resp = test::call_service(&mut app, preflight_req).await;
+        assert_eq!(preflight_resp.status(), StatusCode::BAD_REQUEST);
+        assert!(preflight_resp
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+
+        let req = test::TestRequest::get()
+            .uri("/posts")
+            .header(header::ORIGIN, "https://evil.example.com")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+}
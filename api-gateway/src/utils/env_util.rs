@@ -0,0 +1,42 @@
+use std::env;
+use std::str::FromStr;
+
+/// Returns the value of the environment variable, or the default if it is not set or invalid.
+pub fn get_env_var_or<T: FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Returns the comma-separated values of the environment variable without surrounding spaces,
+/// or the default if it is not set.
+pub fn get_env_var_list_or(key: &str, default: Vec<String>) -> Vec<String> {
+    match env::var(key) {
+        Ok(value) => value
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect(),
+        Err(_) => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_env_var_list_or() {
+        env::set_var("DARIM_TEST_ENV_UTIL_LIST", "GET, POST,,DELETE ");
+
+        assert_eq!(
+            get_env_var_list_or("DARIM_TEST_ENV_UTIL_LIST", vec![]),
+            vec!["GET", "POST", "DELETE"]
+        );
+        assert_eq!(
+            get_env_var_list_or("DARIM_TEST_ENV_UTIL_MISSING", vec![String::from("GET")]),
+            vec!["GET"]
+        );
+    }
+}
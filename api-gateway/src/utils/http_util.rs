@@ -1,9 +1,14 @@
-use actix_web::HttpResponse;
+use actix_web::dev::{self, Service, ServiceRequest};
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use http::header::{HeaderMap, HeaderValue, ETAG, IF_NONE_MATCH, RETRY_AFTER};
 use http::StatusCode;
-use reqwest::Response;
+use reqwest::{Client, Response};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
 use std::env;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
 
 use crate::models::error::ApiGatewayError;
 
@@ -11,7 +16,24 @@ use crate::models::error::ApiGatewayError;
 #[derive(Deserialize, Serialize)]
 pub struct ServiceResponse<T> {
     data: Option<T>,
-    error: Option<String>,
+    error: Option<ErrorBody>,
+}
+
+/// An error in HTTP response, which has a stable code for clients and a message for humans.
+#[derive(Deserialize, Serialize)]
+pub struct ErrorBody {
+    code: String,
+    message: String,
+    /// Invalid fields of the request, which are given only for validation errors.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<FieldError>,
+}
+
+/// A validation failure of a field of the request.
+#[derive(Deserialize, Serialize)]
+pub struct FieldError {
+    field: String,
+    reason: String,
 }
 
 impl<T> ServiceResponse<T> {
@@ -21,11 +43,25 @@ impl<T> ServiceResponse<T> {
     }
 
     /// Creates a response containing error.
-    fn err(error: Option<String>) -> Self {
+    fn err(error: Option<ErrorBody>) -> Self {
         ServiceResponse { data: None, error }
     }
 }
 
+/// Returns a machine-readable code of the error responded with the status code by api gateway,
+/// such as `UNAUTHORIZED`. It is consistent with the code of the same error from back-end service.
+///
+/// # Arguments
+///
+/// * `status_code` - HTTP status code.
+fn get_error_code(status_code: StatusCode) -> String {
+    status_code
+        .canonical_reason()
+        .unwrap_or("Unknown")
+        .to_uppercase()
+        .replace(' ', "_")
+}
+
 /// Returns HttpResponse by status code.
 ///
 /// # Arguments
@@ -48,6 +84,13 @@ fn get_response_by_status_code<T: DeserializeOwned + Serialize>(
         StatusCode::UNAUTHORIZED => {
             HttpResponse::Unauthorized().json(ServiceResponse::<T>::err(error))
         }
+        StatusCode::TOO_MANY_REQUESTS => {
+            HttpResponse::TooManyRequests().json(ServiceResponse::<T>::err(error))
+        }
+        StatusCode::GONE => HttpResponse::Gone().json(ServiceResponse::<T>::err(error)),
+        StatusCode::PAYLOAD_TOO_LARGE => {
+            HttpResponse::PayloadTooLarge().json(ServiceResponse::<T>::err(error))
+        }
         _ => HttpResponse::InternalServerError().json(ServiceResponse::<T>::err(error)),
     }
 }
@@ -71,21 +114,27 @@ pub async fn parse_data_from_service_response<T: DeserializeOwned + Serialize>(
 /// # Arguments
 ///
 /// * `response` - HTTP response received from back-end service.
+///   `Retry-After` header is passed as well.
 pub async fn pass_response<T: DeserializeOwned + Serialize>(
     response: reqwest::Result<Response>,
 ) -> HttpResponse {
     match response {
         Ok(response) => {
             let status_code = response.status();
+            let retry_after = response.headers().get(RETRY_AFTER).cloned();
             match response.json::<ServiceResponse<T>>().await {
                 Ok(service_response) => {
-                    get_response_by_status_code::<T>(status_code, service_response)
-                }
-                Err(_) => {
-                    HttpResponse::InternalServerError().json(ServiceResponse::<T>::err(Some(
-                        format!("{}", ApiGatewayError::ServiceResponseParsingFailure),
-                    )))
+                    let mut http_response =
+                        get_response_by_status_code::<T>(status_code, service_response);
+                    if let Some(retry_after) = retry_after {
+                        http_response.headers_mut().insert(RETRY_AFTER, retry_after);
+                    }
+                    http_response
                 }
+                Err(_) => get_err_response::<T>(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    &format!("{}", ApiGatewayError::ServiceResponseParsingFailure),
+                ),
             }
         }
         Err(error) => get_response_by_status_code::<T>(
@@ -98,6 +147,93 @@ pub async fn pass_response<T: DeserializeOwned + Serialize>(
     }
 }
 
+/// Returns an entity tag of the data, which is a hash of the serialized data.
+/// It changes whenever anything in the data changes, such as the update time of a post.
+///
+/// # Arguments
+///
+/// * `data` - The data to be contained in response.
+fn get_etag<T: Serialize>(data: &T) -> String {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(data)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+/// Returns whether `If-None-Match` header of the request matches the entity tag.
+/// Weak tags such as `W/"..."` are compared as strong ones, as in GET requests.
+///
+/// # Arguments
+///
+/// * `req` - A request of the client.
+/// * `etag` - An entity tag of the current data.
+fn is_not_modified(req: &HttpRequest, etag: &str) -> bool {
+    let if_none_match = match req
+        .headers()
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(if_none_match) => if_none_match,
+        None => return false,
+    };
+
+    if_none_match.split(',').any(|tag| {
+        let tag = tag.trim();
+        tag == "*" || tag.trim_start_matches("W/") == etag
+    })
+}
+
+/// Returns 200 OK HTTP response that contains `data` with `ETag` header, or 304 Not Modified
+/// response without body if `If-None-Match` header of the request matches it.
+///
+/// # Arguments
+///
+/// * `req` - A request of the client.
+/// * `data` - The data to be contained in response.
+pub fn get_ok_response_with_etag<T: DeserializeOwned + Serialize>(
+    req: &HttpRequest,
+    data: T,
+) -> HttpResponse {
+    let etag = get_etag(&data);
+    if is_not_modified(req, &etag) {
+        return HttpResponse::NotModified().header(ETAG, etag).finish();
+    }
+
+    let mut http_response = get_ok_response(data);
+    if let Ok(etag) = etag.parse() {
+        http_response.headers_mut().insert(ETAG, etag);
+    }
+    http_response
+}
+
+/// Converts http response from back-end service as `pass_response`, with `ETag` header on 200 OK.
+/// It responds 304 Not Modified if `If-None-Match` header of the request matches the data.
+///
+/// # Arguments
+///
+/// * `req` - A request of the client.
+/// * `response` - HTTP response received from back-end service.
+pub async fn pass_response_with_etag<T: DeserializeOwned + Serialize>(
+    req: &HttpRequest,
+    response: reqwest::Result<Response>,
+) -> HttpResponse {
+    match response {
+        Ok(response) if response.status() == StatusCode::OK => {
+            match response.json::<ServiceResponse<T>>().await {
+                Ok(ServiceResponse {
+                    data: Some(data), ..
+                }) => get_ok_response_with_etag(req, data),
+                _ => get_err_response::<T>(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    &format!("{}", ApiGatewayError::ServiceResponseParsingFailure),
+                ),
+            }
+        }
+        response => pass_response::<T>(response).await,
+    }
+}
+
 /// Returns 200 OK HTTP response that contains `data`.
 ///
 /// # Arguments
@@ -117,7 +253,65 @@ pub fn get_err_response<T: DeserializeOwned + Serialize>(
     status_code: StatusCode,
     error: &str,
 ) -> HttpResponse {
-    get_response_by_status_code::<T>(status_code, ServiceResponse::err(Some(error.to_string())))
+    let error = ErrorBody {
+        code: get_error_code(status_code),
+        message: error.to_string(),
+        fields: vec![],
+    };
+    get_response_by_status_code::<T>(status_code, ServiceResponse::err(Some(error)))
+}
+
+/// A header forwarding the IP address of the client to back-end service, which keys rate limiting
+/// on it. Back-end service is only reachable through api gateway, so it can trust the header.
+pub const CLIENT_IP_HEADER: &str = "X-Forwarded-For";
+
+tokio::task_local! {
+    /// An IP address of the client of the request being handled.
+    static CLIENT_IP: Option<String>;
+}
+
+/// A middleware handling the request in the scope of the IP address of its client, so that
+/// requests to back-end service made while handling it forward the address.
+///
+/// The address of the peer is used rather than `X-Forwarded-For` header of the request, which the
+/// client could forge to get a new bucket of rate limiting on every request.
+///
+/// # Arguments
+///
+/// * `req` - A request
+/// * `srv` - A service processing the request
+pub fn forward_client_ip<S, B>(
+    req: ServiceRequest,
+    srv: &mut S,
+) -> impl Future<Output = Result<dev::ServiceResponse<B>, Error>>
+where
+    S: Service<Request = ServiceRequest, Response = dev::ServiceResponse<B>, Error = Error>,
+{
+    let client_ip = req.peer_addr().map(|address| address.ip().to_string());
+    CLIENT_IP.scope(client_ip, srv.call(req))
+}
+
+/// Returns headers forwarding the IP address of the client of the request being handled, which
+/// are empty outside of `forward_client_ip` middleware.
+fn get_client_ip_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let client_ip = CLIENT_IP
+        .try_with(|client_ip| client_ip.clone())
+        .ok()
+        .flatten();
+    if let Some(Ok(value)) = client_ip.map(|client_ip| HeaderValue::from_str(&client_ip)) {
+        headers.insert(CLIENT_IP_HEADER, value);
+    }
+    headers
+}
+
+/// Returns a client of back-end service, which forwards the IP address of the client of the
+/// request being handled.
+pub fn get_client() -> Client {
+    Client::builder()
+        .default_headers(get_client_ip_headers())
+        .build()
+        .unwrap_or_else(|_| Client::new())
 }
 
 /// Returns back-end service url.
@@ -129,3 +323,146 @@ pub fn get_url(resource: &str) -> String {
     let base_url = env::var("BACK_END_SERVICE_ADDRESS").unwrap();
     format!("{}{}", base_url, resource)
 }
+
+/// Runs the test which stubs the back-end service by setting `BACK_END_SERVICE_ADDRESS`.
+/// Such tests run one at a time, since the variable is shared by all tests running at the same
+/// time.
+///
+/// # Arguments
+///
+/// * `test` - A test starting the stubbed back-end service and calling routes.
+#[cfg(test)]
+pub fn run_back_end_test<F: Future<Output = ()> + 'static>(test: F) {
+    static BACK_END_TEST_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    let _lock = BACK_END_TEST_LOCK
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+    actix_rt::System::new("back_end_test").block_on(test);
+}
+
+/// Returns all pairs of the parameter repeated in the query string, in the given order, so that
+/// they can be passed to the back-end service as they are.
+/// `web::Query` can't deserialize a repeated parameter into a field, so it is read separately.
+///
+/// # Arguments
+///
+/// * `query_string` - A query string of the request.
+/// * `key` - A name of the parameter.
+pub fn get_query_pairs(query_string: &str, key: &str) -> Vec<(String, String)> {
+    match web::Query::<Vec<(String, String)>>::from_query(query_string) {
+        Ok(pairs) => pairs
+            .into_inner()
+            .into_iter()
+            .filter(|(pair_key, _)| pair_key == key)
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::{Body, ResponseBody};
+    use actix_web::test;
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_forward_client_ip() {
+        let mut app = test::init_service(actix_web::App::new().wrap_fn(forward_client_ip).route(
+            "/",
+            web::get().to(|| async {
+                get_client_ip_headers()
+                    .get(CLIENT_IP_HEADER)
+                    .map(|value| value.to_str().unwrap().to_string())
+                    .unwrap_or_default()
+            }),
+        ))
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/")
+            .peer_addr("203.0.113.7:54321".parse().unwrap())
+            .header(CLIENT_IP_HEADER, "10.0.0.1")
+            .to_request();
+        let body = test::read_response(&mut app, req).await;
+        assert_eq!(body, "203.0.113.7");
+    }
+
+    #[test]
+    fn test_get_client_ip_headers_outside_of_request() {
+        assert!(get_client_ip_headers().is_empty());
+    }
+
+    #[test]
+    fn test_get_err_response() {
+        let response = get_err_response::<bool>(StatusCode::UNAUTHORIZED, "unauthorized");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let body: Value = match response.body() {
+            ResponseBody::Body(Body::Bytes(bytes)) => serde_json::from_slice(bytes).unwrap(),
+            _ => panic!("unexpected response body"),
+        };
+        assert_eq!(
+            body,
+            json!({
+                "data": null,
+                "error": { "code": "UNAUTHORIZED", "message": "unauthorized" }
+            })
+        );
+    }
+
+    #[test]
+    fn test_get_ok_response_with_etag() {
+        let req = test::TestRequest::get().uri("/posts").to_http_request();
+        let response = get_ok_response_with_etag(&req, vec![1, 2]);
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers().get(ETAG).unwrap().to_str().unwrap();
+
+        let req = test::TestRequest::get()
+            .uri("/posts")
+            .header(IF_NONE_MATCH, etag)
+            .to_http_request();
+        let response = get_ok_response_with_etag(&req, vec![1, 2]);
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get(ETAG).unwrap(), etag);
+        assert!(matches!(response.body(), ResponseBody::Body(Body::Empty)));
+
+        let req = test::TestRequest::get()
+            .uri("/posts")
+            .header(IF_NONE_MATCH, format!("\"0\", W/{}", etag))
+            .to_http_request();
+        let response = get_ok_response_with_etag(&req, vec![1, 2]);
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let req = test::TestRequest::get()
+            .uri("/posts")
+            .header(IF_NONE_MATCH, etag)
+            .to_http_request();
+        let response = get_ok_response_with_etag(&req, vec![1, 3]);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers().get(ETAG).unwrap(), etag);
+    }
+
+    #[test]
+    fn test_get_error_code() {
+        assert_eq!(get_error_code(StatusCode::NOT_FOUND), "NOT_FOUND");
+        assert_eq!(
+            get_error_code(StatusCode::INTERNAL_SERVER_ERROR),
+            "INTERNAL_SERVER_ERROR"
+        );
+    }
+
+    #[test]
+    fn test_get_query_pairs() {
+        assert_eq!(
+            get_query_pairs("tag=travel&limit=20&tag=food%20trip", "tag"),
+            vec![
+                (String::from("tag"), String::from("travel")),
+                (String::from("tag"), String::from("food trip")),
+            ]
+        );
+        assert!(get_query_pairs("limit=20", "tag").is_empty());
+    }
+}
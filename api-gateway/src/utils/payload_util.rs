@@ -0,0 +1,169 @@
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::header::CONTENT_LENGTH;
+use actix_web::{web, Error};
+use http::StatusCode;
+use std::future::Future;
+
+use crate::models::error::{get_api_error_message, ApiGatewayError};
+use crate::utils::env_util::get_env_var_or;
+use crate::utils::http_util;
+use crate::utils::version_util::API_VERSION;
+
+/// Maximum numbers of bytes in the body of a request.
+#[derive(Clone, Copy)]
+pub struct PayloadLimits {
+    /// A limit of routes other than the below.
+    pub default: usize,
+    /// A limit of `/auth` routes, which never take a large body.
+    pub auth: usize,
+    /// A limit of `POST /posts/import` API, which takes a whole archive.
+    pub import: usize,
+}
+
+impl PayloadLimits {
+    /// Creates the default limits, which are 256 KiB, 16 KiB for auth routes, and 10 MiB for the
+    /// import route.
+    pub fn new() -> Self {
+        Self {
+            default: 256 * 1024,
+            auth: 16 * 1024,
+            import: 10 * 1024 * 1024,
+        }
+    }
+
+    /// Creates the default limits overridden by `MAX_PAYLOAD_SIZE`, `MAX_AUTH_PAYLOAD_SIZE` and
+    /// `MAX_IMPORT_PAYLOAD_SIZE`.
+    pub fn from_env() -> Self {
+        let default = Self::new();
+        Self {
+            default: get_env_var_or("MAX_PAYLOAD_SIZE", default.default),
+            auth: get_env_var_or("MAX_AUTH_PAYLOAD_SIZE", default.auth),
+            import: get_env_var_or("MAX_IMPORT_PAYLOAD_SIZE", default.import),
+        }
+    }
+
+    /// Returns the limit of the path, which may be prefixed with the version of the API.
+    pub fn get_limit(&self, path: &str) -> usize {
+        let version_prefix = format!("/{}/", API_VERSION);
+        let path = if path.starts_with(&version_prefix) {
+            &path[version_prefix.len() - 1..]
+        } else {
+            path
+        };
+
+        if path == "/auth" || path.starts_with("/auth/") {
+            self.auth
+        } else if path == "/posts/import" {
+            self.import
+        } else {
+            self.default
+        }
+    }
+}
+
+impl Default for PayloadLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the config of JSON bodies of the app, which limits a body to the default limit.
+pub fn get_json_config(limits: &PayloadLimits) -> web::JsonConfig {
+    web::JsonConfig::default().limit(limits.default)
+}
+
+/// Returns the config of raw bodies of the app, which limits a body to the default limit.
+pub fn get_payload_config(limits: &PayloadLimits) -> web::PayloadConfig {
+    web::PayloadConfig::new(limits.default)
+}
+
+/// A middleware responding 413 Payload Too Large without reading the body, if `Content-Length`
+/// header of the request is over the limit of the path.
+///
+/// A body without the header is still limited to the default limit by the JSON config of the app.
+///
+/// # Arguments
+///
+/// * `req` - A request
+/// * `srv` - A service processing the request
+/// * `limits` - Limits of the body
+pub fn limit_payload<S, B>(
+    req: ServiceRequest,
+    srv: &mut S,
+    limits: PayloadLimits,
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    let content_length = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+
+    let response = match content_length {
+        Some(content_length) if content_length > limits.get_limit(req.path()) => {
+            let message = get_api_error_message(ApiGatewayError::PayloadTooLarge);
+            let response =
+                http_util::get_err_response::<bool>(StatusCode::PAYLOAD_TOO_LARGE, &message);
+            Err(Error::from(InternalError::from_response(message, response)))
+        }
+        _ => Ok(srv.call(req)),
+    };
+
+    async move { response?.await }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_session::CookieSession;
+    use actix_web::{test, App};
+    use serde_json::json;
+
+    use super::*;
+    use crate::routes::auth::login;
+
+    #[test]
+    fn test_get_limit() {
+        let limits = PayloadLimits::new();
+
+        assert_eq!(limits.get_limit("/auth/login"), limits.auth);
+        assert_eq!(limits.get_limit("/v1/auth/login"), limits.auth);
+        assert_eq!(limits.get_limit("/posts/import"), limits.import);
+        assert_eq!(limits.get_limit("/v1/posts/import"), limits.import);
+        assert_eq!(limits.get_limit("/posts"), limits.default);
+        assert_eq!(limits.get_limit("/authors"), limits.default);
+    }
+
+    #[actix_rt::test]
+    async fn test_limit_payload_of_auth_route() {
+        let limits = PayloadLimits::new();
+        let mut app = test::init_service(
+            App::new()
+                .app_data(get_json_config(&limits))
+                .wrap_fn(move |req, srv| limit_payload(req, srv, limits))
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .service(login),
+        )
+        .await;
+
+        let body = json!({
+            "email": "park@email.com",
+            "password": "a".repeat(limits.auth),
+        })
+        .to_string();
+        let req = test::TestRequest::post()
+            .uri("/auth/login")
+            .header(CONTENT_LENGTH, body.len())
+            .header("Content-Type", "application/json")
+            .set_payload(body)
+            .to_request();
+        let error = app.call(req).await.err().unwrap();
+
+        assert_eq!(
+            error.as_response_error().status_code(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+}
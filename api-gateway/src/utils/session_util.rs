@@ -1,53 +1,382 @@
-use actix_session::Session;
+use actix_session::{CookieSession, Session};
+use actix_web::cookie::SameSite;
+use actix_web::{HttpRequest, HttpResponse};
+use chrono::{NaiveDateTime, Utc};
+use http::StatusCode;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use reqwest::Response;
+use std::env;
+use time::Duration;
 
-use crate::models::auth::UserSession;
+use crate::models::auth::{ActiveSession, UserSession};
+use crate::models::error::ApiGatewayError;
+use crate::utils::env_util::get_env_var_or;
+use crate::utils::{api_key_util, http_util};
+
+/// A header in which the client sends the CSRF token of the session.
+pub const CSRF_TOKEN_HEADER: &str = "X-CSRF-Token";
+
+/// A length of the CSRF token.
+const CSRF_TOKEN_LENGTH: usize = 32;
+
+/// A minimum number of bytes in the key signing the session cookie.
+const MIN_SESSION_COOKIE_KEY_LENGTH: usize = 64;
+
+/// A key and attributes of the session cookie.
+#[derive(Clone)]
+pub struct SessionCookieConfig {
+    /// A secret key signing the session cookie, so that a cookie forged by the client is rejected.
+    pub key: Vec<u8>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: SameSite,
+    pub domain: Option<String>,
+    pub max_age: Duration,
+}
+
+impl SessionCookieConfig {
+    /// Creates the default attributes with the key: `HttpOnly`, `SameSite=Lax`, a max-age of 30
+    /// days, and `Secure` only in production so that local development over HTTP still works.
+    pub fn new(is_production: bool, key: Vec<u8>) -> Self {
+        Self {
+            key,
+            secure: is_production,
+            http_only: true,
+            same_site: SameSite::Lax,
+            domain: None,
+            max_age: Duration::days(30),
+        }
+    }
+
+    /// Creates the default attributes overridden by `SESSION_COOKIE_SECURE`,
+    /// `SESSION_COOKIE_HTTP_ONLY`, `SESSION_COOKIE_SAME_SITE` (`strict`, `lax` or `none`),
+    /// `SESSION_COOKIE_DOMAIN` and `SESSION_COOKIE_MAX_AGE_DAYS`.
+    ///
+    /// The key is read from `SESSION_COOKIE_KEY`, which is required and must be at least 64 bytes.
+    /// It panics if the key is missing or too short, so that the gateway doesn't start with a
+    /// key anyone can sign a cookie with.
+    pub fn from_env(is_production: bool) -> Self {
+        let key = env::var("SESSION_COOKIE_KEY").expect("SESSION_COOKIE_KEY not found");
+        let key = parse_session_cookie_key(&key)
+            .unwrap_or_else(|error| panic!("SESSION_COOKIE_KEY is invalid: {}", error));
+
+        let default = Self::new(is_production, key);
+        Self {
+            key: default.key.clone(),
+            secure: get_env_var_or("SESSION_COOKIE_SECURE", default.secure),
+            http_only: get_env_var_or("SESSION_COOKIE_HTTP_ONLY", default.http_only),
+            same_site: env::var("SESSION_COOKIE_SAME_SITE")
+                .ok()
+                .and_then(|same_site| parse_same_site(&same_site))
+                .unwrap_or(default.same_site),
+            domain: env::var("SESSION_COOKIE_DOMAIN").ok().or(default.domain),
+            max_age: Duration::days(get_env_var_or(
+                "SESSION_COOKIE_MAX_AGE_DAYS",
+                default.max_age.whole_days(),
+            )),
+        }
+    }
+}
+
+/// Returns the bytes of the secret key signing the session cookie.
+/// Returns an error if it is shorter than 64 bytes.
+fn parse_session_cookie_key(key: &str) -> Result<Vec<u8>, String> {
+    if key.len() < MIN_SESSION_COOKIE_KEY_LENGTH {
+        return Err(format!(
+            "it must be at least {} bytes",
+            MIN_SESSION_COOKIE_KEY_LENGTH
+        ));
+    }
+
+    Ok(key.as_bytes().to_vec())
+}
+
+/// Parses `SameSite` attribute of a cookie case-insensitively.
+fn parse_same_site(same_site: &str) -> Option<SameSite> {
+    match same_site.to_lowercase().as_str() {
+        "strict" => Some(SameSite::Strict),
+        "lax" => Some(SameSite::Lax),
+        "none" => Some(SameSite::None),
+        _ => None,
+    }
+}
+
+/// Returns the session middleware storing user session in a signed cookie with the attributes.
+///
+/// # Arguments
+///
+/// * `config` - Attributes of the session cookie
+pub fn get_cookie_session(config: &SessionCookieConfig) -> CookieSession {
+    let cookie_session = CookieSession::signed(&config.key)
+        .secure(config.secure)
+        .http_only(config.http_only)
+        .same_site(config.same_site)
+        .max_age_time(config.max_age);
+
+    match &config.domain {
+        Some(domain) => cookie_session.domain(domain.as_str()),
+        None => cookie_session,
+    }
+}
 
 /// Sets user session.
 ///
 /// # Arguments
 ///
 /// * `session` - An session object
-/// * `user_id` - A record id of the user account
-/// * `user_email` -  An email of the user account
-/// * `user_name` - A name of the user account
-/// * `user_public_key` - A public key of the user account
-/// * `user_avatar_url` - A avatar image url of the user account
-pub fn set_session(
+/// * `user_session` - A session of the user account containing a record id, an email, a name,
+///   a public key, an avatar image url, a session epoch when the session is issued, a session id,
+///   times of the last and previous login, a version of the terms of service accepted by the
+///   user, and times when the session started and expires
+pub fn set_session(session: &mut Session, user_session: &UserSession) -> bool {
+    let is_set_user_id = session.set("user_id", user_session.user_id);
+    let is_set_user_email = session.set("user_email", &user_session.user_email);
+    let is_set_user_name = session.set("user_name", &user_session.user_name);
+    let is_set_user_public_key = session.set("user_public_key", &user_session.user_public_key);
+    let is_set_user_session_epoch =
+        session.set("user_session_epoch", user_session.user_session_epoch);
+    let is_set_user_session_id = session.set("user_session_id", &user_session.user_session_id);
+    let is_set_user_session_started_at = session.set(
+        "user_session_started_at",
+        user_session.user_session_started_at,
+    );
+    let is_set_user_session_expires_at = session.set(
+        "user_session_expires_at",
+        user_session.user_session_expires_at,
+    );
+
+    let is_set_user_avatar_url = if let Some(user_avatar_url) = &user_session.user_avatar_url {
+        session.set("user_avatar_url", user_avatar_url)
+    } else {
+        Ok(())
+    };
+
+    let is_set_user_last_login_at =
+        if let Some(user_last_login_at) = &user_session.user_last_login_at {
+            session.set("user_last_login_at", user_last_login_at)
+        } else {
+            Ok(())
+        };
+
+    let is_set_user_previous_login_at =
+        if let Some(user_previous_login_at) = &user_session.user_previous_login_at {
+            session.set("user_previous_login_at", user_previous_login_at)
+        } else {
+            Ok(())
+        };
+
+    let is_set_user_tos_version_accepted =
+        if let Some(user_tos_version_accepted) = &user_session.user_tos_version_accepted {
+            session.set("user_tos_version_accepted", user_tos_version_accepted)
+        } else {
+            Ok(())
+        };
+
+    !(is_set_user_id.is_err()
+        || is_set_user_email.is_err()
+        || is_set_user_name.is_err()
+        || is_set_user_public_key.is_err()
+        || is_set_user_avatar_url.is_err()
+        || is_set_user_session_epoch.is_err()
+        || is_set_user_session_id.is_err()
+        || is_set_user_session_started_at.is_err()
+        || is_set_user_session_expires_at.is_err()
+        || is_set_user_last_login_at.is_err()
+        || is_set_user_previous_login_at.is_err()
+        || is_set_user_tos_version_accepted.is_err())
+}
+
+/// Updates the name and the avatar image url cached in user session.
+///
+/// # Arguments
+///
+/// * `session` - An session object
+/// * `user_name` - A name of the user
+/// * `user_avatar_url` - An avatar image url of the user. It is removed from the session if it is `None`.
+pub fn set_session_profile(
     session: &mut Session,
-    user_id: u64,
-    user_email: &str,
     user_name: &str,
-    user_public_key: &str,
     user_avatar_url: &Option<String>,
 ) -> bool {
-    let is_set_user_id = session.set("user_id", user_id);
-    let is_set_user_email = session.set("user_email", user_email);
     let is_set_user_name = session.set("user_name", user_name);
-    let is_set_user_public_key = session.set("user_public_key", user_public_key);
-
     let is_set_user_avatar_url = if let Some(user_avatar_url) = user_avatar_url {
         session.set("user_avatar_url", user_avatar_url)
     } else {
+        session.remove("user_avatar_url");
         Ok(())
     };
 
-    !(is_set_user_id.is_err()
-        || is_set_user_email.is_err()
-        || is_set_user_name.is_err()
-        || is_set_user_public_key.is_err()
-        || is_set_user_avatar_url.is_err())
+    !(is_set_user_name.is_err() || is_set_user_avatar_url.is_err())
+}
+
+/// Updates the public key cached in user session, such as after the key is rotated.
+///
+/// # Arguments
+///
+/// * `session` - An session object
+/// * `user_public_key` - A public key of the user
+pub fn set_session_public_key(session: &mut Session, user_public_key: &str) -> bool {
+    session.set("user_public_key", user_public_key).is_ok()
+}
+
+/// Updates the version of the terms of service accepted by the user cached in user session.
+///
+/// # Arguments
+///
+/// * `session` - An session object
+/// * `version` - A version of the terms of service
+pub fn set_session_tos_version_accepted(session: &mut Session, version: &str) -> bool {
+    session.set("user_tos_version_accepted", version).is_ok()
 }
 
-/// Clears session.
+/// Sets user session by the session received from back-end service, and responds it.
+/// The error response from back-end service is passed as it is.
+///
+/// # Arguments
+///
+/// * `session` - An session object
+/// * `response` - A response of back-end service containing user session
+pub async fn set_session_by_service_response(
+    session: &mut Session,
+    response: reqwest::Result<Response>,
+) -> HttpResponse {
+    let response = match response {
+        Ok(response) if response.status().is_success() => response,
+        response => return http_util::pass_response::<UserSession>(response).await,
+    };
+
+    match http_util::parse_data_from_service_response::<UserSession>(response).await {
+        Ok(Some(user_session)) => {
+            set_session(session, &user_session);
+            set_csrf_token(session);
+            http_util::get_ok_response::<UserSession>(user_session)
+        }
+        Ok(None) => http_util::get_err_response::<UserSession>(
+            StatusCode::UNAUTHORIZED,
+            &format!("{}", ApiGatewayError::Unauthorized),
+        ),
+        Err(_) => http_util::get_err_response::<UserSession>(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("{}", ApiGatewayError::ServiceResponseParsingFailure),
+        ),
+    }
+}
+
+/// Issues a new CSRF token and sets it in user session, and returns it.
+/// It is issued on login, so that a token of the previous session is never reused.
+///
+/// # Arguments
+///
+/// * `session` - An session object
+pub fn set_csrf_token(session: &mut Session) -> Option<String> {
+    let csrf_token = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(CSRF_TOKEN_LENGTH)
+        .collect::<String>();
+
+    session.set("csrf_token", &csrf_token).ok()?;
+    Some(csrf_token)
+}
+
+/// Returns the CSRF token of user session.
+///
+/// # Arguments
+///
+/// * `session` - An session object
+pub fn get_csrf_token(session: &Session) -> Option<String> {
+    session.get::<String>("csrf_token").ok()?
+}
+
+/// Returns whether the `X-CSRF-Token` header of the request matches the CSRF token of user session.
+/// It is required for state-changing requests, since the session cookie is sent by the browser
+/// even if the request is forged by another site. The session authenticated by an API key is
+/// always verified.
+///
+/// # Arguments
+///
+/// * `session` - An session object
+/// * `req` - A request to be verified
+pub fn verify_csrf_token(session: &Session, req: &HttpRequest) -> bool {
+    if api_key_util::is_api_key_session(session) {
+        return true;
+    }
+
+    let csrf_token = if let Some(csrf_token) = get_csrf_token(session) {
+        csrf_token
+    } else {
+        return false;
+    };
+
+    match req.headers().get(CSRF_TOKEN_HEADER) {
+        Some(header) => is_equal_in_constant_time(header.as_bytes(), csrf_token.as_bytes()),
+        None => false,
+    }
+}
+
+/// Compares two byte strings in a time independent of where they differ.
+fn is_equal_in_constant_time(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Clears session and purges the session cookie.
 ///
 /// # Arguments
 ///
 /// * `session` - An session object
 pub fn unset_session(session: &mut Session) {
-    session.clear();
+    session.purge();
+}
+
+/// Returns user session only if it is authenticated by the session cookie, or `None` if the session
+/// is authenticated by an API key. Routes of the account, the auth and the user key use it instead
+/// of `get_session`, because an API key grants access only to posts and categories.
+///
+/// # Arguments
+///
+/// * `session` - An session object
+pub fn get_browser_session(session: &Session) -> Option<UserSession> {
+    if api_key_util::is_api_key_session(session) {
+        None
+    } else {
+        get_session(session)
+    }
+}
+
+/// Returns user session authenticated by the session cookie only if it is still active in the
+/// service, or `None` if it has been signed out, revoked or invalidated by another session.
+/// The session cookie itself stays valid until it expires, so routes changing the credentials or
+/// the account use it instead of `get_browser_session`.
+///
+/// # Arguments
+///
+/// * `session` - An session object
+pub async fn get_active_session(session: &Session) -> Option<UserSession> {
+    let user_session = get_browser_session(session)?;
+    let response = http_util::get_client()
+        .get(&http_util::get_url(&format!(
+            "/auth/sessions/{}",
+            user_session.user_id
+        )))
+        .send()
+        .await
+        .ok()?;
+    let active_sessions =
+        http_util::parse_data_from_service_response::<Vec<ActiveSession>>(response)
+            .await
+            .ok()??;
+
+    if active_sessions
+        .iter()
+        .any(|active_session| active_session.id == user_session.user_session_id)
+    {
+        Some(user_session)
+    } else {
+        None
+    }
 }
 
-/// Returns user session.
+/// Returns user session, or `None` if the session has expired.
 ///
 /// # Arguments
 ///
@@ -83,22 +412,193 @@ pub fn get_session(session: &Session) -> Option<UserSession> {
         return None;
     };
 
+    let user_session_epoch = if let Ok(session_epoch) = session.get::<u64>("user_session_epoch") {
+        session_epoch?
+    } else {
+        return None;
+    };
+
+    let user_session_id = if let Ok(session_id) = session.get::<String>("user_session_id") {
+        session_id?
+    } else {
+        return None;
+    };
+
+    let user_last_login_at =
+        if let Ok(last_login_at) = session.get::<NaiveDateTime>("user_last_login_at") {
+            last_login_at
+        } else {
+            return None;
+        };
+
+    let user_previous_login_at =
+        if let Ok(previous_login_at) = session.get::<NaiveDateTime>("user_previous_login_at") {
+            previous_login_at
+        } else {
+            return None;
+        };
+
+    let user_tos_version_accepted =
+        if let Ok(tos_version_accepted) = session.get::<String>("user_tos_version_accepted") {
+            tos_version_accepted
+        } else {
+            return None;
+        };
+
+    let user_session_started_at =
+        if let Ok(session_started_at) = session.get::<NaiveDateTime>("user_session_started_at") {
+            session_started_at?
+        } else {
+            return None;
+        };
+
+    let user_session_expires_at =
+        if let Ok(session_expires_at) = session.get::<NaiveDateTime>("user_session_expires_at") {
+            session_expires_at?
+        } else {
+            return None;
+        };
+
+    if user_session_expires_at <= Utc::now().naive_utc() {
+        return None;
+    }
+
     Some(UserSession {
         user_id,
         user_email,
         user_name,
         user_public_key,
         user_avatar_url,
+        user_session_epoch,
+        user_session_id,
+        user_last_login_at,
+        user_previous_login_at,
+        user_tos_version_accepted,
+        user_session_started_at,
+        user_session_expires_at,
     })
 }
 
 #[cfg(test)]
 mod tests {
-    use actix_session::UserSession;
-    use actix_web::test;
+    use actix_session::UserSession as _;
+    use actix_web::cookie::Cookie;
+    use actix_web::{test, web, App};
+    use chrono::Utc;
 
     use super::*;
 
+    #[test]
+    fn test_parse_same_site() {
+        assert_eq!(parse_same_site("Strict"), Some(SameSite::Strict));
+        assert_eq!(parse_same_site("lax"), Some(SameSite::Lax));
+        assert_eq!(parse_same_site("none"), Some(SameSite::None));
+        assert_eq!(parse_same_site("loose"), None);
+    }
+
+    async fn set_test_session(session: Session) -> HttpResponse {
+        session.set("user_id", 10).unwrap();
+        HttpResponse::Ok().finish()
+    }
+
+    async fn get_test_session(session: Session) -> HttpResponse {
+        HttpResponse::Ok().json(session.get::<u64>("user_id").unwrap())
+    }
+
+    fn get_test_key(byte: u8) -> Vec<u8> {
+        vec![byte; MIN_SESSION_COOKIE_KEY_LENGTH]
+    }
+
+    #[test]
+    fn test_parse_session_cookie_key() {
+        assert!(parse_session_cookie_key(&"k".repeat(64)).is_ok());
+        assert!(parse_session_cookie_key(&"k".repeat(63)).is_err());
+        assert!(parse_session_cookie_key("").is_err());
+    }
+
+    /// Returns the user id in the session cookie read with the key, if it is accepted.
+    async fn get_session_user_id(session_cookie: Cookie<'static>, key: Vec<u8>) -> Option<u64> {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(get_cookie_session(&SessionCookieConfig::new(false, key)))
+                .route("/test/session", web::get().to(get_test_session)),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/test/session")
+            .cookie(session_cookie)
+            .to_request();
+        test::read_response_json(&mut app, req).await
+    }
+
+    #[actix_rt::test]
+    async fn test_get_cookie_session_signed_with_another_key() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(get_cookie_session(&SessionCookieConfig::new(
+                    false,
+                    get_test_key(b'a'),
+                )))
+                .route("/test/session", web::post().to(set_test_session)),
+        )
+        .await;
+        let req = test::TestRequest::post().uri("/test/session").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let session_cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "actix-session")
+            .unwrap()
+            .into_owned();
+
+        assert_eq!(
+            get_session_user_id(session_cookie.clone(), get_test_key(b'a')).await,
+            Some(10)
+        );
+        assert_eq!(
+            get_session_user_id(session_cookie, get_test_key(b'b')).await,
+            None
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_get_cookie_session_in_production() {
+        let config = SessionCookieConfig {
+            domain: Some(String::from("example.com")),
+            ..SessionCookieConfig::new(true, get_test_key(b'a'))
+        };
+        let mut app = test::init_service(
+            App::new()
+                .wrap(get_cookie_session(&config))
+                .route("/test/session", web::post().to(set_test_session)),
+        )
+        .await;
+
+        let req = test::TestRequest::post().uri("/test/session").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let session_cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "actix-session")
+            .unwrap()
+            .into_owned();
+
+        assert_eq!(session_cookie.secure(), Some(true));
+        assert_eq!(session_cookie.http_only(), Some(true));
+        assert_eq!(session_cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(session_cookie.domain(), Some("example.com"));
+        assert_eq!(session_cookie.max_age(), Some(Duration::days(30)));
+    }
+
+    #[test]
+    fn test_session_cookie_config_in_local() {
+        let config = SessionCookieConfig::new(false, get_test_key(b'a'));
+
+        assert!(!config.secure);
+        assert!(config.http_only);
+        assert_eq!(config.same_site, SameSite::Lax);
+    }
+
     #[test]
     fn test_set_session() {
         let req = test::TestRequest::default().to_srv_request();
@@ -109,14 +609,25 @@ mod tests {
         let user_name = String::from("park");
         let user_public_key = String::from("d63ee429");
         let user_avatar_url = String::from("image.jpg");
+        let user_session_epoch = 2;
+        let user_last_login_at = Utc::now().naive_utc();
 
         let is_set_session = set_session(
             &mut session,
-            user_id,
-            &user_email,
-            &user_name,
-            &user_public_key,
-            &Some(user_avatar_url.clone()),
+            &UserSession {
+                user_id,
+                user_email: user_email.clone(),
+                user_name: user_name.clone(),
+                user_public_key: user_public_key.clone(),
+                user_avatar_url: Some(user_avatar_url.clone()),
+                user_session_epoch,
+                user_session_id: String::from("c2Vzc2lvbg"),
+                user_last_login_at: Some(user_last_login_at),
+                user_previous_login_at: None,
+                user_tos_version_accepted: None,
+                user_session_started_at: user_last_login_at,
+                user_session_expires_at: user_last_login_at + chrono::Duration::days(7),
+            },
         );
 
         assert_eq!(is_set_session, true);
@@ -134,6 +645,94 @@ mod tests {
             session.get::<String>("user_avatar_url").unwrap(),
             Some(user_avatar_url)
         );
+        assert_eq!(
+            session.get::<u64>("user_session_epoch").unwrap(),
+            Some(user_session_epoch)
+        );
+        assert_eq!(
+            session.get::<NaiveDateTime>("user_last_login_at").unwrap(),
+            Some(user_last_login_at)
+        );
+        assert_eq!(
+            session
+                .get::<NaiveDateTime>("user_previous_login_at")
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_set_session_profile() {
+        let req = test::TestRequest::default().to_srv_request();
+        let mut session = req.get_session();
+
+        session.set("user_name", "park").unwrap();
+        session.set("user_avatar_url", "image.jpg").unwrap();
+
+        assert!(set_session_profile(
+            &mut session,
+            "parksb",
+            &Some(String::from("https://example.com/avatar.jpg"))
+        ));
+        assert_eq!(
+            session.get::<String>("user_name").unwrap(),
+            Some(String::from("parksb"))
+        );
+        assert_eq!(
+            session.get::<String>("user_avatar_url").unwrap(),
+            Some(String::from("https://example.com/avatar.jpg"))
+        );
+
+        assert!(set_session_profile(&mut session, "parksb", &None));
+        assert_eq!(session.get::<String>("user_avatar_url").unwrap(), None);
+    }
+
+    #[test]
+    fn test_set_session_public_key() {
+        let req = test::TestRequest::default().to_srv_request();
+        let mut session = req.get_session();
+
+        session.set("user_public_key", "d63ee429").unwrap();
+
+        assert!(set_session_public_key(&mut session, "f0e2b8a1"));
+        assert_eq!(
+            session.get::<String>("user_public_key").unwrap(),
+            Some(String::from("f0e2b8a1"))
+        );
+    }
+
+    #[test]
+    fn test_verify_csrf_token() {
+        let req = test::TestRequest::default().to_srv_request();
+        let mut session = req.get_session();
+
+        let csrf_token = set_csrf_token(&mut session).unwrap();
+        assert_eq!(csrf_token.len(), CSRF_TOKEN_LENGTH);
+        assert_eq!(get_csrf_token(&session), Some(csrf_token.clone()));
+
+        let req_without_header = test::TestRequest::default().to_http_request();
+        assert!(!verify_csrf_token(&session, &req_without_header));
+
+        let req_with_wrong_token = test::TestRequest::default()
+            .header(CSRF_TOKEN_HEADER, "wrong")
+            .to_http_request();
+        assert!(!verify_csrf_token(&session, &req_with_wrong_token));
+
+        let req_with_token = test::TestRequest::default()
+            .header(CSRF_TOKEN_HEADER, csrf_token.as_str())
+            .to_http_request();
+        assert!(verify_csrf_token(&session, &req_with_token));
+    }
+
+    #[test]
+    fn test_verify_csrf_token_without_token_in_session() {
+        let req = test::TestRequest::default().to_srv_request();
+        let session = req.get_session();
+
+        let req = test::TestRequest::default()
+            .header(CSRF_TOKEN_HEADER, "")
+            .to_http_request();
+        assert!(!verify_csrf_token(&session, &req));
     }
 
     #[test]
@@ -157,6 +756,8 @@ mod tests {
         let user_name = "park";
         let user_public_key = "d63ee429";
         let user_avatar_url = String::from("image.jpg");
+        let user_session_epoch = 2;
+        let user_session_id = "c2Vzc2lvbg";
 
         session.set("user_id", user_id).unwrap();
         session.set("user_email", user_email).unwrap();
@@ -165,6 +766,19 @@ mod tests {
         session
             .set("user_avatar_url", &Some(user_avatar_url.clone()))
             .unwrap();
+        session
+            .set("user_session_epoch", user_session_epoch)
+            .unwrap();
+        session.set("user_session_id", user_session_id).unwrap();
+        session
+            .set("user_session_started_at", Utc::now().naive_utc())
+            .unwrap();
+        session
+            .set(
+                "user_session_expires_at",
+                Utc::now().naive_utc() + chrono::Duration::days(7),
+            )
+            .unwrap();
 
         let user_session = get_session(&session);
 
@@ -180,5 +794,40 @@ mod tests {
             user_session.as_ref().unwrap().user_avatar_url,
             Some(user_avatar_url)
         );
+        assert_eq!(
+            user_session.as_ref().unwrap().user_session_epoch,
+            user_session_epoch
+        );
+        assert_eq!(
+            user_session.as_ref().unwrap().user_session_id,
+            user_session_id
+        );
+    }
+
+    #[test]
+    fn test_get_expired_session() {
+        let req = test::TestRequest::default().to_srv_request();
+        let session = req.get_session();
+
+        session.set("user_id", 10).unwrap();
+        session.set("user_email", "user@email.com").unwrap();
+        session.set("user_name", "park").unwrap();
+        session.set("user_public_key", "d63ee429").unwrap();
+        session.set("user_session_epoch", 2).unwrap();
+        session.set("user_session_id", "c2Vzc2lvbg").unwrap();
+        session
+            .set(
+                "user_session_started_at",
+                Utc::now().naive_utc() - chrono::Duration::days(8),
+            )
+            .unwrap();
+        session
+            .set(
+                "user_session_expires_at",
+                Utc::now().naive_utc() - chrono::Duration::days(1),
+            )
+            .unwrap();
+
+        assert!(get_session(&session).is_none());
     }
 }
@@ -0,0 +1,33 @@
+use actix_session::Session;
+use actix_web::HttpResponse;
+use chrono::{Duration, Utc};
+
+use crate::models::auth::UserSession;
+use crate::utils::session_util;
+
+/// Returns the session of the user 10, who has accepted the terms of service of version 1.
+/// Fields are overridden by the struct update syntax, such as `UserSession { user_session_id, ..get_user_session() }`.
+pub fn get_user_session() -> UserSession {
+    UserSession {
+        user_id: 10,
+        user_email: String::from("user@email.com"),
+        user_name: String::from("park"),
+        user_public_key: String::from("d63ee429"),
+        user_avatar_url: None,
+        user_session_epoch: 0,
+        user_session_id: String::from("c2Vzc2lvbg"),
+        user_last_login_at: None,
+        user_previous_login_at: None,
+        user_tos_version_accepted: Some(String::from("1")),
+        user_session_started_at: Utc::now().naive_utc(),
+        user_session_expires_at: Utc::now().naive_utc() + Duration::days(7),
+    }
+}
+
+/// Sets the session of the user 10, and responds the CSRF token of the session.
+/// Tests route it to `POST /test/session` to get the session cookie.
+pub async fn set_test_session(mut session: Session) -> HttpResponse {
+    session_util::set_session(&mut session, &get_user_session());
+    let csrf_token = session_util::set_csrf_token(&mut session).unwrap();
+    HttpResponse::Ok().body(csrf_token)
+}
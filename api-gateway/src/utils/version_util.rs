@@ -0,0 +1,93 @@
+use actix_web::middleware::DefaultHeaders;
+use actix_web::web;
+
+/// A current version of the API, under which all routes are mounted as `/v1`.
+pub const API_VERSION: &str = "v1";
+/// A header of every response having the version of the API.
+pub const API_VERSION_HEADER: &str = "X-API-Version";
+/// A header of responses from the legacy unversioned routes, which will be removed.
+pub const DEPRECATION_HEADER: &str = "Deprecation";
+
+/// Returns the middleware adding the version of the API to every response.
+pub fn get_version_headers() -> DefaultHeaders {
+    DefaultHeaders::new().header(API_VERSION_HEADER, API_VERSION)
+}
+
+/// Registers the routes under the scope of the current version of the API such as `/v1/posts`.
+///
+/// The same routes are also registered without the prefix such as `/posts` during the deprecation
+/// window, so that existing clients keep working. Responses from them have `Deprecation` header.
+///
+/// # Arguments
+///
+/// * `cfg` - A service config of the app
+/// * `init_routes` - A function registering the routes
+/// * `legacy_routes_enabled` - Whether the unversioned routes are registered as aliases
+pub fn init_versioned_routes<F>(
+    cfg: &mut web::ServiceConfig,
+    init_routes: F,
+    legacy_routes_enabled: bool,
+) where
+    F: Fn(&mut web::ServiceConfig),
+{
+    cfg.service(web::scope(&format!("/{}", API_VERSION)).configure(|cfg| init_routes(cfg)));
+
+    if legacy_routes_enabled {
+        cfg.service(
+            web::scope("")
+                .wrap(DefaultHeaders::new().header(DEPRECATION_HEADER, "true"))
+                .configure(|cfg| init_routes(cfg)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_session::CookieSession;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+
+    use super::*;
+    use crate::routes;
+
+    #[actix_rt::test]
+    async fn test_init_versioned_routes() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(get_version_headers())
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .configure(|cfg| init_versioned_routes(cfg, routes::init_routes, true)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/v1/posts").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.headers().get(API_VERSION_HEADER).unwrap(), "v1");
+        assert!(resp.headers().get(DEPRECATION_HEADER).is_none());
+
+        let req = test::TestRequest::get().uri("/posts").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.headers().get(API_VERSION_HEADER).unwrap(), "v1");
+        assert_eq!(resp.headers().get(DEPRECATION_HEADER).unwrap(), "true");
+    }
+
+    #[actix_rt::test]
+    async fn test_init_versioned_routes_without_legacy_routes() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .configure(|cfg| init_versioned_routes(cfg, routes::init_routes, false)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/v1/posts").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::get().uri("/posts").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -21,6 +21,11 @@ interface SetPasswordTokenBody {
   email: string;
 }
 
+interface ChangePasswordBody {
+  current_password: string;
+  new_password: string;
+}
+
 async function fetchSession(): Promise<Session | null> {
   const url = `${serverBaseUrl}/auth`;
 
@@ -150,4 +155,27 @@ async function setPasswordToken(email: string): Promise<boolean | null> {
   return null;
 }
 
-export { fetchSession, refreshSession, login, logout, setSignUpToken, setPasswordToken };
+async function changePassword(currentPassword: string, newPassword: string): Promise<boolean | null> {
+  const url = `${serverBaseUrl}/auth/change-password`;
+  const body: ChangePasswordBody = {
+    current_password: SHA3(currentPassword, { outputLength: 512 }).toString(),
+    new_password: SHA3(newPassword, { outputLength: 512 }).toString(),
+  };
+
+  try {
+    return await Http.post<ChangePasswordBody, boolean>(url, body);
+  } catch (e) {
+    const i18n = getI18n({
+      error: {
+        ko: '변경에 실패했습니다',
+        en: 'Failed to update',
+      },
+    });
+
+    alert(i18n.text('error'));
+  }
+
+  return null;
+}
+
+export { fetchSession, refreshSession, login, logout, setSignUpToken, setPasswordToken, changePassword };
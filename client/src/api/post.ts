@@ -8,9 +8,11 @@ interface CreatePostBody {
   title: string;
   date: string;
   content: string;
+  status: 'draft' | 'published';
 }
 
 interface UpdatePostBody {
+  version: number;
   title?: string;
   date?: string;
   content?: string;
@@ -70,7 +72,7 @@ async function fetchPost(id: number, publicKey: string): Promise<Post | null> {
     const privateKey = Secret.decryptAES(encryptedPrivateKey, publicKey);
 
     if (privateKey) {
-      const { id, title, content, date, created_at, updated_at } = post;
+      const { id, title, content, date, created_at, updated_at, version } = post;
       return {
         id,
         title: Secret.decryptAES(title, privateKey),
@@ -78,6 +80,7 @@ async function fetchPost(id: number, publicKey: string): Promise<Post | null> {
         date,
         created_at,
         updated_at,
+        version,
       };
     } else {
       return null;
@@ -109,6 +112,7 @@ async function createPost(publicKey: string, title: string, date: string, conten
       title: encryptedTitle,
       date,
       content: encryptedContent,
+      status: 'published',
     };
 
     return await Http.post<CreatePostBody, number>(url, body);
@@ -126,7 +130,7 @@ async function createPost(publicKey: string, title: string, date: string, conten
   return null;
 }
 
-async function updatePost(publicKey: string, id: number, title?: string, date?: string, content?: string): Promise<boolean> {
+async function updatePost(publicKey: string, id: number, version: number, title?: string, date?: string, content?: string): Promise<boolean> {
   if (!title && !date && !content) {
     return false;
   }
@@ -145,6 +149,7 @@ async function updatePost(publicKey: string, id: number, title?: string, date?:
 
     const url = `${serverBaseUrl}/posts/${id}`;
     const body: UpdatePostBody = {
+      version,
       title: encryptedTitle,
       date,
       content: encryptedContent,
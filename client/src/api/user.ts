@@ -3,6 +3,7 @@ import { Http } from 'snowball-js';
 
 import { getI18n } from '../utils/i18n';
 import { serverBaseUrl } from '../constants';
+import { Session } from '../models';
 
 interface CreateUserBody {
   user_public_key: string;
@@ -13,7 +14,6 @@ interface CreateUserBody {
 
 interface UpdateUserBody {
   name?: string;
-  password?: string;
   avatar_url?: string;
 }
 
@@ -24,7 +24,7 @@ interface ResetPasswordBody {
   new_password: string;
 }
 
-async function createUser(user_public_key: string, token_key: string, token_pin: string, recaptcha_token: string): Promise<boolean | null> {
+async function createUser(user_public_key: string, token_key: string, token_pin: string, recaptcha_token: string): Promise<Session | null> {
   const url = `${serverBaseUrl}/users`;
 
   const body: CreateUserBody = {
@@ -35,7 +35,7 @@ async function createUser(user_public_key: string, token_key: string, token_pin:
   };
 
   try {
-    return await Http.post<CreateUserBody, boolean>(url, body);
+    return await Http.post<CreateUserBody, Session>(url, body);
   } catch (e) {
     const i18n = getI18n({
       error: {
@@ -50,11 +50,10 @@ async function createUser(user_public_key: string, token_key: string, token_pin:
   return null;
 }
 
-async function updateUser(userId: string, password?: string, name?: string, avatar?: string): Promise<boolean | null> {
+async function updateUser(userId: string, name?: string, avatar?: string): Promise<boolean | null> {
   const url = `${serverBaseUrl}/users/${userId}`;
 
   const body: UpdateUserBody = {
-    password: password ? SHA3(password, { outputLength: 512 }).toString() : undefined,
     name: name,
     avatar_url: avatar,
   };
@@ -5,6 +5,7 @@ interface Post {
   date: string;
   created_at: string;
   updated_at: string | null;
+  version: number;
 }
 
 export default Post;
@@ -113,7 +113,7 @@ const Post: React.FC<Props> = ({ session }) => {
   const query = new URLSearchParams(useLocation().search);
   const dateFromQuery = query.get('date');
 
-  const initialPost: Post = { id: null, title: '', content: '', date: getFormattedDate(dateFromQuery), updated_at: null, created_at: getFormattedDate() };
+  const initialPost: Post = { id: null, title: '', content: '', date: getFormattedDate(dateFromQuery), updated_at: null, created_at: getFormattedDate(), version: 1 };
   const [post, setPost] = useState<Post>(initialPost);
   const [originalPost, setOriginalPost] = useState<Post | null>(null);
 
@@ -139,10 +139,11 @@ const Post: React.FC<Props> = ({ session }) => {
         const dateWithTime = getFormattedDate(post.date, true);
 
         setSaveStatus(SaveStatus.ONGOING);
-        const result = await api.updatePost(session?.user_public_key || '', post.id, post.title, dateWithTime, post.content);
+        const result = await api.updatePost(session?.user_public_key || '', post.id, post.version, post.title, dateWithTime, post.content);
 
         if (result) {
-          setOriginalPost(post);
+          setPost((currentPost) => ({ ...currentPost, version: post.version + 1 }));
+          setOriginalPost({ ...post, version: post.version + 1 });
           setSaveStatus(SaveStatus.SUCCESS);
         } else {
           setSaveStatus(SaveStatus.FAILURE);
@@ -59,7 +59,7 @@ const ProfileSettings: React.FC<Props> = ({ userId, setSession }) => {
 
   const saveNewName = async () => {
     setNewNameSaveStatus(SaveStatus.ONGOING);
-    const result = await userApi.updateUser(userId, undefined, newName);
+    const result = await userApi.updateUser(userId, newName);
 
     setNewName('');
 
@@ -72,7 +72,7 @@ const ProfileSettings: React.FC<Props> = ({ userId, setSession }) => {
 
   const saveNewAvatar = async () => {
     setNewAvatarSaveStatus(SaveStatus.ONGOING);
-    const result = await userApi.updateUser(userId, undefined, undefined, newAvatar);
+    const result = await userApi.updateUser(userId, undefined, newAvatar);
 
     setNewAvatar('');
 
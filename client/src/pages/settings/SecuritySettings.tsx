@@ -5,7 +5,6 @@ import { Storage } from 'snowball-js';
 import { getI18n } from '../../utils/i18n';
 import { localStoragePrivateKey } from '../../constants';
 import * as authApi from '../../api/auth';
-import * as userApi from '../../api/user';
 import { Button, Section, TextField } from '../../components';
 import { getSaveStatusText, SaveStatus } from '../../utils/status';
 
@@ -49,7 +48,7 @@ const RefreshLink = styled.span`
   }
 `
 
-const SecuritySettings: React.FC<Props> = ({ userId, userEmail }) => {
+const SecuritySettings: React.FC<Props> = ({ userEmail }) => {
   const [newSecretKey, setNewSecretKey] = useState('');
   const [newSecretKeySaveStatus, setNewSecretKeySaveStatus] = useState(SaveStatus.NONE);
   const [newSecretKeyPassword, setNewSecretKeyPassword] = useState('');
@@ -98,19 +97,13 @@ const SecuritySettings: React.FC<Props> = ({ userId, userEmail }) => {
 
   const saveNewPassword = async () => {
     setNewPasswordSaveStatus(SaveStatus.ONGOING);
-    const result = await authApi.login(userEmail, newPasswordPassword);
+    const result = await authApi.changePassword(newPasswordPassword, newPassword);
 
-    if (result) {
-      const updateResult = await userApi.updateUser(userId, newPassword);
-
-      setNewPassword('');
-      setNewPasswordPassword('');
+    setNewPassword('');
+    setNewPasswordPassword('');
 
-      if (updateResult) {
-        setNewPasswordSaveStatus(SaveStatus.SUCCESS);
-      } else {
-        setNewPasswordSaveStatus(SaveStatus.FAILURE);
-      }
+    if (result) {
+      setNewPasswordSaveStatus(SaveStatus.SUCCESS);
     } else {
       setNewPasswordSaveStatus(SaveStatus.FAILURE);
     }
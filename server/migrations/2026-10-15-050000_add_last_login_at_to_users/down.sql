@@ -0,0 +1,2 @@
+ALTER TABLE users DROP COLUMN previous_login_at;
+ALTER TABLE users DROP COLUMN last_login_at;
@@ -0,0 +1,10 @@
+CREATE TABLE post_tags (
+    id BIGINT(20) UNSIGNED AUTO_INCREMENT NOT NULL,
+    post_id BIGINT(20) UNSIGNED NOT NULL,
+    tag VARCHAR(100) NOT NULL,
+    PRIMARY KEY (id),
+    UNIQUE INDEX ux_post_tags_post_id_tag (post_id, tag),
+    INDEX ix_post_tags_tag (tag),
+    CONSTRAINT fk_post_tags_post_id FOREIGN KEY (post_id) REFERENCES posts(id) ON DELETE CASCADE
+) CHARACTER SET 'utf8mb4'
+  COLLATE 'utf8mb4_general_ci';
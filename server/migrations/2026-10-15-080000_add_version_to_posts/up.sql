@@ -0,0 +1 @@
+ALTER TABLE posts ADD COLUMN version BIGINT(20) UNSIGNED NOT NULL DEFAULT 1;
@@ -0,0 +1,3 @@
+ALTER TABLE users DROP COLUMN reminder_sent_on;
+ALTER TABLE users DROP COLUMN reminder_hour;
+ALTER TABLE users DROP COLUMN reminder_enabled;
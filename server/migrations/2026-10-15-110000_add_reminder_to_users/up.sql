@@ -0,0 +1,3 @@
+ALTER TABLE users ADD COLUMN reminder_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN reminder_hour TINYINT UNSIGNED NOT NULL DEFAULT 21;
+ALTER TABLE users ADD COLUMN reminder_sent_on DATE;
@@ -0,0 +1,2 @@
+ALTER TABLE user_keys DROP COLUMN rotated_at;
+ALTER TABLE user_keys DROP COLUMN previous_public_key;
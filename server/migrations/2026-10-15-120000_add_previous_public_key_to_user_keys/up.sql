@@ -0,0 +1,2 @@
+ALTER TABLE user_keys ADD COLUMN previous_public_key VARCHAR(255);
+ALTER TABLE user_keys ADD COLUMN rotated_at DATETIME;
@@ -0,0 +1,13 @@
+CREATE TABLE user_api_keys (
+    id BIGINT(20) UNSIGNED AUTO_INCREMENT NOT NULL,
+    user_id BIGINT(20) UNSIGNED NOT NULL,
+    prefix VARCHAR(16) NOT NULL,
+    hashed_secret VARCHAR(255) NOT NULL,
+    expires_at DATETIME,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id),
+    UNIQUE INDEX ux_user_api_keys_prefix (prefix),
+    INDEX ix_user_api_keys_user_id (user_id),
+    CONSTRAINT fk_user_api_keys_user_id FOREIGN KEY (user_id) REFERENCES users(id)
+) CHARACTER SET 'utf8mb4'
+  COLLATE 'utf8mb4_general_ci';
@@ -0,0 +1 @@
+ALTER TABLE posts ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT FALSE, ADD COLUMN pinned_at DATETIME;
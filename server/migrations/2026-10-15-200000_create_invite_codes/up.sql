@@ -0,0 +1,12 @@
+CREATE TABLE invite_codes (
+    id BIGINT(20) UNSIGNED AUTO_INCREMENT NOT NULL,
+    code VARCHAR(32) NOT NULL,
+    max_uses INT UNSIGNED NOT NULL,
+    remaining_uses INT UNSIGNED NOT NULL,
+    expires_at DATETIME,
+    revoked_at DATETIME,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id),
+    UNIQUE INDEX ux_invite_codes_code (code)
+) CHARACTER SET 'utf8mb4'
+  COLLATE 'utf8mb4_general_ci';
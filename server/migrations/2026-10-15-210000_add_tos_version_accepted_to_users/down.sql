@@ -0,0 +1 @@
+ALTER TABLE users DROP COLUMN tos_version_accepted;
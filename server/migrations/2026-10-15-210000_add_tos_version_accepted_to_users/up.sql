@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN tos_version_accepted VARCHAR(32);
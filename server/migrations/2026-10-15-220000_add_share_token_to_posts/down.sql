@@ -0,0 +1 @@
+ALTER TABLE posts DROP INDEX ux_posts_share_token, DROP COLUMN share_token;
@@ -0,0 +1 @@
+ALTER TABLE posts ADD COLUMN share_token VARCHAR(64), ADD UNIQUE INDEX ux_posts_share_token (share_token);
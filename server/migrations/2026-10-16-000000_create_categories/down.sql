@@ -0,0 +1,4 @@
+ALTER TABLE posts DROP FOREIGN KEY fk_posts_category_id;
+ALTER TABLE posts DROP INDEX ix_posts_category_id;
+ALTER TABLE posts DROP COLUMN category_id;
+DROP TABLE categories;
@@ -0,0 +1,14 @@
+CREATE TABLE categories (
+    id BIGINT(20) UNSIGNED AUTO_INCREMENT NOT NULL,
+    user_id BIGINT(20) UNSIGNED NOT NULL,
+    name VARCHAR(64) NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id),
+    UNIQUE INDEX ux_categories_user_id_name (user_id, name),
+    CONSTRAINT fk_categories_user_id FOREIGN KEY (user_id) REFERENCES users(id)
+) CHARACTER SET 'utf8mb4'
+  COLLATE 'utf8mb4_general_ci';
+
+ALTER TABLE posts ADD COLUMN category_id BIGINT(20) UNSIGNED;
+ALTER TABLE posts ADD INDEX ix_posts_category_id (category_id);
+ALTER TABLE posts ADD CONSTRAINT fk_posts_category_id FOREIGN KEY (category_id) REFERENCES categories(id);
@@ -1,6 +1,13 @@
-use actix_web::{get, App, HttpResponse, HttpServer, Responder};
+use actix_web::dev::{Server, Service, ServiceRequest};
+use actix_web::rt::signal::unix::{signal, SignalKind};
+use actix_web::{get, web, App, HttpResponse, HttpServer, Responder};
+use chrono::Utc;
 use std::collections::HashMap;
 use std::env;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use crate::models::error::ServiceError;
 
 #[macro_use]
 mod macros;
@@ -12,46 +19,119 @@ extern crate diesel;
 pub mod models {
     /// Model related to authentication.
     pub mod auth;
-    /// Model related to Database connection.
+    /// Model related to 2FA backup code.
+    pub mod backup_code;
+    /// Model related to category of posts.
+    pub mod category;
+    /// Model related to Database connection and the connection pool.
     pub mod connection;
     /// Model related to error.
     pub mod error;
+    /// Model related to health of the data stores.
+    pub mod health;
+    /// Model related to invite code.
+    pub mod invite_code;
+    /// Model related to OAuth.
+    pub mod oauth;
+    /// Model related to password history.
+    pub mod password_history;
     /// Model related to post.
     pub mod post;
+    /// Model related to post tag.
+    pub mod post_tag;
+    /// Model related to breached passwords.
+    pub mod pwned_password;
+    /// Model related to rate limiting.
+    pub mod rate_limit;
     /// Model related to user.
     pub mod user;
+    /// Model related to user API key.
+    pub mod user_api_key;
     /// Model related to user key.
     pub mod user_key;
+    /// Model related to webhook.
+    pub mod webhook;
 }
 
 /// A presentation layer that makes API public and passes request/response data to other layers.
 pub mod routes {
     /// API related to authentication.
     pub mod auth;
+    /// API related to category of posts.
+    pub mod category;
+    /// API related to health checks.
+    pub mod health;
+    /// API related to invite code.
+    pub mod invite_code;
+    /// API related to OAuth.
+    pub mod oauth;
     /// API related to post.
     pub mod post;
     /// API related to user.
     pub mod user;
+    /// API related to user key.
+    pub mod user_key;
+    /// API related to webhook.
+    pub mod webhook;
 }
 
 /// A business layer that processes the transaction.
 pub mod services {
     /// Service related to authentication.
     pub mod auth;
+    /// Service related to category of posts.
+    pub mod category;
+    /// Service related to health checks.
+    pub mod health;
+    /// Service related to invite code.
+    pub mod invite_code;
+    /// Service related to OAuth.
+    pub mod oauth;
     /// Service related to post.
     pub mod post;
+    /// Service related to rate limiting.
+    pub mod rate_limit;
+    /// Service related to daily reminder.
+    pub mod reminder;
     /// Service related to user.
     pub mod user;
+    /// Service related to user API key.
+    pub mod user_api_key;
+    /// Service related to user key.
+    pub mod user_key;
+    /// Service related to webhook.
+    pub mod webhook;
 }
 
 /// Reusable functions for multiple modules.
 pub mod utils {
+    /// Utilities related to the configuration read at startup.
+    pub mod config_util;
+    /// Utilities related to devices used to log in.
+    pub mod device_util;
     /// Utilities related to email.
     pub mod email_util;
+    /// Utilities related to environment variables.
+    pub mod env_util;
     /// Utilities related to HTTP.
     pub mod http_util;
+    /// Utilities related to logging.
+    pub mod log_util;
+    /// Utilities related to Prometheus metrics.
+    pub mod metric_util;
     /// Utilities related to password.
     pub mod password_util;
+    /// Fixtures shared by tests of multiple modules.
+    #[cfg(test)]
+    pub mod test_util;
+    /// Utilities related to random tokens.
+    pub mod token_util;
+    /// Utilities related to TOTP.
+    pub mod totp_util;
+    /// Utilities related to distributed tracing.
+    pub mod trace_util;
+    /// Utilities related to webhooks.
+    pub mod webhook_util;
 }
 
 /// A database schema.
@@ -65,24 +145,166 @@ async fn health_check() -> impl Responder {
     HttpResponse::Ok().json(response)
 }
 
+/// Metrics of the database connection pool
+#[get("/metrics/rdb_pool")]
+async fn rdb_pool_metrics() -> impl Responder {
+    HttpResponse::Ok().json(models::connection::get_rdb_pool_metrics())
+}
+
+/// Metrics in the Prometheus text format
+#[get("/metrics")]
+async fn metrics() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(utils::metric_util::render_metrics())
+}
+
+/// Returns an IP address of the client, which is the first address of `X-Forwarded-For` header
+/// if it exists, or the peer address without the port.
+///
+/// API gateway sets the header to the address of its client, so that clients don't share a bucket
+/// of rate limiting. The header is trusted as the server is only reachable through API gateway.
+fn get_client_ip(req: &ServiceRequest) -> String {
+    let connection_info = req.connection_info();
+    let address = connection_info.realip_remote_addr().unwrap_or("unknown");
+    match address.parse::<SocketAddr>() {
+        Ok(socket_address) => socket_address.ip().to_string(),
+        Err(_) => address.to_string(),
+    }
+}
+
+/// Stops the server gracefully on SIGTERM or SIGINT. The server stops accepting new
+/// connections, and waits for in-flight requests up to the shutdown grace period.
+fn stop_on_signal(server: &Server) -> std::io::Result<()> {
+    for kind in &[SignalKind::terminate(), SignalKind::interrupt()] {
+        let mut signal_stream = signal(*kind)?;
+        let server = server.clone();
+        actix_web::rt::spawn(async move {
+            if signal_stream.recv().await.is_some() {
+                println!("Shutting down gracefully");
+                server.stop(true).await;
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Runs the server.
+///
+/// The server shuts down gracefully on SIGTERM or SIGINT. It waits for in-flight requests and
+/// then for queued emails and webhook events, each up to `SHUTDOWN_GRACE_PERIOD_SECONDS`
+/// (30 seconds by default).
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv::dotenv().expect("Failed to read .env file");
 
-    let host = env::var("HOST").expect("HOST not found"); // 0.0.0.0
+    // All settings are validated before binding the server, reporting every invalid one at once.
+    let config = match utils::config_util::Config::from_env_validated() {
+        Ok(config) => config,
+        Err(error) => panic!("{}", error),
+    };
+
     let port = env!("PORT"); // 0000
-    let address = format!("{}:{}", host, port);
+    let address = format!("{}:{}", config.host, port);
 
     println!("Server running at {}", address);
 
-    HttpServer::new(|| {
+    let shutdown_grace_period_seconds = config.shutdown_grace_period_seconds;
+    let max_payload_size = config.max_payload_size;
+    // Tracing is a no-op unless the collector endpoint is configured.
+    let tracer = utils::trace_util::Tracer::with_otlp_exporter(
+        config.otlp_endpoint.clone(),
+        config.trace_user_id_hash_key.clone(),
+    );
+    let shutdown_tracer = tracer.clone();
+
+    println!(
+        "{} disposable email domains are blocked",
+        utils::email_util::load_disposable_domains()
+    );
+
+    services::reminder::start_reminder_job();
+    utils::metric_util::register_metrics();
+
+    let server = HttpServer::new(move || {
         App::new()
+            .app_data(web::JsonConfig::default().limit(max_payload_size))
+            .app_data(web::PayloadConfig::new(max_payload_size))
+            .wrap_fn(|req, srv| {
+                let result = services::rate_limit::RateLimitService::new().check(
+                    req.path(),
+                    &get_client_ip(&req),
+                    Utc::now().naive_utc(),
+                );
+                // Requests are allowed if the rate limiter itself fails or redis is unavailable,
+                // not to make redis a single point of failure of all routes.
+                let response = match result {
+                    Err(error @ ServiceError::TooManyRequests(_)) => {
+                        Err(req.into_response(utils::http_util::get_response::<bool>(Err(error))))
+                    }
+                    _ => Ok(srv.call(req)),
+                };
+                async move {
+                    match response {
+                        Ok(response) => response.await,
+                        Err(response) => Ok(response),
+                    }
+                }
+            })
+            .wrap_fn(|req, srv| {
+                let started_at = Instant::now();
+                let response = srv.call(req);
+                async move {
+                    let response = response.await?;
+                    let request = response.request();
+                    utils::metric_util::observe_request_duration(
+                        request.method().as_str(),
+                        &request
+                            .match_pattern()
+                            .unwrap_or_else(|| String::from("unmatched")),
+                        response.status().as_u16(),
+                        started_at.elapsed().as_secs_f64(),
+                    );
+                    Ok(response)
+                }
+            })
+            .wrap_fn({
+                let tracer = tracer.clone();
+                move |req, srv| utils::trace_util::trace_span(req, srv, tracer.clone())
+            })
+            .wrap_fn(utils::log_util::trace_request)
             .service(health_check)
+            .service(rdb_pool_metrics)
+            .service(metrics)
+            .configure(routes::health::init_routes)
             .configure(routes::post::init_routes)
             .configure(routes::user::init_routes)
+            .configure(routes::user_key::init_routes)
             .configure(routes::auth::init_routes)
+            .configure(routes::oauth::init_routes)
+            .configure(routes::invite_code::init_routes)
+            .configure(routes::webhook::init_routes)
+            .configure(routes::category::init_routes)
     })
+    .disable_signals()
+    .shutdown_timeout(shutdown_grace_period_seconds)
     .bind(address)?
-    .run()
-    .await
+    .run();
+
+    stop_on_signal(&server)?;
+    server.await?;
+
+    if !utils::email_util::shutdown_email_worker(Duration::from_secs(shutdown_grace_period_seconds))
+    {
+        println!("Some queued emails are not sent within the grace period");
+    }
+    if !utils::webhook_util::shutdown_webhook_worker(Duration::from_secs(
+        shutdown_grace_period_seconds,
+    )) {
+        println!("Some queued webhook events are not delivered within the grace period");
+    }
+    if let Some(tracer) = shutdown_tracer {
+        tracer.export();
+    }
+    Ok(())
 }
@@ -1,10 +1,13 @@
+use chrono::{Duration, NaiveDateTime};
 use mockall::automock;
-use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use redis::{Commands, RedisError};
 use serde::{Deserialize, Serialize};
+use std::cmp;
+use std::collections::HashMap;
 
 use crate::models::connection;
 use crate::models::error::{get_service_error, ServiceError};
+use crate::utils::{env_util, token_util, trace_util};
 
 /// Session containing information of the logged-in user.
 #[derive(Serialize, Deserialize)]
@@ -14,6 +17,60 @@ pub struct UserSession {
     pub user_name: String,
     pub user_public_key: String,
     pub user_avatar_url: Option<String>,
+    pub user_session_epoch: u64,
+    pub user_session_id: String,
+    pub user_last_login_at: Option<NaiveDateTime>,
+    pub user_previous_login_at: Option<NaiveDateTime>,
+    /// A version of the terms of service the user has accepted last.
+    pub user_tos_version_accepted: Option<String>,
+    pub user_session_started_at: NaiveDateTime,
+    pub user_session_expires_at: NaiveDateTime,
+}
+
+/// An active session of the user, which is listed so that the user can see where the user is
+/// logged in, and revoke it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActiveSession {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Lifetime of user session. The session expires after the idle timeout unless it is refreshed,
+/// and each refresh pushes the expiry forward but never beyond the maximum lifetime from the login.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SessionLifetime {
+    pub idle_timeout: Duration,
+    pub max_lifetime: Duration,
+}
+
+impl SessionLifetime {
+    /// Reads the lifetime from `SESSION_IDLE_TIMEOUT_SECONDS` and `SESSION_MAX_LIFETIME_SECONDS`,
+    /// which are 7 days and 30 days by default.
+    pub fn from_env() -> Self {
+        Self {
+            idle_timeout: Duration::seconds(env_util::get_env_var_or(
+                "SESSION_IDLE_TIMEOUT_SECONDS",
+                7 * 86400,
+            )),
+            max_lifetime: Duration::seconds(env_util::get_env_var_or(
+                "SESSION_MAX_LIFETIME_SECONDS",
+                30 * 86400,
+            )),
+        }
+    }
+
+    /// Returns when the session started at `started_at` expires, if it is refreshed at `now`.
+    pub fn get_expiry(&self, started_at: &NaiveDateTime, now: &NaiveDateTime) -> NaiveDateTime {
+        cmp::min(*now + self.idle_timeout, *started_at + self.max_lifetime)
+    }
+}
+
+/// TOTP enrollment containing information to register the secret to authenticator apps.
+#[derive(Serialize, Deserialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub provisioning_uri: String,
 }
 
 /// Sign up token that represents data in redis.
@@ -26,6 +83,8 @@ pub struct SignUpToken {
     pub email: String,
     pub password: String,
     pub avatar_url: Option<String>,
+    #[serde(default)]
+    pub locale: Option<String>,
 }
 
 /// A core data repository for token.
@@ -36,8 +95,17 @@ pub struct SignUpTokenRepository {
 #[automock]
 pub trait SignUpTokenRepositoryTrait {
     fn find(&mut self, key: &str) -> Result<String, ServiceError>;
+    fn find_key_by_email(&mut self, email: &str) -> Result<String, ServiceError>;
     fn delete(&mut self, key: &str) -> Result<bool, ServiceError>;
-    fn save(&mut self, serialized_token: &str) -> Result<bool, ServiceError>;
+    fn save(
+        &mut self,
+        serialized_token: &str,
+        email: &str,
+        ttl_seconds: usize,
+    ) -> Result<String, ServiceError>;
+    fn refresh(&mut self, key: &str, email: &str, ttl_seconds: usize)
+        -> Result<bool, ServiceError>;
+    fn lock_resend(&mut self, email: &str, ttl_seconds: usize) -> Result<bool, ServiceError>;
 }
 
 impl SignUpTokenRepository {
@@ -49,9 +117,21 @@ impl SignUpTokenRepository {
     }
 
     /// Finds a token by key.
+    /// An expired token is treated as missing.
     pub fn find(&mut self, key: &str) -> Result<String, ServiceError> {
-        match self.client.get::<&str, String>(key) {
-            Ok(token) => Ok(token),
+        match self.client.get::<&str, Option<String>>(key) {
+            Ok(Some(token)) => Ok(token),
+            Ok(None) => Err(get_service_error(ServiceError::NotFound(key.to_string()))),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Finds a key of the pending token by email.
+    pub fn find_key_by_email(&mut self, email: &str) -> Result<String, ServiceError> {
+        let email_key = format!("sign_up_token_key:{}", email);
+        match self.client.get::<&str, Option<String>>(&email_key) {
+            Ok(Some(key)) => Ok(key),
+            Ok(None) => Err(get_service_error(ServiceError::NotFound(email_key))),
             Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
         }
     }
@@ -64,21 +144,108 @@ impl SignUpTokenRepository {
         }
     }
 
-    /// Creates a new token and returns key.
-    pub fn save(&mut self, serialized_token: &str) -> Result<String, ServiceError> {
-        let key: String = thread_rng().sample_iter(&Alphanumeric).take(32).collect();
-        let ttl_seconds = 180; // 3 min
+    /// Creates a new token that expires after `ttl_seconds`, and returns key.
+    /// The key is also indexed by email to find the pending token of the email.
+    pub fn save(
+        &mut self,
+        serialized_token: &str,
+        email: &str,
+        ttl_seconds: usize,
+    ) -> Result<String, ServiceError> {
+        let key = token_util::get_random_string(32);
+        let email_key = format!("sign_up_token_key:{}", email);
 
-        let result: Result<bool, RedisError> =
-            self.client.set::<&str, &str, _>(&key, &serialized_token);
+        let result: Result<(), RedisError> = redis::pipe()
+            .atomic()
+            .set_ex(&key, serialized_token, ttl_seconds)
+            .set_ex(&email_key, &key, ttl_seconds)
+            .query(&mut self.client);
         match result {
-            Ok(_) => match self.client.expire::<&str, bool>(&key, ttl_seconds) {
-                Ok(_) => Ok(key),
-                Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
-            },
+            Ok(_) => Ok(key),
             Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
         }
     }
+
+    /// Refreshes the TTL of the token and its email index.
+    pub fn refresh(
+        &mut self,
+        key: &str,
+        email: &str,
+        ttl_seconds: usize,
+    ) -> Result<bool, ServiceError> {
+        let email_key = format!("sign_up_token_key:{}", email);
+
+        let result: Result<(bool, bool), RedisError> = redis::pipe()
+            .atomic()
+            .expire(key, ttl_seconds)
+            .expire(&email_key, ttl_seconds)
+            .query(&mut self.client);
+        match result {
+            Ok((true, _)) => Ok(true),
+            Ok((false, _)) => Err(get_service_error(ServiceError::NotFound(key.to_string()))),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Marks that the token of the email has been resent for `ttl_seconds`.
+    /// It returns `false` if it has already been marked.
+    pub fn lock_resend(&mut self, email: &str, ttl_seconds: usize) -> Result<bool, ServiceError> {
+        let resend_key = format!("sign_up_token_resend:{}", email);
+
+        let result: Result<Option<String>, RedisError> = redis::cmd("SET")
+            .arg(&resend_key)
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_seconds)
+            .query(&mut self.client);
+        match result {
+            Ok(result) => Ok(result.is_some()),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl SignUpTokenRepositoryTrait for SignUpTokenRepository {
+    fn find(&mut self, key: &str) -> Result<String, ServiceError> {
+        let _span = trace_util::start_span("SignUpTokenRepository::find", None);
+        SignUpTokenRepository::find(self, key)
+    }
+
+    fn find_key_by_email(&mut self, email: &str) -> Result<String, ServiceError> {
+        let _span = trace_util::start_span("SignUpTokenRepository::find_key_by_email", None);
+        SignUpTokenRepository::find_key_by_email(self, email)
+    }
+
+    fn delete(&mut self, key: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("SignUpTokenRepository::delete", None);
+        SignUpTokenRepository::delete(self, key)
+    }
+
+    fn save(
+        &mut self,
+        serialized_token: &str,
+        email: &str,
+        ttl_seconds: usize,
+    ) -> Result<String, ServiceError> {
+        let _span = trace_util::start_span("SignUpTokenRepository::save", None);
+        SignUpTokenRepository::save(self, serialized_token, email, ttl_seconds)
+    }
+
+    fn refresh(
+        &mut self,
+        key: &str,
+        email: &str,
+        ttl_seconds: usize,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("SignUpTokenRepository::refresh", None);
+        SignUpTokenRepository::refresh(self, key, email, ttl_seconds)
+    }
+
+    fn lock_resend(&mut self, email: &str, ttl_seconds: usize) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("SignUpTokenRepository::lock_resend", None);
+        SignUpTokenRepository::lock_resend(self, email, ttl_seconds)
+    }
 }
 
 impl Default for SignUpTokenRepository {
@@ -106,7 +273,7 @@ pub trait PasswordTokenRepositoryTrait {
     fn new(user_id: u64) -> Self;
     fn find(&mut self) -> Result<String, ServiceError>;
     fn delete(&mut self) -> Result<bool, ServiceError>;
-    fn save(&mut self, serialized_token: &str) -> Result<bool, ServiceError>;
+    fn save(&mut self, serialized_token: &str, ttl_seconds: usize) -> Result<bool, ServiceError>;
 }
 
 impl PasswordTokenRepository {
@@ -119,17 +286,21 @@ impl PasswordTokenRepository {
     }
 
     /// Finds a token by key.
+    /// An expired token is treated as missing.
     pub fn find(&mut self) -> Result<String, ServiceError> {
-        match self.client.get::<&str, String>(&self.key) {
-            Ok(token) => Ok(token),
+        match self.client.get::<&str, Option<String>>(&self.key) {
+            Ok(Some(token)) => Ok(token),
+            Ok(None) => Err(get_service_error(ServiceError::NotFound(self.key.clone()))),
             Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
         }
     }
 
-    /// Creates a new token.
-    pub fn save(&mut self, serialized_token: &str) -> Result<bool, ServiceError> {
-        let ttl_seconds = 180; // 3 min
-
+    /// Creates a new token that expires after `ttl_seconds`.
+    pub fn save(
+        &mut self,
+        serialized_token: &str,
+        ttl_seconds: usize,
+    ) -> Result<bool, ServiceError> {
         let result: Result<bool, RedisError> = self
             .client
             .set::<&str, &str, _>(&self.key, &serialized_token);
@@ -150,3 +321,581 @@ impl PasswordTokenRepository {
         }
     }
 }
+
+impl PasswordTokenRepositoryTrait for PasswordTokenRepository {
+    fn new(user_id: u64) -> Self {
+        PasswordTokenRepository::new(user_id)
+    }
+
+    fn find(&mut self) -> Result<String, ServiceError> {
+        let _span = trace_util::start_span("PasswordTokenRepository::find", None);
+        PasswordTokenRepository::find(self)
+    }
+
+    fn delete(&mut self) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("PasswordTokenRepository::delete", None);
+        PasswordTokenRepository::delete(self)
+    }
+
+    fn save(&mut self, serialized_token: &str, ttl_seconds: usize) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("PasswordTokenRepository::save", None);
+        PasswordTokenRepository::save(self, serialized_token, ttl_seconds)
+    }
+}
+
+/// A core data repository for known devices.
+/// It has fingerprints of the devices which the user has logged in with.
+pub struct KnownDeviceRepository {
+    key: String,
+    client: redis::Connection,
+}
+
+#[automock]
+pub trait KnownDeviceRepositoryTrait {
+    fn new(user_id: u64) -> Self;
+    fn add(&mut self, fingerprint: &str) -> Result<bool, ServiceError>;
+    fn delete(&mut self) -> Result<bool, ServiceError>;
+}
+
+impl KnownDeviceRepository {
+    /// Creates a new known device repository.
+    pub fn new(user_id: u64) -> Self {
+        Self {
+            key: format!("known_devices:{}", user_id),
+            client: connection::connect_redis(),
+        }
+    }
+
+    /// Adds the fingerprint of the device, and returns `false` if it is already known.
+    pub fn add(&mut self, fingerprint: &str) -> Result<bool, ServiceError> {
+        match self.client.sadd::<&str, &str, u64>(&self.key, fingerprint) {
+            Ok(count) => Ok(count > 0),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Deletes all known devices of the user.
+    pub fn delete(&mut self) -> Result<bool, ServiceError> {
+        match self.client.del::<&str, _>(&self.key) {
+            Ok(result) => Ok(result),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl KnownDeviceRepositoryTrait for KnownDeviceRepository {
+    fn new(user_id: u64) -> Self {
+        KnownDeviceRepository::new(user_id)
+    }
+
+    fn add(&mut self, fingerprint: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("KnownDeviceRepository::add", None);
+        KnownDeviceRepository::add(self, fingerprint)
+    }
+
+    fn delete(&mut self) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("KnownDeviceRepository::delete", None);
+        KnownDeviceRepository::delete(self)
+    }
+}
+
+/// A core data repository for magic link token.
+/// The token is a part of the login link, and it has the id of the user to log in.
+pub struct MagicLinkTokenRepository {
+    key: String,
+    client: redis::Connection,
+}
+
+#[automock]
+pub trait MagicLinkTokenRepositoryTrait {
+    fn new(token: &str) -> Self;
+    fn find(&mut self) -> Result<u64, ServiceError>;
+    fn save(&mut self, user_id: u64, ttl_seconds: usize) -> Result<bool, ServiceError>;
+    fn delete(&mut self) -> Result<bool, ServiceError>;
+}
+
+impl MagicLinkTokenRepository {
+    /// Creates a new magic link token repository.
+    pub fn new(token: &str) -> Self {
+        Self {
+            key: format!("magic_link_token:{}", token),
+            client: connection::connect_redis(),
+        }
+    }
+
+    /// Finds the id of the user by the token.
+    /// An expired token is treated as missing.
+    pub fn find(&mut self) -> Result<u64, ServiceError> {
+        match self.client.get::<&str, Option<u64>>(&self.key) {
+            Ok(Some(user_id)) => Ok(user_id),
+            Ok(None) => Err(get_service_error(ServiceError::NotFound(self.key.clone()))),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Saves the token with the id of the user. It expires after `ttl_seconds`.
+    pub fn save(&mut self, user_id: u64, ttl_seconds: usize) -> Result<bool, ServiceError> {
+        let result: Result<(), RedisError> = self.client.set_ex(&self.key, user_id, ttl_seconds);
+        match result {
+            Ok(_) => Ok(true),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Deletes the token, and returns `false` if it has already been deleted.
+    pub fn delete(&mut self) -> Result<bool, ServiceError> {
+        match self.client.del::<&str, _>(&self.key) {
+            Ok(result) => Ok(result),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl MagicLinkTokenRepositoryTrait for MagicLinkTokenRepository {
+    fn new(token: &str) -> Self {
+        MagicLinkTokenRepository::new(token)
+    }
+
+    fn find(&mut self) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("MagicLinkTokenRepository::find", None);
+        MagicLinkTokenRepository::find(self)
+    }
+
+    fn save(&mut self, user_id: u64, ttl_seconds: usize) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("MagicLinkTokenRepository::save", None);
+        MagicLinkTokenRepository::save(self, user_id, ttl_seconds)
+    }
+
+    fn delete(&mut self) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("MagicLinkTokenRepository::delete", None);
+        MagicLinkTokenRepository::delete(self)
+    }
+}
+
+/// Email change token that represents data in redis.
+/// The token has the id of the user and the new email which is not confirmed yet.
+#[derive(Serialize, Deserialize)]
+pub struct EmailChangeToken {
+    pub user_id: u64,
+    pub email: String,
+}
+
+/// A core data repository for email change token.
+/// The token is a part of the confirmation link sent to the new email.
+pub struct EmailChangeTokenRepository {
+    key: String,
+    client: redis::Connection,
+}
+
+#[automock]
+pub trait EmailChangeTokenRepositoryTrait {
+    fn new(token: &str) -> Self;
+    fn find(&mut self) -> Result<String, ServiceError>;
+    fn save(&mut self, serialized_token: &str, ttl_seconds: usize) -> Result<bool, ServiceError>;
+    fn delete(&mut self) -> Result<bool, ServiceError>;
+}
+
+impl EmailChangeTokenRepository {
+    /// Creates a new email change token repository.
+    pub fn new(token: &str) -> Self {
+        Self {
+            key: format!("email_change_token:{}", token),
+            client: connection::connect_redis(),
+        }
+    }
+
+    /// Finds the serialized pending email change by the token.
+    /// An expired token is treated as missing.
+    pub fn find(&mut self) -> Result<String, ServiceError> {
+        match self.client.get::<&str, Option<String>>(&self.key) {
+            Ok(Some(token)) => Ok(token),
+            Ok(None) => Err(get_service_error(ServiceError::NotFound(self.key.clone()))),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Saves the pending email change with the token. It expires after `ttl_seconds`.
+    pub fn save(
+        &mut self,
+        serialized_token: &str,
+        ttl_seconds: usize,
+    ) -> Result<bool, ServiceError> {
+        let result: Result<(), RedisError> =
+            self.client.set_ex(&self.key, serialized_token, ttl_seconds);
+        match result {
+            Ok(_) => Ok(true),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Deletes the token, and returns `false` if it has already been deleted.
+    pub fn delete(&mut self) -> Result<bool, ServiceError> {
+        match self.client.del::<&str, _>(&self.key) {
+            Ok(result) => Ok(result),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl EmailChangeTokenRepositoryTrait for EmailChangeTokenRepository {
+    fn new(token: &str) -> Self {
+        EmailChangeTokenRepository::new(token)
+    }
+
+    fn find(&mut self) -> Result<String, ServiceError> {
+        let _span = trace_util::start_span("EmailChangeTokenRepository::find", None);
+        EmailChangeTokenRepository::find(self)
+    }
+
+    fn save(&mut self, serialized_token: &str, ttl_seconds: usize) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("EmailChangeTokenRepository::save", None);
+        EmailChangeTokenRepository::save(self, serialized_token, ttl_seconds)
+    }
+
+    fn delete(&mut self) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("EmailChangeTokenRepository::delete", None);
+        EmailChangeTokenRepository::delete(self)
+    }
+}
+
+/// A core data repository for account deletion token.
+/// The token is sent to the email of the user to confirm the deletion of the account.
+pub struct AccountDeletionTokenRepository {
+    key: String,
+    client: redis::Connection,
+}
+
+#[automock]
+pub trait AccountDeletionTokenRepositoryTrait {
+    fn new(user_id: u64) -> Self;
+    fn find(&mut self) -> Result<String, ServiceError>;
+    fn save(&mut self, token: &str, ttl_seconds: usize) -> Result<bool, ServiceError>;
+    fn delete(&mut self) -> Result<bool, ServiceError>;
+}
+
+impl AccountDeletionTokenRepository {
+    /// Creates a new account deletion token repository.
+    pub fn new(user_id: u64) -> Self {
+        Self {
+            key: format!("account_deletion_token:{}", user_id),
+            client: connection::connect_redis(),
+        }
+    }
+
+    /// Finds the token of the user.
+    /// An expired token is treated as missing.
+    pub fn find(&mut self) -> Result<String, ServiceError> {
+        match self.client.get::<&str, Option<String>>(&self.key) {
+            Ok(Some(token)) => Ok(token),
+            Ok(None) => Err(get_service_error(ServiceError::NotFound(self.key.clone()))),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Saves the token of the user, replacing the previous one. It expires after `ttl_seconds`.
+    pub fn save(&mut self, token: &str, ttl_seconds: usize) -> Result<bool, ServiceError> {
+        let result: Result<(), RedisError> = self.client.set_ex(&self.key, token, ttl_seconds);
+        match result {
+            Ok(_) => Ok(true),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Deletes the token, and returns `false` if it has already been deleted.
+    pub fn delete(&mut self) -> Result<bool, ServiceError> {
+        match self.client.del::<&str, _>(&self.key) {
+            Ok(result) => Ok(result),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl AccountDeletionTokenRepositoryTrait for AccountDeletionTokenRepository {
+    fn new(user_id: u64) -> Self {
+        AccountDeletionTokenRepository::new(user_id)
+    }
+
+    fn find(&mut self) -> Result<String, ServiceError> {
+        let _span = trace_util::start_span("AccountDeletionTokenRepository::find", None);
+        AccountDeletionTokenRepository::find(self)
+    }
+
+    fn save(&mut self, token: &str, ttl_seconds: usize) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("AccountDeletionTokenRepository::save", None);
+        AccountDeletionTokenRepository::save(self, token, ttl_seconds)
+    }
+
+    fn delete(&mut self) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("AccountDeletionTokenRepository::delete", None);
+        AccountDeletionTokenRepository::delete(self)
+    }
+}
+
+/// A core data repository for login attempt.
+/// It counts failed login attempts of the user specified by email.
+pub struct LoginAttemptRepository {
+    key: String,
+    client: redis::Connection,
+}
+
+#[automock]
+pub trait LoginAttemptRepositoryTrait {
+    fn new(email: &str) -> Self;
+    fn find(&mut self) -> Result<u64, ServiceError>;
+    fn increase(&mut self, ttl_seconds: usize) -> Result<u64, ServiceError>;
+    fn delete(&mut self) -> Result<bool, ServiceError>;
+}
+
+impl LoginAttemptRepository {
+    /// Creates a new login attempt repository.
+    pub fn new(email: &str) -> Self {
+        Self {
+            key: format!("login_attempt:{}", email),
+            client: connection::connect_redis(),
+        }
+    }
+
+    /// Finds the number of failed login attempts.
+    pub fn find(&mut self) -> Result<u64, ServiceError> {
+        match self.client.get::<&str, Option<u64>>(&self.key) {
+            Ok(count) => Ok(count.unwrap_or(0)),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Increases the number of failed login attempts and returns it.
+    /// The window starts at the first failure, and the count expires after `ttl_seconds`.
+    pub fn increase(&mut self, ttl_seconds: usize) -> Result<u64, ServiceError> {
+        let result: Result<u64, RedisError> = self.client.incr::<&str, u64, _>(&self.key, 1);
+        match result {
+            Ok(count) => {
+                if count > 1 {
+                    return Ok(count);
+                }
+
+                match self.client.expire::<&str, bool>(&self.key, ttl_seconds) {
+                    Ok(_) => Ok(count),
+                    Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+                }
+            }
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Deletes the number of failed login attempts.
+    pub fn delete(&mut self) -> Result<bool, ServiceError> {
+        match self.client.del::<&str, _>(&self.key) {
+            Ok(result) => Ok(result),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl LoginAttemptRepositoryTrait for LoginAttemptRepository {
+    fn new(email: &str) -> Self {
+        LoginAttemptRepository::new(email)
+    }
+
+    fn find(&mut self) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("LoginAttemptRepository::find", None);
+        LoginAttemptRepository::find(self)
+    }
+
+    fn increase(&mut self, ttl_seconds: usize) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("LoginAttemptRepository::increase", None);
+        LoginAttemptRepository::increase(self, ttl_seconds)
+    }
+
+    fn delete(&mut self) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("LoginAttemptRepository::delete", None);
+        LoginAttemptRepository::delete(self)
+    }
+}
+
+/// A core data repository for session epoch.
+/// Sessions issued with an epoch older than the current epoch of the user are no longer valid.
+pub struct SessionEpochRepository {
+    key: String,
+    client: redis::Connection,
+}
+
+#[automock]
+pub trait SessionEpochRepositoryTrait {
+    fn new(user_id: u64) -> Self;
+    fn find(&mut self) -> Result<u64, ServiceError>;
+    fn increase(&mut self) -> Result<u64, ServiceError>;
+}
+
+impl SessionEpochRepository {
+    /// Creates a new session epoch repository.
+    pub fn new(user_id: u64) -> Self {
+        Self {
+            key: format!("session_epoch:{}", user_id),
+            client: connection::connect_redis(),
+        }
+    }
+
+    /// Finds the current session epoch.
+    pub fn find(&mut self) -> Result<u64, ServiceError> {
+        match self.client.get::<&str, Option<u64>>(&self.key) {
+            Ok(epoch) => Ok(epoch.unwrap_or(0)),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Increases the session epoch and returns it.
+    pub fn increase(&mut self) -> Result<u64, ServiceError> {
+        match self.client.incr::<&str, u64, u64>(&self.key, 1) {
+            Ok(epoch) => Ok(epoch),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl SessionEpochRepositoryTrait for SessionEpochRepository {
+    fn new(user_id: u64) -> Self {
+        SessionEpochRepository::new(user_id)
+    }
+
+    fn find(&mut self) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("SessionEpochRepository::find", None);
+        SessionEpochRepository::find(self)
+    }
+
+    fn increase(&mut self) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("SessionEpochRepository::increase", None);
+        SessionEpochRepository::increase(self)
+    }
+}
+
+/// A core data repository for active sessions.
+/// It has the active sessions of the user by id, and a session not in it is no longer valid.
+pub struct ActiveSessionRepository {
+    key: String,
+    client: redis::Connection,
+}
+
+#[automock]
+pub trait ActiveSessionRepositoryTrait {
+    fn new(user_id: u64) -> Self;
+    fn find_all(&mut self) -> Result<Vec<ActiveSession>, ServiceError>;
+    fn exists(&mut self, id: &str) -> Result<bool, ServiceError>;
+    fn save(
+        &mut self,
+        active_session: &ActiveSession,
+        ttl_seconds: usize,
+    ) -> Result<bool, ServiceError>;
+    fn delete(&mut self, id: &str) -> Result<bool, ServiceError>;
+    fn delete_all(&mut self) -> Result<bool, ServiceError>;
+}
+
+impl ActiveSessionRepository {
+    /// Creates a new active session repository.
+    pub fn new(user_id: u64) -> Self {
+        Self {
+            key: format!("active_sessions:{}", user_id),
+            client: connection::connect_redis(),
+        }
+    }
+
+    /// Finds all active sessions of the user.
+    pub fn find_all(&mut self) -> Result<Vec<ActiveSession>, ServiceError> {
+        match self
+            .client
+            .hgetall::<&str, HashMap<String, String>>(&self.key)
+        {
+            Ok(serialized_sessions) => Ok(serialized_sessions
+                .values()
+                .filter_map(|serialized_session| serde_json::from_str(serialized_session).ok())
+                .collect()),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Returns whether the session is active.
+    pub fn exists(&mut self, id: &str) -> Result<bool, ServiceError> {
+        match self.client.hexists::<&str, &str, bool>(&self.key, id) {
+            Ok(exists) => Ok(exists),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Saves the active session.
+    /// All active sessions of the user expire after the TTL since the latest one is saved.
+    pub fn save(
+        &mut self,
+        active_session: &ActiveSession,
+        ttl_seconds: usize,
+    ) -> Result<bool, ServiceError> {
+        let serialized_session =
+            if let Ok(serialized_session) = serde_json::to_string(active_session) {
+                serialized_session
+            } else {
+                return Err(get_service_error(ServiceError::InvalidFormat));
+            };
+
+        let result: Result<(), RedisError> = redis::pipe()
+            .atomic()
+            .hset(&self.key, &active_session.id, serialized_session)
+            .ignore()
+            .expire(&self.key, ttl_seconds)
+            .ignore()
+            .query(&mut self.client);
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Deletes the active session, and returns `false` if it is not active.
+    pub fn delete(&mut self, id: &str) -> Result<bool, ServiceError> {
+        match self.client.hdel::<&str, &str, u64>(&self.key, id) {
+            Ok(count) => Ok(count > 0),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Deletes all active sessions of the user.
+    pub fn delete_all(&mut self) -> Result<bool, ServiceError> {
+        match self.client.del::<&str, _>(&self.key) {
+            Ok(result) => Ok(result),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl ActiveSessionRepositoryTrait for ActiveSessionRepository {
+    fn new(user_id: u64) -> Self {
+        ActiveSessionRepository::new(user_id)
+    }
+
+    fn find_all(&mut self) -> Result<Vec<ActiveSession>, ServiceError> {
+        let _span = trace_util::start_span("ActiveSessionRepository::find_all", None);
+        ActiveSessionRepository::find_all(self)
+    }
+
+    fn exists(&mut self, id: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("ActiveSessionRepository::exists", None);
+        ActiveSessionRepository::exists(self, id)
+    }
+
+    fn save(
+        &mut self,
+        active_session: &ActiveSession,
+        ttl_seconds: usize,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("ActiveSessionRepository::save", None);
+        ActiveSessionRepository::save(self, active_session, ttl_seconds)
+    }
+
+    fn delete(&mut self, id: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("ActiveSessionRepository::delete", None);
+        ActiveSessionRepository::delete(self, id)
+    }
+
+    fn delete_all(&mut self) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("ActiveSessionRepository::delete_all", None);
+        ActiveSessionRepository::delete_all(self)
+    }
+}
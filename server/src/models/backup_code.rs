@@ -0,0 +1,143 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::Error;
+use mockall::automock;
+use serde::{Deserialize, Serialize};
+
+use crate::models::connection;
+use crate::models::error::{get_service_error, ServiceError};
+use crate::schema::{backup_codes, backup_codes::dsl};
+use crate::utils::trace_util;
+
+/// Backup code representing `backup_codes` table.
+/// It can be used once in place of a TOTP code, and only its hash is stored.
+#[derive(Debug, Serialize, Deserialize, Queryable)]
+pub struct BackupCode {
+    pub id: u64,
+    pub user_id: u64,
+    pub hashed_code: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Backup code DAO using between models layer and RDB.
+#[derive(Insertable)]
+#[table_name = "backup_codes"]
+struct BackupCodeDAO {
+    user_id: u64,
+    hashed_code: String,
+}
+
+/// A core data repository for backup code.
+pub struct BackupCodeRepository {
+    conn: connection::RdbConnection,
+}
+
+#[automock]
+pub trait BackupCodeRepositoryTrait {
+    fn find_all_by_user_id(&self, user_id: u64) -> Result<Vec<BackupCode>, ServiceError>;
+    fn create(&self, user_id: u64, hashed_code: &str) -> Result<bool, ServiceError>;
+    fn delete(&self, id: u64) -> Result<bool, ServiceError>;
+    fn delete_all_by_user_id(&self, user_id: u64) -> Result<usize, ServiceError>;
+}
+
+impl BackupCodeRepository {
+    /// Creates a new backup code repository.
+    pub fn new() -> Self {
+        Self {
+            conn: connection::connect_rdb(),
+        }
+    }
+
+    /// Finds all backup codes of the user.
+    pub fn find_all_by_user_id(&self, user_id: u64) -> Result<Vec<BackupCode>, ServiceError> {
+        let backup_code_list: Result<Vec<BackupCode>, Error> = dsl::backup_codes
+            .filter(dsl::user_id.eq(user_id))
+            .load::<BackupCode>(&self.conn);
+
+        match backup_code_list {
+            Ok(backup_code_list) => Ok(backup_code_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Creates a new backup code.
+    pub fn create(&self, user_id: u64, hashed_code: &str) -> Result<bool, ServiceError> {
+        let backup_code_to_create = BackupCodeDAO {
+            user_id,
+            hashed_code: hashed_code.to_string(),
+        };
+
+        let count = diesel::insert_into(dsl::backup_codes)
+            .values(backup_code_to_create)
+            .execute(&self.conn);
+
+        if let Ok(count) = count {
+            if count > 0 {
+                Ok(true)
+            } else {
+                Err(get_service_error(ServiceError::QueryExecutionFailure))
+            }
+        } else {
+            Err(get_service_error(ServiceError::QueryExecutionFailure))
+        }
+    }
+
+    /// Deletes a backup code.
+    pub fn delete(&self, id: u64) -> Result<bool, ServiceError> {
+        let target_backup_code = dsl::backup_codes.find(id);
+        let count = diesel::delete(target_backup_code).execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::QueryExecutionFailure))
+                }
+            }
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(id.to_string()))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
+    /// Deletes all backup codes of the user, and returns the number of deleted codes.
+    pub fn delete_all_by_user_id(&self, user_id: u64) -> Result<usize, ServiceError> {
+        let target_backup_codes = dsl::backup_codes.filter(dsl::user_id.eq(user_id));
+        let count = diesel::delete(target_backup_codes).execute(&self.conn);
+
+        match count {
+            Ok(count) => Ok(count),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl BackupCodeRepositoryTrait for BackupCodeRepository {
+    fn find_all_by_user_id(&self, user_id: u64) -> Result<Vec<BackupCode>, ServiceError> {
+        let _span = trace_util::start_span("BackupCodeRepository::find_all_by_user_id", None);
+        BackupCodeRepository::find_all_by_user_id(self, user_id)
+    }
+
+    fn create(&self, user_id: u64, hashed_code: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("BackupCodeRepository::create", None);
+        BackupCodeRepository::create(self, user_id, hashed_code)
+    }
+
+    fn delete(&self, id: u64) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("BackupCodeRepository::delete", None);
+        BackupCodeRepository::delete(self, id)
+    }
+
+    fn delete_all_by_user_id(&self, user_id: u64) -> Result<usize, ServiceError> {
+        let _span = trace_util::start_span("BackupCodeRepository::delete_all_by_user_id", None);
+        BackupCodeRepository::delete_all_by_user_id(self, user_id)
+    }
+}
+
+impl Default for BackupCodeRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
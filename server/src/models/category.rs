@@ -0,0 +1,229 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::Error;
+use mockall::automock;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::models::connection;
+use crate::models::error::{get_service_error, ServiceError};
+use crate::schema::{categories, posts};
+use crate::utils::trace_util;
+
+/// Category representing `categories` table.
+/// It is a notebook of the user grouping posts, and a post belongs to at most one category.
+#[derive(Clone, Debug, Serialize, Deserialize, Queryable)]
+pub struct Category {
+    pub id: u64,
+    pub user_id: u64,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Category DAO using between models layer and RDB.
+#[derive(Insertable)]
+#[table_name = "categories"]
+struct CategoryDAO {
+    user_id: u64,
+    name: String,
+}
+
+/// Category DTO using between routes layer and service layer.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryDTO {
+    pub id: u64,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// A way to delete a category having posts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CategoryDeletionPolicy {
+    /// The category is not deleted while it has posts.
+    Restrict,
+    /// The posts are removed from the category, and the category is deleted.
+    Nullify,
+}
+
+impl FromStr for CategoryDeletionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "restrict" => Ok(CategoryDeletionPolicy::Restrict),
+            "nullify" => Ok(CategoryDeletionPolicy::Nullify),
+            _ => Err(String::from("it must be `restrict` or `nullify`")),
+        }
+    }
+}
+
+/// A core data repository for category.
+pub struct CategoryRepository {
+    conn: connection::RdbConnection,
+}
+
+#[automock]
+pub trait CategoryRepositoryTrait {
+    fn find(&self, id: u64, user_id: u64) -> Result<Category, ServiceError>;
+    fn find_all_by_user_id(&self, user_id: u64) -> Result<Vec<Category>, ServiceError>;
+    fn count_posts(&self, id: u64) -> Result<u64, ServiceError>;
+    fn create(&self, user_id: u64, name: &str) -> Result<u64, ServiceError>;
+    fn update(&self, id: u64, user_id: u64, name: &str) -> Result<bool, ServiceError>;
+    fn delete(&self, id: u64, user_id: u64) -> Result<bool, ServiceError>;
+}
+
+impl CategoryRepository {
+    /// Creates a new category repository.
+    pub fn new() -> Self {
+        Self {
+            conn: connection::connect_rdb(),
+        }
+    }
+
+    /// Finds the category of the user.
+    pub fn find(&self, id: u64, user_id: u64) -> Result<Category, ServiceError> {
+        let category: Result<Category, Error> = categories::table
+            .filter(categories::id.eq(id))
+            .filter(categories::user_id.eq(user_id))
+            .first::<Category>(&self.conn);
+
+        match category {
+            Ok(category) => Ok(category),
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(id.to_string()))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
+    /// Finds all categories of the user in name order.
+    pub fn find_all_by_user_id(&self, user_id: u64) -> Result<Vec<Category>, ServiceError> {
+        let category_list: Result<Vec<Category>, Error> = categories::table
+            .filter(categories::user_id.eq(user_id))
+            .order((categories::name.asc(), categories::id.asc()))
+            .load::<Category>(&self.conn);
+
+        match category_list {
+            Ok(category_list) => Ok(category_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Counts posts in the category, including drafts and posts in the trash.
+    pub fn count_posts(&self, id: u64) -> Result<u64, ServiceError> {
+        let count: Result<i64, Error> = posts::table
+            .filter(posts::category_id.eq(id))
+            .count()
+            .get_result(&self.conn);
+
+        match count {
+            Ok(count) => Ok(count as u64),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Creates a new category, and returns id of the created category.
+    pub fn create(&self, user_id: u64, name: &str) -> Result<u64, ServiceError> {
+        let category_to_create = CategoryDAO {
+            user_id,
+            name: name.to_string(),
+        };
+
+        connection::with_transaction(&self.conn, || {
+            diesel::insert_into(categories::table)
+                .values(category_to_create)
+                .execute(&self.conn)?;
+
+            let id = categories::table
+                .select(categories::id)
+                .filter(categories::user_id.eq(user_id))
+                .order(categories::id.desc())
+                .first::<u64>(&self.conn)?;
+            Ok(id)
+        })
+        .map_err(get_service_error)
+    }
+
+    /// Renames the category of the user.
+    pub fn update(&self, id: u64, user_id: u64, name: &str) -> Result<bool, ServiceError> {
+        let target_category = categories::table
+            .filter(categories::id.eq(id))
+            .filter(categories::user_id.eq(user_id));
+        let count = diesel::update(target_category)
+            .set(categories::name.eq(name))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::NotFound(id.to_string())))
+                }
+            }
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Deletes the category of the user, after removing its posts from it in a transaction.
+    pub fn delete(&self, id: u64, user_id: u64) -> Result<bool, ServiceError> {
+        connection::with_transaction(&self.conn, || {
+            let target_category = categories::table
+                .filter(categories::id.eq(id))
+                .filter(categories::user_id.eq(user_id));
+            if target_category
+                .select(categories::id)
+                .first::<u64>(&self.conn)
+                .optional()?
+                .is_none()
+            {
+                return Err(ServiceError::NotFound(id.to_string()));
+            }
+
+            diesel::update(posts::table.filter(posts::category_id.eq(id)))
+                .set(posts::category_id.eq(None::<u64>))
+                .execute(&self.conn)?;
+            diesel::delete(target_category).execute(&self.conn)?;
+            Ok(true)
+        })
+        .map_err(get_service_error)
+    }
+}
+
+impl CategoryRepositoryTrait for CategoryRepository {
+    fn find(&self, id: u64, user_id: u64) -> Result<Category, ServiceError> {
+        let _span = trace_util::start_span("CategoryRepository::find", None);
+        CategoryRepository::find(self, id, user_id)
+    }
+
+    fn find_all_by_user_id(&self, user_id: u64) -> Result<Vec<Category>, ServiceError> {
+        let _span = trace_util::start_span("CategoryRepository::find_all_by_user_id", None);
+        CategoryRepository::find_all_by_user_id(self, user_id)
+    }
+
+    fn count_posts(&self, id: u64) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("CategoryRepository::count_posts", None);
+        CategoryRepository::count_posts(self, id)
+    }
+
+    fn create(&self, user_id: u64, name: &str) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("CategoryRepository::create", None);
+        CategoryRepository::create(self, user_id, name)
+    }
+
+    fn update(&self, id: u64, user_id: u64, name: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("CategoryRepository::update", None);
+        CategoryRepository::update(self, id, user_id, name)
+    }
+
+    fn delete(&self, id: u64, user_id: u64) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("CategoryRepository::delete", None);
+        CategoryRepository::delete(self, id, user_id)
+    }
+}
+
+impl Default for CategoryRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
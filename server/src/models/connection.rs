@@ -1,11 +1,163 @@
-use diesel::{mysql::MysqlConnection, prelude::*};
+use diesel::mysql::MysqlConnection;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, ManageConnection, Pool, PooledConnection};
+use lazy_static::lazy_static;
+use serde::Serialize;
 use std::env;
+use std::time::Duration;
 
-/// Get established MySQL connection.
-pub fn connect_rdb() -> MysqlConnection {
-    dotenv::dotenv().expect("Failed to read .env file");
-    let rdb_url = env::var("DATABASE_URL").expect("DATABASE_URL not found");
-    MysqlConnection::establish(&rdb_url).expect("Failed to establish a db connection")
+use crate::models::error::ServiceError;
+use crate::utils::env_util;
+
+/// A MySQL connection borrowed from the connection pool, which is returned to the pool when dropped.
+pub type RdbConnection = PooledConnection<ConnectionManager<MysqlConnection>>;
+
+/// A redis connection borrowed from the connection pool, which is returned to the pool when dropped.
+pub type PooledRedisConnection = PooledConnection<RedisConnectionManager>;
+
+/// A timeout of connecting and sending commands through pooled redis connections, which bounds
+/// the delay added to every request while redis is unavailable.
+const REDIS_POOL_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// A maximum number of redis connections managed by the pool.
+const REDIS_POOL_MAX_SIZE: u32 = 16;
+
+lazy_static! {
+    /// A pool of MySQL connections shared by all repositories.
+    static ref RDB_POOL: Pool<ConnectionManager<MysqlConnection>> = {
+        dotenv::dotenv().expect("Failed to read .env file");
+        let rdb_url = env::var("DATABASE_URL").expect("DATABASE_URL not found");
+        build_pool(ConnectionManager::new(rdb_url), &PoolConfig::from_env())
+    };
+
+    /// A pool of redis connections for the commands sent on every request, such as rate limiting.
+    static ref REDIS_POOL: Pool<RedisConnectionManager> = build_pool(
+        RedisConnectionManager {
+            redis_url: env::var("REDIS_URL").unwrap_or_default(),
+            timeout: REDIS_POOL_TIMEOUT,
+        },
+        &PoolConfig {
+            max_size: REDIS_POOL_MAX_SIZE,
+            connection_timeout: REDIS_POOL_TIMEOUT,
+            idle_timeout: Duration::from_secs(600),
+        },
+    );
+}
+
+/// A connection manager of the redis connection pool.
+/// Commands sent through the connections time out after the timeout.
+pub struct RedisConnectionManager {
+    redis_url: String,
+    timeout: Duration,
+}
+
+impl ManageConnection for RedisConnectionManager {
+    type Connection = redis::Connection;
+    type Error = redis::RedisError;
+
+    fn connect(&self) -> Result<redis::Connection, Self::Error> {
+        let client = redis::Client::open(self.redis_url.as_str())?;
+        let connection = client.get_connection_with_timeout(self.timeout)?;
+        connection.set_read_timeout(Some(self.timeout))?;
+        connection.set_write_timeout(Some(self.timeout))?;
+        Ok(connection)
+    }
+
+    fn is_valid(&self, connection: &mut redis::Connection) -> Result<(), Self::Error> {
+        redis::cmd("PING").query(connection)
+    }
+
+    fn has_broken(&self, connection: &mut redis::Connection) -> bool {
+        !redis::ConnectionLike::is_open(connection)
+    }
+}
+
+/// Configuration of a connection pool.
+pub struct PoolConfig {
+    /// A maximum number of connections managed by the pool.
+    pub max_size: u32,
+    /// A maximum time to wait for a connection to be available.
+    pub connection_timeout: Duration,
+    /// A time after which an idle connection is closed.
+    pub idle_timeout: Duration,
+}
+
+impl PoolConfig {
+    /// Reads the configuration from `DATABASE_POOL_MAX_SIZE`,
+    /// `DATABASE_POOL_CONNECTION_TIMEOUT_SECONDS` and `DATABASE_POOL_IDLE_TIMEOUT_SECONDS`.
+    pub fn from_env() -> Self {
+        Self {
+            max_size: env_util::get_env_var_or("DATABASE_POOL_MAX_SIZE", 10),
+            connection_timeout: Duration::from_secs(env_util::get_env_var_or(
+                "DATABASE_POOL_CONNECTION_TIMEOUT_SECONDS",
+                30,
+            )),
+            idle_timeout: Duration::from_secs(env_util::get_env_var_or(
+                "DATABASE_POOL_IDLE_TIMEOUT_SECONDS",
+                600,
+            )),
+        }
+    }
+}
+
+/// Metrics of a connection pool.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PoolMetrics {
+    /// A number of connections currently managed by the pool.
+    pub size: u32,
+    /// A number of idle connections in the pool.
+    pub idle: u32,
+    /// A number of connections borrowed from the pool.
+    pub in_use: u32,
+}
+
+/// Builds a connection pool with the configuration.
+/// Connections are established lazily when they are needed.
+fn build_pool<M: ManageConnection>(manager: M, config: &PoolConfig) -> Pool<M> {
+    Pool::builder()
+        .max_size(config.max_size)
+        .min_idle(Some(0))
+        .connection_timeout(config.connection_timeout)
+        .idle_timeout(Some(config.idle_timeout))
+        .build_unchecked(manager)
+}
+
+/// Returns metrics of the connection pool.
+fn get_pool_metrics<M: ManageConnection>(pool: &Pool<M>) -> PoolMetrics {
+    let state = pool.state();
+    PoolMetrics {
+        size: state.connections,
+        idle: state.idle_connections,
+        in_use: state.connections - state.idle_connections,
+    }
+}
+
+/// Get MySQL connection from the connection pool.
+pub fn connect_rdb() -> RdbConnection {
+    RDB_POOL
+        .get()
+        .expect("Failed to get a db connection from the pool")
+}
+
+/// Get MySQL connection from the connection pool, or `None` if no connection is available
+/// within the timeout.
+pub fn try_connect_rdb(timeout: Duration) -> Option<RdbConnection> {
+    RDB_POOL.get_timeout(timeout).ok()
+}
+
+/// Returns metrics of the MySQL connection pool.
+pub fn get_rdb_pool_metrics() -> PoolMetrics {
+    get_pool_metrics(&RDB_POOL)
+}
+
+/// Runs the function in a transaction of the connection.
+/// All writes in the function are committed if it succeeds, and rolled back if it fails.
+pub fn with_transaction<C, T, F>(conn: &C, f: F) -> Result<T, ServiceError>
+where
+    C: Connection,
+    F: FnOnce() -> Result<T, ServiceError>,
+{
+    conn.transaction(f)
 }
 
 /// Get established redis connection.
@@ -17,3 +169,205 @@ pub fn connect_redis() -> redis::Connection {
         .get_connection()
         .expect("Failed to get redis connection")
 }
+
+/// Get redis connection from the connection pool, or `None` if redis doesn't respond within the
+/// timeout of the pool.
+pub fn try_connect_pooled_redis() -> Option<PooledRedisConnection> {
+    REDIS_POOL.get().ok()
+}
+
+/// Get established redis connection, or `None` if redis doesn't respond within the timeout.
+/// Commands sent through the connection also time out after the timeout.
+pub fn try_connect_redis(timeout: Duration) -> Option<redis::Connection> {
+    let redis_url = env::var("REDIS_URL").ok()?;
+    let client = redis::Client::open(redis_url).ok()?;
+    let connection = client.get_connection_with_timeout(timeout).ok()?;
+    connection.set_read_timeout(Some(timeout)).ok()?;
+    connection.set_write_timeout(Some(timeout)).ok()?;
+    Some(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::connection::{AnsiTransactionManager, SimpleConnection};
+    use diesel::deserialize::QueryableByName;
+    use diesel::mysql::Mysql;
+    use diesel::query_builder::{AsQuery, QueryFragment, QueryId};
+    use diesel::result::Error;
+    use diesel::sql_types::HasSqlType;
+    use diesel::ConnectionResult;
+    use std::cell::RefCell;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// A connection manager counting connections it has established.
+    struct CountingConnectionManager {
+        established: Arc<AtomicUsize>,
+    }
+
+    impl ManageConnection for CountingConnectionManager {
+        type Connection = usize;
+        type Error = diesel::r2d2::Error;
+
+        fn connect(&self) -> Result<usize, Self::Error> {
+            Ok(self.established.fetch_add(1, Ordering::SeqCst))
+        }
+
+        fn is_valid(&self, _: &mut usize) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn has_broken(&self, _: &mut usize) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_pool_reuses_connections() {
+        let established = Arc::new(AtomicUsize::new(0));
+        let config = PoolConfig {
+            max_size: 2,
+            connection_timeout: Duration::from_secs(1),
+            idle_timeout: Duration::from_secs(600),
+        };
+        let pool = build_pool(
+            CountingConnectionManager {
+                established: established.clone(),
+            },
+            &config,
+        );
+
+        for _ in 0..5 {
+            let connection = pool.get().unwrap();
+            assert_eq!(*connection, 0);
+            assert_eq!(
+                get_pool_metrics(&pool),
+                PoolMetrics {
+                    size: 1,
+                    idle: 0,
+                    in_use: 1
+                }
+            );
+        }
+
+        assert_eq!(established.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            get_pool_metrics(&pool),
+            PoolMetrics {
+                size: 1,
+                idle: 1,
+                in_use: 0
+            }
+        );
+    }
+
+    /// A connection recording the statements executed on it instead of running them.
+    /// Queries are not supported, so they are not found.
+    struct RecordingConnection {
+        statements: RefCell<Vec<String>>,
+        transaction_manager: AnsiTransactionManager,
+    }
+
+    impl RecordingConnection {
+        fn new() -> Self {
+            Self {
+                statements: RefCell::new(vec![]),
+                transaction_manager: AnsiTransactionManager::new(),
+            }
+        }
+
+        fn write(&self, query: &str) -> Result<(), ServiceError> {
+            self.batch_execute(query)?;
+            Ok(())
+        }
+    }
+
+    impl SimpleConnection for RecordingConnection {
+        fn batch_execute(&self, query: &str) -> QueryResult<()> {
+            self.statements.borrow_mut().push(query.to_string());
+            Ok(())
+        }
+    }
+
+    impl Connection for RecordingConnection {
+        type Backend = Mysql;
+        type TransactionManager = AnsiTransactionManager;
+
+        fn establish(_: &str) -> ConnectionResult<Self> {
+            Ok(Self::new())
+        }
+
+        fn execute(&self, query: &str) -> QueryResult<usize> {
+            self.batch_execute(query).map(|_| 1)
+        }
+
+        fn query_by_index<T, U>(&self, _: T) -> QueryResult<Vec<U>>
+        where
+            T: AsQuery,
+            T::Query: QueryFragment<Mysql> + QueryId,
+            Mysql: HasSqlType<T::SqlType>,
+            U: Queryable<T::SqlType, Mysql>,
+        {
+            Err(Error::NotFound)
+        }
+
+        fn query_by_name<T, U>(&self, _: &T) -> QueryResult<Vec<U>>
+        where
+            T: QueryFragment<Mysql> + QueryId,
+            U: QueryableByName<Mysql>,
+        {
+            Err(Error::NotFound)
+        }
+
+        fn execute_returning_count<T>(&self, _: &T) -> QueryResult<usize>
+        where
+            T: QueryFragment<Mysql> + QueryId,
+        {
+            Err(Error::NotFound)
+        }
+
+        fn transaction_manager(&self) -> &AnsiTransactionManager {
+            &self.transaction_manager
+        }
+    }
+
+    #[test]
+    fn test_with_transaction_commits_all_writes() {
+        let conn = RecordingConnection::new();
+
+        let result = with_transaction(&conn, || {
+            conn.write("INSERT INTO users")?;
+            conn.write("INSERT INTO user_keys")?;
+            Ok(true)
+        });
+
+        assert!(result.unwrap());
+        assert_eq!(
+            *conn.statements.borrow(),
+            vec![
+                "BEGIN",
+                "INSERT INTO users",
+                "INSERT INTO user_keys",
+                "COMMIT"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_with_transaction_rolls_back_first_write_when_second_write_fails() {
+        let conn = RecordingConnection::new();
+
+        let result: Result<bool, ServiceError> = with_transaction(&conn, || {
+            conn.write("INSERT INTO users")?;
+            Err(ServiceError::QueryExecutionFailure)
+        });
+
+        assert!(matches!(result, Err(ServiceError::QueryExecutionFailure)));
+        assert_eq!(
+            *conn.statements.borrow(),
+            vec!["BEGIN", "INSERT INTO users", "ROLLBACK"]
+        );
+    }
+}
@@ -1,8 +1,9 @@
-use chrono::Utc;
 use diesel::result;
 use serde::Serialize;
 use thiserror::Error;
 
+use crate::utils::log_util;
+
 /// Errors using in model layer.
 #[derive(Error, Debug)]
 pub enum ModelError {
@@ -19,17 +20,34 @@ pub enum ServiceError {
     #[error("invalid argument supplied")]
     InvalidArgument,
 
+    #[error("invalid fields: {}", get_field_names(.0))]
+    Validation(Vec<FieldError>),
+
     #[error("invalid format")]
     InvalidFormat,
 
     #[error("duplicated key")]
     DuplicatedKey,
 
+    #[error("data for key `{0}` conflicts with the current state")]
+    Conflict(String),
+
     #[error("query execution failure")]
     QueryExecutionFailure,
 
-    #[error("unauthorized")]
-    Unauthorized,
+    /// It has the reason of the denial if it should be told to the user, such as a locked account.
+    #[error("unauthorized{}", get_reason_suffix(.0))]
+    Unauthorized(Option<UnauthorizedReason>),
+
+    #[error("totp code required")]
+    TotpRequired,
+
+    /// It has seconds after which the request may be retried.
+    #[error("too many requests, retry after {0} seconds")]
+    TooManyRequests(u64),
+
+    #[error("expired")]
+    Expired,
 
     #[error("internal server error")]
     InternalServerError,
@@ -41,8 +59,139 @@ pub enum ServiceError {
     EmailFailure(String),
 }
 
+impl ServiceError {
+    /// Returns a stable machine-readable code of the error, which is given to clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ServiceError::NotFound(_) => "NOT_FOUND",
+            ServiceError::InvalidArgument => "INVALID_ARGUMENT",
+            ServiceError::Validation(_) => "VALIDATION_FAILED",
+            ServiceError::InvalidFormat => "INVALID_FORMAT",
+            ServiceError::DuplicatedKey => "DUPLICATED_KEY",
+            ServiceError::Conflict(_) => "CONFLICT",
+            ServiceError::QueryExecutionFailure => "QUERY_EXECUTION_FAILURE",
+            ServiceError::Unauthorized(_) => "UNAUTHORIZED",
+            ServiceError::TotpRequired => "TOTP_REQUIRED",
+            ServiceError::TooManyRequests(_) => "TOO_MANY_REQUESTS",
+            ServiceError::Expired => "EXPIRED",
+            ServiceError::InternalServerError => "INTERNAL_SERVER_ERROR",
+            ServiceError::UserNotFound(_) => "USER_NOT_FOUND",
+            ServiceError::EmailFailure(_) => "EMAIL_FAILURE",
+        }
+    }
+}
+
+impl From<result::Error> for ServiceError {
+    /// Converts a failed query, such as a write in a transaction, into a service error.
+    fn from(error: result::Error) -> Self {
+        log_util::log(&error);
+        ServiceError::QueryExecutionFailure
+    }
+}
+
+/// Reasons of `ServiceError::Unauthorized` which are told to the user who knows the password.
+#[derive(Error, Debug, PartialEq, Serialize)]
+pub enum UnauthorizedReason {
+    #[error("account locked until `{0}`")]
+    AccountLocked(String),
+
+    #[error("account deactivated")]
+    AccountDeactivated,
+}
+
+/// Returns the reason of the denial following a colon, or an empty string if there is no reason.
+fn get_reason_suffix(reason: &Option<UnauthorizedReason>) -> String {
+    match reason {
+        Some(reason) => format!(": {}", reason),
+        None => String::new(),
+    }
+}
+
+/// A validation failure of a field of the request, such as `name` which must not be empty.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub reason: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, reason: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Returns names of the invalid fields separated by commas.
+fn get_field_names(field_errors: &[FieldError]) -> String {
+    field_errors
+        .iter()
+        .map(|field_error| field_error.field.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+/// Errors of password policy violation.
+#[derive(Error, Debug, PartialEq)]
+pub enum PasswordPolicyError {
+    #[error("password must be at least {0} characters long")]
+    TooShort(usize),
+
+    #[error("password must not consist only of numbers")]
+    AllNumeric,
+
+    #[error("password is too common")]
+    Common,
+}
+
+/// Errors of sending an email.
+#[derive(Error, Debug, PartialEq)]
+pub enum EmailError {
+    /// It fails however many times it is retried.
+    #[error("invalid email address `{0}`")]
+    InvalidAddress(String),
+
+    /// It may succeed if it is retried later.
+    #[error("mail server unavailable: {0}")]
+    Unavailable(String),
+}
+
+impl EmailError {
+    /// Returns whether the email may be sent if it is retried.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, EmailError::Unavailable(_))
+    }
+}
+
+/// Errors of a setting read from an environment variable at startup.
+#[derive(Error, Debug, PartialEq)]
+pub enum ConfigError {
+    #[error("{0} is required but not set")]
+    Missing(String),
+
+    #[error("{0} is invalid: {1}")]
+    Invalid(String, String),
+}
+
+/// Errors of all the settings found to be missing or invalid at startup, which are reported at
+/// once so that the operator can fix them together.
+#[derive(Error, Debug, PartialEq)]
+#[error("invalid configuration: {}", get_config_error_messages(.0))]
+pub struct InvalidConfigError(pub Vec<ConfigError>);
+
+/// Returns messages of the config errors separated by semicolons.
+fn get_config_error_messages(config_errors: &[ConfigError]) -> String {
+    config_errors
+        .iter()
+        .map(|config_error| config_error.to_string())
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
 /// Logs and returns service error passed by parameter.
+/// The log has the correlation id of the request being processed.
 pub fn get_service_error(error: ServiceError) -> ServiceError {
-    println!("[{}] {}", Utc::now(), error);
+    log_util::log(&error);
     error
 }
@@ -0,0 +1,59 @@
+use diesel::prelude::*;
+use mockall::automock;
+use std::time::Duration;
+
+use crate::models::connection;
+use crate::utils::trace_util;
+
+/// A maximum time to wait for each dependency to respond to a health check.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A core data repository checking whether the data stores are available.
+pub struct HealthRepository {}
+
+#[automock]
+pub trait HealthRepositoryTrait {
+    fn ping_rdb(&self) -> bool;
+    fn ping_redis(&self) -> bool;
+}
+
+impl HealthRepository {
+    /// Creates a new health repository.
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Returns whether MySQL responds to `SELECT 1` within the timeout.
+    pub fn ping_rdb(&self) -> bool {
+        match connection::try_connect_rdb(HEALTH_CHECK_TIMEOUT) {
+            Some(conn) => diesel::sql_query("SELECT 1").execute(&conn).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Returns whether redis responds to `PING` within the timeout.
+    pub fn ping_redis(&self) -> bool {
+        match connection::try_connect_redis(HEALTH_CHECK_TIMEOUT) {
+            Some(mut conn) => redis::cmd("PING").query::<String>(&mut conn).is_ok(),
+            None => false,
+        }
+    }
+}
+
+impl HealthRepositoryTrait for HealthRepository {
+    fn ping_rdb(&self) -> bool {
+        let _span = trace_util::start_span("HealthRepository::ping_rdb", None);
+        HealthRepository::ping_rdb(self)
+    }
+
+    fn ping_redis(&self) -> bool {
+        let _span = trace_util::start_span("HealthRepository::ping_redis", None);
+        HealthRepository::ping_redis(self)
+    }
+}
+
+impl Default for HealthRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
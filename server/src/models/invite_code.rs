@@ -0,0 +1,174 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::result::Error;
+use diesel::sql_types::{Integer, Unsigned};
+use mockall::automock;
+use serde::{Deserialize, Serialize};
+
+use crate::models::connection;
+use crate::models::error::{get_service_error, ServiceError};
+use crate::schema::{invite_codes, invite_codes::dsl};
+use crate::utils::trace_util;
+
+/// Invite code representing `invite_codes` table.
+/// A user can sign up with the code until its remaining uses run out, it expires, or it is revoked.
+#[derive(Debug, Serialize, Deserialize, Queryable)]
+pub struct InviteCode {
+    pub id: u64,
+    pub code: String,
+    pub max_uses: u32,
+    pub remaining_uses: u32,
+    pub expires_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Invite code DAO using between models layer and RDB.
+#[derive(Insertable)]
+#[table_name = "invite_codes"]
+struct InviteCodeDAO {
+    code: String,
+    max_uses: u32,
+    remaining_uses: u32,
+    expires_at: Option<NaiveDateTime>,
+}
+
+/// A core data repository for invite code.
+pub struct InviteCodeRepository {
+    conn: connection::RdbConnection,
+}
+
+#[automock]
+pub trait InviteCodeRepositoryTrait {
+    fn find_by_code(&self, code: &str) -> Result<InviteCode, ServiceError>;
+    fn create(
+        &self,
+        code: &str,
+        max_uses: u32,
+        expires_at: &Option<NaiveDateTime>,
+    ) -> Result<bool, ServiceError>;
+    fn consume(&self, code: &str, now: NaiveDateTime) -> Result<bool, ServiceError>;
+    fn revoke(&self, code: &str, now: NaiveDateTime) -> Result<bool, ServiceError>;
+}
+
+impl InviteCodeRepository {
+    /// Creates a new invite code repository.
+    pub fn new() -> Self {
+        Self {
+            conn: connection::connect_rdb(),
+        }
+    }
+
+    /// Finds an invite code by code.
+    pub fn find_by_code(&self, code: &str) -> Result<InviteCode, ServiceError> {
+        let invite_code: Result<InviteCode, Error> = dsl::invite_codes
+            .filter(dsl::code.eq(code))
+            .get_result::<InviteCode>(&self.conn);
+
+        match invite_code {
+            Ok(invite_code) => Ok(invite_code),
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(code.to_string()))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
+    /// Creates a new invite code which can be used `max_uses` times.
+    pub fn create(
+        &self,
+        code: &str,
+        max_uses: u32,
+        expires_at: &Option<NaiveDateTime>,
+    ) -> Result<bool, ServiceError> {
+        let invite_code_to_create = InviteCodeDAO {
+            code: code.to_string(),
+            max_uses,
+            remaining_uses: max_uses,
+            expires_at: *expires_at,
+        };
+
+        let count = diesel::insert_into(dsl::invite_codes)
+            .values(invite_code_to_create)
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) if count > 0 => Ok(true),
+            _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Decrements the remaining uses of the invite code, and returns whether it is consumed.
+    /// It is done in a single conditional update, so concurrent sign ups can't use the code more
+    /// than its maximum uses. An exhausted, expired, revoked or unknown code is not consumed.
+    pub fn consume(&self, code: &str, now: NaiveDateTime) -> Result<bool, ServiceError> {
+        let target_invite_code = dsl::invite_codes
+            .filter(dsl::code.eq(code))
+            .filter(dsl::remaining_uses.gt(0))
+            .filter(dsl::revoked_at.is_null())
+            .filter(dsl::expires_at.is_null().or(dsl::expires_at.gt(now)));
+        let count = diesel::update(target_invite_code)
+            .set(dsl::remaining_uses.eq(sql::<Unsigned<Integer>>("remaining_uses - 1")))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => Ok(count > 0),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Revokes the invite code, after which it can't be used anymore.
+    pub fn revoke(&self, code: &str, now: NaiveDateTime) -> Result<bool, ServiceError> {
+        let target_invite_code = dsl::invite_codes
+            .filter(dsl::code.eq(code))
+            .filter(dsl::revoked_at.is_null());
+        let count = diesel::update(target_invite_code)
+            .set(dsl::revoked_at.eq(now))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::NotFound(code.to_string())))
+                }
+            }
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl InviteCodeRepositoryTrait for InviteCodeRepository {
+    fn find_by_code(&self, code: &str) -> Result<InviteCode, ServiceError> {
+        let _span = trace_util::start_span("InviteCodeRepository::find_by_code", None);
+        InviteCodeRepository::find_by_code(self, code)
+    }
+
+    fn create(
+        &self,
+        code: &str,
+        max_uses: u32,
+        expires_at: &Option<NaiveDateTime>,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("InviteCodeRepository::create", None);
+        InviteCodeRepository::create(self, code, max_uses, expires_at)
+    }
+
+    fn consume(&self, code: &str, now: NaiveDateTime) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("InviteCodeRepository::consume", None);
+        InviteCodeRepository::consume(self, code, now)
+    }
+
+    fn revoke(&self, code: &str, now: NaiveDateTime) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("InviteCodeRepository::revoke", None);
+        InviteCodeRepository::revoke(self, code, now)
+    }
+}
+
+impl Default for InviteCodeRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
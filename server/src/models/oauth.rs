@@ -0,0 +1,374 @@
+use mockall::automock;
+use redis::{Commands, RedisError};
+use reqwest::header::{ACCEPT, USER_AGENT};
+use reqwest::{Client, Response, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::models::connection;
+use crate::models::error::{get_service_error, ServiceError};
+use crate::utils::trace_util;
+
+/// A future resolving to a response of OAuth provider API.
+pub type OAuthFuture<T> = Pin<Box<dyn Future<Output = Result<T, ServiceError>>>>;
+
+/// Profile of the user fetched from OAuth provider.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OAuthProfile {
+    pub email: String,
+    pub email_verified: bool,
+    pub name: String,
+    pub avatar_url: Option<String>,
+}
+
+/// A core data repository for OAuth state.
+/// The state is issued when the login begins, and it has the name of the OAuth provider.
+pub struct OAuthStateRepository {
+    key: String,
+    client: redis::Connection,
+}
+
+#[automock]
+pub trait OAuthStateRepositoryTrait {
+    fn new(state: &str) -> Self;
+    fn find(&mut self) -> Result<String, ServiceError>;
+    fn save(&mut self, provider: &str, ttl_seconds: usize) -> Result<bool, ServiceError>;
+    fn delete(&mut self) -> Result<bool, ServiceError>;
+}
+
+impl OAuthStateRepository {
+    /// Creates a new OAuth state repository.
+    pub fn new(state: &str) -> Self {
+        Self {
+            key: format!("oauth_state:{}", state),
+            client: connection::connect_redis(),
+        }
+    }
+
+    /// Finds the provider of the state.
+    pub fn find(&mut self) -> Result<String, ServiceError> {
+        match self.client.get::<&str, Option<String>>(&self.key) {
+            Ok(Some(provider)) => Ok(provider),
+            Ok(None) => Err(get_service_error(ServiceError::NotFound(self.key.clone()))),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Saves the state with the provider. It expires after `ttl_seconds`.
+    pub fn save(&mut self, provider: &str, ttl_seconds: usize) -> Result<bool, ServiceError> {
+        let result: Result<(), RedisError> = self.client.set_ex(&self.key, provider, ttl_seconds);
+        match result {
+            Ok(_) => Ok(true),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Deletes the state.
+    pub fn delete(&mut self) -> Result<bool, ServiceError> {
+        match self.client.del::<&str, _>(&self.key) {
+            Ok(result) => Ok(result),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl OAuthStateRepositoryTrait for OAuthStateRepository {
+    fn new(state: &str) -> Self {
+        OAuthStateRepository::new(state)
+    }
+
+    fn find(&mut self) -> Result<String, ServiceError> {
+        let _span = trace_util::start_span("OAuthStateRepository::find", None);
+        OAuthStateRepository::find(self)
+    }
+
+    fn save(&mut self, provider: &str, ttl_seconds: usize) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("OAuthStateRepository::save", None);
+        OAuthStateRepository::save(self, provider, ttl_seconds)
+    }
+
+    fn delete(&mut self) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("OAuthStateRepository::delete", None);
+        OAuthStateRepository::delete(self)
+    }
+}
+
+/// Parses JSON body of the response from OAuth provider.
+async fn parse_response<T: DeserializeOwned>(
+    response: reqwest::Result<Response>,
+) -> Result<T, ServiceError> {
+    match response {
+        Ok(response) if response.status().is_success() => match response.json::<T>().await {
+            Ok(data) => Ok(data),
+            Err(_) => Err(get_service_error(ServiceError::InvalidFormat)),
+        },
+        Ok(_) => Err(get_service_error(ServiceError::Unauthorized(None))),
+        Err(_) => Err(get_service_error(ServiceError::InternalServerError)),
+    }
+}
+
+/// Response of token API of OAuth provider.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+/// Response of Google userinfo API.
+#[derive(Deserialize)]
+struct GoogleProfileResponse {
+    email: String,
+    email_verified: bool,
+    name: Option<String>,
+    picture: Option<String>,
+}
+
+/// A core data repository for Google OAuth2 API.
+pub struct GoogleOAuthRepository {
+    client: Client,
+    client_id: String,
+    client_secret: String,
+    redirect_uri: String,
+}
+
+#[automock]
+pub trait GoogleOAuthRepositoryTrait {
+    fn get_authorization_url(&self, state: &str) -> String;
+    fn exchange_code(&self, code: &str) -> OAuthFuture<String>;
+    fn find_profile(&self, access_token: &str) -> OAuthFuture<OAuthProfile>;
+}
+
+impl GoogleOAuthRepository {
+    /// Creates a new Google OAuth repository.
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+            client_id: env::var("GOOGLE_CLIENT_ID").expect("GOOGLE_CLIENT_ID not found"),
+            client_secret: env::var("GOOGLE_CLIENT_SECRET")
+                .expect("GOOGLE_CLIENT_SECRET not found"),
+            redirect_uri: env::var("GOOGLE_REDIRECT_URI").expect("GOOGLE_REDIRECT_URI not found"),
+        }
+    }
+
+    /// Returns the URL of Google consent screen.
+    pub fn get_authorization_url(&self, state: &str) -> String {
+        Url::parse_with_params(
+            "https://accounts.google.com/o/oauth2/v2/auth",
+            &[
+                ("client_id", self.client_id.as_str()),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("response_type", "code"),
+                ("scope", "openid email profile"),
+                ("state", state),
+            ],
+        )
+        .map(|url| url.to_string())
+        .unwrap_or_default()
+    }
+
+    /// Exchanges the authorization code for an access token.
+    pub fn exchange_code(&self, code: &str) -> OAuthFuture<String> {
+        let request = self
+            .client
+            .post("https://oauth2.googleapis.com/token")
+            .form(&[
+                ("code", code),
+                ("client_id", &self.client_id),
+                ("client_secret", &self.client_secret),
+                ("redirect_uri", &self.redirect_uri),
+                ("grant_type", "authorization_code"),
+            ])
+            .send();
+
+        Box::pin(async move {
+            let token = parse_response::<TokenResponse>(request.await).await?;
+            Ok(token.access_token)
+        })
+    }
+
+    /// Finds the profile of the user who owns the access token.
+    pub fn find_profile(&self, access_token: &str) -> OAuthFuture<OAuthProfile> {
+        let request = self
+            .client
+            .get("https://openidconnect.googleapis.com/v1/userinfo")
+            .bearer_auth(access_token)
+            .send();
+
+        Box::pin(async move {
+            let GoogleProfileResponse {
+                email,
+                email_verified,
+                name,
+                picture,
+            } = parse_response::<GoogleProfileResponse>(request.await).await?;
+
+            Ok(OAuthProfile {
+                name: name.unwrap_or_else(|| email.clone()),
+                email,
+                email_verified,
+                avatar_url: picture,
+            })
+        })
+    }
+}
+
+impl GoogleOAuthRepositoryTrait for GoogleOAuthRepository {
+    fn get_authorization_url(&self, state: &str) -> String {
+        let _span = trace_util::start_span("GoogleOAuthRepository::get_authorization_url", None);
+        GoogleOAuthRepository::get_authorization_url(self, state)
+    }
+
+    fn exchange_code(&self, code: &str) -> OAuthFuture<String> {
+        let _span = trace_util::start_span("GoogleOAuthRepository::exchange_code", None);
+        GoogleOAuthRepository::exchange_code(self, code)
+    }
+
+    fn find_profile(&self, access_token: &str) -> OAuthFuture<OAuthProfile> {
+        let _span = trace_util::start_span("GoogleOAuthRepository::find_profile", None);
+        GoogleOAuthRepository::find_profile(self, access_token)
+    }
+}
+
+impl Default for GoogleOAuthRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Response of GitHub user API.
+#[derive(Deserialize)]
+struct GitHubUserResponse {
+    login: String,
+    name: Option<String>,
+    avatar_url: Option<String>,
+}
+
+/// Response of GitHub user emails API.
+#[derive(Deserialize)]
+struct GitHubEmailResponse {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+/// A core data repository for GitHub OAuth2 API.
+pub struct GitHubOAuthRepository {
+    client: Client,
+    client_id: String,
+    client_secret: String,
+    redirect_uri: String,
+}
+
+#[automock]
+pub trait GitHubOAuthRepositoryTrait {
+    fn get_authorization_url(&self, state: &str) -> String;
+    fn exchange_code(&self, code: &str) -> OAuthFuture<String>;
+    fn find_profile(&self, access_token: &str) -> OAuthFuture<OAuthProfile>;
+}
+
+impl GitHubOAuthRepository {
+    /// Creates a new GitHub OAuth repository.
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+            client_id: env::var("GITHUB_CLIENT_ID").expect("GITHUB_CLIENT_ID not found"),
+            client_secret: env::var("GITHUB_CLIENT_SECRET")
+                .expect("GITHUB_CLIENT_SECRET not found"),
+            redirect_uri: env::var("GITHUB_REDIRECT_URI").expect("GITHUB_REDIRECT_URI not found"),
+        }
+    }
+
+    /// Returns the URL of GitHub authorization page.
+    pub fn get_authorization_url(&self, state: &str) -> String {
+        Url::parse_with_params(
+            "https://github.com/login/oauth/authorize",
+            &[
+                ("client_id", self.client_id.as_str()),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("scope", "read:user user:email"),
+                ("state", state),
+            ],
+        )
+        .map(|url| url.to_string())
+        .unwrap_or_default()
+    }
+
+    /// Exchanges the authorization code for an access token.
+    pub fn exchange_code(&self, code: &str) -> OAuthFuture<String> {
+        let request = self
+            .client
+            .post("https://github.com/login/oauth/access_token")
+            .header(ACCEPT, "application/json")
+            .form(&[
+                ("code", code),
+                ("client_id", &self.client_id),
+                ("client_secret", &self.client_secret),
+                ("redirect_uri", &self.redirect_uri),
+            ])
+            .send();
+
+        Box::pin(async move {
+            let token = parse_response::<TokenResponse>(request.await).await?;
+            Ok(token.access_token)
+        })
+    }
+
+    /// Finds the profile of the user who owns the access token.
+    /// The email of the profile is the primary email of the GitHub account.
+    pub fn find_profile(&self, access_token: &str) -> OAuthFuture<OAuthProfile> {
+        let user_request = self
+            .client
+            .get("https://api.github.com/user")
+            .header(USER_AGENT, "Darim")
+            .bearer_auth(access_token)
+            .send();
+        let emails_request = self
+            .client
+            .get("https://api.github.com/user/emails")
+            .header(USER_AGENT, "Darim")
+            .bearer_auth(access_token)
+            .send();
+
+        Box::pin(async move {
+            let user = parse_response::<GitHubUserResponse>(user_request.await).await?;
+            let emails = parse_response::<Vec<GitHubEmailResponse>>(emails_request.await).await?;
+
+            let primary_email = match emails.into_iter().find(|email| email.primary) {
+                Some(primary_email) => primary_email,
+                None => return Err(get_service_error(ServiceError::Unauthorized(None))),
+            };
+
+            Ok(OAuthProfile {
+                name: user.name.unwrap_or(user.login),
+                email: primary_email.email,
+                email_verified: primary_email.verified,
+                avatar_url: user.avatar_url,
+            })
+        })
+    }
+}
+
+impl GitHubOAuthRepositoryTrait for GitHubOAuthRepository {
+    fn get_authorization_url(&self, state: &str) -> String {
+        let _span = trace_util::start_span("GitHubOAuthRepository::get_authorization_url", None);
+        GitHubOAuthRepository::get_authorization_url(self, state)
+    }
+
+    fn exchange_code(&self, code: &str) -> OAuthFuture<String> {
+        let _span = trace_util::start_span("GitHubOAuthRepository::exchange_code", None);
+        GitHubOAuthRepository::exchange_code(self, code)
+    }
+
+    fn find_profile(&self, access_token: &str) -> OAuthFuture<OAuthProfile> {
+        let _span = trace_util::start_span("GitHubOAuthRepository::find_profile", None);
+        GitHubOAuthRepository::find_profile(self, access_token)
+    }
+}
+
+impl Default for GitHubOAuthRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
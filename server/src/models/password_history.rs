@@ -0,0 +1,152 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::Error;
+use mockall::automock;
+use serde::{Deserialize, Serialize};
+
+use crate::models::connection;
+use crate::models::error::{get_service_error, ServiceError};
+use crate::schema::{password_history, password_history::dsl};
+use crate::utils::trace_util;
+
+/// Password history representing `password_history` table.
+/// It keeps the hash of a previous password of the user to prevent reusing it.
+#[derive(Debug, Serialize, Deserialize, Queryable)]
+pub struct PasswordHistory {
+    pub id: u64,
+    pub user_id: u64,
+    pub hashed_password: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Password history DAO using between models layer and RDB.
+#[derive(Insertable)]
+#[table_name = "password_history"]
+struct PasswordHistoryDAO {
+    user_id: u64,
+    hashed_password: String,
+}
+
+/// A core data repository for password history.
+pub struct PasswordHistoryRepository {
+    conn: connection::RdbConnection,
+}
+
+#[automock]
+pub trait PasswordHistoryRepositoryTrait {
+    fn find_recent_by_user_id(
+        &self,
+        user_id: u64,
+        limit: usize,
+    ) -> Result<Vec<PasswordHistory>, ServiceError>;
+    fn create(&self, user_id: u64, hashed_password: &str) -> Result<bool, ServiceError>;
+    fn delete_all_except_recent(&self, user_id: u64, count: usize) -> Result<usize, ServiceError>;
+}
+
+impl PasswordHistoryRepository {
+    /// Creates a new password history repository.
+    pub fn new() -> Self {
+        Self {
+            conn: connection::connect_rdb(),
+        }
+    }
+
+    /// Finds the recent password histories of the user from the latest one.
+    pub fn find_recent_by_user_id(
+        &self,
+        user_id: u64,
+        limit: usize,
+    ) -> Result<Vec<PasswordHistory>, ServiceError> {
+        let password_history_list: Result<Vec<PasswordHistory>, Error> = dsl::password_history
+            .filter(dsl::user_id.eq(user_id))
+            .order(dsl::id.desc())
+            .limit(limit as i64)
+            .load::<PasswordHistory>(&self.conn);
+
+        match password_history_list {
+            Ok(password_history_list) => Ok(password_history_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Creates a new password history.
+    pub fn create(&self, user_id: u64, hashed_password: &str) -> Result<bool, ServiceError> {
+        let password_history_to_create = PasswordHistoryDAO {
+            user_id,
+            hashed_password: hashed_password.to_string(),
+        };
+
+        let count = diesel::insert_into(dsl::password_history)
+            .values(password_history_to_create)
+            .execute(&self.conn);
+
+        if let Ok(count) = count {
+            if count > 0 {
+                Ok(true)
+            } else {
+                Err(get_service_error(ServiceError::QueryExecutionFailure))
+            }
+        } else {
+            Err(get_service_error(ServiceError::QueryExecutionFailure))
+        }
+    }
+
+    /// Deletes the password histories of the user except the given number of recent ones,
+    /// and returns the number of deleted histories.
+    pub fn delete_all_except_recent(
+        &self,
+        user_id: u64,
+        count: usize,
+    ) -> Result<usize, ServiceError> {
+        let old_ids: Result<Vec<u64>, Error> = dsl::password_history
+            .select(dsl::id)
+            .filter(dsl::user_id.eq(user_id))
+            .order(dsl::id.desc())
+            .load::<u64>(&self.conn);
+
+        let old_ids: Vec<u64> = match old_ids {
+            Ok(ids) => ids.into_iter().skip(count).collect(),
+            Err(_) => return Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        };
+        if old_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let target_password_histories = dsl::password_history.filter(dsl::id.eq_any(old_ids));
+        let count = diesel::delete(target_password_histories).execute(&self.conn);
+
+        match count {
+            Ok(count) => Ok(count),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl PasswordHistoryRepositoryTrait for PasswordHistoryRepository {
+    fn find_recent_by_user_id(
+        &self,
+        user_id: u64,
+        limit: usize,
+    ) -> Result<Vec<PasswordHistory>, ServiceError> {
+        let _span =
+            trace_util::start_span("PasswordHistoryRepository::find_recent_by_user_id", None);
+        PasswordHistoryRepository::find_recent_by_user_id(self, user_id, limit)
+    }
+
+    fn create(&self, user_id: u64, hashed_password: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("PasswordHistoryRepository::create", None);
+        PasswordHistoryRepository::create(self, user_id, hashed_password)
+    }
+
+    fn delete_all_except_recent(&self, user_id: u64, count: usize) -> Result<usize, ServiceError> {
+        let _span =
+            trace_util::start_span("PasswordHistoryRepository::delete_all_except_recent", None);
+        PasswordHistoryRepository::delete_all_except_recent(self, user_id, count)
+    }
+}
+
+impl Default for PasswordHistoryRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,15 +1,24 @@
-use chrono::{NaiveDateTime, Utc};
+use base32::Alphabet;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::deserialize::{self, FromSql};
+use diesel::mysql::Mysql;
 use diesel::prelude::*;
 use diesel::result::Error;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
 use mockall::automock;
+use redis::{Commands, RedisError};
 use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::str::FromStr;
 
 use crate::models::connection;
 use crate::models::error::{get_service_error, ServiceError};
-use crate::schema::{posts, posts::dsl};
+use crate::schema::{post_tags, posts, posts::dsl};
+use crate::utils::trace_util;
 
 /// Post representing `posts` table.
-#[derive(Debug, Serialize, Deserialize, Queryable)]
+#[derive(Clone, Debug, Serialize, Deserialize, Queryable)]
 pub struct Post {
     pub id: u64,
     pub user_id: u64,
@@ -18,17 +27,260 @@ pub struct Post {
     pub date: NaiveDateTime,
     pub created_at: NaiveDateTime,
     pub updated_at: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub version: u64,
+    pub publish_at: Option<NaiveDateTime>,
+    pub status: PostStatus,
+    pub pinned: bool,
+    pub pinned_at: Option<NaiveDateTime>,
+    /// A token of the public link of the post, which is `None` if the post is not shared.
+    pub share_token: Option<String>,
+    /// A mood of the writer tagged to the post. It is not encrypted, so it can be aggregated.
+    pub mood: Option<Mood>,
+    /// An id of the category of the writer to which the post belongs, if any.
+    pub category_id: Option<u64>,
+}
+
+/// Status of a post. A draft is a half-written post, which is not listed with the published ones.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum PostStatus {
+    Draft,
+    Published,
+}
+
+impl PostStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PostStatus::Draft => "draft",
+            PostStatus::Published => "published",
+        }
+    }
+}
+
+impl ToSql<Text, Mysql> for PostStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Mysql>) -> serialize::Result {
+        <str as ToSql<Text, Mysql>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Mysql> for PostStatus {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <String as FromSql<Text, Mysql>>::from_sql(bytes)?.as_str() {
+            "draft" => Ok(PostStatus::Draft),
+            "published" => Ok(PostStatus::Published),
+            status => Err(format!("unknown post status `{}`", status).into()),
+        }
+    }
+}
+
+/// Mood of the writer tagged to a post.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum Mood {
+    Happy,
+    Sad,
+    Neutral,
+    Anxious,
+    Excited,
+}
+
+impl Mood {
+    /// All the moods in the order they are listed.
+    pub const ALL: [Mood; 5] = [
+        Mood::Happy,
+        Mood::Sad,
+        Mood::Neutral,
+        Mood::Anxious,
+        Mood::Excited,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Mood::Happy => "happy",
+            Mood::Sad => "sad",
+            Mood::Neutral => "neutral",
+            Mood::Anxious => "anxious",
+            Mood::Excited => "excited",
+        }
+    }
+}
+
+impl FromStr for Mood {
+    type Err = String;
+
+    fn from_str(mood: &str) -> Result<Self, Self::Err> {
+        Mood::ALL
+            .iter()
+            .find(|candidate| candidate.as_str() == mood)
+            .copied()
+            .ok_or_else(|| format!("unknown mood `{}`", mood))
+    }
+}
+
+impl ToSql<Text, Mysql> for Mood {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Mysql>) -> serialize::Result {
+        <str as ToSql<Text, Mysql>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Mysql> for Mood {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        Ok(<String as FromSql<Text, Mysql>>::from_sql(bytes)?.parse()?)
+    }
+}
+
+/// A way to match posts against multiple tags.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TagMode {
+    /// Posts bearing all of the tags are found.
+    All,
+    /// Posts bearing any of the tags are found. It is the default.
+    Any,
+}
+
+impl FromStr for TagMode {
+    type Err = String;
+
+    fn from_str(tag_mode: &str) -> Result<Self, Self::Err> {
+        match tag_mode {
+            "all" => Ok(TagMode::All),
+            "any" => Ok(TagMode::Any),
+            _ => Err(format!("unknown tag mode `{}`", tag_mode)),
+        }
+    }
+}
+
+/// Conditions to find posts.
+#[derive(Debug)]
+pub struct PostFilter {
+    /// Only posts dated on or after it are found, if it is given.
+    pub from: Option<NaiveDateTime>,
+    /// Only posts dated on or before it are found, if it is given.
+    pub to: Option<NaiveDateTime>,
+    /// Only posts bearing the tags are found, if they are given.
+    pub tags: Vec<String>,
+    /// Whether posts must bear all of the tags or any of them.
+    pub tag_mode: TagMode,
+    /// Only posts in the category of the id are found, if it is given.
+    pub category_id: Option<u64>,
+    /// Only posts published on or before it are found.
+    /// A post without the publish time is published as soon as it is created.
+    pub published_until: NaiveDateTime,
 }
 
 /// Post DTO using between routes layer and service layer.
+/// The creation time and the update time are serialized in RFC 3339 format with UTC offset.
 #[derive(Serialize, Deserialize)]
 pub struct PostDTO {
     pub id: u64,
     pub title: String,
     pub content: String,
     pub date: NaiveDateTime,
-    pub created_at: NaiveDateTime,
-    pub updated_at: Option<NaiveDateTime>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+    pub version: u64,
+    pub publish_at: Option<NaiveDateTime>,
+    pub status: PostStatus,
+    pub pinned: bool,
+    pub mood: Option<Mood>,
+    pub category_id: Option<u64>,
+}
+
+/// Changes of a post using between routes layer and service layer.
+/// A field is not changed if it is `None`.
+#[derive(Serialize, Deserialize)]
+pub struct PostUpdateDTO {
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub date: Option<NaiveDateTime>,
+    pub tags: Option<Vec<String>>,
+    pub mood: Option<String>,
+}
+
+/// A page of post DTOs with the total count of the posts.
+/// `next` is a cursor after the last post of the page, if more posts may follow.
+#[derive(Serialize, Deserialize)]
+pub struct PostPageDTO {
+    pub posts: Vec<PostDTO>,
+    pub total_count: u64,
+    pub next: Option<String>,
+}
+
+/// A position in the list of posts in desc date order, after which the next page starts.
+/// It is compared by the date and the id as the list is ordered, so that the next page is not
+/// shifted by posts created or deleted between page loads.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PostCursor {
+    pub date: NaiveDateTime,
+    pub id: u64,
+}
+
+/// An alphabet of the encoded cursor, which is safe in a query string.
+const CURSOR_ALPHABET: Alphabet = Alphabet::RFC4648 { padding: false };
+/// A format of the date in the cursor.
+const CURSOR_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+impl PostCursor {
+    /// Returns the cursor after the post.
+    pub fn after(post: &Post) -> Self {
+        Self {
+            date: post.date,
+            id: post.id,
+        }
+    }
+
+    /// Returns the opaque string of the cursor.
+    pub fn encode(&self) -> String {
+        let raw = format!("{}_{}", self.date.format(CURSOR_DATE_FORMAT), self.id);
+        base32::encode(CURSOR_ALPHABET, raw.as_bytes())
+    }
+
+    /// Returns the cursor of the opaque string, or `None` if it is malformed.
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let raw = String::from_utf8(base32::decode(CURSOR_ALPHABET, cursor)?).ok()?;
+        let mut parts = raw.splitn(2, '_');
+        let date = NaiveDateTime::parse_from_str(parts.next()?, CURSOR_DATE_FORMAT).ok()?;
+        let id = parts.next()?.parse().ok()?;
+
+        Some(Self { date, id })
+    }
+}
+
+/// Writing streaks of a user, which are numbers of consecutive days with at least one post.
+#[derive(Serialize, Deserialize)]
+pub struct PostStreakDTO {
+    pub current_streak: u32,
+    pub longest_streak: u32,
+}
+
+/// Result of deleting a post in a batch.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkDeleteStatus {
+    /// The post is moved to the trash.
+    Deleted,
+    /// The post doesn't exist, or is already in the trash.
+    NotFound,
+    /// The post has been written by another user.
+    Forbidden,
+}
+
+/// Result of deleting a post of the id in a batch.
+#[derive(Serialize, Deserialize)]
+pub struct BulkDeleteResultDTO {
+    pub id: u64,
+    pub status: BulkDeleteStatus,
+}
+
+/// Number of posts tagged with a mood.
+#[derive(Serialize, Deserialize)]
+pub struct MoodCountDTO {
+    pub mood: Mood,
+    pub count: u64,
 }
 
 /// Summarized post DTO using between routes layer and service layer.
@@ -39,6 +291,26 @@ pub struct SummarizedPostDTO {
     pub date: NaiveDateTime,
 }
 
+/// Post to be imported from an exported archive, using between service layer and models layer.
+/// The original timestamps are preserved if they are given.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportedPost {
+    pub title: String,
+    pub content: String,
+    pub date: NaiveDateTime,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub publish_at: Option<NaiveDateTime>,
+}
+
+/// Result of importing posts from an exported archive.
+#[derive(Serialize, Deserialize)]
+pub struct PostImportResultDTO {
+    pub imported_count: u64,
+    pub skipped_count: u64,
+}
+
 /// Post DAO using between models layer and RDB.
 #[derive(Insertable, AsChangeset)]
 #[table_name = "posts"]
@@ -49,34 +321,117 @@ struct PostDAO {
     content: Option<String>,
     date: Option<NaiveDateTime>,
     updated_at: Option<NaiveDateTime>,
+    publish_at: Option<NaiveDateTime>,
+    status: Option<PostStatus>,
+    mood: Option<Mood>,
+}
+
+/// Imported post DAO using between models layer and RDB.
+#[derive(Insertable)]
+#[table_name = "posts"]
+struct ImportedPostDAO {
+    user_id: u64,
+    title: String,
+    content: String,
+    date: NaiveDateTime,
+    created_at: NaiveDateTime,
+    updated_at: Option<NaiveDateTime>,
+    deleted_at: Option<NaiveDateTime>,
+    publish_at: Option<NaiveDateTime>,
 }
 
 /// A core data repository for post.
 pub struct PostRepository {
-    conn: MysqlConnection,
+    conn: connection::RdbConnection,
 }
 
 #[automock]
 pub trait PostRepositoryTrait {
     fn find(&self, user_id: u64, post_id: u64) -> Result<Post, ServiceError>;
+    fn find_by_id(&self, post_id: u64) -> Result<Post, ServiceError>;
+    fn find_by_share_token(&self, share_token: &str) -> Result<Post, ServiceError>;
     fn find_all(&self, user_id: u64) -> Result<Vec<Post>, ServiceError>;
+    fn find_all_by_ids(
+        &self,
+        user_id: u64,
+        post_ids: &[u64],
+        published_until: &NaiveDateTime,
+    ) -> Result<Vec<Post>, ServiceError>;
+    fn find_all_by_ids_of_any_user(&self, post_ids: &[u64]) -> Result<Vec<Post>, ServiceError>;
     fn find_all_in_desc_date_order(&self, user_id: u64) -> Result<Vec<Post>, ServiceError>;
+    fn find_all_deleted(&self, user_id: u64) -> Result<Vec<Post>, ServiceError>;
+    fn find_all_drafts(&self, user_id: u64) -> Result<Vec<Post>, ServiceError>;
+    fn find_all_pinned(&self, user_id: u64, filter: &PostFilter)
+        -> Result<Vec<Post>, ServiceError>;
+    fn count_pinned(&self, user_id: u64) -> Result<u64, ServiceError>;
+    fn find_all_scheduled(
+        &self,
+        user_id: u64,
+        now: &NaiveDateTime,
+    ) -> Result<Vec<Post>, ServiceError>;
+    fn find_page_in_desc_date_order(
+        &self,
+        user_id: u64,
+        filter: &PostFilter,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<Post>, ServiceError>;
+    fn find_page_after(
+        &self,
+        user_id: u64,
+        filter: &PostFilter,
+        cursor: &PostCursor,
+        limit: u64,
+    ) -> Result<Vec<Post>, ServiceError>;
+    fn count(&self, user_id: u64, filter: &PostFilter) -> Result<u64, ServiceError>;
+    fn find_all_dates(
+        &self,
+        user_id: u64,
+        filter: &PostFilter,
+    ) -> Result<Vec<NaiveDateTime>, ServiceError>;
+    fn find_all_moods(&self, user_id: u64, filter: &PostFilter) -> Result<Vec<Mood>, ServiceError>;
+    #[allow(clippy::too_many_arguments)]
     fn create(
         &self,
         user_id: u64,
         title: &str,
         content: &str,
         date: &NaiveDateTime,
+        publish_at: &Option<NaiveDateTime>,
+        status: PostStatus,
+        mood: &Option<Mood>,
     ) -> Result<bool, ServiceError>;
+    fn create_all(&self, user_id: u64, posts: &[ImportedPost]) -> Result<u64, ServiceError>;
+    #[allow(clippy::too_many_arguments)]
     fn update(
         &self,
         user_id: u64,
         post_id: u64,
+        version: u64,
         title: &Option<String>,
         content: &Option<String>,
         date: &Option<NaiveDateTime>,
+        mood: &Option<Mood>,
+    ) -> Result<bool, ServiceError>;
+    fn publish(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError>;
+    fn pin(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError>;
+    fn unpin(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError>;
+    fn set_share_token(
+        &self,
+        user_id: u64,
+        post_id: u64,
+        share_token: &Option<String>,
+    ) -> Result<bool, ServiceError>;
+    fn set_category(
+        &self,
+        user_id: u64,
+        post_id: u64,
+        category_id: &Option<u64>,
     ) -> Result<bool, ServiceError>;
     fn delete(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError>;
+    fn bulk_delete(&self, user_id: u64, post_ids: &[u64]) -> Result<u64, ServiceError>;
+    fn restore(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError>;
+    fn purge(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError>;
 }
 
 impl PostRepository {
@@ -87,11 +442,12 @@ impl PostRepository {
         }
     }
 
-    /// Finds a post by user id and post id.
+    /// Finds a post by user id and post id, except a post in the trash.
     pub fn find(&self, user_id: u64, post_id: u64) -> Result<Post, ServiceError> {
         let post: Result<Post, Error> = dsl::posts
             .find(post_id)
             .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::deleted_at.is_null())
             .get_result::<Post>(&self.conn);
 
         match post {
@@ -105,6 +461,38 @@ impl PostRepository {
         }
     }
 
+    /// Finds a post by post id regardless of the user who wrote it.
+    pub fn find_by_id(&self, post_id: u64) -> Result<Post, ServiceError> {
+        let post: Result<Post, Error> = dsl::posts.find(post_id).get_result::<Post>(&self.conn);
+
+        match post {
+            Ok(post) => Ok(post),
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(
+                    post_id.to_string(),
+                ))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
+    /// Finds a post by the token of its public link.
+    pub fn find_by_share_token(&self, share_token: &str) -> Result<Post, ServiceError> {
+        let post: Result<Post, Error> = dsl::posts
+            .filter(dsl::share_token.eq(share_token))
+            .get_result::<Post>(&self.conn);
+
+        match post {
+            Ok(post) => Ok(post),
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(
+                    share_token.to_string(),
+                ))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
     /// Finds all post written by specific user.
     pub fn find_all(&self, user_id: u64) -> Result<Vec<Post>, ServiceError> {
         let post_list: Result<Vec<Post>, Error> = dsl::posts
@@ -117,10 +505,48 @@ impl PostRepository {
         }
     }
 
-    /// Finds all post written by specific user in desc date order.
+    /// Finds all post of the ids written by specific user, except posts in the trash and posts
+    /// scheduled to be published after `published_until`.
+    pub fn find_all_by_ids(
+        &self,
+        user_id: u64,
+        post_ids: &[u64],
+        published_until: &NaiveDateTime,
+    ) -> Result<Vec<Post>, ServiceError> {
+        let post_list: Result<Vec<Post>, Error> = dsl::posts
+            .filter(dsl::id.eq_any(post_ids))
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::deleted_at.is_null())
+            .filter(
+                dsl::publish_at
+                    .is_null()
+                    .or(dsl::publish_at.le(*published_until)),
+            )
+            .load::<Post>(&self.conn);
+
+        match post_list {
+            Ok(post_list) => Ok(post_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Finds all posts of the ids regardless of the writer, including posts in the trash.
+    pub fn find_all_by_ids_of_any_user(&self, post_ids: &[u64]) -> Result<Vec<Post>, ServiceError> {
+        let post_list: Result<Vec<Post>, Error> = dsl::posts
+            .filter(dsl::id.eq_any(post_ids))
+            .load::<Post>(&self.conn);
+
+        match post_list {
+            Ok(post_list) => Ok(post_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Finds all post written by specific user in desc date order, except posts in the trash.
     pub fn find_all_in_desc_date_order(&self, user_id: u64) -> Result<Vec<Post>, ServiceError> {
         let post_list: Result<Vec<Post>, Error> = dsl::posts
             .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::deleted_at.is_null())
             .order((dsl::date.desc(), dsl::id.desc()))
             .load::<Post>(&self.conn);
 
@@ -130,103 +556,1012 @@ impl PostRepository {
         }
     }
 
-    /// Creates a new post.
-    pub fn create(
-        &self,
-        user_id: u64,
-        title: &str,
-        content: &str,
-        date: &NaiveDateTime,
-    ) -> Result<bool, ServiceError> {
-        let post_to_create = PostDAO {
-            id: None,
-            user_id: Some(user_id),
-            title: Some(title.to_string()),
-            content: Some(content.to_string()),
-            date: Some(*date),
-            updated_at: None,
-        };
-
-        let count = diesel::insert_into(dsl::posts)
-            .values(post_to_create)
-            .execute(&self.conn);
+    /// Returns a query for published posts written by specific user, matching the filter.
+    /// Drafts and posts in the trash are excluded.
+    fn filter_posts(user_id: u64, filter: &PostFilter) -> posts::BoxedQuery<'static, Mysql> {
+        let PostFilter {
+            from,
+            to,
+            tags,
+            tag_mode,
+            category_id,
+            published_until,
+        } = filter;
 
-        if let Ok(count) = count {
-            if count > 0 {
-                Ok(true)
-            } else {
-                Err(get_service_error(ServiceError::QueryExecutionFailure))
+        let mut query = dsl::posts
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::status.eq(PostStatus::Published))
+            .filter(
+                dsl::publish_at
+                    .is_null()
+                    .or(dsl::publish_at.le(*published_until)),
+            )
+            .into_boxed();
+        if let Some(from) = from {
+            query = query.filter(dsl::date.ge(*from));
+        }
+        if let Some(to) = to {
+            query = query.filter(dsl::date.le(*to));
+        }
+        if let Some(category_id) = category_id {
+            query = query.filter(dsl::category_id.eq(*category_id));
+        }
+        if !tags.is_empty() {
+            match tag_mode {
+                TagMode::All => {
+                    for tag in tags {
+                        let tagged_post_ids = post_tags::table
+                            .select(post_tags::post_id)
+                            .filter(post_tags::tag.eq(tag.clone()));
+                        query = query.filter(dsl::id.eq_any(tagged_post_ids));
+                    }
+                }
+                TagMode::Any => {
+                    let tagged_post_ids = post_tags::table
+                        .select(post_tags::post_id)
+                        .filter(post_tags::tag.eq_any(tags.clone()));
+                    query = query.filter(dsl::id.eq_any(tagged_post_ids));
+                }
             }
-        } else {
-            Err(get_service_error(ServiceError::QueryExecutionFailure))
         }
+
+        query
     }
 
-    /// Updates a post written by specific user.
-    pub fn update(
-        &self,
+    /// Returns a query for a page of posts written by specific user in desc date order, which
+    /// reads only the rows of the page from the database with `LIMIT` and `OFFSET`.
+    /// It finds published posts matching the filter only, and pinned posts are excluded.
+    fn page_query(
         user_id: u64,
-        post_id: u64,
-        title: &Option<String>,
-        content: &Option<String>,
-        date: &Option<NaiveDateTime>,
-    ) -> Result<bool, ServiceError> {
-        let post_to_update = PostDAO {
-            id: Some(post_id),
-            user_id: None,
-            title: title.clone(),
-            content: content.clone(),
-            date: *date,
-            updated_at: Some(Utc::now().naive_utc()),
-        };
+        filter: &PostFilter,
+        limit: u64,
+        offset: u64,
+    ) -> posts::BoxedQuery<'static, Mysql> {
+        Self::filter_posts(user_id, filter)
+            .filter(dsl::pinned.eq(false))
+            .order((dsl::date.desc(), dsl::id.desc()))
+            .limit(limit as i64)
+            .offset(offset as i64)
+    }
 
-        let target_post = dsl::posts.find(post_id).filter(dsl::user_id.eq(user_id));
-        let count = diesel::update(target_post)
-            .set(post_to_update)
-            .execute(&self.conn);
+    /// Returns a query for a page of posts written by specific user after the cursor in desc date
+    /// order. It finds published posts matching the filter only, and pinned posts are excluded.
+    fn keyset_page_query(
+        user_id: u64,
+        filter: &PostFilter,
+        cursor: &PostCursor,
+        limit: u64,
+    ) -> posts::BoxedQuery<'static, Mysql> {
+        Self::filter_posts(user_id, filter)
+            .filter(dsl::pinned.eq(false))
+            .filter(
+                dsl::date
+                    .lt(cursor.date)
+                    .or(dsl::date.eq(cursor.date).and(dsl::id.lt(cursor.id))),
+            )
+            .order((dsl::date.desc(), dsl::id.desc()))
+            .limit(limit as i64)
+    }
 
-        match count {
-            Ok(count) => {
-                if count > 0 {
-                    Ok(true)
-                } else {
-                    Err(get_service_error(ServiceError::QueryExecutionFailure))
-                }
-            }
-            Err(error) => match error {
-                Error::NotFound => Err(get_service_error(ServiceError::NotFound(
-                    post_id.to_string(),
-                ))),
-                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
-            },
+    /// Finds all post in the trash written by specific user, the most recently deleted first.
+    pub fn find_all_deleted(&self, user_id: u64) -> Result<Vec<Post>, ServiceError> {
+        let post_list: Result<Vec<Post>, Error> = dsl::posts
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::deleted_at.is_not_null())
+            .order((dsl::deleted_at.desc(), dsl::id.desc()))
+            .load::<Post>(&self.conn);
+
+        match post_list {
+            Ok(post_list) => Ok(post_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
         }
     }
 
-    /// Deletes a post written by specific user.
-    pub fn delete(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError> {
-        let target_post = dsl::posts.find(post_id).filter(dsl::user_id.eq(user_id));
-        let count = diesel::delete(target_post).execute(&self.conn);
+    /// Finds all drafts written by specific user in desc date order, except drafts in the trash.
+    pub fn find_all_drafts(&self, user_id: u64) -> Result<Vec<Post>, ServiceError> {
+        let post_list: Result<Vec<Post>, Error> = dsl::posts
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::status.eq(PostStatus::Draft))
+            .order((dsl::date.desc(), dsl::id.desc()))
+            .load::<Post>(&self.conn);
 
-        match count {
-            Ok(count) => {
-                if count > 0 {
-                    Ok(true)
-                } else {
-                    Err(get_service_error(ServiceError::QueryExecutionFailure))
-                }
-            }
-            Err(error) => match error {
-                Error::NotFound => Err(get_service_error(ServiceError::NotFound(
-                    post_id.to_string(),
-                ))),
-                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
-            },
+        match post_list {
+            Ok(post_list) => Ok(post_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
         }
     }
-}
 
-impl Default for PostRepository {
-    fn default() -> Self {
-        Self::new()
+    /// Finds all post written by specific user, which will be published after now.
+    /// The post to be published first comes first. Drafts are excluded.
+    pub fn find_all_scheduled(
+        &self,
+        user_id: u64,
+        now: &NaiveDateTime,
+    ) -> Result<Vec<Post>, ServiceError> {
+        let post_list: Result<Vec<Post>, Error> = dsl::posts
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::status.eq(PostStatus::Published))
+            .filter(dsl::publish_at.gt(*now))
+            .order((dsl::publish_at.asc(), dsl::id.asc()))
+            .load::<Post>(&self.conn);
+
+        match post_list {
+            Ok(post_list) => Ok(post_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Finds posts written by specific user in desc date order.
+    /// It finds published posts matching the filter only, and pinned posts are excluded.
+    /// It skips `offset` posts, and finds up to `limit` posts.
+    pub fn find_page_in_desc_date_order(
+        &self,
+        user_id: u64,
+        filter: &PostFilter,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<Post>, ServiceError> {
+        let post_list: Result<Vec<Post>, Error> =
+            Self::page_query(user_id, filter, limit, offset).load::<Post>(&self.conn);
+
+        match post_list {
+            Ok(post_list) => Ok(post_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Finds posts written by specific user after the cursor in desc date order.
+    /// It finds published posts matching the filter only, and pinned posts are excluded.
+    /// It finds up to `limit` posts with a keyset query, which doesn't skip rows by offset.
+    pub fn find_page_after(
+        &self,
+        user_id: u64,
+        filter: &PostFilter,
+        cursor: &PostCursor,
+        limit: u64,
+    ) -> Result<Vec<Post>, ServiceError> {
+        let post_list: Result<Vec<Post>, Error> =
+            Self::keyset_page_query(user_id, filter, cursor, limit).load::<Post>(&self.conn);
+
+        match post_list {
+            Ok(post_list) => Ok(post_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Finds pinned posts written by specific user.
+    /// It finds published posts matching the filter only.
+    pub fn find_all_pinned(
+        &self,
+        user_id: u64,
+        filter: &PostFilter,
+    ) -> Result<Vec<Post>, ServiceError> {
+        let post_list: Result<Vec<Post>, Error> = Self::filter_posts(user_id, filter)
+            .filter(dsl::pinned.eq(true))
+            .load::<Post>(&self.conn);
+
+        match post_list {
+            Ok(post_list) => Ok(post_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Counts pinned posts written by specific user, except posts in the trash.
+    pub fn count_pinned(&self, user_id: u64) -> Result<u64, ServiceError> {
+        let count: Result<i64, Error> = dsl::posts
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::pinned.eq(true))
+            .count()
+            .get_result(&self.conn);
+
+        match count {
+            Ok(count) => Ok(count as u64),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Counts posts written by specific user.
+    /// It counts published posts matching the filter only.
+    pub fn count(&self, user_id: u64, filter: &PostFilter) -> Result<u64, ServiceError> {
+        let count: Result<i64, Error> = Self::filter_posts(user_id, filter)
+            .count()
+            .get_result(&self.conn);
+
+        match count {
+            Ok(count) => Ok(count as u64),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Finds dates of all posts written by specific user.
+    /// It finds dates of published posts matching the filter only.
+    pub fn find_all_dates(
+        &self,
+        user_id: u64,
+        filter: &PostFilter,
+    ) -> Result<Vec<NaiveDateTime>, ServiceError> {
+        let date_list: Result<Vec<NaiveDateTime>, Error> = Self::filter_posts(user_id, filter)
+            .select(dsl::date)
+            .load::<NaiveDateTime>(&self.conn);
+
+        match date_list {
+            Ok(date_list) => Ok(date_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Finds moods tagged to all posts written by specific user.
+    /// It finds moods of published posts matching the filter only, and skips untagged posts.
+    pub fn find_all_moods(
+        &self,
+        user_id: u64,
+        filter: &PostFilter,
+    ) -> Result<Vec<Mood>, ServiceError> {
+        let mood_list: Result<Vec<Option<Mood>>, Error> = Self::filter_posts(user_id, filter)
+            .filter(dsl::mood.is_not_null())
+            .select(dsl::mood)
+            .load::<Option<Mood>>(&self.conn);
+
+        match mood_list {
+            Ok(mood_list) => Ok(mood_list.into_iter().flatten().collect()),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Creates a new post of the status.
+    /// The post is not published until `publish_at`, if it is given.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create(
+        &self,
+        user_id: u64,
+        title: &str,
+        content: &str,
+        date: &NaiveDateTime,
+        publish_at: &Option<NaiveDateTime>,
+        status: PostStatus,
+        mood: &Option<Mood>,
+    ) -> Result<bool, ServiceError> {
+        let post_to_create = PostDAO {
+            id: None,
+            user_id: Some(user_id),
+            title: Some(title.to_string()),
+            content: Some(content.to_string()),
+            date: Some(*date),
+            updated_at: None,
+            publish_at: *publish_at,
+            status: Some(status),
+            mood: *mood,
+        };
+
+        let count = diesel::insert_into(dsl::posts)
+            .values(post_to_create)
+            .execute(&self.conn);
+
+        if let Ok(count) = count {
+            if count > 0 {
+                Ok(true)
+            } else {
+                Err(get_service_error(ServiceError::QueryExecutionFailure))
+            }
+        } else {
+            Err(get_service_error(ServiceError::QueryExecutionFailure))
+        }
+    }
+
+    /// Creates all the imported posts at once.
+    /// A post without the creation time is regarded as created now.
+    pub fn create_all(&self, user_id: u64, posts: &[ImportedPost]) -> Result<u64, ServiceError> {
+        let now = Utc::now().naive_utc();
+        let posts_to_create: Vec<ImportedPostDAO> = posts
+            .iter()
+            .map(|post| ImportedPostDAO {
+                user_id,
+                title: post.title.clone(),
+                content: post.content.clone(),
+                date: post.date,
+                created_at: post.created_at.unwrap_or(now),
+                updated_at: post.updated_at,
+                deleted_at: post.deleted_at,
+                publish_at: post.publish_at,
+            })
+            .collect();
+
+        let count = diesel::insert_into(dsl::posts)
+            .values(&posts_to_create)
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => Ok(count as u64),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Returns the changes of a post updating only the given columns and the update time.
+    /// A column of `None` is left out of the `SET` clause, rather than set to `NULL`.
+    fn post_to_update(
+        post_id: u64,
+        title: &Option<String>,
+        content: &Option<String>,
+        date: &Option<NaiveDateTime>,
+        mood: &Option<Mood>,
+    ) -> PostDAO {
+        PostDAO {
+            id: Some(post_id),
+            user_id: None,
+            title: title.clone(),
+            content: content.clone(),
+            date: *date,
+            updated_at: Some(Utc::now().naive_utc()),
+            publish_at: None,
+            status: None,
+            mood: *mood,
+        }
+    }
+
+    /// Updates the given columns of a post written by specific user, only if the post is of the
+    /// version, and increments the version. The other columns are left untouched.
+    /// Returns `ServiceError::Conflict` if no post is updated, as the post may have been updated
+    /// by another request.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update(
+        &self,
+        user_id: u64,
+        post_id: u64,
+        version: u64,
+        title: &Option<String>,
+        content: &Option<String>,
+        date: &Option<NaiveDateTime>,
+        mood: &Option<Mood>,
+    ) -> Result<bool, ServiceError> {
+        let post_to_update = Self::post_to_update(post_id, title, content, date, mood);
+
+        let target_post = dsl::posts
+            .find(post_id)
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::version.eq(version));
+        let count = diesel::update(target_post)
+            .set((post_to_update, dsl::version.eq(version + 1)))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::Conflict(
+                        post_id.to_string(),
+                    )))
+                }
+            }
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(
+                    post_id.to_string(),
+                ))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
+    /// Publishes a draft written by specific user.
+    pub fn publish(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError> {
+        let target_post = dsl::posts
+            .find(post_id)
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::status.eq(PostStatus::Draft));
+        let count = diesel::update(target_post)
+            .set((
+                dsl::status.eq(PostStatus::Published),
+                dsl::updated_at.eq(Some(Utc::now().naive_utc())),
+            ))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::NotFound(
+                        post_id.to_string(),
+                    )))
+                }
+            }
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Pins a post written by specific user, or unpins it.
+    /// The pin time is set to now when it is pinned, and cleared when it is unpinned.
+    fn set_pinned(&self, user_id: u64, post_id: u64, pinned: bool) -> Result<bool, ServiceError> {
+        let pinned_at = some_if_true!(pinned => Utc::now().naive_utc());
+        let target_post = dsl::posts.find(post_id).filter(dsl::user_id.eq(user_id));
+        let count = diesel::update(target_post)
+            .set((dsl::pinned.eq(pinned), dsl::pinned_at.eq(pinned_at)))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::NotFound(
+                        post_id.to_string(),
+                    )))
+                }
+            }
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Pins a post written by specific user to the top of the list.
+    pub fn pin(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError> {
+        self.set_pinned(user_id, post_id, true)
+    }
+
+    /// Unpins a post written by specific user.
+    pub fn unpin(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError> {
+        self.set_pinned(user_id, post_id, false)
+    }
+
+    /// Sets the token of the public link of a post written by specific user.
+    /// The post is not shared anymore if it is `None`.
+    pub fn set_share_token(
+        &self,
+        user_id: u64,
+        post_id: u64,
+        share_token: &Option<String>,
+    ) -> Result<bool, ServiceError> {
+        let target_post = dsl::posts.find(post_id).filter(dsl::user_id.eq(user_id));
+        let count = diesel::update(target_post)
+            .set(dsl::share_token.eq(share_token))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::NotFound(
+                        post_id.to_string(),
+                    )))
+                }
+            }
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Sets the category of a post written by specific user.
+    /// The post doesn't belong to any category if it is `None`.
+    pub fn set_category(
+        &self,
+        user_id: u64,
+        post_id: u64,
+        category_id: &Option<u64>,
+    ) -> Result<bool, ServiceError> {
+        let target_post = dsl::posts.find(post_id).filter(dsl::user_id.eq(user_id));
+        let count = diesel::update(target_post)
+            .set(dsl::category_id.eq(category_id))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::NotFound(
+                        post_id.to_string(),
+                    )))
+                }
+            }
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Moves a post written by specific user to the trash.
+    pub fn delete(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError> {
+        let target_post = dsl::posts
+            .find(post_id)
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::deleted_at.is_null());
+        let count = diesel::update(target_post)
+            .set(dsl::deleted_at.eq(Some(Utc::now().naive_utc())))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::QueryExecutionFailure))
+                }
+            }
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(
+                    post_id.to_string(),
+                ))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
+    /// Moves all posts of the ids written by specific user to the trash in a transaction.
+    /// Nothing is moved and `ServiceError::Conflict` is returned if any of the posts is not moved,
+    /// as it may have been deleted by another request.
+    pub fn bulk_delete(&self, user_id: u64, post_ids: &[u64]) -> Result<u64, ServiceError> {
+        connection::with_transaction(&self.conn, || {
+            let target_posts = dsl::posts
+                .filter(dsl::id.eq_any(post_ids))
+                .filter(dsl::user_id.eq(user_id))
+                .filter(dsl::deleted_at.is_null());
+            let count = diesel::update(target_posts)
+                .set(dsl::deleted_at.eq(Some(Utc::now().naive_utc())))
+                .execute(&self.conn)?;
+
+            if count == post_ids.len() {
+                Ok(count as u64)
+            } else {
+                Err(ServiceError::Conflict(user_id.to_string()))
+            }
+        })
+        .map_err(get_service_error)
+    }
+
+    /// Restores a post written by specific user from the trash.
+    pub fn restore(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError> {
+        let target_post = dsl::posts
+            .find(post_id)
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::deleted_at.is_not_null());
+        let count = diesel::update(target_post)
+            .set(dsl::deleted_at.eq(None::<NaiveDateTime>))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::QueryExecutionFailure))
+                }
+            }
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(
+                    post_id.to_string(),
+                ))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
+    /// Deletes a post written by specific user permanently.
+    pub fn purge(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError> {
+        let target_post = dsl::posts.find(post_id).filter(dsl::user_id.eq(user_id));
+        let count = diesel::delete(target_post).execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::QueryExecutionFailure))
+                }
+            }
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(
+                    post_id.to_string(),
+                ))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+}
+
+impl PostRepositoryTrait for PostRepository {
+    fn find(&self, user_id: u64, post_id: u64) -> Result<Post, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find", None);
+        PostRepository::find(self, user_id, post_id)
+    }
+
+    fn find_by_id(&self, post_id: u64) -> Result<Post, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find_by_id", None);
+        PostRepository::find_by_id(self, post_id)
+    }
+
+    fn find_by_share_token(&self, share_token: &str) -> Result<Post, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find_by_share_token", None);
+        PostRepository::find_by_share_token(self, share_token)
+    }
+
+    fn find_all(&self, user_id: u64) -> Result<Vec<Post>, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find_all", None);
+        PostRepository::find_all(self, user_id)
+    }
+
+    fn find_all_by_ids(
+        &self,
+        user_id: u64,
+        post_ids: &[u64],
+        published_until: &NaiveDateTime,
+    ) -> Result<Vec<Post>, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find_all_by_ids", None);
+        PostRepository::find_all_by_ids(self, user_id, post_ids, published_until)
+    }
+
+    fn find_all_by_ids_of_any_user(&self, post_ids: &[u64]) -> Result<Vec<Post>, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find_all_by_ids_of_any_user", None);
+        PostRepository::find_all_by_ids_of_any_user(self, post_ids)
+    }
+
+    fn find_all_in_desc_date_order(&self, user_id: u64) -> Result<Vec<Post>, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find_all_in_desc_date_order", None);
+        PostRepository::find_all_in_desc_date_order(self, user_id)
+    }
+
+    fn find_all_deleted(&self, user_id: u64) -> Result<Vec<Post>, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find_all_deleted", None);
+        PostRepository::find_all_deleted(self, user_id)
+    }
+
+    fn find_all_drafts(&self, user_id: u64) -> Result<Vec<Post>, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find_all_drafts", None);
+        PostRepository::find_all_drafts(self, user_id)
+    }
+
+    fn find_all_pinned(
+        &self,
+        user_id: u64,
+        filter: &PostFilter,
+    ) -> Result<Vec<Post>, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find_all_pinned", None);
+        PostRepository::find_all_pinned(self, user_id, filter)
+    }
+
+    fn count_pinned(&self, user_id: u64) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::count_pinned", None);
+        PostRepository::count_pinned(self, user_id)
+    }
+
+    fn find_all_scheduled(
+        &self,
+        user_id: u64,
+        now: &NaiveDateTime,
+    ) -> Result<Vec<Post>, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find_all_scheduled", None);
+        PostRepository::find_all_scheduled(self, user_id, now)
+    }
+
+    fn find_page_in_desc_date_order(
+        &self,
+        user_id: u64,
+        filter: &PostFilter,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<Post>, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find_page_in_desc_date_order", None);
+        PostRepository::find_page_in_desc_date_order(self, user_id, filter, limit, offset)
+    }
+
+    fn find_page_after(
+        &self,
+        user_id: u64,
+        filter: &PostFilter,
+        cursor: &PostCursor,
+        limit: u64,
+    ) -> Result<Vec<Post>, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find_page_after", None);
+        PostRepository::find_page_after(self, user_id, filter, cursor, limit)
+    }
+
+    fn count(&self, user_id: u64, filter: &PostFilter) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::count", None);
+        PostRepository::count(self, user_id, filter)
+    }
+
+    fn find_all_dates(
+        &self,
+        user_id: u64,
+        filter: &PostFilter,
+    ) -> Result<Vec<NaiveDateTime>, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find_all_dates", None);
+        PostRepository::find_all_dates(self, user_id, filter)
+    }
+
+    fn find_all_moods(&self, user_id: u64, filter: &PostFilter) -> Result<Vec<Mood>, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::find_all_moods", None);
+        PostRepository::find_all_moods(self, user_id, filter)
+    }
+
+    fn create(
+        &self,
+        user_id: u64,
+        title: &str,
+        content: &str,
+        date: &NaiveDateTime,
+        publish_at: &Option<NaiveDateTime>,
+        status: PostStatus,
+        mood: &Option<Mood>,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::create", None);
+        PostRepository::create(
+            self, user_id, title, content, date, publish_at, status, mood,
+        )
+    }
+
+    fn create_all(&self, user_id: u64, posts: &[ImportedPost]) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::create_all", None);
+        PostRepository::create_all(self, user_id, posts)
+    }
+
+    fn update(
+        &self,
+        user_id: u64,
+        post_id: u64,
+        version: u64,
+        title: &Option<String>,
+        content: &Option<String>,
+        date: &Option<NaiveDateTime>,
+        mood: &Option<Mood>,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::update", None);
+        PostRepository::update(self, user_id, post_id, version, title, content, date, mood)
+    }
+
+    fn publish(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::publish", None);
+        PostRepository::publish(self, user_id, post_id)
+    }
+
+    fn pin(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::pin", None);
+        PostRepository::pin(self, user_id, post_id)
+    }
+
+    fn unpin(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::unpin", None);
+        PostRepository::unpin(self, user_id, post_id)
+    }
+
+    fn set_share_token(
+        &self,
+        user_id: u64,
+        post_id: u64,
+        share_token: &Option<String>,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::set_share_token", None);
+        PostRepository::set_share_token(self, user_id, post_id, share_token)
+    }
+
+    fn set_category(
+        &self,
+        user_id: u64,
+        post_id: u64,
+        category_id: &Option<u64>,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::set_category", None);
+        PostRepository::set_category(self, user_id, post_id, category_id)
+    }
+
+    fn delete(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::delete", None);
+        PostRepository::delete(self, user_id, post_id)
+    }
+
+    fn bulk_delete(&self, user_id: u64, post_ids: &[u64]) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::bulk_delete", None);
+        PostRepository::bulk_delete(self, user_id, post_ids)
+    }
+
+    fn restore(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::restore", None);
+        PostRepository::restore(self, user_id, post_id)
+    }
+
+    fn purge(&self, user_id: u64, post_id: u64) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("PostRepository::purge", None);
+        PostRepository::purge(self, user_id, post_id)
+    }
+}
+
+impl Default for PostRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A core data repository for the number of posts created by a user in the current window.
+pub struct PostCreationCountRepository {
+    key: String,
+    client: redis::Connection,
+}
+
+#[automock]
+pub trait PostCreationCountRepositoryTrait {
+    fn new(user_id: u64) -> Self;
+    fn find(&mut self) -> Result<u64, ServiceError>;
+    fn increase(&mut self, ttl_seconds: usize) -> Result<u64, ServiceError>;
+}
+
+impl PostCreationCountRepository {
+    /// Creates a new post creation count repository.
+    pub fn new(user_id: u64) -> Self {
+        Self {
+            key: format!("post_creation_count:{}", user_id),
+            client: connection::connect_redis(),
+        }
+    }
+
+    /// Finds the number of posts created in the current window.
+    pub fn find(&mut self) -> Result<u64, ServiceError> {
+        match self.client.get::<&str, Option<u64>>(&self.key) {
+            Ok(count) => Ok(count.unwrap_or(0)),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Increases the number of posts created in the current window and returns it.
+    /// The window starts at the first creation, and the count expires after `ttl_seconds`.
+    pub fn increase(&mut self, ttl_seconds: usize) -> Result<u64, ServiceError> {
+        let result: Result<u64, RedisError> = self.client.incr::<&str, u64, _>(&self.key, 1);
+        match result {
+            Ok(count) => {
+                if count > 1 {
+                    return Ok(count);
+                }
+
+                match self.client.expire::<&str, bool>(&self.key, ttl_seconds) {
+                    Ok(_) => Ok(count),
+                    Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+                }
+            }
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl PostCreationCountRepositoryTrait for PostCreationCountRepository {
+    fn new(user_id: u64) -> Self {
+        PostCreationCountRepository::new(user_id)
+    }
+
+    fn find(&mut self) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("PostCreationCountRepository::find", None);
+        PostCreationCountRepository::find(self)
+    }
+
+    fn increase(&mut self, ttl_seconds: usize) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("PostCreationCountRepository::increase", None);
+        PostCreationCountRepository::increase(self, ttl_seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::debug_query;
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn get_filter() -> PostFilter {
+        PostFilter {
+            from: None,
+            to: None,
+            tags: vec![],
+            tag_mode: TagMode::Any,
+            category_id: None,
+            published_until: Utc::now().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn test_page_query_reads_only_page() {
+        let query = PostRepository::page_query(5, &get_filter(), 20, 40);
+        let sql = debug_query::<Mysql, _>(&query).to_string();
+
+        assert!(sql.contains("LIMIT ? OFFSET ?"));
+        assert!(sql.ends_with(", 20, 40]"));
+    }
+
+    #[test]
+    fn test_keyset_page_query_reads_only_page() {
+        let cursor = PostCursor {
+            date: NaiveDateTime::from_str("2020-06-01T09:30:00").unwrap(),
+            id: 3,
+        };
+        let query = PostRepository::keyset_page_query(5, &get_filter(), &cursor, 20);
+        let sql = debug_query::<Mysql, _>(&query).to_string();
+
+        assert!(sql.contains("(`posts`.`date` < ? OR `posts`.`date` = ? AND `posts`.`id` < ?)"));
+        assert!(sql.contains("LIMIT ?"));
+        assert!(!sql.contains("OFFSET"));
+    }
+
+    #[test]
+    fn test_encode_and_decode_cursor() {
+        let cursor = PostCursor {
+            date: NaiveDateTime::from_str("2020-06-01T09:30:00.123").unwrap(),
+            id: 3,
+        };
+        let encoded_cursor = cursor.encode();
+
+        assert!(encoded_cursor.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_eq!(PostCursor::decode(&encoded_cursor), Some(cursor));
+        assert_eq!(PostCursor::decode("not a cursor"), None);
+        assert_eq!(
+            PostCursor::decode(&base32::encode(CURSOR_ALPHABET, b"3")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_update_query_sets_only_given_columns() {
+        let post_to_update = PostRepository::post_to_update(
+            3,
+            &Some(String::from("New title")),
+            &None,
+            &None,
+            &None,
+        );
+        let query = diesel::update(dsl::posts.find(3)).set(post_to_update);
+        let sql = debug_query::<Mysql, _>(&query).to_string();
+
+        assert!(sql.contains("`title` = ?"));
+        assert!(sql.contains("`updated_at` = ?"));
+        assert!(!sql.contains("`content`"));
+        assert!(!sql.contains("`date`"));
+        assert!(!sql.contains("`mood`"));
+    }
+
+    #[test]
+    fn test_page_query_filters_category() {
+        let filter = PostFilter {
+            category_id: Some(7),
+            ..get_filter()
+        };
+        let query = PostRepository::page_query(5, &filter, 20, 0);
+        let sql = debug_query::<Mysql, _>(&query).to_string();
+
+        assert!(sql.contains("`posts`.`category_id` = ?"));
+        assert!(!sql.contains("`post_tags`"));
+    }
+
+    #[test]
+    fn test_page_query_filters_all_tags() {
+        let filter = PostFilter {
+            tags: vec![String::from("travel"), String::from("food")],
+            tag_mode: TagMode::All,
+            ..get_filter()
+        };
+        let query = PostRepository::page_query(5, &filter, 20, 0);
+        let sql = debug_query::<Mysql, _>(&query).to_string();
+
+        assert_eq!(sql.matches("FROM `post_tags`").count(), 2);
+        assert!(sql.contains("\"travel\""));
+        assert!(sql.contains("\"food\""));
+    }
+
+    #[test]
+    fn test_page_query_filters_any_tags() {
+        let filter = PostFilter {
+            tags: vec![String::from("travel"), String::from("food")],
+            tag_mode: TagMode::Any,
+            ..get_filter()
+        };
+        let query = PostRepository::page_query(5, &filter, 20, 0);
+        let sql = debug_query::<Mysql, _>(&query).to_string();
+
+        assert_eq!(sql.matches("FROM `post_tags`").count(), 1);
+        assert!(sql.contains("`post_tags`.`tag` IN (?, ?)"));
+    }
+
+    #[test]
+    fn test_count_query_is_not_paginated() {
+        let query = PostRepository::filter_posts(5, &get_filter()).count();
+        let sql = debug_query::<Mysql, _>(&query).to_string();
+
+        assert!(sql.starts_with("SELECT COUNT(*) FROM `posts`"));
+        assert!(!sql.contains("LIMIT"));
     }
 }
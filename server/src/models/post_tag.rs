@@ -0,0 +1,168 @@
+use diesel::mysql::Mysql;
+use diesel::prelude::*;
+use diesel::result::Error;
+use diesel::sql_types::Bool;
+use mockall::automock;
+use serde::{Deserialize, Serialize};
+
+use crate::models::connection;
+use crate::models::error::{get_service_error, ServiceError};
+use crate::schema::{post_tags, post_tags::dsl, posts};
+use crate::utils::trace_util;
+
+/// Post tag representing `post_tags` table.
+#[derive(Debug, Serialize, Deserialize, Queryable)]
+pub struct PostTag {
+    pub id: u64,
+    pub post_id: u64,
+    pub tag: String,
+}
+
+/// Post tag DAO using between models layer and RDB.
+#[derive(Insertable)]
+#[table_name = "post_tags"]
+struct PostTagDAO {
+    post_id: u64,
+    tag: String,
+}
+
+/// A core data repository for post tag.
+pub struct PostTagRepository {
+    conn: connection::RdbConnection,
+}
+
+#[automock]
+pub trait PostTagRepositoryTrait {
+    fn find_all_by_post_ids(&self, post_ids: &[u64]) -> Result<Vec<PostTag>, ServiceError>;
+    fn find_all_matching(
+        &self,
+        user_id: u64,
+        terms: &[String],
+    ) -> Result<Vec<PostTag>, ServiceError>;
+    fn replace(&self, post_id: u64, tags: &[String]) -> Result<bool, ServiceError>;
+}
+
+/// Escapes wildcard characters of `LIKE` pattern, so that the term is matched literally.
+fn escape_like_pattern(term: &str) -> String {
+    term.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+impl PostTagRepository {
+    /// Creates a new post tag repository.
+    pub fn new() -> Self {
+        Self {
+            conn: connection::connect_rdb(),
+        }
+    }
+
+    /// Finds all tags of the posts.
+    pub fn find_all_by_post_ids(&self, post_ids: &[u64]) -> Result<Vec<PostTag>, ServiceError> {
+        let post_tag_list: Result<Vec<PostTag>, Error> = dsl::post_tags
+            .filter(dsl::post_id.eq_any(post_ids))
+            .order(dsl::id.asc())
+            .load::<PostTag>(&self.conn);
+
+        match post_tag_list {
+            Ok(post_tag_list) => Ok(post_tag_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Finds all tags starting with any of the terms, of posts written by specific user.
+    /// Posts in the trash are excluded.
+    pub fn find_all_matching(
+        &self,
+        user_id: u64,
+        terms: &[String],
+    ) -> Result<Vec<PostTag>, ServiceError> {
+        let condition = terms.iter().fold(
+            Box::new(false.into_sql::<Bool>())
+                as Box<dyn BoxableExpression<post_tags::table, Mysql, SqlType = Bool>>,
+            |condition, term| {
+                let pattern = format!("{}%", escape_like_pattern(term));
+                Box::new(condition.or(dsl::tag.like(pattern)))
+            },
+        );
+        let user_post_ids = posts::table
+            .select(posts::id)
+            .filter(posts::user_id.eq(user_id))
+            .filter(posts::deleted_at.is_null());
+
+        let post_tag_list: Result<Vec<PostTag>, Error> = dsl::post_tags
+            .filter(dsl::post_id.eq_any(user_post_ids))
+            .filter(condition)
+            .load::<PostTag>(&self.conn);
+
+        match post_tag_list {
+            Ok(post_tag_list) => Ok(post_tag_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Replaces all tags of the post with the given tags.
+    pub fn replace(&self, post_id: u64, tags: &[String]) -> Result<bool, ServiceError> {
+        let post_tags_to_create: Vec<PostTagDAO> = tags
+            .iter()
+            .map(|tag| PostTagDAO {
+                post_id,
+                tag: tag.clone(),
+            })
+            .collect();
+
+        let result = self.conn.transaction::<_, Error, _>(|| {
+            diesel::delete(dsl::post_tags.filter(dsl::post_id.eq(post_id))).execute(&self.conn)?;
+            if !post_tags_to_create.is_empty() {
+                diesel::insert_into(dsl::post_tags)
+                    .values(&post_tags_to_create)
+                    .execute(&self.conn)?;
+            }
+            Ok(())
+        });
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl PostTagRepositoryTrait for PostTagRepository {
+    fn find_all_by_post_ids(&self, post_ids: &[u64]) -> Result<Vec<PostTag>, ServiceError> {
+        let _span = trace_util::start_span("PostTagRepository::find_all_by_post_ids", None);
+        PostTagRepository::find_all_by_post_ids(self, post_ids)
+    }
+
+    fn find_all_matching(
+        &self,
+        user_id: u64,
+        terms: &[String],
+    ) -> Result<Vec<PostTag>, ServiceError> {
+        let _span = trace_util::start_span("PostTagRepository::find_all_matching", None);
+        PostTagRepository::find_all_matching(self, user_id, terms)
+    }
+
+    fn replace(&self, post_id: u64, tags: &[String]) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("PostTagRepository::replace", None);
+        PostTagRepository::replace(self, post_id, tags)
+    }
+}
+
+impl Default for PostTagRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_like_pattern() {
+        assert_eq!(escape_like_pattern("travel"), "travel");
+        assert_eq!(escape_like_pattern("100%_off"), "100\\%\\_off");
+        assert_eq!(escape_like_pattern("a\\b"), "a\\\\b");
+    }
+}
@@ -0,0 +1,73 @@
+use mockall::automock;
+use reqwest::Client;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use crate::models::error::{get_service_error, ServiceError};
+use crate::utils::{env_util, trace_util};
+
+/// A future resolving to a response body of the range API.
+pub type RangeFuture = Pin<Box<dyn Future<Output = Result<String, ServiceError>>>>;
+
+/// A core data repository for the Pwned Passwords range API of Have I Been Pwned.
+pub struct PwnedPasswordRepository {
+    client: Client,
+    api_url: String,
+}
+
+#[automock]
+pub trait PwnedPasswordRepositoryTrait {
+    fn find_range(&self, hash_prefix: &str) -> RangeFuture;
+}
+
+impl PwnedPasswordRepository {
+    /// Creates a new pwned password repository.
+    pub fn new() -> Self {
+        let timeout_seconds = env_util::get_env_var_or("PWNED_PASSWORDS_TIMEOUT_SECONDS", 3);
+        Self {
+            client: Client::builder()
+                .timeout(Duration::from_secs(timeout_seconds))
+                .build()
+                .unwrap_or_else(|_| Client::new()),
+            api_url: env_util::get_env_var_or(
+                "PWNED_PASSWORDS_API_URL",
+                String::from("https://api.pwnedpasswords.com"),
+            ),
+        }
+    }
+
+    /// Finds suffixes of the breached SHA-1 hashes starting with the prefix.
+    /// Only the first 5 hex characters of the hash are sent to the API.
+    ///
+    /// Each line of the response body is formatted as `SUFFIX:COUNT`.
+    pub fn find_range(&self, hash_prefix: &str) -> RangeFuture {
+        let request = self
+            .client
+            .get(&format!("{}/range/{}", self.api_url, hash_prefix))
+            .send();
+
+        Box::pin(async move {
+            match request.await {
+                Ok(response) if response.status().is_success() => match response.text().await {
+                    Ok(body) => Ok(body),
+                    Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+                },
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            }
+        })
+    }
+}
+
+impl PwnedPasswordRepositoryTrait for PwnedPasswordRepository {
+    fn find_range(&self, hash_prefix: &str) -> RangeFuture {
+        let _span = trace_util::start_span("PwnedPasswordRepository::find_range", None);
+        PwnedPasswordRepository::find_range(self, hash_prefix)
+    }
+}
+
+impl Default for PwnedPasswordRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
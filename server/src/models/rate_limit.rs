@@ -0,0 +1,152 @@
+use chrono::NaiveDateTime;
+use lazy_static::lazy_static;
+use mockall::automock;
+use redis::{Commands, Script};
+use serde::{Deserialize, Serialize};
+
+use crate::models::connection;
+use crate::models::error::{get_service_error, ServiceError};
+use crate::utils::{env_util, trace_util};
+
+/// A rule of the token bucket rate limiter. A bucket holds up to `capacity` tokens, each request
+/// takes one of them, and the bucket is refilled with `refill_per_minute` tokens per minute.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimitRule {
+    pub capacity: u64,
+    pub refill_per_minute: u64,
+}
+
+impl RateLimitRule {
+    /// Reads the rule from `{prefix}_CAPACITY` and `{prefix}_REFILL_PER_MINUTE`.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - A prefix of the environment variables such as `RATE_LIMIT_AUTH`
+    /// * `default` - A fallback rule for the missing variables
+    pub fn from_env(prefix: &str, default: RateLimitRule) -> Self {
+        Self {
+            capacity: env_util::get_env_var_or(&format!("{}_CAPACITY", prefix), default.capacity),
+            refill_per_minute: env_util::get_env_var_or(
+                &format!("{}_REFILL_PER_MINUTE", prefix),
+                default.refill_per_minute,
+            ),
+        }
+    }
+
+    /// Returns the number of seconds to refill the empty bucket, after which an idle bucket is the
+    /// same as a new one and doesn't have to be stored.
+    pub fn get_refill_seconds(&self) -> usize {
+        if self.refill_per_minute == 0 {
+            return 0;
+        }
+        (self.capacity as f64 * 60.0 / self.refill_per_minute as f64).ceil() as usize
+    }
+}
+
+lazy_static! {
+    /// A script setting the bucket only if it is still the one found, which makes the
+    /// read-modify-write of the bucket atomic. A missing bucket is passed as an empty string.
+    static ref COMPARE_AND_SET_SCRIPT: Script = Script::new(
+        r"
+        if (redis.call('GET', KEYS[1]) or '') ~= ARGV[1] then
+            return 0
+        end
+        redis.call('SET', KEYS[1], ARGV[2], 'EX', ARGV[3])
+        return 1
+        ",
+    );
+}
+
+/// Token bucket of a client that represents data in redis.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TokenBucket {
+    pub tokens: f64,
+    pub updated_at: NaiveDateTime,
+}
+
+/// A core data repository for token buckets of rate limiting.
+pub struct RateLimitRepository {
+    key: String,
+    client: connection::PooledRedisConnection,
+    found_bucket: Option<String>,
+}
+
+#[automock]
+pub trait RateLimitRepositoryTrait {
+    fn try_new(scope: &str, client_ip: &str) -> Option<Self>
+    where
+        Self: Sized;
+    fn find(&mut self) -> Result<Option<TokenBucket>, ServiceError>;
+    fn save(&mut self, bucket: &TokenBucket, ttl_seconds: usize) -> Result<bool, ServiceError>;
+}
+
+impl RateLimitRepository {
+    /// Creates a new rate limit repository for the client in the scope of routes, or returns
+    /// `None` if no pooled redis connection is available within the timeout.
+    pub fn try_new(scope: &str, client_ip: &str) -> Option<Self> {
+        Some(Self {
+            key: format!("rate_limit:{}:{}", scope, client_ip),
+            client: connection::try_connect_pooled_redis()?,
+            found_bucket: None,
+        })
+    }
+
+    /// Finds the token bucket of the client.
+    /// The bucket which has been idle until it is refilled is treated as missing.
+    pub fn find(&mut self) -> Result<Option<TokenBucket>, ServiceError> {
+        let found_bucket = match self.client.get::<&str, Option<String>>(&self.key) {
+            Ok(found_bucket) => found_bucket,
+            Err(_) => return Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        };
+        self.found_bucket = found_bucket.clone();
+
+        match found_bucket {
+            Some(serialized_bucket) => match serde_json::from_str(&serialized_bucket) {
+                Ok(bucket) => Ok(Some(bucket)),
+                Err(_) => Err(get_service_error(ServiceError::InvalidFormat)),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Saves the token bucket of the client, which expires after `ttl_seconds`.
+    /// It returns `false` without saving if the bucket has been changed since it was found.
+    pub fn save(&mut self, bucket: &TokenBucket, ttl_seconds: usize) -> Result<bool, ServiceError> {
+        let serialized_bucket = if let Ok(serialized_bucket) = serde_json::to_string(bucket) {
+            serialized_bucket
+        } else {
+            return Err(get_service_error(ServiceError::InvalidFormat));
+        };
+
+        let result = COMPARE_AND_SET_SCRIPT
+            .key(&self.key)
+            .arg(self.found_bucket.as_deref().unwrap_or(""))
+            .arg(&serialized_bucket)
+            .arg(ttl_seconds.max(1))
+            .invoke::<i32>(&mut *self.client);
+        match result {
+            Ok(1) => {
+                self.found_bucket = Some(serialized_bucket);
+                Ok(true)
+            }
+            Ok(_) => Ok(false),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl RateLimitRepositoryTrait for RateLimitRepository {
+    fn try_new(scope: &str, client_ip: &str) -> Option<Self> {
+        RateLimitRepository::try_new(scope, client_ip)
+    }
+
+    fn find(&mut self) -> Result<Option<TokenBucket>, ServiceError> {
+        let _span = trace_util::start_span("RateLimitRepository::find", None);
+        RateLimitRepository::find(self)
+    }
+
+    fn save(&mut self, bucket: &TokenBucket, ttl_seconds: usize) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("RateLimitRepository::save", None);
+        RateLimitRepository::save(self, bucket, ttl_seconds)
+    }
+}
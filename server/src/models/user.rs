@@ -1,15 +1,27 @@
-use chrono::{NaiveDateTime, Utc};
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use diesel::deserialize::{self, FromSql};
+use diesel::mysql::Mysql;
 use diesel::prelude::*;
-use diesel::result::Error;
+use diesel::result::{DatabaseErrorKind, Error};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
 use mockall::automock;
 use serde::{Deserialize, Serialize};
+use std::io::Write;
 
+use crate::models::auth::SignUpToken;
 use crate::models::connection;
 use crate::models::error::{get_service_error, ServiceError};
-use crate::schema::{users, users::dsl};
+use crate::models::post::Post;
+use crate::models::user_key::UserKeyDAO;
+use crate::schema::{
+    backup_codes, categories, password_history, posts, user_api_keys, user_keys, users, users::dsl,
+    webhook_deliveries, webhooks,
+};
+use crate::utils::trace_util;
 
 /// User representing `users` table.
-#[derive(Debug, Serialize, Deserialize, Queryable)]
+#[derive(Clone, Debug, Serialize, Deserialize, Queryable)]
 pub struct User {
     pub id: u64,
     pub name: String,
@@ -18,6 +30,55 @@ pub struct User {
     pub avatar_url: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: Option<NaiveDateTime>,
+    pub locked_until: Option<NaiveDateTime>,
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
+    pub has_password: bool,
+    pub last_login_at: Option<NaiveDateTime>,
+    pub previous_login_at: Option<NaiveDateTime>,
+    pub timezone: Option<String>,
+    pub reminder_enabled: bool,
+    pub reminder_hour: u8,
+    pub reminder_sent_on: Option<NaiveDate>,
+    pub locale: Option<String>,
+    pub status: UserStatus,
+    /// A version of the terms of service the user has accepted last.
+    pub tos_version_accepted: Option<String>,
+}
+
+/// Status of a user. A deactivated user can't log in and isn't reminded, but the posts of the
+/// user are kept until the user is reactivated.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum UserStatus {
+    Active,
+    Deactivated,
+}
+
+impl UserStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            UserStatus::Active => "active",
+            UserStatus::Deactivated => "deactivated",
+        }
+    }
+}
+
+impl ToSql<Text, Mysql> for UserStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Mysql>) -> serialize::Result {
+        <str as ToSql<Text, Mysql>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Mysql> for UserStatus {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <String as FromSql<Text, Mysql>>::from_sql(bytes)?.as_str() {
+            "active" => Ok(UserStatus::Active),
+            "deactivated" => Ok(UserStatus::Deactivated),
+            status => Err(format!("unknown user status `{}`", status).into()),
+        }
+    }
 }
 
 /// User DTO using between routes layer and service layer.
@@ -29,6 +90,28 @@ pub struct UserDTO {
     pub avatar_url: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: Option<NaiveDateTime>,
+    pub last_login_at: Option<NaiveDateTime>,
+    pub previous_login_at: Option<NaiveDateTime>,
+    pub timezone: Option<String>,
+    pub reminder_enabled: bool,
+    pub reminder_hour: u8,
+    pub locale: Option<String>,
+}
+
+/// Profile of a user which can be updated by the user.
+#[derive(Serialize, Deserialize)]
+pub struct UserProfileDTO {
+    pub name: String,
+    pub avatar_url: Option<String>,
+}
+
+/// Personal data of a user to be exported.
+/// Sensitive internal fields such as the password hash and the TOTP secret are excluded.
+#[derive(Serialize, Deserialize)]
+pub struct PersonalDataDTO {
+    pub profile: UserDTO,
+    pub public_key: Option<String>,
+    pub posts: Vec<Post>,
 }
 
 /// User DAO using between models layer and RDB.
@@ -41,6 +124,10 @@ struct UserDAO {
     password: Option<String>,
     avatar_url: Option<String>,
     updated_at: Option<NaiveDateTime>,
+    has_password: Option<bool>,
+    timezone: Option<String>,
+    locale: Option<String>,
+    tos_version_accepted: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -50,7 +137,7 @@ pub struct ReCaptchaResponse {
 
 /// A core data repository for user.
 pub struct UserRepository {
-    conn: MysqlConnection,
+    conn: connection::RdbConnection,
 }
 
 #[automock]
@@ -66,14 +153,56 @@ pub trait UserRepositoryTrait {
         password: &str,
         avatar_url: &Option<String>,
     ) -> Result<bool, ServiceError>;
+    fn create_with_key(
+        &self,
+        token: &SignUpToken,
+        public_key: &str,
+        tos_version_accepted: &str,
+    ) -> Result<u64, ServiceError>;
+    fn create_without_password(
+        &self,
+        name: &str,
+        email: &str,
+        avatar_url: &Option<String>,
+    ) -> Result<bool, ServiceError>;
     fn update(
         &self,
         id: u64,
         name: &Option<String>,
         password: &Option<String>,
         avatar_url: &Option<String>,
+        timezone: &Option<String>,
+    ) -> Result<bool, ServiceError>;
+    fn update_email(&self, id: u64, email: &str) -> Result<bool, ServiceError>;
+    fn update_locked_until(
+        &self,
+        id: u64,
+        locked_until: &Option<NaiveDateTime>,
     ) -> Result<bool, ServiceError>;
+    fn update_last_login_at(
+        &self,
+        id: u64,
+        last_login_at: &NaiveDateTime,
+    ) -> Result<bool, ServiceError>;
+    fn update_totp(
+        &self,
+        id: u64,
+        totp_secret: &Option<String>,
+        totp_enabled: bool,
+    ) -> Result<bool, ServiceError>;
+    fn find_all_reminded(&self) -> Result<Vec<User>, ServiceError>;
+    fn update_reminder(
+        &self,
+        id: u64,
+        reminder_enabled: bool,
+        reminder_hour: u8,
+    ) -> Result<bool, ServiceError>;
+    fn update_locale(&self, id: u64, locale: &Option<String>) -> Result<bool, ServiceError>;
+    fn update_reminder_sent_on(&self, id: u64, sent_on: &NaiveDate) -> Result<bool, ServiceError>;
+    fn update_status(&self, id: u64, status: UserStatus) -> Result<bool, ServiceError>;
+    fn update_tos_version_accepted(&self, id: u64, version: &str) -> Result<bool, ServiceError>;
     fn delete(&self, id: u64) -> Result<bool, ServiceError>;
+    fn delete_account(&self, id: u64) -> Result<bool, ServiceError>;
 }
 
 impl UserRepository {
@@ -157,6 +286,10 @@ impl UserRepository {
             password: Some(password.to_string()),
             avatar_url: avatar_url.clone(),
             updated_at: None,
+            has_password: None,
+            timezone: None,
+            locale: None,
+            tos_version_accepted: None,
         };
 
         let count = diesel::insert_into(dsl::users)
@@ -174,13 +307,100 @@ impl UserRepository {
         }
     }
 
+    /// Creates a new user of the sign up token with the public key in a transaction,
+    /// and returns the id of the user. Neither of them is created if any of the writes fails.
+    /// The version of the terms of service accepted on sign up is recorded with the user.
+    pub fn create_with_key(
+        &self,
+        token: &SignUpToken,
+        public_key: &str,
+        tos_version_accepted: &str,
+    ) -> Result<u64, ServiceError> {
+        let user_to_create = UserDAO {
+            id: None,
+            name: Some(token.name.clone()),
+            email: Some(token.email.clone()),
+            password: Some(token.password.clone()),
+            avatar_url: token.avatar_url.clone(),
+            updated_at: None,
+            has_password: None,
+            timezone: None,
+            locale: token.locale.clone(),
+            tos_version_accepted: Some(tos_version_accepted.to_string()),
+        };
+
+        connection::with_transaction(&self.conn, || {
+            // Another sign up of the same email may finish between the sign up token is set and
+            // now, which is rejected by the unique index of the email.
+            diesel::insert_into(dsl::users)
+                .values(user_to_create)
+                .execute(&self.conn)
+                .map_err(|error| match error {
+                    Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                        ServiceError::Conflict(token.email.clone())
+                    }
+                    error => ServiceError::from(error),
+                })?;
+
+            let user_id = dsl::users
+                .filter(dsl::email.eq(&token.email))
+                .select(dsl::id)
+                .get_result::<u64>(&self.conn)?;
+
+            let user_key_to_create = UserKeyDAO {
+                user_id,
+                public_key: public_key.to_string(),
+                updated_at: None,
+            };
+            diesel::insert_into(user_keys::table)
+                .values(user_key_to_create)
+                .execute(&self.conn)?;
+
+            Ok(user_id)
+        })
+        .map_err(get_service_error)
+    }
+
+    /// Creates a new user who doesn't have a local password, such as the user signed up via OAuth.
+    /// The password is left empty so that it never matches any password.
+    pub fn create_without_password(
+        &self,
+        name: &str,
+        email: &str,
+        avatar_url: &Option<String>,
+    ) -> Result<bool, ServiceError> {
+        let user_to_create = UserDAO {
+            id: None,
+            name: Some(name.to_string()),
+            email: Some(email.to_string()),
+            password: Some(String::new()),
+            avatar_url: avatar_url.clone(),
+            updated_at: None,
+            has_password: Some(false),
+            timezone: None,
+            locale: None,
+            tos_version_accepted: None,
+        };
+
+        let count = diesel::insert_into(dsl::users)
+            .values(user_to_create)
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) if count > 0 => Ok(true),
+            _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
     /// Updates a new user.
+    /// If the password is updated, the user is marked as having a local password.
     pub fn update(
         &self,
         id: u64,
         name: &Option<String>,
         password: &Option<String>,
         avatar_url: &Option<String>,
+        timezone: &Option<String>,
     ) -> Result<bool, ServiceError> {
         let user_to_update = UserDAO {
             id: Some(id),
@@ -189,6 +409,10 @@ impl UserRepository {
             password: password.clone(),
             avatar_url: avatar_url.clone(),
             updated_at: Some(Utc::now().naive_utc()),
+            has_password: password.as_ref().map(|_| true),
+            timezone: timezone.clone(),
+            locale: None,
+            tos_version_accepted: None,
         };
 
         let target_user = dsl::users.find(id);
@@ -211,6 +435,237 @@ impl UserRepository {
         }
     }
 
+    /// Updates the email of the user.
+    pub fn update_email(&self, id: u64, email: &str) -> Result<bool, ServiceError> {
+        let target_user = dsl::users.find(id);
+        let count = diesel::update(target_user)
+            .set((
+                dsl::email.eq(email),
+                dsl::updated_at.eq(Some(Utc::now().naive_utc())),
+            ))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::QueryExecutionFailure))
+                }
+            }
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(id.to_string()))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
+    /// Updates a time until which the user is locked.
+    pub fn update_locked_until(
+        &self,
+        id: u64,
+        locked_until: &Option<NaiveDateTime>,
+    ) -> Result<bool, ServiceError> {
+        let target_user = dsl::users.find(id);
+        let count = diesel::update(target_user)
+            .set(dsl::locked_until.eq(*locked_until))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::QueryExecutionFailure))
+                }
+            }
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(id.to_string()))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
+    /// Updates a time when the user logged in.
+    /// The time of the previous login is kept as `previous_login_at`.
+    pub fn update_last_login_at(
+        &self,
+        id: u64,
+        last_login_at: &NaiveDateTime,
+    ) -> Result<bool, ServiceError> {
+        let target_user = dsl::users.find(id);
+        // MySQL assigns columns from left to right, so `previous_login_at` takes the value before the update.
+        let count = diesel::update(target_user)
+            .set((
+                dsl::previous_login_at.eq(dsl::last_login_at),
+                dsl::last_login_at.eq(Some(*last_login_at)),
+            ))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::QueryExecutionFailure))
+                }
+            }
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(id.to_string()))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
+    /// Updates a TOTP secret of the user and whether the TOTP is enabled.
+    pub fn update_totp(
+        &self,
+        id: u64,
+        totp_secret: &Option<String>,
+        totp_enabled: bool,
+    ) -> Result<bool, ServiceError> {
+        let target_user = dsl::users.find(id);
+        let count = diesel::update(target_user)
+            .set((
+                dsl::totp_secret.eq(totp_secret.clone()),
+                dsl::totp_enabled.eq(totp_enabled),
+            ))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::QueryExecutionFailure))
+                }
+            }
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(id.to_string()))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
+    /// Finds all active users who opted in to the daily reminder.
+    pub fn find_all_reminded(&self) -> Result<Vec<User>, ServiceError> {
+        let user_list: Result<Vec<User>, Error> = dsl::users
+            .filter(dsl::reminder_enabled.eq(true))
+            .filter(dsl::status.eq(UserStatus::Active))
+            .load::<User>(&self.conn);
+
+        match user_list {
+            Ok(user_list) => Ok(user_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Updates whether the user is reminded daily, and the hour to be reminded.
+    pub fn update_reminder(
+        &self,
+        id: u64,
+        reminder_enabled: bool,
+        reminder_hour: u8,
+    ) -> Result<bool, ServiceError> {
+        let target_user = dsl::users.find(id);
+        let count = diesel::update(target_user)
+            .set((
+                dsl::reminder_enabled.eq(reminder_enabled),
+                dsl::reminder_hour.eq(reminder_hour),
+            ))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::QueryExecutionFailure))
+                }
+            }
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(id.to_string()))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
+    /// Updates the locale of the user, such as `ko`. It is removed if it is `None`.
+    pub fn update_locale(&self, id: u64, locale: &Option<String>) -> Result<bool, ServiceError> {
+        let target_user = dsl::users.find(id);
+        let count = diesel::update(target_user)
+            .set(dsl::locale.eq(locale))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::QueryExecutionFailure))
+                }
+            }
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(id.to_string()))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
+    /// Records that the reminder of the day has been sent to the user.
+    /// Returns `false` if it has already been recorded on or after the day, so that the reminder
+    /// is sent at most once a day even if multiple jobs run at the same time.
+    pub fn update_reminder_sent_on(
+        &self,
+        id: u64,
+        sent_on: &NaiveDate,
+    ) -> Result<bool, ServiceError> {
+        let target_user = dsl::users.find(id).filter(
+            dsl::reminder_sent_on
+                .is_null()
+                .or(dsl::reminder_sent_on.lt(*sent_on)),
+        );
+        let count = diesel::update(target_user)
+            .set(dsl::reminder_sent_on.eq(Some(*sent_on)))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => Ok(count > 0),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Updates the status of the user.
+    /// Returns `false` if the user doesn't exist or already has the status.
+    pub fn update_status(&self, id: u64, status: UserStatus) -> Result<bool, ServiceError> {
+        let target_user = dsl::users.find(id);
+        let count = diesel::update(target_user)
+            .set(dsl::status.eq(status))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => Ok(count > 0),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Updates the version of the terms of service the user has accepted.
+    pub fn update_tos_version_accepted(
+        &self,
+        id: u64,
+        version: &str,
+    ) -> Result<bool, ServiceError> {
+        let target_user = dsl::users.find(id);
+        let count = diesel::update(target_user)
+            .set(dsl::tos_version_accepted.eq(Some(version)))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => Ok(count > 0),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
     /// Deletes a user.
     pub fn delete(&self, id: u64) -> Result<bool, ServiceError> {
         let target_user = dsl::users.find(id);
@@ -231,6 +686,186 @@ impl UserRepository {
             },
         }
     }
+
+    /// Deletes the user with all data of the user in a transaction: the posts with their tags,
+    /// the categories, the public key, the backup codes, the password history, the API keys, and
+    /// the webhooks with their deliveries.
+    /// Nothing is deleted if any of the deletions fails.
+    pub fn delete_account(&self, id: u64) -> Result<bool, ServiceError> {
+        connection::with_transaction(&self.conn, || {
+            diesel::delete(posts::table.filter(posts::user_id.eq(id))).execute(&self.conn)?;
+            diesel::delete(categories::table.filter(categories::user_id.eq(id)))
+                .execute(&self.conn)?;
+            diesel::delete(user_keys::table.filter(user_keys::user_id.eq(id)))
+                .execute(&self.conn)?;
+            diesel::delete(backup_codes::table.filter(backup_codes::user_id.eq(id)))
+                .execute(&self.conn)?;
+            diesel::delete(password_history::table.filter(password_history::user_id.eq(id)))
+                .execute(&self.conn)?;
+            diesel::delete(user_api_keys::table.filter(user_api_keys::user_id.eq(id)))
+                .execute(&self.conn)?;
+            let webhook_ids = webhooks::table
+                .select(webhooks::id)
+                .filter(webhooks::user_id.eq(id));
+            diesel::delete(
+                webhook_deliveries::table
+                    .filter(webhook_deliveries::webhook_id.eq_any(webhook_ids)),
+            )
+            .execute(&self.conn)?;
+            diesel::delete(webhooks::table.filter(webhooks::user_id.eq(id))).execute(&self.conn)?;
+
+            let count = diesel::delete(dsl::users.find(id)).execute(&self.conn)?;
+            if count > 0 {
+                Ok(true)
+            } else {
+                Err(ServiceError::NotFound(id.to_string()))
+            }
+        })
+        .map_err(get_service_error)
+    }
+}
+
+impl UserRepositoryTrait for UserRepository {
+    fn find_by_id(&self, id: u64) -> Result<User, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::find_by_id", None);
+        UserRepository::find_by_id(self, id)
+    }
+
+    fn find_by_email(&self, email: &str) -> Result<User, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::find_by_email", None);
+        UserRepository::find_by_email(self, email)
+    }
+
+    fn find_password_by_email(&self, email: &str) -> Result<String, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::find_password_by_email", None);
+        UserRepository::find_password_by_email(self, email)
+    }
+
+    fn find_all(&self) -> Result<Vec<User>, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::find_all", None);
+        UserRepository::find_all(self)
+    }
+
+    fn create(
+        &self,
+        name: &str,
+        email: &str,
+        password: &str,
+        avatar_url: &Option<String>,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::create", None);
+        UserRepository::create(self, name, email, password, avatar_url)
+    }
+
+    fn create_with_key(
+        &self,
+        token: &SignUpToken,
+        public_key: &str,
+        tos_version_accepted: &str,
+    ) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::create_with_key", None);
+        UserRepository::create_with_key(self, token, public_key, tos_version_accepted)
+    }
+
+    fn create_without_password(
+        &self,
+        name: &str,
+        email: &str,
+        avatar_url: &Option<String>,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::create_without_password", None);
+        UserRepository::create_without_password(self, name, email, avatar_url)
+    }
+
+    fn update(
+        &self,
+        id: u64,
+        name: &Option<String>,
+        password: &Option<String>,
+        avatar_url: &Option<String>,
+        timezone: &Option<String>,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::update", None);
+        UserRepository::update(self, id, name, password, avatar_url, timezone)
+    }
+
+    fn update_email(&self, id: u64, email: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::update_email", None);
+        UserRepository::update_email(self, id, email)
+    }
+
+    fn update_locked_until(
+        &self,
+        id: u64,
+        locked_until: &Option<NaiveDateTime>,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::update_locked_until", None);
+        UserRepository::update_locked_until(self, id, locked_until)
+    }
+
+    fn update_last_login_at(
+        &self,
+        id: u64,
+        last_login_at: &NaiveDateTime,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::update_last_login_at", None);
+        UserRepository::update_last_login_at(self, id, last_login_at)
+    }
+
+    fn update_totp(
+        &self,
+        id: u64,
+        totp_secret: &Option<String>,
+        totp_enabled: bool,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::update_totp", None);
+        UserRepository::update_totp(self, id, totp_secret, totp_enabled)
+    }
+
+    fn find_all_reminded(&self) -> Result<Vec<User>, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::find_all_reminded", None);
+        UserRepository::find_all_reminded(self)
+    }
+
+    fn update_reminder(
+        &self,
+        id: u64,
+        reminder_enabled: bool,
+        reminder_hour: u8,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::update_reminder", None);
+        UserRepository::update_reminder(self, id, reminder_enabled, reminder_hour)
+    }
+
+    fn update_locale(&self, id: u64, locale: &Option<String>) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::update_locale", None);
+        UserRepository::update_locale(self, id, locale)
+    }
+
+    fn update_reminder_sent_on(&self, id: u64, sent_on: &NaiveDate) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::update_reminder_sent_on", None);
+        UserRepository::update_reminder_sent_on(self, id, sent_on)
+    }
+
+    fn update_status(&self, id: u64, status: UserStatus) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::update_status", None);
+        UserRepository::update_status(self, id, status)
+    }
+
+    fn update_tos_version_accepted(&self, id: u64, version: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::update_tos_version_accepted", None);
+        UserRepository::update_tos_version_accepted(self, id, version)
+    }
+
+    fn delete(&self, id: u64) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::delete", None);
+        UserRepository::delete(self, id)
+    }
+
+    fn delete_account(&self, id: u64) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserRepository::delete_account", None);
+        UserRepository::delete_account(self, id)
+    }
 }
 
 impl Default for UserRepository {
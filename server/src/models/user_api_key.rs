@@ -0,0 +1,163 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::Error;
+use mockall::automock;
+use serde::{Deserialize, Serialize};
+
+use crate::models::connection;
+use crate::models::error::{get_service_error, ServiceError};
+use crate::schema::{user_api_keys, user_api_keys::dsl};
+use crate::utils::trace_util;
+
+/// User API key representing `user_api_keys` table.
+/// The key is `{prefix}.{secret}`, and only the hash of the secret is stored.
+/// The prefix is stored as it is to find the key.
+#[derive(Clone, Debug, Serialize, Deserialize, Queryable)]
+pub struct UserApiKey {
+    pub id: u64,
+    pub user_id: u64,
+    pub prefix: String,
+    pub hashed_secret: String,
+    pub expires_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+/// User API key DAO using between models layer and RDB.
+#[derive(Insertable)]
+#[table_name = "user_api_keys"]
+struct UserApiKeyDAO {
+    user_id: u64,
+    prefix: String,
+    hashed_secret: String,
+    expires_at: Option<NaiveDateTime>,
+}
+
+/// A newly created user API key. It is the only time the key is shown to the user.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedUserApiKey {
+    pub id: u64,
+    pub key: String,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// A core data repository for user API key.
+pub struct UserApiKeyRepository {
+    conn: connection::RdbConnection,
+}
+
+#[automock]
+pub trait UserApiKeyRepositoryTrait {
+    fn find_by_prefix(&self, prefix: &str) -> Result<UserApiKey, ServiceError>;
+    fn create(
+        &self,
+        user_id: u64,
+        prefix: &str,
+        hashed_secret: &str,
+        expires_at: &Option<NaiveDateTime>,
+    ) -> Result<bool, ServiceError>;
+    fn delete(&self, id: u64, user_id: u64) -> Result<bool, ServiceError>;
+}
+
+impl UserApiKeyRepository {
+    /// Creates a new user API key repository.
+    pub fn new() -> Self {
+        Self {
+            conn: connection::connect_rdb(),
+        }
+    }
+
+    /// Finds a user API key by prefix.
+    pub fn find_by_prefix(&self, prefix: &str) -> Result<UserApiKey, ServiceError> {
+        let user_api_key: Result<UserApiKey, Error> = dsl::user_api_keys
+            .filter(dsl::prefix.eq(prefix))
+            .get_result::<UserApiKey>(&self.conn);
+
+        match user_api_key {
+            Ok(user_api_key) => Ok(user_api_key),
+            Err(error) => match error {
+                Error::NotFound => Err(get_service_error(ServiceError::NotFound(
+                    prefix.to_string(),
+                ))),
+                _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+            },
+        }
+    }
+
+    /// Creates a new user API key.
+    pub fn create(
+        &self,
+        user_id: u64,
+        prefix: &str,
+        hashed_secret: &str,
+        expires_at: &Option<NaiveDateTime>,
+    ) -> Result<bool, ServiceError> {
+        let user_api_key_to_create = UserApiKeyDAO {
+            user_id,
+            prefix: prefix.to_string(),
+            hashed_secret: hashed_secret.to_string(),
+            expires_at: *expires_at,
+        };
+
+        let count = diesel::insert_into(dsl::user_api_keys)
+            .values(user_api_key_to_create)
+            .execute(&self.conn);
+
+        if let Ok(count) = count {
+            if count > 0 {
+                Ok(true)
+            } else {
+                Err(get_service_error(ServiceError::QueryExecutionFailure))
+            }
+        } else {
+            Err(get_service_error(ServiceError::QueryExecutionFailure))
+        }
+    }
+
+    /// Deletes the user API key of the user.
+    pub fn delete(&self, id: u64, user_id: u64) -> Result<bool, ServiceError> {
+        let target_user_api_key = dsl::user_api_keys
+            .filter(dsl::id.eq(id))
+            .filter(dsl::user_id.eq(user_id));
+        let count = diesel::delete(target_user_api_key).execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::NotFound(id.to_string())))
+                }
+            }
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl UserApiKeyRepositoryTrait for UserApiKeyRepository {
+    fn find_by_prefix(&self, prefix: &str) -> Result<UserApiKey, ServiceError> {
+        let _span = trace_util::start_span("UserApiKeyRepository::find_by_prefix", None);
+        UserApiKeyRepository::find_by_prefix(self, prefix)
+    }
+
+    fn create(
+        &self,
+        user_id: u64,
+        prefix: &str,
+        hashed_secret: &str,
+        expires_at: &Option<NaiveDateTime>,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserApiKeyRepository::create", None);
+        UserApiKeyRepository::create(self, user_id, prefix, hashed_secret, expires_at)
+    }
+
+    fn delete(&self, id: u64, user_id: u64) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserApiKeyRepository::delete", None);
+        UserApiKeyRepository::delete(self, id, user_id)
+    }
+}
+
+impl Default for UserApiKeyRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
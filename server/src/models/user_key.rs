@@ -7,17 +7,30 @@ use serde::{Deserialize, Serialize};
 use crate::models::connection;
 use crate::models::error::{get_service_error, ServiceError};
 use crate::schema::{user_keys, user_keys::dsl};
+use crate::utils::trace_util;
 
 /// User key representing `user_keys` table.
 /// One user must have only one public key.
 /// This key is known to both client and server.
-#[derive(Debug, Serialize, Deserialize, Queryable)]
+/// While the key is being rotated, the previous public key is also kept.
+#[derive(Clone, Debug, Serialize, Deserialize, Queryable)]
 pub struct UserKey {
     pub id: u64,
     pub user_id: u64,
     pub public_key: String,
     pub created_at: NaiveDateTime,
     pub updated_at: Option<NaiveDateTime>,
+    pub previous_public_key: Option<String>,
+    pub rotated_at: Option<NaiveDateTime>,
+}
+
+/// User key DTO using between routes layer and service layer.
+#[derive(Serialize, Deserialize)]
+pub struct UserKeyDTO {
+    pub public_key: String,
+    /// The public key before rotation. It is available only during the grace period of rotation.
+    pub previous_public_key: Option<String>,
+    pub rotated_at: Option<NaiveDateTime>,
 }
 
 /// User DAO using between models layer and RDB.
@@ -31,13 +44,20 @@ pub struct UserKeyDAO {
 
 /// A core data repository for user key.
 pub struct UserKeyRepository {
-    conn: MysqlConnection,
+    conn: connection::RdbConnection,
 }
 
 #[automock]
 pub trait UserKeyRepositoryTrait {
     fn find_by_user_id(&self, user_id: u64) -> Result<UserKey, ServiceError>;
     fn create(&self, user_id: u64, public_key: &str) -> Result<bool, ServiceError>;
+    fn rotate(
+        &self,
+        user_id: u64,
+        public_key: &str,
+        rotated_at: &NaiveDateTime,
+    ) -> Result<bool, ServiceError>;
+    fn delete_previous(&self, user_id: u64) -> Result<bool, ServiceError>;
 }
 
 impl UserKeyRepository {
@@ -87,6 +107,79 @@ impl UserKeyRepository {
             Err(get_service_error(ServiceError::QueryExecutionFailure))
         }
     }
+
+    /// Replaces the public key of the user with a new one, keeping the current key as the previous
+    /// public key.
+    pub fn rotate(
+        &self,
+        user_id: u64,
+        public_key: &str,
+        rotated_at: &NaiveDateTime,
+    ) -> Result<bool, ServiceError> {
+        let target_user_key = dsl::user_keys.filter(dsl::user_id.eq(user_id));
+        // MySQL assigns columns from left to right, so the previous public key is the current one.
+        let count = diesel::update(target_user_key)
+            .set((
+                dsl::previous_public_key.eq(dsl::public_key.nullable()),
+                dsl::public_key.eq(public_key),
+                dsl::rotated_at.eq(Some(*rotated_at)),
+                dsl::updated_at.eq(Some(*rotated_at)),
+            ))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Err(get_service_error(ServiceError::NotFound(
+                        user_id.to_string(),
+                    )))
+                }
+            }
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Deletes the previous public key of the user, which is kept while the key is being rotated.
+    pub fn delete_previous(&self, user_id: u64) -> Result<bool, ServiceError> {
+        let target_user_key = dsl::user_keys.filter(dsl::user_id.eq(user_id));
+        let count = diesel::update(target_user_key)
+            .set(dsl::previous_public_key.eq(None::<String>))
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) => Ok(count > 0),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl UserKeyRepositoryTrait for UserKeyRepository {
+    fn find_by_user_id(&self, user_id: u64) -> Result<UserKey, ServiceError> {
+        let _span = trace_util::start_span("UserKeyRepository::find_by_user_id", None);
+        UserKeyRepository::find_by_user_id(self, user_id)
+    }
+
+    fn create(&self, user_id: u64, public_key: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserKeyRepository::create", None);
+        UserKeyRepository::create(self, user_id, public_key)
+    }
+
+    fn rotate(
+        &self,
+        user_id: u64,
+        public_key: &str,
+        rotated_at: &NaiveDateTime,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserKeyRepository::rotate", None);
+        UserKeyRepository::rotate(self, user_id, public_key, rotated_at)
+    }
+
+    fn delete_previous(&self, user_id: u64) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("UserKeyRepository::delete_previous", None);
+        UserKeyRepository::delete_previous(self, user_id)
+    }
 }
 
 impl Default for UserKeyRepository {
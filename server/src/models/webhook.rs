@@ -0,0 +1,226 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::result::Error;
+use mockall::automock;
+use serde::{Deserialize, Serialize};
+
+use crate::models::connection;
+use crate::models::error::{get_service_error, ServiceError};
+use crate::schema::{webhook_deliveries, webhooks};
+use crate::utils::trace_util;
+
+/// Webhook representing `webhooks` table.
+/// Events of the user are posted to the URL, signed with the secret.
+#[derive(Clone, Debug, Serialize, Deserialize, Queryable)]
+pub struct Webhook {
+    pub id: u64,
+    pub user_id: u64,
+    pub url: String,
+    pub secret: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Webhook DAO using between models layer and RDB.
+#[derive(Insertable)]
+#[table_name = "webhooks"]
+struct WebhookDAO {
+    user_id: u64,
+    url: String,
+    secret: String,
+}
+
+/// Webhook DTO using between routes layer and service layer. The secret is not included.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookDTO {
+    pub id: u64,
+    pub url: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// A newly registered webhook. It is the only time the secret is shown to the user.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisteredWebhook {
+    pub id: u64,
+    pub url: String,
+    pub secret: String,
+}
+
+/// Webhook delivery representing `webhook_deliveries` table.
+/// It is a log of an attempt to deliver an event to the webhook.
+#[derive(Debug, Serialize, Deserialize, Queryable)]
+pub struct WebhookDelivery {
+    pub id: u64,
+    pub webhook_id: u64,
+    pub event: String,
+    pub payload: String,
+    pub attempt: u32,
+    pub status_code: Option<u16>,
+    pub is_success: bool,
+    pub created_at: NaiveDateTime,
+}
+
+/// Webhook delivery DAO using between models layer and RDB.
+#[derive(Debug, PartialEq, Insertable)]
+#[table_name = "webhook_deliveries"]
+pub struct WebhookDeliveryDAO {
+    pub webhook_id: u64,
+    pub event: String,
+    pub payload: String,
+    pub attempt: u32,
+    pub status_code: Option<u16>,
+    pub is_success: bool,
+}
+
+/// A core data repository for webhook and its deliveries.
+pub struct WebhookRepository {
+    conn: connection::RdbConnection,
+}
+
+#[automock]
+pub trait WebhookRepositoryTrait {
+    fn find_all_by_user_id(&self, user_id: u64) -> Result<Vec<Webhook>, ServiceError>;
+    fn create(&self, user_id: u64, url: &str, secret: &str) -> Result<u64, ServiceError>;
+    fn delete(&self, id: u64, user_id: u64) -> Result<bool, ServiceError>;
+    fn find_deliveries_by_webhook_id(
+        &self,
+        webhook_id: u64,
+        limit: usize,
+    ) -> Result<Vec<WebhookDelivery>, ServiceError>;
+    fn create_delivery(&self, delivery: &WebhookDeliveryDAO) -> Result<bool, ServiceError>;
+}
+
+impl WebhookRepository {
+    /// Creates a new webhook repository.
+    pub fn new() -> Self {
+        Self {
+            conn: connection::connect_rdb(),
+        }
+    }
+
+    /// Finds all webhooks of the user.
+    pub fn find_all_by_user_id(&self, user_id: u64) -> Result<Vec<Webhook>, ServiceError> {
+        let webhook_list: Result<Vec<Webhook>, Error> = webhooks::table
+            .filter(webhooks::user_id.eq(user_id))
+            .order(webhooks::id.asc())
+            .load::<Webhook>(&self.conn);
+
+        match webhook_list {
+            Ok(webhook_list) => Ok(webhook_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Creates a new webhook, and returns id of the created webhook.
+    pub fn create(&self, user_id: u64, url: &str, secret: &str) -> Result<u64, ServiceError> {
+        let webhook_to_create = WebhookDAO {
+            user_id,
+            url: url.to_string(),
+            secret: secret.to_string(),
+        };
+
+        connection::with_transaction(&self.conn, || {
+            diesel::insert_into(webhooks::table)
+                .values(webhook_to_create)
+                .execute(&self.conn)?;
+
+            let id = webhooks::table
+                .select(webhooks::id)
+                .filter(webhooks::user_id.eq(user_id))
+                .order(webhooks::id.desc())
+                .first::<u64>(&self.conn)?;
+            Ok(id)
+        })
+        .map_err(get_service_error)
+    }
+
+    /// Deletes the webhook of the user with its deliveries.
+    pub fn delete(&self, id: u64, user_id: u64) -> Result<bool, ServiceError> {
+        connection::with_transaction(&self.conn, || {
+            let target_webhook = webhooks::table
+                .filter(webhooks::id.eq(id))
+                .filter(webhooks::user_id.eq(user_id));
+            if target_webhook
+                .select(webhooks::id)
+                .first::<u64>(&self.conn)
+                .optional()?
+                .is_none()
+            {
+                return Err(ServiceError::NotFound(id.to_string()));
+            }
+
+            diesel::delete(webhook_deliveries::table.filter(webhook_deliveries::webhook_id.eq(id)))
+                .execute(&self.conn)?;
+            diesel::delete(target_webhook).execute(&self.conn)?;
+            Ok(true)
+        })
+        .map_err(get_service_error)
+    }
+
+    /// Finds the recent deliveries of the webhook from the latest one.
+    pub fn find_deliveries_by_webhook_id(
+        &self,
+        webhook_id: u64,
+        limit: usize,
+    ) -> Result<Vec<WebhookDelivery>, ServiceError> {
+        let delivery_list: Result<Vec<WebhookDelivery>, Error> = webhook_deliveries::table
+            .filter(webhook_deliveries::webhook_id.eq(webhook_id))
+            .order(webhook_deliveries::id.desc())
+            .limit(limit as i64)
+            .load::<WebhookDelivery>(&self.conn);
+
+        match delivery_list {
+            Ok(delivery_list) => Ok(delivery_list),
+            Err(_) => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+
+    /// Creates a log of an attempt to deliver an event.
+    pub fn create_delivery(&self, delivery: &WebhookDeliveryDAO) -> Result<bool, ServiceError> {
+        let count = diesel::insert_into(webhook_deliveries::table)
+            .values(delivery)
+            .execute(&self.conn);
+
+        match count {
+            Ok(count) if count > 0 => Ok(true),
+            _ => Err(get_service_error(ServiceError::QueryExecutionFailure)),
+        }
+    }
+}
+
+impl WebhookRepositoryTrait for WebhookRepository {
+    fn find_all_by_user_id(&self, user_id: u64) -> Result<Vec<Webhook>, ServiceError> {
+        let _span = trace_util::start_span("WebhookRepository::find_all_by_user_id", None);
+        WebhookRepository::find_all_by_user_id(self, user_id)
+    }
+
+    fn create(&self, user_id: u64, url: &str, secret: &str) -> Result<u64, ServiceError> {
+        let _span = trace_util::start_span("WebhookRepository::create", None);
+        WebhookRepository::create(self, user_id, url, secret)
+    }
+
+    fn delete(&self, id: u64, user_id: u64) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("WebhookRepository::delete", None);
+        WebhookRepository::delete(self, id, user_id)
+    }
+
+    fn find_deliveries_by_webhook_id(
+        &self,
+        webhook_id: u64,
+        limit: usize,
+    ) -> Result<Vec<WebhookDelivery>, ServiceError> {
+        let _span =
+            trace_util::start_span("WebhookRepository::find_deliveries_by_webhook_id", None);
+        WebhookRepository::find_deliveries_by_webhook_id(self, webhook_id, limit)
+    }
+
+    fn create_delivery(&self, delivery: &WebhookDeliveryDAO) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("WebhookRepository::create_delivery", None);
+        WebhookRepository::create_delivery(self, delivery)
+    }
+}
+
+impl Default for WebhookRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
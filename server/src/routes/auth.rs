@@ -1,15 +1,22 @@
-use actix_web::{post, web, Responder};
+use actix_web::{delete, get, post, web, Responder};
+use chrono::{NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::models::auth::*;
+use crate::models::error::ServiceError;
+use crate::models::user_api_key::CreatedUserApiKey;
 use crate::services::auth::AuthService;
-use crate::utils::http_util;
+use crate::services::user_api_key::UserApiKeyService;
+use crate::utils::{http_util, token_util};
 
 /// Arguments for `GET /auth` API.
 #[derive(Serialize, Deserialize)]
 pub struct LoginArgs {
     pub email: String,
     pub password: String,
+    pub totp_code: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
 }
 
 /// Arguments for `POST /auth/token` API.
@@ -19,6 +26,14 @@ pub struct SetSignUpTokenArgs {
     pub email: String,
     pub password: String,
     pub avatar_url: Option<String>,
+    pub locale: Option<String>,
+    pub invite_code: Option<String>,
+}
+
+/// Arguments for `POST /auth/token/sign_up/resend` API.
+#[derive(Serialize, Deserialize)]
+pub struct ResendSignUpTokenArgs {
+    pub email: String,
 }
 
 /// Arguments for `POST /auth/token/password` API.
@@ -27,6 +42,84 @@ pub struct SetPasswordTokenArgs {
     pub email: String,
 }
 
+/// Arguments for `POST /auth/magic_link` API.
+#[derive(Serialize, Deserialize)]
+pub struct RequestMagicLinkArgs {
+    pub email: String,
+}
+
+/// Arguments for `POST /auth/magic_link/login` API.
+#[derive(Serialize, Deserialize)]
+pub struct ConsumeMagicLinkArgs {
+    pub token: String,
+    pub totp_code: Option<String>,
+}
+
+/// Arguments for `POST /auth/sessions/validate` API.
+#[derive(Serialize, Deserialize)]
+pub struct ValidateSessionArgs {
+    pub user_id: u64,
+    pub user_session_epoch: u64,
+}
+
+/// Arguments for `POST /auth/sessions/refresh` API.
+#[derive(Serialize, Deserialize)]
+pub struct RefreshSessionArgs {
+    pub user_id: u64,
+    pub user_session_epoch: u64,
+    pub user_session_id: String,
+    pub user_session_started_at: NaiveDateTime,
+    pub user_session_expires_at: NaiveDateTime,
+}
+
+/// Arguments for `POST /auth/sessions/invalidate` API.
+#[derive(Serialize, Deserialize)]
+pub struct InvalidateSessionsArgs {
+    pub user_id: u64,
+}
+
+/// Arguments for `POST /auth/change-password` API.
+#[derive(Serialize, Deserialize)]
+pub struct ChangePasswordArgs {
+    pub user_id: u64,
+    pub user_session_id: String,
+    pub current_password: String,
+    pub new_password: String,
+    pub invalidate_other_sessions: bool,
+}
+
+/// Arguments for `POST /auth/totp` API.
+#[derive(Serialize, Deserialize)]
+pub struct EnrollTotpArgs {
+    pub user_id: u64,
+}
+
+/// Arguments for `POST /auth/totp/confirm` API.
+#[derive(Serialize, Deserialize)]
+pub struct ConfirmTotpArgs {
+    pub user_id: u64,
+    pub code: String,
+}
+
+/// Arguments for `POST /auth/totp/backup_codes` API.
+#[derive(Serialize, Deserialize)]
+pub struct GenerateBackupCodesArgs {
+    pub user_id: u64,
+}
+
+/// Arguments for `POST /auth/api-keys` API.
+#[derive(Serialize, Deserialize)]
+pub struct CreateApiKeyArgs {
+    pub user_id: u64,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// Arguments for `POST /auth/api-keys/authenticate` API.
+#[derive(Serialize, Deserialize)]
+pub struct AuthenticateApiKeyArgs {
+    pub key: String,
+}
+
 /// Sets token for creating user.
 #[post("/auth/token/sign_up")]
 pub async fn set_sign_up_token(args: web::Json<SetSignUpTokenArgs>) -> impl Responder {
@@ -35,11 +128,28 @@ pub async fn set_sign_up_token(args: web::Json<SetSignUpTokenArgs>) -> impl Resp
         email,
         password,
         avatar_url,
+        locale,
+        invite_code,
     } = args.into_inner();
-    let result = AuthService::new().set_sign_up_token(&name, &email, &password, &avatar_url);
+    let result = match AuthService::new()
+        .set_sign_up_token(&name, &email, &password, &avatar_url, &locale, &invite_code)
+        .await
+    {
+        // Responds with a key that can't be used, not to reveal the email is already registered.
+        Err(ServiceError::Conflict(_)) => Ok(token_util::get_random_string(32)),
+        result => result,
+    };
     http_util::get_response::<String>(result)
 }
 
+/// Resends the email of the pending token for creating user.
+#[post("/auth/token/sign_up/resend")]
+pub async fn resend_sign_up_token(args: web::Json<ResendSignUpTokenArgs>) -> impl Responder {
+    let ResendSignUpTokenArgs { email } = args.into_inner();
+    let result = AuthService::new().resend_sign_up_token(&email);
+    http_util::get_response::<bool>(result)
+}
+
 /// Sets token for resetting password.
 #[post("/auth/token/password")]
 pub async fn set_password_token(args: web::Json<SetPasswordTokenArgs>) -> impl Responder {
@@ -51,14 +161,186 @@ pub async fn set_password_token(args: web::Json<SetPasswordTokenArgs>) -> impl R
 /// Signs in to set user session.
 #[post("/auth/login")]
 pub async fn login(args: web::Json<LoginArgs>) -> impl Responder {
-    let LoginArgs { email, password } = args.into_inner();
-    let result = AuthService::new().login(&email, &password);
+    let LoginArgs {
+        email,
+        password,
+        totp_code,
+        user_agent,
+        ip_address,
+    } = args.into_inner();
+    let result = AuthService::new().login(
+        &email,
+        &password,
+        totp_code.as_deref(),
+        user_agent.as_deref(),
+        ip_address.as_deref(),
+    );
+    http_util::get_response::<UserSession>(result)
+}
+
+/// Requests a magic link to log in without password.
+#[post("/auth/magic_link")]
+pub async fn request_magic_link(args: web::Json<RequestMagicLinkArgs>) -> impl Responder {
+    let RequestMagicLinkArgs { email } = args.into_inner();
+    let result = AuthService::new().request_magic_link(&email);
+    http_util::get_response::<bool>(result)
+}
+
+/// Signs in with the token of the magic link to set user session.
+#[post("/auth/magic_link/login")]
+pub async fn consume_magic_link(args: web::Json<ConsumeMagicLinkArgs>) -> impl Responder {
+    let ConsumeMagicLinkArgs { token, totp_code } = args.into_inner();
+    let result = AuthService::new().consume_magic_link(&token, totp_code.as_deref());
+    http_util::get_response::<UserSession>(result)
+}
+
+/// Checks whether the session has not been invalidated.
+#[post("/auth/sessions/validate")]
+pub async fn validate_session(args: web::Json<ValidateSessionArgs>) -> impl Responder {
+    let ValidateSessionArgs {
+        user_id,
+        user_session_epoch,
+    } = args.into_inner();
+    let result = AuthService::new().validate_session_epoch(user_id, user_session_epoch);
+    http_util::get_response::<bool>(result)
+}
+
+/// Refreshes the session, and responds the extended expiry of the session.
+#[post("/auth/sessions/refresh")]
+pub async fn refresh_session(args: web::Json<RefreshSessionArgs>) -> impl Responder {
+    let RefreshSessionArgs {
+        user_id,
+        user_session_epoch,
+        user_session_id,
+        user_session_started_at,
+        user_session_expires_at,
+    } = args.into_inner();
+    let result = AuthService::new().refresh_user_session(
+        user_id,
+        user_session_epoch,
+        &user_session_id,
+        &user_session_started_at,
+        &user_session_expires_at,
+        &Utc::now().naive_utc(),
+    );
+    http_util::get_response::<NaiveDateTime>(result)
+}
+
+/// Invalidates all sessions of the user.
+#[post("/auth/sessions/invalidate")]
+pub async fn invalidate_sessions(args: web::Json<InvalidateSessionsArgs>) -> impl Responder {
+    let InvalidateSessionsArgs { user_id } = args.into_inner();
+    let result = AuthService::new().invalidate_all_sessions(user_id);
+    http_util::get_response::<bool>(result)
+}
+
+/// Responds the active sessions of the user.
+#[get("/auth/sessions/{user_id}")]
+pub async fn get_active_sessions(user_id: web::Path<u64>) -> impl Responder {
+    let result =
+        AuthService::new().get_active_sessions(user_id.into_inner(), &Utc::now().naive_utc());
+    http_util::get_response::<Vec<ActiveSession>>(result)
+}
+
+/// Revokes an active session of the user.
+#[delete("/auth/sessions/{user_id}/{id}")]
+pub async fn revoke_session(web::Path((user_id, id)): web::Path<(u64, String)>) -> impl Responder {
+    let result = AuthService::new().revoke_session(user_id, &id);
+    http_util::get_response::<bool>(result)
+}
+
+/// Changes the password of the user, and revokes the other sessions if requested.
+#[post("/auth/change-password")]
+pub async fn change_password(args: web::Json<ChangePasswordArgs>) -> impl Responder {
+    let ChangePasswordArgs {
+        user_id,
+        user_session_id,
+        current_password,
+        new_password,
+        invalidate_other_sessions,
+    } = args.into_inner();
+    let mut auth_service = AuthService::new();
+    let result = auth_service
+        .change_password(user_id, &current_password, &new_password)
+        .await
+        .and_then(|is_changed| {
+            if invalidate_other_sessions {
+                auth_service.revoke_other_sessions(user_id, &user_session_id)
+            } else {
+                Ok(is_changed)
+            }
+        });
+    http_util::get_response::<bool>(result)
+}
+
+/// Starts TOTP enrollment.
+#[post("/auth/totp")]
+pub async fn enroll_totp(args: web::Json<EnrollTotpArgs>) -> impl Responder {
+    let EnrollTotpArgs { user_id } = args.into_inner();
+    let result = AuthService::new().enroll_totp(user_id);
+    http_util::get_response::<TotpEnrollment>(result)
+}
+
+/// Confirms TOTP enrollment.
+#[post("/auth/totp/confirm")]
+pub async fn confirm_totp(args: web::Json<ConfirmTotpArgs>) -> impl Responder {
+    let ConfirmTotpArgs { user_id, code } = args.into_inner();
+    let result = AuthService::new().confirm_totp(user_id, &code);
+    http_util::get_response::<bool>(result)
+}
+
+/// Generates backup codes used in place of TOTP codes.
+#[post("/auth/totp/backup_codes")]
+pub async fn generate_backup_codes(args: web::Json<GenerateBackupCodesArgs>) -> impl Responder {
+    let GenerateBackupCodesArgs { user_id } = args.into_inner();
+    let result = AuthService::new().generate_backup_codes(user_id);
+    http_util::get_response::<Vec<String>>(result)
+}
+
+/// Creates an API key of the user.
+#[post("/auth/api-keys")]
+pub async fn create_api_key(args: web::Json<CreateApiKeyArgs>) -> impl Responder {
+    let CreateApiKeyArgs {
+        user_id,
+        expires_at,
+    } = args.into_inner();
+    let result = UserApiKeyService::new().create(user_id, &expires_at, &Utc::now().naive_utc());
+    http_util::get_response::<CreatedUserApiKey>(result)
+}
+
+/// Revokes an API key of the user.
+#[delete("/auth/api-keys/{user_id}/{id}")]
+pub async fn revoke_api_key(web::Path((user_id, id)): web::Path<(u64, u64)>) -> impl Responder {
+    let result = UserApiKeyService::new().revoke(user_id, id);
+    http_util::get_response::<bool>(result)
+}
+
+/// Authenticates an API key, and responds the session of the owning user.
+#[post("/auth/api-keys/authenticate")]
+pub async fn authenticate_api_key(args: web::Json<AuthenticateApiKeyArgs>) -> impl Responder {
+    let AuthenticateApiKeyArgs { key } = args.into_inner();
+    let result = UserApiKeyService::new().authenticate(&key, &Utc::now().naive_utc());
     http_util::get_response::<UserSession>(result)
 }
 
 /// Initializes the auth routes.
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(set_sign_up_token);
+    cfg.service(resend_sign_up_token);
     cfg.service(set_password_token);
     cfg.service(login);
+    cfg.service(request_magic_link);
+    cfg.service(consume_magic_link);
+    cfg.service(validate_session);
+    cfg.service(refresh_session);
+    cfg.service(invalidate_sessions);
+    cfg.service(get_active_sessions);
+    cfg.service(revoke_session);
+    cfg.service(change_password);
+    cfg.service(enroll_totp);
+    cfg.service(confirm_totp);
+    cfg.service(generate_backup_codes);
+    cfg.service(create_api_key);
+    cfg.service(revoke_api_key);
+    cfg.service(authenticate_api_key);
 }
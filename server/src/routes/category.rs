@@ -0,0 +1,59 @@
+use actix_web::{delete, get, patch, post, web, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::models::category::CategoryDTO;
+use crate::services::category::CategoryService;
+use crate::utils::http_util;
+
+/// Arguments for `POST /categories` API.
+#[derive(Serialize, Deserialize)]
+pub struct CreateCategoryArgs {
+    pub user_id: u64,
+    pub name: String,
+}
+
+/// Arguments for `PATCH /categories/:user_id/:id` API.
+#[derive(Serialize, Deserialize)]
+pub struct RenameCategoryArgs {
+    pub name: String,
+}
+
+/// Creates a new category, and responds it
+#[post("/categories")]
+pub async fn create_category(args: web::Json<CreateCategoryArgs>) -> impl Responder {
+    let CreateCategoryArgs { user_id, name } = args.into_inner();
+    let category = CategoryService::new().create(user_id, &name);
+    http_util::get_response::<CategoryDTO>(category)
+}
+
+/// Responds categories of a user
+#[get("/categories/{user_id}")]
+pub async fn get_categories(user_id: web::Path<u64>) -> impl Responder {
+    let categories = CategoryService::new().get_list(user_id.into_inner());
+    http_util::get_response::<Vec<CategoryDTO>>(categories)
+}
+
+/// Renames a category of a user
+#[patch("/categories/{user_id}/{id}")]
+pub async fn rename_category(
+    web::Path((user_id, id)): web::Path<(u64, u64)>,
+    args: web::Json<RenameCategoryArgs>,
+) -> impl Responder {
+    let result = CategoryService::new().rename(user_id, id, &args.into_inner().name);
+    http_util::get_response::<bool>(result)
+}
+
+/// Deletes a category of a user
+#[delete("/categories/{user_id}/{id}")]
+pub async fn delete_category(web::Path((user_id, id)): web::Path<(u64, u64)>) -> impl Responder {
+    let result = CategoryService::new().delete(user_id, id);
+    http_util::get_response::<bool>(result)
+}
+
+/// Initializes the category routes.
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_category);
+    cfg.service(get_categories);
+    cfg.service(rename_category);
+    cfg.service(delete_category);
+}
@@ -0,0 +1,94 @@
+use actix_web::{get, web, HttpResponse, Responder};
+
+use crate::services::health::{BuildInfo, HealthService};
+use crate::utils::http_util;
+
+/// Responds whether the process is up, regardless of its dependencies
+#[get("/health")]
+pub async fn liveness_check() -> impl Responder {
+    http_util::get_response::<bool>(Ok(true))
+}
+
+/// Responds whether the server is ready to handle requests, with availability of each dependency
+#[get("/ready")]
+pub async fn readiness_check() -> impl Responder {
+    get_readiness_response(&mut HealthService::new())
+}
+
+/// Responds the version of the server, with the git commit and the time it is built from
+#[get("/version")]
+pub async fn version() -> impl Responder {
+    HttpResponse::Ok().json(BuildInfo::current())
+}
+
+/// Responds `200 OK` if all dependencies are available, or `503 Service Unavailable` if not.
+fn get_readiness_response(health_service: &mut HealthService) -> HttpResponse {
+    let readiness = health_service.check_readiness();
+    if readiness.is_ready() {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
+
+/// Initializes the health routes.
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(liveness_check);
+    cfg.service(readiness_check);
+    cfg.service(version);
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::models::health::MockHealthRepositoryTrait as HealthRepository;
+
+    fn get_health_repository(rdb: bool, redis: bool) -> HealthRepository {
+        let mut mocked_health_repository = HealthRepository::new();
+        mocked_health_repository
+            .expect_ping_rdb()
+            .times(1)
+            .return_const(rdb);
+        mocked_health_repository
+            .expect_ping_redis()
+            .times(1)
+            .return_const(redis);
+        mocked_health_repository
+    }
+
+    #[test]
+    fn test_readiness_when_all_dependencies_are_up() {
+        let mut health_service =
+            HealthService::new_with_repository(get_health_repository(true, true));
+
+        let response = get_readiness_response(&mut health_service);
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_readiness_when_rdb_is_down() {
+        let mut health_service =
+            HealthService::new_with_repository(get_health_repository(false, true));
+
+        let response = get_readiness_response(&mut health_service);
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[actix_rt::test]
+    async fn test_version() {
+        let mut app = test::init_service(App::new().configure(init_routes)).await;
+
+        let req = test::TestRequest::get().uri("/version").to_request();
+        let response = test::call_service(&mut app, req).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert!(!body["commit_hash"].as_str().unwrap().is_empty());
+        assert!(body["built_at"].as_str().unwrap().ends_with('Z'));
+    }
+}
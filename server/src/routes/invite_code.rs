@@ -0,0 +1,40 @@
+use actix_web::{delete, post, web, Responder};
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::invite_code::InviteCode;
+use crate::services::invite_code::InviteCodeService;
+use crate::utils::http_util;
+
+/// Arguments for `POST /invite_codes` API.
+#[derive(Serialize, Deserialize)]
+pub struct MintArgs {
+    pub max_uses: u32,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// Mints an invite code required to sign up.
+/// It is not exposed by the API gateway, and is called by the operator of the service.
+#[post("/invite_codes")]
+pub async fn mint_invite_code(args: web::Json<MintArgs>) -> impl Responder {
+    let MintArgs {
+        max_uses,
+        expires_at,
+    } = args.into_inner();
+    let result = InviteCodeService::new().mint(max_uses, &expires_at, &Utc::now().naive_utc());
+    http_util::get_response::<InviteCode>(result)
+}
+
+/// Revokes an invite code.
+/// It is not exposed by the API gateway, and is called by the operator of the service.
+#[delete("/invite_codes/{code}")]
+pub async fn revoke_invite_code(code: web::Path<String>) -> impl Responder {
+    let result = InviteCodeService::new().revoke(&code.into_inner(), &Utc::now().naive_utc());
+    http_util::get_response::<bool>(result)
+}
+
+/// Initializes the invite code routes.
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(mint_invite_code);
+    cfg.service(revoke_invite_code);
+}
@@ -0,0 +1,67 @@
+use actix_web::{post, web, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::models::auth::UserSession;
+use crate::services::oauth::OAuthService;
+use crate::utils::http_util;
+
+/// Arguments for `POST /oauth/google/complete` and `POST /oauth/github/complete` API.
+#[derive(Serialize, Deserialize)]
+pub struct CompleteOAuthLoginArgs {
+    pub code: String,
+    pub state: String,
+    pub totp_code: Option<String>,
+    pub invite_code: Option<String>,
+}
+
+/// Begins Google login and responds the authorization URL.
+#[post("/oauth/google")]
+pub async fn begin_google_login() -> impl Responder {
+    let result = OAuthService::new().begin_google_login();
+    http_util::get_response::<String>(result)
+}
+
+/// Completes Google login.
+#[post("/oauth/google/complete")]
+pub async fn complete_google_login(args: web::Json<CompleteOAuthLoginArgs>) -> impl Responder {
+    let CompleteOAuthLoginArgs {
+        code,
+        state,
+        totp_code,
+        invite_code,
+    } = args.into_inner();
+    let result = OAuthService::new()
+        .complete_google_login(&code, &state, totp_code.as_deref(), invite_code.as_deref())
+        .await;
+    http_util::get_response::<UserSession>(result)
+}
+
+/// Begins GitHub login and responds the authorization URL.
+#[post("/oauth/github")]
+pub async fn begin_github_login() -> impl Responder {
+    let result = OAuthService::new().begin_github_login();
+    http_util::get_response::<String>(result)
+}
+
+/// Completes GitHub login.
+#[post("/oauth/github/complete")]
+pub async fn complete_github_login(args: web::Json<CompleteOAuthLoginArgs>) -> impl Responder {
+    let CompleteOAuthLoginArgs {
+        code,
+        state,
+        totp_code,
+        invite_code,
+    } = args.into_inner();
+    let result = OAuthService::new()
+        .complete_github_login(&code, &state, totp_code.as_deref(), invite_code.as_deref())
+        .await;
+    http_util::get_response::<UserSession>(result)
+}
+
+/// Initializes the OAuth routes.
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(begin_google_login);
+    cfg.service(complete_google_login);
+    cfg.service(begin_github_login);
+    cfg.service(complete_github_login);
+}
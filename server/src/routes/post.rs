@@ -1,10 +1,47 @@
-use actix_web::{delete, get, patch, post, web, Responder};
-use chrono::NaiveDateTime;
+use actix_web::{delete, get, patch, post, put, web, HttpRequest, Responder};
+use chrono::{NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::models::post::*;
 use crate::services::post::PostService;
-use crate::utils::http_util;
+use crate::utils::{env_util, http_util};
+
+/// Arguments for `GET /posts/:user_id` API.
+/// The `tag` parameter can be repeated, so it is read from the query string separately.
+#[derive(Serialize, Deserialize)]
+pub struct GetListArgs {
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+    pub tag_mode: Option<String>,
+    pub category: Option<u64>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+    pub after: Option<String>,
+}
+
+/// Arguments for `GET /posts/:user_id/count` API.
+#[derive(Serialize, Deserialize)]
+pub struct CountArgs {
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+    pub tag: Option<String>,
+}
+
+/// Arguments for `GET /posts/:user_id/mood-stats` API.
+#[derive(Serialize, Deserialize)]
+pub struct MoodStatsArgs {
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
+
+/// Arguments for `GET /posts/:user_id/search` API.
+#[derive(Serialize, Deserialize)]
+pub struct SearchArgs {
+    pub q: String,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
 
 /// Arguments for `POST /posts` API.
 #[derive(Serialize, Deserialize)]
@@ -13,32 +50,120 @@ pub struct CreateArgs {
     pub title: String,
     pub content: String,
     pub date: NaiveDateTime,
+    pub tags: Vec<String>,
+    pub publish_at: Option<NaiveDateTime>,
+    pub status: Option<PostStatus>,
+    pub mood: Option<String>,
+}
+
+/// A maximum number of bytes in the payload of `POST /posts/import` API, if it is not configured.
+/// It is larger than the limit of the api gateway, as the archive is wrapped with the user id.
+const DEFAULT_MAX_IMPORT_PAYLOAD_SIZE: usize = 16 * 1024 * 1024;
+
+/// Arguments for `POST /posts/import` API.
+#[derive(Serialize, Deserialize)]
+pub struct ImportArgs {
+    pub user_id: u64,
+    pub posts: Vec<Value>,
+}
+
+/// Arguments for `POST /posts/bulk-delete` API.
+#[derive(Serialize, Deserialize)]
+pub struct BulkDeleteArgs {
+    pub user_id: u64,
+    pub ids: Vec<u64>,
+}
+
+/// Arguments for `PUT /posts/:user_id/:id/category` API.
+#[derive(Serialize, Deserialize)]
+pub struct SetCategoryArgs {
+    pub category_id: Option<u64>,
 }
 
 /// Arguments for `PATCH /posts/:id` API.
 #[derive(Serialize, Deserialize)]
 pub struct UpdateArgs {
     pub user_id: u64,
+    pub version: u64,
     pub title: Option<String>,
     pub content: Option<String>,
     pub date: Option<NaiveDateTime>,
+    pub tags: Option<Vec<String>>,
+    pub mood: Option<String>,
 }
 
-/// Responds a post written by logged-in user
+/// Responds a page of posts written by logged-in user
 #[get("/posts/{user_id}")]
-pub async fn get_posts(user_id: web::Path<u64>) -> impl Responder {
-    let posts = PostService::new().get_list(user_id.into_inner());
-    http_util::get_response::<Vec<PostDTO>>(posts)
+pub async fn get_posts(
+    req: HttpRequest,
+    user_id: web::Path<u64>,
+    args: web::Query<GetListArgs>,
+) -> impl Responder {
+    let GetListArgs {
+        from,
+        to,
+        tag_mode,
+        category,
+        limit,
+        offset,
+        after,
+    } = args.into_inner();
+    let posts = PostService::new().get_list(
+        user_id.into_inner(),
+        from,
+        to,
+        &http_util::get_query_values(req.query_string(), "tag"),
+        tag_mode,
+        category,
+        limit,
+        offset,
+        after,
+    );
+    http_util::get_response::<PostPageDTO>(posts)
+}
+
+/// Responds the number of posts written by logged-in user
+#[get("/posts/{user_id}/count")]
+pub async fn count_posts(user_id: web::Path<u64>, args: web::Query<CountArgs>) -> impl Responder {
+    let CountArgs { from, to, tag } = args.into_inner();
+    let count = PostService::new().count(user_id.into_inner(), from, to, tag);
+    http_util::get_response::<u64>(count)
+}
+
+/// Responds the number of posts written by logged-in user for each mood
+#[get("/posts/{user_id}/mood-stats")]
+pub async fn get_mood_stats(
+    user_id: web::Path<u64>,
+    args: web::Query<MoodStatsArgs>,
+) -> impl Responder {
+    let MoodStatsArgs { from, to } = args.into_inner();
+    let stats = PostService::new().get_mood_stats(user_id.into_inner(), from, to);
+    http_util::get_response::<Vec<MoodCountDTO>>(stats)
 }
 
-/// Responds a summarized post written by logged-in user
+/// Responds the writing streaks of logged-in user
+#[get("/posts/{user_id}/streak")]
+pub async fn get_post_streak(user_id: web::Path<u64>) -> impl Responder {
+    let streak = PostService::new().get_streak(user_id.into_inner());
+    http_util::get_response::<PostStreakDTO>(streak)
+}
+
+/// Responds a page of posts written by logged-in user, matching the search query
+#[get("/posts/{user_id}/search")]
+pub async fn search_posts(user_id: web::Path<u64>, args: web::Query<SearchArgs>) -> impl Responder {
+    let SearchArgs { q, limit, offset } = args.into_inner();
+    let posts = PostService::new().search(user_id.into_inner(), &q, limit, offset);
+    http_util::get_response::<PostPageDTO>(posts)
+}
+
+/// Lists summarized posts written by logged-in user
 #[get("/summarized_posts/{user_id}")]
 pub async fn get_summarized_posts(user_id: web::Path<u64>) -> impl Responder {
     let posts = PostService::new().get_summarized_list(user_id.into_inner());
     http_util::get_response::<Vec<SummarizedPostDTO>>(posts)
 }
 
-/// Lists posts written by logged-in user
+/// Responds a post written by logged-in user
 #[get("/posts/{user_id}/{id}")]
 pub async fn get_post(web::Path((user_id, id)): web::Path<(u64, u64)>) -> impl Responder {
     let post = PostService::new().get(user_id, id);
@@ -53,37 +178,195 @@ pub async fn create_post(args: web::Json<CreateArgs>) -> impl Responder {
         title,
         content,
         date,
+        tags,
+        publish_at,
+        status,
+        mood,
     } = args.into_inner();
-    let result = PostService::new().create(user_id, &title, &content, &date);
+    let status = status.unwrap_or(PostStatus::Draft);
+    let result = PostService::new().create(
+        user_id,
+        &title,
+        &content,
+        &date,
+        &tags,
+        &publish_at,
+        status,
+        &mood,
+    );
     http_util::get_response::<u64>(result)
 }
 
-/// Deletes a post
+/// Responds all posts written by logged-in user to be exported
+#[get("/posts/{user_id}/export")]
+pub async fn export_posts(user_id: web::Path<u64>) -> impl Responder {
+    let posts = PostService::new().export(user_id.into_inner());
+    http_util::get_response::<Vec<Post>>(posts)
+}
+
+/// Imports posts of an exported archive as posts of logged-in user
+pub async fn import_posts(args: web::Json<ImportArgs>) -> impl Responder {
+    let ImportArgs { user_id, posts } = args.into_inner();
+    let result = PostService::new().import(user_id, &posts);
+    http_util::get_response::<PostImportResultDTO>(result)
+}
+
+/// Lists posts in the trash written by logged-in user
+#[get("/posts/{user_id}/trash")]
+pub async fn get_trashed_posts(user_id: web::Path<u64>) -> impl Responder {
+    let posts = PostService::new().get_trash(user_id.into_inner());
+    http_util::get_response::<Vec<PostDTO>>(posts)
+}
+
+/// Lists posts written by logged-in user, which are scheduled to be published later
+#[get("/posts/{user_id}/scheduled")]
+pub async fn get_scheduled_posts(user_id: web::Path<u64>) -> impl Responder {
+    let posts = PostService::new().get_scheduled(user_id.into_inner());
+    http_util::get_response::<Vec<PostDTO>>(posts)
+}
+
+/// Lists drafts written by logged-in user
+#[get("/posts/{user_id}/drafts")]
+pub async fn get_draft_posts(user_id: web::Path<u64>) -> impl Responder {
+    let posts = PostService::new().get_drafts(user_id.into_inner());
+    http_util::get_response::<Vec<PostDTO>>(posts)
+}
+
+/// Publishes a draft
+#[post("/posts/{user_id}/{id}/publish")]
+pub async fn publish_post(web::Path((user_id, id)): web::Path<(u64, u64)>) -> impl Responder {
+    let result = PostService::new().publish(id, user_id);
+    http_util::get_response::<bool>(result)
+}
+
+/// Pins a post to the top of the list
+#[post("/posts/{user_id}/{id}/pin")]
+pub async fn pin_post(web::Path((user_id, id)): web::Path<(u64, u64)>) -> impl Responder {
+    let result = PostService::new().pin(id, user_id);
+    http_util::get_response::<bool>(result)
+}
+
+/// Unpins a post
+#[post("/posts/{user_id}/{id}/unpin")]
+pub async fn unpin_post(web::Path((user_id, id)): web::Path<(u64, u64)>) -> impl Responder {
+    let result = PostService::new().unpin(id, user_id);
+    http_util::get_response::<bool>(result)
+}
+
+/// Shares a post by a public link, and responds the token of the link
+#[post("/posts/{user_id}/{id}/share")]
+pub async fn share_post(web::Path((user_id, id)): web::Path<(u64, u64)>) -> impl Responder {
+    let result = PostService::new().share(id, user_id);
+    http_util::get_response::<String>(result)
+}
+
+/// Revokes the public link of a post
+#[delete("/posts/{user_id}/{id}/share")]
+pub async fn unshare_post(web::Path((user_id, id)): web::Path<(u64, u64)>) -> impl Responder {
+    let result = PostService::new().unshare(id, user_id);
+    http_util::get_response::<bool>(result)
+}
+
+/// Puts a post into a category, or removes it from its category
+#[put("/posts/{user_id}/{id}/category")]
+pub async fn set_post_category(
+    web::Path((user_id, id)): web::Path<(u64, u64)>,
+    args: web::Json<SetCategoryArgs>,
+) -> impl Responder {
+    let result = PostService::new().set_category(id, user_id, args.into_inner().category_id);
+    http_util::get_response::<bool>(result)
+}
+
+/// Responds a post shared by a public link, which is read without logging in
+#[get("/public/posts/{share_token}")]
+pub async fn get_shared_post(share_token: web::Path<String>) -> impl Responder {
+    let post = PostService::new().get_shared(&share_token.into_inner(), &Utc::now().naive_utc());
+    http_util::get_response::<PostDTO>(post)
+}
+
+/// Moves a post to the trash
 #[delete("/posts/{user_id}/{id}")]
 pub async fn delete_post(web::Path((user_id, id)): web::Path<(u64, u64)>) -> impl Responder {
     let result = PostService::new().delete(id, user_id);
     http_util::get_response::<bool>(result)
 }
 
+/// Moves posts to the trash at once, and responds the result of each post
+#[post("/posts/bulk-delete")]
+pub async fn bulk_delete_posts(args: web::Json<BulkDeleteArgs>) -> impl Responder {
+    let BulkDeleteArgs { user_id, ids } = args.into_inner();
+    let results = PostService::new().bulk_delete(&ids, user_id);
+    http_util::get_response::<Vec<BulkDeleteResultDTO>>(results)
+}
+
+/// Restores a post from the trash
+#[post("/posts/{user_id}/{id}/restore")]
+pub async fn restore_post(web::Path((user_id, id)): web::Path<(u64, u64)>) -> impl Responder {
+    let result = PostService::new().restore(id, user_id);
+    http_util::get_response::<bool>(result)
+}
+
+/// Deletes a post permanently
+#[delete("/posts/{user_id}/{id}/purge")]
+pub async fn purge_post(web::Path((user_id, id)): web::Path<(u64, u64)>) -> impl Responder {
+    let result = PostService::new().purge(id, user_id);
+    http_util::get_response::<bool>(result)
+}
+
 /// Updates a post
 #[patch("/posts/{id}")]
 pub async fn update_post(id: web::Path<u64>, args: web::Json<UpdateArgs>) -> impl Responder {
     let UpdateArgs {
         user_id,
+        version,
         title,
         content,
         date,
+        tags,
+        mood,
     } = args.into_inner();
-    let result = PostService::new().update(id.into_inner(), user_id, &title, &content, &date);
+    let post_update = PostUpdateDTO {
+        title,
+        content,
+        date,
+        tags,
+        mood,
+    };
+    let result = PostService::new().update(id.into_inner(), user_id, version, &post_update);
     http_util::get_response::<bool>(result)
 }
 
 /// Initializes the post routes.
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(export_posts);
+    cfg.service(get_trashed_posts);
+    cfg.service(get_scheduled_posts);
+    cfg.service(get_draft_posts);
+    cfg.service(search_posts);
+    cfg.service(count_posts);
+    cfg.service(get_post_streak);
+    cfg.service(get_mood_stats);
     cfg.service(get_post);
     cfg.service(get_posts);
     cfg.service(get_summarized_posts);
     cfg.service(create_post);
+    let max_import_payload_size =
+        env_util::get_env_var_or("MAX_IMPORT_PAYLOAD_SIZE", DEFAULT_MAX_IMPORT_PAYLOAD_SIZE);
+    cfg.service(
+        web::resource("/posts/import")
+            .app_data(web::JsonConfig::default().limit(max_import_payload_size))
+            .route(web::post().to(import_posts)),
+    );
     cfg.service(delete_post);
+    cfg.service(bulk_delete_posts);
+    cfg.service(publish_post);
+    cfg.service(pin_post);
+    cfg.service(unpin_post);
+    cfg.service(share_post);
+    cfg.service(unshare_post);
+    cfg.service(set_post_category);
+    cfg.service(get_shared_post);
+    cfg.service(restore_post);
+    cfg.service(purge_post);
     cfg.service(update_post);
 }
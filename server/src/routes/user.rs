@@ -1,7 +1,8 @@
 use actix_web::{delete, get, patch, post, web, Responder};
 use serde::{Deserialize, Serialize};
 
-use crate::models::user::UserDTO;
+use crate::models::auth::UserSession;
+use crate::models::user::{PersonalDataDTO, UserDTO, UserProfileDTO};
 use crate::services::user::UserService;
 use crate::utils::http_util;
 
@@ -12,14 +13,68 @@ pub struct CreateArgs {
     pub token_key: String,
     pub token_pin: String,
     pub recaptcha_token: String,
+    pub accepted_tos_version: Option<String>,
+}
+
+/// Arguments for `POST /users/{id}/tos` API.
+#[derive(Serialize, Deserialize)]
+pub struct AcceptTosArgs {
+    pub version: String,
 }
 
 /// Arguments for `PATCH /users/:id` API.
 #[derive(Serialize, Deserialize)]
 pub struct UpdateArgs {
     pub name: Option<String>,
-    pub password: Option<String>,
     pub avatar_url: Option<String>,
+    pub timezone: Option<String>,
+}
+
+/// Arguments for `PATCH /users/:id/profile` API.
+#[derive(Serialize, Deserialize)]
+pub struct UpdateProfileArgs {
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+/// Arguments for `PATCH /users/:id/reminder` API.
+#[derive(Serialize, Deserialize)]
+pub struct UpdateReminderArgs {
+    pub reminder_enabled: bool,
+    pub reminder_hour: u8,
+}
+
+/// Arguments for `PATCH /users/:id/locale` API.
+#[derive(Serialize, Deserialize)]
+pub struct UpdateLocaleArgs {
+    pub locale: Option<String>,
+}
+
+/// Arguments for `POST /users/{id}/deletion` API.
+#[derive(Serialize, Deserialize)]
+pub struct RequestAccountDeletionArgs {
+    pub password: Option<String>,
+    pub totp_code: Option<String>,
+}
+
+/// Arguments for `DELETE /users/{id}/account` API.
+#[derive(Serialize, Deserialize)]
+pub struct DeleteAccountArgs {
+    pub token: String,
+}
+
+/// Arguments for `POST /users/{id}/email` API.
+#[derive(Serialize, Deserialize)]
+pub struct RequestEmailChangeArgs {
+    pub email: String,
+    pub password: Option<String>,
+    pub totp_code: Option<String>,
+}
+
+/// Arguments for `POST /users/email/confirm` API.
+#[derive(Serialize, Deserialize)]
+pub struct ConfirmEmailChangeArgs {
+    pub token: String,
 }
 
 /// Arguments for `POST /users/password` API.
@@ -38,6 +93,13 @@ pub async fn get_user(id: web::Path<u64>) -> impl Responder {
     http_util::get_response::<UserDTO>(user)
 }
 
+/// Responds all personal data of a user to be exported
+#[get("/users/{id}/export")]
+pub async fn export_personal_data(id: web::Path<u64>) -> impl Responder {
+    let personal_data = UserService::new().export_personal_data(id.into_inner());
+    http_util::get_response::<PersonalDataDTO>(personal_data)
+}
+
 /// Creates a new user
 #[post("/users")]
 pub async fn create_user(args: web::Json<CreateArgs>) -> impl Responder {
@@ -46,11 +108,18 @@ pub async fn create_user(args: web::Json<CreateArgs>) -> impl Responder {
         token_key,
         token_pin,
         recaptcha_token,
+        accepted_tos_version,
     } = args.into_inner();
     let result = UserService::new()
-        .create(&user_public_key, &token_key, &token_pin, &recaptcha_token)
+        .create(
+            &user_public_key,
+            &token_key,
+            &token_pin,
+            &recaptcha_token,
+            &accepted_tos_version,
+        )
         .await;
-    http_util::get_response::<bool>(result)
+    http_util::get_response::<UserSession>(result)
 }
 
 /// Deletes a user
@@ -60,15 +129,126 @@ pub async fn delete_user(id: web::Path<u64>) -> impl Responder {
     http_util::get_response::<bool>(result)
 }
 
+/// Re-authenticates a user, and sends the token confirming the deletion of the account
+#[post("/users/{id}/deletion")]
+pub async fn request_account_deletion(
+    id: web::Path<u64>,
+    args: web::Json<RequestAccountDeletionArgs>,
+) -> impl Responder {
+    let RequestAccountDeletionArgs {
+        password,
+        totp_code,
+    } = args.into_inner();
+    let result =
+        UserService::new().request_account_deletion(id.into_inner(), &password, &totp_code);
+    http_util::get_response::<bool>(result)
+}
+
+/// Deletes a user with all data of the user, if the token confirms the deletion
+#[delete("/users/{id}/account")]
+pub async fn delete_account(
+    id: web::Path<u64>,
+    args: web::Json<DeleteAccountArgs>,
+) -> impl Responder {
+    let DeleteAccountArgs { token } = args.into_inner();
+    let result = UserService::new().confirm_account_deletion(id.into_inner(), &token);
+    http_util::get_response::<bool>(result)
+}
+
+/// Deactivates a user, whose posts are kept, and invalidates all sessions of the user
+#[post("/users/{id}/deactivate")]
+pub async fn deactivate_user(id: web::Path<u64>) -> impl Responder {
+    let result = UserService::new().deactivate(id.into_inner());
+    http_util::get_response::<bool>(result)
+}
+
+/// Reactivates a deactivated user.
+/// It is not exposed by the API gateway, and is called by the operator of the service.
+#[post("/users/{id}/reactivate")]
+pub async fn reactivate_user(id: web::Path<u64>) -> impl Responder {
+    let result = UserService::new().reactivate(id.into_inner());
+    http_util::get_response::<bool>(result)
+}
+
+/// Records that a user has accepted the current version of the terms of service
+#[post("/users/{id}/tos")]
+pub async fn accept_tos(id: web::Path<u64>, args: web::Json<AcceptTosArgs>) -> impl Responder {
+    let AcceptTosArgs { version } = args.into_inner();
+    let result = UserService::new().accept_tos(id.into_inner(), &version);
+    http_util::get_response::<bool>(result)
+}
+
 /// Updates a user
 #[patch("/users/{id}")]
 pub async fn update_user(id: web::Path<u64>, args: web::Json<UpdateArgs>) -> impl Responder {
     let UpdateArgs {
         name,
-        password,
         avatar_url,
+        timezone,
+    } = args.into_inner();
+    let result = UserService::new().update(id.into_inner(), &name, &avatar_url, &timezone);
+    http_util::get_response::<bool>(result)
+}
+
+/// Updates the profile of a user, and responds the updated profile
+#[patch("/users/{id}/profile")]
+pub async fn update_profile(
+    id: web::Path<u64>,
+    args: web::Json<UpdateProfileArgs>,
+) -> impl Responder {
+    let UpdateProfileArgs { name, avatar_url } = args.into_inner();
+    let profile = UserService::new().update_profile(id.into_inner(), &name, &avatar_url);
+    http_util::get_response::<UserProfileDTO>(profile)
+}
+
+/// Updates the daily reminder setting of a user
+#[patch("/users/{id}/reminder")]
+pub async fn update_reminder(
+    id: web::Path<u64>,
+    args: web::Json<UpdateReminderArgs>,
+) -> impl Responder {
+    let UpdateReminderArgs {
+        reminder_enabled,
+        reminder_hour,
+    } = args.into_inner();
+    let result =
+        UserService::new().update_reminder(id.into_inner(), reminder_enabled, reminder_hour);
+    http_util::get_response::<bool>(result)
+}
+
+/// Updates the locale of a user, in which emails are written
+#[patch("/users/{id}/locale")]
+pub async fn update_locale(
+    id: web::Path<u64>,
+    args: web::Json<UpdateLocaleArgs>,
+) -> impl Responder {
+    let UpdateLocaleArgs { locale } = args.into_inner();
+    let result = UserService::new().update_locale(id.into_inner(), &locale);
+    http_util::get_response::<bool>(result)
+}
+
+/// Requests to change the email of a user after re-authenticating the user, and sends the
+/// confirmation link to the new email
+#[post("/users/{id}/email")]
+pub async fn request_email_change(
+    id: web::Path<u64>,
+    args: web::Json<RequestEmailChangeArgs>,
+) -> impl Responder {
+    let RequestEmailChangeArgs {
+        email,
+        password,
+        totp_code,
     } = args.into_inner();
-    let result = UserService::new().update(id.into_inner(), &name, &password, &avatar_url);
+    let result =
+        UserService::new().request_email_change(id.into_inner(), &email, &password, &totp_code);
+    http_util::get_response::<bool>(result)
+}
+
+/// Confirms the pending email change of a user
+#[post("/users/email/confirm")]
+pub async fn confirm_email_change(args: web::Json<ConfirmEmailChangeArgs>) -> impl Responder {
+    let ConfirmEmailChangeArgs { token } = args.into_inner();
+    let result = UserService::new().confirm_email_change(&token);
     http_util::get_response::<bool>(result)
 }
 
@@ -89,8 +269,19 @@ pub async fn reset_password(args: web::Json<ResetPasswordArgs>) -> impl Responde
 /// Initializes the user routes.
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(get_user);
+    cfg.service(export_personal_data);
     cfg.service(create_user);
     cfg.service(delete_user);
+    cfg.service(request_account_deletion);
+    cfg.service(delete_account);
+    cfg.service(deactivate_user);
+    cfg.service(reactivate_user);
+    cfg.service(accept_tos);
     cfg.service(update_user);
+    cfg.service(update_profile);
+    cfg.service(update_reminder);
+    cfg.service(update_locale);
+    cfg.service(request_email_change);
+    cfg.service(confirm_email_change);
     cfg.service(reset_password);
 }
@@ -0,0 +1,49 @@
+use actix_web::{delete, get, post, web, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::models::user_key::UserKeyDTO;
+use crate::services::user_key::UserKeyService;
+use crate::utils::http_util;
+
+/// Arguments for `POST /users/{id}/key/rotation` API.
+#[derive(Serialize, Deserialize)]
+pub struct RotateArgs {
+    pub public_key: String,
+}
+
+/// Responds the public key of a user
+#[get("/users/{id}/key")]
+pub async fn get_user_key(id: web::Path<u64>) -> impl Responder {
+    let user_key = UserKeyService::new().get_one(id.into_inner());
+    http_util::get_response::<UserKeyDTO>(user_key)
+}
+
+/// Responds the fingerprint of the public key of a user
+#[get("/users/{id}/key-fingerprint")]
+pub async fn get_user_key_fingerprint(id: web::Path<u64>) -> impl Responder {
+    let fingerprint = UserKeyService::new().fingerprint(id.into_inner());
+    http_util::get_response::<String>(fingerprint)
+}
+
+/// Rotates the public key of a user, and responds ids of the posts to be re-encrypted
+#[post("/users/{id}/key/rotation")]
+pub async fn rotate_user_key(id: web::Path<u64>, args: web::Json<RotateArgs>) -> impl Responder {
+    let RotateArgs { public_key } = args.into_inner();
+    let post_ids = UserKeyService::new().rotate(id.into_inner(), &public_key);
+    http_util::get_response::<Vec<u64>>(post_ids)
+}
+
+/// Completes the rotation of the public key of a user
+#[delete("/users/{id}/key/rotation")]
+pub async fn complete_user_key_rotation(id: web::Path<u64>) -> impl Responder {
+    let result = UserKeyService::new().complete_rotation(id.into_inner());
+    http_util::get_response::<bool>(result)
+}
+
+/// Initializes the user key routes.
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_user_key);
+    cfg.service(get_user_key_fingerprint);
+    cfg.service(rotate_user_key);
+    cfg.service(complete_user_key_rotation);
+}
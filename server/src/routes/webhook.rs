@@ -0,0 +1,52 @@
+use actix_web::{delete, get, post, web, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::models::webhook::{RegisteredWebhook, WebhookDTO, WebhookDelivery};
+use crate::services::webhook::WebhookService;
+use crate::utils::http_util;
+
+/// Arguments for `POST /webhooks` API.
+#[derive(Serialize, Deserialize)]
+pub struct RegisterWebhookArgs {
+    pub user_id: u64,
+    pub url: String,
+}
+
+/// Registers a new webhook, and responds it with the secret
+#[post("/webhooks")]
+pub async fn register_webhook(args: web::Json<RegisterWebhookArgs>) -> impl Responder {
+    let RegisterWebhookArgs { user_id, url } = args.into_inner();
+    let webhook = WebhookService::new().register(user_id, &url);
+    http_util::get_response::<RegisteredWebhook>(webhook)
+}
+
+/// Responds webhooks of a user
+#[get("/webhooks/{user_id}")]
+pub async fn get_webhooks(user_id: web::Path<u64>) -> impl Responder {
+    let webhooks = WebhookService::new().get_list(user_id.into_inner());
+    http_util::get_response::<Vec<WebhookDTO>>(webhooks)
+}
+
+/// Deletes a webhook of a user
+#[delete("/webhooks/{user_id}/{id}")]
+pub async fn delete_webhook(web::Path((user_id, id)): web::Path<(u64, u64)>) -> impl Responder {
+    let result = WebhookService::new().delete(user_id, id);
+    http_util::get_response::<bool>(result)
+}
+
+/// Responds recent deliveries of a webhook of a user
+#[get("/webhooks/{user_id}/{id}/deliveries")]
+pub async fn get_webhook_deliveries(
+    web::Path((user_id, id)): web::Path<(u64, u64)>,
+) -> impl Responder {
+    let deliveries = WebhookService::new().get_deliveries(user_id, id);
+    http_util::get_response::<Vec<WebhookDelivery>>(deliveries)
+}
+
+/// Initializes the webhook routes.
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(register_webhook);
+    cfg.service(get_webhooks);
+    cfg.service(delete_webhook);
+    cfg.service(get_webhook_deliveries);
+}
@@ -1,3 +1,50 @@
+table! {
+    backup_codes (id) {
+        id -> Unsigned<Bigint>,
+        user_id -> Unsigned<Bigint>,
+        hashed_code -> Varchar,
+        created_at -> Datetime,
+    }
+}
+
+table! {
+    categories (id) {
+        id -> Unsigned<Bigint>,
+        user_id -> Unsigned<Bigint>,
+        name -> Varchar,
+        created_at -> Datetime,
+    }
+}
+
+table! {
+    invite_codes (id) {
+        id -> Unsigned<Bigint>,
+        code -> Varchar,
+        max_uses -> Unsigned<Integer>,
+        remaining_uses -> Unsigned<Integer>,
+        expires_at -> Nullable<Datetime>,
+        revoked_at -> Nullable<Datetime>,
+        created_at -> Datetime,
+    }
+}
+
+table! {
+    password_history (id) {
+        id -> Unsigned<Bigint>,
+        user_id -> Unsigned<Bigint>,
+        hashed_password -> Varchar,
+        created_at -> Datetime,
+    }
+}
+
+table! {
+    post_tags (id) {
+        id -> Unsigned<Bigint>,
+        post_id -> Unsigned<Bigint>,
+        tag -> Varchar,
+    }
+}
+
 table! {
     posts (id) {
         id -> Unsigned<Bigint>,
@@ -7,6 +54,15 @@ table! {
         date -> Datetime,
         created_at -> Datetime,
         updated_at -> Nullable<Datetime>,
+        deleted_at -> Nullable<Datetime>,
+        version -> Unsigned<Bigint>,
+        publish_at -> Nullable<Datetime>,
+        status -> Varchar,
+        pinned -> Bool,
+        pinned_at -> Nullable<Datetime>,
+        share_token -> Nullable<Varchar>,
+        mood -> Nullable<Varchar>,
+        category_id -> Nullable<Unsigned<Bigint>>,
     }
 }
 
@@ -19,6 +75,30 @@ table! {
         avatar_url -> Nullable<Varchar>,
         created_at -> Datetime,
         updated_at -> Nullable<Datetime>,
+        locked_until -> Nullable<Datetime>,
+        totp_secret -> Nullable<Varchar>,
+        totp_enabled -> Bool,
+        has_password -> Bool,
+        last_login_at -> Nullable<Datetime>,
+        previous_login_at -> Nullable<Datetime>,
+        timezone -> Nullable<Varchar>,
+        reminder_enabled -> Bool,
+        reminder_hour -> Unsigned<Tinyint>,
+        reminder_sent_on -> Nullable<Date>,
+        locale -> Nullable<Varchar>,
+        status -> Varchar,
+        tos_version_accepted -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    user_api_keys (id) {
+        id -> Unsigned<Bigint>,
+        user_id -> Unsigned<Bigint>,
+        prefix -> Varchar,
+        hashed_secret -> Varchar,
+        expires_at -> Nullable<Datetime>,
+        created_at -> Datetime,
     }
 }
 
@@ -29,10 +109,54 @@ table! {
         public_key -> Varchar,
         created_at -> Datetime,
         updated_at -> Nullable<Datetime>,
+        previous_public_key -> Nullable<Varchar>,
+        rotated_at -> Nullable<Datetime>,
+    }
+}
+
+table! {
+    webhook_deliveries (id) {
+        id -> Unsigned<Bigint>,
+        webhook_id -> Unsigned<Bigint>,
+        event -> Varchar,
+        payload -> Text,
+        attempt -> Unsigned<Integer>,
+        status_code -> Nullable<Unsigned<Smallint>>,
+        is_success -> Bool,
+        created_at -> Datetime,
+    }
+}
+
+table! {
+    webhooks (id) {
+        id -> Unsigned<Bigint>,
+        user_id -> Unsigned<Bigint>,
+        url -> Varchar,
+        secret -> Varchar,
+        created_at -> Datetime,
     }
 }
 
+joinable!(backup_codes -> users (user_id));
+joinable!(categories -> users (user_id));
+joinable!(password_history -> users (user_id));
+joinable!(post_tags -> posts (post_id));
+joinable!(posts -> categories (category_id));
 joinable!(posts -> users (user_id));
+joinable!(user_api_keys -> users (user_id));
 joinable!(user_keys -> users (user_id));
+joinable!(webhook_deliveries -> webhooks (webhook_id));
+joinable!(webhooks -> users (user_id));
 
-allow_tables_to_appear_in_same_query!(posts, users,);
+allow_tables_to_appear_in_same_query!(
+    backup_codes,
+    categories,
+    invite_codes,
+    password_history,
+    post_tags,
+    posts,
+    user_api_keys,
+    users,
+    webhook_deliveries,
+    webhooks,
+);
@@ -1,17 +1,58 @@
-use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use chrono::{Duration, NaiveDateTime, Utc};
+use std::cmp::Reverse;
 use std::env;
 
 use crate::models::auth::*;
-use crate::models::error::{get_service_error, ServiceError};
-use crate::models::user::UserRepository;
-use crate::models::user_key::UserKeyRepository;
-use crate::utils::{email_util, password_util};
+use crate::models::backup_code::*;
+use crate::models::error::{get_service_error, FieldError, ServiceError, UnauthorizedReason};
+use crate::models::invite_code::*;
+use crate::models::password_history::*;
+#[cfg(not(test))]
+use crate::models::pwned_password::PwnedPasswordRepository;
+use crate::models::user::*;
+use crate::models::user_key::*;
+#[cfg(not(test))]
+use crate::utils::email_util::EmailSender;
+use crate::utils::email_util::{EmailTemplate, Locale};
+use crate::utils::password_util::{PasswordHashParams, PasswordPolicy};
+use crate::utils::token_util::PinFormat;
+use crate::utils::{
+    device_util, email_util, env_util, metric_util, password_util, token_util, totp_util,
+    trace_util,
+};
+
+/// A number of backup codes generated at once.
+const BACKUP_CODE_COUNT: usize = 10;
+const SIGN_UP_TOKEN_RESEND_INTERVAL_SECONDS: usize = 60;
 
 pub struct AuthService {
     sign_up_token_repository: Option<SignUpTokenRepository>,
     password_token_repository: Option<PasswordTokenRepository>,
+    magic_link_token_repository: Option<MagicLinkTokenRepository>,
+    login_attempt_repository: Option<LoginAttemptRepository>,
+    backup_code_repository: Option<BackupCodeRepository>,
+    known_device_repository: Option<KnownDeviceRepository>,
+    session_epoch_repository: Option<SessionEpochRepository>,
+    active_session_repository: Option<ActiveSessionRepository>,
+    pwned_password_repository: Option<PwnedPasswordRepository>,
     user_key_repository: Option<UserKeyRepository>,
     user_repository: Option<UserRepository>,
+    password_history_repository: Option<PasswordHistoryRepository>,
+    invite_code_repository: Option<InviteCodeRepository>,
+    email_sender: Option<EmailSender>,
+    invite_code_required: bool,
+    login_attempt_limit: u64,
+    login_attempt_window_seconds: usize,
+    login_lock_duration_seconds: i64,
+    sign_up_token_ttl_seconds: usize,
+    password_token_ttl_seconds: usize,
+    magic_link_token_ttl_seconds: usize,
+    device_fingerprint_with_ip: bool,
+    password_hash_params: PasswordHashParams,
+    password_policy: PasswordPolicy,
+    session_lifetime: SessionLifetime,
+    sign_up_pin_format: PinFormat,
+    password_reset_id_format: PinFormat,
 }
 
 impl AuthService {
@@ -19,11 +60,55 @@ impl AuthService {
         Self {
             sign_up_token_repository: None,
             password_token_repository: None,
+            magic_link_token_repository: None,
+            login_attempt_repository: None,
+            backup_code_repository: None,
+            known_device_repository: None,
+            session_epoch_repository: None,
+            active_session_repository: None,
+            pwned_password_repository: None,
             user_key_repository: None,
             user_repository: None,
+            password_history_repository: None,
+            invite_code_repository: None,
+            email_sender: None,
+            invite_code_required: env_util::get_env_var_or("SIGN_UP_INVITE_CODE_REQUIRED", true),
+            login_attempt_limit: env_util::get_env_var_or("LOGIN_ATTEMPT_LIMIT", 5),
+            login_attempt_window_seconds: env_util::get_env_var_or(
+                "LOGIN_ATTEMPT_WINDOW_SECONDS",
+                600,
+            ),
+            login_lock_duration_seconds: env_util::get_env_var_or(
+                "LOGIN_LOCK_DURATION_SECONDS",
+                900,
+            ),
+            sign_up_token_ttl_seconds: env_util::get_env_var_or("SIGN_UP_TOKEN_TTL_SECONDS", 86400),
+            password_token_ttl_seconds: env_util::get_env_var_or(
+                "PASSWORD_TOKEN_TTL_SECONDS",
+                1800,
+            ),
+            magic_link_token_ttl_seconds: env_util::get_env_var_or(
+                "MAGIC_LINK_TOKEN_TTL_SECONDS",
+                600,
+            ),
+            device_fingerprint_with_ip: env_util::get_env_var_or(
+                "LOGIN_DEVICE_FINGERPRINT_WITH_IP",
+                false,
+            ),
+            password_hash_params: PasswordHashParams::from_env(),
+            password_policy: PasswordPolicy::from_env(),
+            session_lifetime: SessionLifetime::from_env(),
+            sign_up_pin_format: PinFormat::sign_up_pin_from_env(),
+            password_reset_id_format: PinFormat::password_reset_id_from_env(),
         }
     }
 
+    /// Returns a builder of the auth service, which sets any of the repositories and the email
+    /// sender.
+    pub fn builder() -> AuthServiceBuilder {
+        AuthServiceBuilder::new()
+    }
+
     fn sign_up_token_repository(
         &mut self,
         new_repository: Option<SignUpTokenRepository>,
@@ -50,6 +135,97 @@ impl AuthService {
         }
     }
 
+    fn magic_link_token_repository(
+        &mut self,
+        new_repository: Option<MagicLinkTokenRepository>,
+    ) -> &mut MagicLinkTokenRepository {
+        match new_repository {
+            Some(_) => {
+                self.magic_link_token_repository = new_repository;
+                self.magic_link_token_repository.as_mut().unwrap()
+            }
+            None => self.magic_link_token_repository.as_mut().unwrap(),
+        }
+    }
+
+    fn login_attempt_repository(
+        &mut self,
+        new_repository: Option<LoginAttemptRepository>,
+    ) -> &mut LoginAttemptRepository {
+        match new_repository {
+            Some(_) => {
+                self.login_attempt_repository = new_repository;
+                self.login_attempt_repository.as_mut().unwrap()
+            }
+            None => self.login_attempt_repository.as_mut().unwrap(),
+        }
+    }
+
+    fn backup_code_repository(
+        &mut self,
+        new_repository: Option<BackupCodeRepository>,
+    ) -> &BackupCodeRepository {
+        match new_repository {
+            Some(_) => {
+                self.backup_code_repository = new_repository;
+                self.backup_code_repository.as_ref().unwrap()
+            }
+            None => self.backup_code_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn known_device_repository(
+        &mut self,
+        new_repository: Option<KnownDeviceRepository>,
+    ) -> &mut KnownDeviceRepository {
+        match new_repository {
+            Some(_) => {
+                self.known_device_repository = new_repository;
+                self.known_device_repository.as_mut().unwrap()
+            }
+            None => self.known_device_repository.as_mut().unwrap(),
+        }
+    }
+
+    fn session_epoch_repository(
+        &mut self,
+        new_repository: Option<SessionEpochRepository>,
+    ) -> &mut SessionEpochRepository {
+        match new_repository {
+            Some(_) => {
+                self.session_epoch_repository = new_repository;
+                self.session_epoch_repository.as_mut().unwrap()
+            }
+            None => self.session_epoch_repository.as_mut().unwrap(),
+        }
+    }
+
+    fn active_session_repository(
+        &mut self,
+        new_repository: Option<ActiveSessionRepository>,
+    ) -> &mut ActiveSessionRepository {
+        match new_repository {
+            Some(_) => {
+                self.active_session_repository = new_repository;
+                self.active_session_repository.as_mut().unwrap()
+            }
+            None => self.active_session_repository.as_mut().unwrap(),
+        }
+    }
+
+    fn pwned_password_repository(
+        &mut self,
+        new_repository: Option<PwnedPasswordRepository>,
+    ) -> &PwnedPasswordRepository {
+        match new_repository {
+            Some(_) => {
+                self.pwned_password_repository = new_repository;
+                self.pwned_password_repository.as_ref().unwrap()
+            }
+            None => self.pwned_password_repository.as_ref().unwrap(),
+        }
+    }
+
     fn user_key_repository(
         &mut self,
         new_repository: Option<UserKeyRepository>,
@@ -63,6 +239,19 @@ impl AuthService {
         }
     }
 
+    fn password_history_repository(
+        &mut self,
+        new_repository: Option<PasswordHistoryRepository>,
+    ) -> &PasswordHistoryRepository {
+        match new_repository {
+            Some(_) => {
+                self.password_history_repository = new_repository;
+                self.password_history_repository.as_ref().unwrap()
+            }
+            None => self.password_history_repository.as_ref().unwrap(),
+        }
+    }
+
     fn user_repository(&mut self, new_repository: Option<UserRepository>) -> &UserRepository {
         match new_repository {
             Some(_) => {
@@ -73,177 +262,3436 @@ impl AuthService {
         }
     }
 
+    fn invite_code_repository(
+        &mut self,
+        new_repository: Option<InviteCodeRepository>,
+    ) -> &InviteCodeRepository {
+        match new_repository {
+            Some(_) => {
+                self.invite_code_repository = new_repository;
+                self.invite_code_repository.as_ref().unwrap()
+            }
+            None => self.invite_code_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn email_sender(&mut self, new_email_sender: Option<EmailSender>) -> &EmailSender {
+        match new_email_sender {
+            Some(_) => {
+                self.email_sender = new_email_sender;
+                self.email_sender.as_ref().unwrap()
+            }
+            None => self.email_sender.as_ref().unwrap(),
+        }
+    }
+
+    /// Counts a failed login attempt, and locks the user if it exceeds the limit.
+    fn record_failed_login(
+        &mut self,
+        user_id: u64,
+        now: &NaiveDateTime,
+    ) -> Result<(), ServiceError> {
+        let window_seconds = self.login_attempt_window_seconds;
+        let attempt_count = self
+            .login_attempt_repository(None)
+            .increase(window_seconds)?;
+
+        if attempt_count >= self.login_attempt_limit {
+            let locked_until = *now + Duration::seconds(self.login_lock_duration_seconds);
+            self.user_repository(None)
+                .update_locked_until(user_id, &Some(locked_until))?;
+        }
+
+        Ok(())
+    }
+
+    /// Finds a backup code of the user matching the code from arguments, and deletes it.
+    /// Returns whether the matching backup code has been found.
+    fn consume_backup_code(&mut self, user_id: u64, code: &str) -> Result<bool, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.backup_code_repository.is_none() => BackupCodeRepository::new());
+        let backup_code_list = self
+            .backup_code_repository(fallback_repository)
+            .find_all_by_user_id(user_id)?;
+
+        let matching_backup_code = backup_code_list
+            .iter()
+            .find(|backup_code| password_util::check_password(code, &backup_code.hashed_code));
+
+        if let Some(backup_code) = matching_backup_code {
+            self.backup_code_repository(None).delete(backup_code.id)
+        } else {
+            Ok(false)
+        }
+    }
+
     /// Signs in to set user session.
     ///
-    /// 1. Finds password of the user by email from arguments.
-    /// 2. Compares password from the found user and it from the arguments.
-    /// 3. If the passwords are equal, returns the found user.
-    pub fn login(&mut self, email: &str, password: &str) -> Result<UserSession, ServiceError> {
+    /// 1. Normalizes the email from arguments, and checks the number of failed login attempts of the email.
+    /// 2. Finds the user by email from arguments, and checks whether the user is locked.
+    /// 3. Compares password from the found user and it from the arguments, and checks whether the user
+    ///    is deactivated.
+    /// 4. If the user enabled TOTP, checks the TOTP code from the arguments.
+    ///    A backup code is also accepted in place of the TOTP code, and consumed once it is used.
+    /// 5. If any of them is invalid, counts the failure and locks the user if it exceeds the limit.
+    /// 6. If all of them are valid, clears failed login attempts and the lock, and returns the found user.
+    ///    The password is rehashed with the current Argon2id parameters if it was hashed by the legacy scheme.
+    /// 7. Records the login time, and keeps the time of the previous login in the session.
+    /// 8. If the User-Agent is given, notifies the user by email when the device is not known.
+    /// 9. Counts the login as a success or a failure.
+    pub fn login(
+        &mut self,
+        email: &str,
+        password: &str,
+        totp_code: Option<&str>,
+        user_agent: Option<&str>,
+        ip_address: Option<&str>,
+    ) -> Result<UserSession, ServiceError> {
+        let _span = trace_util::start_span("AuthService::login", None);
+        let result = self.try_login(email, password, totp_code, user_agent, ip_address);
+        metric_util::count_login(result.is_ok());
+        result
+    }
+
+    /// Signs in to set user session, without counting the login.
+    fn try_login(
+        &mut self,
+        email: &str,
+        password: &str,
+        totp_code: Option<&str>,
+        user_agent: Option<&str>,
+        ip_address: Option<&str>,
+    ) -> Result<UserSession, ServiceError> {
+        let email = &email_util::normalize(email);
+
+        let attempt_count = {
+            let fallback_repository = some_if_true!(self.login_attempt_repository.is_none() => LoginAttemptRepository::new(email));
+            self.login_attempt_repository(fallback_repository).find()?
+        };
+
+        if attempt_count >= self.login_attempt_limit {
+            return Err(get_service_error(ServiceError::TooManyRequests(
+                self.login_attempt_window_seconds as u64,
+            )));
+        }
+
         let user = {
             let fallback_repository =
                 some_if_true!(self.user_repository.is_none() => UserRepository::new());
-            let found_password = self
-                .user_repository(fallback_repository)
-                .find_password_by_email(email)?;
+            self.user_repository(fallback_repository)
+                .find_by_email(email)?
+        };
 
-            if password_util::check_password(password, &found_password) {
-                self.user_repository(None).find_by_email(email)?
-            } else {
-                return Err(ServiceError::Unauthorized);
+        let now = Utc::now().naive_utc();
+        if let Some(locked_until) = user.locked_until {
+            if locked_until > now {
+                return Err(get_service_error(ServiceError::Unauthorized(Some(
+                    UnauthorizedReason::AccountLocked(locked_until.to_string()),
+                ))));
             }
+        }
+
+        if !password_util::check_password(password, &user.password) {
+            self.record_failed_login(user.id, &now)?;
+            return Err(ServiceError::Unauthorized(None));
+        }
+
+        // It is told only to the user who knows the password, not to reveal the account to others.
+        if user.status == UserStatus::Deactivated {
+            return Err(get_service_error(ServiceError::Unauthorized(Some(
+                UnauthorizedReason::AccountDeactivated,
+            ))));
+        }
+
+        self.verify_totp_code(&user, totp_code, &now)?;
+
+        self.login_attempt_repository(None).delete()?;
+        if user.locked_until.is_some() {
+            self.user_repository(None)
+                .update_locked_until(user.id, &None)?;
+        }
+        if password_util::needs_rehash(&user.password, &self.password_hash_params) {
+            let rehashed_password = password_util::get_hashed_password_with_params(
+                password,
+                &self.password_hash_params,
+            );
+            self.user_repository(None).update(
+                user.id,
+                &None,
+                &Some(rehashed_password),
+                &None,
+                &None,
+            )?;
+        }
+        if let Some(user_agent) = user_agent {
+            // The login succeeds even if the device can't be checked.
+            let _ = self.register_login_device(&user, user_agent, ip_address);
+        }
+
+        self.start_user_session(user, &now, user_agent)
+    }
+
+    /// Checks the TOTP code from the arguments if the user enabled TOTP.
+    /// A backup code is also accepted in place of the TOTP code, and consumed once it is used.
+    /// A wrong code is counted as a failed login of the user.
+    ///
+    /// It returns `ServiceError::TotpRequired` if the code is not given,
+    /// and `ServiceError::Unauthorized` if it is wrong.
+    fn verify_totp_code(
+        &mut self,
+        user: &User,
+        totp_code: Option<&str>,
+        now: &NaiveDateTime,
+    ) -> Result<(), ServiceError> {
+        if !user.totp_enabled {
+            return Ok(());
+        }
+
+        let totp_code = if let Some(totp_code) = totp_code {
+            totp_code
+        } else {
+            return Err(get_service_error(ServiceError::TotpRequired));
         };
 
-        let logged_in_user_session = {
-            let user_public_key = {
-                let fallback_repository =
-                    some_if_true!(self.user_key_repository.is_none() => UserKeyRepository::new());
-                self.user_key_repository(fallback_repository)
-                    .find_by_user_id(user.id)?
-                    .public_key
-            };
+        let is_valid_totp_code = match &user.totp_secret {
+            Some(totp_secret) => totp_util::check_code(totp_secret, totp_code),
+            None => false,
+        };
+
+        if !is_valid_totp_code && !self.consume_backup_code(user.id, totp_code)? {
+            self.record_failed_login(user.id, now)?;
+            return Err(ServiceError::Unauthorized(None));
+        }
+
+        Ok(())
+    }
 
-            UserSession {
-                user_id: user.id,
-                user_email: user.email,
-                user_name: user.name,
-                user_public_key,
-                user_avatar_url: user.avatar_url,
+    /// Starts the session of the user signed in with an OAuth provider.
+    ///
+    /// 1. Checks whether the user is locked or deactivated.
+    /// 2. If the user enabled TOTP, checks the TOTP code from the arguments as the password login does.
+    ///    A backup code is also accepted in place of the TOTP code, and consumed once it is used.
+    /// 3. Returns the session of the user.
+    pub fn start_oauth_session(
+        &mut self,
+        user: User,
+        totp_code: Option<&str>,
+    ) -> Result<UserSession, ServiceError> {
+        let _span = trace_util::start_span("AuthService::start_oauth_session", Some(user.id));
+        let now = Utc::now().naive_utc();
+        if let Some(locked_until) = user.locked_until {
+            if locked_until > now {
+                return Err(get_service_error(ServiceError::Unauthorized(Some(
+                    UnauthorizedReason::AccountLocked(locked_until.to_string()),
+                ))));
             }
-        };
+        }
+        if user.status == UserStatus::Deactivated {
+            return Err(get_service_error(ServiceError::Unauthorized(Some(
+                UnauthorizedReason::AccountDeactivated,
+            ))));
+        }
+
+        if user.totp_enabled {
+            let fallback_repository = some_if_true!(self.login_attempt_repository.is_none() => LoginAttemptRepository::new(&user.email));
+            self.login_attempt_repository(fallback_repository);
+            let fallback_repository =
+                some_if_true!(self.user_repository.is_none() => UserRepository::new());
+            self.user_repository(fallback_repository);
+
+            self.verify_totp_code(&user, totp_code, &now)?;
+            self.login_attempt_repository(None).delete()?;
+        }
 
-        Ok(logged_in_user_session)
+        self.start_user_session(user, &now, None)
     }
 
-    /// Sets token for sign up process.
+    /// Adds the device used to log in to the known devices of the user,
+    /// and sends a notification email if the device is new. It returns whether the device is new.
     ///
-    /// 1. Generates a random string called pin.
-    /// 2. Creates a new token containing the pin and information of the user from arguments.
-    /// 3. Serializes the token and inserts it to redis.
-    pub fn set_sign_up_token(
+    /// The device is identified by the fingerprint of the User-Agent,
+    /// and also of the truncated IP address if `LOGIN_DEVICE_FINGERPRINT_WITH_IP` is set.
+    /// The email is sent in the background not to delay the login.
+    fn register_login_device(
         &mut self,
-        name: &str,
-        email: &str,
-        password: &str,
-        avatar_url: &Option<String>,
-    ) -> Result<String, ServiceError> {
-        if name.trim().is_empty() || email.trim().is_empty() || password.trim().is_empty() {
-            return Err(get_service_error(ServiceError::InvalidArgument));
+        user: &User,
+        user_agent: &str,
+        ip_address: Option<&str>,
+    ) -> Result<bool, ServiceError> {
+        let ip_address = if self.device_fingerprint_with_ip {
+            ip_address
+        } else {
+            None
+        };
+        let fingerprint = device_util::get_fingerprint(user_agent, ip_address);
+
+        let is_new_device = {
+            let fallback_repository = some_if_true!(self.known_device_repository.is_none() => KnownDeviceRepository::new(user.id));
+            self.known_device_repository(fallback_repository)
+                .add(&fingerprint)?
+        };
+
+        if is_new_device {
+            let to = format!("{} <{}>", user.name, user.email);
+            let email_content = format!(
+                "Hello :)<br/><br/>\
+                Your account has been accessed from a new device:<br/><br/>\
+                <div style=\"background-color: #f0f0f0; padding: 10px\">{}</div><br/><br/>\
+                If it wasn't you, please reset your password and sign out of all sessions.",
+                user_agent.replace('<', "&lt;").replace('>', "&gt;"),
+            );
+
+            let fallback_email_sender =
+                some_if_true!(self.email_sender.is_none() => EmailSender::new());
+            self.email_sender(fallback_email_sender).send(
+                &to,
+                &String::from("New login to Darim 🔔"),
+                &email_content,
+            );
         }
 
-        let pin: String = thread_rng().sample_iter(&Alphanumeric).take(8).collect();
-        let hashed_password = password_util::get_hashed_password(password);
+        Ok(is_new_device)
+    }
 
-        let token = SignUpToken {
-            pin,
-            name: name.to_string(),
-            email: email.to_string(),
-            password: hashed_password,
-            avatar_url: avatar_url.clone(),
+    /// Records the login time of the user, adds the session to the active sessions of the user
+    /// with the User-Agent, and returns the session of the logged-in user.
+    pub fn start_user_session(
+        &mut self,
+        user: User,
+        now: &NaiveDateTime,
+        user_agent: Option<&str>,
+    ) -> Result<UserSession, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.user_repository.is_none() => UserRepository::new());
+        self.user_repository(fallback_repository)
+            .update_last_login_at(user.id, now)?;
+
+        // A user created by OAuth doesn't have a public key until the client registers it.
+        let user_public_key = {
+            let fallback_repository =
+                some_if_true!(self.user_key_repository.is_none() => UserKeyRepository::new());
+            match self
+                .user_key_repository(fallback_repository)
+                .find_by_user_id(user.id)
+            {
+                Ok(user_key) => user_key.public_key,
+                Err(ServiceError::NotFound(_)) => String::new(),
+                Err(error) => return Err(error),
+            }
         };
 
-        let serialized_token = serde_json::to_string(&token);
-        let serialized_token = if let Ok(serialized_token) = serialized_token {
-            serialized_token
-        } else {
-            return Err(get_service_error(ServiceError::InvalidFormat));
+        let user_session_epoch = {
+            let fallback_repository = some_if_true!(self.session_epoch_repository.is_none() => SessionEpochRepository::new(user.id));
+            self.session_epoch_repository(fallback_repository).find()?
         };
 
-        // FIXME: The compiler doesn't consider it string type when running test.
-        let result = {
-            let fallback_repository = some_if_true!(self.sign_up_token_repository.is_none() => SignUpTokenRepository::new());
-            self.sign_up_token_repository(fallback_repository)
-                .save(&serialized_token)?
-                .to_string()
+        let user_session_id = token_util::get_random_string(32);
+        let active_session = ActiveSession {
+            id: user_session_id.clone(),
+            user_agent: user_agent.map(String::from),
+            created_at: *now,
+        };
+        let ttl_seconds = self.session_lifetime.max_lifetime.num_seconds() as usize;
+        let fallback_repository = some_if_true!(self.active_session_repository.is_none() => ActiveSessionRepository::new(user.id));
+        self.active_session_repository(fallback_repository)
+            .save(&active_session, ttl_seconds)?;
+
+        Ok(UserSession {
+            user_id: user.id,
+            user_email: user.email,
+            user_name: user.name,
+            user_public_key,
+            user_avatar_url: user.avatar_url,
+            user_session_epoch,
+            user_session_id,
+            user_last_login_at: Some(*now),
+            user_previous_login_at: user.last_login_at,
+            user_tos_version_accepted: user.tos_version_accepted,
+            user_session_started_at: *now,
+            user_session_expires_at: self.session_lifetime.get_expiry(now, now),
+        })
+    }
+
+    /// Requests a magic link to log in without password.
+    ///
+    /// 1. Finds the user by email from arguments.
+    /// 2. Generates a single-use token that expires after the magic link token TTL, and stores the id of the user with it.
+    /// 3. Sends the email containing the login link with the token.
+    ///
+    /// It returns `true` even if the user doesn't exist, not to reveal whether the email is registered.
+    /// The link isn't sent to the user who enabled TOTP, because it would bypass the TOTP code.
+    pub fn request_magic_link(&mut self, email: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("AuthService::request_magic_link", None);
+        let email = &email_util::normalize(email);
+
+        let user = {
+            let fallback_repository =
+                some_if_true!(self.user_repository.is_none() => UserRepository::new());
+            match self
+                .user_repository(fallback_repository)
+                .find_by_email(email)
+            {
+                Ok(user) => user,
+                Err(ServiceError::NotFound(_)) => return Ok(true),
+                Err(error) => return Err(error),
+            }
         };
 
+        if user.totp_enabled {
+            return Ok(true);
+        }
+
+        let token = token_util::get_random_string(32);
+        {
+            let ttl_seconds = self.magic_link_token_ttl_seconds;
+            let fallback_repository = some_if_true!(self.magic_link_token_repository.is_none() => MagicLinkTokenRepository::new(&token));
+            self.magic_link_token_repository(fallback_repository)
+                .save(user.id, ttl_seconds)?;
+        }
+
+        let client_address = env::var("CLIENT_ADDRESS").expect("CLIENT_ADDRESS not found");
         let email_content = format!(
-            "<h1>🏕 Welcome to Darim</h1>\
-            <h2>Hello {} :)</h2>\
-            You've joined Darim.<br/><br/>\
-            Please copy the key below to finish the sign up process:<br/><br/>\
-            <div style=\"background-color: #f0f0f0; padding: 10px; font-size: 20px; font-weight: bold\">{}</div>",
-            token.name, token.pin,
+            "Hello :)<br/><br/>\
+            Please visit the link to log in to Darim:<br/><br/>\
+            <a href=\"{}/magic_link/{}\">{}/magic_link/{}</a><br/><br/>\
+            The link can be used only once, and it expires in {} minutes.",
+            client_address,
+            token,
+            client_address,
+            token,
+            self.magic_link_token_ttl_seconds / 60,
         );
 
-        let _ = email_util::send_email(
-            &format!("{} <{}>", &token.name, &token.email),
-            &String::from("Welcome to Darim 🎉"),
+        let fallback_email_sender =
+            some_if_true!(self.email_sender.is_none() => EmailSender::new());
+        self.email_sender(fallback_email_sender).send(
+            &format!("{} <{}>", user.name, email),
+            &String::from("Log in to Darim 🔑"),
             &email_content,
         );
 
-        Ok(result)
+        Ok(true)
     }
 
-    /// Sets token for temporary password deposition in password finding process.
-    pub fn set_password_token(&mut self, email: &str) -> Result<bool, ServiceError> {
+    /// Logs in with the token of the magic link.
+    ///
+    /// 1. Finds the id of the user by the token from arguments.
+    /// 2. Finds the user, and checks whether the user is locked or deactivated.
+    /// 3. If the user enabled TOTP, checks the TOTP code from the arguments as the password login does,
+    ///    so that the magic link alone can't bypass the second factor.
+    ///    The token is kept if the code is missing or wrong, so that the link can be retried with the code.
+    /// 4. Deletes the token so that it can't be used again, and returns the session of the user.
+    ///
+    /// It returns `ServiceError::Unauthorized` if the token has expired or has already been used,
+    /// and `ServiceError::TotpRequired` if the user enabled TOTP but the code is not given.
+    pub fn consume_magic_link(
+        &mut self,
+        token: &str,
+        totp_code: Option<&str>,
+    ) -> Result<UserSession, ServiceError> {
+        let _span = trace_util::start_span("AuthService::consume_magic_link", None);
+        let user_id = {
+            let fallback_repository = some_if_true!(self.magic_link_token_repository.is_none() => MagicLinkTokenRepository::new(token));
+            match self.magic_link_token_repository(fallback_repository).find() {
+                Ok(user_id) => user_id,
+                Err(ServiceError::NotFound(_)) => {
+                    return Err(get_service_error(ServiceError::Unauthorized(None)))
+                }
+                Err(error) => return Err(error),
+            }
+        };
+
         let user = {
             let fallback_repository =
                 some_if_true!(self.user_repository.is_none() => UserRepository::new());
             self.user_repository(fallback_repository)
-                .find_by_email(email)?
+                .find_by_id(user_id)?
         };
 
-        let token = PasswordToken {
-            id: thread_rng().sample_iter(&Alphanumeric).take(32).collect(),
-            password: thread_rng().sample_iter(&Alphanumeric).take(512).collect(),
+        let now = Utc::now().naive_utc();
+        if let Some(locked_until) = user.locked_until {
+            if locked_until > now {
+                return Err(get_service_error(ServiceError::Unauthorized(Some(
+                    UnauthorizedReason::AccountLocked(locked_until.to_string()),
+                ))));
+            }
+        }
+        if user.status == UserStatus::Deactivated {
+            return Err(get_service_error(ServiceError::Unauthorized(Some(
+                UnauthorizedReason::AccountDeactivated,
+            ))));
+        }
+
+        if user.totp_enabled {
+            let fallback_repository = some_if_true!(self.login_attempt_repository.is_none() => LoginAttemptRepository::new(&user.email));
+            self.login_attempt_repository(fallback_repository);
+
+            self.verify_totp_code(&user, totp_code, &now)?;
+            self.login_attempt_repository(None).delete()?;
+        }
+
+        // Only one of concurrent requests with the same token can delete it.
+        if !self.magic_link_token_repository(None).delete()? {
+            return Err(get_service_error(ServiceError::Unauthorized(None)));
+        }
+
+        self.start_user_session(user, &now, None)
+    }
+
+    /// Checks whether the session epoch from arguments is the current session epoch of the user.
+    /// Returns `ServiceError::Unauthorized` if the session has been invalidated.
+    pub fn validate_session_epoch(
+        &mut self,
+        user_id: u64,
+        user_session_epoch: u64,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("AuthService::validate_session_epoch", Some(user_id));
+        let current_epoch = {
+            let fallback_repository = some_if_true!(self.session_epoch_repository.is_none() => SessionEpochRepository::new(user_id));
+            self.session_epoch_repository(fallback_repository).find()?
         };
 
-        let serialized_token = serde_json::to_string(&token);
-        let serialized_token = if let Ok(serialized_token) = serialized_token {
-            serialized_token
+        if user_session_epoch == current_epoch {
+            Ok(true)
         } else {
-            return Err(get_service_error(ServiceError::InvalidFormat));
+            Err(get_service_error(ServiceError::Unauthorized(None)))
+        }
+    }
+
+    /// Refreshes the session of the user, and returns the extended expiry of the session.
+    ///
+    /// 1. Checks whether the session has neither been invalidated nor revoked.
+    /// 2. Checks whether the session has neither been idle longer than the idle timeout,
+    ///    nor lived longer than the maximum lifetime since the login.
+    /// 3. Pushes the expiry forward by the idle timeout, but never beyond the maximum lifetime.
+    ///
+    /// It returns `ServiceError::Unauthorized` if the session has expired, so that the user logs in again.
+    pub fn refresh_user_session(
+        &mut self,
+        user_id: u64,
+        user_session_epoch: u64,
+        user_session_id: &str,
+        user_session_started_at: &NaiveDateTime,
+        user_session_expires_at: &NaiveDateTime,
+        now: &NaiveDateTime,
+    ) -> Result<NaiveDateTime, ServiceError> {
+        let _span = trace_util::start_span("AuthService::refresh_user_session", Some(user_id));
+        self.validate_session_epoch(user_id, user_session_epoch)?;
+
+        let is_active_session = {
+            let fallback_repository = some_if_true!(self.active_session_repository.is_none() => ActiveSessionRepository::new(user_id));
+            self.active_session_repository(fallback_repository)
+                .exists(user_session_id)?
         };
+        if !is_active_session {
+            return Err(get_service_error(ServiceError::Unauthorized(None)));
+        }
 
-        let result = {
-            let fallback_repository = some_if_true!(self.password_token_repository.is_none() => PasswordTokenRepository::new(user.id));
-            self.password_token_repository(fallback_repository)
-                .save(&serialized_token)?
+        let max_expires_at = *user_session_started_at + self.session_lifetime.max_lifetime;
+        if *now >= *user_session_expires_at || *now >= max_expires_at {
+            return Err(get_service_error(ServiceError::Unauthorized(None)));
+        }
+
+        Ok(self
+            .session_lifetime
+            .get_expiry(user_session_started_at, now))
+    }
+
+    /// Invalidates all sessions of the user by increasing the session epoch,
+    /// and clears the active sessions of the user.
+    pub fn invalidate_all_sessions(&mut self, user_id: u64) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("AuthService::invalidate_all_sessions", Some(user_id));
+        let fallback_repository = some_if_true!(self.session_epoch_repository.is_none() => SessionEpochRepository::new(user_id));
+        self.session_epoch_repository(fallback_repository)
+            .increase()?;
+
+        let fallback_repository = some_if_true!(self.active_session_repository.is_none() => ActiveSessionRepository::new(user_id));
+        self.active_session_repository(fallback_repository)
+            .delete_all()?;
+        Ok(true)
+    }
+
+    /// Lists the active sessions of the user from the latest one.
+    /// Sessions which have reached the maximum lifetime are excluded.
+    pub fn get_active_sessions(
+        &mut self,
+        user_id: u64,
+        now: &NaiveDateTime,
+    ) -> Result<Vec<ActiveSession>, ServiceError> {
+        let _span = trace_util::start_span("AuthService::get_active_sessions", Some(user_id));
+        let mut active_sessions = {
+            let fallback_repository = some_if_true!(self.active_session_repository.is_none() => ActiveSessionRepository::new(user_id));
+            self.active_session_repository(fallback_repository)
+                .find_all()?
         };
 
-        let client_address = env::var("CLIENT_ADDRESS").expect("CLIENT_ADDRESS not found");
-        let email_content = format!(
-            "Hello :)<br/><br/>\
-            Please copy the temporary password:<br/><br/>\
-            <div style=\"background-color: #f0f0f0; padding: 10px; font-weight: bold\">{}</div><br/><br/>\
-            and visit the link to reset your password:<br/><br/>\
-            <a href=\"{}/password_reset/{}\">{}/password_reset/{}</a>",
-            token.password, client_address, token.id, client_address, token.id,
-        );
+        let max_lifetime = self.session_lifetime.max_lifetime;
+        active_sessions.retain(|active_session| active_session.created_at + max_lifetime > *now);
+        active_sessions.sort_by_key(|active_session| Reverse(active_session.created_at));
 
-        let _ = email_util::send_email(
-            &format!("{} <{}>", user.name, email),
-            &String::from("Please reset your password 🔒"),
-            &email_content,
-        );
+        Ok(active_sessions)
+    }
 
-        Ok(result)
+    /// Revokes all active sessions of the user except the current session.
+    pub fn revoke_other_sessions(
+        &mut self,
+        user_id: u64,
+        user_session_id: &str,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("AuthService::revoke_other_sessions", Some(user_id));
+        let fallback_repository = some_if_true!(self.active_session_repository.is_none() => ActiveSessionRepository::new(user_id));
+        let active_session_repository = self.active_session_repository(fallback_repository);
+        for active_session in active_session_repository.find_all()? {
+            if active_session.id != user_session_id {
+                active_session_repository.delete(&active_session.id)?;
+            }
+        }
+        Ok(true)
     }
-}
 
-impl Default for AuthService {
-    fn default() -> Self {
-        Self::new()
+    /// Revokes the active session of the user, so that the session can't be refreshed anymore.
+    /// Returns `ServiceError::NotFound` if the session is not active.
+    pub fn revoke_session(&mut self, user_id: u64, id: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("AuthService::revoke_session", Some(user_id));
+        let fallback_repository = some_if_true!(self.active_session_repository.is_none() => ActiveSessionRepository::new(user_id));
+        if self
+            .active_session_repository(fallback_repository)
+            .delete(id)?
+        {
+            Ok(true)
+        } else {
+            Err(get_service_error(ServiceError::NotFound(id.to_string())))
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Changes the password of the user.
+    ///
+    /// 1. Checks whether the current password is correct.
+    ///    It returns `ServiceError::Unauthorized` if the current password is wrong.
+    /// 2. Validates the strength of the new password.
+    ///    With `hibp` feature, it also rejects the new password found in data breaches.
+    /// 3. Checks whether the new password is neither the current password nor one of the recent passwords.
+    ///    It returns `ServiceError::InvalidArgument` if the password is reused.
+    /// 4. Hashes the new password, updates the password of the user,
+    ///    and pushes the previous password into the password history.
+    pub async fn change_password(
+        &mut self,
+        user_id: u64,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("AuthService::change_password", Some(user_id));
+        let fallback_repository =
+            some_if_true!(self.user_repository.is_none() => UserRepository::new());
+        let user = self
+            .user_repository(fallback_repository)
+            .find_by_id(user_id)?;
 
-    impl AuthService {
-        pub fn new_with_repository(
-            sign_up_token_repository: SignUpTokenRepository,
-            password_token_repository: PasswordTokenRepository,
-            user_key_repository: UserKeyRepository,
-            user_repository: UserRepository,
-        ) -> Self {
-            Self {
-                sign_up_token_repository: Some(sign_up_token_repository),
-                password_token_repository: Some(password_token_repository),
-                user_key_repository: Some(user_key_repository),
-                user_repository: Some(user_repository),
-            }
+        if !user.has_password || !password_util::check_password(current_password, &user.password) {
+            return Err(get_service_error(ServiceError::Unauthorized(None)));
         }
+
+        if let Err(error) =
+            password_util::validate_strength_with_policy(new_password, &self.password_policy)
+        {
+            return Err(get_service_error(ServiceError::Validation(vec![
+                FieldError::new("new_password", &error.to_string()),
+            ])));
+        }
+
+        if cfg!(feature = "hibp") {
+            let fallback_repository = some_if_true!(self.pwned_password_repository.is_none() => PwnedPasswordRepository::new());
+            let pwned_password_repository = self.pwned_password_repository(fallback_repository);
+            if password_util::is_breached(new_password, pwned_password_repository).await {
+                return Err(get_service_error(ServiceError::Validation(vec![
+                    FieldError::new("new_password", "password is found in data breaches"),
+                ])));
+            }
+        }
+
+        let history_size = self.password_policy.history_size;
+        if history_size > 0 {
+            let mut used_passwords = vec![user.password.clone()];
+            let fallback_repository = some_if_true!(self.password_history_repository.is_none() => PasswordHistoryRepository::new());
+            used_passwords.extend(
+                self.password_history_repository(fallback_repository)
+                    .find_recent_by_user_id(user_id, history_size)?
+                    .into_iter()
+                    .map(|password_history| password_history.hashed_password),
+            );
+            if password_util::is_reused(new_password, &used_passwords) {
+                return Err(get_service_error(ServiceError::InvalidArgument));
+            }
+        }
+
+        let hashed_password = password_util::get_hashed_password_with_params(
+            new_password,
+            &self.password_hash_params,
+        );
+        self.user_repository(None)
+            .update(user_id, &None, &Some(hashed_password), &None, &None)?;
+
+        if history_size > 0 {
+            let password_history_repository = self.password_history_repository(None);
+            password_history_repository.create(user_id, &user.password)?;
+            password_history_repository.delete_all_except_recent(user_id, history_size)?;
+        }
+        Ok(true)
+    }
+
+    /// Starts TOTP enrollment of the user.
+    ///
+    /// 1. Generates a new secret and stores it to the user as an unconfirmed secret.
+    /// 2. Returns the secret and the provisioning URI to be registered to authenticator apps.
+    pub fn enroll_totp(&mut self, user_id: u64) -> Result<TotpEnrollment, ServiceError> {
+        let _span = trace_util::start_span("AuthService::enroll_totp", Some(user_id));
+        let user = {
+            let fallback_repository =
+                some_if_true!(self.user_repository.is_none() => UserRepository::new());
+            self.user_repository(fallback_repository)
+                .find_by_id(user_id)?
+        };
+
+        if user.totp_enabled {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        let secret = totp_util::generate_secret();
+        self.user_repository(None)
+            .update_totp(user.id, &Some(secret.clone()), false)?;
+
+        Ok(TotpEnrollment {
+            provisioning_uri: totp_util::get_provisioning_uri(&secret, &user.email),
+            secret,
+        })
+    }
+
+    /// Confirms TOTP enrollment of the user.
+    ///
+    /// 1. Finds the unconfirmed secret of the user.
+    /// 2. Checks the code from arguments against the secret.
+    /// 3. If the code is valid, enables TOTP of the user.
+    pub fn confirm_totp(&mut self, user_id: u64, code: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("AuthService::confirm_totp", Some(user_id));
+        let user = {
+            let fallback_repository =
+                some_if_true!(self.user_repository.is_none() => UserRepository::new());
+            self.user_repository(fallback_repository)
+                .find_by_id(user_id)?
+        };
+
+        let secret = if let Some(secret) = user.totp_secret {
+            secret
+        } else {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        };
+
+        if totp_util::check_code(&secret, code) {
+            self.user_repository(None)
+                .update_totp(user.id, &Some(secret), true)
+        } else {
+            Err(get_service_error(ServiceError::Unauthorized(None)))
+        }
+    }
+
+    /// Generates backup codes of the user used in place of TOTP codes.
+    ///
+    /// 1. Deletes all previous backup codes of the user.
+    /// 2. Generates new random codes and stores their hashes.
+    /// 3. Returns the plain codes. They can not be found again after this.
+    pub fn generate_backup_codes(&mut self, user_id: u64) -> Result<Vec<String>, ServiceError> {
+        let _span = trace_util::start_span("AuthService::generate_backup_codes", Some(user_id));
+        let fallback_repository =
+            some_if_true!(self.backup_code_repository.is_none() => BackupCodeRepository::new());
+        self.backup_code_repository(fallback_repository)
+            .delete_all_by_user_id(user_id)?;
+
+        let backup_code_list: Vec<String> = (0..BACKUP_CODE_COUNT)
+            .map(|_| token_util::get_random_string(10))
+            .collect();
+
+        for backup_code in &backup_code_list {
+            let hashed_code = password_util::get_hashed_password_with_params(
+                backup_code,
+                &self.password_hash_params,
+            );
+            self.backup_code_repository(None)
+                .create(user_id, &hashed_code)?;
+        }
+
+        Ok(backup_code_list)
+    }
+
+    /// Consumes a use of the invite code if invite codes are required to sign up, which is set by
+    /// `SIGN_UP_INVITE_CODE_REQUIRED`. It returns `ServiceError::Unauthorized` if the code is
+    /// missing, unknown, exhausted, expired or revoked.
+    pub fn consume_invite_code(&mut self, invite_code: Option<&str>) -> Result<(), ServiceError> {
+        if !self.invite_code_required {
+            return Ok(());
+        }
+
+        let invite_code = match invite_code {
+            Some(invite_code) => invite_code.trim(),
+            None => return Err(get_service_error(ServiceError::Unauthorized(None))),
+        };
+        let fallback_repository =
+            some_if_true!(self.invite_code_repository.is_none() => InviteCodeRepository::new());
+        let consumed = self
+            .invite_code_repository(fallback_repository)
+            .consume(invite_code, Utc::now().naive_utc())?;
+        if !consumed {
+            return Err(get_service_error(ServiceError::Unauthorized(None)));
+        }
+
+        Ok(())
+    }
+
+    /// Sets token for sign up process.
+    ///
+    /// 1. Checks the name, the email and the strength of the password against the password policy,
+    ///    and returns `ServiceError::Validation` with all invalid fields.
+    ///    With `hibp` feature, it also rejects the password found in data breaches.
+    ///    It returns `ServiceError::InvalidArgument` if the email is of a disposable email service
+    ///    in the blocklist.
+    /// 2. If invite codes are required, consumes a use of the invite code, and returns
+    ///    `ServiceError::Unauthorized` if the code is missing, unknown, exhausted, expired or
+    ///    revoked. It is consumed before checking the email, not to tell whether the email is
+    ///    registered to anyone without a valid code.
+    /// 3. Normalizes the email and returns `ServiceError::Conflict` if the user of the email already exists.
+    /// 4. Generates a random string called pin from a cryptographically secure RNG.
+    /// 5. Creates a new token containing the pin and information of the user from arguments.
+    /// 6. Serializes the token and inserts it to redis. The token expires after the sign up token TTL.
+    /// 7. Sends the pin by email in the locale, which is saved as the locale of the user on sign up.
+    pub async fn set_sign_up_token(
+        &mut self,
+        name: &str,
+        email: &str,
+        password: &str,
+        avatar_url: &Option<String>,
+        locale: &Option<String>,
+        invite_code: &Option<String>,
+    ) -> Result<String, ServiceError> {
+        let email = &email_util::normalize(email);
+
+        let mut field_errors = vec![];
+        if name.trim().is_empty() {
+            field_errors.push(FieldError::new("name", "must not be empty"));
+        }
+        if email.trim().is_empty() {
+            field_errors.push(FieldError::new("email", "must not be empty"));
+        }
+        if password.trim().is_empty() {
+            field_errors.push(FieldError::new("password", "must not be empty"));
+        } else if let Err(error) =
+            password_util::validate_strength_with_policy(password, &self.password_policy)
+        {
+            field_errors.push(FieldError::new("password", &error.to_string()));
+        }
+        if !field_errors.is_empty() {
+            return Err(get_service_error(ServiceError::Validation(field_errors)));
+        }
+
+        if email_util::is_disposable(email) {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        if cfg!(feature = "hibp") {
+            let fallback_repository = some_if_true!(self.pwned_password_repository.is_none() => PwnedPasswordRepository::new());
+            let pwned_password_repository = self.pwned_password_repository(fallback_repository);
+            if password_util::is_breached(password, pwned_password_repository).await {
+                return Err(get_service_error(ServiceError::Validation(vec![
+                    FieldError::new("password", "password is found in data breaches"),
+                ])));
+            }
+        }
+
+        self.consume_invite_code(invite_code.as_deref())?;
+
+        let fallback_repository =
+            some_if_true!(self.user_repository.is_none() => UserRepository::new());
+        match self
+            .user_repository(fallback_repository)
+            .find_by_email(email)
+        {
+            Ok(_) => return Err(get_service_error(ServiceError::Conflict(email.to_string()))),
+            Err(ServiceError::NotFound(_)) => {}
+            Err(error) => return Err(error),
+        }
+
+        let pin = token_util::get_random_pin(&self.sign_up_pin_format);
+        let hashed_password =
+            password_util::get_hashed_password_with_params(password, &self.password_hash_params);
+
+        let token = SignUpToken {
+            pin,
+            name: name.to_string(),
+            email: email.to_string(),
+            password: hashed_password,
+            avatar_url: avatar_url.clone(),
+            locale: locale.clone(),
+        };
+
+        let serialized_token = serde_json::to_string(&token);
+        let serialized_token = if let Ok(serialized_token) = serialized_token {
+            serialized_token
+        } else {
+            return Err(get_service_error(ServiceError::InvalidFormat));
+        };
+
+        let result = {
+            let ttl_seconds = self.sign_up_token_ttl_seconds;
+            let fallback_repository = some_if_true!(self.sign_up_token_repository.is_none() => SignUpTokenRepository::new());
+            self.sign_up_token_repository(fallback_repository).save(
+                &serialized_token,
+                &token.email,
+                ttl_seconds,
+            )?
+        };
+
+        let fallback_email_sender =
+            some_if_true!(self.email_sender.is_none() => EmailSender::new());
+        send_sign_up_token_email(self.email_sender(fallback_email_sender), &token);
+
+        metric_util::count_sign_up_token_issued();
+        Ok(result)
+    }
+
+    /// Resends the email of the pending sign up token.
+    ///
+    /// 1. Rejects the request if the token of the email has been resent within a minute.
+    /// 2. Finds the pending token by email.
+    /// 3. Sends the email with the same pin and refreshes the TTL of the token.
+    pub fn resend_sign_up_token(&mut self, email: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("AuthService::resend_sign_up_token", None);
+        let email = &email_util::normalize(email);
+
+        let fallback_repository =
+            some_if_true!(self.sign_up_token_repository.is_none() => SignUpTokenRepository::new());
+        let sign_up_token_repository = self.sign_up_token_repository(fallback_repository);
+
+        if !sign_up_token_repository.lock_resend(email, SIGN_UP_TOKEN_RESEND_INTERVAL_SECONDS)? {
+            return Err(get_service_error(ServiceError::TooManyRequests(
+                SIGN_UP_TOKEN_RESEND_INTERVAL_SECONDS as u64,
+            )));
+        }
+
+        let key = sign_up_token_repository.find_key_by_email(email)?;
+        let serialized_token = sign_up_token_repository.find(&key)?;
+        let token: SignUpToken =
+            if let Ok(deserialized_token) = serde_json::from_str(&serialized_token) {
+                deserialized_token
+            } else {
+                return Err(get_service_error(ServiceError::InvalidFormat));
+            };
+
+        let ttl_seconds = self.sign_up_token_ttl_seconds;
+        self.sign_up_token_repository(None)
+            .refresh(&key, email, ttl_seconds)?;
+
+        let fallback_email_sender =
+            some_if_true!(self.email_sender.is_none() => EmailSender::new());
+        send_sign_up_token_email(self.email_sender(fallback_email_sender), &token);
+
+        Ok(true)
+    }
+
+    /// Sets token for temporary password deposition in password finding process.
+    /// It is skipped for the user who doesn't have a local password.
+    /// The id and temporary password are generated from a cryptographically secure RNG,
+    /// and the token expires after the password token TTL.
+    ///
+    /// It returns `true` even if the user doesn't exist or doesn't have a local password,
+    /// not to reveal whether the email is registered. The unregistered email is only logged.
+    pub fn set_password_token(&mut self, email: &str) -> Result<bool, ServiceError> {
+        let _span = trace_util::start_span("AuthService::set_password_token", None);
+        let email = &email_util::normalize(email);
+
+        let user = {
+            let fallback_repository =
+                some_if_true!(self.user_repository.is_none() => UserRepository::new());
+            match self
+                .user_repository(fallback_repository)
+                .find_by_email(email)
+            {
+                Ok(user) => user,
+                Err(ServiceError::NotFound(_)) => return Ok(true),
+                Err(error) => return Err(error),
+            }
+        };
+
+        // The user signed up via OAuth doesn't have a password to reset.
+        if !user.has_password {
+            return Ok(true);
+        }
+
+        let token = PasswordToken {
+            id: token_util::get_random_pin(&self.password_reset_id_format),
+            password: token_util::get_random_string(512),
+        };
+
+        let serialized_token = serde_json::to_string(&token);
+        let serialized_token = if let Ok(serialized_token) = serialized_token {
+            serialized_token
+        } else {
+            return Err(get_service_error(ServiceError::InvalidFormat));
+        };
+
+        {
+            let ttl_seconds = self.password_token_ttl_seconds;
+            let fallback_repository = some_if_true!(self.password_token_repository.is_none() => PasswordTokenRepository::new(user.id));
+            self.password_token_repository(fallback_repository)
+                .save(&serialized_token, ttl_seconds)?;
+        }
+
+        let client_address = env::var("CLIENT_ADDRESS").expect("CLIENT_ADDRESS not found");
+        let link = format!("{}/password_reset/{}", client_address, token.id);
+
+        let fallback_email_sender =
+            some_if_true!(self.email_sender.is_none() => EmailSender::new());
+        self.email_sender(fallback_email_sender).send_templated(
+            &format!("{} <{}>", user.name, email),
+            EmailTemplate::PasswordReset,
+            Locale::from_tag(&user.locale),
+            &[
+                ("name", user.name.clone()),
+                ("password", token.password.clone()),
+                ("link", link),
+            ],
+        );
+
+        Ok(true)
+    }
+}
+
+impl Default for AuthService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A builder of the auth service, such as with the repositories sharing a connection, or with
+/// in-memory stores implementing the repository traits in tests.
+/// The repositories and the email sender which are not set are created when they are used, as in
+/// `AuthService::new`.
+#[derive(Default)]
+pub struct AuthServiceBuilder {
+    sign_up_token_repository: Option<SignUpTokenRepository>,
+    password_token_repository: Option<PasswordTokenRepository>,
+    magic_link_token_repository: Option<MagicLinkTokenRepository>,
+    login_attempt_repository: Option<LoginAttemptRepository>,
+    backup_code_repository: Option<BackupCodeRepository>,
+    known_device_repository: Option<KnownDeviceRepository>,
+    session_epoch_repository: Option<SessionEpochRepository>,
+    active_session_repository: Option<ActiveSessionRepository>,
+    pwned_password_repository: Option<PwnedPasswordRepository>,
+    user_key_repository: Option<UserKeyRepository>,
+    user_repository: Option<UserRepository>,
+    password_history_repository: Option<PasswordHistoryRepository>,
+    invite_code_repository: Option<InviteCodeRepository>,
+    invite_code_required: Option<bool>,
+    email_sender: Option<EmailSender>,
+}
+
+impl AuthServiceBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sign_up_token_repository(mut self, repository: SignUpTokenRepository) -> Self {
+        self.sign_up_token_repository = Some(repository);
+        self
+    }
+
+    pub fn password_token_repository(mut self, repository: PasswordTokenRepository) -> Self {
+        self.password_token_repository = Some(repository);
+        self
+    }
+
+    pub fn magic_link_token_repository(mut self, repository: MagicLinkTokenRepository) -> Self {
+        self.magic_link_token_repository = Some(repository);
+        self
+    }
+
+    pub fn login_attempt_repository(mut self, repository: LoginAttemptRepository) -> Self {
+        self.login_attempt_repository = Some(repository);
+        self
+    }
+
+    pub fn backup_code_repository(mut self, repository: BackupCodeRepository) -> Self {
+        self.backup_code_repository = Some(repository);
+        self
+    }
+
+    pub fn known_device_repository(mut self, repository: KnownDeviceRepository) -> Self {
+        self.known_device_repository = Some(repository);
+        self
+    }
+
+    pub fn session_epoch_repository(mut self, repository: SessionEpochRepository) -> Self {
+        self.session_epoch_repository = Some(repository);
+        self
+    }
+
+    pub fn active_session_repository(mut self, repository: ActiveSessionRepository) -> Self {
+        self.active_session_repository = Some(repository);
+        self
+    }
+
+    pub fn pwned_password_repository(mut self, repository: PwnedPasswordRepository) -> Self {
+        self.pwned_password_repository = Some(repository);
+        self
+    }
+
+    pub fn user_key_repository(mut self, repository: UserKeyRepository) -> Self {
+        self.user_key_repository = Some(repository);
+        self
+    }
+
+    pub fn user_repository(mut self, repository: UserRepository) -> Self {
+        self.user_repository = Some(repository);
+        self
+    }
+
+    pub fn password_history_repository(mut self, repository: PasswordHistoryRepository) -> Self {
+        self.password_history_repository = Some(repository);
+        self
+    }
+
+    pub fn invite_code_repository(mut self, repository: InviteCodeRepository) -> Self {
+        self.invite_code_repository = Some(repository);
+        self
+    }
+
+    pub fn invite_code_required(mut self, invite_code_required: bool) -> Self {
+        self.invite_code_required = Some(invite_code_required);
+        self
+    }
+
+    pub fn email_sender(mut self, email_sender: EmailSender) -> Self {
+        self.email_sender = Some(email_sender);
+        self
+    }
+
+    /// Builds the auth service. The limits and TTLs are read from environment variables unless
+    /// they are given.
+    pub fn build(self) -> AuthService {
+        let auth_service = AuthService::new();
+        AuthService {
+            invite_code_required: self
+                .invite_code_required
+                .unwrap_or(auth_service.invite_code_required),
+            sign_up_token_repository: self.sign_up_token_repository,
+            password_token_repository: self.password_token_repository,
+            magic_link_token_repository: self.magic_link_token_repository,
+            login_attempt_repository: self.login_attempt_repository,
+            backup_code_repository: self.backup_code_repository,
+            known_device_repository: self.known_device_repository,
+            session_epoch_repository: self.session_epoch_repository,
+            active_session_repository: self.active_session_repository,
+            pwned_password_repository: self.pwned_password_repository,
+            user_key_repository: self.user_key_repository,
+            user_repository: self.user_repository,
+            password_history_repository: self.password_history_repository,
+            invite_code_repository: self.invite_code_repository,
+            email_sender: self.email_sender,
+            ..auth_service
+        }
+    }
+}
+
+/// Sends the email containing the pin of the sign up token.
+fn send_sign_up_token_email(email_sender: &EmailSender, token: &SignUpToken) {
+    email_sender.send_templated(
+        &format!("{} <{}>", &token.name, &token.email),
+        EmailTemplate::Welcome,
+        Locale::from_tag(&token.locale),
+        &[("name", token.name.clone()), ("pin", token.pin.clone())],
+    );
+}
+
+#[cfg(test)]
+use crate::models::auth::{
+    MockActiveSessionRepositoryTrait as ActiveSessionRepository,
+    MockKnownDeviceRepositoryTrait as KnownDeviceRepository,
+    MockLoginAttemptRepositoryTrait as LoginAttemptRepository,
+    MockMagicLinkTokenRepositoryTrait as MagicLinkTokenRepository,
+    MockPasswordTokenRepositoryTrait as PasswordTokenRepository,
+    MockSessionEpochRepositoryTrait as SessionEpochRepository,
+    MockSignUpTokenRepositoryTrait as SignUpTokenRepository,
+};
+#[cfg(test)]
+use crate::models::backup_code::MockBackupCodeRepositoryTrait as BackupCodeRepository;
+#[cfg(test)]
+use crate::models::invite_code::MockInviteCodeRepositoryTrait as InviteCodeRepository;
+#[cfg(test)]
+use crate::models::password_history::MockPasswordHistoryRepositoryTrait as PasswordHistoryRepository;
+#[cfg(test)]
+use crate::models::pwned_password::MockPwnedPasswordRepositoryTrait as PwnedPasswordRepository;
+#[cfg(test)]
+use crate::models::user::MockUserRepositoryTrait as UserRepository;
+#[cfg(test)]
+use crate::models::user_key::MockUserKeyRepositoryTrait as UserKeyRepository;
+#[cfg(test)]
+use crate::utils::email_util::{EmailSenderTrait, MockEmailSenderTrait as EmailSender};
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate::*;
+    use scrypt::{scrypt_simple, ScryptParams};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::utils::test_util::{
+        self, get_clean_pwned_password_repository, get_in_memory_password_history_repository,
+        get_session_epoch_repository, get_user_key,
+    };
+
+    /// Returns an auth service with the repositories, which doesn't send emails nor request the
+    /// Pwned Passwords API, and isn't affected by environment variables of the limits and TTLs.
+    fn get_auth_service(
+        sign_up_token_repository: SignUpTokenRepository,
+        password_token_repository: PasswordTokenRepository,
+        login_attempt_repository: LoginAttemptRepository,
+        backup_code_repository: BackupCodeRepository,
+        session_epoch_repository: SessionEpochRepository,
+        user_key_repository: UserKeyRepository,
+        user_repository: UserRepository,
+    ) -> AuthService {
+        AuthService {
+            pwned_password_repository: Some(get_clean_pwned_password_repository()),
+            email_sender: Some(get_no_op_email_sender()),
+            invite_code_required: false,
+            login_attempt_limit: 5,
+            login_attempt_window_seconds: 600,
+            login_lock_duration_seconds: 900,
+            sign_up_token_ttl_seconds: 86400,
+            password_token_ttl_seconds: 1800,
+            magic_link_token_ttl_seconds: 600,
+            device_fingerprint_with_ip: false,
+            session_lifetime: SessionLifetime {
+                idle_timeout: Duration::days(7),
+                max_lifetime: Duration::days(30),
+            },
+            sign_up_pin_format: PinFormat {
+                length: 8,
+                numeric_only: false,
+            },
+            password_reset_id_format: PinFormat {
+                length: 32,
+                numeric_only: false,
+            },
+            active_session_repository: Some(get_in_memory_active_session_repository(Arc::new(
+                Mutex::new(HashMap::new()),
+            ))),
+            ..AuthService::builder()
+                .sign_up_token_repository(sign_up_token_repository)
+                .password_token_repository(password_token_repository)
+                .login_attempt_repository(login_attempt_repository)
+                .backup_code_repository(backup_code_repository)
+                .session_epoch_repository(session_epoch_repository)
+                .user_key_repository(user_key_repository)
+                .user_repository(user_repository)
+                .build()
+        }
+    }
+
+    /// Returns an email sender which accepts any email without sending it.
+    fn get_no_op_email_sender() -> EmailSender {
+        let mut mocked_email_sender = EmailSender::default();
+        mocked_email_sender.expect_send().returning(|_, _, _| true);
+        mocked_email_sender
+            .expect_send_templated()
+            .returning(|_, _, _, _| true);
+        mocked_email_sender
+    }
+
+    /// Returns an active session repository which keeps the sessions in the given map.
+    fn get_in_memory_active_session_repository(
+        active_sessions: Arc<Mutex<HashMap<String, ActiveSession>>>,
+    ) -> ActiveSessionRepository {
+        let mut mocked_active_session_repository = ActiveSessionRepository::default();
+        let found_active_sessions = active_sessions.clone();
+        mocked_active_session_repository
+            .expect_find_all()
+            .returning(move || {
+                Ok(found_active_sessions
+                    .lock()
+                    .unwrap()
+                    .values()
+                    .cloned()
+                    .collect())
+            });
+        let existing_active_sessions = active_sessions.clone();
+        mocked_active_session_repository
+            .expect_exists()
+            .returning(move |id| Ok(existing_active_sessions.lock().unwrap().contains_key(id)));
+        let saved_active_sessions = active_sessions.clone();
+        mocked_active_session_repository
+            .expect_save()
+            .returning(move |active_session, _| {
+                saved_active_sessions
+                    .lock()
+                    .unwrap()
+                    .insert(active_session.id.clone(), active_session.clone());
+                Ok(true)
+            });
+        let deleted_active_sessions = active_sessions.clone();
+        mocked_active_session_repository
+            .expect_delete()
+            .returning(move |id| Ok(deleted_active_sessions.lock().unwrap().remove(id).is_some()));
+        mocked_active_session_repository
+            .expect_delete_all()
+            .returning(move || {
+                active_sessions.lock().unwrap().clear();
+                Ok(true)
+            });
+        mocked_active_session_repository
+    }
+
+    fn get_user(id: u64, email: &str, password: &str) -> User {
+        User {
+            password: password_util::get_hashed_password(password),
+            ..test_util::get_user(id, email)
+        }
+    }
+
+    #[test]
+    fn test_build_with_partial_dependencies() {
+        let auth_service = AuthService::builder()
+            .user_repository(UserRepository::new())
+            .email_sender(get_no_op_email_sender())
+            .build();
+
+        assert!(auth_service.user_repository.is_some());
+        assert!(auth_service.email_sender.is_some());
+        assert!(auth_service.sign_up_token_repository.is_none());
+        assert!(auth_service.session_epoch_repository.is_none());
+        assert!(auth_service.pwned_password_repository.is_none());
+    }
+
+    #[test]
+    fn test_build_with_all_dependencies() {
+        let auth_service = AuthService::builder()
+            .sign_up_token_repository(SignUpTokenRepository::new())
+            .password_token_repository(PasswordTokenRepository::default())
+            .magic_link_token_repository(MagicLinkTokenRepository::default())
+            .login_attempt_repository(LoginAttemptRepository::default())
+            .backup_code_repository(BackupCodeRepository::new())
+            .known_device_repository(KnownDeviceRepository::default())
+            .session_epoch_repository(SessionEpochRepository::default())
+            .active_session_repository(ActiveSessionRepository::default())
+            .pwned_password_repository(get_clean_pwned_password_repository())
+            .user_key_repository(UserKeyRepository::new())
+            .user_repository(UserRepository::new())
+            .password_history_repository(PasswordHistoryRepository::new())
+            .email_sender(get_no_op_email_sender())
+            .build();
+
+        assert!(auth_service.sign_up_token_repository.is_some());
+        assert!(auth_service.password_token_repository.is_some());
+        assert!(auth_service.magic_link_token_repository.is_some());
+        assert!(auth_service.login_attempt_repository.is_some());
+        assert!(auth_service.backup_code_repository.is_some());
+        assert!(auth_service.known_device_repository.is_some());
+        assert!(auth_service.session_epoch_repository.is_some());
+        assert!(auth_service.active_session_repository.is_some());
+        assert!(auth_service.pwned_password_repository.is_some());
+        assert!(auth_service.user_key_repository.is_some());
+        assert!(auth_service.user_repository.is_some());
+        assert!(auth_service.password_history_repository.is_some());
+        assert!(auth_service.email_sender.is_some());
+    }
+
+    #[test]
+    fn test_login_locked_out_after_failures() {
+        let email = "park@email.com";
+        let user_id = 1;
+        let attempt_count = Arc::new(Mutex::new(0u64));
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        let found_count = attempt_count.clone();
+        mocked_login_attempt_repository
+            .expect_find()
+            .returning(move || Ok(*found_count.lock().unwrap()));
+        let increased_count = attempt_count.clone();
+        mocked_login_attempt_repository
+            .expect_increase()
+            .with(eq(600))
+            .times(5)
+            .returning(move |_| {
+                let mut count = increased_count.lock().unwrap();
+                *count += 1;
+                Ok(*count)
+            });
+        mocked_login_attempt_repository.expect_delete().times(0);
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .times(0);
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq(email))
+            .times(5)
+            .returning(move |email| Ok(get_user(user_id, email, "password")));
+        mocked_user_repository
+            .expect_update_locked_until()
+            .withf(move |id, locked_until| *id == user_id && locked_until.is_some())
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            mocked_login_attempt_repository,
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        for _ in 0..5 {
+            let result = auth_service.login(email, "wrong password", None, None, None);
+            assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+        }
+
+        let result = auth_service.login(email, "password", None, None, None);
+        assert!(matches!(result, Err(ServiceError::TooManyRequests(600))));
+    }
+
+    #[test]
+    fn test_login_resets_failures_on_success() {
+        let email = "park@email.com";
+        let user_id = 1;
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_find()
+            .times(1)
+            .returning(|| Ok(4));
+        mocked_login_attempt_repository
+            .expect_delete()
+            .times(1)
+            .returning(|| Ok(true));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .times(1)
+            .returning(|_, _| Ok(true));
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq(email))
+            .times(1)
+            .returning(move |email| Ok(get_user(user_id, email, "password")));
+
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .with(eq(user_id))
+            .times(1)
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            mocked_login_attempt_repository,
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            mocked_user_key_repository,
+            mocked_user_repository,
+        );
+
+        let user_session = auth_service
+            .login(email, "password", None, None, None)
+            .unwrap();
+        assert_eq!(user_session.user_id, user_id);
+    }
+
+    #[test]
+    fn test_login_rejected_while_locked() {
+        let email = "park@email.com";
+        let user_id = 1;
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_find()
+            .times(1)
+            .returning(|| Ok(0));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq(email))
+            .times(1)
+            .returning(move |email| {
+                let mut user = get_user(user_id, email, "password");
+                user.locked_until = Some(Utc::now().naive_utc() + Duration::minutes(10));
+                Ok(user)
+            });
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            mocked_login_attempt_repository,
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let result = auth_service.login(email, "password", None, None, None);
+        assert!(matches!(
+            result,
+            Err(ServiceError::Unauthorized(Some(
+                UnauthorizedReason::AccountLocked(_)
+            )))
+        ));
+    }
+
+    #[test]
+    fn test_login_unlocked_after_expiry() {
+        let email = "park@email.com";
+        let user_id = 1;
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_find()
+            .times(1)
+            .returning(|| Ok(0));
+        mocked_login_attempt_repository
+            .expect_delete()
+            .times(1)
+            .returning(|| Ok(true));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .times(1)
+            .returning(|_, _| Ok(true));
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq(email))
+            .times(1)
+            .returning(move |email| {
+                let mut user = get_user(user_id, email, "password");
+                user.locked_until = Some(Utc::now().naive_utc() - Duration::minutes(1));
+                Ok(user)
+            });
+        mocked_user_repository
+            .expect_update_locked_until()
+            .with(eq(user_id), eq(None::<NaiveDateTime>))
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .with(eq(user_id))
+            .times(1)
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            mocked_login_attempt_repository,
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            mocked_user_key_repository,
+            mocked_user_repository,
+        );
+
+        let user_session = auth_service
+            .login(email, "password", None, None, None)
+            .unwrap();
+        assert_eq!(user_session.user_id, user_id);
+    }
+
+    fn get_current_timestamp() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[test]
+    fn test_enroll_totp() {
+        let user_id = 1;
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(user_id))
+            .times(1)
+            .returning(|id| Ok(get_user(id, "park@email.com", "password")));
+        mocked_user_repository
+            .expect_update_totp()
+            .withf(move |id, totp_secret, totp_enabled| {
+                *id == user_id && totp_secret.is_some() && !*totp_enabled
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(true));
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            LoginAttemptRepository::default(),
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let enrollment = auth_service.enroll_totp(user_id).unwrap();
+        assert!(enrollment
+            .provisioning_uri
+            .starts_with("otpauth://totp/Darim:park%40email.com?"));
+        assert!(enrollment
+            .provisioning_uri
+            .contains(&format!("secret={}", enrollment.secret)));
+    }
+
+    #[test]
+    fn test_confirm_totp_with_incorrect_code() {
+        let user_id = 1;
+        let secret = totp_util::generate_secret();
+        let code = totp_util::get_code(&secret, get_current_timestamp()).unwrap();
+        let incorrect_code = format!("{:06}", (code.parse::<u32>().unwrap() + 1) % 1_000_000);
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(user_id))
+            .times(1)
+            .returning(move |id| {
+                let mut user = get_user(id, "park@email.com", "password");
+                user.totp_secret = Some(secret.clone());
+                Ok(user)
+            });
+        mocked_user_repository.expect_update_totp().times(0);
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            LoginAttemptRepository::default(),
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let result = auth_service.confirm_totp(user_id, &incorrect_code);
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[test]
+    fn test_confirm_totp_with_valid_code() {
+        let user_id = 1;
+        let secret = totp_util::generate_secret();
+        let code = totp_util::get_code(&secret, get_current_timestamp()).unwrap();
+
+        let mut mocked_user_repository = UserRepository::new();
+        let found_secret = secret.clone();
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(user_id))
+            .times(1)
+            .returning(move |id| {
+                let mut user = get_user(id, "park@email.com", "password");
+                user.totp_secret = Some(found_secret.clone());
+                Ok(user)
+            });
+        mocked_user_repository
+            .expect_update_totp()
+            .with(eq(user_id), eq(Some(secret)), eq(true))
+            .times(1)
+            .returning(|_, _, _| Ok(true));
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            LoginAttemptRepository::default(),
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        assert!(auth_service.confirm_totp(user_id, &code).unwrap());
+    }
+
+    fn get_totp_enabled_user(id: u64, email: &str, totp_secret: &str) -> User {
+        let mut user = get_user(id, email, "password");
+        user.totp_secret = Some(totp_secret.to_string());
+        user.totp_enabled = true;
+        user
+    }
+
+    #[test]
+    fn test_login_without_totp_code() {
+        let email = "park@email.com";
+        let user_id = 1;
+        let secret = totp_util::generate_secret();
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_find()
+            .times(1)
+            .returning(|| Ok(0));
+        mocked_login_attempt_repository.expect_increase().times(0);
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq(email))
+            .times(1)
+            .returning(move |email| Ok(get_totp_enabled_user(user_id, email, &secret)));
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            mocked_login_attempt_repository,
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let result = auth_service.login(email, "password", None, None, None);
+        assert!(matches!(result, Err(ServiceError::TotpRequired)));
+    }
+
+    #[test]
+    fn test_login_with_wrong_totp_code() {
+        let email = "park@email.com";
+        let user_id = 1;
+        let secret = totp_util::generate_secret();
+        let code = totp_util::get_code(&secret, get_current_timestamp()).unwrap();
+        let wrong_code = format!("{:06}", (code.parse::<u32>().unwrap() + 1) % 1_000_000);
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_find()
+            .times(1)
+            .returning(|| Ok(0));
+        mocked_login_attempt_repository
+            .expect_increase()
+            .times(1)
+            .returning(|_| Ok(1));
+
+        let mut mocked_backup_code_repository = BackupCodeRepository::new();
+        mocked_backup_code_repository
+            .expect_find_all_by_user_id()
+            .with(eq(user_id))
+            .times(1)
+            .returning(|_| Ok(vec![]));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq(email))
+            .times(1)
+            .returning(move |email| Ok(get_totp_enabled_user(user_id, email, &secret)));
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            mocked_login_attempt_repository,
+            mocked_backup_code_repository,
+            get_session_epoch_repository(0),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let result = auth_service.login(email, "password", Some(wrong_code.as_str()), None, None);
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[test]
+    fn test_login_with_correct_totp_code() {
+        let email = "park@email.com";
+        let user_id = 1;
+        let secret = totp_util::generate_secret();
+        let code = totp_util::get_code(&secret, get_current_timestamp()).unwrap();
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_find()
+            .times(1)
+            .returning(|| Ok(0));
+        mocked_login_attempt_repository
+            .expect_delete()
+            .times(1)
+            .returning(|| Ok(true));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .times(1)
+            .returning(|_, _| Ok(true));
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq(email))
+            .times(1)
+            .returning(move |email| Ok(get_totp_enabled_user(user_id, email, &secret)));
+
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .with(eq(user_id))
+            .times(1)
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            mocked_login_attempt_repository,
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            mocked_user_key_repository,
+            mocked_user_repository,
+        );
+
+        let user_session = auth_service
+            .login(email, "password", Some(code.as_str()), None, None)
+            .unwrap();
+        assert_eq!(user_session.user_id, user_id);
+    }
+
+    /// Returns a mocked backup code repository that stores backup codes in memory.
+    fn get_in_memory_backup_code_repository() -> BackupCodeRepository {
+        let store: Arc<Mutex<Vec<BackupCode>>> = Arc::new(Mutex::new(vec![]));
+        let mut mocked_backup_code_repository = BackupCodeRepository::new();
+
+        let found_store = store.clone();
+        mocked_backup_code_repository
+            .expect_find_all_by_user_id()
+            .returning(move |user_id| {
+                Ok(found_store
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|backup_code| backup_code.user_id == user_id)
+                    .map(|backup_code| BackupCode {
+                        id: backup_code.id,
+                        user_id: backup_code.user_id,
+                        hashed_code: backup_code.hashed_code.clone(),
+                        created_at: backup_code.created_at,
+                    })
+                    .collect())
+            });
+
+        let created_store = store.clone();
+        mocked_backup_code_repository
+            .expect_create()
+            .returning(move |user_id, hashed_code| {
+                let mut store = created_store.lock().unwrap();
+                let id = store.len() as u64 + 1;
+                store.push(BackupCode {
+                    id,
+                    user_id,
+                    hashed_code: hashed_code.to_string(),
+                    created_at: Utc::now().naive_utc(),
+                });
+                Ok(true)
+            });
+
+        let deleted_store = store.clone();
+        mocked_backup_code_repository
+            .expect_delete()
+            .returning(move |id| {
+                let mut store = deleted_store.lock().unwrap();
+                let count = store.len();
+                store.retain(|backup_code| backup_code.id != id);
+                Ok(store.len() < count)
+            });
+
+        mocked_backup_code_repository
+            .expect_delete_all_by_user_id()
+            .returning(move |user_id| {
+                let mut store = store.lock().unwrap();
+                let count = store.len();
+                store.retain(|backup_code| backup_code.user_id != user_id);
+                Ok(count - store.len())
+            });
+
+        mocked_backup_code_repository
+    }
+
+    fn get_auth_service_for_backup_code(email: &'static str, user_id: u64) -> AuthService {
+        let secret = totp_util::generate_secret();
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_find()
+            .returning(|| Ok(0));
+        mocked_login_attempt_repository
+            .expect_increase()
+            .returning(|_| Ok(1));
+        mocked_login_attempt_repository
+            .expect_delete()
+            .returning(|| Ok(true));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .times(1)
+            .returning(|_, _| Ok(true));
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq(email))
+            .returning(move |email| Ok(get_totp_enabled_user(user_id, email, &secret)));
+
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            mocked_login_attempt_repository,
+            get_in_memory_backup_code_repository(),
+            get_session_epoch_repository(0),
+            mocked_user_key_repository,
+            mocked_user_repository,
+        )
+    }
+
+    #[test]
+    fn test_login_with_consumed_backup_code() {
+        let email = "park@email.com";
+        let user_id = 1;
+        let mut auth_service = get_auth_service_for_backup_code(email, user_id);
+
+        let backup_code_list = auth_service.generate_backup_codes(user_id).unwrap();
+        assert_eq!(backup_code_list.len(), BACKUP_CODE_COUNT);
+
+        let backup_code = backup_code_list.first().unwrap();
+        let user_session = auth_service
+            .login(email, "password", Some(backup_code.as_str()), None, None)
+            .unwrap();
+        assert_eq!(user_session.user_id, user_id);
+
+        let result = auth_service.login(email, "password", Some(backup_code.as_str()), None, None);
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[test]
+    fn test_login_with_regenerated_backup_code() {
+        let email = "park@email.com";
+        let user_id = 1;
+        let mut auth_service = get_auth_service_for_backup_code(email, user_id);
+
+        let previous_backup_code_list = auth_service.generate_backup_codes(user_id).unwrap();
+        let backup_code_list = auth_service.generate_backup_codes(user_id).unwrap();
+
+        let result = auth_service.login(
+            email,
+            "password",
+            previous_backup_code_list.first().map(|code| code.as_str()),
+            None,
+            None,
+        );
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+
+        let user_session = auth_service
+            .login(
+                email,
+                "password",
+                backup_code_list.first().map(|code| code.as_str()),
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(user_session.user_id, user_id);
+    }
+
+    /// Returns an auth service with an active session whose id is `session`.
+    fn get_auth_service_for_session(epoch: u64) -> AuthService {
+        let mut active_sessions = HashMap::new();
+        active_sessions.insert(
+            String::from("session"),
+            ActiveSession {
+                id: String::from("session"),
+                user_agent: None,
+                created_at: Utc::now().naive_utc() - Duration::hours(1),
+            },
+        );
+
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .returning(|_, _| Ok(true));
+
+        AuthService {
+            active_session_repository: Some(get_in_memory_active_session_repository(Arc::new(
+                Mutex::new(active_sessions),
+            ))),
+            ..get_auth_service(
+                SignUpTokenRepository::new(),
+                PasswordTokenRepository::default(),
+                LoginAttemptRepository::default(),
+                BackupCodeRepository::new(),
+                get_session_epoch_repository(epoch),
+                mocked_user_key_repository,
+                mocked_user_repository,
+            )
+        }
+    }
+
+    #[test]
+    fn test_refresh_user_session_slides_expiry() {
+        let started_at = Utc::now().naive_utc();
+        let expires_at = started_at + Duration::days(7);
+        let now = started_at + Duration::days(3);
+
+        let mut auth_service = get_auth_service_for_session(0);
+
+        let extended_expires_at = auth_service
+            .refresh_user_session(1, 0, "session", &started_at, &expires_at, &now)
+            .unwrap();
+        assert_eq!(extended_expires_at, now + Duration::days(7));
+
+        let later = now + Duration::days(6);
+        let extended_expires_at = auth_service
+            .refresh_user_session(1, 0, "session", &started_at, &extended_expires_at, &later)
+            .unwrap();
+        assert_eq!(extended_expires_at, later + Duration::days(7));
+    }
+
+    #[test]
+    fn test_refresh_user_session_after_idle_timeout() {
+        let started_at = Utc::now().naive_utc();
+        let expires_at = started_at + Duration::days(7);
+        let now = expires_at + Duration::seconds(1);
+
+        let result = get_auth_service_for_session(0).refresh_user_session(
+            1,
+            0,
+            "session",
+            &started_at,
+            &expires_at,
+            &now,
+        );
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[test]
+    fn test_refresh_user_session_capped_by_max_lifetime() {
+        let started_at = Utc::now().naive_utc();
+        let max_expires_at = started_at + Duration::days(30);
+        let mut auth_service = get_auth_service_for_session(0);
+
+        let now = started_at + Duration::days(27);
+        let extended_expires_at = auth_service
+            .refresh_user_session(
+                1,
+                0,
+                "session",
+                &started_at,
+                &(now + Duration::days(1)),
+                &now,
+            )
+            .unwrap();
+        assert_eq!(extended_expires_at, max_expires_at);
+
+        let result = auth_service.refresh_user_session(
+            1,
+            0,
+            "session",
+            &started_at,
+            &extended_expires_at,
+            &max_expires_at,
+        );
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[test]
+    fn test_refresh_invalidated_user_session() {
+        let started_at = Utc::now().naive_utc();
+        let expires_at = started_at + Duration::days(7);
+
+        let result = get_auth_service_for_session(1).refresh_user_session(
+            1,
+            0,
+            "session",
+            &started_at,
+            &expires_at,
+            &started_at,
+        );
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[test]
+    fn test_revoke_one_of_active_sessions() {
+        let now = Utc::now().naive_utc();
+        let mut auth_service = get_auth_service_for_session(0);
+        let user_agents = ["Firefox", "Safari", "Chrome"];
+        let user_sessions: Vec<UserSession> = user_agents
+            .iter()
+            .enumerate()
+            .map(|(index, user_agent)| {
+                auth_service
+                    .start_user_session(
+                        get_user(1, "park@email.com", "password"),
+                        &(now + Duration::minutes(index as i64)),
+                        Some(user_agent),
+                    )
+                    .unwrap()
+            })
+            .collect();
+
+        let active_sessions = auth_service.get_active_sessions(1, &now).unwrap();
+        assert_eq!(active_sessions.len(), 4);
+        assert_eq!(active_sessions[0].id, user_sessions[2].user_session_id);
+        assert_eq!(active_sessions[0].user_agent, Some(String::from("Chrome")));
+        assert_eq!(active_sessions[3].id, "session");
+
+        let revoked_session = &user_sessions[1];
+        assert!(auth_service
+            .revoke_session(1, &revoked_session.user_session_id)
+            .unwrap());
+        assert!(matches!(
+            auth_service.revoke_session(1, &revoked_session.user_session_id),
+            Err(ServiceError::NotFound(_))
+        ));
+
+        let active_session_ids: Vec<String> = auth_service
+            .get_active_sessions(1, &now)
+            .unwrap()
+            .into_iter()
+            .map(|active_session| active_session.id)
+            .collect();
+        assert_eq!(
+            active_session_ids,
+            vec![
+                user_sessions[2].user_session_id.clone(),
+                user_sessions[0].user_session_id.clone(),
+                String::from("session"),
+            ]
+        );
+
+        let refresh = |auth_service: &mut AuthService, user_session: &UserSession| {
+            auth_service.refresh_user_session(
+                1,
+                0,
+                &user_session.user_session_id,
+                &user_session.user_session_started_at,
+                &user_session.user_session_expires_at,
+                &now,
+            )
+        };
+        assert!(matches!(
+            refresh(&mut auth_service, revoked_session),
+            Err(ServiceError::Unauthorized(None))
+        ));
+        assert!(refresh(&mut auth_service, &user_sessions[0]).is_ok());
+        assert!(refresh(&mut auth_service, &user_sessions[2]).is_ok());
+    }
+
+    #[test]
+    fn test_get_active_sessions_without_expired_sessions() {
+        let now = Utc::now().naive_utc();
+        let mut auth_service = get_auth_service_for_session(0);
+        auth_service
+            .start_user_session(
+                get_user(1, "park@email.com", "password"),
+                &(now - Duration::days(31)),
+                None,
+            )
+            .unwrap();
+
+        let active_sessions = auth_service.get_active_sessions(1, &now).unwrap();
+        assert_eq!(active_sessions.len(), 1);
+        assert_eq!(active_sessions[0].id, "session");
+    }
+
+    fn get_auth_service_for_password_change(mocked_user_repository: UserRepository) -> AuthService {
+        AuthService {
+            password_history_repository: Some(get_in_memory_password_history_repository(Arc::new(
+                Mutex::new(vec![]),
+            ))),
+            ..get_auth_service(
+                SignUpTokenRepository::new(),
+                PasswordTokenRepository::default(),
+                LoginAttemptRepository::default(),
+                BackupCodeRepository::new(),
+                get_session_epoch_repository(0),
+                UserKeyRepository::new(),
+                mocked_user_repository,
+            )
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_change_password_with_wrong_current_password() {
+        let user_id = 1;
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(user_id))
+            .times(1)
+            .returning(|id| Ok(get_user(id, "park@email.com", "password")));
+        mocked_user_repository.expect_update().times(0);
+
+        let result = get_auth_service_for_password_change(mocked_user_repository)
+            .change_password(user_id, "wrong password", "correct horse battery staple")
+            .await;
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[actix_rt::test]
+    async fn test_change_password_to_weak_password() {
+        let user_id = 1;
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_id()
+            .returning(|id| Ok(get_user(id, "park@email.com", "password")));
+        mocked_user_repository.expect_update().times(0);
+
+        let result = get_auth_service_for_password_change(mocked_user_repository)
+            .change_password(user_id, "password", "1234")
+            .await;
+        assert!(matches!(
+            result,
+            Err(ServiceError::Validation(field_errors)) if field_errors[0].field == "new_password"
+        ));
+    }
+
+    #[cfg(feature = "hibp")]
+    #[actix_rt::test]
+    async fn test_change_password_to_breached_password() {
+        let user_id = 1;
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_id()
+            .returning(|id| Ok(get_user(id, "park@email.com", "password")));
+        mocked_user_repository.expect_update().times(0);
+
+        let mut auth_service = get_auth_service_for_password_change(mocked_user_repository);
+        auth_service.pwned_password_repository = Some(
+            test_util::get_breached_pwned_password_repository("correct horse battery staple"),
+        );
+
+        let result = auth_service
+            .change_password(user_id, "password", "correct horse battery staple")
+            .await;
+        match result {
+            Err(ServiceError::Validation(field_errors)) => assert_eq!(
+                field_errors,
+                vec![FieldError::new(
+                    "new_password",
+                    "password is found in data breaches"
+                )]
+            ),
+            _ => panic!("expected validation error"),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_change_password() {
+        let user_id = 1;
+        let stored_password = Arc::new(Mutex::new(password_util::get_hashed_password("password")));
+
+        let mut mocked_user_repository = UserRepository::new();
+        let found_password = stored_password.clone();
+        mocked_user_repository
+            .expect_find_by_id()
+            .returning(move |id| {
+                let mut user = get_user(id, "park@email.com", "password");
+                user.password = found_password.lock().unwrap().clone();
+                Ok(user)
+            });
+        mocked_user_repository
+            .expect_update()
+            .withf(move |id, name, password, avatar_url, timezone| {
+                *id == user_id
+                    && name.is_none()
+                    && password.is_some()
+                    && avatar_url.is_none()
+                    && timezone.is_none()
+            })
+            .times(1)
+            .returning(move |_, _, password, _, _| {
+                *stored_password.lock().unwrap() = password.clone().unwrap();
+                Ok(true)
+            });
+
+        let mut auth_service = get_auth_service_for_password_change(mocked_user_repository);
+        assert!(auth_service
+            .change_password(user_id, "password", "correct horse battery staple")
+            .await
+            .unwrap());
+
+        let result = auth_service
+            .change_password(user_id, "password", "another password")
+            .await;
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[actix_rt::test]
+    async fn test_change_password_to_previous_password() {
+        let user_id = 1;
+        let stored_password =
+            Arc::new(Mutex::new(password_util::get_hashed_password("Ir5c7y8dS3")));
+
+        let mut mocked_user_repository = UserRepository::new();
+        let found_password = stored_password.clone();
+        mocked_user_repository
+            .expect_find_by_id()
+            .returning(move |id| {
+                let mut user = get_user(id, "park@email.com", "password");
+                user.password = found_password.lock().unwrap().clone();
+                Ok(user)
+            });
+        mocked_user_repository
+            .expect_update()
+            .times(1)
+            .returning(move |_, _, password, _, _| {
+                *stored_password.lock().unwrap() = password.clone().unwrap();
+                Ok(true)
+            });
+
+        let mut auth_service = get_auth_service_for_password_change(mocked_user_repository);
+        assert!(auth_service
+            .change_password(user_id, "Ir5c7y8dS3", "correct horse battery staple")
+            .await
+            .unwrap());
+
+        let result = auth_service
+            .change_password(
+                user_id,
+                "correct horse battery staple",
+                "correct horse battery staple",
+            )
+            .await;
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+
+        let result = auth_service
+            .change_password(user_id, "correct horse battery staple", "Ir5c7y8dS3")
+            .await;
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_revoke_other_sessions() {
+        let now = Utc::now().naive_utc();
+        let mut auth_service = get_auth_service_for_session(0);
+        let user_session = auth_service
+            .start_user_session(get_user(1, "park@email.com", "password"), &now, None)
+            .unwrap();
+
+        assert!(auth_service
+            .revoke_other_sessions(1, &user_session.user_session_id)
+            .unwrap());
+
+        let active_sessions = auth_service.get_active_sessions(1, &now).unwrap();
+        assert_eq!(active_sessions.len(), 1);
+        assert_eq!(active_sessions[0].id, user_session.user_session_id);
+    }
+
+    #[test]
+    fn test_validate_session_epoch_after_invalidation() {
+        let email = "park@email.com";
+        let user_id = 1;
+        let epoch = Arc::new(Mutex::new(0u64));
+
+        let mut mocked_session_epoch_repository = SessionEpochRepository::default();
+        let found_epoch = epoch.clone();
+        mocked_session_epoch_repository
+            .expect_find()
+            .returning(move || Ok(*found_epoch.lock().unwrap()));
+        mocked_session_epoch_repository
+            .expect_increase()
+            .times(1)
+            .returning(move || {
+                let mut epoch = epoch.lock().unwrap();
+                *epoch += 1;
+                Ok(*epoch)
+            });
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_find()
+            .returning(|| Ok(0));
+        mocked_login_attempt_repository
+            .expect_delete()
+            .returning(|| Ok(true));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .times(2)
+            .returning(|_, _| Ok(true));
+        mocked_user_repository
+            .expect_find_by_email()
+            .returning(move |email| Ok(get_user(user_id, email, "password")));
+
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            mocked_login_attempt_repository,
+            BackupCodeRepository::new(),
+            mocked_session_epoch_repository,
+            mocked_user_key_repository,
+            mocked_user_repository,
+        );
+
+        let old_session = auth_service
+            .login(email, "password", None, None, None)
+            .unwrap();
+        assert!(auth_service
+            .validate_session_epoch(user_id, old_session.user_session_epoch)
+            .unwrap());
+
+        assert!(auth_service.invalidate_all_sessions(user_id).unwrap());
+
+        let result = auth_service.validate_session_epoch(user_id, old_session.user_session_epoch);
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+
+        let new_session = auth_service
+            .login(email, "password", None, None, None)
+            .unwrap();
+        assert!(auth_service
+            .validate_session_epoch(user_id, new_session.user_session_epoch)
+            .unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn test_set_sign_up_token_with_new_email() {
+        let mut mocked_sign_up_token_repository = SignUpTokenRepository::new();
+        mocked_sign_up_token_repository
+            .expect_save()
+            .with(always(), eq("park@email.com"), eq(86400))
+            .times(1)
+            .returning(|_, _, _| Ok(String::from("a1lam9cBko")));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq("park@email.com"))
+            .times(1)
+            .returning(|email| Err(ServiceError::NotFound(email.to_string())));
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+        let captured_emails = sent_emails.clone();
+        let mut mocked_email_sender = EmailSender::default();
+        mocked_email_sender
+            .expect_send_templated()
+            .times(1)
+            .returning(move |to, template, locale, vars| {
+                captured_emails.lock().unwrap().push((
+                    to.to_string(),
+                    email_util::render_email(template, locale, vars),
+                ));
+                true
+            });
+
+        let mut auth_service = get_auth_service_for_sign_up_token(
+            mocked_sign_up_token_repository,
+            mocked_user_repository,
+        );
+        auth_service.email_sender = Some(mocked_email_sender);
+
+        let key = auth_service
+            .set_sign_up_token("park", "Park@Email.com", "Ir5c7y8dS3", &None, &None, &None)
+            .await
+            .unwrap();
+        assert_eq!(key, "a1lam9cBko");
+
+        let sent_emails = sent_emails.lock().unwrap();
+        let (to, email) = &sent_emails[0];
+        assert_eq!(to, "park <park@email.com>");
+        assert_eq!(email.subject, "Welcome to Darim 🎉");
+        assert!(email.html_body.contains("<h2>Hello park :)</h2>"));
+        assert!(email.text_body.contains("to finish the sign up process"));
+    }
+
+    #[actix_rt::test]
+    async fn test_set_sign_up_token_with_numeric_pin() {
+        let mut mocked_sign_up_token_repository = SignUpTokenRepository::new();
+        mocked_sign_up_token_repository
+            .expect_save()
+            .with(
+                function(|serialized_token: &str| {
+                    let token: SignUpToken = serde_json::from_str(serialized_token).unwrap();
+                    token.pin.len() == 6 && token.pin.chars().all(|c| c.is_ascii_digit())
+                }),
+                eq("park@email.com"),
+                eq(86400),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(String::from("a1lam9cBko")));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .returning(|email| Err(ServiceError::NotFound(email.to_string())));
+
+        let mut auth_service = get_auth_service_for_sign_up_token(
+            mocked_sign_up_token_repository,
+            mocked_user_repository,
+        );
+        auth_service.sign_up_pin_format = PinFormat {
+            length: 6,
+            numeric_only: true,
+        };
+
+        let result = auth_service
+            .set_sign_up_token("park", "park@email.com", "Ir5c7y8dS3", &None, &None, &None)
+            .await;
+        assert_eq!(result.unwrap(), "a1lam9cBko");
+    }
+
+    #[actix_rt::test]
+    async fn test_set_sign_up_token_with_disposable_email() {
+        let mut auth_service =
+            get_auth_service_for_sign_up_token(SignUpTokenRepository::new(), UserRepository::new());
+
+        let result = auth_service
+            .set_sign_up_token(
+                "park",
+                "park@mail.Mailinator.com",
+                "Ir5c7y8dS3",
+                &None,
+                &None,
+                &None,
+            )
+            .await;
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    /// Returns an invite code repository storing the remaining uses of each code in memory.
+    fn get_in_memory_invite_code_repository(
+        remaining_uses: Arc<Mutex<HashMap<String, u32>>>,
+    ) -> InviteCodeRepository {
+        let mut invite_code_repository = InviteCodeRepository::new();
+        invite_code_repository
+            .expect_consume()
+            .returning(
+                move |code, _| match remaining_uses.lock().unwrap().get_mut(code) {
+                    Some(remaining_uses) if *remaining_uses > 0 => {
+                        *remaining_uses -= 1;
+                        Ok(true)
+                    }
+                    _ => Ok(false),
+                },
+            );
+        invite_code_repository
+    }
+
+    #[actix_rt::test]
+    async fn test_set_sign_up_token_with_single_use_invite_code() {
+        let mut mocked_sign_up_token_repository = SignUpTokenRepository::new();
+        mocked_sign_up_token_repository
+            .expect_save()
+            .times(1)
+            .returning(|_, _, _| Ok(String::from("a1lam9cBko")));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .returning(|email| Err(ServiceError::NotFound(email.to_string())));
+
+        let remaining_uses = Arc::new(Mutex::new(HashMap::new()));
+        remaining_uses
+            .lock()
+            .unwrap()
+            .insert(String::from("Kq3Zt8WmPx2c"), 1);
+
+        let mut auth_service = get_auth_service_for_sign_up_token(
+            mocked_sign_up_token_repository,
+            mocked_user_repository,
+        );
+        auth_service.invite_code_required = true;
+        auth_service.invite_code_repository =
+            Some(get_in_memory_invite_code_repository(remaining_uses.clone()));
+
+        let invite_code = Some(String::from("Kq3Zt8WmPx2c"));
+        let result = auth_service
+            .set_sign_up_token(
+                "park",
+                "park@email.com",
+                "Ir5c7y8dS3",
+                &None,
+                &None,
+                &invite_code,
+            )
+            .await;
+        assert_eq!(result.unwrap(), "a1lam9cBko");
+        assert_eq!(remaining_uses.lock().unwrap()["Kq3Zt8WmPx2c"], 0);
+
+        let result = auth_service
+            .set_sign_up_token(
+                "kim",
+                "kim@email.com",
+                "Ir5c7y8dS3",
+                &None,
+                &None,
+                &invite_code,
+            )
+            .await;
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+
+        let result = auth_service
+            .set_sign_up_token("kim", "kim@email.com", "Ir5c7y8dS3", &None, &None, &None)
+            .await;
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    fn get_auth_service_for_sign_up_token(
+        mocked_sign_up_token_repository: SignUpTokenRepository,
+        mocked_user_repository: UserRepository,
+    ) -> AuthService {
+        get_auth_service(
+            mocked_sign_up_token_repository,
+            PasswordTokenRepository::default(),
+            LoginAttemptRepository::default(),
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        )
+    }
+
+    #[test]
+    fn test_resend_sign_up_token() {
+        let token = SignUpToken {
+            pin: String::from("P9d82Jc5"),
+            name: String::from("park"),
+            email: String::from("park@email.com"),
+            password: String::from("hashed password"),
+            avatar_url: None,
+            locale: None,
+        };
+        let serialized_token = serde_json::to_string(&token).unwrap();
+        let resent = Arc::new(Mutex::new(false));
+
+        let mut mocked_sign_up_token_repository = SignUpTokenRepository::new();
+        mocked_sign_up_token_repository
+            .expect_lock_resend()
+            .with(eq("park@email.com"), eq(60))
+            .times(2)
+            .returning(move |_, _| {
+                let mut resent = resent.lock().unwrap();
+                let acquired = !*resent;
+                *resent = true;
+                Ok(acquired)
+            });
+        mocked_sign_up_token_repository
+            .expect_find_key_by_email()
+            .with(eq("park@email.com"))
+            .times(1)
+            .returning(|_| Ok(String::from("a1lam9cBko")));
+        mocked_sign_up_token_repository
+            .expect_find()
+            .with(eq("a1lam9cBko"))
+            .times(1)
+            .returning(move |_| Ok(serialized_token.clone()));
+        mocked_sign_up_token_repository
+            .expect_refresh()
+            .with(eq("a1lam9cBko"), eq("park@email.com"), eq(86400))
+            .times(1)
+            .returning(|_, _, _| Ok(true));
+        mocked_sign_up_token_repository.expect_save().times(0);
+
+        let mut auth_service = get_auth_service_for_sign_up_token(
+            mocked_sign_up_token_repository,
+            UserRepository::new(),
+        );
+
+        assert!(auth_service.resend_sign_up_token("park@email.com").unwrap());
+        assert!(matches!(
+            auth_service.resend_sign_up_token("park@email.com"),
+            Err(ServiceError::TooManyRequests(60))
+        ));
+    }
+
+    #[test]
+    fn test_resend_sign_up_token_without_pending_token() {
+        let mut mocked_sign_up_token_repository = SignUpTokenRepository::new();
+        mocked_sign_up_token_repository
+            .expect_lock_resend()
+            .times(1)
+            .returning(|_, _| Ok(true));
+        mocked_sign_up_token_repository
+            .expect_find_key_by_email()
+            .times(1)
+            .returning(|email| Err(ServiceError::NotFound(email.to_string())));
+        mocked_sign_up_token_repository.expect_refresh().times(0);
+
+        let mut auth_service = get_auth_service_for_sign_up_token(
+            mocked_sign_up_token_repository,
+            UserRepository::new(),
+        );
+
+        assert!(matches!(
+            auth_service.resend_sign_up_token("park@email.com"),
+            Err(ServiceError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_login_with_legacy_password_hash() {
+        let email = "park@email.com";
+        let user_id = 1;
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_find()
+            .times(1)
+            .returning(|| Ok(0));
+        mocked_login_attempt_repository
+            .expect_delete()
+            .times(1)
+            .returning(|| Ok(true));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .times(1)
+            .returning(|_, _| Ok(true));
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq(email))
+            .times(1)
+            .returning(move |email| {
+                let mut user = get_user(user_id, email, "password");
+                user.password =
+                    scrypt_simple("password", &ScryptParams::new(7, 4, 1).unwrap()).unwrap();
+                Ok(user)
+            });
+        mocked_user_repository
+            .expect_update()
+            .withf(move |id, name, password, avatar_url, timezone| {
+                *id == user_id
+                    && name.is_none()
+                    && avatar_url.is_none()
+                    && timezone.is_none()
+                    && matches!(password, Some(password) if password.starts_with("$argon2id$")
+                        && password_util::check_password("password", password))
+            })
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(true));
+
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .times(1)
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            mocked_login_attempt_repository,
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            mocked_user_key_repository,
+            mocked_user_repository,
+        );
+
+        let user_session = auth_service
+            .login(email, "password", None, None, None)
+            .unwrap();
+        assert_eq!(user_session.user_id, user_id);
+    }
+
+    #[actix_rt::test]
+    async fn test_set_sign_up_token_with_weak_password() {
+        let mut mocked_sign_up_token_repository = SignUpTokenRepository::new();
+        mocked_sign_up_token_repository.expect_save().times(0);
+
+        let mut auth_service = get_auth_service_for_sign_up_token(
+            mocked_sign_up_token_repository,
+            UserRepository::new(),
+        );
+
+        for password in &["Ir5c7y8", "0123456789", "password123"] {
+            let result = auth_service
+                .set_sign_up_token("park", "park@email.com", password, &None, &None, &None)
+                .await;
+            match result {
+                Err(ServiceError::Validation(field_errors)) => {
+                    assert_eq!(field_errors.len(), 1);
+                    assert_eq!(field_errors[0].field, "password");
+                }
+                _ => panic!("expected validation error"),
+            }
+        }
+    }
+
+    #[cfg(feature = "hibp")]
+    #[actix_rt::test]
+    async fn test_set_sign_up_token_with_breached_password() {
+        let mut mocked_sign_up_token_repository = SignUpTokenRepository::new();
+        mocked_sign_up_token_repository.expect_save().times(0);
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository.expect_find_by_email().times(0);
+
+        let mut auth_service = get_auth_service_for_sign_up_token(
+            mocked_sign_up_token_repository,
+            mocked_user_repository,
+        );
+        auth_service.pwned_password_repository = Some(
+            test_util::get_breached_pwned_password_repository("Ir5c7y8dS3"),
+        );
+
+        let result = auth_service
+            .set_sign_up_token("park", "park@email.com", "Ir5c7y8dS3", &None, &None, &None)
+            .await;
+        match result {
+            Err(ServiceError::Validation(field_errors)) => assert_eq!(
+                field_errors,
+                vec![FieldError::new(
+                    "password",
+                    "password is found in data breaches"
+                )]
+            ),
+            _ => panic!("expected validation error"),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_set_sign_up_token_with_multiple_invalid_fields() {
+        let mut mocked_sign_up_token_repository = SignUpTokenRepository::new();
+        mocked_sign_up_token_repository.expect_save().times(0);
+
+        let mut auth_service = get_auth_service_for_sign_up_token(
+            mocked_sign_up_token_repository,
+            UserRepository::new(),
+        );
+
+        let result = auth_service
+            .set_sign_up_token(" ", "park@email.com", "0123456789", &None, &None, &None)
+            .await;
+        let field_errors = match result {
+            Err(ServiceError::Validation(field_errors)) => field_errors,
+            _ => panic!("expected validation error"),
+        };
+        assert_eq!(
+            field_errors,
+            vec![
+                FieldError::new("name", "must not be empty"),
+                FieldError::new("password", "password must not consist only of numbers"),
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_set_sign_up_token_with_registered_email() {
+        let mut mocked_sign_up_token_repository = SignUpTokenRepository::new();
+        mocked_sign_up_token_repository.expect_save().times(0);
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq("park@email.com"))
+            .times(1)
+            .returning(|email| Ok(get_user(1, email, "Ir5c7y8dS3")));
+
+        let mut auth_service = get_auth_service_for_sign_up_token(
+            mocked_sign_up_token_repository,
+            mocked_user_repository,
+        );
+
+        let result = auth_service
+            .set_sign_up_token(
+                "park",
+                " PARK@email.COM ",
+                "Ir5c7y8dS3",
+                &None,
+                &None,
+                &None,
+            )
+            .await;
+        assert!(matches!(result, Err(ServiceError::Conflict(_))));
+    }
+
+    #[test]
+    fn test_login_with_mixed_case_email() {
+        let user_id = 1;
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_find()
+            .times(1)
+            .returning(|| Ok(0));
+        mocked_login_attempt_repository
+            .expect_delete()
+            .times(1)
+            .returning(|| Ok(true));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .times(1)
+            .returning(|_, _| Ok(true));
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq("park@email.com"))
+            .times(1)
+            .returning(move |email| Ok(get_user(user_id, email, "password")));
+
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .times(1)
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            mocked_login_attempt_repository,
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            mocked_user_key_repository,
+            mocked_user_repository,
+        );
+
+        let user_session = auth_service
+            .login("Park@EMAIL.com", "password", None, None, None)
+            .unwrap();
+        assert_eq!(user_session.user_email, "park@email.com");
+    }
+
+    #[test]
+    fn test_set_password_token_for_user_without_password() {
+        let mut mocked_password_token_repository = PasswordTokenRepository::default();
+        mocked_password_token_repository.expect_save().times(0);
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq("park@email.com"))
+            .times(1)
+            .returning(|email| {
+                Ok(User {
+                    has_password: false,
+                    ..get_user(1, email, "")
+                })
+            });
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            mocked_password_token_repository,
+            LoginAttemptRepository::default(),
+            BackupCodeRepository::new(),
+            SessionEpochRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let result = auth_service.set_password_token("park@email.com");
+        assert!(result.unwrap());
+    }
+
+    #[test]
+    fn test_set_password_token_with_unregistered_email() {
+        env::set_var("CLIENT_ADDRESS", "https://darim.app");
+
+        let mut mocked_password_token_repository = PasswordTokenRepository::default();
+        mocked_password_token_repository
+            .expect_save()
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .times(2)
+            .returning(|email| {
+                if email == "park@email.com" {
+                    Ok(get_user(1, email, "password"))
+                } else {
+                    Err(ServiceError::NotFound(email.to_string()))
+                }
+            });
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            mocked_password_token_repository,
+            LoginAttemptRepository::default(),
+            BackupCodeRepository::new(),
+            SessionEpochRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let registered_result = auth_service.set_password_token("park@email.com").unwrap();
+        let unregistered_result = auth_service.set_password_token("kim@email.com").unwrap();
+        assert_eq!(registered_result, unregistered_result);
+    }
+
+    /// Requests the password reset of the user in the locale, and returns the subject of the email.
+    fn get_password_reset_subject(locale: Option<&'static str>) -> String {
+        env::set_var("CLIENT_ADDRESS", "https://darim.app");
+
+        let mut mocked_password_token_repository = PasswordTokenRepository::default();
+        mocked_password_token_repository
+            .expect_save()
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq("park@email.com"))
+            .times(1)
+            .returning(move |email| {
+                Ok(User {
+                    locale: locale.map(String::from),
+                    ..get_user(1, email, "")
+                })
+            });
+
+        let subjects = Arc::new(Mutex::new(vec![]));
+        let captured_subjects = subjects.clone();
+        let mut mocked_email_sender = EmailSender::default();
+        mocked_email_sender
+            .expect_send_templated()
+            .times(1)
+            .returning(move |_, template, locale, vars| {
+                let email = email_util::render_email(template, locale, vars);
+                captured_subjects.lock().unwrap().push(email.subject);
+                true
+            });
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            mocked_password_token_repository,
+            LoginAttemptRepository::default(),
+            BackupCodeRepository::new(),
+            SessionEpochRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+        auth_service.email_sender = Some(mocked_email_sender);
+
+        assert!(auth_service.set_password_token("park@email.com").unwrap());
+        let subject = subjects.lock().unwrap()[0].clone();
+        subject
+    }
+
+    #[test]
+    fn test_set_password_token_in_locale_of_user() {
+        assert_eq!(
+            get_password_reset_subject(Some("ko")),
+            "비밀번호를 재설정해주세요 🔒"
+        );
+    }
+
+    #[test]
+    fn test_set_password_token_in_unknown_locale() {
+        assert_eq!(
+            get_password_reset_subject(Some("fr")),
+            "Please reset your password 🔒"
+        );
+        assert_eq!(
+            get_password_reset_subject(None),
+            "Please reset your password 🔒"
+        );
+    }
+
+    fn get_auth_service_for_magic_link(
+        magic_link_token_repository: MagicLinkTokenRepository,
+        user_repository: UserRepository,
+    ) -> AuthService {
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            LoginAttemptRepository::default(),
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            mocked_user_key_repository,
+            user_repository,
+        );
+        auth_service.magic_link_token_repository = Some(magic_link_token_repository);
+        auth_service
+    }
+
+    #[test]
+    fn test_request_magic_link_with_unregistered_email() {
+        let mut mocked_magic_link_token_repository = MagicLinkTokenRepository::default();
+        mocked_magic_link_token_repository.expect_save().times(0);
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq("park@email.com"))
+            .times(1)
+            .returning(|email| Err(ServiceError::NotFound(email.to_string())));
+
+        let mut auth_service = get_auth_service_for_magic_link(
+            mocked_magic_link_token_repository,
+            mocked_user_repository,
+        );
+
+        let result = auth_service.request_magic_link("Park@email.com");
+        assert!(result.unwrap());
+    }
+
+    #[test]
+    fn test_consume_magic_link_twice() {
+        let user_id = 1;
+        let stored_user_id = Arc::new(Mutex::new(Some(user_id)));
+
+        let mut mocked_magic_link_token_repository = MagicLinkTokenRepository::default();
+        let found_user_id = Arc::clone(&stored_user_id);
+        mocked_magic_link_token_repository
+            .expect_find()
+            .times(2)
+            .returning(move || match *found_user_id.lock().unwrap() {
+                Some(user_id) => Ok(user_id),
+                None => Err(ServiceError::NotFound(String::from(
+                    "magic_link_token:d63ee429",
+                ))),
+            });
+        let deleted_user_id = Arc::clone(&stored_user_id);
+        mocked_magic_link_token_repository
+            .expect_delete()
+            .times(1)
+            .returning(move || Ok(deleted_user_id.lock().unwrap().take().is_some()));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .times(1)
+            .returning(|_, _| Ok(true));
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(user_id))
+            .times(1)
+            .returning(|id| Ok(get_user(id, "park@email.com", "password")));
+
+        let mut auth_service = get_auth_service_for_magic_link(
+            mocked_magic_link_token_repository,
+            mocked_user_repository,
+        );
+
+        let user_session = auth_service.consume_magic_link("d63ee429", None).unwrap();
+        assert_eq!(user_session.user_id, user_id);
+
+        let result = auth_service.consume_magic_link("d63ee429", None);
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[test]
+    fn test_consume_expired_magic_link() {
+        let mut mocked_magic_link_token_repository = MagicLinkTokenRepository::default();
+        mocked_magic_link_token_repository
+            .expect_find()
+            .times(1)
+            .returning(|| {
+                Err(ServiceError::NotFound(String::from(
+                    "magic_link_token:d63ee429",
+                )))
+            });
+        mocked_magic_link_token_repository.expect_delete().times(0);
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository.expect_find_by_id().times(0);
+
+        let mut auth_service = get_auth_service_for_magic_link(
+            mocked_magic_link_token_repository,
+            mocked_user_repository,
+        );
+
+        let result = auth_service.consume_magic_link("d63ee429", None);
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[test]
+    fn test_consume_magic_link_without_totp_code() {
+        let user_id = 1;
+        let secret = totp_util::generate_secret();
+
+        let mut mocked_magic_link_token_repository = MagicLinkTokenRepository::default();
+        mocked_magic_link_token_repository
+            .expect_find()
+            .times(1)
+            .returning(move || Ok(user_id));
+        mocked_magic_link_token_repository.expect_delete().times(0);
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(user_id))
+            .times(1)
+            .returning(move |id| Ok(get_totp_enabled_user(id, "park@email.com", &secret)));
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .times(0);
+
+        let mut auth_service = get_auth_service_for_magic_link(
+            mocked_magic_link_token_repository,
+            mocked_user_repository,
+        );
+
+        let result = auth_service.consume_magic_link("d63ee429", None);
+        assert!(matches!(result, Err(ServiceError::TotpRequired)));
+    }
+
+    #[test]
+    fn test_consume_magic_link_with_totp_code() {
+        let user_id = 1;
+        let secret = totp_util::generate_secret();
+        let code = totp_util::get_code(&secret, get_current_timestamp()).unwrap();
+
+        let mut mocked_magic_link_token_repository = MagicLinkTokenRepository::default();
+        mocked_magic_link_token_repository
+            .expect_find()
+            .times(1)
+            .returning(move || Ok(user_id));
+        mocked_magic_link_token_repository
+            .expect_delete()
+            .times(1)
+            .returning(|| Ok(true));
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_delete()
+            .times(1)
+            .returning(|| Ok(true));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(user_id))
+            .times(1)
+            .returning(move |id| Ok(get_totp_enabled_user(id, "park@email.com", &secret)));
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut auth_service = get_auth_service_for_magic_link(
+            mocked_magic_link_token_repository,
+            mocked_user_repository,
+        );
+        auth_service.login_attempt_repository = Some(mocked_login_attempt_repository);
+
+        let user_session = auth_service
+            .consume_magic_link("d63ee429", Some(&code))
+            .unwrap();
+        assert_eq!(user_session.user_id, user_id);
+    }
+
+    #[test]
+    fn test_login_records_last_login_at() {
+        let email = "park@email.com";
+        let user_id = 1;
+        let previous_login_at = Utc::now().naive_utc() - Duration::days(1);
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_find()
+            .times(1)
+            .returning(|| Ok(0));
+        mocked_login_attempt_repository
+            .expect_delete()
+            .times(1)
+            .returning(|| Ok(true));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq(email))
+            .times(1)
+            .returning(move |email| {
+                Ok(User {
+                    last_login_at: Some(previous_login_at),
+                    ..get_user(user_id, email, "password")
+                })
+            });
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .withf(move |id, last_login_at| *id == user_id && *last_login_at > previous_login_at)
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .times(1)
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            mocked_login_attempt_repository,
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            mocked_user_key_repository,
+            mocked_user_repository,
+        );
+
+        let user_session = auth_service
+            .login(email, "password", None, None, None)
+            .unwrap();
+        assert_eq!(user_session.user_previous_login_at, Some(previous_login_at));
+        assert!(user_session.user_last_login_at.unwrap() > previous_login_at);
+    }
+
+    /// Logs in from the device, which is new if `is_new_device` is true, with the email sender.
+    fn login_from_device(
+        user_agent: &'static str,
+        is_new_device: bool,
+        email_sender: EmailSender,
+    ) -> Result<UserSession, ServiceError> {
+        let mut mocked_known_device_repository = KnownDeviceRepository::default();
+        mocked_known_device_repository
+            .expect_add()
+            .withf(move |fingerprint| fingerprint == device_util::get_fingerprint(user_agent, None))
+            .times(1)
+            .returning(move |_| Ok(is_new_device));
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_find()
+            .times(1)
+            .returning(|| Ok(0));
+        mocked_login_attempt_repository
+            .expect_delete()
+            .times(1)
+            .returning(|| Ok(true));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .times(1)
+            .returning(|email| Ok(get_user(1, email, "password")));
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .times(1)
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        AuthService::builder()
+            .known_device_repository(mocked_known_device_repository)
+            .login_attempt_repository(mocked_login_attempt_repository)
+            .user_repository(mocked_user_repository)
+            .user_key_repository(mocked_user_key_repository)
+            .session_epoch_repository(get_session_epoch_repository(0))
+            .active_session_repository(get_in_memory_active_session_repository(Arc::new(
+                Mutex::new(HashMap::new()),
+            )))
+            .email_sender(email_sender)
+            .build()
+            .login(
+                "park@email.com",
+                "password",
+                None,
+                Some(user_agent),
+                Some("203.0.113.42"),
+            )
+    }
+
+    #[test]
+    fn test_login_from_new_device_notifies_once() {
+        let user_agent = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15";
+
+        let mut mocked_email_sender = EmailSender::default();
+        mocked_email_sender
+            .expect_send()
+            .withf(move |to, subject, body| {
+                to == "park <park@email.com>"
+                    && subject == "New login to Darim 🔔"
+                    && body.contains(user_agent)
+            })
+            .times(1)
+            .returning(|_, _, _| true);
+        assert!(login_from_device(user_agent, true, mocked_email_sender).is_ok());
+
+        let mut mocked_email_sender = EmailSender::default();
+        mocked_email_sender.expect_send().times(0);
+        assert!(login_from_device(user_agent, false, mocked_email_sender).is_ok());
+    }
+
+    #[test]
+    fn test_login_from_known_device() {
+        let email = "park@email.com";
+        let user_id = 1;
+        let user_agent = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15";
+
+        let mut mocked_known_device_repository = KnownDeviceRepository::default();
+        mocked_known_device_repository
+            .expect_add()
+            .withf(move |fingerprint| fingerprint == device_util::get_fingerprint(user_agent, None))
+            .times(1)
+            .returning(|_| Ok(false));
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_find()
+            .times(1)
+            .returning(|| Ok(0));
+        mocked_login_attempt_repository
+            .expect_delete()
+            .times(1)
+            .returning(|| Ok(true));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .times(1)
+            .returning(move |email| Ok(get_user(user_id, email, "password")));
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .times(1)
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        let mut auth_service = get_auth_service(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            mocked_login_attempt_repository,
+            BackupCodeRepository::new(),
+            get_session_epoch_repository(0),
+            mocked_user_key_repository,
+            mocked_user_repository,
+        );
+        auth_service.known_device_repository = Some(mocked_known_device_repository);
+
+        let user_session = auth_service
+            .login(
+                email,
+                "password",
+                None,
+                Some(user_agent),
+                Some("203.0.113.42"),
+            )
+            .unwrap();
+        assert_eq!(user_session.user_id, user_id);
     }
 }
@@ -0,0 +1,282 @@
+use crate::models::category::*;
+use crate::models::error::{get_service_error, ServiceError};
+use crate::utils::env_util;
+
+/// A maximum number of characters in the name of a category.
+const MAX_CATEGORY_NAME_LENGTH: usize = 64;
+/// A maximum number of categories of a user.
+const MAX_CATEGORIES: usize = 100;
+
+pub struct CategoryService {
+    category_repository: Option<CategoryRepository>,
+    deletion_policy: CategoryDeletionPolicy,
+}
+
+impl CategoryService {
+    /// Creates a new category service. A category having posts is not deleted unless
+    /// `CATEGORY_DELETION_POLICY` is `nullify`.
+    pub fn new() -> Self {
+        Self {
+            category_repository: None,
+            deletion_policy: env_util::get_env_var_or(
+                "CATEGORY_DELETION_POLICY",
+                CategoryDeletionPolicy::Restrict,
+            ),
+        }
+    }
+
+    fn category_repository(
+        &mut self,
+        new_repository: Option<CategoryRepository>,
+    ) -> &CategoryRepository {
+        match new_repository {
+            Some(_) => {
+                self.category_repository = new_repository;
+                self.category_repository.as_ref().unwrap()
+            }
+            None => self.category_repository.as_ref().unwrap(),
+        }
+    }
+
+    /// Returns the trimmed name, or `ServiceError::InvalidArgument` if it is empty or longer than
+    /// the maximum length.
+    fn normalize_name(name: &str) -> Result<String, ServiceError> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > MAX_CATEGORY_NAME_LENGTH {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        Ok(name.to_string())
+    }
+
+    /// Returns `ServiceError::DuplicatedKey` if the user has another category of the name.
+    fn verify_unique_name(
+        category_list: &[Category],
+        id: Option<u64>,
+        name: &str,
+    ) -> Result<(), ServiceError> {
+        let is_duplicated = category_list.iter().any(|category| {
+            Some(category.id) != id && category.name.to_lowercase() == name.to_lowercase()
+        });
+        if is_duplicated {
+            return Err(get_service_error(ServiceError::DuplicatedKey));
+        }
+
+        Ok(())
+    }
+
+    /// Creates a new category of the user, and returns it.
+    /// Returns `ServiceError::InvalidArgument` if the name is empty or longer than 64 characters,
+    /// or the user already has 100 categories.
+    /// Returns `ServiceError::DuplicatedKey` if the user already has a category of the name.
+    pub fn create(&mut self, user_id: u64, name: &str) -> Result<CategoryDTO, ServiceError> {
+        let name = Self::normalize_name(name)?;
+
+        let fallback_repository =
+            some_if_true!(self.category_repository.is_none() => CategoryRepository::new());
+        let category_repository = self.category_repository(fallback_repository);
+
+        let category_list = category_repository.find_all_by_user_id(user_id)?;
+        if category_list.len() >= MAX_CATEGORIES {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+        Self::verify_unique_name(&category_list, None, &name)?;
+
+        let id = category_repository.create(user_id, &name)?;
+        let category = category_repository.find(id, user_id)?;
+        Ok(CategoryDTO {
+            id: category.id,
+            name: category.name,
+            created_at: category.created_at,
+        })
+    }
+
+    /// Finds all categories of the user in name order.
+    pub fn get_list(&mut self, user_id: u64) -> Result<Vec<CategoryDTO>, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.category_repository.is_none() => CategoryRepository::new());
+        let category_list = self
+            .category_repository(fallback_repository)
+            .find_all_by_user_id(user_id)?;
+
+        Ok(category_list
+            .into_iter()
+            .map(|category| CategoryDTO {
+                id: category.id,
+                name: category.name,
+                created_at: category.created_at,
+            })
+            .collect())
+    }
+
+    /// Renames the category of the user.
+    /// Returns `ServiceError::NotFound` if the category isn't the user's one.
+    /// Returns `ServiceError::DuplicatedKey` if the user has another category of the name.
+    pub fn rename(&mut self, user_id: u64, id: u64, name: &str) -> Result<bool, ServiceError> {
+        let name = Self::normalize_name(name)?;
+
+        let fallback_repository =
+            some_if_true!(self.category_repository.is_none() => CategoryRepository::new());
+        let category_repository = self.category_repository(fallback_repository);
+
+        let category_list = category_repository.find_all_by_user_id(user_id)?;
+        if !category_list.iter().any(|category| category.id == id) {
+            return Err(get_service_error(ServiceError::NotFound(id.to_string())));
+        }
+        Self::verify_unique_name(&category_list, Some(id), &name)?;
+
+        category_repository.update(id, user_id, &name)
+    }
+
+    /// Deletes the category of the user.
+    ///
+    /// If the category has posts, it returns `ServiceError::Conflict` by the default policy.
+    /// If the policy is `CategoryDeletionPolicy::Nullify`, the posts are removed from the category
+    /// and remain without a category instead.
+    /// Returns `ServiceError::NotFound` if the category isn't the user's one.
+    pub fn delete(&mut self, user_id: u64, id: u64) -> Result<bool, ServiceError> {
+        let deletion_policy = self.deletion_policy;
+        let fallback_repository =
+            some_if_true!(self.category_repository.is_none() => CategoryRepository::new());
+        let category_repository = self.category_repository(fallback_repository);
+
+        category_repository.find(id, user_id)?;
+        if deletion_policy == CategoryDeletionPolicy::Restrict
+            && category_repository.count_posts(id)? > 0
+        {
+            return Err(get_service_error(ServiceError::Conflict(id.to_string())));
+        }
+
+        category_repository.delete(id, user_id)
+    }
+}
+
+impl Default for CategoryService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+use crate::models::category::MockCategoryRepositoryTrait as CategoryRepository;
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use mockall::predicate::*;
+
+    use super::*;
+    use crate::models::category::MockCategoryRepositoryTrait;
+
+    impl CategoryService {
+        pub fn new_with_repository(
+            category_repository: CategoryRepository,
+            deletion_policy: CategoryDeletionPolicy,
+        ) -> Self {
+            Self {
+                category_repository: Some(category_repository),
+                deletion_policy,
+            }
+        }
+    }
+
+    fn get_category(id: u64, user_id: u64, name: &str) -> Category {
+        Category {
+            id,
+            user_id,
+            name: name.to_string(),
+            created_at: Utc::now().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn test_create_with_duplicated_name() {
+        let user_id = 5;
+
+        let mut mocked_category_repository = MockCategoryRepositoryTrait::new();
+        mocked_category_repository
+            .expect_find_all_by_user_id()
+            .with(eq(user_id))
+            .times(1)
+            .returning(move |_| Ok(vec![get_category(1, user_id, "Travel")]));
+        mocked_category_repository.expect_create().times(0);
+
+        let mut category_service = CategoryService::new_with_repository(
+            mocked_category_repository,
+            CategoryDeletionPolicy::Restrict,
+        );
+        let result = category_service.create(user_id, " travel ");
+
+        assert!(matches!(result, Err(ServiceError::DuplicatedKey)));
+    }
+
+    #[test]
+    fn test_delete_with_posts_restricted() {
+        let user_id = 5;
+
+        let mut mocked_category_repository = MockCategoryRepositoryTrait::new();
+        mocked_category_repository
+            .expect_find()
+            .with(eq(1), eq(user_id))
+            .times(1)
+            .returning(move |id, user_id| Ok(get_category(id, user_id, "Travel")));
+        mocked_category_repository
+            .expect_count_posts()
+            .with(eq(1))
+            .times(1)
+            .returning(|_| Ok(3));
+        mocked_category_repository.expect_delete().times(0);
+
+        let mut category_service = CategoryService::new_with_repository(
+            mocked_category_repository,
+            CategoryDeletionPolicy::Restrict,
+        );
+        let result = category_service.delete(user_id, 1);
+
+        assert!(matches!(result, Err(ServiceError::Conflict(_))));
+    }
+
+    #[test]
+    fn test_delete_with_posts_nullified() {
+        let user_id = 5;
+
+        let mut mocked_category_repository = MockCategoryRepositoryTrait::new();
+        mocked_category_repository
+            .expect_find()
+            .with(eq(1), eq(user_id))
+            .times(1)
+            .returning(move |id, user_id| Ok(get_category(id, user_id, "Travel")));
+        mocked_category_repository.expect_count_posts().times(0);
+        mocked_category_repository
+            .expect_delete()
+            .with(eq(1), eq(user_id))
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut category_service = CategoryService::new_with_repository(
+            mocked_category_repository,
+            CategoryDeletionPolicy::Nullify,
+        );
+
+        assert!(category_service.delete(user_id, 1).unwrap());
+    }
+
+    #[test]
+    fn test_delete_category_of_another_user() {
+        let mut mocked_category_repository = MockCategoryRepositoryTrait::new();
+        mocked_category_repository
+            .expect_find()
+            .with(eq(1), eq(7))
+            .times(1)
+            .returning(|id, _| Err(ServiceError::NotFound(id.to_string())));
+        mocked_category_repository.expect_delete().times(0);
+
+        let mut category_service = CategoryService::new_with_repository(
+            mocked_category_repository,
+            CategoryDeletionPolicy::Nullify,
+        );
+        let result = category_service.delete(7, 1);
+
+        assert!(matches!(result, Err(ServiceError::NotFound(_))));
+    }
+}
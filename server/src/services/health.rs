@@ -0,0 +1,122 @@
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::models::health::*;
+
+/// Availability of each dependency of the server.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Readiness {
+    pub rdb: bool,
+    pub redis: bool,
+}
+
+impl Readiness {
+    /// Returns whether all dependencies are available, so that the server can handle requests.
+    pub fn is_ready(&self) -> bool {
+        self.rdb && self.redis
+    }
+}
+
+/// Information of the running build, which is embedded at compile time by the build script.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    /// A hash of the git commit the server is built from, or `unknown`.
+    pub commit_hash: &'static str,
+    /// A time the server is built in RFC 3339 format with UTC offset.
+    pub built_at: Option<DateTime<Utc>>,
+}
+
+impl BuildInfo {
+    /// Returns the information of the running build.
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            commit_hash: env!("GIT_COMMIT_HASH"),
+            built_at: env!("BUILD_TIMESTAMP")
+                .parse()
+                .ok()
+                .and_then(|timestamp| Utc.timestamp_opt(timestamp, 0).single()),
+        }
+    }
+}
+
+pub struct HealthService {
+    health_repository: Option<HealthRepository>,
+}
+
+impl HealthService {
+    pub fn new() -> Self {
+        Self {
+            health_repository: None,
+        }
+    }
+
+    fn health_repository(&mut self, new_repository: Option<HealthRepository>) -> &HealthRepository {
+        match new_repository {
+            Some(_) => {
+                self.health_repository = new_repository;
+                self.health_repository.as_ref().unwrap()
+            }
+            None => self.health_repository.as_ref().unwrap(),
+        }
+    }
+
+    /// Checks whether MySQL and redis are available.
+    pub fn check_readiness(&mut self) -> Readiness {
+        let fallback_repository =
+            some_if_true!(self.health_repository.is_none() => HealthRepository::new());
+        let health_repository = self.health_repository(fallback_repository);
+
+        Readiness {
+            rdb: health_repository.ping_rdb(),
+            redis: health_repository.ping_redis(),
+        }
+    }
+}
+
+impl Default for HealthService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+use crate::models::health::MockHealthRepositoryTrait as HealthRepository;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl HealthService {
+        pub fn new_with_repository(health_repository: HealthRepository) -> Self {
+            Self {
+                health_repository: Some(health_repository),
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_readiness_when_redis_is_down() {
+        let mut mocked_health_repository = HealthRepository::new();
+        mocked_health_repository
+            .expect_ping_rdb()
+            .times(1)
+            .return_const(true);
+        mocked_health_repository
+            .expect_ping_redis()
+            .times(1)
+            .return_const(false);
+
+        let readiness =
+            HealthService::new_with_repository(mocked_health_repository).check_readiness();
+        assert_eq!(
+            readiness,
+            Readiness {
+                rdb: true,
+                redis: false
+            }
+        );
+        assert!(!readiness.is_ready());
+    }
+}
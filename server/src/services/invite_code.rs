@@ -0,0 +1,137 @@
+use chrono::NaiveDateTime;
+
+use crate::models::error::{get_service_error, ServiceError};
+use crate::models::invite_code::*;
+use crate::utils::token_util;
+
+/// A length of the invite code, which is short enough to be typed by hand.
+const INVITE_CODE_LENGTH: usize = 12;
+
+pub struct InviteCodeService {
+    invite_code_repository: Option<InviteCodeRepository>,
+}
+
+impl InviteCodeService {
+    pub fn new() -> Self {
+        Self {
+            invite_code_repository: None,
+        }
+    }
+
+    fn invite_code_repository(
+        &mut self,
+        new_repository: Option<InviteCodeRepository>,
+    ) -> &InviteCodeRepository {
+        match new_repository {
+            Some(_) => {
+                self.invite_code_repository = new_repository;
+                self.invite_code_repository.as_ref().unwrap()
+            }
+            None => self.invite_code_repository.as_ref().unwrap(),
+        }
+    }
+
+    /// Mints a new invite code which can be used `max_uses` times to sign up, and expires at
+    /// `expires_at` if it is given.
+    pub fn mint(
+        &mut self,
+        max_uses: u32,
+        expires_at: &Option<NaiveDateTime>,
+        now: &NaiveDateTime,
+    ) -> Result<InviteCode, ServiceError> {
+        if max_uses == 0 {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+        if let Some(expires_at) = expires_at {
+            if expires_at <= now {
+                return Err(get_service_error(ServiceError::InvalidArgument));
+            }
+        }
+
+        let code = token_util::get_random_string(INVITE_CODE_LENGTH);
+
+        let fallback_repository =
+            some_if_true!(self.invite_code_repository.is_none() => InviteCodeRepository::new());
+        let invite_code_repository = self.invite_code_repository(fallback_repository);
+        invite_code_repository.create(&code, max_uses, expires_at)?;
+        invite_code_repository.find_by_code(&code)
+    }
+
+    /// Revokes the invite code, after which nobody can sign up with it.
+    pub fn revoke(&mut self, code: &str, now: &NaiveDateTime) -> Result<bool, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.invite_code_repository.is_none() => InviteCodeRepository::new());
+        self.invite_code_repository(fallback_repository)
+            .revoke(code, *now)
+    }
+}
+
+impl Default for InviteCodeService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+use crate::models::invite_code::MockInviteCodeRepositoryTrait as InviteCodeRepository;
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use mockall::predicate::*;
+
+    use super::*;
+
+    #[test]
+    fn test_mint() {
+        let now = Utc::now().naive_utc();
+        let expires_at = Some(now + Duration::days(7));
+
+        let mut mocked_invite_code_repository = InviteCodeRepository::new();
+        mocked_invite_code_repository
+            .expect_create()
+            .with(
+                function(|code: &str| code.len() == INVITE_CODE_LENGTH),
+                eq(3),
+                eq(expires_at),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(true));
+        mocked_invite_code_repository
+            .expect_find_by_code()
+            .times(1)
+            .returning(move |code| {
+                Ok(InviteCode {
+                    id: 1,
+                    code: code.to_string(),
+                    max_uses: 3,
+                    remaining_uses: 3,
+                    expires_at,
+                    revoked_at: None,
+                    created_at: now,
+                })
+            });
+
+        let mut invite_code_service = InviteCodeService {
+            invite_code_repository: Some(mocked_invite_code_repository),
+        };
+
+        let invite_code = invite_code_service.mint(3, &expires_at, &now).unwrap();
+        assert_eq!(invite_code.code.len(), INVITE_CODE_LENGTH);
+        assert_eq!(invite_code.remaining_uses, 3);
+    }
+
+    #[test]
+    fn test_mint_with_invalid_arguments() {
+        let now = Utc::now().naive_utc();
+        let mut invite_code_service = InviteCodeService {
+            invite_code_repository: Some(InviteCodeRepository::new()),
+        };
+
+        let result = invite_code_service.mint(0, &None, &now);
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+
+        let result = invite_code_service.mint(1, &Some(now - Duration::hours(1)), &now);
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+}
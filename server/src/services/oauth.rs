@@ -0,0 +1,797 @@
+use crate::models::auth::*;
+use crate::models::error::{get_service_error, ServiceError};
+use crate::models::oauth::*;
+use crate::models::user::*;
+use crate::services::auth::AuthService;
+use crate::utils::{email_util, token_util};
+
+const OAUTH_STATE_TTL_SECONDS: usize = 600;
+const GOOGLE_PROVIDER: &str = "google";
+const GITHUB_PROVIDER: &str = "github";
+
+pub struct OAuthService {
+    oauth_state_repository: Option<OAuthStateRepository>,
+    google_oauth_repository: Option<GoogleOAuthRepository>,
+    github_oauth_repository: Option<GitHubOAuthRepository>,
+    user_repository: Option<UserRepository>,
+    auth_service: Option<AuthService>,
+}
+
+impl OAuthService {
+    pub fn new() -> Self {
+        Self {
+            oauth_state_repository: None,
+            google_oauth_repository: None,
+            github_oauth_repository: None,
+            user_repository: None,
+            auth_service: None,
+        }
+    }
+
+    fn oauth_state_repository(
+        &mut self,
+        new_repository: Option<OAuthStateRepository>,
+    ) -> &mut OAuthStateRepository {
+        match new_repository {
+            Some(_) => {
+                self.oauth_state_repository = new_repository;
+                self.oauth_state_repository.as_mut().unwrap()
+            }
+            None => self.oauth_state_repository.as_mut().unwrap(),
+        }
+    }
+
+    fn google_oauth_repository(
+        &mut self,
+        new_repository: Option<GoogleOAuthRepository>,
+    ) -> &GoogleOAuthRepository {
+        match new_repository {
+            Some(_) => {
+                self.google_oauth_repository = new_repository;
+                self.google_oauth_repository.as_ref().unwrap()
+            }
+            None => self.google_oauth_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn github_oauth_repository(
+        &mut self,
+        new_repository: Option<GitHubOAuthRepository>,
+    ) -> &GitHubOAuthRepository {
+        match new_repository {
+            Some(_) => {
+                self.github_oauth_repository = new_repository;
+                self.github_oauth_repository.as_ref().unwrap()
+            }
+            None => self.github_oauth_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn user_repository(&mut self, new_repository: Option<UserRepository>) -> &UserRepository {
+        match new_repository {
+            Some(_) => {
+                self.user_repository = new_repository;
+                self.user_repository.as_ref().unwrap()
+            }
+            None => self.user_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn auth_service(&mut self, new_service: Option<AuthService>) -> &mut AuthService {
+        match new_service {
+            Some(_) => {
+                self.auth_service = new_service;
+                self.auth_service.as_mut().unwrap()
+            }
+            None => self.auth_service.as_mut().unwrap(),
+        }
+    }
+
+    /// Begins Google login.
+    ///
+    /// 1. Generates a random state and stores it in redis to prevent CSRF.
+    /// 2. Returns the authorization URL of Google containing the state.
+    pub fn begin_google_login(&mut self) -> Result<String, ServiceError> {
+        let state = self.issue_state(GOOGLE_PROVIDER)?;
+
+        let fallback_repository =
+            some_if_true!(self.google_oauth_repository.is_none() => GoogleOAuthRepository::new());
+        Ok(self
+            .google_oauth_repository(fallback_repository)
+            .get_authorization_url(&state))
+    }
+
+    /// Completes Google login.
+    ///
+    /// 1. Checks whether the state from arguments has been issued for Google login, and deletes it.
+    /// 2. Exchanges the code from arguments for an access token, and fetches the profile of the user.
+    /// 3. Finds the user by the verified email of the profile, or creates a new user if it doesn't
+    ///    exist. The new user is checked as the sign up does, which consumes a use of the invite
+    ///    code from arguments if invite codes are required.
+    /// 4. If the user enabled TOTP, checks the TOTP code from arguments as the password login does.
+    /// 5. Returns the session of the user.
+    ///
+    /// It returns `ServiceError::TotpRequired` if the user enabled TOTP but the code is not given.
+    /// As the authorization code can't be used again, the login must begin again with the TOTP code.
+    pub async fn complete_google_login(
+        &mut self,
+        code: &str,
+        state: &str,
+        totp_code: Option<&str>,
+        invite_code: Option<&str>,
+    ) -> Result<UserSession, ServiceError> {
+        self.consume_state(GOOGLE_PROVIDER, state)?;
+
+        let fallback_repository =
+            some_if_true!(self.google_oauth_repository.is_none() => GoogleOAuthRepository::new());
+        let google_oauth_repository = self.google_oauth_repository(fallback_repository);
+        let access_token = google_oauth_repository.exchange_code(code).await?;
+        let profile = google_oauth_repository.find_profile(&access_token).await?;
+
+        self.get_user_session(&profile, totp_code, invite_code)
+    }
+
+    /// Begins GitHub login.
+    ///
+    /// 1. Generates a random state and stores it in redis to prevent CSRF.
+    /// 2. Returns the authorization URL of GitHub containing the state.
+    pub fn begin_github_login(&mut self) -> Result<String, ServiceError> {
+        let state = self.issue_state(GITHUB_PROVIDER)?;
+
+        let fallback_repository =
+            some_if_true!(self.github_oauth_repository.is_none() => GitHubOAuthRepository::new());
+        Ok(self
+            .github_oauth_repository(fallback_repository)
+            .get_authorization_url(&state))
+    }
+
+    /// Completes GitHub login.
+    ///
+    /// 1. Checks whether the state from arguments has been issued for GitHub login, and deletes it.
+    /// 2. Exchanges the code from arguments for an access token, and fetches the profile of the user with the primary email.
+    /// 3. Finds the user by the verified email of the profile, or creates a new user if it doesn't
+    ///    exist. The new user is checked as the sign up does, which consumes a use of the invite
+    ///    code from arguments if invite codes are required.
+    /// 4. If the user enabled TOTP, checks the TOTP code from arguments as the password login does.
+    /// 5. Returns the session of the user.
+    ///
+    /// It returns `ServiceError::TotpRequired` if the user enabled TOTP but the code is not given.
+    /// As the authorization code can't be used again, the login must begin again with the TOTP code.
+    pub async fn complete_github_login(
+        &mut self,
+        code: &str,
+        state: &str,
+        totp_code: Option<&str>,
+        invite_code: Option<&str>,
+    ) -> Result<UserSession, ServiceError> {
+        self.consume_state(GITHUB_PROVIDER, state)?;
+
+        let fallback_repository =
+            some_if_true!(self.github_oauth_repository.is_none() => GitHubOAuthRepository::new());
+        let github_oauth_repository = self.github_oauth_repository(fallback_repository);
+        let access_token = github_oauth_repository.exchange_code(code).await?;
+        let profile = github_oauth_repository.find_profile(&access_token).await?;
+
+        self.get_user_session(&profile, totp_code, invite_code)
+    }
+
+    /// Issues a new state for the provider.
+    fn issue_state(&mut self, provider: &str) -> Result<String, ServiceError> {
+        let state = token_util::get_random_string(32);
+
+        let fallback_repository = some_if_true!(self.oauth_state_repository.is_none() => OAuthStateRepository::new(&state));
+        self.oauth_state_repository(fallback_repository)
+            .save(provider, OAUTH_STATE_TTL_SECONDS)?;
+
+        Ok(state)
+    }
+
+    /// Deletes the state, and returns `ServiceError::Unauthorized` if it hasn't been issued for the provider.
+    fn consume_state(&mut self, provider: &str, state: &str) -> Result<(), ServiceError> {
+        let fallback_repository = some_if_true!(self.oauth_state_repository.is_none() => OAuthStateRepository::new(state));
+        let oauth_state_repository = self.oauth_state_repository(fallback_repository);
+
+        let issued_provider = match oauth_state_repository.find() {
+            Ok(issued_provider) => issued_provider,
+            Err(ServiceError::NotFound(_)) => {
+                return Err(get_service_error(ServiceError::Unauthorized(None)))
+            }
+            Err(error) => return Err(error),
+        };
+        oauth_state_repository.delete()?;
+
+        if issued_provider == provider {
+            Ok(())
+        } else {
+            Err(get_service_error(ServiceError::Unauthorized(None)))
+        }
+    }
+
+    /// Returns the session of the user linked with the profile.
+    /// If the user doesn't exist, creates a new user without a local password.
+    /// The session is started by the auth service, which checks the lock, the status and the TOTP
+    /// code of the user.
+    fn get_user_session(
+        &mut self,
+        profile: &OAuthProfile,
+        totp_code: Option<&str>,
+        invite_code: Option<&str>,
+    ) -> Result<UserSession, ServiceError> {
+        if !profile.email_verified {
+            return Err(get_service_error(ServiceError::Unauthorized(None)));
+        }
+
+        let email = email_util::normalize(&profile.email);
+        let fallback_repository =
+            some_if_true!(self.user_repository.is_none() => UserRepository::new());
+        let found_user = self
+            .user_repository(fallback_repository)
+            .find_by_email(&email);
+
+        let user = match found_user {
+            Ok(user) => user,
+            Err(ServiceError::NotFound(_)) => self.create_user(profile, &email, invite_code)?,
+            Err(error) => return Err(error),
+        };
+
+        let fallback_service = some_if_true!(self.auth_service.is_none() => AuthService::new());
+        self.auth_service(fallback_service)
+            .start_oauth_session(user, totp_code)
+    }
+
+    /// Creates a new user of the profile without a local password.
+    ///
+    /// As the sign up does, it returns `ServiceError::InvalidArgument` if the email is of a
+    /// disposable email service in the blocklist, and `ServiceError::Unauthorized` if invite codes
+    /// are required but the invite code is missing or can't be consumed.
+    fn create_user(
+        &mut self,
+        profile: &OAuthProfile,
+        email: &str,
+        invite_code: Option<&str>,
+    ) -> Result<User, ServiceError> {
+        if email_util::is_disposable(email) {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        let fallback_service = some_if_true!(self.auth_service.is_none() => AuthService::new());
+        self.auth_service(fallback_service)
+            .consume_invite_code(invite_code)?;
+
+        let user_repository = self.user_repository(None);
+        user_repository.create_without_password(&profile.name, email, &profile.avatar_url)?;
+        user_repository.find_by_email(email)
+    }
+}
+
+impl Default for OAuthService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+use crate::models::oauth::{
+    MockGitHubOAuthRepositoryTrait as GitHubOAuthRepository,
+    MockGoogleOAuthRepositoryTrait as GoogleOAuthRepository,
+    MockOAuthStateRepositoryTrait as OAuthStateRepository,
+};
+#[cfg(test)]
+use crate::models::user::MockUserRepositoryTrait as UserRepository;
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate::*;
+
+    use super::*;
+    use crate::models::auth::{
+        MockActiveSessionRepositoryTrait as ActiveSessionRepository,
+        MockLoginAttemptRepositoryTrait as LoginAttemptRepository,
+    };
+    use crate::models::backup_code::MockBackupCodeRepositoryTrait as BackupCodeRepository;
+    use crate::models::error::UnauthorizedReason;
+    use crate::models::invite_code::MockInviteCodeRepositoryTrait as InviteCodeRepository;
+    use crate::models::user_key::MockUserKeyRepositoryTrait as UserKeyRepository;
+    use crate::services::auth::AuthServiceBuilder;
+    use crate::utils::test_util::{get_session_epoch_repository, get_user, get_user_key};
+
+    impl OAuthService {
+        pub fn new_with_repository(
+            oauth_state_repository: OAuthStateRepository,
+            google_oauth_repository: GoogleOAuthRepository,
+            github_oauth_repository: GitHubOAuthRepository,
+            user_repository: UserRepository,
+            auth_service: AuthService,
+        ) -> Self {
+            Self {
+                oauth_state_repository: Some(oauth_state_repository),
+                google_oauth_repository: Some(google_oauth_repository),
+                github_oauth_repository: Some(github_oauth_repository),
+                user_repository: Some(user_repository),
+                auth_service: Some(auth_service),
+            }
+        }
+    }
+
+    /// Returns an auth service starting sessions with the repositories, which records the login
+    /// time once.
+    fn get_auth_service(user_key_repository: UserKeyRepository) -> AuthService {
+        get_auth_service_builder(user_key_repository).build()
+    }
+
+    fn get_auth_service_builder(user_key_repository: UserKeyRepository) -> AuthServiceBuilder {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        AuthService::builder()
+            .session_epoch_repository(get_session_epoch_repository(0))
+            .active_session_repository(get_active_session_repository())
+            .user_key_repository(user_key_repository)
+            .user_repository(mocked_user_repository)
+            .invite_code_required(false)
+    }
+
+    /// Returns an invite code repository accepting only the code once.
+    fn get_invite_code_repository(code: &'static str) -> InviteCodeRepository {
+        let mut mocked_invite_code_repository = InviteCodeRepository::new();
+        mocked_invite_code_repository
+            .expect_consume()
+            .with(eq(code), always())
+            .times(1)
+            .returning(|_, _| Ok(true));
+        mocked_invite_code_repository
+    }
+
+    fn get_active_session_repository() -> ActiveSessionRepository {
+        let mut mocked_active_session_repository = ActiveSessionRepository::default();
+        mocked_active_session_repository
+            .expect_save()
+            .returning(|_, _| Ok(true));
+        mocked_active_session_repository
+    }
+
+    fn get_oauth_state_repository(issued_provider: &'static str) -> OAuthStateRepository {
+        let mut mocked_oauth_state_repository = OAuthStateRepository::default();
+        mocked_oauth_state_repository
+            .expect_find()
+            .times(1)
+            .returning(move || Ok(issued_provider.to_string()));
+        mocked_oauth_state_repository
+            .expect_delete()
+            .times(1)
+            .returning(|| Ok(true));
+        mocked_oauth_state_repository
+    }
+
+    fn get_google_oauth_repository(profile: OAuthProfile) -> GoogleOAuthRepository {
+        let mut mocked_google_oauth_repository = GoogleOAuthRepository::new();
+        mocked_google_oauth_repository
+            .expect_exchange_code()
+            .with(eq("4/0AY0e-g7"))
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(String::from("ya29.a0AfH6")) }));
+        mocked_google_oauth_repository
+            .expect_find_profile()
+            .with(eq("ya29.a0AfH6"))
+            .times(1)
+            .returning(move |_| {
+                let profile = profile.clone();
+                Box::pin(async move { Ok(profile) })
+            });
+        mocked_google_oauth_repository
+    }
+
+    fn get_github_oauth_repository(profile: OAuthProfile) -> GitHubOAuthRepository {
+        let mut mocked_github_oauth_repository = GitHubOAuthRepository::new();
+        mocked_github_oauth_repository
+            .expect_exchange_code()
+            .with(eq("e72e16c7e42f292c6912"))
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(String::from("gho_16C7e42F292c6912E7710c83")) }));
+        mocked_github_oauth_repository
+            .expect_find_profile()
+            .with(eq("gho_16C7e42F292c6912E7710c83"))
+            .times(1)
+            .returning(move |_| {
+                let profile = profile.clone();
+                Box::pin(async move { Ok(profile) })
+            });
+        mocked_github_oauth_repository
+    }
+
+    fn get_profile() -> OAuthProfile {
+        OAuthProfile {
+            email: String::from("Park@email.com"),
+            email_verified: true,
+            name: String::from("park"),
+            avatar_url: None,
+        }
+    }
+
+    #[test]
+    fn test_begin_google_login() {
+        let mut mocked_oauth_state_repository = OAuthStateRepository::default();
+        mocked_oauth_state_repository
+            .expect_save()
+            .with(eq(GOOGLE_PROVIDER), eq(OAUTH_STATE_TTL_SECONDS))
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut mocked_google_oauth_repository = GoogleOAuthRepository::new();
+        mocked_google_oauth_repository
+            .expect_get_authorization_url()
+            .times(1)
+            .returning(|state| {
+                format!(
+                    "https://accounts.google.com/o/oauth2/v2/auth?state={}",
+                    state
+                )
+            });
+
+        let mut oauth_service = OAuthService::new_with_repository(
+            mocked_oauth_state_repository,
+            mocked_google_oauth_repository,
+            GitHubOAuthRepository::new(),
+            UserRepository::new(),
+            AuthService::builder().build(),
+        );
+
+        let authorization_url = oauth_service.begin_google_login().unwrap();
+        assert!(authorization_url.starts_with("https://accounts.google.com/"));
+    }
+
+    #[actix_rt::test]
+    async fn test_complete_google_login_with_mismatched_state() {
+        let mut mocked_oauth_state_repository = OAuthStateRepository::default();
+        mocked_oauth_state_repository
+            .expect_find()
+            .times(1)
+            .returning(|| Err(ServiceError::NotFound(String::from("oauth_state:Xs8dm2"))));
+
+        let mut mocked_google_oauth_repository = GoogleOAuthRepository::new();
+        mocked_google_oauth_repository
+            .expect_exchange_code()
+            .times(0);
+
+        let mut oauth_service = OAuthService::new_with_repository(
+            mocked_oauth_state_repository,
+            mocked_google_oauth_repository,
+            GitHubOAuthRepository::new(),
+            UserRepository::new(),
+            AuthService::builder().build(),
+        );
+
+        let result = oauth_service
+            .complete_google_login("4/0AY0e-g7", "Xs8dm2", None, None)
+            .await;
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[actix_rt::test]
+    async fn test_complete_google_login_with_state_of_another_provider() {
+        let mut mocked_google_oauth_repository = GoogleOAuthRepository::new();
+        mocked_google_oauth_repository
+            .expect_exchange_code()
+            .times(0);
+
+        let mut oauth_service = OAuthService::new_with_repository(
+            get_oauth_state_repository("github"),
+            mocked_google_oauth_repository,
+            GitHubOAuthRepository::new(),
+            UserRepository::new(),
+            AuthService::builder().build(),
+        );
+
+        let result = oauth_service
+            .complete_google_login("4/0AY0e-g7", "Xs8dm2", None, None)
+            .await;
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[actix_rt::test]
+    async fn test_complete_google_login_with_existing_user() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq("park@email.com"))
+            .times(1)
+            .returning(|email| Ok(get_user(1, email)));
+        mocked_user_repository
+            .expect_create_without_password()
+            .times(0);
+
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .with(eq(1))
+            .times(1)
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        let mut oauth_service = OAuthService::new_with_repository(
+            get_oauth_state_repository(GOOGLE_PROVIDER),
+            get_google_oauth_repository(get_profile()),
+            GitHubOAuthRepository::new(),
+            mocked_user_repository,
+            get_auth_service(mocked_user_key_repository),
+        );
+
+        let user_session = oauth_service
+            .complete_google_login("4/0AY0e-g7", "Xs8dm2", None, None)
+            .await
+            .unwrap();
+        assert_eq!(user_session.user_id, 1);
+        assert_eq!(user_session.user_public_key, "d63ee429");
+    }
+
+    #[actix_rt::test]
+    async fn test_complete_google_login_with_existing_user_without_injected_auth_service() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq("park@email.com"))
+            .times(1)
+            .returning(|email| {
+                Ok(User {
+                    status: UserStatus::Deactivated,
+                    ..get_user(1, email)
+                })
+            });
+
+        let mut oauth_service = OAuthService::new_with_repository(
+            get_oauth_state_repository(GOOGLE_PROVIDER),
+            get_google_oauth_repository(get_profile()),
+            GitHubOAuthRepository::new(),
+            mocked_user_repository,
+            AuthService::new(),
+        );
+        oauth_service.auth_service = None;
+
+        let result = oauth_service
+            .complete_google_login("4/0AY0e-g7", "Xs8dm2", None, None)
+            .await;
+        assert!(matches!(
+            result,
+            Err(ServiceError::Unauthorized(Some(
+                UnauthorizedReason::AccountDeactivated
+            )))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_complete_github_login_with_unverified_email() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository.expect_find_by_email().times(0);
+        mocked_user_repository
+            .expect_create_without_password()
+            .times(0);
+
+        let mut oauth_service = OAuthService::new_with_repository(
+            get_oauth_state_repository(GITHUB_PROVIDER),
+            GoogleOAuthRepository::new(),
+            get_github_oauth_repository(OAuthProfile {
+                email_verified: false,
+                ..get_profile()
+            }),
+            mocked_user_repository,
+            AuthService::builder().build(),
+        );
+
+        let result = oauth_service
+            .complete_github_login("e72e16c7e42f292c6912", "Xs8dm2", None, None)
+            .await;
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[actix_rt::test]
+    async fn test_complete_github_login_with_new_user() {
+        let mut sequence = mockall::Sequence::new();
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq("park@email.com"))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|email| Err(ServiceError::NotFound(email.to_string())));
+        mocked_user_repository
+            .expect_create_without_password()
+            .with(eq("park"), eq("park@email.com"), eq(None))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _, _| Ok(true));
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq("park@email.com"))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|email| {
+                Ok(User {
+                    password: String::new(),
+                    has_password: false,
+                    ..get_user(1, email)
+                })
+            });
+        mocked_user_repository.expect_create().times(0);
+
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .with(eq(1))
+            .times(1)
+            .returning(|user_id| Err(ServiceError::NotFound(user_id.to_string())));
+
+        let mut oauth_service = OAuthService::new_with_repository(
+            get_oauth_state_repository(GITHUB_PROVIDER),
+            GoogleOAuthRepository::new(),
+            get_github_oauth_repository(get_profile()),
+            mocked_user_repository,
+            get_auth_service_builder(mocked_user_key_repository)
+                .invite_code_required(true)
+                .invite_code_repository(get_invite_code_repository("Kq3Zt8WmPx2c"))
+                .build(),
+        );
+
+        let user_session = oauth_service
+            .complete_github_login("e72e16c7e42f292c6912", "Xs8dm2", None, Some("Kq3Zt8WmPx2c"))
+            .await
+            .unwrap();
+        assert_eq!(user_session.user_id, 1);
+        assert_eq!(user_session.user_email, "park@email.com");
+        assert_eq!(user_session.user_public_key, "");
+    }
+
+    #[actix_rt::test]
+    async fn test_complete_google_login_with_new_user_without_invite_code() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq("park@email.com"))
+            .times(1)
+            .returning(|email| Err(ServiceError::NotFound(email.to_string())));
+        mocked_user_repository
+            .expect_create_without_password()
+            .times(0);
+
+        let mut mocked_invite_code_repository = InviteCodeRepository::new();
+        mocked_invite_code_repository
+            .expect_consume()
+            .returning(|_, _| Ok(false));
+
+        let mut oauth_service = OAuthService::new_with_repository(
+            get_oauth_state_repository(GOOGLE_PROVIDER),
+            get_google_oauth_repository(get_profile()),
+            GitHubOAuthRepository::new(),
+            mocked_user_repository,
+            AuthService::builder()
+                .invite_code_required(true)
+                .invite_code_repository(mocked_invite_code_repository)
+                .build(),
+        );
+
+        let result = oauth_service
+            .complete_google_login("4/0AY0e-g7", "Xs8dm2", None, None)
+            .await;
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[actix_rt::test]
+    async fn test_complete_google_login_with_new_user_of_disposable_email() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq("park@mailinator.com"))
+            .times(1)
+            .returning(|email| Err(ServiceError::NotFound(email.to_string())));
+        mocked_user_repository
+            .expect_create_without_password()
+            .times(0);
+
+        let mut mocked_invite_code_repository = InviteCodeRepository::new();
+        mocked_invite_code_repository.expect_consume().times(0);
+
+        let mut oauth_service = OAuthService::new_with_repository(
+            get_oauth_state_repository(GOOGLE_PROVIDER),
+            get_google_oauth_repository(OAuthProfile {
+                email: String::from("park@Mailinator.com"),
+                ..get_profile()
+            }),
+            GitHubOAuthRepository::new(),
+            mocked_user_repository,
+            AuthService::builder()
+                .invite_code_required(true)
+                .invite_code_repository(mocked_invite_code_repository)
+                .build(),
+        );
+
+        let result = oauth_service
+            .complete_google_login("4/0AY0e-g7", "Xs8dm2", None, Some("Kq3Zt8WmPx2c"))
+            .await;
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[actix_rt::test]
+    async fn test_complete_google_login_with_totp_enabled_user() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq("park@email.com"))
+            .times(1)
+            .returning(|email| {
+                Ok(User {
+                    totp_secret: Some(String::from("JBSWY3DPEHPK3PXP")),
+                    totp_enabled: true,
+                    ..get_user(1, email)
+                })
+            });
+
+        let mut mocked_active_session_repository = ActiveSessionRepository::default();
+        mocked_active_session_repository.expect_save().times(0);
+        let auth_service = AuthService::builder()
+            .login_attempt_repository(LoginAttemptRepository::default())
+            .active_session_repository(mocked_active_session_repository)
+            .build();
+
+        let mut oauth_service = OAuthService::new_with_repository(
+            get_oauth_state_repository(GOOGLE_PROVIDER),
+            get_google_oauth_repository(get_profile()),
+            GitHubOAuthRepository::new(),
+            mocked_user_repository,
+            auth_service,
+        );
+
+        let result = oauth_service
+            .complete_google_login("4/0AY0e-g7", "Xs8dm2", None, None)
+            .await;
+        assert!(matches!(result, Err(ServiceError::TotpRequired)));
+    }
+
+    #[actix_rt::test]
+    async fn test_complete_github_login_with_wrong_totp_code() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq("park@email.com"))
+            .times(1)
+            .returning(|email| {
+                Ok(User {
+                    totp_secret: Some(String::from("JBSWY3DPEHPK3PXP")),
+                    totp_enabled: true,
+                    ..get_user(1, email)
+                })
+            });
+
+        let mut mocked_login_attempt_repository = LoginAttemptRepository::default();
+        mocked_login_attempt_repository
+            .expect_increase()
+            .times(1)
+            .returning(|_| Ok(1));
+        let mut mocked_backup_code_repository = BackupCodeRepository::new();
+        mocked_backup_code_repository
+            .expect_find_all_by_user_id()
+            .with(eq(1))
+            .times(1)
+            .returning(|_| Ok(vec![]));
+        let mut mocked_active_session_repository = ActiveSessionRepository::default();
+        mocked_active_session_repository.expect_save().times(0);
+        let auth_service = AuthService::builder()
+            .login_attempt_repository(mocked_login_attempt_repository)
+            .backup_code_repository(mocked_backup_code_repository)
+            .active_session_repository(mocked_active_session_repository)
+            .build();
+
+        let mut oauth_service = OAuthService::new_with_repository(
+            get_oauth_state_repository(GITHUB_PROVIDER),
+            GoogleOAuthRepository::new(),
+            get_github_oauth_repository(get_profile()),
+            mocked_user_repository,
+            auth_service,
+        );
+
+        let result = oauth_service
+            .complete_github_login("e72e16c7e42f292c6912", "Xs8dm2", Some("000000"), None)
+            .await;
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+}
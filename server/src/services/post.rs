@@ -1,16 +1,83 @@
-use chrono::NaiveDateTime;
+use chrono::{Duration, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde_json::Value;
 
+use crate::models::category::*;
 use crate::models::error::{get_service_error, ServiceError};
 use crate::models::post::*;
+use crate::models::post_tag::*;
+use crate::models::user::*;
+#[cfg(not(test))]
+use crate::utils::webhook_util::WebhookDispatcher;
+use crate::utils::webhook_util::WebhookEvent;
+use crate::utils::{env_util, metric_util, token_util};
+
+/// A number of posts in a page if the limit is not given.
+const DEFAULT_POST_PAGE_LIMIT: u64 = 20;
+/// A maximum number of posts in a page.
+const MAX_POST_PAGE_LIMIT: u64 = 100;
+/// A maximum number of tags of a post.
+const MAX_POST_TAGS: usize = 20;
+/// A maximum number of characters in a tag.
+const MAX_TAG_LENGTH: usize = 100;
+/// A maximum number of terms in a search query.
+const MAX_SEARCH_TERMS: usize = 10;
+/// A maximum number of pinned posts of a user.
+const MAX_PINNED_POSTS: u64 = 5;
+/// A length of the token of the public link of a post, which is long enough not to be guessed.
+const SHARE_TOKEN_LENGTH: usize = 32;
+/// A maximum number of posts deleted in a batch.
+const MAX_BULK_DELETE_POSTS: usize = 100;
+/// A maximum number of bytes in the encrypted title of a post, if it is not configured.
+const DEFAULT_MAX_POST_TITLE_LENGTH: usize = 1024;
+/// A maximum number of bytes in the encrypted content of a post, if it is not configured.
+/// It is the capacity of `TEXT` column.
+const DEFAULT_MAX_POST_CONTENT_LENGTH: usize = 65_535;
+/// A maximum number of posts a user can create in a window, if it is not configured.
+const DEFAULT_POST_CREATION_LIMIT: u64 = 60;
+/// A number of seconds of the window of the post creation limit, if it is not configured.
+const DEFAULT_POST_CREATION_WINDOW_SECONDS: usize = 3600;
+/// A maximum number of entries imported at once.
+const MAX_IMPORTED_POSTS: usize = 1000;
 
 pub struct PostService {
     post_repository: Option<PostRepository>,
+    post_tag_repository: Option<PostTagRepository>,
+    user_repository: Option<UserRepository>,
+    category_repository: Option<CategoryRepository>,
+    webhook_dispatcher: Option<WebhookDispatcher>,
+    post_creation_count_repository: Option<PostCreationCountRepository>,
+    max_title_length: usize,
+    max_content_length: usize,
+    post_creation_limit: u64,
+    post_creation_window_seconds: usize,
 }
 
 impl PostService {
     pub fn new() -> Self {
         Self {
             post_repository: None,
+            post_tag_repository: None,
+            user_repository: None,
+            category_repository: None,
+            webhook_dispatcher: None,
+            post_creation_count_repository: None,
+            max_title_length: env_util::get_env_var_or(
+                "POST_MAX_TITLE_LENGTH",
+                DEFAULT_MAX_POST_TITLE_LENGTH,
+            ),
+            max_content_length: env_util::get_env_var_or(
+                "POST_MAX_CONTENT_LENGTH",
+                DEFAULT_MAX_POST_CONTENT_LENGTH,
+            ),
+            post_creation_limit: env_util::get_env_var_or(
+                "POST_CREATION_LIMIT",
+                DEFAULT_POST_CREATION_LIMIT,
+            ),
+            post_creation_window_seconds: env_util::get_env_var_or(
+                "POST_CREATION_WINDOW_SECONDS",
+                DEFAULT_POST_CREATION_WINDOW_SECONDS,
+            ),
         }
     }
 
@@ -24,6 +91,198 @@ impl PostService {
         }
     }
 
+    fn post_creation_count_repository(
+        &mut self,
+        new_repository: Option<PostCreationCountRepository>,
+    ) -> &mut PostCreationCountRepository {
+        match new_repository {
+            Some(_) => {
+                self.post_creation_count_repository = new_repository;
+                self.post_creation_count_repository.as_mut().unwrap()
+            }
+            None => self.post_creation_count_repository.as_mut().unwrap(),
+        }
+    }
+
+    fn post_tag_repository(
+        &mut self,
+        new_repository: Option<PostTagRepository>,
+    ) -> &PostTagRepository {
+        match new_repository {
+            Some(_) => {
+                self.post_tag_repository = new_repository;
+                self.post_tag_repository.as_ref().unwrap()
+            }
+            None => self.post_tag_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn user_repository(&mut self, new_repository: Option<UserRepository>) -> &UserRepository {
+        match new_repository {
+            Some(_) => {
+                self.user_repository = new_repository;
+                self.user_repository.as_ref().unwrap()
+            }
+            None => self.user_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn category_repository(
+        &mut self,
+        new_repository: Option<CategoryRepository>,
+    ) -> &CategoryRepository {
+        match new_repository {
+            Some(_) => {
+                self.category_repository = new_repository;
+                self.category_repository.as_ref().unwrap()
+            }
+            None => self.category_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn webhook_dispatcher(
+        &mut self,
+        new_webhook_dispatcher: Option<WebhookDispatcher>,
+    ) -> &WebhookDispatcher {
+        match new_webhook_dispatcher {
+            Some(_) => {
+                self.webhook_dispatcher = new_webhook_dispatcher;
+                self.webhook_dispatcher.as_ref().unwrap()
+            }
+            None => self.webhook_dispatcher.as_ref().unwrap(),
+        }
+    }
+
+    /// Emits the event of the post to the webhooks of the user in the background.
+    fn dispatch_webhook_event(&mut self, user_id: u64, event: WebhookEvent, post_id: u64) {
+        let fallback_webhook_dispatcher =
+            some_if_true!(self.webhook_dispatcher.is_none() => WebhookDispatcher::new());
+        self.webhook_dispatcher(fallback_webhook_dispatcher)
+            .dispatch(user_id, event, post_id);
+    }
+
+    /// Parses the mood tagged to a post.
+    /// Returns `ServiceError::InvalidArgument` if the mood is unknown.
+    fn parse_mood(mood: &Option<String>) -> Result<Option<Mood>, ServiceError> {
+        match mood {
+            Some(mood) => match mood.parse() {
+                Ok(mood) => Ok(Some(mood)),
+                Err(_) => Err(get_service_error(ServiceError::InvalidArgument)),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Trims and lowercases tags, and removes empty and duplicated ones.
+    /// Returns `ServiceError::InvalidArgument` if there are more than 20 tags,
+    /// or a tag is longer than 100 characters.
+    fn normalize_tags(tags: &[String]) -> Result<Vec<String>, ServiceError> {
+        let mut normalized_tags: Vec<String> = vec![];
+        for tag in tags {
+            let tag = tag.trim().to_lowercase();
+            if tag.is_empty() || normalized_tags.contains(&tag) {
+                continue;
+            }
+            if tag.chars().count() > MAX_TAG_LENGTH {
+                return Err(get_service_error(ServiceError::InvalidArgument));
+            }
+            normalized_tags.push(tag);
+        }
+
+        if normalized_tags.len() > MAX_POST_TAGS {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        Ok(normalized_tags)
+    }
+
+    /// Parses an entry of an exported archive into a post to be imported.
+    /// Returns `None` if the entry is malformed, or its title or content is empty or longer than
+    /// the maximum length of a post.
+    fn parse_imported_post(&self, entry: &Value) -> Option<ImportedPost> {
+        let post: ImportedPost = serde_json::from_value(entry.clone()).ok()?;
+        if post.title.trim().is_empty()
+            || post.content.trim().is_empty()
+            || self
+                .validate_length(Some(&post.title), Some(&post.content))
+                .is_err()
+        {
+            return None;
+        }
+
+        Some(post)
+    }
+
+    /// Counts the current streak and the longest streak of consecutive days.
+    /// The current streak is kept if the last day is today or yesterday, as today is not over yet.
+    /// Days after today are ignored.
+    fn count_streaks(mut days: Vec<NaiveDate>, today: NaiveDate) -> (u32, u32) {
+        days.retain(|day| *day <= today);
+        days.sort();
+        days.dedup();
+
+        let mut streak = 0;
+        let mut longest_streak = 0;
+        let mut last_day: Option<NaiveDate> = None;
+        for day in days {
+            streak = match last_day {
+                Some(last_day) if day - last_day == Duration::days(1) => streak + 1,
+                _ => 1,
+            };
+            longest_streak = longest_streak.max(streak);
+            last_day = Some(day);
+        }
+
+        let current_streak = match last_day {
+            Some(last_day) if today - last_day <= Duration::days(1) => streak,
+            _ => 0,
+        };
+
+        (current_streak, longest_streak)
+    }
+
+    /// Converts posts into post DTOs with their tags.
+    fn get_post_dtos(&mut self, post_list: Vec<Post>) -> Result<Vec<PostDTO>, ServiceError> {
+        let post_ids: Vec<u64> = post_list.iter().map(|post| post.id).collect();
+        let post_tag_list = if post_ids.is_empty() {
+            vec![]
+        } else {
+            let fallback_repository =
+                some_if_true!(self.post_tag_repository.is_none() => PostTagRepository::new());
+            self.post_tag_repository(fallback_repository)
+                .find_all_by_post_ids(&post_ids)?
+        };
+
+        Ok(post_list
+            .into_iter()
+            .map(|post| -> PostDTO {
+                let tags = post_tag_list
+                    .iter()
+                    .filter(|post_tag| post_tag.post_id == post.id)
+                    .map(|post_tag| post_tag.tag.clone())
+                    .collect();
+
+                PostDTO {
+                    id: post.id,
+                    title: post.title,
+                    content: post.content,
+                    date: post.date,
+                    created_at: Utc.from_utc_datetime(&post.created_at),
+                    updated_at: post
+                        .updated_at
+                        .map(|updated_at| Utc.from_utc_datetime(&updated_at)),
+                    tags,
+                    version: post.version,
+                    publish_at: post.publish_at,
+                    status: post.status,
+                    pinned: post.pinned,
+                    mood: post.mood,
+                    category_id: post.category_id,
+                }
+            })
+            .collect())
+    }
+
     /// Finds a post by user id and post id.
     pub fn get(&mut self, user_id: u64, id: u64) -> Result<PostDTO, ServiceError> {
         let post = {
@@ -33,51 +292,343 @@ impl PostService {
                 .find(user_id, id)?
         };
 
-        Ok(PostDTO {
-            id: post.id,
-            title: post.title,
-            content: post.content,
-            date: post.date,
-            updated_at: post.updated_at,
-            created_at: post.created_at,
+        let mut post_dtos = self.get_post_dtos(vec![post])?;
+        Ok(post_dtos.remove(0))
+    }
+
+    /// Finds the timezone of the user, which is UTC if it is not set or unknown.
+    fn find_timezone(&mut self, user_id: u64) -> Result<Tz, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.user_repository.is_none() => UserRepository::new());
+        Ok(self
+            .user_repository(fallback_repository)
+            .find_by_id(user_id)?
+            .timezone
+            .and_then(|timezone| timezone.parse::<Tz>().ok())
+            .unwrap_or(Tz::UTC))
+    }
+
+    /// Converts the local date and time in the timezone to UTC.
+    /// The local time skipped by a transition of the offset such as DST is converted with the
+    /// offset around it.
+    fn to_utc(timezone: Tz, local: NaiveDateTime) -> NaiveDateTime {
+        match timezone.from_local_datetime(&local).earliest() {
+            Some(datetime) => datetime.naive_utc(),
+            None => {
+                let offset = timezone.offset_from_utc_datetime(&local).fix();
+                local - Duration::seconds(i64::from(offset.local_minus_utc()))
+            }
+        }
+    }
+
+    /// Returns a filter for published posts of the user dated between `from` and `to`, bearing
+    /// the tags and in the category. The bounds are in the timezone of the user, so that a day is
+    /// divided as the user sees it. The tags are normalized, and posts must bear any of them
+    /// unless the tag mode is `all`.
+    /// Returns `ServiceError::InvalidArgument` if `from` is later than `to`, the tag mode is
+    /// unknown, or the tags are invalid.
+    fn get_filter(
+        &mut self,
+        user_id: u64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        tags: &[String],
+        tag_mode: Option<String>,
+        category_id: Option<u64>,
+    ) -> Result<PostFilter, ServiceError> {
+        let tag_mode = match tag_mode {
+            Some(tag_mode) => match tag_mode.parse() {
+                Ok(tag_mode) => tag_mode,
+                Err(_) => return Err(get_service_error(ServiceError::InvalidArgument)),
+            },
+            None => TagMode::Any,
+        };
+        let tags = Self::normalize_tags(tags)?;
+
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err(get_service_error(ServiceError::InvalidArgument));
+            }
+        }
+
+        let (from, to) = if from.is_some() || to.is_some() {
+            let timezone = self.find_timezone(user_id)?;
+            (
+                from.map(|from| Self::to_utc(timezone, from)),
+                to.map(|to| Self::to_utc(timezone, to)),
+            )
+        } else {
+            (from, to)
+        };
+
+        Ok(PostFilter {
+            from,
+            to,
+            tags,
+            tag_mode,
+            category_id,
+            published_until: Utc::now().naive_utc(),
         })
     }
 
-    /// Finds all post written by specific user.
-    pub fn get_list(&mut self, user_id: u64) -> Result<Vec<PostDTO>, ServiceError> {
-        let post_list = {
+    /// Returns the cursor after the last post if the page is full, as more posts may follow.
+    fn get_next_cursor(post_list: &[Post], limit: u64) -> Option<String> {
+        if post_list.len() as u64 == limit {
+            post_list
+                .last()
+                .map(|post| PostCursor::after(post).encode())
+        } else {
+            None
+        }
+    }
+
+    /// Finds a page of posts written by specific user, with the total count of the posts.
+    ///
+    /// The limit is 20 by default, and it is clamped between 1 and 100.
+    /// The offset is 0 by default, and the page is empty if it is beyond the end.
+    /// If the `after` cursor of the previous page is given, the page starts after it instead of
+    /// the offset, and pinned posts are not found again. Unlike the offset, the cursor doesn't
+    /// duplicate or skip posts even if posts are created or deleted between page loads.
+    /// The page has the `next` cursor if it ends with an unpinned post and is full.
+    /// Only posts dated between `from` and `to` inclusive are found if the bounds are given,
+    /// and a missing bound is open-ended. The bounds are in the timezone of the user.
+    /// Only posts bearing any of the tags are found if they are given, or posts bearing all of
+    /// them if the tag mode is `all`. Only posts in the category of the id are found if it is
+    /// given. A category of another user finds no post.
+    /// Posts scheduled to be published later are not found until their publish time passes.
+    /// Pinned posts come first from the latest pinned one, and the rest are in desc date order.
+    /// Returns `ServiceError::InvalidArgument` if the cursor is malformed, or the tag mode is
+    /// neither `all` nor `any`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_list(
+        &mut self,
+        user_id: u64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        tags: &[String],
+        tag_mode: Option<String>,
+        category_id: Option<u64>,
+        limit: Option<u64>,
+        offset: Option<u64>,
+        after: Option<String>,
+    ) -> Result<PostPageDTO, ServiceError> {
+        let cursor = after
+            .map(|after| {
+                PostCursor::decode(&after)
+                    .ok_or_else(|| get_service_error(ServiceError::InvalidArgument))
+            })
+            .transpose()?;
+        let filter = self.get_filter(user_id, from, to, tags, tag_mode, category_id)?;
+        let limit = limit
+            .unwrap_or(DEFAULT_POST_PAGE_LIMIT)
+            .clamp(1, MAX_POST_PAGE_LIMIT);
+        let offset = offset.unwrap_or(0);
+
+        let (post_list, total_count, next) = {
+            let fallback_repository =
+                some_if_true!(self.post_repository.is_none() => PostRepository::new());
+            let post_repository = self.post_repository(fallback_repository);
+
+            if let Some(cursor) = cursor {
+                let post_list =
+                    post_repository.find_page_after(user_id, &filter, &cursor, limit)?;
+                let next = Self::get_next_cursor(&post_list, limit);
+                (post_list, post_repository.count(user_id, &filter)?, next)
+            } else {
+                let mut pinned_post_list = post_repository.find_all_pinned(user_id, &filter)?;
+                pinned_post_list.sort_by(|a, b| {
+                    b.pinned_at
+                        .cmp(&a.pinned_at)
+                        .then(b.date.cmp(&a.date))
+                        .then(b.id.cmp(&a.id))
+                });
+                let pinned_count = pinned_post_list.len() as u64;
+
+                let mut post_list: Vec<Post> = pinned_post_list
+                    .into_iter()
+                    .skip(offset as usize)
+                    .take(limit as usize)
+                    .collect();
+                let unpinned_limit = limit - post_list.len() as u64;
+                let mut next = None;
+                if unpinned_limit > 0 {
+                    let unpinned_post_list = post_repository.find_page_in_desc_date_order(
+                        user_id,
+                        &filter,
+                        unpinned_limit,
+                        offset.saturating_sub(pinned_count),
+                    )?;
+                    next = Self::get_next_cursor(&unpinned_post_list, unpinned_limit);
+                    post_list.extend(unpinned_post_list);
+                }
+
+                (post_list, post_repository.count(user_id, &filter)?, next)
+            }
+        };
+
+        Ok(PostPageDTO {
+            posts: self.get_post_dtos(post_list)?,
+            total_count,
+            next,
+        })
+    }
+
+    /// Counts posts written by specific user.
+    /// Only posts dated between `from` and `to` inclusive in the timezone of the user are counted
+    /// if the bounds are given, and only posts bearing the tag are counted if it is given.
+    /// Posts scheduled to be published later are not counted until their publish time passes.
+    pub fn count(
+        &mut self,
+        user_id: u64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        tag: Option<String>,
+    ) -> Result<u64, ServiceError> {
+        let tags: Vec<String> = tag.into_iter().collect();
+        let filter = self.get_filter(user_id, from, to, &tags, None, None)?;
+
+        let fallback_repository =
+            some_if_true!(self.post_repository.is_none() => PostRepository::new());
+        self.post_repository(fallback_repository)
+            .count(user_id, &filter)
+    }
+
+    /// Counts posts written by specific user for each mood.
+    /// Only posts dated between `from` and `to` inclusive in the timezone of the user are counted
+    /// if the bounds are given, and posts without a mood are not counted. Every mood is listed
+    /// even if no post is tagged with it.
+    pub fn get_mood_stats(
+        &mut self,
+        user_id: u64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> Result<Vec<MoodCountDTO>, ServiceError> {
+        let filter = self.get_filter(user_id, from, to, &[], None, None)?;
+
+        let mood_list = {
             let fallback_repository =
                 some_if_true!(self.post_repository.is_none() => PostRepository::new());
             self.post_repository(fallback_repository)
-                .find_all_in_desc_date_order(user_id)?
+                .find_all_moods(user_id, &filter)?
         };
 
-        Ok(post_list
+        Ok(Mood::ALL
             .iter()
-            .map(|post| -> PostDTO {
-                PostDTO {
-                    id: post.id,
-                    title: post.title.clone(),
-                    content: post.content.clone(),
-                    date: post.date,
-                    created_at: post.created_at,
-                    updated_at: post.updated_at,
-                }
+            .map(|mood| MoodCountDTO {
+                mood: *mood,
+                count: mood_list
+                    .iter()
+                    .filter(|tagged_mood| *tagged_mood == mood)
+                    .count() as u64,
             })
             .collect())
     }
 
-    /// Finds all summarized post written by specific user.
+    /// Searches posts written by specific user, and finds a page of matched posts with the total
+    /// count of them.
+    ///
+    /// As the title and the content of a post are encrypted, the query is matched against tags.
+    /// The query is split into terms by whitespace, and a post matches if any of its tags starts
+    /// with any of the terms. Posts matching more terms come first, then they are in desc date
+    /// order. The limit and the offset are handled as in `get_list`, but the page has no `next`
+    /// cursor as it is not in date order. Drafts and posts scheduled to be published later are not
+    /// searched.
+    /// Returns `ServiceError::InvalidArgument` if the query is empty or has more than 10 terms.
+    pub fn search(
+        &mut self,
+        user_id: u64,
+        query: &str,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<PostPageDTO, ServiceError> {
+        let mut terms: Vec<String> = vec![];
+        for term in query.split_whitespace() {
+            let term = term.to_lowercase();
+            if !terms.contains(&term) {
+                terms.push(term);
+            }
+        }
+        if terms.is_empty() || terms.len() > MAX_SEARCH_TERMS {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        let limit = limit
+            .unwrap_or(DEFAULT_POST_PAGE_LIMIT)
+            .clamp(1, MAX_POST_PAGE_LIMIT) as usize;
+        let offset = offset.unwrap_or(0) as usize;
+
+        let post_tag_list = {
+            let fallback_repository =
+                some_if_true!(self.post_tag_repository.is_none() => PostTagRepository::new());
+            self.post_tag_repository(fallback_repository)
+                .find_all_matching(user_id, &terms)?
+        };
+        if post_tag_list.is_empty() {
+            return Ok(PostPageDTO {
+                posts: vec![],
+                total_count: 0,
+                next: None,
+            });
+        }
+
+        let mut post_ids: Vec<u64> = post_tag_list
+            .iter()
+            .map(|post_tag| post_tag.post_id)
+            .collect();
+        post_ids.sort_unstable();
+        post_ids.dedup();
+
+        let now = Utc::now().naive_utc();
+        let mut post_list = {
+            let fallback_repository =
+                some_if_true!(self.post_repository.is_none() => PostRepository::new());
+            self.post_repository(fallback_repository)
+                .find_all_by_ids(user_id, &post_ids, &now)?
+        };
+        post_list.retain(|post| {
+            post.status == PostStatus::Published
+                && !matches!(post.publish_at, Some(publish_at) if publish_at > now)
+        });
+
+        let count_matched_terms = |post: &Post| {
+            terms
+                .iter()
+                .filter(|term| {
+                    post_tag_list.iter().any(|post_tag| {
+                        post_tag.post_id == post.id && post_tag.tag.starts_with(term.as_str())
+                    })
+                })
+                .count()
+        };
+        post_list.sort_by(|a, b| {
+            count_matched_terms(b)
+                .cmp(&count_matched_terms(a))
+                .then(b.date.cmp(&a.date))
+                .then(b.id.cmp(&a.id))
+        });
+
+        let total_count = post_list.len() as u64;
+        let post_list = post_list.into_iter().skip(offset).take(limit).collect();
+
+        Ok(PostPageDTO {
+            posts: self.get_post_dtos(post_list)?,
+            total_count,
+            next: None,
+        })
+    }
+
+    /// Finds all summarized post written by specific user, except drafts.
     pub fn get_summarized_list(
         &mut self,
         user_id: u64,
     ) -> Result<Vec<SummarizedPostDTO>, ServiceError> {
-        let post_list = {
+        let mut post_list = {
             let fallback_repository =
                 some_if_true!(self.post_repository.is_none() => PostRepository::new());
             self.post_repository(fallback_repository)
                 .find_all_in_desc_date_order(user_id)?
         };
+        post_list.retain(|post| post.status == PostStatus::Published);
 
         Ok(post_list
             .iter()
@@ -91,95 +642,671 @@ impl PostService {
             .collect())
     }
 
-    /// Creates a new post and returns id of the created post.
+    /// Returns `ServiceError::InvalidArgument` if the title or the content is longer than the
+    /// maximum length. As they are encrypted by the client, their lengths are the numbers of bytes
+    /// of the ciphertexts.
+    fn validate_length(
+        &self,
+        title: Option<&str>,
+        content: Option<&str>,
+    ) -> Result<(), ServiceError> {
+        let is_too_long = |text: Option<&str>, max_length: usize| matches!(text, Some(text) if text.len() > max_length);
+        if is_too_long(title, self.max_title_length)
+            || is_too_long(content, self.max_content_length)
+        {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        Ok(())
+    }
+
+    /// Creates a new post of the status and returns id of the created post.
+    /// The tags are normalized before they are saved.
+    /// The mood is optional, and returns `ServiceError::InvalidArgument` if it is unknown.
+    /// The post is not published until `publish_at`, if it is given.
+    /// A draft may have an empty content, but a published post may not.
+    /// The title and the content must not be longer than the maximum lengths, which are 1024 and
+    /// 65535 bytes by default.
+    ///
+    /// A user can create up to 60 posts an hour by default, which is counted separately from the
+    /// rate limit of the client IP. Returns `ServiceError::TooManyRequests` if the user has
+    /// already created that many posts in the current window.
+    #[allow(clippy::too_many_arguments)]
     pub fn create(
         &mut self,
         user_id: u64,
         title: &str,
         content: &str,
         date: &NaiveDateTime,
+        tags: &[String],
+        publish_at: &Option<NaiveDateTime>,
+        status: PostStatus,
+        mood: &Option<String>,
     ) -> Result<u64, ServiceError> {
-        if title.trim().is_empty() || content.trim().is_empty() {
+        if title.trim().is_empty() || (status == PostStatus::Published && content.trim().is_empty())
+        {
             return Err(get_service_error(ServiceError::InvalidArgument));
         }
+        self.validate_length(Some(title), Some(content))?;
+        let mood = Self::parse_mood(mood)?;
+
+        let tags = Self::normalize_tags(tags)?;
+
+        let creation_count = {
+            let fallback_repository = some_if_true!(self.post_creation_count_repository.is_none() => PostCreationCountRepository::new(user_id));
+            self.post_creation_count_repository(fallback_repository)
+                .find()?
+        };
+        if creation_count >= self.post_creation_limit {
+            return Err(get_service_error(ServiceError::TooManyRequests(
+                self.post_creation_window_seconds as u64,
+            )));
+        }
 
         let post_list = {
             let fallback_repository =
                 some_if_true!(self.post_repository.is_none() => PostRepository::new());
             self.post_repository(fallback_repository)
-                .create(user_id, title, content, date)?;
+                .create(user_id, title, content, date, publish_at, status, &mood)?;
             self.post_repository(None).find_all(user_id)?
         };
+        let id = post_list[post_list.len() - 1].id;
+
+        if !tags.is_empty() {
+            let fallback_repository =
+                some_if_true!(self.post_tag_repository.is_none() => PostTagRepository::new());
+            self.post_tag_repository(fallback_repository)
+                .replace(id, &tags)?;
+        }
+
+        let post_creation_window_seconds = self.post_creation_window_seconds;
+        self.post_creation_count_repository(None)
+            .increase(post_creation_window_seconds)?;
 
-        Ok(post_list[post_list.len() - 1].id)
+        metric_util::count_post_created();
+        self.dispatch_webhook_event(user_id, WebhookEvent::PostCreated, id);
+        Ok(id)
     }
 
-    /// Deletes a post written by specific user.
-    pub fn delete(&mut self, id: u64, user_id: u64) -> Result<bool, ServiceError> {
-        let fallback_repository =
-            some_if_true!(self.post_repository.is_none() => PostRepository::new());
-        self.post_repository(fallback_repository)
-            .delete(user_id, id)
+    /// Counts the current and the longest writing streaks of specific user, which are numbers of
+    /// consecutive days with at least one post.
+    /// Days are divided in the timezone of the user, which is UTC if it is not set.
+    /// Posts in the trash and posts scheduled to be published later are not counted.
+    pub fn get_streak(&mut self, user_id: u64) -> Result<PostStreakDTO, ServiceError> {
+        let timezone = self.find_timezone(user_id)?;
+
+        let now = Utc::now().naive_utc();
+        let filter = PostFilter {
+            from: None,
+            to: None,
+            tags: vec![],
+            tag_mode: TagMode::Any,
+            category_id: None,
+            published_until: now,
+        };
+        let date_list = {
+            let fallback_repository =
+                some_if_true!(self.post_repository.is_none() => PostRepository::new());
+            self.post_repository(fallback_repository)
+                .find_all_dates(user_id, &filter)?
+        };
+
+        let to_local_day = |date: &NaiveDateTime| timezone.from_utc_datetime(date).date_naive();
+        let days = date_list.iter().map(to_local_day).collect();
+        let (current_streak, longest_streak) = Self::count_streaks(days, to_local_day(&now));
+
+        Ok(PostStreakDTO {
+            current_streak,
+            longest_streak,
+        })
     }
 
-    /// Updates a post written by specific user.
-    pub fn update(
+    /// Finds all post written by specific user, which are scheduled to be published later.
+    pub fn get_scheduled(&mut self, user_id: u64) -> Result<Vec<PostDTO>, ServiceError> {
+        let post_list = {
+            let fallback_repository =
+                some_if_true!(self.post_repository.is_none() => PostRepository::new());
+            self.post_repository(fallback_repository)
+                .find_all_scheduled(user_id, &Utc::now().naive_utc())?
+        };
+
+        self.get_post_dtos(post_list)
+    }
+
+    /// Finds all drafts written by specific user.
+    pub fn get_drafts(&mut self, user_id: u64) -> Result<Vec<PostDTO>, ServiceError> {
+        let post_list = {
+            let fallback_repository =
+                some_if_true!(self.post_repository.is_none() => PostRepository::new());
+            self.post_repository(fallback_repository)
+                .find_all_drafts(user_id)?
+        };
+
+        self.get_post_dtos(post_list)
+    }
+
+    /// Finds all post written by specific user to be exported, including posts in the trash
+    /// and posts scheduled to be published later.
+    /// The title and the content of the posts remain encrypted.
+    pub fn export(&mut self, user_id: u64) -> Result<Vec<Post>, ServiceError> {
+        let mut post_list = {
+            let fallback_repository =
+                some_if_true!(self.post_repository.is_none() => PostRepository::new());
+            self.post_repository(fallback_repository)
+                .find_all(user_id)?
+        };
+        post_list.retain(|post| post.user_id == user_id);
+
+        Ok(post_list)
+    }
+
+    /// Creates posts of an exported archive as new posts of specific user.
+    /// Original timestamps of the entries are preserved, and new ids are assigned.
+    /// Malformed entries and entries longer than the maximum lengths of `create` are skipped
+    /// instead of aborting the whole import.
+    ///
+    /// Returns `ServiceError::InvalidArgument` if there are more than 1000 entries.
+    /// An import counts as a creation against the post creation limit, so that the archive is
+    /// imported at once, and returns `ServiceError::TooManyRequests` if the limit is reached.
+    pub fn import(
         &mut self,
-        id: u64,
         user_id: u64,
-        title: &Option<String>,
-        content: &Option<String>,
-        date: &Option<NaiveDateTime>,
-    ) -> Result<bool, ServiceError> {
-        if title.is_none() && content.is_none() && date.is_none() {
+        entries: &[Value],
+    ) -> Result<PostImportResultDTO, ServiceError> {
+        if entries.len() > MAX_IMPORTED_POSTS {
             return Err(get_service_error(ServiceError::InvalidArgument));
         }
 
-        if let Some(content) = content {
-            if content.trim().is_empty() {
-                return Err(get_service_error(ServiceError::InvalidArgument));
-            }
-        }
+        let posts_to_import: Vec<ImportedPost> = entries
+            .iter()
+            .filter_map(|entry| self.parse_imported_post(entry))
+            .collect();
+        let skipped_count = (entries.len() - posts_to_import.len()) as u64;
 
-        if let Some(title) = title {
-            if title.trim().is_empty() {
-                return Err(get_service_error(ServiceError::InvalidArgument));
+        let imported_count = if posts_to_import.is_empty() {
+            0
+        } else {
+            let creation_count = {
+                let fallback_repository = some_if_true!(self.post_creation_count_repository.is_none() => PostCreationCountRepository::new(user_id));
+                self.post_creation_count_repository(fallback_repository)
+                    .find()?
+            };
+            if creation_count >= self.post_creation_limit {
+                return Err(get_service_error(ServiceError::TooManyRequests(
+                    self.post_creation_window_seconds as u64,
+                )));
             }
-        }
 
-        let fallback_repository =
-            some_if_true!(self.post_repository.is_none() => PostRepository::new());
-        self.post_repository(fallback_repository)
-            .update(user_id, id, title, content, date)
-    }
-}
+            let fallback_repository =
+                some_if_true!(self.post_repository.is_none() => PostRepository::new());
+            let imported_count = self
+                .post_repository(fallback_repository)
+                .create_all(user_id, &posts_to_import)?;
 
-impl Default for PostService {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+            let post_creation_window_seconds = self.post_creation_window_seconds;
+            self.post_creation_count_repository(None)
+                .increase(post_creation_window_seconds)?;
+            imported_count
+        };
 
-#[cfg(test)]
-use crate::models::post::MockPostRepositoryTrait as PostRepository;
+        Ok(PostImportResultDTO {
+            imported_count,
+            skipped_count,
+        })
+    }
 
-#[cfg(test)]
-mod tests {
-    use chrono::Utc;
-    use mockall::predicate::*;
+    /// Finds all post in the trash written by specific user.
+    pub fn get_trash(&mut self, user_id: u64) -> Result<Vec<PostDTO>, ServiceError> {
+        let post_list = {
+            let fallback_repository =
+                some_if_true!(self.post_repository.is_none() => PostRepository::new());
+            self.post_repository(fallback_repository)
+                .find_all_deleted(user_id)?
+        };
 
-    use super::*;
-    use crate::models::post::MockPostRepositoryTrait;
+        self.get_post_dtos(post_list)
+    }
 
-    impl PostService {
-        pub fn new_with_repository(post_repository: PostRepository) -> Self {
-            Self {
-                post_repository: Some(post_repository),
-            }
+    /// Returns `ServiceError::Unauthorized` if the post has been written by another user.
+    fn verify_owner(
+        post_repository: &PostRepository,
+        id: u64,
+        user_id: u64,
+    ) -> Result<(), ServiceError> {
+        let post = post_repository.find_by_id(id)?;
+        if post.user_id != user_id {
+            return Err(get_service_error(ServiceError::Unauthorized(None)));
         }
+
+        Ok(())
     }
 
-    #[test]
+    /// Moves a post written by specific user to the trash.
+    /// Returns `ServiceError::Unauthorized` if the post has been written by another user.
+    pub fn delete(&mut self, id: u64, user_id: u64) -> Result<bool, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.post_repository.is_none() => PostRepository::new());
+        let post_repository = self.post_repository(fallback_repository);
+
+        Self::verify_owner(post_repository, id, user_id)?;
+        let result = post_repository.delete(user_id, id)?;
+
+        metric_util::count_post_deleted();
+        self.dispatch_webhook_event(user_id, WebhookEvent::PostDeleted, id);
+        Ok(result)
+    }
+
+    /// Moves posts of the ids written by specific user to the trash at once, and returns the result
+    /// of each id in the given order. Duplicated ids are handled once.
+    /// A post written by another user is `Forbidden`, and a post which doesn't exist or is already
+    /// in the trash is `NotFound`. They are not deleted, but the rest of the batch is.
+    /// Returns `ServiceError::InvalidArgument` if no id is given or there are more than 100 ids.
+    pub fn bulk_delete(
+        &mut self,
+        ids: &[u64],
+        user_id: u64,
+    ) -> Result<Vec<BulkDeleteResultDTO>, ServiceError> {
+        let mut unique_ids: Vec<u64> = Vec::with_capacity(ids.len());
+        for id in ids {
+            if !unique_ids.contains(id) {
+                unique_ids.push(*id);
+            }
+        }
+        if unique_ids.is_empty() || unique_ids.len() > MAX_BULK_DELETE_POSTS {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        let fallback_repository =
+            some_if_true!(self.post_repository.is_none() => PostRepository::new());
+        let post_repository = self.post_repository(fallback_repository);
+
+        let post_list = post_repository.find_all_by_ids_of_any_user(&unique_ids)?;
+        let results: Vec<BulkDeleteResultDTO> = unique_ids
+            .iter()
+            .map(|id| {
+                let status = match post_list.iter().find(|post| post.id == *id) {
+                    Some(post) if post.user_id != user_id => BulkDeleteStatus::Forbidden,
+                    Some(post) if post.deleted_at.is_none() => BulkDeleteStatus::Deleted,
+                    _ => BulkDeleteStatus::NotFound,
+                };
+                BulkDeleteResultDTO { id: *id, status }
+            })
+            .collect();
+
+        let deleted_ids: Vec<u64> = results
+            .iter()
+            .filter(|result| result.status == BulkDeleteStatus::Deleted)
+            .map(|result| result.id)
+            .collect();
+        if !deleted_ids.is_empty() {
+            post_repository.bulk_delete(user_id, &deleted_ids)?;
+        }
+
+        for id in deleted_ids {
+            metric_util::count_post_deleted();
+            self.dispatch_webhook_event(user_id, WebhookEvent::PostDeleted, id);
+        }
+        Ok(results)
+    }
+
+    /// Publishes a draft written by specific user, so it is listed with the published posts.
+    /// Returns `ServiceError::InvalidArgument` if the draft has an empty title or content.
+    /// Returns `ServiceError::Unauthorized` if the post has been written by another user.
+    pub fn publish(&mut self, id: u64, user_id: u64) -> Result<bool, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.post_repository.is_none() => PostRepository::new());
+        let post_repository = self.post_repository(fallback_repository);
+
+        let post = post_repository.find_by_id(id)?;
+        if post.user_id != user_id {
+            return Err(get_service_error(ServiceError::Unauthorized(None)));
+        }
+        if post.title.trim().is_empty() || post.content.trim().is_empty() {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+        let result = post_repository.publish(user_id, id)?;
+
+        self.dispatch_webhook_event(user_id, WebhookEvent::PostUpdated, id);
+        Ok(result)
+    }
+
+    /// Pins a post written by specific user to the top of the list.
+    /// Returns `ServiceError::InvalidArgument` if the user has already pinned 5 posts.
+    /// Returns `ServiceError::Unauthorized` if the post has been written by another user.
+    pub fn pin(&mut self, id: u64, user_id: u64) -> Result<bool, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.post_repository.is_none() => PostRepository::new());
+        let post_repository = self.post_repository(fallback_repository);
+
+        let post = post_repository.find_by_id(id)?;
+        if post.user_id != user_id {
+            return Err(get_service_error(ServiceError::Unauthorized(None)));
+        }
+        if post.pinned {
+            return Ok(true);
+        }
+        if post_repository.count_pinned(user_id)? >= MAX_PINNED_POSTS {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        post_repository.pin(user_id, id)
+    }
+
+    /// Unpins a post written by specific user.
+    /// Returns `ServiceError::Unauthorized` if the post has been written by another user.
+    pub fn unpin(&mut self, id: u64, user_id: u64) -> Result<bool, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.post_repository.is_none() => PostRepository::new());
+        let post_repository = self.post_repository(fallback_repository);
+
+        Self::verify_owner(post_repository, id, user_id)?;
+        post_repository.unpin(user_id, id)
+    }
+
+    /// Shares a post written by specific user by a public link, and returns the token of the link.
+    /// The same token is returned while the post is shared.
+    /// Returns `ServiceError::Unauthorized` if the post has been written by another user.
+    pub fn share(&mut self, id: u64, user_id: u64) -> Result<String, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.post_repository.is_none() => PostRepository::new());
+        let post_repository = self.post_repository(fallback_repository);
+
+        let post = post_repository.find_by_id(id)?;
+        if post.user_id != user_id {
+            return Err(get_service_error(ServiceError::Unauthorized(None)));
+        }
+        if post.deleted_at.is_some() {
+            return Err(get_service_error(ServiceError::NotFound(id.to_string())));
+        }
+        if let Some(share_token) = post.share_token {
+            return Ok(share_token);
+        }
+
+        let share_token = token_util::get_random_string(SHARE_TOKEN_LENGTH);
+        post_repository.set_share_token(user_id, id, &Some(share_token.clone()))?;
+        Ok(share_token)
+    }
+
+    /// Revokes the public link of a post written by specific user.
+    /// Returns `ServiceError::Unauthorized` if the post has been written by another user.
+    pub fn unshare(&mut self, id: u64, user_id: u64) -> Result<bool, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.post_repository.is_none() => PostRepository::new());
+        let post_repository = self.post_repository(fallback_repository);
+
+        Self::verify_owner(post_repository, id, user_id)?;
+        post_repository.set_share_token(user_id, id, &None)
+    }
+
+    /// Puts a post written by specific user into the category of the user, or removes it from
+    /// its category if the category is `None`.
+    /// Returns `ServiceError::NotFound` if the category isn't the user's one.
+    /// Returns `ServiceError::Unauthorized` if the post has been written by another user.
+    pub fn set_category(
+        &mut self,
+        id: u64,
+        user_id: u64,
+        category_id: Option<u64>,
+    ) -> Result<bool, ServiceError> {
+        if let Some(category_id) = category_id {
+            let fallback_repository =
+                some_if_true!(self.category_repository.is_none() => CategoryRepository::new());
+            self.category_repository(fallback_repository)
+                .find(category_id, user_id)?;
+        }
+
+        let fallback_repository =
+            some_if_true!(self.post_repository.is_none() => PostRepository::new());
+        let post_repository = self.post_repository(fallback_repository);
+
+        Self::verify_owner(post_repository, id, user_id)?;
+        post_repository.set_category(user_id, id, &category_id)
+    }
+
+    /// Finds a post shared by the public link of the token, which is read by anyone with the link.
+    /// The title and the content remain encrypted, so only a reader given the key of the writer
+    /// can decrypt them.
+    /// Returns `ServiceError::NotFound` if the link is revoked, or the post is in the trash or
+    /// not published yet.
+    pub fn get_shared(
+        &mut self,
+        share_token: &str,
+        now: &NaiveDateTime,
+    ) -> Result<PostDTO, ServiceError> {
+        let post = {
+            let fallback_repository =
+                some_if_true!(self.post_repository.is_none() => PostRepository::new());
+            self.post_repository(fallback_repository)
+                .find_by_share_token(share_token)?
+        };
+
+        let is_published = post.status == PostStatus::Published
+            && post.deleted_at.is_none()
+            && !matches!(post.publish_at, Some(publish_at) if publish_at > *now);
+        if !is_published {
+            return Err(get_service_error(ServiceError::NotFound(
+                share_token.to_string(),
+            )));
+        }
+
+        let mut post_dtos = self.get_post_dtos(vec![post])?;
+        Ok(post_dtos.remove(0))
+    }
+
+    /// Restores a post written by specific user from the trash.
+    /// Returns `ServiceError::Unauthorized` if the post has been written by another user.
+    pub fn restore(&mut self, id: u64, user_id: u64) -> Result<bool, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.post_repository.is_none() => PostRepository::new());
+        let post_repository = self.post_repository(fallback_repository);
+
+        Self::verify_owner(post_repository, id, user_id)?;
+        post_repository.restore(user_id, id)
+    }
+
+    /// Deletes a post written by specific user permanently.
+    /// Returns `ServiceError::Unauthorized` if the post has been written by another user.
+    pub fn purge(&mut self, id: u64, user_id: u64) -> Result<bool, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.post_repository.is_none() => PostRepository::new());
+        let post_repository = self.post_repository(fallback_repository);
+
+        Self::verify_owner(post_repository, id, user_id)?;
+        post_repository.purge(user_id, id)
+    }
+
+    /// Updates the given fields of a post written by specific user, leaving the others untouched,
+    /// and increments the version of the post. Returns `ServiceError::InvalidArgument` if no
+    /// field is given.
+    /// The tags of the post are replaced with the normalized tags, if they are given.
+    /// The mood of the post is replaced, if it is given.
+    ///
+    /// The version is the one of the post the client has seen. Returns `ServiceError::Conflict`
+    /// if the post has been updated since then, so the client can merge the changes.
+    /// The content of a draft may be emptied, but the one of a published post may not.
+    /// The title and the content must not be longer than the maximum lengths as in `create`.
+    /// Returns `ServiceError::Unauthorized` if the post has been written by another user.
+    pub fn update(
+        &mut self,
+        id: u64,
+        user_id: u64,
+        version: u64,
+        post_update: &PostUpdateDTO,
+    ) -> Result<bool, ServiceError> {
+        let PostUpdateDTO {
+            title,
+            content,
+            date,
+            tags,
+            mood,
+        } = post_update;
+
+        if title.is_none()
+            && content.is_none()
+            && date.is_none()
+            && tags.is_none()
+            && mood.is_none()
+        {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+        let mood = Self::parse_mood(mood)?;
+
+        if let Some(title) = title {
+            if title.trim().is_empty() {
+                return Err(get_service_error(ServiceError::InvalidArgument));
+            }
+        }
+        self.validate_length(title.as_deref(), content.as_deref())?;
+
+        let tags = match tags {
+            Some(tags) => Some(Self::normalize_tags(tags)?),
+            None => None,
+        };
+
+        let result = {
+            let fallback_repository =
+                some_if_true!(self.post_repository.is_none() => PostRepository::new());
+            let post_repository = self.post_repository(fallback_repository);
+
+            let post = post_repository.find_by_id(id)?;
+            if post.user_id != user_id {
+                return Err(get_service_error(ServiceError::Unauthorized(None)));
+            }
+            if post.version != version {
+                return Err(get_service_error(ServiceError::Conflict(id.to_string())));
+            }
+            if let Some(content) = content {
+                if post.status == PostStatus::Published && content.trim().is_empty() {
+                    return Err(get_service_error(ServiceError::InvalidArgument));
+                }
+            }
+
+            post_repository.update(user_id, id, version, title, content, date, &mood)?
+        };
+
+        if let Some(tags) = tags {
+            let fallback_repository =
+                some_if_true!(self.post_tag_repository.is_none() => PostTagRepository::new());
+            self.post_tag_repository(fallback_repository)
+                .replace(id, &tags)?;
+        }
+
+        self.dispatch_webhook_event(user_id, WebhookEvent::PostUpdated, id);
+        Ok(result)
+    }
+}
+
+impl Default for PostService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+use crate::models::category::MockCategoryRepositoryTrait as CategoryRepository;
+#[cfg(test)]
+use crate::models::post::{
+    MockPostCreationCountRepositoryTrait as PostCreationCountRepository,
+    MockPostRepositoryTrait as PostRepository,
+};
+#[cfg(test)]
+use crate::models::post_tag::MockPostTagRepositoryTrait as PostTagRepository;
+#[cfg(test)]
+use crate::models::user::MockUserRepositoryTrait as UserRepository;
+#[cfg(test)]
+use crate::utils::webhook_util::{
+    MockWebhookDispatcherTrait as WebhookDispatcher, WebhookDispatcherTrait,
+};
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use mockall::predicate::*;
+    use serde_json::json;
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use super::*;
+    use crate::models::category::MockCategoryRepositoryTrait;
+    use crate::models::post::MockPostRepositoryTrait;
+    use crate::models::post_tag::MockPostTagRepositoryTrait;
+    use crate::models::user::MockUserRepositoryTrait;
+    use crate::utils::test_util;
+    use crate::utils::webhook_util::MockWebhookDispatcherTrait;
+
+    impl PostService {
+        pub fn new_with_repository(
+            post_repository: PostRepository,
+            post_tag_repository: PostTagRepository,
+            user_repository: UserRepository,
+        ) -> Self {
+            let mut webhook_dispatcher = MockWebhookDispatcherTrait::default();
+            webhook_dispatcher
+                .expect_dispatch()
+                .returning(|_, _, _| true);
+            let mut post_creation_count_repository = PostCreationCountRepository::default();
+            post_creation_count_repository
+                .expect_find()
+                .returning(|| Ok(0));
+            post_creation_count_repository
+                .expect_increase()
+                .returning(|_| Ok(1));
+
+            Self {
+                post_repository: Some(post_repository),
+                post_tag_repository: Some(post_tag_repository),
+                user_repository: Some(user_repository),
+                category_repository: Some(CategoryRepository::default()),
+                webhook_dispatcher: Some(webhook_dispatcher),
+                post_creation_count_repository: Some(post_creation_count_repository),
+                max_title_length: DEFAULT_MAX_POST_TITLE_LENGTH,
+                max_content_length: DEFAULT_MAX_POST_CONTENT_LENGTH,
+                post_creation_limit: DEFAULT_POST_CREATION_LIMIT,
+                post_creation_window_seconds: DEFAULT_POST_CREATION_WINDOW_SECONDS,
+            }
+        }
+
+        pub fn with_category_repository(mut self, category_repository: CategoryRepository) -> Self {
+            self.category_repository = Some(category_repository);
+            self
+        }
+
+        pub fn with_webhook_dispatcher(mut self, webhook_dispatcher: WebhookDispatcher) -> Self {
+            self.webhook_dispatcher = Some(webhook_dispatcher);
+            self
+        }
+
+        pub fn with_post_creation_count_repository(
+            mut self,
+            post_creation_count_repository: PostCreationCountRepository,
+        ) -> Self {
+            self.post_creation_count_repository = Some(post_creation_count_repository);
+            self
+        }
+    }
+
+    fn is_filter(
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        tag: Option<String>,
+    ) -> impl Fn(&PostFilter) -> bool {
+        move |filter| {
+            filter.from == from
+                && filter.to == to
+                && filter.tags == tag.iter().cloned().collect::<Vec<String>>()
+        }
+    }
+
+    fn get_post_tag_repository_without_tags() -> MockPostTagRepositoryTrait {
+        let mut mocked_post_tag_repository = MockPostTagRepositoryTrait::new();
+        mocked_post_tag_repository
+            .expect_find_all_by_post_ids()
+            .returning(|_| Ok(vec![]));
+        mocked_post_tag_repository
+    }
+
+    #[test]
     fn test_get_list() {
         let mut mocked_post_repository = MockPostRepositoryTrait::new();
 
@@ -187,10 +1314,18 @@ mod tests {
         let user_id = 5;
 
         mocked_post_repository
-            .expect_find_all_in_desc_date_order()
-            .with(eq(user_id))
+            .expect_find_all_pinned()
+            .returning(|_, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .with(
+                eq(user_id),
+                function(is_filter(None, None, None)),
+                eq(DEFAULT_POST_PAGE_LIMIT),
+                eq(0),
+            )
             .times(1)
-            .returning(move |passed_user_id| {
+            .returning(move |passed_user_id, _, _, _| {
                 let now = Utc::now().naive_utc();
                 let post = Post {
                     id,
@@ -200,14 +1335,2691 @@ mod tests {
                     date: now.clone(),
                     created_at: now.clone(),
                     updated_at: None,
+                    deleted_at: None,
+                    version: 1,
+                    publish_at: None,
+                    status: PostStatus::Published,
+                    pinned: false,
+                    pinned_at: None,
+                    share_token: None,
+                    mood: None,
+                    category_id: None,
                 };
 
                 Ok(vec![post])
             });
+        mocked_post_repository
+            .expect_count()
+            .with(eq(user_id), function(is_filter(None, None, None)))
+            .times(1)
+            .returning(|_, _| Ok(1));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+        let post_page: PostPageDTO = post_service
+            .get_list(user_id, None, None, &[], None, None, None, None, None)
+            .unwrap();
+
+        assert_eq!(post_page.posts.first().unwrap().id, id);
+        assert_eq!(post_page.total_count, 1);
+    }
+
+    #[test]
+    fn test_get_list_with_out_of_range_limit() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+
+        let user_id = 5;
+
+        mocked_post_repository
+            .expect_find_all_pinned()
+            .returning(|_, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .with(
+                eq(user_id),
+                function(is_filter(None, None, None)),
+                eq(MAX_POST_PAGE_LIMIT),
+                eq(0),
+            )
+            .times(1)
+            .returning(|_, _, _, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_find_all_pinned()
+            .returning(|_, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .with(
+                eq(user_id),
+                function(is_filter(None, None, None)),
+                eq(1),
+                eq(0),
+            )
+            .times(1)
+            .returning(|_, _, _, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_count()
+            .with(eq(user_id), function(is_filter(None, None, None)))
+            .times(2)
+            .returning(|_, _| Ok(0));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+
+        assert!(post_service
+            .get_list(user_id, None, None, &[], None, None, Some(1000), None, None)
+            .is_ok());
+        assert!(post_service
+            .get_list(user_id, None, None, &[], None, None, Some(0), None, None)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_get_list_with_offset_beyond_end() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+
+        let user_id = 5;
+
+        mocked_post_repository
+            .expect_find_all_pinned()
+            .returning(|_, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .with(
+                eq(user_id),
+                function(is_filter(None, None, None)),
+                eq(20),
+                eq(40),
+            )
+            .times(1)
+            .returning(|_, _, _, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_count()
+            .with(eq(user_id), function(is_filter(None, None, None)))
+            .times(1)
+            .returning(|_, _| Ok(25));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+        let post_page = post_service
+            .get_list(
+                user_id,
+                None,
+                None,
+                &[],
+                None,
+                None,
+                Some(20),
+                Some(40),
+                None,
+            )
+            .unwrap();
+
+        assert!(post_page.posts.is_empty());
+        assert_eq!(post_page.total_count, 25);
+    }
+
+    #[test]
+    fn test_get_list_with_date_range() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+
+        let user_id = 5;
+        let from = NaiveDateTime::from_str("2020-06-01T00:00:00").unwrap();
+        let to = NaiveDateTime::from_str("2020-06-30T23:59:59").unwrap();
+
+        mocked_post_repository
+            .expect_find_all_pinned()
+            .returning(|_, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .with(
+                eq(user_id),
+                function(is_filter(Some(from), Some(to), None)),
+                eq(DEFAULT_POST_PAGE_LIMIT),
+                eq(0),
+            )
+            .times(1)
+            .returning(move |passed_user_id, _, _, _| {
+                Ok(vec![Post {
+                    id: 3,
+                    user_id: passed_user_id,
+                    title: String::from("Title"),
+                    content: String::from("Content"),
+                    date: to,
+                    created_at: from,
+                    updated_at: None,
+                    deleted_at: None,
+                    version: 1,
+                    publish_at: None,
+                    status: PostStatus::Published,
+                    pinned: false,
+                    pinned_at: None,
+                    share_token: None,
+                    mood: None,
+                    category_id: None,
+                }])
+            });
+        mocked_post_repository
+            .expect_count()
+            .with(eq(user_id), function(is_filter(Some(from), Some(to), None)))
+            .times(1)
+            .returning(|_, _| Ok(1));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            get_user_repository_with_timezone(None),
+        );
+        let post_page = post_service
+            .get_list(
+                user_id,
+                Some(from),
+                Some(to),
+                &[],
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(post_page.posts.first().unwrap().date, to);
+        assert_eq!(post_page.total_count, 1);
+    }
+
+    #[test]
+    fn test_get_list_with_open_ended_date_range() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+
+        let user_id = 5;
+        let from = NaiveDateTime::from_str("2020-06-01T00:00:00").unwrap();
+
+        mocked_post_repository
+            .expect_find_all_pinned()
+            .returning(|_, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .with(
+                eq(user_id),
+                function(is_filter(Some(from), None, None)),
+                eq(DEFAULT_POST_PAGE_LIMIT),
+                eq(0),
+            )
+            .times(1)
+            .returning(|_, _, _, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_count()
+            .with(eq(user_id), function(is_filter(Some(from), None, None)))
+            .times(1)
+            .returning(|_, _| Ok(0));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            get_user_repository_with_timezone(None),
+        );
+
+        assert!(post_service
+            .get_list(user_id, Some(from), None, &[], None, None, None, None, None)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_get_list_with_invalid_date_range() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_pinned()
+            .returning(|_, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .times(0);
+        mocked_post_repository.expect_count().times(0);
+
+        let from = NaiveDateTime::from_str("2020-06-30T00:00:00").unwrap();
+        let to = NaiveDateTime::from_str("2020-06-01T00:00:00").unwrap();
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result =
+            post_service.get_list(5, Some(from), Some(to), &[], None, None, None, None, None);
+
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_count_without_posts() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_count()
+            .with(eq(5), function(is_filter(None, None, None)))
+            .times(1)
+            .returning(|_, _| Ok(0));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let count = post_service.count(5, None, None, None).unwrap();
+
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_count_with_date_range() {
+        let from = NaiveDateTime::from_str("2020-06-01T00:00:00").unwrap();
+        let to = NaiveDateTime::from_str("2020-06-30T00:00:00").unwrap();
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_count()
+            .with(
+                eq(5),
+                function(is_filter(
+                    Some(from),
+                    Some(to),
+                    Some(String::from("travel")),
+                )),
+            )
+            .times(1)
+            .returning(|_, _| Ok(3));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            get_user_repository_with_timezone(None),
+        );
+        let count = post_service
+            .count(5, Some(from), Some(to), Some(String::from(" Travel ")))
+            .unwrap();
+
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_count_with_date_range_in_timezone_of_user() {
+        // A post written at 23:30 on June 2 in Seoul is dated 14:30 on June 2 in UTC.
+        let from = NaiveDateTime::from_str("2020-06-02T00:00:00").unwrap();
+        let to = NaiveDateTime::from_str("2020-06-02T23:59:59").unwrap();
+        let date = NaiveDateTime::from_str("2020-06-02T14:30:00").unwrap();
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_count()
+            .withf(move |user_id, filter| {
+                *user_id == 5
+                    && filter.from == Some(NaiveDateTime::from_str("2020-06-01T15:00:00").unwrap())
+                    && filter.to == Some(NaiveDateTime::from_str("2020-06-02T14:59:59").unwrap())
+                    && filter.from.unwrap() <= date
+                    && date <= filter.to.unwrap()
+            })
+            .times(1)
+            .returning(|_, _| Ok(1));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            get_user_repository_with_timezone(Some("Asia/Seoul")),
+        );
+        let count = post_service.count(5, Some(from), Some(to), None).unwrap();
+
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_local_time_to_utc() {
+        let local = NaiveDateTime::from_str("2020-03-08T02:30:00").unwrap();
+        assert_eq!(
+            PostService::to_utc("America/New_York".parse().unwrap(), local),
+            NaiveDateTime::from_str("2020-03-08T07:30:00").unwrap()
+        );
+
+        let local = NaiveDateTime::from_str("2020-06-02T23:30:00").unwrap();
+        assert_eq!(PostService::to_utc(Tz::UTC, local), local);
+    }
+
+    #[test]
+    fn test_count_with_invalid_date_range() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository.expect_count().times(0);
+
+        let from = NaiveDateTime::from_str("2020-06-30T00:00:00").unwrap();
+        let to = NaiveDateTime::from_str("2020-06-01T00:00:00").unwrap();
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result = post_service.count(5, Some(from), Some(to), None);
+
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_get() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+
+        let id = 3;
+        let user_id = 5;
+
+        mocked_post_repository
+            .expect_find()
+            .with(eq(user_id), eq(id))
+            .times(1)
+            .returning(|passed_user_id, passed_id| {
+                let now = Utc::now().naive_utc();
+                Ok(Post {
+                    id: passed_id,
+                    user_id: passed_user_id,
+                    title: String::from("Title"),
+                    content: String::from("Content"),
+                    date: now,
+                    created_at: now,
+                    updated_at: None,
+                    deleted_at: None,
+                    version: 1,
+                    publish_at: None,
+                    status: PostStatus::Published,
+                    pinned: false,
+                    pinned_at: None,
+                    share_token: None,
+                    mood: None,
+                    category_id: None,
+                })
+            });
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+        let post = post_service.get(user_id, id).unwrap();
+
+        assert_eq!(post.id, id);
+        assert_eq!(post.title, "Title");
+    }
+
+    #[test]
+    fn test_get_not_found() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+
+        mocked_post_repository
+            .expect_find()
+            .times(1)
+            .returning(|_, id| Err(ServiceError::NotFound(id.to_string())));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result = post_service.get(5, 3);
+
+        assert!(matches!(result, Err(ServiceError::NotFound(_))));
+    }
+
+    fn get_post_repository_for_delete(id: u64, owner_id: u64) -> MockPostRepositoryTrait {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_by_id()
+            .with(eq(id))
+            .times(1)
+            .returning(move |passed_id| {
+                let now = Utc::now().naive_utc();
+                Ok(Post {
+                    id: passed_id,
+                    user_id: owner_id,
+                    title: String::from("Title"),
+                    content: String::from("Content"),
+                    date: now,
+                    created_at: now,
+                    updated_at: None,
+                    deleted_at: None,
+                    version: 1,
+                    publish_at: None,
+                    status: PostStatus::Published,
+                    pinned: false,
+                    pinned_at: None,
+                    share_token: None,
+                    mood: None,
+                    category_id: None,
+                })
+            });
+        mocked_post_repository
+    }
+
+    #[test]
+    fn test_delete_by_owner() {
+        let id = 3;
+        let user_id = 5;
+
+        let mut mocked_post_repository = get_post_repository_for_delete(id, user_id);
+        mocked_post_repository
+            .expect_delete()
+            .with(eq(user_id), eq(id))
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+
+        assert!(post_service.delete(id, user_id).unwrap());
+    }
+
+    #[test]
+    fn test_delete_by_another_user() {
+        let id = 3;
+
+        let mut mocked_post_repository = get_post_repository_for_delete(id, 5);
+        mocked_post_repository.expect_delete().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result = post_service.delete(id, 7);
+
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[test]
+    fn test_bulk_delete_mixed_batch() {
+        let user_id = 5;
+        let another_user_id = 7;
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_by_ids_of_any_user()
+            .withf(|post_ids| post_ids == [1, 2, 3, 4])
+            .times(1)
+            .returning(move |_| {
+                Ok(vec![
+                    get_post_with_date(1, user_id, "2020-06-01T00:00:00"),
+                    get_post_with_date(2, another_user_id, "2020-06-02T00:00:00"),
+                    Post {
+                        deleted_at: Some(Utc::now().naive_utc()),
+                        ..get_post_with_date(4, user_id, "2020-06-04T00:00:00")
+                    },
+                ])
+            });
+        mocked_post_repository
+            .expect_bulk_delete()
+            .withf(move |passed_user_id, post_ids| *passed_user_id == user_id && post_ids == [1])
+            .times(1)
+            .returning(|_, post_ids| Ok(post_ids.len() as u64));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let results = post_service.bulk_delete(&[1, 2, 3, 4, 1], user_id).unwrap();
+        let statuses: Vec<(u64, BulkDeleteStatus)> = results
+            .iter()
+            .map(|result| (result.id, result.status))
+            .collect();
+
+        assert_eq!(
+            statuses,
+            vec![
+                (1, BulkDeleteStatus::Deleted),
+                (2, BulkDeleteStatus::Forbidden),
+                (3, BulkDeleteStatus::NotFound),
+                (4, BulkDeleteStatus::NotFound),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bulk_delete_over_batch_size() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_by_ids_of_any_user()
+            .times(0);
+        mocked_post_repository.expect_bulk_delete().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let ids: Vec<u64> = (1..=MAX_BULK_DELETE_POSTS as u64 + 1).collect();
+
+        let result = post_service.bulk_delete(&ids, 5);
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+
+        let result = post_service.bulk_delete(&[], 5);
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_delete_list_and_restore() {
+        let user_id = 5;
+        let id = 3;
+        let now = Utc::now().naive_utc();
+        let posts = Arc::new(Mutex::new(vec![Post {
+            id,
+            user_id,
+            title: String::from("Title"),
+            content: String::from("Content"),
+            date: now,
+            created_at: now,
+            updated_at: None,
+            deleted_at: None,
+            version: 1,
+            publish_at: None,
+            status: PostStatus::Published,
+            pinned: false,
+            pinned_at: None,
+            share_token: None,
+            mood: None,
+            category_id: None,
+        }]));
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        let find_by_id_posts = posts.clone();
+        mocked_post_repository
+            .expect_find_by_id()
+            .returning(move |passed_id| {
+                let posts = find_by_id_posts.lock().unwrap();
+                let post = posts.iter().find(|post| post.id == passed_id).unwrap();
+                Ok(Post {
+                    title: post.title.clone(),
+                    content: post.content.clone(),
+                    share_token: post.share_token.clone(),
+                    ..*post
+                })
+            });
+        let find_page_posts = posts.clone();
+        mocked_post_repository
+            .expect_find_all_pinned()
+            .returning(|_, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .returning(move |_, _, _, _| {
+                let posts = find_page_posts.lock().unwrap();
+                Ok(posts
+                    .iter()
+                    .filter(|post| post.deleted_at.is_none())
+                    .map(|post| Post {
+                        title: post.title.clone(),
+                        content: post.content.clone(),
+                        share_token: post.share_token.clone(),
+                        ..*post
+                    })
+                    .collect())
+            });
+        let count_posts = posts.clone();
+        mocked_post_repository
+            .expect_count()
+            .returning(move |_, _| {
+                let posts = count_posts.lock().unwrap();
+                Ok(posts
+                    .iter()
+                    .filter(|post| post.deleted_at.is_none())
+                    .count() as u64)
+            });
+        let delete_posts = posts.clone();
+        mocked_post_repository
+            .expect_delete()
+            .with(eq(user_id), eq(id))
+            .times(1)
+            .returning(move |_, passed_id| {
+                let mut posts = delete_posts.lock().unwrap();
+                let post = posts.iter_mut().find(|post| post.id == passed_id).unwrap();
+                post.deleted_at = Some(Utc::now().naive_utc());
+                Ok(true)
+            });
+        let restore_posts = posts;
+        mocked_post_repository
+            .expect_restore()
+            .with(eq(user_id), eq(id))
+            .times(1)
+            .returning(move |_, passed_id| {
+                let mut posts = restore_posts.lock().unwrap();
+                let post = posts.iter_mut().find(|post| post.id == passed_id).unwrap();
+                post.deleted_at = None;
+                Ok(true)
+            });
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+
+        assert!(post_service.delete(id, user_id).unwrap());
+        let post_page = post_service
+            .get_list(user_id, None, None, &[], None, None, None, None, None)
+            .unwrap();
+        assert!(post_page.posts.is_empty());
+        assert_eq!(post_page.total_count, 0);
+
+        assert!(post_service.restore(id, user_id).unwrap());
+        let post_page = post_service
+            .get_list(user_id, None, None, &[], None, None, None, None, None)
+            .unwrap();
+        assert_eq!(post_page.posts.first().unwrap().id, id);
+        assert_eq!(post_page.total_count, 1);
+    }
+
+    #[test]
+    fn test_create_list_and_publish_draft() {
+        let user_id = 5;
+        let id = 3;
+        let now = Utc::now().naive_utc();
+        let posts: Arc<Mutex<Vec<Post>>> = Arc::new(Mutex::new(vec![]));
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        let create_posts = posts.clone();
+        mocked_post_repository
+            .expect_create()
+            .withf(|_, _, content, _, _, status, _| {
+                content.is_empty() && *status == PostStatus::Draft
+            })
+            .times(1)
+            .returning(move |user_id, title, content, date, _, status, _| {
+                let mut posts = create_posts.lock().unwrap();
+                posts.push(Post {
+                    id,
+                    user_id,
+                    title: title.to_string(),
+                    content: content.to_string(),
+                    date: *date,
+                    created_at: now,
+                    updated_at: None,
+                    deleted_at: None,
+                    version: 1,
+                    publish_at: None,
+                    status,
+                    pinned: false,
+                    pinned_at: None,
+                    share_token: None,
+                    mood: None,
+                    category_id: None,
+                });
+                Ok(true)
+            });
+        let copy_post = |post: &Post| Post {
+            title: post.title.clone(),
+            content: post.content.clone(),
+            share_token: post.share_token.clone(),
+            ..*post
+        };
+        let find_all_posts = posts.clone();
+        mocked_post_repository
+            .expect_find_all()
+            .returning(move |_| {
+                Ok(find_all_posts
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(copy_post)
+                    .collect())
+            });
+        let find_by_id_posts = posts.clone();
+        mocked_post_repository
+            .expect_find_by_id()
+            .returning(move |passed_id| {
+                let posts = find_by_id_posts.lock().unwrap();
+                Ok(copy_post(
+                    posts.iter().find(|post| post.id == passed_id).unwrap(),
+                ))
+            });
+        let find_page_posts = posts.clone();
+        mocked_post_repository
+            .expect_find_all_pinned()
+            .returning(|_, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .returning(move |_, _, _, _| {
+                let posts = find_page_posts.lock().unwrap();
+                Ok(posts
+                    .iter()
+                    .filter(|post| post.status == PostStatus::Published)
+                    .map(copy_post)
+                    .collect())
+            });
+        let count_posts = posts.clone();
+        mocked_post_repository
+            .expect_count()
+            .returning(move |_, _| {
+                let posts = count_posts.lock().unwrap();
+                Ok(posts
+                    .iter()
+                    .filter(|post| post.status == PostStatus::Published)
+                    .count() as u64)
+            });
+        let find_drafts_posts = posts.clone();
+        mocked_post_repository
+            .expect_find_all_drafts()
+            .returning(move |_| {
+                let posts = find_drafts_posts.lock().unwrap();
+                Ok(posts
+                    .iter()
+                    .filter(|post| post.status == PostStatus::Draft)
+                    .map(copy_post)
+                    .collect())
+            });
+        let update_posts = posts.clone();
+        mocked_post_repository.expect_update().times(1).returning(
+            move |_, passed_id, _, _, content, _, _| {
+                let mut posts = update_posts.lock().unwrap();
+                let post = posts.iter_mut().find(|post| post.id == passed_id).unwrap();
+                post.content = content.clone().unwrap();
+                Ok(true)
+            },
+        );
+        let publish_posts = posts;
+        mocked_post_repository
+            .expect_publish()
+            .with(eq(user_id), eq(id))
+            .times(1)
+            .returning(move |_, passed_id| {
+                let mut posts = publish_posts.lock().unwrap();
+                let post = posts.iter_mut().find(|post| post.id == passed_id).unwrap();
+                post.status = PostStatus::Published;
+                Ok(true)
+            });
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+
+        let created_id = post_service
+            .create(
+                user_id,
+                "Title",
+                "",
+                &now,
+                &[],
+                &None,
+                PostStatus::Draft,
+                &None,
+            )
+            .unwrap();
+        assert_eq!(created_id, id);
+
+        let post_page = post_service
+            .get_list(user_id, None, None, &[], None, None, None, None, None)
+            .unwrap();
+        assert!(post_page.posts.is_empty());
+        assert_eq!(post_page.total_count, 0);
+        let drafts = post_service.get_drafts(user_id).unwrap();
+        assert_eq!(drafts.first().unwrap().id, id);
+        assert_eq!(drafts.first().unwrap().status, PostStatus::Draft);
+
+        let result = post_service.publish(id, user_id);
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+
+        let post_update = PostUpdateDTO {
+            title: None,
+            content: Some(String::from("Content")),
+            date: None,
+            tags: None,
+            mood: None,
+        };
+        assert!(post_service.update(id, user_id, 1, &post_update).unwrap());
+        assert!(post_service.publish(id, user_id).unwrap());
+
+        let post_page = post_service
+            .get_list(user_id, None, None, &[], None, None, None, None, None)
+            .unwrap();
+        assert_eq!(post_page.posts.first().unwrap().id, id);
+        assert_eq!(post_page.total_count, 1);
+        assert!(post_service.get_drafts(user_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_create_published_post_with_empty_content() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository.expect_create().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result = post_service.create(
+            5,
+            "Title",
+            " ",
+            &Utc::now().naive_utc(),
+            &[],
+            &None,
+            PostStatus::Published,
+            &None,
+        );
+
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_create_with_max_length() {
+        let user_id = 5;
+        let max_title = "a".repeat(DEFAULT_MAX_POST_TITLE_LENGTH);
+        let max_content = "a".repeat(DEFAULT_MAX_POST_CONTENT_LENGTH);
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_create()
+            .withf(|_, title, content, _, _, _, _| {
+                title.len() == DEFAULT_MAX_POST_TITLE_LENGTH
+                    && content.len() == DEFAULT_MAX_POST_CONTENT_LENGTH
+            })
+            .times(1)
+            .returning(|_, _, _, _, _, _, _| Ok(true));
+        mocked_post_repository
+            .expect_find_all()
+            .with(eq(user_id))
+            .times(1)
+            .returning(|passed_user_id| {
+                Ok(vec![get_post_with_date(
+                    3,
+                    passed_user_id,
+                    "2020-06-01T00:00:00",
+                )])
+            });
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let date = Utc::now().naive_utc();
+
+        let result = post_service.create(
+            user_id,
+            &max_title,
+            &max_content,
+            &date,
+            &[],
+            &None,
+            PostStatus::Published,
+            &None,
+        );
+        assert_eq!(result.unwrap(), 3);
+
+        let result = post_service.create(
+            user_id,
+            &format!("{}a", max_title),
+            &max_content,
+            &date,
+            &[],
+            &None,
+            PostStatus::Published,
+            &None,
+        );
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+
+        let result = post_service.create(
+            user_id,
+            &max_title,
+            &format!("{}a", max_content),
+            &date,
+            &[],
+            &None,
+            PostStatus::Draft,
+            &None,
+        );
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_create_beyond_creation_limit() {
+        let user_id = 5;
+        let creation_count = Arc::new(Mutex::new(0));
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_create()
+            .times(DEFAULT_POST_CREATION_LIMIT as usize + 1)
+            .returning(|_, _, _, _, _, _, _| Ok(true));
+        mocked_post_repository
+            .expect_find_all()
+            .returning(|passed_user_id| {
+                Ok(vec![get_post_with_date(
+                    3,
+                    passed_user_id,
+                    "2020-06-01T00:00:00",
+                )])
+            });
+
+        let mut mocked_post_creation_count_repository = PostCreationCountRepository::default();
+        let found_creation_count = creation_count.clone();
+        mocked_post_creation_count_repository
+            .expect_find()
+            .returning(move || Ok(*found_creation_count.lock().unwrap()));
+        let increased_creation_count = creation_count.clone();
+        mocked_post_creation_count_repository
+            .expect_increase()
+            .with(eq(DEFAULT_POST_CREATION_WINDOW_SECONDS))
+            .returning(move |_| {
+                let mut creation_count = increased_creation_count.lock().unwrap();
+                *creation_count += 1;
+                Ok(*creation_count)
+            });
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        )
+        .with_post_creation_count_repository(mocked_post_creation_count_repository);
+        let date = Utc::now().naive_utc();
+        let mut create = || {
+            post_service.create(
+                user_id,
+                "Title",
+                "Content",
+                &date,
+                &[],
+                &None,
+                PostStatus::Published,
+                &None,
+            )
+        };
+
+        for _ in 0..DEFAULT_POST_CREATION_LIMIT {
+            assert!(create().is_ok());
+        }
+        let result = create();
+        assert!(matches!(result, Err(ServiceError::TooManyRequests(3600))));
+
+        *creation_count.lock().unwrap() = 0;
+        assert!(create().is_ok());
+    }
+
+    #[test]
+    fn test_update_with_too_long_content() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository.expect_update().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let post_update = PostUpdateDTO {
+            title: None,
+            content: Some("a".repeat(DEFAULT_MAX_POST_CONTENT_LENGTH + 1)),
+            date: None,
+            tags: None,
+            mood: None,
+        };
+        let result = post_service.update(3, 5, 1, &post_update);
+
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_purge_by_another_user() {
+        let id = 3;
+
+        let mut mocked_post_repository = get_post_repository_for_delete(id, 5);
+        mocked_post_repository.expect_purge().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result = post_service.purge(id, 7);
+
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[test]
+    fn test_normalize_tags() {
+        let tags = vec![
+            String::from(" Travel"),
+            String::from("travel "),
+            String::from("  "),
+            String::from("FOOD"),
+        ];
+
+        assert_eq!(
+            PostService::normalize_tags(&tags).unwrap(),
+            vec![String::from("travel"), String::from("food")]
+        );
+
+        let too_many_tags: Vec<String> = (0..=MAX_POST_TAGS).map(|i| i.to_string()).collect();
+        assert!(matches!(
+            PostService::normalize_tags(&too_many_tags),
+            Err(ServiceError::InvalidArgument)
+        ));
+
+        let duplicated_tags: Vec<String> = (0..=MAX_POST_TAGS).map(|_| String::from("a")).collect();
+        assert_eq!(
+            PostService::normalize_tags(&duplicated_tags).unwrap(),
+            vec![String::from("a")]
+        );
+    }
+
+    #[test]
+    fn test_create_with_tags() {
+        let id = 3;
+        let user_id = 5;
+        let date = Utc::now().naive_utc();
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_create()
+            .times(1)
+            .returning(|_, _, _, _, _, _, _| Ok(true));
+        mocked_post_repository
+            .expect_find_all()
+            .with(eq(user_id))
+            .times(1)
+            .returning(move |passed_user_id| {
+                Ok(vec![Post {
+                    id,
+                    user_id: passed_user_id,
+                    title: String::from("Title"),
+                    content: String::from("Content"),
+                    date,
+                    created_at: date,
+                    updated_at: None,
+                    deleted_at: None,
+                    version: 1,
+                    publish_at: None,
+                    status: PostStatus::Published,
+                    pinned: false,
+                    pinned_at: None,
+                    share_token: None,
+                    mood: None,
+                    category_id: None,
+                }])
+            });
+
+        let mut mocked_post_tag_repository = MockPostTagRepositoryTrait::new();
+        mocked_post_tag_repository
+            .expect_replace()
+            .withf(move |post_id, tags| {
+                *post_id == id && tags == [String::from("travel"), String::from("food")]
+            })
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            mocked_post_tag_repository,
+            MockUserRepositoryTrait::new(),
+        );
+        let tags = vec![
+            String::from("Travel"),
+            String::from(" food "),
+            String::from("travel"),
+        ];
+
+        assert_eq!(
+            post_service
+                .create(
+                    user_id,
+                    "Title",
+                    "Content",
+                    &date,
+                    &tags,
+                    &None,
+                    PostStatus::Published,
+                    &None,
+                )
+                .unwrap(),
+            id
+        );
+    }
+
+    #[test]
+    fn test_create_dispatches_webhook_event() {
+        let id = 3;
+        let user_id = 5;
+        let date = Utc::now().naive_utc();
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_create()
+            .times(1)
+            .returning(|_, _, _, _, _, _, _| Ok(true));
+        mocked_post_repository
+            .expect_find_all()
+            .with(eq(user_id))
+            .times(1)
+            .returning(move |passed_user_id| {
+                Ok(vec![Post {
+                    id,
+                    user_id: passed_user_id,
+                    title: String::from("Title"),
+                    content: String::from("Content"),
+                    date,
+                    created_at: date,
+                    updated_at: None,
+                    deleted_at: None,
+                    version: 1,
+                    publish_at: None,
+                    status: PostStatus::Published,
+                    pinned: false,
+                    pinned_at: None,
+                    share_token: None,
+                    mood: None,
+                    category_id: None,
+                }])
+            });
+
+        let mut mocked_webhook_dispatcher = MockWebhookDispatcherTrait::default();
+        mocked_webhook_dispatcher
+            .expect_dispatch()
+            .with(eq(user_id), eq(WebhookEvent::PostCreated), eq(id))
+            .times(1)
+            .returning(|_, _, _| true);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        )
+        .with_webhook_dispatcher(mocked_webhook_dispatcher);
+
+        assert_eq!(
+            post_service
+                .create(
+                    user_id,
+                    "Title",
+                    "Content",
+                    &date,
+                    &[],
+                    &None,
+                    PostStatus::Published,
+                    &None,
+                )
+                .unwrap(),
+            id
+        );
+    }
+
+    #[test]
+    fn test_create_with_too_many_tags() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository.expect_create().times(0);
+
+        let mut mocked_webhook_dispatcher = MockWebhookDispatcherTrait::default();
+        mocked_webhook_dispatcher.expect_dispatch().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        )
+        .with_webhook_dispatcher(mocked_webhook_dispatcher);
+        let tags: Vec<String> = (0..=MAX_POST_TAGS).map(|i| i.to_string()).collect();
+        let result = post_service.create(
+            5,
+            "Title",
+            "Content",
+            &Utc::now().naive_utc(),
+            &tags,
+            &None,
+            PostStatus::Published,
+            &None,
+        );
+
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_create_with_unknown_mood() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository.expect_create().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result = post_service.create(
+            5,
+            "Title",
+            "Content",
+            &Utc::now().naive_utc(),
+            &[],
+            &None,
+            PostStatus::Published,
+            &Some(String::from("grumpy")),
+        );
+
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_get_mood_stats() {
+        let from = NaiveDateTime::from_str("2020-06-01T00:00:00").unwrap();
+        let to = NaiveDateTime::from_str("2020-06-30T00:00:00").unwrap();
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_moods()
+            .with(eq(5), function(is_filter(Some(from), Some(to), None)))
+            .times(1)
+            .returning(|_, _| {
+                Ok(vec![
+                    Mood::Happy,
+                    Mood::Sad,
+                    Mood::Happy,
+                    Mood::Excited,
+                    Mood::Happy,
+                ])
+            });
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            get_user_repository_with_timezone(None),
+        );
+        let stats = post_service
+            .get_mood_stats(5, Some(from), Some(to))
+            .unwrap();
+        let counts: Vec<(Mood, u64)> = stats.iter().map(|stat| (stat.mood, stat.count)).collect();
+
+        assert_eq!(
+            counts,
+            vec![
+                (Mood::Happy, 3),
+                (Mood::Sad, 1),
+                (Mood::Neutral, 0),
+                (Mood::Anxious, 0),
+                (Mood::Excited, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_list_with_tag() {
+        let id = 3;
+        let user_id = 5;
+        let tag = Some(String::from("travel"));
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_pinned()
+            .returning(|_, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .with(
+                eq(user_id),
+                function(is_filter(None, None, tag.clone())),
+                eq(DEFAULT_POST_PAGE_LIMIT),
+                eq(0),
+            )
+            .times(1)
+            .returning(move |passed_user_id, _, _, _| {
+                let now = Utc::now().naive_utc();
+                Ok(vec![Post {
+                    id,
+                    user_id: passed_user_id,
+                    title: String::from("Title"),
+                    content: String::from("Content"),
+                    date: now,
+                    created_at: now,
+                    updated_at: None,
+                    deleted_at: None,
+                    version: 1,
+                    publish_at: None,
+                    status: PostStatus::Published,
+                    pinned: false,
+                    pinned_at: None,
+                    share_token: None,
+                    mood: None,
+                    category_id: None,
+                }])
+            });
+        mocked_post_repository
+            .expect_count()
+            .with(eq(user_id), function(is_filter(None, None, tag)))
+            .times(1)
+            .returning(|_, _| Ok(1));
+
+        let mut mocked_post_tag_repository = MockPostTagRepositoryTrait::new();
+        mocked_post_tag_repository
+            .expect_find_all_by_post_ids()
+            .withf(move |post_ids| post_ids == [id])
+            .times(1)
+            .returning(move |_| {
+                Ok(vec![
+                    PostTag {
+                        id: 1,
+                        post_id: id,
+                        tag: String::from("travel"),
+                    },
+                    PostTag {
+                        id: 2,
+                        post_id: id,
+                        tag: String::from("food"),
+                    },
+                ])
+            });
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            mocked_post_tag_repository,
+            MockUserRepositoryTrait::new(),
+        );
+        let post_page = post_service
+            .get_list(
+                user_id,
+                None,
+                None,
+                &[String::from(" Travel ")],
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(
+            post_page.posts.first().unwrap().tags,
+            vec![String::from("travel"), String::from("food")]
+        );
+        assert_eq!(post_page.total_count, 1);
+    }
+
+    fn get_tag_mode_post_repository(
+        user_id: u64,
+        tags: Vec<String>,
+        tag_mode: TagMode,
+    ) -> MockPostRepositoryTrait {
+        let is_tag_filter =
+            move |filter: &PostFilter| filter.tags == tags && filter.tag_mode == tag_mode;
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_pinned()
+            .returning(|_, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .withf({
+                let is_tag_filter = is_tag_filter.clone();
+                move |passed_user_id, filter, _, _| {
+                    *passed_user_id == user_id && is_tag_filter(filter)
+                }
+            })
+            .times(1)
+            .returning(|_, _, _, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_count()
+            .withf(move |passed_user_id, filter| {
+                *passed_user_id == user_id && is_tag_filter(filter)
+            })
+            .times(1)
+            .returning(|_, _| Ok(0));
+        mocked_post_repository
+    }
+
+    #[test]
+    fn test_get_list_with_all_tags() {
+        let user_id = 5;
+        let tags = vec![String::from("travel"), String::from("food")];
+
+        let mut post_service = PostService::new_with_repository(
+            get_tag_mode_post_repository(user_id, tags, TagMode::All),
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result = post_service.get_list(
+            user_id,
+            None,
+            None,
+            &[
+                String::from("Travel"),
+                String::from(" food"),
+                String::from(""),
+            ],
+            Some(String::from("all")),
+            None,
+            None,
+            None,
+            None,
+        );
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_get_list_with_any_tags_by_default() {
+        let user_id = 5;
+        let tags = vec![String::from("travel"), String::from("food")];
+
+        let mut post_service = PostService::new_with_repository(
+            get_tag_mode_post_repository(user_id, tags.clone(), TagMode::Any),
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result =
+            post_service.get_list(user_id, None, None, &tags, None, None, None, None, None);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_get_list_with_unknown_tag_mode() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result = post_service.get_list(
+            5,
+            None,
+            None,
+            &[String::from("travel")],
+            Some(String::from("none")),
+            None,
+            None,
+            None,
+            None,
+        );
+
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_get_list_with_category() {
+        let user_id = 5;
+        let category_id = 7;
+        let posts = Arc::new(vec![
+            Post {
+                category_id: Some(category_id),
+                ..get_post_with_date(1, user_id, "2020-06-02T00:00:00")
+            },
+            Post {
+                category_id: None,
+                ..get_post_with_date(2, user_id, "2020-06-01T00:00:00")
+            },
+        ]);
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_pinned()
+            .returning(|_, _| Ok(vec![]));
+        let find_page_posts = posts.clone();
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .withf(move |passed_user_id, filter, _, _| {
+                *passed_user_id == user_id && filter.category_id == Some(category_id)
+            })
+            .times(1)
+            .returning(move |_, filter, _, _| {
+                Ok(find_page_posts
+                    .iter()
+                    .filter(|post| post.category_id == filter.category_id)
+                    .map(|post| Post {
+                        title: post.title.clone(),
+                        content: post.content.clone(),
+                        share_token: post.share_token.clone(),
+                        ..*post
+                    })
+                    .collect())
+            });
+        mocked_post_repository
+            .expect_count()
+            .withf(move |_, filter| filter.category_id == Some(category_id))
+            .times(1)
+            .returning(|_, _| Ok(1));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+        let post_page = post_service
+            .get_list(
+                user_id,
+                None,
+                None,
+                &[],
+                None,
+                Some(category_id),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(post_page.posts.len(), 1);
+        assert_eq!(post_page.posts[0].id, 1);
+        assert_eq!(post_page.posts[0].category_id, Some(category_id));
+        assert_eq!(post_page.total_count, 1);
+    }
+
+    #[test]
+    fn test_set_category_of_another_user() {
+        let user_id = 5;
+
+        let mut mocked_category_repository = MockCategoryRepositoryTrait::new();
+        mocked_category_repository
+            .expect_find()
+            .with(eq(7), eq(user_id))
+            .times(1)
+            .returning(|id, _| Err(ServiceError::NotFound(id.to_string())));
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository.expect_set_category().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        )
+        .with_category_repository(mocked_category_repository);
+        let result = post_service.set_category(3, user_id, Some(7));
+
+        assert!(matches!(result, Err(ServiceError::NotFound(_))));
+    }
+
+    fn get_post_with_date(id: u64, user_id: u64, date: &str) -> Post {
+        let date = NaiveDateTime::from_str(date).unwrap();
+        Post {
+            id,
+            user_id,
+            title: String::from("Title"),
+            content: String::from("Content"),
+            date,
+            created_at: date,
+            updated_at: None,
+            deleted_at: None,
+            version: 1,
+            publish_at: None,
+            status: PostStatus::Published,
+            pinned: false,
+            pinned_at: None,
+            share_token: None,
+            mood: None,
+            category_id: None,
+        }
+    }
+
+    fn get_post_tag(post_id: u64, tag: &str) -> PostTag {
+        PostTag {
+            id: 0,
+            post_id,
+            tag: String::from(tag),
+        }
+    }
+
+    fn get_pinned_post(id: u64, user_id: u64, date: &str, pinned_at: &str) -> Post {
+        Post {
+            pinned: true,
+            pinned_at: Some(NaiveDateTime::from_str(pinned_at).unwrap()),
+            ..get_post_with_date(id, user_id, date)
+        }
+    }
+
+    #[test]
+    fn test_get_list_with_pinned_posts() {
+        let user_id = 5;
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_pinned()
+            .returning(|passed_user_id, _| {
+                Ok(vec![
+                    get_pinned_post(
+                        1,
+                        passed_user_id,
+                        "2020-01-01T00:00:00",
+                        "2020-06-01T00:00:00",
+                    ),
+                    get_pinned_post(
+                        2,
+                        passed_user_id,
+                        "2020-02-01T00:00:00",
+                        "2020-07-01T00:00:00",
+                    ),
+                ])
+            });
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .with(always(), always(), eq(3), eq(0))
+            .times(1)
+            .returning(|passed_user_id, _, _, _| {
+                Ok(vec![
+                    get_post_with_date(4, passed_user_id, "2020-05-01T00:00:00"),
+                    get_post_with_date(3, passed_user_id, "2020-04-01T00:00:00"),
+                    get_post_with_date(5, passed_user_id, "2020-03-01T00:00:00"),
+                ])
+            });
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .with(always(), always(), eq(2), eq(0))
+            .times(1)
+            .returning(|passed_user_id, _, _, _| {
+                Ok(vec![
+                    get_post_with_date(4, passed_user_id, "2020-05-01T00:00:00"),
+                    get_post_with_date(3, passed_user_id, "2020-04-01T00:00:00"),
+                ])
+            });
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .with(always(), always(), eq(2), eq(1))
+            .times(1)
+            .returning(|passed_user_id, _, _, _| {
+                Ok(vec![
+                    get_post_with_date(3, passed_user_id, "2020-04-01T00:00:00"),
+                    get_post_with_date(5, passed_user_id, "2020-03-01T00:00:00"),
+                ])
+            });
+        mocked_post_repository
+            .expect_count()
+            .returning(|_, _| Ok(5));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+
+        let post_page = post_service
+            .get_list(user_id, None, None, &[], None, None, Some(5), None, None)
+            .unwrap();
+        let post_ids: Vec<u64> = post_page.posts.iter().map(|post| post.id).collect();
+        assert_eq!(post_ids, vec![2, 1, 4, 3, 5]);
+        assert!(post_page.posts[0].pinned && post_page.posts[1].pinned);
+        assert!(!post_page.posts[2].pinned);
+        assert_eq!(post_page.total_count, 5);
+
+        let post_page = post_service
+            .get_list(user_id, None, None, &[], None, None, Some(3), Some(1), None)
+            .unwrap();
+        let post_ids: Vec<u64> = post_page.posts.iter().map(|post| post.id).collect();
+        assert_eq!(post_ids, vec![1, 4, 3]);
+
+        let post_page = post_service
+            .get_list(user_id, None, None, &[], None, None, Some(2), Some(3), None)
+            .unwrap();
+        let post_ids: Vec<u64> = post_page.posts.iter().map(|post| post.id).collect();
+        assert_eq!(post_ids, vec![3, 5]);
+    }
+
+    fn sort_in_desc_date_order(posts: &[Post]) -> Vec<Post> {
+        let mut posts: Vec<Post> = posts
+            .iter()
+            .map(|post| Post {
+                title: post.title.clone(),
+                content: post.content.clone(),
+                share_token: post.share_token.clone(),
+                ..*post
+            })
+            .collect();
+        posts.sort_by(|a, b| b.date.cmp(&a.date).then(b.id.cmp(&a.id)));
+        posts
+    }
+
+    #[test]
+    fn test_get_list_with_cursor_while_creating_post() {
+        let user_id = 5;
+        let posts: Arc<Mutex<Vec<Post>>> = Arc::new(Mutex::new(
+            (1..=5)
+                .map(|id| get_post_with_date(id, user_id, &format!("2020-06-0{}T09:00:00", id)))
+                .collect(),
+        ));
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_pinned()
+            .returning(|_, _| Ok(vec![]));
+        let find_page_posts = posts.clone();
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .returning(move |_, _, limit, offset| {
+                let posts = sort_in_desc_date_order(&find_page_posts.lock().unwrap());
+                Ok(posts
+                    .into_iter()
+                    .skip(offset as usize)
+                    .take(limit as usize)
+                    .collect())
+            });
+        let find_page_after_posts = posts.clone();
+        mocked_post_repository
+            .expect_find_page_after()
+            .returning(move |_, _, cursor, limit| {
+                let posts = sort_in_desc_date_order(&find_page_after_posts.lock().unwrap());
+                Ok(posts
+                    .into_iter()
+                    .filter(|post| (post.date, post.id) < (cursor.date, cursor.id))
+                    .take(limit as usize)
+                    .collect())
+            });
+        let count_posts = posts.clone();
+        mocked_post_repository
+            .expect_count()
+            .returning(move |_, _| Ok(count_posts.lock().unwrap().len() as u64));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+
+        let first_page = post_service
+            .get_list(user_id, None, None, &[], None, None, Some(2), None, None)
+            .unwrap();
+        let mut post_ids: Vec<u64> = first_page.posts.iter().map(|post| post.id).collect();
+
+        posts
+            .lock()
+            .unwrap()
+            .push(get_post_with_date(6, user_id, "2020-06-06T09:00:00"));
+
+        // The offset drifts by the new post, so the last post of the first page comes again.
+        let offset_page = post_service
+            .get_list(user_id, None, None, &[], None, None, Some(2), Some(2), None)
+            .unwrap();
+        assert_eq!(offset_page.posts.first().unwrap().id, 4);
+
+        let mut next = first_page.next;
+        while let Some(after) = next {
+            let post_page = post_service
+                .get_list(
+                    user_id,
+                    None,
+                    None,
+                    &[],
+                    None,
+                    None,
+                    Some(2),
+                    None,
+                    Some(after),
+                )
+                .unwrap();
+            post_ids.extend(post_page.posts.iter().map(|post| post.id));
+            next = post_page.next;
+        }
+
+        assert_eq!(post_ids, vec![5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_get_list_with_invalid_cursor() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository.expect_find_page_after().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result = post_service.get_list(
+            5,
+            None,
+            None,
+            &[],
+            None,
+            None,
+            None,
+            None,
+            Some(String::from("not a cursor")),
+        );
+
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_pin_over_limit() {
+        let user_id = 5;
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_by_id()
+            .returning(move |id| Ok(get_post_with_date(id, user_id, "2020-06-01T00:00:00")));
+        mocked_post_repository
+            .expect_count_pinned()
+            .with(eq(user_id))
+            .times(1)
+            .returning(|_| Ok(MAX_PINNED_POSTS - 1));
+        mocked_post_repository
+            .expect_count_pinned()
+            .with(eq(user_id))
+            .returning(|_| Ok(MAX_PINNED_POSTS));
+        mocked_post_repository
+            .expect_pin()
+            .with(eq(user_id), eq(3))
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+
+        assert!(post_service.pin(3, user_id).unwrap());
+        let result = post_service.pin(4, user_id);
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    /// Returns a post repository which keeps the share token of the post in memory.
+    fn get_in_memory_post_repository_for_sharing(
+        user_id: u64,
+        deleted_at: Option<NaiveDateTime>,
+        share_token: Arc<Mutex<Option<String>>>,
+    ) -> MockPostRepositoryTrait {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        let get_post = move |share_token: Option<String>| Post {
+            deleted_at,
+            share_token,
+            ..get_post_with_date(3, user_id, "2020-06-01T00:00:00")
+        };
+
+        let found_share_token = share_token.clone();
+        mocked_post_repository
+            .expect_find_by_id()
+            .returning(move |_| Ok(get_post(found_share_token.lock().unwrap().clone())));
+
+        let found_share_token = share_token.clone();
+        mocked_post_repository
+            .expect_find_by_share_token()
+            .returning(move |token| match &*found_share_token.lock().unwrap() {
+                Some(share_token) if share_token == token => {
+                    Ok(get_post(Some(share_token.clone())))
+                }
+                _ => Err(ServiceError::NotFound(token.to_string())),
+            });
+
+        mocked_post_repository
+            .expect_set_share_token()
+            .returning(move |_, _, token| {
+                *share_token.lock().unwrap() = token.clone();
+                Ok(true)
+            });
+
+        mocked_post_repository
+    }
+
+    #[test]
+    fn test_share_and_unshare() {
+        let user_id = 5;
+        let share_token = Arc::new(Mutex::new(None));
+
+        let mut mocked_post_tag_repository = MockPostTagRepositoryTrait::new();
+        mocked_post_tag_repository
+            .expect_find_all_by_post_ids()
+            .returning(|_| Ok(vec![]));
+
+        let mut post_service = PostService::new_with_repository(
+            get_in_memory_post_repository_for_sharing(user_id, None, share_token.clone()),
+            mocked_post_tag_repository,
+            MockUserRepositoryTrait::new(),
+        );
+        let now = Utc::now().naive_utc();
+
+        let token = post_service.share(3, user_id).unwrap();
+        assert_eq!(token.len(), SHARE_TOKEN_LENGTH);
+        assert_eq!(post_service.share(3, user_id).unwrap(), token);
+
+        let post = post_service.get_shared(&token, &now).unwrap();
+        assert_eq!(post.id, 3);
+        assert_eq!(post.content, "Content");
+
+        let result = post_service.get_shared("guessed", &now);
+        assert!(matches!(result, Err(ServiceError::NotFound(_))));
+
+        let result = post_service.share(3, 7);
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+
+        assert!(post_service.unshare(3, user_id).unwrap());
+        assert_eq!(*share_token.lock().unwrap(), None);
+
+        let result = post_service.get_shared(&token, &now);
+        assert!(matches!(result, Err(ServiceError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_get_shared_post_in_trash() {
+        let share_token = Arc::new(Mutex::new(Some(String::from("a1lam9cBko"))));
+        let deleted_at = Some(Utc::now().naive_utc());
+
+        let mut post_service = PostService::new_with_repository(
+            get_in_memory_post_repository_for_sharing(5, deleted_at, share_token),
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+
+        let result = post_service.get_shared("a1lam9cBko", &Utc::now().naive_utc());
+        assert!(matches!(result, Err(ServiceError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_pin_by_another_user() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_by_id()
+            .returning(|id| Ok(get_post_with_date(id, 5, "2020-06-01T00:00:00")));
+        mocked_post_repository.expect_pin().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result = post_service.pin(3, 7);
+
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[test]
+    fn test_search() {
+        let user_id = 5;
+
+        let mut mocked_post_tag_repository = MockPostTagRepositoryTrait::new();
+        mocked_post_tag_repository
+            .expect_find_all_matching()
+            .withf(move |passed_user_id, terms| {
+                *passed_user_id == user_id && terms == [String::from("tra"), String::from("food")]
+            })
+            .times(1)
+            .returning(|_, _| {
+                Ok(vec![
+                    get_post_tag(1, "travel"),
+                    get_post_tag(2, "travel"),
+                    get_post_tag(2, "food"),
+                    get_post_tag(3, "trail"),
+                ])
+            });
+        mocked_post_tag_repository
+            .expect_find_all_by_post_ids()
+            .returning(|_| Ok(vec![]));
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_by_ids()
+            .withf(move |passed_user_id, post_ids, _| {
+                *passed_user_id == user_id && post_ids == [1, 2, 3]
+            })
+            .times(1)
+            .returning(|passed_user_id, _, _| {
+                Ok(vec![
+                    get_post_with_date(1, passed_user_id, "2020-06-01T00:00:00"),
+                    get_post_with_date(2, passed_user_id, "2020-05-01T00:00:00"),
+                    get_post_with_date(3, passed_user_id, "2020-07-01T00:00:00"),
+                ])
+            });
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            mocked_post_tag_repository,
+            MockUserRepositoryTrait::new(),
+        );
+        let post_page = post_service
+            .search(user_id, " Tra  food tra", None, None)
+            .unwrap();
+
+        let post_ids: Vec<u64> = post_page.posts.iter().map(|post| post.id).collect();
+        assert_eq!(post_ids, vec![2, 3, 1]);
+        assert_eq!(post_page.total_count, 3);
+    }
+
+    #[test]
+    fn test_search_without_scheduled_post() {
+        let user_id = 5;
+
+        let mut mocked_post_tag_repository = MockPostTagRepositoryTrait::new();
+        mocked_post_tag_repository
+            .expect_find_all_matching()
+            .times(1)
+            .returning(|_, _| Ok(vec![get_post_tag(1, "travel"), get_post_tag(2, "travel")]));
+        mocked_post_tag_repository
+            .expect_find_all_by_post_ids()
+            .returning(|_| Ok(vec![]));
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_by_ids()
+            .withf(|_, _, published_until| {
+                (Utc::now().naive_utc() - *published_until)
+                    .num_seconds()
+                    .abs()
+                    < 60
+            })
+            .times(1)
+            .returning(|passed_user_id, _, _| {
+                Ok(vec![
+                    get_post_with_date(1, passed_user_id, "2020-06-01T00:00:00"),
+                    Post {
+                        publish_at: Some(Utc::now().naive_utc() + Duration::days(1)),
+                        ..get_post_with_date(2, passed_user_id, "2020-06-02T00:00:00")
+                    },
+                ])
+            });
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            mocked_post_tag_repository,
+            MockUserRepositoryTrait::new(),
+        );
+        let post_page = post_service.search(user_id, "travel", None, None).unwrap();
+
+        let post_ids: Vec<u64> = post_page.posts.iter().map(|post| post.id).collect();
+        assert_eq!(post_ids, vec![1]);
+        assert_eq!(post_page.total_count, 1);
+    }
+
+    #[test]
+    fn test_search_without_match() {
+        let mut mocked_post_tag_repository = MockPostTagRepositoryTrait::new();
+        mocked_post_tag_repository
+            .expect_find_all_matching()
+            .times(1)
+            .returning(|_, _| Ok(vec![]));
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository.expect_find_all_by_ids().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            mocked_post_tag_repository,
+            MockUserRepositoryTrait::new(),
+        );
+        let post_page = post_service.search(5, "nothing", None, None).unwrap();
+
+        assert!(post_page.posts.is_empty());
+        assert_eq!(post_page.total_count, 0);
+    }
+
+    #[test]
+    fn test_search_with_empty_query() {
+        let mut mocked_post_tag_repository = MockPostTagRepositoryTrait::new();
+        mocked_post_tag_repository
+            .expect_find_all_matching()
+            .times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            MockPostRepositoryTrait::new(),
+            mocked_post_tag_repository,
+            MockUserRepositoryTrait::new(),
+        );
+
+        assert!(matches!(
+            post_service.search(5, "", None, None),
+            Err(ServiceError::InvalidArgument)
+        ));
+        assert!(matches!(
+            post_service.search(5, "   ", None, None),
+            Err(ServiceError::InvalidArgument)
+        ));
+    }
+
+    fn get_post_update() -> PostUpdateDTO {
+        PostUpdateDTO {
+            title: None,
+            content: Some(String::from("New content")),
+            date: None,
+            tags: None,
+            mood: None,
+        }
+    }
+
+    #[test]
+    fn test_update_with_current_version() {
+        let id = 3;
+        let user_id = 5;
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_by_id()
+            .with(eq(id))
+            .times(1)
+            .returning(move |passed_id| {
+                Ok(Post {
+                    version: 2,
+                    ..get_post_with_date(passed_id, user_id, "2020-06-01T00:00:00")
+                })
+            });
+        mocked_post_repository
+            .expect_update()
+            .withf(
+                move |passed_user_id, passed_id, version, _, content, _, _| {
+                    *passed_user_id == user_id
+                        && *passed_id == id
+                        && *version == 2
+                        && *content == Some(String::from("New content"))
+                },
+            )
+            .times(1)
+            .returning(|_, _, _, _, _, _, _| Ok(true));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+
+        assert!(post_service
+            .update(id, user_id, 2, &get_post_update())
+            .unwrap());
+    }
+
+    #[test]
+    fn test_update_refreshes_updated_at() {
+        let id = 3;
+        let user_id = 5;
+        let updated_at: Arc<Mutex<Option<NaiveDateTime>>> = Arc::new(Mutex::new(None));
+
+        let get_post = move |updated_at: Option<NaiveDateTime>| Post {
+            updated_at,
+            ..get_post_with_date(id, user_id, "2020-06-01T00:00:00")
+        };
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        let found_updated_at = updated_at.clone();
+        mocked_post_repository
+            .expect_find()
+            .with(eq(user_id), eq(id))
+            .times(2)
+            .returning(move |_, _| Ok(get_post(*found_updated_at.lock().unwrap())));
+        mocked_post_repository
+            .expect_find_by_id()
+            .with(eq(id))
+            .times(1)
+            .returning(move |_| Ok(get_post(None)));
+        let refreshed_updated_at = updated_at.clone();
+        mocked_post_repository
+            .expect_update()
+            .times(1)
+            .returning(move |_, _, _, _, _, _, _| {
+                *refreshed_updated_at.lock().unwrap() = Some(Utc::now().naive_utc());
+                Ok(true)
+            });
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+
+        let post = post_service.get(user_id, id).unwrap();
+        let serialized_post = serde_json::to_value(&post).unwrap();
+        assert_eq!(serialized_post["created_at"], json!("2020-06-01T00:00:00Z"));
+        assert_eq!(serialized_post["updated_at"], json!(null));
+
+        let updated_since = Utc::now();
+        post_service
+            .update(id, user_id, 1, &get_post_update())
+            .unwrap();
+
+        let updated_post = post_service.get(user_id, id).unwrap();
+        assert_eq!(updated_post.created_at, post.created_at);
+        assert!(updated_post.updated_at.unwrap() >= updated_since);
+        assert!(serde_json::to_value(&updated_post).unwrap()["updated_at"]
+            .as_str()
+            .unwrap()
+            .ends_with('Z'));
+    }
+
+    #[test]
+    fn test_update_with_stale_version() {
+        let id = 3;
+        let user_id = 5;
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_by_id()
+            .with(eq(id))
+            .times(1)
+            .returning(move |passed_id| {
+                Ok(Post {
+                    version: 3,
+                    ..get_post_with_date(passed_id, user_id, "2020-06-01T00:00:00")
+                })
+            });
+        mocked_post_repository.expect_update().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result = post_service.update(id, user_id, 2, &get_post_update());
+
+        assert!(matches!(result, Err(ServiceError::Conflict(_))));
+    }
+
+    #[test]
+    fn test_update_only_title() {
+        let id = 3;
+        let user_id = 5;
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_by_id()
+            .with(eq(id))
+            .times(1)
+            .returning(move |passed_id| {
+                Ok(Post {
+                    version: 2,
+                    ..get_post_with_date(passed_id, user_id, "2020-06-01T00:00:00")
+                })
+            });
+        mocked_post_repository
+            .expect_update()
+            .withf(|_, _, _, title, content, date, mood| {
+                *title == Some(String::from("New title"))
+                    && content.is_none()
+                    && date.is_none()
+                    && mood.is_none()
+            })
+            .times(1)
+            .returning(|_, _, _, _, _, _, _| Ok(true));
+        let mut mocked_post_tag_repository = MockPostTagRepositoryTrait::new();
+        mocked_post_tag_repository.expect_replace().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            mocked_post_tag_repository,
+            MockUserRepositoryTrait::new(),
+        );
+        let post_update = PostUpdateDTO {
+            title: Some(String::from("New title")),
+            content: None,
+            ..get_post_update()
+        };
+
+        assert!(post_service.update(id, user_id, 2, &post_update).unwrap());
+    }
+
+    #[test]
+    fn test_update_without_fields() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository.expect_find_by_id().times(0);
+        mocked_post_repository.expect_update().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let post_update = PostUpdateDTO {
+            content: None,
+            ..get_post_update()
+        };
+        let result = post_service.update(3, 5, 2, &post_update);
+
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_get_list_with_scheduled_post() {
+        let id = 3;
+        let user_id = 5;
+        let now = Utc::now().naive_utc();
+        let post = Arc::new(Post {
+            publish_at: Some(now + Duration::milliseconds(300)),
+            status: PostStatus::Published,
+            pinned: false,
+            pinned_at: None,
+            share_token: None,
+            mood: None,
+            ..get_post_with_date(id, user_id, "2020-06-01T00:00:00")
+        });
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        let find_page_post = post.clone();
+        mocked_post_repository
+            .expect_find_all_pinned()
+            .returning(|_, _| Ok(vec![]));
+        mocked_post_repository
+            .expect_find_page_in_desc_date_order()
+            .times(2)
+            .returning(move |_, filter, _, _| {
+                if find_page_post.publish_at.unwrap() <= filter.published_until {
+                    Ok(vec![Post {
+                        title: find_page_post.title.clone(),
+                        content: find_page_post.content.clone(),
+                        share_token: find_page_post.share_token.clone(),
+                        ..*find_page_post
+                    }])
+                } else {
+                    Ok(vec![])
+                }
+            });
+        mocked_post_repository
+            .expect_count()
+            .times(2)
+            .returning(move |_, filter| {
+                Ok((post.publish_at.unwrap() <= filter.published_until) as u64)
+            });
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            get_post_tag_repository_without_tags(),
+            MockUserRepositoryTrait::new(),
+        );
+
+        let post_page = post_service
+            .get_list(user_id, None, None, &[], None, None, None, None, None)
+            .unwrap();
+        assert!(post_page.posts.is_empty());
+        assert_eq!(post_page.total_count, 0);
+
+        thread::sleep(std::time::Duration::from_millis(400));
+
+        let post_page = post_service
+            .get_list(user_id, None, None, &[], None, None, None, None, None)
+            .unwrap();
+        assert_eq!(post_page.posts.first().unwrap().id, id);
+        assert_eq!(post_page.total_count, 1);
+    }
+
+    #[test]
+    fn test_export_without_posts_of_another_user() {
+        let user_id = 5;
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all()
+            .with(eq(user_id))
+            .times(1)
+            .returning(|passed_user_id| {
+                Ok(vec![
+                    get_post_with_date(1, passed_user_id, "2020-06-01T00:00:00"),
+                    get_post_with_date(2, 7, "2020-06-02T00:00:00"),
+                    Post {
+                        deleted_at: Some(NaiveDateTime::from_str("2020-06-04T00:00:00").unwrap()),
+                        ..get_post_with_date(3, passed_user_id, "2020-06-03T00:00:00")
+                    },
+                ])
+            });
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let post_list = post_service.export(user_id).unwrap();
+
+        let post_ids: Vec<u64> = post_list.iter().map(|post| post.id).collect();
+        assert_eq!(post_ids, vec![1, 3]);
+        assert!(post_list.iter().all(|post| post.user_id == user_id));
+    }
+
+    #[test]
+    fn test_import_with_malformed_entries() {
+        let user_id = 5;
+        let entries = vec![
+            json!({
+                "id": 1,
+                "user_id": 7,
+                "title": "Lorem ipsum",
+                "content": "Lorem ipsum dolor sit amet",
+                "date": "2020-06-01T00:00:00",
+                "created_at": "2020-06-01T09:00:00",
+                "updated_at": "2020-06-02T09:00:00",
+                "deleted_at": null,
+                "version": 3,
+                "publish_at": null,
+            }),
+            json!({
+                "title": "Dolor sit amet",
+                "content": "Consectetur adipiscing elit",
+                "date": "2020-06-03T00:00:00",
+            }),
+            json!({
+                "title": "Lorem ipsum",
+                "date": "2020-06-04T00:00:00",
+            }),
+            json!({
+                "title": "Lorem ipsum",
+                "content": "Lorem ipsum dolor sit amet",
+                "date": "yesterday",
+            }),
+            json!({
+                "title": " ",
+                "content": "Lorem ipsum dolor sit amet",
+                "date": "2020-06-05T00:00:00",
+            }),
+            json!({
+                "title": "Lorem ipsum",
+                "content": "a".repeat(DEFAULT_MAX_POST_CONTENT_LENGTH + 1),
+                "date": "2020-06-06T00:00:00",
+            }),
+            json!("Lorem ipsum"),
+        ];
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_create_all()
+            .withf(move |passed_user_id, posts| {
+                *passed_user_id == user_id
+                    && posts.len() == 2
+                    && posts[0].created_at
+                        == Some(NaiveDateTime::from_str("2020-06-01T09:00:00").unwrap())
+                    && posts[0].updated_at
+                        == Some(NaiveDateTime::from_str("2020-06-02T09:00:00").unwrap())
+                    && posts[1].title == "Dolor sit amet"
+                    && posts[1].created_at.is_none()
+            })
+            .times(1)
+            .returning(|_, posts| Ok(posts.len() as u64));
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result = post_service.import(user_id, &entries).unwrap();
+
+        assert_eq!(result.imported_count, 2);
+        assert_eq!(result.skipped_count, 5);
+    }
+
+    #[test]
+    fn test_import_without_valid_entries() {
+        let entries = vec![json!({ "title": "Lorem ipsum" }), json!(null)];
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository.expect_create_all().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result = post_service.import(5, &entries).unwrap();
+
+        assert_eq!(result.imported_count, 0);
+        assert_eq!(result.skipped_count, 2);
+    }
+
+    #[test]
+    fn test_import_with_configured_max_length() {
+        let entries = vec![
+            json!({
+                "title": "Lorem ipsum",
+                "content": "Lorem ipsum dolor sit amet",
+                "date": "2020-06-01T00:00:00",
+            }),
+            json!({
+                "title": "Lorem ipsum",
+                "content": "Lorem ipsum dolor sit amet, consectetur adipiscing elit",
+                "date": "2020-06-02T00:00:00",
+            }),
+        ];
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_create_all()
+            .withf(|_, posts| posts.len() == 1 && posts[0].content == "Lorem ipsum dolor sit amet")
+            .times(1)
+            .returning(|_, posts| Ok(posts.len() as u64));
+
+        let mut post_service = PostService {
+            max_content_length: 32,
+            ..PostService::new_with_repository(
+                mocked_post_repository,
+                MockPostTagRepositoryTrait::new(),
+                MockUserRepositoryTrait::new(),
+            )
+        };
+        let result = post_service.import(5, &entries).unwrap();
+
+        assert_eq!(result.imported_count, 1);
+        assert_eq!(result.skipped_count, 1);
+    }
+
+    #[test]
+    fn test_import_with_too_many_entries() {
+        let entry = json!({
+            "title": "Lorem ipsum",
+            "content": "Lorem ipsum dolor sit amet",
+            "date": "2020-06-01T00:00:00",
+        });
+        let entries = vec![entry; MAX_IMPORTED_POSTS + 1];
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository.expect_create_all().times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        );
+        let result = post_service.import(5, &entries);
+
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_import_over_creation_limit() {
+        let entries = vec![json!({
+            "title": "Lorem ipsum",
+            "content": "Lorem ipsum dolor sit amet",
+            "date": "2020-06-01T00:00:00",
+        })];
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository.expect_create_all().times(0);
+
+        let mut mocked_post_creation_count_repository = PostCreationCountRepository::default();
+        mocked_post_creation_count_repository
+            .expect_find()
+            .times(1)
+            .returning(|| Ok(DEFAULT_POST_CREATION_LIMIT));
+        mocked_post_creation_count_repository
+            .expect_increase()
+            .times(0);
+
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            MockUserRepositoryTrait::new(),
+        )
+        .with_post_creation_count_repository(mocked_post_creation_count_repository);
+        let result = post_service.import(5, &entries);
+
+        assert!(matches!(result, Err(ServiceError::TooManyRequests(3600))));
+    }
+
+    fn get_user_repository_with_timezone(timezone: Option<&str>) -> MockUserRepositoryTrait {
+        let timezone = timezone.map(String::from);
+        let mut mocked_user_repository = MockUserRepositoryTrait::new();
+        mocked_user_repository
+            .expect_find_by_id()
+            .times(1)
+            .returning(move |id| {
+                Ok(User {
+                    timezone: timezone.clone(),
+                    ..test_util::get_user(id, "park@email.com")
+                })
+            });
+        mocked_user_repository
+    }
+
+    #[test]
+    fn test_count_streaks_with_consecutive_days() {
+        let today = NaiveDate::from_str("2020-06-10").unwrap();
+        let days = vec![
+            NaiveDate::from_str("2020-06-10").unwrap(),
+            NaiveDate::from_str("2020-06-08").unwrap(),
+            NaiveDate::from_str("2020-06-09").unwrap(),
+            NaiveDate::from_str("2020-06-09").unwrap(),
+        ];
+
+        assert_eq!(PostService::count_streaks(days, today), (3, 3));
+    }
+
+    #[test]
+    fn test_count_streaks_across_gap() {
+        let today = NaiveDate::from_str("2020-06-10").unwrap();
+        let days = vec![
+            NaiveDate::from_str("2020-06-01").unwrap(),
+            NaiveDate::from_str("2020-06-02").unwrap(),
+            NaiveDate::from_str("2020-06-03").unwrap(),
+            NaiveDate::from_str("2020-06-05").unwrap(),
+            NaiveDate::from_str("2020-06-08").unwrap(),
+            NaiveDate::from_str("2020-06-09").unwrap(),
+        ];
+
+        assert_eq!(PostService::count_streaks(days, today), (2, 3));
+    }
+
+    #[test]
+    fn test_count_streaks_broken_before_yesterday() {
+        let today = NaiveDate::from_str("2020-06-10").unwrap();
+        let days = vec![
+            NaiveDate::from_str("2020-06-07").unwrap(),
+            NaiveDate::from_str("2020-06-08").unwrap(),
+            NaiveDate::from_str("2020-06-12").unwrap(),
+        ];
+
+        assert_eq!(PostService::count_streaks(days, today), (0, 2));
+        assert_eq!(PostService::count_streaks(vec![], today), (0, 0));
+    }
+
+    #[test]
+    fn test_get_streak_in_timezone_of_user() {
+        let user_id = 5;
+        let now = Utc::now().naive_utc();
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_dates()
+            .with(eq(user_id), function(is_filter(None, None, None)))
+            .times(2)
+            .returning(move |_, _| Ok(vec![now, now - Duration::days(1)]));
 
-        let mut post_service = PostService::new_with_repository(mocked_post_repository);
-        let post_list: Vec<PostDTO> = post_service.get_list(user_id).unwrap();
+        let mut post_service = PostService::new_with_repository(
+            mocked_post_repository,
+            MockPostTagRepositoryTrait::new(),
+            get_user_repository_with_timezone(Some("Asia/Seoul")),
+        );
+        let streak = post_service.get_streak(user_id).unwrap();
+        assert_eq!(streak.current_streak, 2);
+        assert_eq!(streak.longest_streak, 2);
 
-        assert_eq!(post_list.first().unwrap().id, id);
+        post_service.user_repository = Some(get_user_repository_with_timezone(Some("Unknown")));
+        let streak = post_service.get_streak(user_id).unwrap();
+        assert_eq!(streak.current_streak, 2);
     }
 }
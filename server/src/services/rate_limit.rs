@@ -0,0 +1,324 @@
+use chrono::NaiveDateTime;
+
+use crate::models::error::{get_service_error, ServiceError};
+use crate::models::rate_limit::*;
+
+/// Paths of routes excluded from rate limiting, such as health checks and metrics scraping.
+const EXCLUDED_PATHS: [&str; 4] = ["/", "/health", "/ready", "/metrics"];
+
+/// A default rule applied to all routes.
+const DEFAULT_RULE: RateLimitRule = RateLimitRule {
+    capacity: 60,
+    refill_per_minute: 60,
+};
+
+/// A maximum number of attempts to take a token while concurrent requests of the same client
+/// keep changing the bucket.
+const MAX_TAKE_ATTEMPTS: usize = 3;
+
+/// A stricter rule applied to the routes under `/auth` to slow down brute force attacks.
+const DEFAULT_AUTH_RULE: RateLimitRule = RateLimitRule {
+    capacity: 10,
+    refill_per_minute: 10,
+};
+
+pub struct RateLimitService {
+    rate_limit_repository: Option<RateLimitRepository>,
+    default_rule: RateLimitRule,
+    auth_rule: RateLimitRule,
+}
+
+impl RateLimitService {
+    /// Creates a new rate limit service.
+    ///
+    /// The default rule is read from `RATE_LIMIT_CAPACITY` and `RATE_LIMIT_REFILL_PER_MINUTE`,
+    /// and the rule of `/auth` routes is read from `RATE_LIMIT_AUTH_CAPACITY` and
+    /// `RATE_LIMIT_AUTH_REFILL_PER_MINUTE`.
+    pub fn new() -> Self {
+        Self {
+            rate_limit_repository: None,
+            default_rule: RateLimitRule::from_env("RATE_LIMIT", DEFAULT_RULE),
+            auth_rule: RateLimitRule::from_env("RATE_LIMIT_AUTH", DEFAULT_AUTH_RULE),
+        }
+    }
+
+    fn rate_limit_repository(
+        &mut self,
+        new_repository: Option<RateLimitRepository>,
+    ) -> &mut RateLimitRepository {
+        match new_repository {
+            Some(_) => {
+                self.rate_limit_repository = new_repository;
+                self.rate_limit_repository.as_mut().unwrap()
+            }
+            None => self.rate_limit_repository.as_mut().unwrap(),
+        }
+    }
+
+    /// Returns the scope and the rule of the route, or `None` if the route is not rate limited.
+    fn get_rule(&self, path: &str) -> Option<(&'static str, RateLimitRule)> {
+        if EXCLUDED_PATHS.contains(&path) || path.starts_with("/metrics/") {
+            None
+        } else if path == "/auth" || path.starts_with("/auth/") {
+            Some(("auth", self.auth_rule))
+        } else {
+            Some(("default", self.default_rule))
+        }
+    }
+
+    /// Takes a token from the bucket of the client for the route.
+    ///
+    /// 1. Finds the rule of the route, and passes if the route is not rate limited or redis is
+    ///    unavailable, not to make redis a single point of failure of all routes.
+    /// 2. Refills the bucket of the client by the time elapsed since its last update.
+    /// 3. Rejects the request with the seconds until the next token if the bucket is empty.
+    /// 4. Takes a token and saves the bucket until it would be full again.
+    ///
+    /// The bucket is saved only if it hasn't been changed since it was found, so concurrent
+    /// requests of the same client can't take the same token. If another request changed it,
+    /// the steps from 2 are retried, and the request is rejected after `MAX_TAKE_ATTEMPTS`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path of the request
+    /// * `client_ip` - An IP address of the client
+    /// * `now` - A current time, passed in so that the refill can be tested
+    pub fn check(
+        &mut self,
+        path: &str,
+        client_ip: &str,
+        now: NaiveDateTime,
+    ) -> Result<(), ServiceError> {
+        let (scope, rule) = match self.get_rule(path) {
+            Some(rule) => rule,
+            None => return Ok(()),
+        };
+
+        let fallback_repository = some_if_true!(self.rate_limit_repository.is_none() => match RateLimitRepository::try_new(scope, client_ip) {
+            Some(rate_limit_repository) => rate_limit_repository,
+            None => return Ok(()),
+        });
+        let rate_limit_repository = self.rate_limit_repository(fallback_repository);
+
+        let capacity = rule.capacity as f64;
+        for _ in 0..MAX_TAKE_ATTEMPTS {
+            let tokens = match rate_limit_repository.find()? {
+                Some(bucket) => {
+                    let elapsed_seconds =
+                        (now - bucket.updated_at).num_milliseconds().max(0) as f64 / 1000.0;
+                    let refilled_tokens = elapsed_seconds * rule.refill_per_minute as f64 / 60.0;
+                    (bucket.tokens + refilled_tokens).min(capacity)
+                }
+                None => capacity,
+            };
+
+            if tokens < 1.0 {
+                let retry_after_seconds = if rule.refill_per_minute == 0 {
+                    60
+                } else {
+                    ((1.0 - tokens) * 60.0 / rule.refill_per_minute as f64).ceil() as u64
+                };
+                return Err(get_service_error(ServiceError::TooManyRequests(
+                    retry_after_seconds.max(1),
+                )));
+            }
+
+            let bucket = TokenBucket {
+                tokens: tokens - 1.0,
+                updated_at: now,
+            };
+            if rate_limit_repository.save(&bucket, rule.get_refill_seconds())? {
+                return Ok(());
+            }
+        }
+
+        Err(get_service_error(ServiceError::TooManyRequests(1)))
+    }
+}
+
+impl Default for RateLimitService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+use crate::models::rate_limit::MockRateLimitRepositoryTrait as RateLimitRepository;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use std::sync::{Arc, Mutex};
+
+    fn get_in_memory_rate_limit_repository(
+        bucket: Arc<Mutex<Option<TokenBucket>>>,
+    ) -> RateLimitRepository {
+        let mut rate_limit_repository = RateLimitRepository::default();
+        let found_bucket = bucket.clone();
+        rate_limit_repository
+            .expect_find()
+            .returning(move || Ok(found_bucket.lock().unwrap().clone()));
+        rate_limit_repository
+            .expect_save()
+            .returning(move |new_bucket, _| {
+                *bucket.lock().unwrap() = Some(new_bucket.clone());
+                Ok(true)
+            });
+        rate_limit_repository
+    }
+
+    fn get_rate_limit_service(bucket: Arc<Mutex<Option<TokenBucket>>>) -> RateLimitService {
+        RateLimitService {
+            rate_limit_repository: Some(get_in_memory_rate_limit_repository(bucket)),
+            default_rule: DEFAULT_RULE,
+            auth_rule: DEFAULT_AUTH_RULE,
+        }
+    }
+
+    #[test]
+    fn test_check_burst_beyond_limit() {
+        let now = Utc::now().naive_utc();
+        let mut rate_limit_service = get_rate_limit_service(Arc::new(Mutex::new(None)));
+
+        for _ in 0..DEFAULT_RULE.capacity {
+            assert!(rate_limit_service.check("/posts", "127.0.0.1", now).is_ok());
+        }
+        let result = rate_limit_service.check("/posts", "127.0.0.1", now);
+        assert!(matches!(result, Err(ServiceError::TooManyRequests(1))));
+    }
+
+    #[test]
+    fn test_check_auth_stricter_than_default() {
+        let now = Utc::now().naive_utc();
+        let mut rate_limit_service = get_rate_limit_service(Arc::new(Mutex::new(None)));
+
+        for _ in 0..DEFAULT_AUTH_RULE.capacity {
+            assert!(rate_limit_service
+                .check("/auth/token", "127.0.0.1", now)
+                .is_ok());
+        }
+        let result = rate_limit_service.check("/auth/token", "127.0.0.1", now);
+        assert!(matches!(result, Err(ServiceError::TooManyRequests(6))));
+    }
+
+    #[test]
+    fn test_check_refill_over_time() {
+        let now = Utc::now().naive_utc();
+        let bucket = Arc::new(Mutex::new(Some(TokenBucket {
+            tokens: 0.0,
+            updated_at: now,
+        })));
+        let mut rate_limit_service = get_rate_limit_service(bucket.clone());
+
+        let result = rate_limit_service.check("/auth/token", "127.0.0.1", now);
+        assert!(matches!(result, Err(ServiceError::TooManyRequests(6))));
+
+        let after_one_token = now + Duration::seconds(6);
+        assert!(rate_limit_service
+            .check("/auth/token", "127.0.0.1", after_one_token)
+            .is_ok());
+        assert!(rate_limit_service
+            .check("/auth/token", "127.0.0.1", after_one_token)
+            .is_err());
+
+        let after_full_refill = after_one_token + Duration::hours(1);
+        for _ in 0..DEFAULT_AUTH_RULE.capacity {
+            assert!(rate_limit_service
+                .check("/auth/token", "127.0.0.1", after_full_refill)
+                .is_ok());
+        }
+        assert!(rate_limit_service
+            .check("/auth/token", "127.0.0.1", after_full_refill)
+            .is_err());
+    }
+
+    #[test]
+    fn test_check_retries_when_bucket_is_changed_concurrently() {
+        let now = Utc::now().naive_utc();
+        let mut sequence = mockall::Sequence::new();
+        let mut rate_limit_repository = RateLimitRepository::default();
+        rate_limit_repository
+            .expect_find()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(None));
+        rate_limit_repository
+            .expect_save()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _| Ok(false));
+        rate_limit_repository
+            .expect_find()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(move || {
+                Ok(Some(TokenBucket {
+                    tokens: 1.0,
+                    updated_at: now,
+                }))
+            });
+        rate_limit_repository
+            .expect_save()
+            .withf(|bucket, _| bucket.tokens == 0.0)
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _| Ok(true));
+
+        let mut rate_limit_service = RateLimitService {
+            rate_limit_repository: Some(rate_limit_repository),
+            ..get_rate_limit_service(Arc::new(Mutex::new(None)))
+        };
+        assert!(rate_limit_service.check("/posts", "127.0.0.1", now).is_ok());
+    }
+
+    #[test]
+    fn test_check_with_bucket_always_changed_concurrently() {
+        let mut rate_limit_repository = RateLimitRepository::default();
+        rate_limit_repository
+            .expect_find()
+            .times(MAX_TAKE_ATTEMPTS)
+            .returning(|| Ok(None));
+        rate_limit_repository
+            .expect_save()
+            .times(MAX_TAKE_ATTEMPTS)
+            .returning(|_, _| Ok(false));
+
+        let mut rate_limit_service = RateLimitService {
+            rate_limit_repository: Some(rate_limit_repository),
+            ..get_rate_limit_service(Arc::new(Mutex::new(None)))
+        };
+        let result = rate_limit_service.check("/posts", "127.0.0.1", Utc::now().naive_utc());
+        assert!(matches!(result, Err(ServiceError::TooManyRequests(1))));
+    }
+
+    #[test]
+    fn test_check_without_redis() {
+        let context = RateLimitRepository::try_new_context();
+        context.expect().times(1).returning(|_, _| None);
+
+        let mut rate_limit_service = RateLimitService {
+            rate_limit_repository: None,
+            ..get_rate_limit_service(Arc::new(Mutex::new(None)))
+        };
+
+        let now = Utc::now().naive_utc();
+        assert!(rate_limit_service.check("/posts", "127.0.0.1", now).is_ok());
+    }
+
+    #[test]
+    fn test_check_excluded_path() {
+        let mut rate_limit_service = RateLimitService {
+            rate_limit_repository: Some(RateLimitRepository::default()),
+            ..get_rate_limit_service(Arc::new(Mutex::new(None)))
+        };
+
+        let now = Utc::now().naive_utc();
+        assert!(rate_limit_service
+            .check("/health", "127.0.0.1", now)
+            .is_ok());
+        assert!(rate_limit_service
+            .check("/metrics/rdb_pool", "127.0.0.1", now)
+            .is_ok());
+    }
+}
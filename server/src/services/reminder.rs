@@ -0,0 +1,385 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use std::env;
+use std::thread;
+use std::time;
+
+use crate::models::error::ServiceError;
+use crate::models::post::*;
+use crate::models::user::*;
+#[cfg(not(test))]
+use crate::utils::email_util::EmailSender;
+use crate::utils::email_util::{EmailTemplate, Locale};
+use crate::utils::log_util;
+
+/// An interval between runs of the reminder job.
+const REMINDER_JOB_INTERVAL: time::Duration = time::Duration::from_secs(10 * 60);
+
+pub struct ReminderService {
+    user_repository: Option<UserRepository>,
+    post_repository: Option<PostRepository>,
+    email_sender: Option<EmailSender>,
+}
+
+impl ReminderService {
+    pub fn new() -> Self {
+        Self {
+            user_repository: None,
+            post_repository: None,
+            email_sender: None,
+        }
+    }
+
+    fn user_repository(&mut self, new_repository: Option<UserRepository>) -> &UserRepository {
+        match new_repository {
+            Some(_) => {
+                self.user_repository = new_repository;
+                self.user_repository.as_ref().unwrap()
+            }
+            None => self.user_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn post_repository(&mut self, new_repository: Option<PostRepository>) -> &PostRepository {
+        match new_repository {
+            Some(_) => {
+                self.post_repository = new_repository;
+                self.post_repository.as_ref().unwrap()
+            }
+            None => self.post_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn email_sender(&mut self, new_email_sender: Option<EmailSender>) -> &EmailSender {
+        match new_email_sender {
+            Some(_) => {
+                self.email_sender = new_email_sender;
+                self.email_sender.as_ref().unwrap()
+            }
+            None => self.email_sender.as_ref().unwrap(),
+        }
+    }
+
+    /// Finds users to be reminded at the time, with the day of the reminder in their timezone.
+    ///
+    /// A user who opted in is reminded once a day, after the hour of the reminder in the timezone
+    /// of the user. The user is not reminded if the user has already been reminded on the day,
+    /// or has written a post dated the day.
+    pub fn get_users_to_remind(
+        &mut self,
+        now: &NaiveDateTime,
+    ) -> Result<Vec<(User, NaiveDate)>, ServiceError> {
+        let user_list = {
+            let fallback_repository =
+                some_if_true!(self.user_repository.is_none() => UserRepository::new());
+            self.user_repository(fallback_repository)
+                .find_all_reminded()?
+        };
+
+        let mut users_to_remind = vec![];
+        for user in user_list {
+            let timezone = user
+                .timezone
+                .as_ref()
+                .and_then(|timezone| timezone.parse::<Tz>().ok())
+                .unwrap_or(Tz::UTC);
+            let local_now = timezone.from_utc_datetime(now);
+            let today = local_now.date_naive();
+
+            let is_before_hour = local_now.hour() < u32::from(user.reminder_hour);
+            let is_reminded = matches!(user.reminder_sent_on, Some(sent_on) if sent_on >= today);
+            if is_before_hour || is_reminded {
+                continue;
+            }
+
+            // A day in any timezone is within a day before and after the time.
+            let filter = PostFilter {
+                from: Some(*now - Duration::days(1)),
+                to: Some(*now + Duration::days(1)),
+                tags: vec![],
+                tag_mode: TagMode::Any,
+                category_id: None,
+                published_until: *now,
+            };
+            let date_list = {
+                let fallback_repository =
+                    some_if_true!(self.post_repository.is_none() => PostRepository::new());
+                self.post_repository(fallback_repository)
+                    .find_all_dates(user.id, &filter)?
+            };
+            let has_posted = date_list
+                .iter()
+                .any(|date| timezone.from_utc_datetime(date).date_naive() == today);
+
+            if !has_posted {
+                users_to_remind.push((user, today));
+            }
+        }
+
+        Ok(users_to_remind)
+    }
+
+    /// Sends reminder emails to users to be reminded at the time, and returns the number of them.
+    ///
+    /// The reminder is recorded before it is sent, so a user is not reminded twice a day even if
+    /// the job is restarted in the middle of a run, or multiple jobs run at the same time.
+    /// A failure of a user is logged, and the others are still reminded.
+    pub fn send_reminders(&mut self, now: &NaiveDateTime) -> Result<u64, ServiceError> {
+        let users_to_remind = self.get_users_to_remind(now)?;
+
+        let mut count = 0;
+        for (user, today) in users_to_remind {
+            let fallback_repository =
+                some_if_true!(self.user_repository.is_none() => UserRepository::new());
+            let is_recorded = match self
+                .user_repository(fallback_repository)
+                .update_reminder_sent_on(user.id, &today)
+            {
+                Ok(is_recorded) => is_recorded,
+                Err(error) => {
+                    log_util::log(&error);
+                    continue;
+                }
+            };
+
+            if is_recorded {
+                let fallback_email_sender =
+                    some_if_true!(self.email_sender.is_none() => EmailSender::new());
+                send_reminder_email(self.email_sender(fallback_email_sender), &user);
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+}
+
+impl Default for ReminderService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Enqueues the email reminding the user to write a post today, in the locale of the user.
+fn send_reminder_email(email_sender: &EmailSender, user: &User) {
+    let client_address = env::var("CLIENT_ADDRESS").expect("CLIENT_ADDRESS not found");
+    email_sender.send_templated(
+        &format!("{} <{}>", user.name, user.email),
+        EmailTemplate::Reminder,
+        Locale::from_tag(&user.locale),
+        &[
+            ("name", user.name.clone()),
+            ("link", format!("{}/post", client_address)),
+        ],
+    );
+}
+
+/// Starts the job sending reminder emails periodically in the background.
+pub fn start_reminder_job() {
+    thread::spawn(|| loop {
+        if let Err(error) = ReminderService::new().send_reminders(&Utc::now().naive_utc()) {
+            log_util::log(&error);
+        }
+        thread::sleep(REMINDER_JOB_INTERVAL);
+    });
+}
+
+#[cfg(test)]
+use crate::models::post::MockPostRepositoryTrait as PostRepository;
+#[cfg(test)]
+use crate::models::user::MockUserRepositoryTrait as UserRepository;
+#[cfg(test)]
+use crate::utils::email_util::{EmailSenderTrait, MockEmailSenderTrait as EmailSender};
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate::*;
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::models::post::MockPostRepositoryTrait;
+    use crate::models::user::MockUserRepositoryTrait;
+    use crate::utils::test_util;
+
+    impl ReminderService {
+        pub fn new_with_repository(
+            user_repository: UserRepository,
+            post_repository: PostRepository,
+        ) -> Self {
+            Self {
+                user_repository: Some(user_repository),
+                post_repository: Some(post_repository),
+                email_sender: None,
+            }
+        }
+    }
+
+    fn get_user(id: u64, timezone: Option<&str>, reminder_sent_on: Option<&str>) -> User {
+        User {
+            created_at: NaiveDateTime::from_str("2020-06-01T00:00:00").unwrap(),
+            timezone: timezone.map(String::from),
+            reminder_enabled: true,
+            reminder_sent_on: reminder_sent_on.map(|day| NaiveDate::from_str(day).unwrap()),
+            ..test_util::get_user(id, "park@email.com")
+        }
+    }
+
+    fn get_user_repository(users: Vec<(u64, Option<&'static str>)>) -> MockUserRepositoryTrait {
+        let mut mocked_user_repository = MockUserRepositoryTrait::new();
+        mocked_user_repository
+            .expect_find_all_reminded()
+            .times(1)
+            .returning(move || {
+                Ok(users
+                    .iter()
+                    .map(|(id, reminder_sent_on)| {
+                        get_user(*id, Some("Asia/Seoul"), *reminder_sent_on)
+                    })
+                    .collect())
+            });
+        mocked_user_repository
+    }
+
+    #[test]
+    fn test_get_users_to_remind_without_post_today() {
+        // It is 2020-06-10T21:30:00 in Asia/Seoul.
+        let now = NaiveDateTime::from_str("2020-06-10T12:30:00").unwrap();
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_dates()
+            .with(eq(1), always())
+            .times(1)
+            .returning(|_, _| Ok(vec![NaiveDateTime::from_str("2020-06-09T14:00:00").unwrap()]));
+
+        let mut reminder_service = ReminderService::new_with_repository(
+            get_user_repository(vec![(1, Some("2020-06-09"))]),
+            mocked_post_repository,
+        );
+        let users_to_remind = reminder_service.get_users_to_remind(&now).unwrap();
+
+        assert_eq!(users_to_remind.len(), 1);
+        assert_eq!(users_to_remind[0].0.id, 1);
+        assert_eq!(
+            users_to_remind[0].1,
+            NaiveDate::from_str("2020-06-10").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_users_to_remind_with_post_today() {
+        // It is 2020-06-10T21:30:00 in Asia/Seoul, and the post is dated 2020-06-10T00:30:00 there.
+        let now = NaiveDateTime::from_str("2020-06-10T12:30:00").unwrap();
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_dates()
+            .with(eq(1), always())
+            .times(1)
+            .returning(|_, _| Ok(vec![NaiveDateTime::from_str("2020-06-09T15:30:00").unwrap()]));
+
+        let mut reminder_service = ReminderService::new_with_repository(
+            get_user_repository(vec![(1, None)]),
+            mocked_post_repository,
+        );
+        let users_to_remind = reminder_service.get_users_to_remind(&now).unwrap();
+
+        assert!(users_to_remind.is_empty());
+    }
+
+    #[test]
+    fn test_get_users_to_remind_before_hour_or_after_reminded() {
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository.expect_find_all_dates().times(0);
+
+        // It is 2020-06-10T20:30:00 in Asia/Seoul, which is before the hour of the reminder.
+        let now = NaiveDateTime::from_str("2020-06-10T11:30:00").unwrap();
+        let mut reminder_service = ReminderService::new_with_repository(
+            get_user_repository(vec![(1, None)]),
+            mocked_post_repository,
+        );
+        assert!(reminder_service
+            .get_users_to_remind(&now)
+            .unwrap()
+            .is_empty());
+
+        // It is 2020-06-10T21:30:00 in Asia/Seoul, but the user has already been reminded.
+        let now = NaiveDateTime::from_str("2020-06-10T12:30:00").unwrap();
+        reminder_service.user_repository = Some(get_user_repository(vec![(1, Some("2020-06-10"))]));
+        assert!(reminder_service
+            .get_users_to_remind(&now)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_send_reminders_already_recorded() {
+        let now = NaiveDateTime::from_str("2020-06-10T12:30:00").unwrap();
+
+        let mut mocked_user_repository = get_user_repository(vec![(1, None)]);
+        mocked_user_repository
+            .expect_update_reminder_sent_on()
+            .with(eq(1), eq(NaiveDate::from_str("2020-06-10").unwrap()))
+            .times(1)
+            .returning(|_, _| Ok(false));
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_dates()
+            .times(1)
+            .returning(|_, _| Ok(vec![]));
+
+        let mut mocked_email_sender = EmailSender::default();
+        mocked_email_sender.expect_send_templated().times(0);
+
+        let mut reminder_service =
+            ReminderService::new_with_repository(mocked_user_repository, mocked_post_repository);
+        reminder_service.email_sender = Some(mocked_email_sender);
+        let count = reminder_service.send_reminders(&now).unwrap();
+
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_send_reminders_continuing_after_failure() {
+        env::set_var("CLIENT_ADDRESS", "https://darim.app");
+        let now = NaiveDateTime::from_str("2020-06-10T12:30:00").unwrap();
+
+        let mut mocked_user_repository = get_user_repository(vec![(1, None), (2, None)]);
+        mocked_user_repository
+            .expect_update_reminder_sent_on()
+            .with(eq(1), always())
+            .times(1)
+            .returning(|_, _| Err(ServiceError::QueryExecutionFailure));
+        mocked_user_repository
+            .expect_update_reminder_sent_on()
+            .with(eq(2), always())
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut mocked_post_repository = MockPostRepositoryTrait::new();
+        mocked_post_repository
+            .expect_find_all_dates()
+            .times(2)
+            .returning(|_, _| Ok(vec![]));
+
+        let mut mocked_email_sender = EmailSender::default();
+        mocked_email_sender
+            .expect_send_templated()
+            .withf(|to, template, locale, vars| {
+                to == "park <park@email.com>"
+                    && *template == EmailTemplate::Reminder
+                    && *locale == Locale::English
+                    && vars.contains(&("link", String::from("https://darim.app/post")))
+            })
+            .times(1)
+            .returning(|_, _, _, _| true);
+
+        let mut reminder_service =
+            ReminderService::new_with_repository(mocked_user_repository, mocked_post_repository);
+        reminder_service.email_sender = Some(mocked_email_sender);
+        let count = reminder_service.send_reminders(&now).unwrap();
+
+        assert_eq!(count, 1);
+    }
+}
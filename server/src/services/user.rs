@@ -1,17 +1,40 @@
-use reqwest::Client;
+use chrono::Utc;
+use chrono_tz::Tz;
+use reqwest::{Client, Url};
 use std::env;
 
 use crate::models::auth::*;
 use crate::models::error::{get_service_error, ServiceError};
+use crate::models::password_history::*;
+use crate::models::post::*;
 use crate::models::user::*;
-use crate::models::user_key::UserKeyRepository;
-use crate::utils::password_util;
+use crate::models::user_key::*;
+use crate::services::auth::AuthService;
+use crate::utils::email_util::Locale;
+use crate::utils::password_util::PasswordPolicy;
+use crate::utils::{email_util, env_util, password_util, token_util, totp_util};
+
+/// A maximum number of characters in the name of a user.
+const MAX_NAME_LENGTH: usize = 50;
+/// A maximum number of bytes in the avatar url of a user, which is the capacity of the column.
+const MAX_AVATAR_URL_LENGTH: usize = 255;
 
 pub struct UserService {
     sign_up_token_repository: Option<SignUpTokenRepository>,
     password_token_repository: Option<PasswordTokenRepository>,
     user_key_repository: Option<UserKeyRepository>,
     user_repository: Option<UserRepository>,
+    password_history_repository: Option<PasswordHistoryRepository>,
+    password_history_size: usize,
+    email_change_token_repository: Option<EmailChangeTokenRepository>,
+    email_change_token_ttl_seconds: usize,
+    post_repository: Option<PostRepository>,
+    account_deletion_token_repository: Option<AccountDeletionTokenRepository>,
+    account_deletion_token_ttl_seconds: usize,
+    known_device_repository: Option<KnownDeviceRepository>,
+    session_epoch_repository: Option<SessionEpochRepository>,
+    auth_service: Option<AuthService>,
+    tos_version: String,
 }
 
 impl UserService {
@@ -21,6 +44,23 @@ impl UserService {
             password_token_repository: None,
             user_key_repository: None,
             user_repository: None,
+            password_history_repository: None,
+            password_history_size: PasswordPolicy::from_env().history_size,
+            email_change_token_repository: None,
+            email_change_token_ttl_seconds: env_util::get_env_var_or(
+                "EMAIL_CHANGE_TOKEN_TTL_SECONDS",
+                86400,
+            ),
+            post_repository: None,
+            account_deletion_token_repository: None,
+            account_deletion_token_ttl_seconds: env_util::get_env_var_or(
+                "ACCOUNT_DELETION_TOKEN_TTL_SECONDS",
+                3600,
+            ),
+            known_device_repository: None,
+            session_epoch_repository: None,
+            auth_service: None,
+            tos_version: env_util::get_env_var_or("TOS_VERSION", String::from("1")),
         }
     }
 
@@ -37,6 +77,16 @@ impl UserService {
         }
     }
 
+    fn auth_service(&mut self, new_service: Option<AuthService>) -> &mut AuthService {
+        match new_service {
+            Some(_) => {
+                self.auth_service = new_service;
+                self.auth_service.as_mut().unwrap()
+            }
+            None => self.auth_service.as_mut().unwrap(),
+        }
+    }
+
     fn password_token_repository(
         &mut self,
         new_repository: Option<PasswordTokenRepository>,
@@ -63,6 +113,19 @@ impl UserService {
         }
     }
 
+    fn password_history_repository(
+        &mut self,
+        new_repository: Option<PasswordHistoryRepository>,
+    ) -> &PasswordHistoryRepository {
+        match new_repository {
+            Some(_) => {
+                self.password_history_repository = new_repository;
+                self.password_history_repository.as_ref().unwrap()
+            }
+            None => self.password_history_repository.as_ref().unwrap(),
+        }
+    }
+
     fn user_repository(&mut self, new_repository: Option<UserRepository>) -> &UserRepository {
         match new_repository {
             Some(_) => {
@@ -73,6 +136,68 @@ impl UserService {
         }
     }
 
+    fn email_change_token_repository(
+        &mut self,
+        new_repository: Option<EmailChangeTokenRepository>,
+    ) -> &mut EmailChangeTokenRepository {
+        match new_repository {
+            Some(_) => {
+                self.email_change_token_repository = new_repository;
+                self.email_change_token_repository.as_mut().unwrap()
+            }
+            None => self.email_change_token_repository.as_mut().unwrap(),
+        }
+    }
+
+    fn post_repository(&mut self, new_repository: Option<PostRepository>) -> &PostRepository {
+        match new_repository {
+            Some(_) => {
+                self.post_repository = new_repository;
+                self.post_repository.as_ref().unwrap()
+            }
+            None => self.post_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn account_deletion_token_repository(
+        &mut self,
+        new_repository: Option<AccountDeletionTokenRepository>,
+    ) -> &mut AccountDeletionTokenRepository {
+        match new_repository {
+            Some(_) => {
+                self.account_deletion_token_repository = new_repository;
+                self.account_deletion_token_repository.as_mut().unwrap()
+            }
+            None => self.account_deletion_token_repository.as_mut().unwrap(),
+        }
+    }
+
+    fn known_device_repository(
+        &mut self,
+        new_repository: Option<KnownDeviceRepository>,
+    ) -> &mut KnownDeviceRepository {
+        match new_repository {
+            Some(_) => {
+                self.known_device_repository = new_repository;
+                self.known_device_repository.as_mut().unwrap()
+            }
+            None => self.known_device_repository.as_mut().unwrap(),
+        }
+    }
+
+    fn session_epoch_repository(
+        &mut self,
+        new_repository: Option<SessionEpochRepository>,
+    ) -> &mut SessionEpochRepository {
+        match new_repository {
+            Some(_) => {
+                self.session_epoch_repository = new_repository;
+                self.session_epoch_repository.as_mut().unwrap()
+            }
+            None => self.session_epoch_repository.as_mut().unwrap(),
+        }
+    }
+
     /// Finds a user by id.
     pub fn get_one(&mut self, id: u64) -> Result<UserDTO, ServiceError> {
         let user = {
@@ -88,6 +213,46 @@ impl UserService {
             avatar_url: user.avatar_url,
             updated_at: user.updated_at,
             created_at: user.created_at,
+            last_login_at: user.last_login_at,
+            previous_login_at: user.previous_login_at,
+            timezone: user.timezone,
+            reminder_enabled: user.reminder_enabled,
+            reminder_hour: user.reminder_hour,
+            locale: user.locale,
+        })
+    }
+
+    /// Collects all personal data of a user to be exported: the profile, the public key, and all
+    /// posts including posts in the trash and posts scheduled to be published later.
+    /// The title and the content of the posts remain encrypted.
+    pub fn export_personal_data(&mut self, user_id: u64) -> Result<PersonalDataDTO, ServiceError> {
+        let profile = self.get_one(user_id)?;
+
+        let public_key = {
+            let fallback_repository =
+                some_if_true!(self.user_key_repository.is_none() => UserKeyRepository::new());
+            match self
+                .user_key_repository(fallback_repository)
+                .find_by_user_id(user_id)
+            {
+                Ok(user_key) => Some(user_key.public_key),
+                Err(ServiceError::NotFound(_)) => None,
+                Err(error) => return Err(error),
+            }
+        };
+
+        let mut posts = {
+            let fallback_repository =
+                some_if_true!(self.post_repository.is_none() => PostRepository::new());
+            self.post_repository(fallback_repository)
+                .find_all(user_id)?
+        };
+        posts.retain(|post| post.user_id == user_id);
+
+        Ok(PersonalDataDTO {
+            profile,
+            public_key,
+            posts,
         })
     }
 
@@ -109,6 +274,12 @@ impl UserService {
                     avatar_url: user.avatar_url.clone(),
                     created_at: user.created_at,
                     updated_at: user.updated_at,
+                    last_login_at: user.last_login_at,
+                    previous_login_at: user.previous_login_at,
+                    timezone: user.timezone.clone(),
+                    reminder_enabled: user.reminder_enabled,
+                    reminder_hour: user.reminder_hour,
+                    locale: user.locale.clone(),
                 }
             })
             .collect())
@@ -139,71 +310,104 @@ impl UserService {
         }
     }
 
-    /// Creates a new user.
+    /// Finds a sign up token by key, and returns it if the pin is correct.
+    /// It returns `ServiceError::NotFound` if the token has expired.
+    fn find_sign_up_token(
+        &mut self,
+        token_key: &str,
+        token_pin: &str,
+    ) -> Result<SignUpToken, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.sign_up_token_repository.is_none() => SignUpTokenRepository::new());
+
+        let serialized_token = self
+            .sign_up_token_repository(fallback_repository)
+            .find(token_key)?;
+
+        let deserialized_token: SignUpToken =
+            if let Ok(deserialized_token) = serde_json::from_str(&serialized_token) {
+                deserialized_token
+            } else {
+                return Err(get_service_error(ServiceError::InvalidFormat));
+            };
+
+        if token_pin == deserialized_token.pin {
+            Ok(deserialized_token)
+        } else {
+            Err(get_service_error(ServiceError::Unauthorized(None)))
+        }
+    }
+
+    /// Creates a new user, and returns the session of the user.
     ///
     /// 1. Finds serialized token by token key from arguments.
+    ///    It returns `ServiceError::Expired` if the token has expired or has already been used.
     /// 2. Deserializes the found token and compares pin from token and it from arguments.
-    /// 3. If the pins are equal, deletes the token from redis and creates a new user.
+    /// 3. If the pins are equal, creates a new user with the public key in a transaction.
+    ///    It returns `ServiceError::Conflict` if the email has been registered by another sign up
+    ///    in the meantime.
+    /// 4. Deletes the token from redis only if the user has been created.
+    /// 5. Starts the session of the new user as the login does.
+    ///
+    /// The user must accept the current version of the terms of service, which is recorded with
+    /// the user. It returns `ServiceError::InvalidArgument` otherwise.
     pub async fn create(
         &mut self,
         user_public_key: &str,
         token_key: &str,
         token_pin: &str,
         recaptcha_token: &str,
-    ) -> Result<bool, ServiceError> {
+        accepted_tos_version: &Option<String>,
+    ) -> Result<UserSession, ServiceError> {
         let has_recaptcha_verified = self.verify_recaptcha(&recaptcha_token).await;
         match has_recaptcha_verified {
             Ok(has_recaptcha_verified) => {
                 if has_recaptcha_verified {
-                    let token: SignUpToken = {
-                        let fallback_repository = some_if_true!(self.sign_up_token_repository.is_none() => SignUpTokenRepository::new());
-
-                        let serialized_token = self
-                            .sign_up_token_repository(fallback_repository)
-                            .find(token_key)?;
-
-                        let deserialized_token: SignUpToken = if let Ok(deserialized_token) =
-                            serde_json::from_str(&serialized_token)
-                        {
-                            deserialized_token
-                        } else {
-                            return Err(get_service_error(ServiceError::InvalidFormat));
-                        };
-
-                        if token_pin == deserialized_token.pin {
-                            let _ = self.sign_up_token_repository(None).delete(token_key)?;
-                            deserialized_token
-                        } else {
-                            return Err(get_service_error(ServiceError::Unauthorized));
-                        }
-                    };
-
-                    let user = {
-                        let fallback_repository =
-                            some_if_true!(self.user_repository.is_none() => UserRepository::new());
-                        let user_repository = self.user_repository(fallback_repository);
-
-                        user_repository.create(
-                            &token.name,
-                            &token.email,
-                            &token.password,
-                            &token.avatar_url,
-                        )?;
-
-                        user_repository.find_by_email(&token.email)?
-                    };
-
-                    let fallback_repository = some_if_true!(self.user_key_repository.is_none() => UserKeyRepository::new());
-                    self.user_key_repository(fallback_repository)
-                        .create(user.id, user_public_key)
+                    self.finish_sign_up(user_public_key, token_key, token_pin, accepted_tos_version)
                 } else {
-                    Err(ServiceError::Unauthorized)
+                    Err(ServiceError::Unauthorized(None))
                 }
             }
             Err(error) => Err(error),
         }
     }
 
+    /// Creates a new user and the public key of the sign up token, deletes the token only if both
+    /// of them have been created, and starts the session of the user.
+    fn finish_sign_up(
+        &mut self,
+        user_public_key: &str,
+        token_key: &str,
+        token_pin: &str,
+        accepted_tos_version: &Option<String>,
+    ) -> Result<UserSession, ServiceError> {
+        if accepted_tos_version.as_deref() != Some(self.tos_version.as_str()) {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        let token = match self.find_sign_up_token(token_key, token_pin) {
+            Ok(token) => token,
+            Err(ServiceError::NotFound(_)) => return Err(get_service_error(ServiceError::Expired)),
+            Err(error) => return Err(error),
+        };
+
+        let fallback_repository =
+            some_if_true!(self.user_repository.is_none() => UserRepository::new());
+        let tos_version = self.tos_version.clone();
+        let user_id = self.user_repository(fallback_repository).create_with_key(
+            &token,
+            user_public_key,
+            &tos_version,
+        )?;
+
+        self.sign_up_token_repository(None).delete(token_key)?;
+
+        let user = self.user_repository(None).find_by_id(user_id)?;
+        let fallback_service = some_if_true!(self.auth_service.is_none() => AuthService::new());
+        self.auth_service(fallback_service)
+            .start_user_session(user, &Utc::now().naive_utc(), None)
+    }
+
     /// Deletes a user.
     pub fn delete(&mut self, id: u64) -> Result<bool, ServiceError> {
         let fallback_repository =
@@ -211,38 +415,475 @@ impl UserService {
         self.user_repository(fallback_repository).delete(id)
     }
 
+    /// Requests to delete the account of the user, and sends the token confirming the deletion to
+    /// the email of the user.
+    ///
+    /// The user must be re-authenticated by either the password or the TOTP code, so a hijacked
+    /// session alone can't delete the account. A user who has neither of them, such as a user
+    /// signed up with OAuth, is authenticated by the email confirmation only.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - An id of the user
+    /// * `password` - A password of the user
+    /// * `totp_code` - A TOTP code of the user, if the user has enabled TOTP
+    pub fn request_account_deletion(
+        &mut self,
+        user_id: u64,
+        password: &Option<String>,
+        totp_code: &Option<String>,
+    ) -> Result<bool, ServiceError> {
+        let user = {
+            let fallback_repository =
+                some_if_true!(self.user_repository.is_none() => UserRepository::new());
+            self.user_repository(fallback_repository)
+                .find_by_id(user_id)?
+        };
+
+        Self::reauthenticate(&user, password, totp_code)?;
+
+        let token = token_util::get_random_string(32);
+        {
+            let ttl_seconds = self.account_deletion_token_ttl_seconds;
+            let fallback_repository = some_if_true!(self.account_deletion_token_repository.is_none() => AccountDeletionTokenRepository::new(user_id));
+            self.account_deletion_token_repository(fallback_repository)
+                .save(&token, ttl_seconds)?;
+        }
+
+        let client_address = env::var("CLIENT_ADDRESS").expect("CLIENT_ADDRESS not found");
+        let email_content = format!(
+            "Hello {} :)<br/><br/>\
+            Please visit the link to delete your Darim account:<br/><br/>\
+            <a href=\"{}/account_deletion/{}\">{}/account_deletion/{}</a><br/><br/>\
+            All of your posts will be deleted permanently, and can't be recovered.<br/>\
+            The link expires in {} minutes. If you didn't request it, please change your password.",
+            user.name,
+            client_address,
+            token,
+            client_address,
+            token,
+            self.account_deletion_token_ttl_seconds / 60,
+        );
+
+        email_util::send_email_async(
+            &format!("{} <{}>", user.name, user.email),
+            &String::from("Please confirm deleting your account 🗑"),
+            &email_content,
+        );
+
+        Ok(true)
+    }
+
+    /// Deletes the account of the user if the token confirms the deletion.
+    /// It returns `ServiceError::Expired` if the token has expired or has already been consumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - An id of the user
+    /// * `token` - A token sent by `request_account_deletion`
+    pub fn confirm_account_deletion(
+        &mut self,
+        user_id: u64,
+        token: &str,
+    ) -> Result<bool, ServiceError> {
+        let fallback_repository = some_if_true!(self.account_deletion_token_repository.is_none() => AccountDeletionTokenRepository::new(user_id));
+        let saved_token = match self
+            .account_deletion_token_repository(fallback_repository)
+            .find()
+        {
+            Ok(saved_token) => saved_token,
+            Err(ServiceError::NotFound(_)) => return Err(get_service_error(ServiceError::Expired)),
+            Err(error) => return Err(error),
+        };
+
+        if saved_token != token {
+            return Err(get_service_error(ServiceError::Unauthorized(None)));
+        }
+
+        self.delete_account(user_id)
+    }
+
+    /// Deletes the user with all data of the user.
+    ///
+    /// 1. Deletes the user, the posts, the public key, and the backup codes in a transaction,
+    ///    so nothing is deleted if any of them fails.
+    /// 2. Deletes the tokens and the known devices of the user in redis, and invalidates all
+    ///    sessions of the user.
+    pub fn delete_account(&mut self, user_id: u64) -> Result<bool, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.user_repository.is_none() => UserRepository::new());
+        self.user_repository(fallback_repository)
+            .delete_account(user_id)?;
+
+        let fallback_repository = some_if_true!(self.account_deletion_token_repository.is_none() => AccountDeletionTokenRepository::new(user_id));
+        self.account_deletion_token_repository(fallback_repository)
+            .delete()?;
+        let fallback_repository = some_if_true!(self.password_token_repository.is_none() => PasswordTokenRepository::new(user_id));
+        self.password_token_repository(fallback_repository)
+            .delete()?;
+        let fallback_repository = some_if_true!(self.known_device_repository.is_none() => KnownDeviceRepository::new(user_id));
+        self.known_device_repository(fallback_repository).delete()?;
+        let fallback_repository = some_if_true!(self.session_epoch_repository.is_none() => SessionEpochRepository::new(user_id));
+        self.session_epoch_repository(fallback_repository)
+            .increase()?;
+
+        Ok(true)
+    }
+
+    /// Deactivates the user instead of deleting the account, and invalidates all sessions of the
+    /// user. The deactivated user can't log in nor is reminded, but the posts are kept.
+    pub fn deactivate(&mut self, user_id: u64) -> Result<bool, ServiceError> {
+        self.update_status(user_id, UserStatus::Deactivated)?;
+
+        let fallback_repository = some_if_true!(self.session_epoch_repository.is_none() => SessionEpochRepository::new(user_id));
+        self.session_epoch_repository(fallback_repository)
+            .increase()?;
+
+        Ok(true)
+    }
+
+    /// Reactivates the deactivated user, who can log in again.
+    pub fn reactivate(&mut self, user_id: u64) -> Result<bool, ServiceError> {
+        self.update_status(user_id, UserStatus::Active)
+    }
+
+    /// Updates the status of the user, which is `true` even if the user already has the status.
+    /// Returns `ServiceError::NotFound` if the user doesn't exist.
+    fn update_status(&mut self, user_id: u64, status: UserStatus) -> Result<bool, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.user_repository.is_none() => UserRepository::new());
+        let user_repository = self.user_repository(fallback_repository);
+
+        let user = user_repository.find_by_id(user_id)?;
+        if user.status != status {
+            user_repository.update_status(user_id, status)?;
+        }
+
+        Ok(true)
+    }
+
+    /// Records that the user has accepted the terms of service, such as after the version is bumped.
+    /// Returns `ServiceError::InvalidArgument` if the version is not the current one.
+    pub fn accept_tos(&mut self, user_id: u64, version: &str) -> Result<bool, ServiceError> {
+        if version != self.tos_version {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        let fallback_repository =
+            some_if_true!(self.user_repository.is_none() => UserRepository::new());
+        let user_repository = self.user_repository(fallback_repository);
+
+        user_repository.find_by_id(user_id)?;
+        user_repository.update_tos_version_accepted(user_id, version)?;
+
+        Ok(true)
+    }
+
     /// Updates a new user.
+    /// The timezone must be a name of the IANA time zone database, such as `Asia/Seoul`.
+    /// The password is not updated here, but by `AuthService::change_password` checking the
+    /// current password.
     pub fn update(
         &mut self,
         id: u64,
         name: &Option<String>,
-        password: &Option<String>,
         avatar_url: &Option<String>,
+        timezone: &Option<String>,
     ) -> Result<bool, ServiceError> {
-        if name.is_none() && password.is_none() && avatar_url.is_none() {
+        if name.is_none() && avatar_url.is_none() && timezone.is_none() {
             return Err(get_service_error(ServiceError::InvalidArgument));
         }
 
-        if let (Some(name), Some(password), Some(avatar_url)) = (name, password, avatar_url) {
-            if name.trim().is_empty() || password.trim().is_empty() || avatar_url.trim().is_empty()
-            {
+        if let Some(timezone) = timezone {
+            if timezone.parse::<Tz>().is_err() {
                 return Err(get_service_error(ServiceError::InvalidArgument));
             }
         }
 
-        let hashed_password = if let Some(password) = password {
-            Some(password_util::get_hashed_password(&password))
-        } else {
-            None
+        if let (Some(name), Some(avatar_url)) = (name, avatar_url) {
+            if name.trim().is_empty() || avatar_url.trim().is_empty() {
+                return Err(get_service_error(ServiceError::InvalidArgument));
+            }
+        }
+
+        let fallback_repository =
+            some_if_true!(self.user_repository.is_none() => UserRepository::new());
+        self.user_repository(fallback_repository)
+            .update(id, name, &None, avatar_url, timezone)
+    }
+
+    /// Updates the name and the avatar url of a user, and returns the updated profile.
+    /// A field is not changed if it is `None`.
+    ///
+    /// Returns `ServiceError::InvalidArgument` if no field is given, the name is empty or longer
+    /// than 50 characters, or the avatar url is not an absolute http(s) url.
+    pub fn update_profile(
+        &mut self,
+        id: u64,
+        name: &Option<String>,
+        avatar_url: &Option<String>,
+    ) -> Result<UserProfileDTO, ServiceError> {
+        if name.is_none() && avatar_url.is_none() {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        let name = name.as_ref().map(|name| name.trim().to_string());
+        if let Some(name) = &name {
+            if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+                return Err(get_service_error(ServiceError::InvalidArgument));
+            }
+        }
+
+        if let Some(avatar_url) = avatar_url {
+            if !Self::is_http_url(avatar_url) {
+                return Err(get_service_error(ServiceError::InvalidArgument));
+            }
+        }
+
+        let user = {
+            let fallback_repository =
+                some_if_true!(self.user_repository.is_none() => UserRepository::new());
+            let user_repository = self.user_repository(fallback_repository);
+            user_repository.update(id, &name, &None, avatar_url, &None)?;
+            user_repository.find_by_id(id)?
         };
 
+        Ok(UserProfileDTO {
+            name: user.name,
+            avatar_url: user.avatar_url,
+        })
+    }
+
+    /// Returns whether the url is an absolute http(s) url, which fits in the avatar url column.
+    fn is_http_url(url: &str) -> bool {
+        if url.len() > MAX_AVATAR_URL_LENGTH {
+            return false;
+        }
+
+        match Url::parse(url) {
+            Ok(url) => (url.scheme() == "http" || url.scheme() == "https") && url.has_host(),
+            Err(_) => false,
+        }
+    }
+
+    /// Updates whether the user is reminded daily, and the hour to be reminded in the timezone of
+    /// the user. Returns `ServiceError::InvalidArgument` if the hour is not between 0 and 23.
+    pub fn update_reminder(
+        &mut self,
+        id: u64,
+        reminder_enabled: bool,
+        reminder_hour: u8,
+    ) -> Result<bool, ServiceError> {
+        if reminder_hour > 23 {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        let fallback_repository =
+            some_if_true!(self.user_repository.is_none() => UserRepository::new());
+        self.user_repository(fallback_repository).update_reminder(
+            id,
+            reminder_enabled,
+            reminder_hour,
+        )
+    }
+
+    /// Updates the locale of the user, in which emails are written.
+    /// Returns `ServiceError::InvalidArgument` if the locale is not supported.
+    /// The locale is removed if it is `None`, so that emails are written in English.
+    pub fn update_locale(
+        &mut self,
+        id: u64,
+        locale: &Option<String>,
+    ) -> Result<bool, ServiceError> {
+        if let Some(locale) = locale {
+            if Locale::parse(locale).is_none() {
+                return Err(get_service_error(ServiceError::InvalidArgument));
+            }
+        }
+
         let fallback_repository =
             some_if_true!(self.user_repository.is_none() => UserRepository::new());
         self.user_repository(fallback_repository)
-            .update(id, name, &hashed_password, avatar_url)
+            .update_locale(id, locale)
+    }
+
+    /// Re-authenticates the user by either the password or the TOTP code before a sensitive
+    /// change of the account. A user who has neither of them, such as a user signed up with
+    /// OAuth, passes without them. It returns `ServiceError::Unauthorized` if neither matches.
+    fn reauthenticate(
+        user: &User,
+        password: &Option<String>,
+        totp_code: &Option<String>,
+    ) -> Result<(), ServiceError> {
+        let is_authenticated_by_password = match password {
+            Some(password) if user.has_password => {
+                password_util::check_password(password, &user.password)
+            }
+            _ => false,
+        };
+        let is_authenticated_by_totp = match (&user.totp_secret, totp_code) {
+            (Some(totp_secret), Some(totp_code)) if user.totp_enabled => {
+                totp_util::check_code(totp_secret, totp_code)
+            }
+            _ => false,
+        };
+        let has_credential = user.has_password || user.totp_enabled;
+        if has_credential && !is_authenticated_by_password && !is_authenticated_by_totp {
+            return Err(get_service_error(ServiceError::Unauthorized(None)));
+        }
+
+        Ok(())
     }
 
-    // Reset the password.
+    /// Requests to change the email of the user, and sends the confirmation link to the new email.
+    ///
+    /// The user is re-authenticated by either the password or the TOTP code as in
+    /// `request_account_deletion`, and the current email is notified of the request so that the
+    /// owner can notice a hijacked session. The change is pending until it is confirmed by
+    /// `confirm_email_change`, so the current email is still used to log in until then.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - An id of the user
+    /// * `new_email` - A new email of the user
+    /// * `password` - A password of the user
+    /// * `totp_code` - A TOTP code of the user
+    pub fn request_email_change(
+        &mut self,
+        user_id: u64,
+        new_email: &str,
+        password: &Option<String>,
+        totp_code: &Option<String>,
+    ) -> Result<bool, ServiceError> {
+        let email = email_util::normalize(new_email);
+        if email.is_empty() || !email.contains('@') {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        let user = {
+            let fallback_repository =
+                some_if_true!(self.user_repository.is_none() => UserRepository::new());
+            self.user_repository(fallback_repository)
+                .find_by_id(user_id)?
+        };
+        Self::reauthenticate(&user, password, totp_code)?;
+        self.check_email_available(&email)?;
+
+        let token = token_util::get_random_string(32);
+        let serialized_token = serde_json::to_string(&EmailChangeToken {
+            user_id,
+            email: email.clone(),
+        });
+        let serialized_token = if let Ok(serialized_token) = serialized_token {
+            serialized_token
+        } else {
+            return Err(get_service_error(ServiceError::InvalidFormat));
+        };
+
+        {
+            let ttl_seconds = self.email_change_token_ttl_seconds;
+            let fallback_repository = some_if_true!(self.email_change_token_repository.is_none() => EmailChangeTokenRepository::new(&token));
+            self.email_change_token_repository(fallback_repository)
+                .save(&serialized_token, ttl_seconds)?;
+        }
+
+        let client_address = env::var("CLIENT_ADDRESS").expect("CLIENT_ADDRESS not found");
+        let email_content = format!(
+            "Hello {} :)<br/><br/>\
+            Please visit the link to change the email of your Darim account to this address:<br/><br/>\
+            <a href=\"{}/email_change/{}\">{}/email_change/{}</a><br/><br/>\
+            The link expires in {} hours. If you didn't request it, please ignore this email.",
+            user.name,
+            client_address,
+            token,
+            client_address,
+            token,
+            self.email_change_token_ttl_seconds / 3600,
+        );
+
+        email_util::send_email_async(
+            &format!("{} <{}>", user.name, email),
+            &String::from("Please confirm your new email 📮"),
+            &email_content,
+        );
+
+        let notice_content = format!(
+            "Hello {} :)<br/><br/>\
+            A change of the email of your Darim account to {} has been requested.<br/>\
+            This address is still used to log in until the change is confirmed from the new one.\
+            <br/><br/>If you didn't request it, please change your password.",
+            user.name, email,
+        );
+
+        email_util::send_email_async(
+            &format!("{} <{}>", user.name, user.email),
+            &String::from("Your email is being changed 📮"),
+            &notice_content,
+        );
+
+        Ok(true)
+    }
+
+    /// Confirms the pending email change of the token, and applies it to the user.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - A token in the confirmation link
+    pub fn confirm_email_change(&mut self, token: &str) -> Result<bool, ServiceError> {
+        let fallback_repository = some_if_true!(self.email_change_token_repository.is_none() => EmailChangeTokenRepository::new(token));
+        let email_change_token: EmailChangeToken = {
+            let serialized_token = match self
+                .email_change_token_repository(fallback_repository)
+                .find()
+            {
+                Ok(serialized_token) => serialized_token,
+                Err(ServiceError::NotFound(_)) => {
+                    return Err(get_service_error(ServiceError::Expired))
+                }
+                Err(error) => return Err(error),
+            };
+            if let Ok(deserialized_token) = serde_json::from_str(&serialized_token) {
+                deserialized_token
+            } else {
+                return Err(get_service_error(ServiceError::InvalidFormat));
+            }
+        };
+
+        // The email may have been taken by another account while the change was pending.
+        self.check_email_available(&email_change_token.email)?;
+
+        if !self.email_change_token_repository(None).delete()? {
+            return Err(get_service_error(ServiceError::Expired));
+        }
+
+        self.user_repository(None)
+            .update_email(email_change_token.user_id, &email_change_token.email)
+    }
+
+    /// Returns `ServiceError::Conflict` if the email is already used by an account.
+    fn check_email_available(&mut self, email: &str) -> Result<(), ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.user_repository.is_none() => UserRepository::new());
+        match self
+            .user_repository(fallback_repository)
+            .find_by_email(email)
+        {
+            Ok(_) => Err(get_service_error(ServiceError::Conflict(email.to_string()))),
+            Err(ServiceError::NotFound(_)) => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Resets the password.
+    ///
+    /// 1. Finds the password token of the user by email from arguments.
+    ///    It returns `ServiceError::Expired` if the token has expired or has already been consumed.
+    /// 2. Compares id and temporary password from the token and it from the arguments.
+    /// 3. Checks whether the new password is neither the current password nor one of the recent passwords.
+    ///    It returns `ServiceError::InvalidArgument` if the password is reused.
+    /// 4. If they are equal, deletes the token so it can't be reused, updates the password, pushes the previous
+    ///    password into the password history, and clears the lock of the user.
+    /// 5. Invalidates all sessions of the user, so that a session held by someone else can't be refreshed.
     pub fn reset_password(
         &mut self,
         email: &str,
@@ -250,6 +891,8 @@ impl UserService {
         temporary_password: &str,
         new_password: &str,
     ) -> Result<bool, ServiceError> {
+        let email = &email_util::normalize(email);
+
         let fallback_repository =
             some_if_true!(self.user_repository.is_none() => UserRepository::new());
         let user = self
@@ -258,7 +901,14 @@ impl UserService {
 
         let fallback_repository = some_if_true!(self.password_token_repository.is_none() => PasswordTokenRepository::new(user.id));
         let token: PasswordToken = {
-            let serialized_token = self.password_token_repository(fallback_repository).find()?;
+            let serialized_token = match self.password_token_repository(fallback_repository).find()
+            {
+                Ok(serialized_token) => serialized_token,
+                Err(ServiceError::NotFound(_)) => {
+                    return Err(get_service_error(ServiceError::Expired))
+                }
+                Err(error) => return Err(error),
+            };
             if let Ok(deserialized_token) = serde_json::from_str(&serialized_token) {
                 deserialized_token
             } else {
@@ -267,10 +917,45 @@ impl UserService {
         };
 
         if token.id == token_id && token.password == temporary_password {
+            let history_size = self.password_history_size;
+            if history_size > 0 {
+                let mut used_passwords = vec![user.password.clone()];
+                let fallback_repository = some_if_true!(self.password_history_repository.is_none() => PasswordHistoryRepository::new());
+                used_passwords.extend(
+                    self.password_history_repository(fallback_repository)
+                        .find_recent_by_user_id(user.id, history_size)?
+                        .into_iter()
+                        .map(|password_history| password_history.hashed_password),
+                );
+                if password_util::is_reused(new_password, &used_passwords) {
+                    return Err(get_service_error(ServiceError::InvalidArgument));
+                }
+            }
+
+            self.password_token_repository(None).delete()?;
+
             let hashed_password = password_util::get_hashed_password(new_password);
-            self.user_repository(None)
-                .update(user.id, &None, &Some(hashed_password), &None)?;
-            self.password_token_repository(None).delete()
+            self.user_repository(None).update(
+                user.id,
+                &None,
+                &Some(hashed_password),
+                &None,
+                &None,
+            )?;
+            if history_size > 0 && user.has_password {
+                let password_history_repository = self.password_history_repository(None);
+                password_history_repository.create(user.id, &user.password)?;
+                password_history_repository.delete_all_except_recent(user.id, history_size)?;
+            }
+            if user.locked_until.is_some() {
+                self.user_repository(None)
+                    .update_locked_until(user.id, &None)?;
+            }
+
+            let fallback_repository = some_if_true!(self.session_epoch_repository.is_none() => SessionEpochRepository::new(user.id));
+            self.session_epoch_repository(fallback_repository)
+                .increase()?;
+            Ok(true)
         } else {
             Err(get_service_error(ServiceError::UserNotFound(
                 email.to_string(),
@@ -285,9 +970,36 @@ impl Default for UserService {
     }
 }
 
+#[cfg(test)]
+use crate::models::auth::{
+    MockAccountDeletionTokenRepositoryTrait as AccountDeletionTokenRepository,
+    MockActiveSessionRepositoryTrait as ActiveSessionRepository,
+    MockEmailChangeTokenRepositoryTrait as EmailChangeTokenRepository,
+    MockKnownDeviceRepositoryTrait as KnownDeviceRepository,
+    MockPasswordTokenRepositoryTrait as PasswordTokenRepository,
+    MockSessionEpochRepositoryTrait as SessionEpochRepository,
+    MockSignUpTokenRepositoryTrait as SignUpTokenRepository,
+};
+#[cfg(test)]
+use crate::models::password_history::MockPasswordHistoryRepositoryTrait as PasswordHistoryRepository;
+#[cfg(test)]
+use crate::models::post::MockPostRepositoryTrait as PostRepository;
+#[cfg(test)]
+use crate::models::user::MockUserRepositoryTrait as UserRepository;
+#[cfg(test)]
+use crate::models::user_key::MockUserKeyRepositoryTrait as UserKeyRepository;
+
 #[cfg(test)]
 mod tests {
+    use chrono::{Duration, NaiveDateTime, Utc};
+    use mockall::predicate::*;
+    use std::sync::{Arc, Mutex};
+
     use super::*;
+    use crate::models::error::UnauthorizedReason;
+    use crate::utils::test_util::{
+        self, get_in_memory_password_history_repository, get_session_epoch_repository, get_user_key,
+    };
 
     impl UserService {
         pub fn new_with_repository(
@@ -301,7 +1013,1134 @@ mod tests {
                 password_token_repository: Some(password_token_repository),
                 user_key_repository: Some(user_key_repository),
                 user_repository: Some(user_repository),
+                password_history_repository: Some(get_in_memory_password_history_repository(
+                    Arc::new(Mutex::new(vec![])),
+                )),
+                password_history_size: 5,
+                email_change_token_repository: None,
+                email_change_token_ttl_seconds: 86400,
+                post_repository: None,
+                account_deletion_token_repository: None,
+                account_deletion_token_ttl_seconds: 3600,
+                known_device_repository: None,
+                session_epoch_repository: None,
+                auth_service: None,
+                tos_version: String::from("1"),
             }
         }
     }
+
+    /// Returns the user whose password is `password`.
+    fn get_user(id: u64, email: &str) -> User {
+        User {
+            password: password_util::get_hashed_password("password"),
+            ..test_util::get_user(id, email)
+        }
+    }
+
+    /// Returns a session epoch repository which keeps the epoch in the given value.
+    fn get_shared_session_epoch_repository(epoch: Arc<Mutex<u64>>) -> SessionEpochRepository {
+        let mut mocked_session_epoch_repository = SessionEpochRepository::default();
+        let found_epoch = epoch.clone();
+        mocked_session_epoch_repository
+            .expect_find()
+            .returning(move || Ok(*found_epoch.lock().unwrap()));
+        mocked_session_epoch_repository
+            .expect_increase()
+            .returning(move || {
+                let mut epoch = epoch.lock().unwrap();
+                *epoch += 1;
+                Ok(*epoch)
+            });
+        mocked_session_epoch_repository
+    }
+
+    #[test]
+    fn test_reset_password_clears_lock() {
+        let email = "park@email.com";
+        let user_id = 1;
+        let token = PasswordToken {
+            id: String::from("71I3Qz9u"),
+            password: String::from("P9d82Jc5"),
+        };
+        let serialized_token = serde_json::to_string(&token).unwrap();
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq(email))
+            .times(1)
+            .returning(move |email| {
+                let mut user = get_user(user_id, email);
+                user.locked_until = Some(Utc::now().naive_utc() + Duration::minutes(10));
+                Ok(user)
+            });
+        mocked_user_repository
+            .expect_update()
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(true));
+        mocked_user_repository
+            .expect_update_locked_until()
+            .with(eq(user_id), eq(None::<NaiveDateTime>))
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut mocked_password_token_repository = PasswordTokenRepository::default();
+        mocked_password_token_repository
+            .expect_find()
+            .times(1)
+            .returning(move || Ok(serialized_token.clone()));
+        mocked_password_token_repository
+            .expect_delete()
+            .times(1)
+            .returning(|| Ok(true));
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            mocked_password_token_repository,
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+        user_service.session_epoch_repository =
+            Some(get_shared_session_epoch_repository(Arc::new(Mutex::new(0))));
+
+        let result = user_service.reset_password(email, &token.id, &token.password, "new password");
+        assert!(result.unwrap());
+    }
+
+    #[test]
+    fn test_reset_password_to_previous_password() {
+        let email = "park@email.com";
+        let token = PasswordToken {
+            id: String::from("71I3Qz9u"),
+            password: String::from("P9d82Jc5"),
+        };
+        let serialized_token = serde_json::to_string(&token).unwrap();
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .returning(|email| Ok(get_user(1, email)));
+        mocked_user_repository.expect_update().times(0);
+
+        let mut mocked_password_token_repository = PasswordTokenRepository::default();
+        mocked_password_token_repository
+            .expect_find()
+            .returning(move || Ok(serialized_token.clone()));
+        mocked_password_token_repository.expect_delete().times(0);
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            mocked_password_token_repository,
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+        user_service.password_history_repository =
+            Some(get_in_memory_password_history_repository(Arc::new(
+                Mutex::new(vec![password_util::get_hashed_password("old password")]),
+            )));
+
+        for reused_password in &["password", "old password"] {
+            let result =
+                user_service.reset_password(email, &token.id, &token.password, reused_password);
+            assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+        }
+    }
+
+    #[test]
+    fn test_refresh_session_after_reset_password() {
+        let email = "park@email.com";
+        let user_id = 1;
+        let token = PasswordToken {
+            id: String::from("71I3Qz9u"),
+            password: String::from("P9d82Jc5"),
+        };
+        let serialized_token = serde_json::to_string(&token).unwrap();
+        let epoch = Arc::new(Mutex::new(0));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .returning(move |email| Ok(get_user(user_id, email)));
+        mocked_user_repository
+            .expect_update()
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(true));
+
+        let mut mocked_password_token_repository = PasswordTokenRepository::default();
+        mocked_password_token_repository
+            .expect_find()
+            .returning(move || Ok(serialized_token.clone()));
+        mocked_password_token_repository
+            .expect_delete()
+            .returning(|| Ok(true));
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            mocked_password_token_repository,
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+        user_service.session_epoch_repository =
+            Some(get_shared_session_epoch_repository(epoch.clone()));
+
+        let mut auth_service = AuthService::builder()
+            .session_epoch_repository(get_shared_session_epoch_repository(epoch))
+            .build();
+
+        // A session issued before the reset, which may be held by an attacker.
+        let user_session_epoch = 0;
+        let user_session_started_at = Utc::now().naive_utc();
+        assert!(auth_service
+            .validate_session_epoch(user_id, user_session_epoch)
+            .unwrap());
+
+        let result = user_service.reset_password(email, &token.id, &token.password, "new password");
+        assert!(result.unwrap());
+
+        let result = auth_service.refresh_user_session(
+            user_id,
+            user_session_epoch,
+            "session",
+            &user_session_started_at,
+            &(user_session_started_at + Duration::days(7)),
+            &user_session_started_at,
+        );
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    /// Returns a user repository of the user, whose status is kept in the given value.
+    fn get_user_repository_with_shared_status(
+        user_id: u64,
+        status: Arc<Mutex<UserStatus>>,
+    ) -> UserRepository {
+        let mut mocked_user_repository = UserRepository::new();
+        let found_status = status.clone();
+        mocked_user_repository
+            .expect_find_by_id()
+            .returning(move |id| {
+                Ok(User {
+                    status: *found_status.lock().unwrap(),
+                    ..get_user(id, "park@email.com")
+                })
+            });
+        let found_status = status.clone();
+        mocked_user_repository
+            .expect_find_by_email()
+            .returning(move |email| {
+                Ok(User {
+                    status: *found_status.lock().unwrap(),
+                    ..get_user(user_id, email)
+                })
+            });
+        mocked_user_repository
+            .expect_update_status()
+            .returning(move |_, new_status| {
+                *status.lock().unwrap() = new_status;
+                Ok(true)
+            });
+        mocked_user_repository
+            .expect_update_last_login_at()
+            .returning(|_, _| Ok(true));
+        mocked_user_repository
+    }
+
+    #[test]
+    fn test_login_after_deactivate_and_reactivate() {
+        let email = "park@email.com";
+        let user_id = 1;
+        let status = Arc::new(Mutex::new(UserStatus::Active));
+        let epoch = Arc::new(Mutex::new(0));
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            get_user_repository_with_shared_status(user_id, status.clone()),
+        );
+        user_service.session_epoch_repository =
+            Some(get_shared_session_epoch_repository(epoch.clone()));
+
+        let mut mocked_login_attempt_repository = MockLoginAttemptRepositoryTrait::default();
+        mocked_login_attempt_repository
+            .expect_find()
+            .returning(|| Ok(0));
+        mocked_login_attempt_repository
+            .expect_delete()
+            .returning(|| Ok(true));
+        let mut mocked_active_session_repository = MockActiveSessionRepositoryTrait::default();
+        mocked_active_session_repository
+            .expect_save()
+            .returning(|_, _| Ok(true));
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .returning(|user_id| Ok(get_user_key(user_id)));
+        let mut auth_service = AuthService::builder()
+            .login_attempt_repository(mocked_login_attempt_repository)
+            .active_session_repository(mocked_active_session_repository)
+            .session_epoch_repository(get_shared_session_epoch_repository(epoch.clone()))
+            .user_key_repository(mocked_user_key_repository)
+            .user_repository(get_user_repository_with_shared_status(user_id, status))
+            .build();
+
+        assert!(user_service.deactivate(user_id).unwrap());
+        assert_eq!(*epoch.lock().unwrap(), 1);
+        let result = auth_service.login(email, "password", None, None, None);
+        assert!(matches!(
+            result,
+            Err(ServiceError::Unauthorized(Some(
+                UnauthorizedReason::AccountDeactivated
+            )))
+        ));
+
+        assert!(user_service.reactivate(user_id).unwrap());
+        let user_session = auth_service
+            .login(email, "password", None, None, None)
+            .unwrap();
+        assert_eq!(user_session.user_id, user_id);
+    }
+
+    #[test]
+    fn test_find_expired_sign_up_token() {
+        let token_key = "a1lam9cBko";
+
+        let mut mocked_sign_up_token_repository = SignUpTokenRepository::new();
+        mocked_sign_up_token_repository
+            .expect_find()
+            .with(eq(token_key))
+            .times(1)
+            .returning(|key| Err(ServiceError::NotFound(key.to_string())));
+        mocked_sign_up_token_repository.expect_delete().times(0);
+
+        let mut user_service = UserService::new_with_repository(
+            mocked_sign_up_token_repository,
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            UserRepository::new(),
+        );
+
+        let result = user_service.find_sign_up_token(token_key, "P9d82Jc5");
+        assert!(matches!(result, Err(ServiceError::NotFound(_))));
+    }
+
+    /// Returns a sign up token repository finding the token of the key once.
+    fn get_sign_up_token_repository(
+        token_key: &'static str,
+        token_pin: &'static str,
+    ) -> SignUpTokenRepository {
+        let mut mocked_sign_up_token_repository = SignUpTokenRepository::new();
+        mocked_sign_up_token_repository
+            .expect_find()
+            .with(eq(token_key))
+            .times(1)
+            .returning(move |_| {
+                Ok(serde_json::to_string(&SignUpToken {
+                    pin: token_pin.to_string(),
+                    name: "park".to_string(),
+                    email: "park@email.com".to_string(),
+                    password: "hashed_password".to_string(),
+                    avatar_url: None,
+                    locale: None,
+                })
+                .unwrap())
+            });
+        mocked_sign_up_token_repository
+    }
+
+    /// Returns an auth service starting a session of the user once.
+    fn get_auth_service_starting_session(user_id: u64) -> AuthService {
+        let mut mocked_auth_user_repository = UserRepository::new();
+        mocked_auth_user_repository
+            .expect_update_last_login_at()
+            .withf(move |id, _| *id == user_id)
+            .times(1)
+            .returning(|_, _| Ok(true));
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .with(eq(user_id))
+            .times(1)
+            .returning(|user_id| {
+                Ok(UserKey {
+                    public_key: String::from("key"),
+                    ..get_user_key(user_id)
+                })
+            });
+        let mut mocked_active_session_repository = ActiveSessionRepository::default();
+        mocked_active_session_repository
+            .expect_save()
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        AuthService::builder()
+            .user_repository(mocked_auth_user_repository)
+            .user_key_repository(mocked_user_key_repository)
+            .session_epoch_repository(get_session_epoch_repository(0))
+            .active_session_repository(mocked_active_session_repository)
+            .build()
+    }
+
+    #[test]
+    fn test_finish_sign_up_with_expired_pin() {
+        let token_key = "a1lam9cBko";
+
+        let mut mocked_sign_up_token_repository = SignUpTokenRepository::new();
+        mocked_sign_up_token_repository
+            .expect_find()
+            .with(eq(token_key))
+            .times(1)
+            .returning(|key| Err(ServiceError::NotFound(key.to_string())));
+        mocked_sign_up_token_repository.expect_delete().times(0);
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository.expect_create_with_key().times(0);
+
+        let mut user_service = UserService::new_with_repository(
+            mocked_sign_up_token_repository,
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let result =
+            user_service.finish_sign_up("key", token_key, "P9d82Jc5", &Some(String::from("1")));
+        assert!(matches!(result, Err(ServiceError::Expired)));
+    }
+
+    #[test]
+    fn test_finish_sign_up_keeps_token_when_user_creation_fails() {
+        let token_key = "a1lam9cBko";
+        let token_pin = "P9d82Jc5";
+
+        let mut mocked_sign_up_token_repository =
+            get_sign_up_token_repository(token_key, token_pin);
+        mocked_sign_up_token_repository.expect_delete().times(0);
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_create_with_key()
+            .withf(|token, public_key, tos_version_accepted| {
+                token.email == "park@email.com"
+                    && public_key == "key"
+                    && tos_version_accepted == "1"
+            })
+            .times(1)
+            .returning(|_, _, _| Err(ServiceError::QueryExecutionFailure));
+
+        let mut user_service = UserService::new_with_repository(
+            mocked_sign_up_token_repository,
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let result =
+            user_service.finish_sign_up("key", token_key, token_pin, &Some(String::from("1")));
+        assert!(matches!(result, Err(ServiceError::QueryExecutionFailure)));
+    }
+
+    #[test]
+    fn test_finish_sign_up_with_email_registered_in_the_meantime() {
+        let token_key = "a1lam9cBko";
+        let token_pin = "P9d82Jc5";
+
+        let mut mocked_sign_up_token_repository =
+            get_sign_up_token_repository(token_key, token_pin);
+        mocked_sign_up_token_repository.expect_delete().times(0);
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_create_with_key()
+            .times(1)
+            .returning(|token, _, _| Err(ServiceError::Conflict(token.email.clone())));
+
+        let mut user_service = UserService::new_with_repository(
+            mocked_sign_up_token_repository,
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let result =
+            user_service.finish_sign_up("key", token_key, token_pin, &Some(String::from("1")));
+        assert!(matches!(result, Err(ServiceError::Conflict(email)) if email == "park@email.com"));
+    }
+
+    #[test]
+    fn test_finish_sign_up_without_accepting_tos() {
+        let mut mocked_sign_up_token_repository = SignUpTokenRepository::new();
+        mocked_sign_up_token_repository.expect_find().times(0);
+        mocked_sign_up_token_repository.expect_delete().times(0);
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository.expect_create_with_key().times(0);
+
+        let mut user_service = UserService::new_with_repository(
+            mocked_sign_up_token_repository,
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+        user_service.tos_version = String::from("2");
+
+        for accepted_tos_version in &[None, Some(String::from("1"))] {
+            let result =
+                user_service.finish_sign_up("key", "a1lam9cBko", "P9d82Jc5", accepted_tos_version);
+            assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+        }
+    }
+
+    #[test]
+    fn test_accept_tos() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(1))
+            .returning(|id| Ok(get_user(id, "park@email.com")));
+        mocked_user_repository
+            .expect_update_tos_version_accepted()
+            .with(eq(1), eq("2"))
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+        user_service.tos_version = String::from("2");
+
+        let result = user_service.accept_tos(1, "1");
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+
+        let result = user_service.accept_tos(1, "2");
+        assert!(result.unwrap());
+    }
+
+    #[test]
+    fn test_finish_sign_up_deletes_token_after_user_creation() {
+        let token_key = "a1lam9cBko";
+        let token_pin = "P9d82Jc5";
+
+        let mut mocked_sign_up_token_repository = SignUpTokenRepository::new();
+        mocked_sign_up_token_repository
+            .expect_find()
+            .with(eq(token_key))
+            .times(1)
+            .returning(move |_| {
+                Ok(serde_json::to_string(&SignUpToken {
+                    pin: token_pin.to_string(),
+                    name: "park".to_string(),
+                    email: "park@email.com".to_string(),
+                    password: "hashed_password".to_string(),
+                    avatar_url: None,
+                    locale: Some("ko".to_string()),
+                })
+                .unwrap())
+            });
+        mocked_sign_up_token_repository
+            .expect_delete()
+            .with(eq(token_key))
+            .times(1)
+            .returning(|_| Ok(true));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_create_with_key()
+            .withf(|token, _, _| token.locale == Some("ko".to_string()))
+            .times(1)
+            .returning(|_, _, _| Ok(1));
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(1))
+            .times(1)
+            .returning(|id| Ok(get_user(id, "park@email.com")));
+
+        let mut user_service = UserService::new_with_repository(
+            mocked_sign_up_token_repository,
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+        user_service.auth_service = Some(get_auth_service_starting_session(1));
+
+        let user_session = user_service
+            .finish_sign_up("key", token_key, token_pin, &Some(String::from("1")))
+            .unwrap();
+        assert_eq!(user_session.user_id, 1);
+        assert_eq!(user_session.user_email, "park@email.com");
+        assert_eq!(user_session.user_public_key, "key");
+    }
+
+    #[test]
+    fn test_reset_password_with_expired_token() {
+        let email = "park@email.com";
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq(email))
+            .times(1)
+            .returning(|email| Ok(get_user(1, email)));
+        mocked_user_repository.expect_update().times(0);
+
+        let mut mocked_password_token_repository = PasswordTokenRepository::default();
+        mocked_password_token_repository
+            .expect_find()
+            .times(1)
+            .returning(|| Err(ServiceError::NotFound(String::from("password_token:1"))));
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            mocked_password_token_repository,
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let result = user_service.reset_password(email, "71I3Qz9u", "P9d82Jc5", "new password");
+        assert!(matches!(result, Err(ServiceError::Expired)));
+    }
+
+    #[test]
+    fn test_reset_password_twice_with_same_token() {
+        let email = "park@email.com";
+        let token = PasswordToken {
+            id: String::from("71I3Qz9u"),
+            password: String::from("P9d82Jc5"),
+        };
+        let stored_token = Arc::new(Mutex::new(Some(serde_json::to_string(&token).unwrap())));
+
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_email()
+            .with(eq(email))
+            .times(2)
+            .returning(|email| Ok(get_user(1, email)));
+        mocked_user_repository
+            .expect_update()
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(true));
+
+        let mut mocked_password_token_repository = PasswordTokenRepository::default();
+        let found_token = stored_token.clone();
+        mocked_password_token_repository
+            .expect_find()
+            .times(2)
+            .returning(move || match found_token.lock().unwrap().clone() {
+                Some(serialized_token) => Ok(serialized_token),
+                None => Err(ServiceError::NotFound(String::from("password_token:1"))),
+            });
+        let deleted_token = stored_token.clone();
+        mocked_password_token_repository
+            .expect_delete()
+            .times(1)
+            .returning(move || Ok(deleted_token.lock().unwrap().take().is_some()));
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            mocked_password_token_repository,
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        user_service.session_epoch_repository =
+            Some(get_shared_session_epoch_repository(Arc::new(Mutex::new(0))));
+
+        let result = user_service.reset_password(email, &token.id, &token.password, "new password");
+        assert!(result.unwrap());
+
+        let result =
+            user_service.reset_password(email, &token.id, &token.password, "another password");
+        assert!(matches!(result, Err(ServiceError::Expired)));
+    }
+
+    #[test]
+    fn test_update_with_unknown_timezone() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository.expect_update().times(0);
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let result = user_service.update(1, &None, &None, &Some(String::from("Mars/Olympus_Mons")));
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_update_timezone() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_update()
+            .withf(|id, name, password, avatar_url, timezone| {
+                *id == 1
+                    && name.is_none()
+                    && password.is_none()
+                    && avatar_url.is_none()
+                    && timezone.as_deref() == Some("Asia/Seoul")
+            })
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(true));
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let result = user_service.update(1, &None, &None, &Some(String::from("Asia/Seoul")));
+        assert!(result.unwrap());
+    }
+
+    #[test]
+    fn test_update_reminder_with_invalid_hour() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository.expect_update_reminder().times(0);
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let result = user_service.update_reminder(1, true, 24);
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_update_locale_with_unsupported_locale() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository.expect_update_locale().times(0);
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let result = user_service.update_locale(1, &Some(String::from("tlh")));
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_is_http_url() {
+        assert!(UserService::is_http_url("https://example.com/avatar.jpg"));
+        assert!(UserService::is_http_url(
+            "http://example.com:8080/avatar.jpg?size=64"
+        ));
+        assert!(!UserService::is_http_url("avatar.jpg"));
+        assert!(!UserService::is_http_url("javascript:alert(1)"));
+        assert!(!UserService::is_http_url("ftp://example.com/avatar.jpg"));
+        assert!(!UserService::is_http_url(&format!(
+            "https://example.com/{}",
+            "a".repeat(MAX_AVATAR_URL_LENGTH)
+        )));
+    }
+
+    #[test]
+    fn test_update_profile() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_update()
+            .withf(|id, name, password, avatar_url, timezone| {
+                *id == 1
+                    && name.as_deref() == Some("parksb")
+                    && password.is_none()
+                    && avatar_url.as_deref() == Some("https://example.com/avatar.jpg")
+                    && timezone.is_none()
+            })
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(true));
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(1))
+            .times(1)
+            .returning(|id| {
+                let mut user = get_user(id, "park@email.com");
+                user.name = String::from("parksb");
+                user.avatar_url = Some(String::from("https://example.com/avatar.jpg"));
+                Ok(user)
+            });
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let profile = user_service
+            .update_profile(
+                1,
+                &Some(String::from(" parksb ")),
+                &Some(String::from("https://example.com/avatar.jpg")),
+            )
+            .unwrap();
+        assert_eq!(profile.name, "parksb");
+        assert_eq!(
+            profile.avatar_url,
+            Some(String::from("https://example.com/avatar.jpg"))
+        );
+    }
+
+    #[test]
+    fn test_update_profile_with_invalid_avatar_url() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository.expect_update().times(0);
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+
+        let result =
+            user_service.update_profile(1, &None, &Some(String::from("not a url/avatar.jpg")));
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+
+        let result = user_service.update_profile(1, &Some(String::from("  ")), &None);
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_request_and_confirm_email_change() {
+        env::set_var("EMAIL_ADDRESS", "Darim <noreply@darim.app>");
+        env::set_var("CLIENT_ADDRESS", "https://darim.app");
+
+        let current_email = Arc::new(Mutex::new(String::from("park@email.com")));
+        let pending_token: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+
+        let mut mocked_user_repository = UserRepository::new();
+        let email = current_email.clone();
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(1))
+            .returning(move |id| Ok(get_user(id, &email.lock().unwrap())));
+        let email = current_email.clone();
+        mocked_user_repository
+            .expect_find_by_email()
+            .returning(move |target_email| {
+                if *email.lock().unwrap() == target_email {
+                    Ok(get_user(1, target_email))
+                } else {
+                    Err(ServiceError::NotFound(target_email.to_string()))
+                }
+            });
+        let email = current_email.clone();
+        mocked_user_repository
+            .expect_update_email()
+            .withf(|id, new_email| *id == 1 && new_email == "new@email.com")
+            .times(1)
+            .returning(move |_, new_email| {
+                *email.lock().unwrap() = new_email.to_string();
+                Ok(true)
+            });
+
+        let mut mocked_email_change_token_repository = EmailChangeTokenRepository::default();
+        let token = pending_token.clone();
+        mocked_email_change_token_repository
+            .expect_save()
+            .withf(|_, ttl_seconds| *ttl_seconds == 86400)
+            .times(1)
+            .returning(move |serialized_token, _| {
+                *token.lock().unwrap() = Some(serialized_token.to_string());
+                Ok(true)
+            });
+        let token = pending_token.clone();
+        mocked_email_change_token_repository
+            .expect_find()
+            .returning(move || match token.lock().unwrap().clone() {
+                Some(serialized_token) => Ok(serialized_token),
+                None => Err(ServiceError::NotFound(String::from("token"))),
+            });
+        let token = pending_token.clone();
+        mocked_email_change_token_repository
+            .expect_delete()
+            .times(1)
+            .returning(move || Ok(token.lock().unwrap().take().is_some()));
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+        user_service.email_change_token_repository = Some(mocked_email_change_token_repository);
+
+        assert!(user_service
+            .request_email_change(1, " New@Email.com ", &Some(String::from("password")), &None)
+            .unwrap());
+        assert!(pending_token.lock().unwrap().is_some());
+        assert_eq!(*current_email.lock().unwrap(), "park@email.com");
+
+        assert!(user_service.confirm_email_change("token").unwrap());
+        assert!(pending_token.lock().unwrap().is_none());
+        assert_eq!(*current_email.lock().unwrap(), "new@email.com");
+
+        let result = user_service.confirm_email_change("token");
+        assert!(matches!(result, Err(ServiceError::Expired)));
+    }
+
+    #[test]
+    fn test_request_email_change_to_email_in_use() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(1))
+            .times(1)
+            .returning(|id| Ok(get_user(id, "park@email.com")));
+        mocked_user_repository
+            .expect_find_by_email()
+            .withf(|email| email == "other@email.com")
+            .times(1)
+            .returning(|email| Ok(get_user(2, email)));
+
+        let mut mocked_email_change_token_repository = EmailChangeTokenRepository::default();
+        mocked_email_change_token_repository.expect_save().times(0);
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+        user_service.email_change_token_repository = Some(mocked_email_change_token_repository);
+
+        let result = user_service.request_email_change(
+            1,
+            "other@email.com",
+            &Some(String::from("password")),
+            &None,
+        );
+        assert!(matches!(result, Err(ServiceError::Conflict(_))));
+    }
+
+    #[test]
+    fn test_request_email_change_with_wrong_password() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(1))
+            .times(2)
+            .returning(|id| Ok(get_user(id, "park@email.com")));
+        mocked_user_repository.expect_find_by_email().times(0);
+
+        let mut mocked_email_change_token_repository = EmailChangeTokenRepository::default();
+        mocked_email_change_token_repository.expect_save().times(0);
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+        user_service.email_change_token_repository = Some(mocked_email_change_token_repository);
+
+        let result = user_service.request_email_change(
+            1,
+            "new@email.com",
+            &Some(String::from("wrong password")),
+            &None,
+        );
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+
+        let result = user_service.request_email_change(1, "new@email.com", &None, &None);
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+    #[test]
+    fn test_export_personal_data_without_sensitive_fields() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(1))
+            .times(1)
+            .returning(|id| {
+                let mut user = get_user(id, "park@email.com");
+                user.totp_secret = Some(String::from("JBSWY3DPEHPK3PXP"));
+                Ok(user)
+            });
+
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .with(eq(1))
+            .times(1)
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        let mut mocked_post_repository = PostRepository::new();
+        mocked_post_repository
+            .expect_find_all()
+            .with(eq(1))
+            .times(1)
+            .returning(|user_id| {
+                Ok(vec![Post {
+                    id: 1,
+                    user_id,
+                    title: String::from("U2FsdGVkX1+title"),
+                    content: String::from("U2FsdGVkX1+content"),
+                    date: Utc::now().naive_utc(),
+                    created_at: Utc::now().naive_utc(),
+                    updated_at: None,
+                    deleted_at: None,
+                    version: 1,
+                    publish_at: None,
+                    status: PostStatus::Published,
+                    pinned: false,
+                    pinned_at: None,
+                    share_token: None,
+                    mood: None,
+                    category_id: None,
+                }])
+            });
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            mocked_user_key_repository,
+            mocked_user_repository,
+        );
+        user_service.post_repository = Some(mocked_post_repository);
+
+        let personal_data = user_service.export_personal_data(1).unwrap();
+        assert_eq!(personal_data.profile.email, "park@email.com");
+        assert_eq!(personal_data.public_key, Some(String::from("d63ee429")));
+        assert_eq!(personal_data.posts.len(), 1);
+
+        let serialized = serde_json::to_value(&personal_data).unwrap();
+        let profile = serialized["profile"].as_object().unwrap();
+        assert!(!profile.contains_key("password"));
+        assert!(!profile.contains_key("totp_secret"));
+        assert!(!profile.contains_key("has_password"));
+        let serialized = serialized.to_string();
+        assert!(!serialized.contains("$argon2"));
+        assert!(!serialized.contains("JBSWY3DPEHPK3PXP"));
+    }
+    #[test]
+    fn test_request_account_deletion_with_wrong_password() {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_find_by_id()
+            .with(eq(1))
+            .times(1)
+            .returning(|id| Ok(get_user(id, "park@email.com")));
+
+        let mut mocked_account_deletion_token_repository =
+            AccountDeletionTokenRepository::default();
+        mocked_account_deletion_token_repository
+            .expect_save()
+            .times(0);
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            PasswordTokenRepository::default(),
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+        user_service.account_deletion_token_repository =
+            Some(mocked_account_deletion_token_repository);
+
+        let result =
+            user_service.request_account_deletion(1, &Some(String::from("wrong password")), &None);
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    /// Returns a user service whose user repository deletes the posts and the keys in the lists
+    /// only if the deletion succeeds as a whole, as the transaction does.
+    fn get_user_service_for_account_deletion(
+        posts: Arc<Mutex<Vec<u64>>>,
+        user_keys: Arc<Mutex<Vec<u64>>>,
+        is_deletion_failed: bool,
+    ) -> UserService {
+        let mut mocked_user_repository = UserRepository::new();
+        mocked_user_repository
+            .expect_delete_account()
+            .with(eq(1))
+            .times(1)
+            .returning(move |_| {
+                if is_deletion_failed {
+                    return Err(ServiceError::QueryExecutionFailure);
+                }
+                posts.lock().unwrap().clear();
+                user_keys.lock().unwrap().clear();
+                Ok(true)
+            });
+
+        let expected_times = if is_deletion_failed { 0 } else { 1 };
+        let mut mocked_account_deletion_token_repository =
+            AccountDeletionTokenRepository::default();
+        mocked_account_deletion_token_repository
+            .expect_find()
+            .returning(|| Ok(String::from("VQxjRkO8HhmHkJ1dCHDBmsAqfpmDgy8i")));
+        mocked_account_deletion_token_repository
+            .expect_delete()
+            .times(expected_times)
+            .returning(|| Ok(true));
+        let mut mocked_password_token_repository = PasswordTokenRepository::default();
+        mocked_password_token_repository
+            .expect_delete()
+            .times(expected_times)
+            .returning(|| Ok(false));
+        let mut mocked_known_device_repository = KnownDeviceRepository::default();
+        mocked_known_device_repository
+            .expect_delete()
+            .times(expected_times)
+            .returning(|| Ok(true));
+        let mut mocked_session_epoch_repository = SessionEpochRepository::default();
+        mocked_session_epoch_repository
+            .expect_increase()
+            .times(expected_times)
+            .returning(|| Ok(1));
+
+        let mut user_service = UserService::new_with_repository(
+            SignUpTokenRepository::new(),
+            mocked_password_token_repository,
+            UserKeyRepository::new(),
+            mocked_user_repository,
+        );
+        user_service.account_deletion_token_repository =
+            Some(mocked_account_deletion_token_repository);
+        user_service.known_device_repository = Some(mocked_known_device_repository);
+        user_service.session_epoch_repository = Some(mocked_session_epoch_repository);
+        user_service
+    }
+
+    #[test]
+    fn test_confirm_account_deletion() {
+        let posts = Arc::new(Mutex::new(vec![1, 2, 3]));
+        let user_keys = Arc::new(Mutex::new(vec![1]));
+        let mut user_service =
+            get_user_service_for_account_deletion(posts.clone(), user_keys.clone(), false);
+
+        let result = user_service.confirm_account_deletion(1, "wrong token");
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+        assert_eq!(posts.lock().unwrap().len(), 3);
+
+        assert!(user_service
+            .confirm_account_deletion(1, "VQxjRkO8HhmHkJ1dCHDBmsAqfpmDgy8i")
+            .unwrap());
+        assert!(posts.lock().unwrap().is_empty());
+        assert!(user_keys.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_confirm_account_deletion_with_failure() {
+        let posts = Arc::new(Mutex::new(vec![1, 2, 3]));
+        let user_keys = Arc::new(Mutex::new(vec![1]));
+        let mut user_service =
+            get_user_service_for_account_deletion(posts.clone(), user_keys.clone(), true);
+
+        let result = user_service.confirm_account_deletion(1, "VQxjRkO8HhmHkJ1dCHDBmsAqfpmDgy8i");
+        assert!(matches!(result, Err(ServiceError::QueryExecutionFailure)));
+        assert_eq!(*posts.lock().unwrap(), vec![1, 2, 3]);
+        assert_eq!(*user_keys.lock().unwrap(), vec![1]);
+    }
 }
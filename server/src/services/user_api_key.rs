@@ -0,0 +1,393 @@
+use chrono::NaiveDateTime;
+
+use crate::models::auth::*;
+use crate::models::error::{get_service_error, ServiceError};
+use crate::models::user::*;
+use crate::models::user_api_key::*;
+use crate::models::user_key::*;
+use crate::utils::{password_util, token_util};
+
+/// A length of the prefix of the API key, by which the key is found.
+const API_KEY_PREFIX_LENGTH: usize = 12;
+
+/// A length of the secret of the API key.
+const API_KEY_SECRET_LENGTH: usize = 32;
+
+pub struct UserApiKeyService {
+    user_api_key_repository: Option<UserApiKeyRepository>,
+    user_repository: Option<UserRepository>,
+    user_key_repository: Option<UserKeyRepository>,
+    session_epoch_repository: Option<SessionEpochRepository>,
+    session_lifetime: SessionLifetime,
+}
+
+impl UserApiKeyService {
+    pub fn new() -> Self {
+        Self {
+            user_api_key_repository: None,
+            user_repository: None,
+            user_key_repository: None,
+            session_epoch_repository: None,
+            session_lifetime: SessionLifetime::from_env(),
+        }
+    }
+
+    fn user_api_key_repository(
+        &mut self,
+        new_repository: Option<UserApiKeyRepository>,
+    ) -> &UserApiKeyRepository {
+        match new_repository {
+            Some(_) => {
+                self.user_api_key_repository = new_repository;
+                self.user_api_key_repository.as_ref().unwrap()
+            }
+            None => self.user_api_key_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn user_repository(&mut self, new_repository: Option<UserRepository>) -> &UserRepository {
+        match new_repository {
+            Some(_) => {
+                self.user_repository = new_repository;
+                self.user_repository.as_ref().unwrap()
+            }
+            None => self.user_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn user_key_repository(
+        &mut self,
+        new_repository: Option<UserKeyRepository>,
+    ) -> &UserKeyRepository {
+        match new_repository {
+            Some(_) => {
+                self.user_key_repository = new_repository;
+                self.user_key_repository.as_ref().unwrap()
+            }
+            None => self.user_key_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn session_epoch_repository(
+        &mut self,
+        new_repository: Option<SessionEpochRepository>,
+    ) -> &mut SessionEpochRepository {
+        match new_repository {
+            Some(_) => {
+                self.session_epoch_repository = new_repository;
+                self.session_epoch_repository.as_mut().unwrap()
+            }
+            None => self.session_epoch_repository.as_mut().unwrap(),
+        }
+    }
+
+    /// Creates a new API key of the user, which expires at `expires_at` if it is given.
+    ///
+    /// 1. Generates a random prefix and secret. The key is `{prefix}.{secret}`.
+    /// 2. Stores the prefix and the hash of the secret.
+    /// 3. Returns the key, which can't be found again.
+    pub fn create(
+        &mut self,
+        user_id: u64,
+        expires_at: &Option<NaiveDateTime>,
+        now: &NaiveDateTime,
+    ) -> Result<CreatedUserApiKey, ServiceError> {
+        if let Some(expires_at) = expires_at {
+            if expires_at <= now {
+                return Err(get_service_error(ServiceError::InvalidArgument));
+            }
+        }
+
+        let prefix = token_util::get_random_string(API_KEY_PREFIX_LENGTH);
+        let secret = token_util::get_random_string(API_KEY_SECRET_LENGTH);
+        let hashed_secret = password_util::get_hashed_password(&secret);
+
+        let fallback_repository =
+            some_if_true!(self.user_api_key_repository.is_none() => UserApiKeyRepository::new());
+        let user_api_key_repository = self.user_api_key_repository(fallback_repository);
+        user_api_key_repository.create(user_id, &prefix, &hashed_secret, expires_at)?;
+        let user_api_key = user_api_key_repository.find_by_prefix(&prefix)?;
+
+        Ok(CreatedUserApiKey {
+            id: user_api_key.id,
+            key: format!("{}.{}", prefix, secret),
+            expires_at: user_api_key.expires_at,
+        })
+    }
+
+    /// Revokes the API key of the user.
+    pub fn revoke(&mut self, user_id: u64, id: u64) -> Result<bool, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.user_api_key_repository.is_none() => UserApiKeyRepository::new());
+        self.user_api_key_repository(fallback_repository)
+            .delete(id, user_id)
+    }
+
+    /// Authenticates the API key, and returns the session of the owning user.
+    ///
+    /// 1. Finds the API key by the prefix of the key.
+    /// 2. Verifies the secret of the key and the expiry.
+    /// 3. Returns the session of the user, which expires with the key, or after the session
+    ///    lifetime if the key doesn't expire.
+    ///
+    /// Any malformed, unknown, expired or revoked key is unauthorized.
+    pub fn authenticate(
+        &mut self,
+        key: &str,
+        now: &NaiveDateTime,
+    ) -> Result<UserSession, ServiceError> {
+        let mut parts = key.splitn(2, '.');
+        let (prefix, secret) = match (parts.next(), parts.next()) {
+            (Some(prefix), Some(secret)) => (prefix, secret),
+            _ => return Err(get_service_error(ServiceError::Unauthorized(None))),
+        };
+
+        let user_api_key = {
+            let fallback_repository = some_if_true!(self.user_api_key_repository.is_none() => UserApiKeyRepository::new());
+            match self
+                .user_api_key_repository(fallback_repository)
+                .find_by_prefix(prefix)
+            {
+                Ok(user_api_key) => user_api_key,
+                Err(ServiceError::NotFound(_)) => {
+                    return Err(get_service_error(ServiceError::Unauthorized(None)))
+                }
+                Err(error) => return Err(error),
+            }
+        };
+
+        if !password_util::check_password(secret, &user_api_key.hashed_secret) {
+            return Err(get_service_error(ServiceError::Unauthorized(None)));
+        }
+        if let Some(expires_at) = user_api_key.expires_at {
+            if expires_at <= *now {
+                return Err(get_service_error(ServiceError::Unauthorized(None)));
+            }
+        }
+
+        let user = {
+            let fallback_repository =
+                some_if_true!(self.user_repository.is_none() => UserRepository::new());
+            self.user_repository(fallback_repository)
+                .find_by_id(user_api_key.user_id)?
+        };
+
+        let user_public_key = {
+            let fallback_repository =
+                some_if_true!(self.user_key_repository.is_none() => UserKeyRepository::new());
+            self.user_key_repository(fallback_repository)
+                .find_by_user_id(user.id)?
+                .public_key
+        };
+
+        let user_session_epoch = {
+            let fallback_repository = some_if_true!(self.session_epoch_repository.is_none() => SessionEpochRepository::new(user.id));
+            self.session_epoch_repository(fallback_repository).find()?
+        };
+
+        Ok(UserSession {
+            user_id: user.id,
+            user_email: user.email,
+            user_name: user.name,
+            user_public_key,
+            user_avatar_url: user.avatar_url,
+            user_session_epoch,
+            user_session_id: format!("api_key:{}", user_api_key.id),
+            user_last_login_at: user.last_login_at,
+            user_previous_login_at: user.previous_login_at,
+            user_tos_version_accepted: user.tos_version_accepted,
+            user_session_started_at: *now,
+            user_session_expires_at: user_api_key
+                .expires_at
+                .unwrap_or_else(|| self.session_lifetime.get_expiry(now, now)),
+        })
+    }
+}
+
+impl Default for UserApiKeyService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+use crate::models::{
+    auth::MockSessionEpochRepositoryTrait as SessionEpochRepository,
+    user::MockUserRepositoryTrait as UserRepository,
+    user_api_key::MockUserApiKeyRepositoryTrait as UserApiKeyRepository,
+    user_key::MockUserKeyRepositoryTrait as UserKeyRepository,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_util::{get_user, get_user_key};
+    use chrono::{Duration, Utc};
+    use mockall::predicate::*;
+
+    /// A prefix of the API key stored by `get_user_api_key`, whose secret is `secret`.
+    const PREFIX: &str = "Tq3yXc8bLw2K";
+
+    fn get_user_api_key(expires_at: Option<NaiveDateTime>) -> UserApiKey {
+        UserApiKey {
+            id: 1,
+            user_id: 1,
+            prefix: String::from(PREFIX),
+            hashed_secret: password_util::get_hashed_password("secret"),
+            expires_at,
+            created_at: Utc::now().naive_utc(),
+        }
+    }
+
+    /// Returns an API key repository which has only the API key of `get_user_api_key`.
+    fn get_user_api_key_repository(expires_at: Option<NaiveDateTime>) -> UserApiKeyRepository {
+        let mut mocked_user_api_key_repository = UserApiKeyRepository::new();
+        mocked_user_api_key_repository
+            .expect_find_by_prefix()
+            .returning(move |prefix| {
+                if prefix == PREFIX {
+                    Ok(get_user_api_key(expires_at))
+                } else {
+                    Err(ServiceError::NotFound(prefix.to_string()))
+                }
+            });
+        mocked_user_api_key_repository
+    }
+
+    fn get_user_api_key_service(
+        user_api_key_repository: UserApiKeyRepository,
+    ) -> UserApiKeyService {
+        let mut user_repository = UserRepository::new();
+        user_repository
+            .expect_find_by_id()
+            .returning(|id| Ok(get_user(id, "park@email.com")));
+
+        let mut user_key_repository = UserKeyRepository::new();
+        user_key_repository
+            .expect_find_by_user_id()
+            .returning(|user_id| Ok(get_user_key(user_id)));
+
+        let mut session_epoch_repository = SessionEpochRepository::default();
+        session_epoch_repository.expect_find().returning(|| Ok(0));
+
+        UserApiKeyService {
+            user_api_key_repository: Some(user_api_key_repository),
+            user_repository: Some(user_repository),
+            user_key_repository: Some(user_key_repository),
+            session_epoch_repository: Some(session_epoch_repository),
+            session_lifetime: SessionLifetime::from_env(),
+        }
+    }
+
+    #[test]
+    fn test_create() {
+        let now = Utc::now().naive_utc();
+
+        let mut mocked_user_api_key_repository = UserApiKeyRepository::new();
+        mocked_user_api_key_repository
+            .expect_create()
+            .withf(|user_id, prefix, hashed_secret, expires_at| {
+                *user_id == 1
+                    && prefix.len() == API_KEY_PREFIX_LENGTH
+                    && hashed_secret.starts_with("$argon2id$")
+                    && expires_at.is_none()
+            })
+            .times(1)
+            .returning(|_, _, _, _| Ok(true));
+        mocked_user_api_key_repository
+            .expect_find_by_prefix()
+            .times(1)
+            .returning(|prefix| {
+                Ok(UserApiKey {
+                    id: 3,
+                    prefix: prefix.to_string(),
+                    ..get_user_api_key(None)
+                })
+            });
+
+        let mut user_api_key_service = get_user_api_key_service(mocked_user_api_key_repository);
+        let created_user_api_key = user_api_key_service.create(1, &None, &now).unwrap();
+
+        assert_eq!(created_user_api_key.id, 3);
+        let mut parts = created_user_api_key.key.split('.');
+        assert_eq!(parts.next().unwrap().len(), API_KEY_PREFIX_LENGTH);
+        assert_eq!(parts.next().unwrap().len(), API_KEY_SECRET_LENGTH);
+    }
+
+    #[test]
+    fn test_authenticate() {
+        let now = Utc::now().naive_utc();
+        let mut user_api_key_service = get_user_api_key_service(get_user_api_key_repository(None));
+
+        let user_session = user_api_key_service
+            .authenticate(&format!("{}.secret", PREFIX), &now)
+            .unwrap();
+
+        assert_eq!(user_session.user_id, 1);
+        assert_eq!(user_session.user_session_id, "api_key:1");
+        assert!(user_session.user_session_expires_at > now);
+    }
+
+    #[test]
+    fn test_authenticate_with_wrong_secret() {
+        let now = Utc::now().naive_utc();
+        let mut user_api_key_service = get_user_api_key_service(get_user_api_key_repository(None));
+
+        for key in &[format!("{}.wrong", PREFIX), PREFIX.to_string()] {
+            let result = user_api_key_service.authenticate(key, &now);
+            assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+        }
+    }
+
+    #[test]
+    fn test_authenticate_with_expired_key() {
+        let now = Utc::now().naive_utc();
+        let expires_at = Some(now + Duration::days(1));
+        let mut user_api_key_service =
+            get_user_api_key_service(get_user_api_key_repository(expires_at));
+
+        let key = format!("{}.secret", PREFIX);
+        let user_session = user_api_key_service.authenticate(&key, &now).unwrap();
+        assert_eq!(Some(user_session.user_session_expires_at), expires_at);
+
+        let result = user_api_key_service.authenticate(&key, &(now + Duration::days(2)));
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[test]
+    fn test_authenticate_with_revoked_key() {
+        let now = Utc::now().naive_utc();
+        let mut user_api_key_service = get_user_api_key_service(get_user_api_key_repository(None));
+
+        let result = user_api_key_service.authenticate("a1lam9cBko2X.secret", &now);
+        assert!(matches!(result, Err(ServiceError::Unauthorized(None))));
+    }
+
+    #[test]
+    fn test_revoke_of_other_user() {
+        let mut mocked_user_api_key_repository = UserApiKeyRepository::new();
+        mocked_user_api_key_repository
+            .expect_delete()
+            .with(eq(1), eq(2))
+            .times(1)
+            .returning(|id, _| Err(ServiceError::NotFound(id.to_string())));
+
+        let mut user_api_key_service = get_user_api_key_service(mocked_user_api_key_repository);
+
+        let result = user_api_key_service.revoke(2, 1);
+        assert!(matches!(result, Err(ServiceError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_create_with_past_expiry() {
+        let now = Utc::now().naive_utc();
+
+        let mut mocked_user_api_key_repository = UserApiKeyRepository::new();
+        mocked_user_api_key_repository.expect_create().times(0);
+
+        let mut user_api_key_service = get_user_api_key_service(mocked_user_api_key_repository);
+
+        let result = user_api_key_service.create(1, &Some(now - Duration::seconds(1)), &now);
+        assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+    }
+}
@@ -0,0 +1,402 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+use crate::models::error::{get_service_error, ServiceError};
+use crate::models::post::*;
+use crate::models::user_key::*;
+
+/// A period for which the previous public key is kept after rotation, while the client
+/// re-encrypts the posts with the new key.
+const KEY_ROTATION_GRACE_PERIOD_DAYS: i64 = 7;
+
+/// A number of hex digits in each group of the fingerprint of a public key.
+const FINGERPRINT_GROUP_LENGTH: usize = 4;
+
+pub struct UserKeyService {
+    user_key_repository: Option<UserKeyRepository>,
+    post_repository: Option<PostRepository>,
+}
+
+impl UserKeyService {
+    pub fn new() -> Self {
+        Self {
+            user_key_repository: None,
+            post_repository: None,
+        }
+    }
+
+    fn user_key_repository(
+        &mut self,
+        new_repository: Option<UserKeyRepository>,
+    ) -> &UserKeyRepository {
+        match new_repository {
+            Some(_) => {
+                self.user_key_repository = new_repository;
+                self.user_key_repository.as_ref().unwrap()
+            }
+            None => self.user_key_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn post_repository(&mut self, new_repository: Option<PostRepository>) -> &PostRepository {
+        match new_repository {
+            Some(_) => {
+                self.post_repository = new_repository;
+                self.post_repository.as_ref().unwrap()
+            }
+            None => self.post_repository.as_ref().unwrap(),
+        }
+    }
+
+    /// Returns whether the previous public key of the user key is still kept at the time.
+    fn is_in_grace_period(user_key: &UserKey, now: &NaiveDateTime) -> bool {
+        match (&user_key.previous_public_key, user_key.rotated_at) {
+            (Some(_), Some(rotated_at)) => {
+                *now < rotated_at + Duration::days(KEY_ROTATION_GRACE_PERIOD_DAYS)
+            }
+            _ => false,
+        }
+    }
+
+    /// Finds the public key of the user.
+    /// The previous public key is given only during the grace period of rotation.
+    pub fn get_one(&mut self, user_id: u64) -> Result<UserKeyDTO, ServiceError> {
+        let user_key = {
+            let fallback_repository =
+                some_if_true!(self.user_key_repository.is_none() => UserKeyRepository::new());
+            self.user_key_repository(fallback_repository)
+                .find_by_user_id(user_id)?
+        };
+
+        let previous_public_key = if Self::is_in_grace_period(&user_key, &Utc::now().naive_utc()) {
+            user_key.previous_public_key
+        } else {
+            None
+        };
+
+        Ok(UserKeyDTO {
+            public_key: user_key.public_key,
+            previous_public_key,
+            rotated_at: user_key.rotated_at,
+        })
+    }
+
+    /// Returns the fingerprint of the public key of the user, which two users compare over a
+    /// separate channel to verify the key. It is the SHA-256 of the key in hex, grouped by 4
+    /// digits such as `3a7f 09c2 ...`, and is the same as long as the key isn't rotated.
+    pub fn fingerprint(&mut self, user_id: u64) -> Result<String, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.user_key_repository.is_none() => UserKeyRepository::new());
+        let user_key = self
+            .user_key_repository(fallback_repository)
+            .find_by_user_id(user_id)?;
+
+        Ok(get_fingerprint(&user_key.public_key))
+    }
+
+    /// Rotates the public key of the user, and returns ids of all posts of the user, which the
+    /// client must re-encrypt with the new key.
+    ///
+    /// The previous public key is kept until the rotation is completed by `complete_rotation`,
+    /// or the grace period is over. Another rotation can't be started during the grace period.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - An id of the user
+    /// * `new_public_key` - A new public key of the user
+    pub fn rotate(&mut self, user_id: u64, new_public_key: &str) -> Result<Vec<u64>, ServiceError> {
+        if new_public_key.trim().is_empty() {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        let now = Utc::now().naive_utc();
+        let user_key = {
+            let fallback_repository =
+                some_if_true!(self.user_key_repository.is_none() => UserKeyRepository::new());
+            self.user_key_repository(fallback_repository)
+                .find_by_user_id(user_id)?
+        };
+
+        if user_key.public_key == new_public_key {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+        if Self::is_in_grace_period(&user_key, &now) {
+            return Err(get_service_error(ServiceError::Conflict(
+                user_id.to_string(),
+            )));
+        }
+
+        self.user_key_repository(None)
+            .rotate(user_id, new_public_key, &now)?;
+
+        let post_list = {
+            let fallback_repository =
+                some_if_true!(self.post_repository.is_none() => PostRepository::new());
+            self.post_repository(fallback_repository)
+                .find_all(user_id)?
+        };
+
+        Ok(post_list
+            .iter()
+            .filter(|post| post.user_id == user_id)
+            .map(|post| post.id)
+            .collect())
+    }
+
+    /// Completes the rotation of the public key of the user after all posts are re-encrypted,
+    /// and deletes the previous public key.
+    pub fn complete_rotation(&mut self, user_id: u64) -> Result<bool, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.user_key_repository.is_none() => UserKeyRepository::new());
+        self.user_key_repository(fallback_repository)
+            .delete_previous(user_id)
+    }
+}
+
+impl Default for UserKeyService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the SHA-256 of the public key in hex grouped by `FINGERPRINT_GROUP_LENGTH` digits.
+fn get_fingerprint(public_key: &str) -> String {
+    let mut hex = String::new();
+    for byte in Sha256::digest(public_key.as_bytes()) {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+
+    hex.as_bytes()
+        .chunks(FINGERPRINT_GROUP_LENGTH)
+        .map(|group| String::from_utf8_lossy(group).into_owned())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+use crate::models::post::MockPostRepositoryTrait as PostRepository;
+#[cfg(test)]
+use crate::models::user_key::MockUserKeyRepositoryTrait as UserKeyRepository;
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate::*;
+
+    use super::*;
+    use crate::utils::test_util::get_user_key;
+
+    impl UserKeyService {
+        pub fn new_with_repository(
+            user_key_repository: UserKeyRepository,
+            post_repository: PostRepository,
+        ) -> Self {
+            Self {
+                user_key_repository: Some(user_key_repository),
+                post_repository: Some(post_repository),
+            }
+        }
+    }
+
+    fn get_post(id: u64, user_id: u64) -> Post {
+        Post {
+            id,
+            user_id,
+            title: String::from("U2FsdGVkX1+title"),
+            content: String::from("U2FsdGVkX1+content"),
+            date: Utc::now().naive_utc(),
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            deleted_at: None,
+            version: 1,
+            publish_at: None,
+            status: PostStatus::Published,
+            pinned: false,
+            pinned_at: None,
+            share_token: None,
+            mood: None,
+            category_id: None,
+        }
+    }
+
+    /// Returns the user key rotated to the public key now, whose previous public key is kept.
+    fn get_rotating_user_key(user_id: u64, public_key: &str) -> UserKey {
+        UserKey {
+            public_key: public_key.to_string(),
+            previous_public_key: Some(String::from("d63ee429")),
+            rotated_at: Some(Utc::now().naive_utc()),
+            ..get_user_key(user_id)
+        }
+    }
+
+    #[test]
+    fn test_rotate_retains_previous_key_until_completed() {
+        let mut sequence = mockall::Sequence::new();
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .with(eq(1))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|user_id| Ok(get_user_key(user_id)));
+        mocked_user_key_repository
+            .expect_rotate()
+            .with(eq(1), eq("f0e2b8a1"), always())
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _, _| Ok(true));
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .with(eq(1))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|user_id| Ok(get_rotating_user_key(user_id, "f0e2b8a1")));
+        mocked_user_key_repository
+            .expect_delete_previous()
+            .with(eq(1))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_| Ok(true));
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .with(eq(1))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|user_id| {
+                Ok(UserKey {
+                    previous_public_key: None,
+                    ..get_rotating_user_key(user_id, "f0e2b8a1")
+                })
+            });
+
+        let mut mocked_post_repository = PostRepository::new();
+        mocked_post_repository
+            .expect_find_all()
+            .with(eq(1))
+            .times(1)
+            .returning(|user_id| Ok(vec![get_post(1, user_id), get_post(2, user_id)]));
+
+        let mut user_key_service =
+            UserKeyService::new_with_repository(mocked_user_key_repository, mocked_post_repository);
+
+        let post_ids = user_key_service.rotate(1, "f0e2b8a1").unwrap();
+        assert_eq!(post_ids, vec![1, 2]);
+
+        let rotating_user_key = user_key_service.get_one(1).unwrap();
+        assert_eq!(rotating_user_key.public_key, "f0e2b8a1");
+        assert_eq!(
+            rotating_user_key.previous_public_key,
+            Some(String::from("d63ee429"))
+        );
+
+        assert!(user_key_service.complete_rotation(1).unwrap());
+
+        let rotated_user_key = user_key_service.get_one(1).unwrap();
+        assert_eq!(rotated_user_key.public_key, "f0e2b8a1");
+        assert_eq!(rotated_user_key.previous_public_key, None);
+    }
+
+    #[test]
+    fn test_fingerprint_changes_after_rotation() {
+        let mut sequence = mockall::Sequence::new();
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .with(eq(1))
+            .times(3)
+            .in_sequence(&mut sequence)
+            .returning(|user_id| Ok(get_user_key(user_id)));
+        mocked_user_key_repository
+            .expect_rotate()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _, _| Ok(true));
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .with(eq(1))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|user_id| Ok(get_rotating_user_key(user_id, "f0e2b8a1")));
+
+        let mut mocked_post_repository = PostRepository::new();
+        mocked_post_repository
+            .expect_find_all()
+            .returning(|_| Ok(vec![]));
+
+        let mut user_key_service =
+            UserKeyService::new_with_repository(mocked_user_key_repository, mocked_post_repository);
+
+        let fingerprint = user_key_service.fingerprint(1).unwrap();
+        assert_eq!(fingerprint, get_fingerprint("d63ee429"));
+        assert_eq!(fingerprint.len(), 64 + 15);
+        assert!(fingerprint
+            .split(' ')
+            .all(|group| group.len() == 4 && group.chars().all(|c| c.is_ascii_hexdigit())));
+        assert_eq!(user_key_service.fingerprint(1).unwrap(), fingerprint);
+
+        user_key_service.rotate(1, "f0e2b8a1").unwrap();
+
+        let rotated_fingerprint = user_key_service.fingerprint(1).unwrap();
+        assert_ne!(rotated_fingerprint, fingerprint);
+        assert_eq!(rotated_fingerprint, get_fingerprint("f0e2b8a1"));
+    }
+
+    #[test]
+    fn test_get_fingerprint() {
+        assert_eq!(
+            get_fingerprint("abc"),
+            "ba78 16bf 8f01 cfea 4141 40de 5dae 2223 b003 61a3 9617 7a9c b410 ff61 f200 15ad"
+        );
+    }
+
+    #[test]
+    fn test_rotate_during_grace_period() {
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .with(eq(1))
+            .times(1)
+            .returning(|user_id| {
+                Ok(UserKey {
+                    public_key: String::from("d63ee429"),
+                    previous_public_key: Some(String::from("a7c3d9e2")),
+                    rotated_at: Some(Utc::now().naive_utc() - Duration::days(1)),
+                    ..get_user_key(user_id)
+                })
+            });
+        mocked_user_key_repository.expect_rotate().times(0);
+
+        let mut mocked_post_repository = PostRepository::new();
+        mocked_post_repository.expect_find_all().times(0);
+
+        let mut user_key_service =
+            UserKeyService::new_with_repository(mocked_user_key_repository, mocked_post_repository);
+
+        let result = user_key_service.rotate(1, "f0e2b8a1");
+        assert!(matches!(result, Err(ServiceError::Conflict(_))));
+    }
+
+    #[test]
+    fn test_get_one_after_grace_period() {
+        let mut mocked_user_key_repository = UserKeyRepository::new();
+        mocked_user_key_repository
+            .expect_find_by_user_id()
+            .with(eq(1))
+            .times(1)
+            .returning(|user_id| {
+                Ok(UserKey {
+                    previous_public_key: Some(String::from("a7c3d9e2")),
+                    rotated_at: Some(
+                        Utc::now().naive_utc() - Duration::days(KEY_ROTATION_GRACE_PERIOD_DAYS + 1),
+                    ),
+                    ..get_user_key(user_id)
+                })
+            });
+
+        let mut user_key_service =
+            UserKeyService::new_with_repository(mocked_user_key_repository, PostRepository::new());
+
+        let user_key = user_key_service.get_one(1).unwrap();
+        assert_eq!(user_key.previous_public_key, None);
+    }
+}
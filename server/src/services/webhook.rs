@@ -0,0 +1,268 @@
+use crate::models::error::{get_service_error, ServiceError};
+use crate::models::webhook::*;
+#[cfg(not(test))]
+use crate::utils::webhook_util::HostResolver;
+use crate::utils::{token_util, webhook_util};
+
+/// A length of the secret of the webhook.
+const WEBHOOK_SECRET_LENGTH: usize = 32;
+/// A maximum number of characters in the URL of the webhook.
+const MAX_WEBHOOK_URL_LENGTH: usize = 2048;
+/// A number of the recent deliveries of a webhook to be found.
+const WEBHOOK_DELIVERY_LIMIT: usize = 50;
+
+pub struct WebhookService {
+    webhook_repository: Option<WebhookRepository>,
+    host_resolver: Option<HostResolver>,
+}
+
+impl WebhookService {
+    pub fn new() -> Self {
+        Self {
+            webhook_repository: None,
+            host_resolver: None,
+        }
+    }
+
+    fn webhook_repository(
+        &mut self,
+        new_repository: Option<WebhookRepository>,
+    ) -> &WebhookRepository {
+        match new_repository {
+            Some(_) => {
+                self.webhook_repository = new_repository;
+                self.webhook_repository.as_ref().unwrap()
+            }
+            None => self.webhook_repository.as_ref().unwrap(),
+        }
+    }
+
+    fn host_resolver(&mut self, new_resolver: Option<HostResolver>) -> &HostResolver {
+        match new_resolver {
+            Some(_) => {
+                self.host_resolver = new_resolver;
+                self.host_resolver.as_ref().unwrap()
+            }
+            None => self.host_resolver.as_ref().unwrap(),
+        }
+    }
+
+    /// Returns whether the URL is an absolute HTTP or HTTPS URL with a host.
+    fn is_valid_url(url: &str) -> bool {
+        let mut parts = url.splitn(2, "://");
+        let (scheme, rest) = match (parts.next(), parts.next()) {
+            (Some(scheme), Some(rest)) => (scheme, rest),
+            _ => return false,
+        };
+        let host = rest.split(&['/', '?', '#'][..]).next();
+
+        (scheme == "https" || scheme == "http")
+            && url.len() <= MAX_WEBHOOK_URL_LENGTH
+            && !url.chars().any(char::is_whitespace)
+            && matches!(host, Some(host) if !host.is_empty())
+    }
+
+    /// Registers a new webhook of the user, to which events of the posts of the user are posted.
+    /// Returns the webhook with a random secret, by which the requests are signed.
+    /// Returns `ServiceError::InvalidArgument` if the URL isn't an HTTP or HTTPS URL, or its host
+    /// resolves to a loopback, private, link-local or unspecified address.
+    pub fn register(&mut self, user_id: u64, url: &str) -> Result<RegisteredWebhook, ServiceError> {
+        let url = url.trim();
+        if !Self::is_valid_url(url) {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        let fallback_resolver =
+            some_if_true!(self.host_resolver.is_none() => HostResolver::default());
+        if webhook_util::verify_public_url(self.host_resolver(fallback_resolver), url).is_err() {
+            return Err(get_service_error(ServiceError::InvalidArgument));
+        }
+
+        let secret = token_util::get_random_string(WEBHOOK_SECRET_LENGTH);
+
+        let fallback_repository =
+            some_if_true!(self.webhook_repository.is_none() => WebhookRepository::new());
+        let id = self
+            .webhook_repository(fallback_repository)
+            .create(user_id, url, &secret)?;
+
+        Ok(RegisteredWebhook {
+            id,
+            url: url.to_string(),
+            secret,
+        })
+    }
+
+    /// Finds all webhooks of the user without their secrets.
+    pub fn get_list(&mut self, user_id: u64) -> Result<Vec<WebhookDTO>, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.webhook_repository.is_none() => WebhookRepository::new());
+        let webhook_list = self
+            .webhook_repository(fallback_repository)
+            .find_all_by_user_id(user_id)?;
+
+        Ok(webhook_list
+            .into_iter()
+            .map(|webhook| WebhookDTO {
+                id: webhook.id,
+                url: webhook.url,
+                created_at: webhook.created_at,
+            })
+            .collect())
+    }
+
+    /// Deletes the webhook of the user with its deliveries.
+    pub fn delete(&mut self, user_id: u64, id: u64) -> Result<bool, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.webhook_repository.is_none() => WebhookRepository::new());
+        self.webhook_repository(fallback_repository)
+            .delete(id, user_id)
+    }
+
+    /// Finds the recent deliveries of the webhook of the user from the latest one.
+    /// Returns `ServiceError::NotFound` if the webhook isn't the user's one.
+    pub fn get_deliveries(
+        &mut self,
+        user_id: u64,
+        id: u64,
+    ) -> Result<Vec<WebhookDelivery>, ServiceError> {
+        let fallback_repository =
+            some_if_true!(self.webhook_repository.is_none() => WebhookRepository::new());
+        let webhook_repository = self.webhook_repository(fallback_repository);
+
+        let webhook_list = webhook_repository.find_all_by_user_id(user_id)?;
+        if !webhook_list.iter().any(|webhook| webhook.id == id) {
+            return Err(get_service_error(ServiceError::NotFound(id.to_string())));
+        }
+
+        webhook_repository.find_deliveries_by_webhook_id(id, WEBHOOK_DELIVERY_LIMIT)
+    }
+}
+
+impl Default for WebhookService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+use crate::models::webhook::MockWebhookRepositoryTrait as WebhookRepository;
+#[cfg(test)]
+use crate::utils::webhook_util::MockHostResolverTrait as HostResolver;
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate::*;
+
+    use super::*;
+    use crate::models::webhook::MockWebhookRepositoryTrait;
+    use crate::utils::test_util::get_webhook;
+    use crate::utils::webhook_util::MockHostResolverTrait;
+
+    impl WebhookService {
+        pub fn new_with_repository(webhook_repository: WebhookRepository) -> Self {
+            Self {
+                webhook_repository: Some(webhook_repository),
+                host_resolver: Some(get_host_resolver("93.184.216.34")),
+            }
+        }
+
+        pub fn with_host_resolver(mut self, host_resolver: HostResolver) -> Self {
+            self.host_resolver = Some(host_resolver);
+            self
+        }
+    }
+
+    /// Returns a host resolver resolving any host to the address.
+    fn get_host_resolver(ip: &'static str) -> MockHostResolverTrait {
+        let mut mocked_host_resolver = MockHostResolverTrait::default();
+        mocked_host_resolver
+            .expect_resolve()
+            .returning(move |_, _| Ok(vec![ip.parse().unwrap()]));
+        mocked_host_resolver
+    }
+
+    #[test]
+    fn test_register() {
+        let mut mocked_webhook_repository = MockWebhookRepositoryTrait::new();
+        mocked_webhook_repository
+            .expect_create()
+            .with(
+                eq(10),
+                eq("https://example.com/hooks/darim"),
+                function(|secret: &str| secret.len() == WEBHOOK_SECRET_LENGTH),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(3));
+
+        let mut webhook_service = WebhookService::new_with_repository(mocked_webhook_repository);
+        let registered_webhook = webhook_service
+            .register(10, " https://example.com/hooks/darim ")
+            .unwrap();
+
+        assert_eq!(registered_webhook.id, 3);
+        assert_eq!(registered_webhook.url, "https://example.com/hooks/darim");
+        assert_eq!(registered_webhook.secret.len(), WEBHOOK_SECRET_LENGTH);
+    }
+
+    #[test]
+    fn test_register_invalid_url() {
+        for url in &[
+            "ftp://example.com",
+            "https://",
+            "example.com",
+            "https://exa mple.com",
+        ] {
+            let mut mocked_webhook_repository = MockWebhookRepositoryTrait::new();
+            mocked_webhook_repository.expect_create().times(0);
+
+            let mut webhook_service =
+                WebhookService::new_with_repository(mocked_webhook_repository);
+            let result = webhook_service.register(10, url);
+            assert!(matches!(result, Err(ServiceError::InvalidArgument)));
+        }
+    }
+
+    #[test]
+    fn test_register_internal_url() {
+        for (url, resolved_ip) in &[
+            ("http://localhost:8080/posts/1", "127.0.0.1"),
+            ("http://127.0.0.1:8080/posts/1", "93.184.216.34"),
+            ("http://169.254.169.254/latest/meta-data", "93.184.216.34"),
+            ("https://intranet.example.com/hooks", "10.0.0.1"),
+            ("https://router.example.com/hooks", "192.168.0.1"),
+            ("http://[::1]/hooks", "93.184.216.34"),
+            ("http://0.0.0.0/hooks", "93.184.216.34"),
+        ] {
+            let mut mocked_webhook_repository = MockWebhookRepositoryTrait::new();
+            mocked_webhook_repository.expect_create().times(0);
+
+            let mut webhook_service =
+                WebhookService::new_with_repository(mocked_webhook_repository)
+                    .with_host_resolver(get_host_resolver(resolved_ip));
+            let result = webhook_service.register(10, url);
+            assert!(
+                matches!(result, Err(ServiceError::InvalidArgument)),
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_deliveries_of_other_user() {
+        let mut mocked_webhook_repository = MockWebhookRepositoryTrait::new();
+        mocked_webhook_repository
+            .expect_find_all_by_user_id()
+            .with(eq(10))
+            .times(1)
+            .returning(|user_id| Ok(vec![get_webhook(3, user_id)]));
+        mocked_webhook_repository
+            .expect_find_deliveries_by_webhook_id()
+            .times(0);
+
+        let mut webhook_service = WebhookService::new_with_repository(mocked_webhook_repository);
+        let result = webhook_service.get_deliveries(10, 4);
+        assert!(matches!(result, Err(ServiceError::NotFound(_))));
+    }
+}
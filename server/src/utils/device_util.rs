@@ -0,0 +1,95 @@
+use sha1::{Digest, Sha1};
+use std::net::{IpAddr, SocketAddr};
+
+/// Returns a fingerprint of the device used to log in.
+///
+/// It is a SHA-1 hash of the User-Agent and the truncated IP address,
+/// so the raw User-Agent and IP address don't have to be stored.
+///
+/// # Arguments
+///
+/// * `user_agent` - A User-Agent of the device
+/// * `ip_address` - An IP address of the device. It is ignored if it is `None` or invalid.
+pub fn get_fingerprint(user_agent: &str, ip_address: Option<&str>) -> String {
+    let truncated_ip_address = ip_address.and_then(truncate_ip_address).unwrap_or_default();
+    let device = format!("{}|{}", user_agent, truncated_ip_address);
+
+    format!("{:x}", Sha1::digest(device.as_bytes()))
+}
+
+/// Returns the network part of the IP address, so that a device keeps the same fingerprint
+/// while its address changes within the network.
+///
+/// An IPv4 address is truncated to /24, and an IPv6 address is truncated to /64.
+/// The address may have a port, as the remote address of the connection does.
+///
+/// # Arguments
+///
+/// * `ip_address` - An IP address to be truncated
+fn truncate_ip_address(ip_address: &str) -> Option<String> {
+    let ip_address = match ip_address.parse::<SocketAddr>() {
+        Ok(socket_address) => socket_address.ip(),
+        Err(_) => ip_address.parse::<IpAddr>().ok()?,
+    };
+
+    match ip_address {
+        IpAddr::V4(ip_address) => {
+            let octets = ip_address.octets();
+            Some(format!("{}.{}.{}.0/24", octets[0], octets[1], octets[2]))
+        }
+        IpAddr::V6(ip_address) => {
+            let segments = ip_address.segments();
+            Some(format!(
+                "{:x}:{:x}:{:x}:{:x}::/64",
+                segments[0], segments[1], segments[2], segments[3]
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15";
+
+    #[test]
+    fn test_truncate_ip_address() {
+        assert_eq!(
+            truncate_ip_address("203.0.113.42"),
+            Some(String::from("203.0.113.0/24"))
+        );
+        assert_eq!(
+            truncate_ip_address("203.0.113.42:52814"),
+            Some(String::from("203.0.113.0/24"))
+        );
+        assert_eq!(
+            truncate_ip_address("2001:db8:85a3:8d3:1319:8a2e:370:7348"),
+            Some(String::from("2001:db8:85a3:8d3::/64"))
+        );
+        assert_eq!(truncate_ip_address("localhost"), None);
+    }
+
+    #[test]
+    fn test_get_fingerprint() {
+        let fingerprint = get_fingerprint(USER_AGENT, Some("203.0.113.42"));
+
+        assert_eq!(fingerprint.len(), 40);
+        assert_eq!(
+            fingerprint,
+            get_fingerprint(USER_AGENT, Some("203.0.113.7:52814"))
+        );
+        assert_ne!(
+            fingerprint,
+            get_fingerprint(USER_AGENT, Some("198.51.100.42"))
+        );
+        assert_ne!(
+            fingerprint,
+            get_fingerprint("curl/7.64.1", Some("203.0.113.42"))
+        );
+        assert_eq!(
+            get_fingerprint(USER_AGENT, None),
+            get_fingerprint(USER_AGENT, Some("localhost"))
+        );
+    }
+}
@@ -1,28 +1,851 @@
+use lazy_static::lazy_static;
 use lettre::message::header::ContentType;
-use lettre::message::{Message, SinglePart};
+use lettre::message::{Message, MultiPart, SinglePart};
 use lettre::transport::sendmail::SendmailTransport;
 use lettre::Transport;
+use mockall::automock;
+use std::collections::HashSet;
 use std::env;
+use std::fs;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-use crate::models::error::ServiceError;
+use crate::models::error::{EmailError, ServiceError};
+use crate::utils::{log_util, metric_util};
 
-pub fn send_email(to: &str, subject: &str, body: &str) -> Result<bool, ServiceError> {
-    let email_address = env::var("EMAIL_ADDRESS").expect("EMAIL_ADDRESS not found");
-    let parsed_email_address = email_address.parse().unwrap();
-    let email = Message::builder()
-        .from(parsed_email_address)
-        .to(to.parse().unwrap())
-        .subject(subject)
-        .singlepart(
-            SinglePart::builder()
-                .header(ContentType("text/html; charset=utf8".parse().unwrap()))
-                .body(body.to_string()),
-        )
+/// A maximum number of retries after an email fails to be sent.
+const MAX_EMAIL_RETRIES: u32 = 3;
+/// A delay before the first retry, which doubles for each of the next retries.
+const EMAIL_RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+/// A default blocklist of disposable email domains, used if `DISPOSABLE_EMAIL_DOMAINS_FILE` is
+/// not set.
+const DEFAULT_DISPOSABLE_DOMAINS: &str = include_str!("../../config/disposable_email_domains.txt");
+
+/// An email to be sent by the email worker.
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    /// A plaintext body shown by mail clients which don't render HTML.
+    pub text_body: Option<String>,
+}
+
+/// Templates of emails in `templates/email`, each of which has an HTML body and a plaintext
+/// fallback. Variables in a template are written as `{{name}}`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmailTemplate {
+    /// A welcome email with the pin of the sign up token, taking `name` and `pin`.
+    Welcome,
+    /// A password reset email, taking `name`, `password` and `link`.
+    PasswordReset,
+    /// A reminder email to write a post today, taking `name` and `link`.
+    Reminder,
+}
+
+impl EmailTemplate {
+    fn subject(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (EmailTemplate::Welcome, Locale::English) => "Welcome to Darim 🎉",
+            (EmailTemplate::Welcome, Locale::Korean) => "다림에 오신 것을 환영합니다 🎉",
+            (EmailTemplate::PasswordReset, Locale::English) => "Please reset your password 🔒",
+            (EmailTemplate::PasswordReset, Locale::Korean) => "비밀번호를 재설정해주세요 🔒",
+            (EmailTemplate::Reminder, Locale::English) => "Time to write your day ✏️",
+            (EmailTemplate::Reminder, Locale::Korean) => "오늘 하루를 기록할 시간이에요 ✏️",
+        }
+    }
+
+    fn html(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (EmailTemplate::Welcome, Locale::English) => {
+                include_str!("../../templates/email/welcome.html")
+            }
+            (EmailTemplate::Welcome, Locale::Korean) => {
+                include_str!("../../templates/email/welcome.ko.html")
+            }
+            (EmailTemplate::PasswordReset, Locale::English) => {
+                include_str!("../../templates/email/password_reset.html")
+            }
+            (EmailTemplate::PasswordReset, Locale::Korean) => {
+                include_str!("../../templates/email/password_reset.ko.html")
+            }
+            (EmailTemplate::Reminder, Locale::English) => {
+                include_str!("../../templates/email/reminder.html")
+            }
+            (EmailTemplate::Reminder, Locale::Korean) => {
+                include_str!("../../templates/email/reminder.ko.html")
+            }
+        }
+    }
+
+    fn text(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (EmailTemplate::Welcome, Locale::English) => {
+                include_str!("../../templates/email/welcome.txt")
+            }
+            (EmailTemplate::Welcome, Locale::Korean) => {
+                include_str!("../../templates/email/welcome.ko.txt")
+            }
+            (EmailTemplate::PasswordReset, Locale::English) => {
+                include_str!("../../templates/email/password_reset.txt")
+            }
+            (EmailTemplate::PasswordReset, Locale::Korean) => {
+                include_str!("../../templates/email/password_reset.ko.txt")
+            }
+            (EmailTemplate::Reminder, Locale::English) => {
+                include_str!("../../templates/email/reminder.txt")
+            }
+            (EmailTemplate::Reminder, Locale::Korean) => {
+                include_str!("../../templates/email/reminder.ko.txt")
+            }
+        }
+    }
+}
+
+/// A locale of emails. Templates of a locale other than English are suffixed with its language
+/// tag, such as `welcome.ko.html`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Locale {
+    English,
+    Korean,
+}
+
+impl Locale {
+    /// Parses a language tag such as `ko` or `ko-KR`, and returns `None` if it is not supported.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let language = tag.trim().split(&['-', '_'][..]).next()?;
+        match language.to_lowercase().as_str() {
+            "en" => Some(Locale::English),
+            "ko" => Some(Locale::Korean),
+            _ => None,
+        }
+    }
+
+    /// Returns the locale of the language tag, which falls back to English if it is not set or
+    /// not supported.
+    pub fn from_tag(tag: &Option<String>) -> Self {
+        tag.as_deref()
+            .and_then(Locale::parse)
+            .unwrap_or(Locale::English)
+    }
+}
+
+/// An email rendered from a template.
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+lazy_static! {
+    /// A blocklist of disposable email domains loaded at startup.
+    static ref DISPOSABLE_DOMAINS: DisposableDomains = DisposableDomains::from_env();
+
+    /// A queue of emails to be sent by the email worker in the background, and the worker.
+    /// It is taken on shutdown, after which emails are not enqueued anymore.
+    static ref EMAIL_QUEUE: Mutex<Option<(Sender<Email>, JoinHandle<()>)>> =
+        Mutex::new(Some(start_email_worker(deliver_email)));
+}
+
+/// A sender of emails, which is injected into services to be replaced in tests.
+/// Emails are sent in the background through the email queue.
+pub struct EmailSender;
+
+#[automock]
+pub trait EmailSenderTrait {
+    fn new() -> Self;
+    fn send(&self, to: &str, subject: &str, body: &str) -> bool;
+    fn send_templated(
+        &self,
+        to: &str,
+        template: EmailTemplate,
+        locale: Locale,
+        vars: &[(&'static str, String)],
+    ) -> bool;
+}
+
+impl EmailSender {
+    /// Creates a new email sender.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Enqueues the email to be sent, and returns whether it is enqueued.
+    pub fn send(&self, to: &str, subject: &str, body: &str) -> bool {
+        send_email_async(to, subject, body)
+    }
+
+    /// Enqueues the email rendered from the template to be sent, and returns whether it is enqueued.
+    pub fn send_templated(
+        &self,
+        to: &str,
+        template: EmailTemplate,
+        locale: Locale,
+        vars: &[(&'static str, String)],
+    ) -> bool {
+        send_templated_email(to, template, locale, vars)
+    }
+}
+
+impl Default for EmailSender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns a normalized email used for lookup and storage.
+///
+/// It trims surrounding whitespace and lowercases the whole email.
+/// The local part is lowercased as well, since mail servers treat it case-insensitively in practice.
+///
+/// # Arguments
+///
+/// * `email` - An email to be normalized
+pub fn normalize(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Returns a normalized domain of the email, or `None` if the email has no domain.
+///
+/// It lowercases the domain, and removes the trailing dot of a fully qualified domain such as
+/// `email.com.`.
+///
+/// # Arguments
+///
+/// * `email` - An email such as `park@email.com`
+pub fn get_domain(email: &str) -> Option<String> {
+    let email = normalize(email);
+    let index = email.rfind('@')?;
+    let domain = email[index + 1..].trim_end_matches('.');
+    if domain.is_empty() {
+        None
+    } else {
+        Some(domain.to_string())
+    }
+}
+
+/// A blocklist of domains of disposable email services.
+pub struct DisposableDomains {
+    domains: HashSet<String>,
+}
+
+impl DisposableDomains {
+    /// Parses a blocklist having a domain in each line.
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn parse(text: &str) -> Self {
+        let domains = text
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.to_lowercase().trim_end_matches('.').to_string())
+            .collect();
+        Self { domains }
+    }
+
+    /// Loads the blocklist from the file set to `DISPOSABLE_EMAIL_DOMAINS_FILE`, or the default
+    /// blocklist if it is not set.
+    ///
+    /// # Panics
+    ///
+    /// It panics if the file cannot be read, not to accept disposable emails silently.
+    pub fn from_env() -> Self {
+        match env::var("DISPOSABLE_EMAIL_DOMAINS_FILE") {
+            Ok(path) => {
+                let text = fs::read_to_string(&path).unwrap_or_else(|error| {
+                    panic!(
+                        "Failed to read disposable email domains {}: {}",
+                        path, error
+                    )
+                });
+                Self::parse(&text)
+            }
+            Err(_) => Self::parse(DEFAULT_DISPOSABLE_DOMAINS),
+        }
+    }
+
+    /// Returns the number of the domains in the blocklist.
+    pub fn len(&self) -> usize {
+        self.domains.len()
+    }
+
+    /// Returns whether the blocklist is empty.
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty()
+    }
+
+    /// Returns whether the domain of the email, or any of its parent domains, is in the blocklist.
+    /// For example, `park@mail.mailinator.com` is disposable if `mailinator.com` is blocked.
+    ///
+    /// # Arguments
+    ///
+    /// * `email` - An email such as `park@email.com`
+    pub fn contains(&self, email: &str) -> bool {
+        let domain = match get_domain(email) {
+            Some(domain) => domain,
+            None => return false,
+        };
+
+        let labels: Vec<&str> = domain.split('.').collect();
+        (0..labels.len()).any(|index| self.domains.contains(&labels[index..].join(".")))
+    }
+}
+
+/// Returns whether the email is of a disposable email service in the blocklist loaded at startup.
+///
+/// # Arguments
+///
+/// * `email` - An email such as `park@email.com`
+pub fn is_disposable(email: &str) -> bool {
+    DISPOSABLE_DOMAINS.contains(email)
+}
+
+/// Loads the blocklist of disposable email domains, and returns the number of the domains.
+/// It is called at startup, so that an invalid blocklist file stops the server immediately.
+pub fn load_disposable_domains() -> usize {
+    DISPOSABLE_DOMAINS.len()
+}
+
+/// Escapes characters having special meanings in HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Replaces each `{{name}}` in the template with the value of the variable.
+fn render(template: &str, vars: &[(&str, String)]) -> String {
+    vars.iter()
+        .fold(template.to_string(), |rendered, (name, value)| {
+            rendered.replace(&format!("{{{{{}}}}}", name), value)
+        })
+}
+
+/// Renders the email template with the variables.
+/// Values of the variables are HTML-escaped in the HTML body.
+///
+/// # Arguments
+///
+/// * `template` - A template of the email
+/// * `locale` - A locale of the email
+/// * `vars` - Pairs of a name and a value of the variables such as `("name", String::from("park"))`
+pub fn render_email(
+    template: EmailTemplate,
+    locale: Locale,
+    vars: &[(&'static str, String)],
+) -> RenderedEmail {
+    let html_vars: Vec<(&str, String)> = vars
+        .iter()
+        .map(|(name, value)| (*name, escape_html(value)))
+        .collect();
+
+    RenderedEmail {
+        subject: template.subject(locale).to_string(),
+        html_body: render(template.html(locale), &html_vars),
+        text_body: render(template.text(locale), vars),
+    }
+}
+
+/// A transport delivering emails.
+#[automock]
+pub trait EmailTransport {
+    fn send(&self, email: &Email) -> Result<(), EmailError>;
+}
+
+/// A transport delivering emails by the sendmail command.
+pub struct SendmailEmailTransport;
+
+impl EmailTransport for SendmailEmailTransport {
+    /// Delivers the email by the sendmail command.
+    /// A failure of the command is treated as transient, since the mail server may be unavailable.
+    fn send(&self, email: &Email) -> Result<(), EmailError> {
+        let email_address = env::var("EMAIL_ADDRESS").expect("EMAIL_ADDRESS not found");
+        let parsed_email_address = email_address.parse().unwrap();
+        let to = match email.to.parse() {
+            Ok(to) => to,
+            Err(_) => return Err(EmailError::InvalidAddress(email.to.clone())),
+        };
+        let builder = Message::builder()
+            .from(parsed_email_address)
+            .to(to)
+            .subject(&email.subject);
+        let html_part = SinglePart::builder()
+            .header(ContentType("text/html; charset=utf8".parse().unwrap()))
+            .body(email.body.clone());
+        let message = match &email.text_body {
+            Some(text_body) => builder.multipart(
+                MultiPart::alternative()
+                    .singlepart(
+                        SinglePart::builder()
+                            .header(ContentType("text/plain; charset=utf8".parse().unwrap()))
+                            .body(text_body.clone()),
+                    )
+                    .singlepart(html_part),
+            ),
+            None => builder.singlepart(html_part),
+        }
         .unwrap();
 
-    let sender = SendmailTransport::new();
-    match sender.send(&email) {
+        match SendmailTransport::new().send(&message) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(EmailError::Unavailable(error.to_string())),
+        }
+    }
+}
+
+/// Sends the email with the transport, and retries it up to `max_retries` times with exponential
+/// backoff while it fails transiently. It returns the last error if it doesn't succeed.
+///
+/// # Arguments
+///
+/// * `transport` - A transport delivering the email
+/// * `email` - An email to be sent
+/// * `max_retries` - A maximum number of retries
+/// * `base_delay` - A delay before the first retry, which doubles for each of the next retries
+pub fn send_with_retry<T: EmailTransport>(
+    transport: &T,
+    email: &Email,
+    max_retries: u32,
+    base_delay: Duration,
+) -> Result<(), EmailError> {
+    let mut retries = 0;
+    loop {
+        match transport.send(email) {
+            Ok(_) => return Ok(()),
+            Err(error) if error.is_transient() && retries < max_retries => {
+                thread::sleep(base_delay * 2u32.pow(retries));
+                retries += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Sends the email, and returns after it is sent.
+/// It is retried while the mail server is unavailable, and the final failure is returned.
+pub fn send_email(to: &str, subject: &str, body: &str) -> Result<bool, ServiceError> {
+    deliver_email(&Email {
+        to: to.to_string(),
+        subject: subject.to_string(),
+        body: body.to_string(),
+        text_body: None,
+    })
+}
+
+/// Sends the email with retries, and returns after it is sent.
+fn deliver_email(email: &Email) -> Result<bool, ServiceError> {
+    let result = send_with_retry(
+        &SendmailEmailTransport,
+        email,
+        MAX_EMAIL_RETRIES,
+        EMAIL_RETRY_BASE_DELAY,
+    );
+    metric_util::count_email_send(result.is_ok());
+
+    match result {
         Ok(_) => Ok(true),
-        Err(_) => Err(ServiceError::EmailFailure(to.to_string())),
+        Err(error) => {
+            log_util::log(&error);
+            Err(ServiceError::EmailFailure(email.to.clone()))
+        }
+    }
+}
+
+/// Enqueues the email to be sent by the email worker in the background, and returns immediately.
+/// A failure of sending the email is logged by the worker.
+///
+/// # Arguments
+///
+/// * `to` - A recipient such as `park <park@email.com>`
+/// * `subject` - A subject of the email
+/// * `body` - An HTML body of the email
+pub fn send_email_async(to: &str, subject: &str, body: &str) -> bool {
+    enqueue_email(Email {
+        to: to.to_string(),
+        subject: subject.to_string(),
+        body: body.to_string(),
+        text_body: None,
+    })
+}
+
+/// Renders the email template, and enqueues the email to be sent by the email worker in the
+/// background with the HTML body and the plaintext fallback.
+///
+/// # Arguments
+///
+/// * `to` - A recipient such as `park <park@email.com>`
+/// * `template` - A template of the email
+/// * `locale` - A locale of the email
+/// * `vars` - Pairs of a name and a value of the variables of the template
+pub fn send_templated_email(
+    to: &str,
+    template: EmailTemplate,
+    locale: Locale,
+    vars: &[(&'static str, String)],
+) -> bool {
+    let rendered_email = render_email(template, locale, vars);
+    enqueue_email(Email {
+        to: to.to_string(),
+        subject: rendered_email.subject,
+        body: rendered_email.html_body,
+        text_body: Some(rendered_email.text_body),
+    })
+}
+
+/// Enqueues the email to be sent by the email worker.
+fn enqueue_email(email: Email) -> bool {
+    match EMAIL_QUEUE.lock() {
+        Ok(queue) => match queue.as_ref() {
+            Some((sender, _)) => sender.send(email).is_ok(),
+            None => false,
+        },
+        Err(_) => false,
+    }
+}
+
+/// Stops the email worker after it sends all queued emails, and returns whether the queue is
+/// drained within the grace period. Emails requested after this are not sent.
+///
+/// # Arguments
+///
+/// * `grace_period` - A maximum time to wait for the queued emails to be sent
+pub fn shutdown_email_worker(grace_period: Duration) -> bool {
+    let email_queue = match EMAIL_QUEUE.lock() {
+        Ok(mut queue) => queue.take(),
+        Err(_) => None,
+    };
+
+    if let Some((sender, worker)) = email_queue {
+        drain_email_worker(sender, worker, grace_period)
+    } else {
+        true
+    }
+}
+
+/// Closes the queue, and waits for the worker to send the remaining emails up to the grace
+/// period. Returns whether the worker has finished in time.
+fn drain_email_worker(
+    sender: Sender<Email>,
+    worker: JoinHandle<()>,
+    grace_period: Duration,
+) -> bool {
+    drop(sender);
+
+    let (finished_sender, finished_receiver) = mpsc::channel::<()>();
+    thread::spawn(move || {
+        if worker.join().is_ok() {
+            let _ = finished_sender.send(());
+        }
+    });
+
+    finished_receiver.recv_timeout(grace_period).is_ok()
+}
+
+/// Starts the worker sending emails of the returned queue one by one in the background.
+/// The worker stops when the queue is dropped.
+///
+/// # Arguments
+///
+/// * `send` - A function sending an email
+fn start_email_worker<F>(send: F) -> (Sender<Email>, JoinHandle<()>)
+where
+    F: Fn(&Email) -> Result<bool, ServiceError> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel::<Email>();
+    let worker = thread::spawn(move || {
+        for email in receiver {
+            if let Err(error) = send(&email) {
+                log_util::log(&error);
+            }
+        }
+    });
+
+    (sender, worker)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("park@email.com"), "park@email.com");
+        assert_eq!(normalize("  Park@Email.COM "), "park@email.com");
+    }
+
+    #[test]
+    fn test_get_domain() {
+        assert_eq!(
+            get_domain(" Park@Email.COM. "),
+            Some(String::from("email.com"))
+        );
+        assert_eq!(get_domain("park"), None);
+        assert_eq!(get_domain("park@"), None);
+    }
+
+    #[test]
+    fn test_disposable_domains() {
+        let domains = DisposableDomains::parse("# comment\n\nMailinator.com\n yopmail.com \n");
+        assert_eq!(domains.len(), 2);
+
+        assert!(domains.contains("park@mailinator.com"));
+        assert!(domains.contains("Park@YOPmail.com."));
+        assert!(!domains.contains("park@email.com"));
+        assert!(!domains.contains("park"));
+    }
+
+    #[test]
+    fn test_disposable_domains_with_subdomain() {
+        let domains = DisposableDomains::parse("mailinator.com");
+
+        assert!(domains.contains("park@mail.mailinator.com"));
+        assert!(domains.contains("park@a.b.mailinator.com"));
+        assert!(!domains.contains("park@notmailinator.com"));
+        assert!(!domains.contains("park@mailinator.com.email.com"));
+    }
+
+    #[test]
+    fn test_default_disposable_domains() {
+        let domains = DisposableDomains::parse(DEFAULT_DISPOSABLE_DOMAINS);
+        assert!(!domains.is_empty());
+        assert!(domains.contains("park@mailinator.com"));
+        assert!(!domains.contains("park@gmail.com"));
+    }
+
+    #[test]
+    fn test_render_welcome_email() {
+        let email = render_email(
+            EmailTemplate::Welcome,
+            Locale::English,
+            &[
+                ("name", String::from("park")),
+                ("pin", String::from("a1lam9cBko")),
+            ],
+        );
+
+        assert_eq!(email.subject, "Welcome to Darim 🎉");
+        assert!(email.html_body.contains("<h2>Hello park :)</h2>"));
+        assert!(email.html_body.contains(">a1lam9cBko</div>"));
+        assert!(email.text_body.contains("Hello park :)"));
+        assert!(email.text_body.contains("a1lam9cBko"));
+        assert!(!email.text_body.contains('<'));
+        assert!(!email.html_body.contains("{{"));
+    }
+
+    #[test]
+    fn test_render_welcome_email_in_korean() {
+        let email = render_email(
+            EmailTemplate::Welcome,
+            Locale::Korean,
+            &[
+                ("name", String::from("park")),
+                ("pin", String::from("a1lam9cBko")),
+            ],
+        );
+
+        assert_eq!(email.subject, "다림에 오신 것을 환영합니다 🎉");
+        assert!(email.html_body.contains("안녕하세요, park님 :)"));
+        assert!(email.text_body.contains("a1lam9cBko"));
+    }
+
+    #[test]
+    fn test_render_reminder_email_in_korean() {
+        let email = render_email(
+            EmailTemplate::Reminder,
+            Locale::Korean,
+            &[
+                ("name", String::from("park")),
+                ("link", String::from("https://darim.vip/post")),
+            ],
+        );
+
+        assert_eq!(email.subject, "오늘 하루를 기록할 시간이에요 ✏️");
+        assert!(email.html_body.contains("안녕하세요, park님 :)"));
+        assert!(email
+            .html_body
+            .contains("<a href=\"https://darim.vip/post\">https://darim.vip/post</a>"));
+        assert!(email.text_body.contains("https://darim.vip/post"));
+        assert!(!email.text_body.contains('<'));
+    }
+
+    #[test]
+    fn test_locale_from_tag() {
+        assert_eq!(Locale::from_tag(&Some(String::from("ko"))), Locale::Korean);
+        assert_eq!(
+            Locale::from_tag(&Some(String::from("ko-KR"))),
+            Locale::Korean
+        );
+        assert_eq!(
+            Locale::from_tag(&Some(String::from("en_US"))),
+            Locale::English
+        );
+        assert_eq!(Locale::from_tag(&Some(String::from("fr"))), Locale::English);
+        assert_eq!(Locale::from_tag(&None), Locale::English);
+        assert_eq!(Locale::parse("fr"), None);
+    }
+
+    #[test]
+    fn test_render_password_reset_email_escaping_html() {
+        let email = render_email(
+            EmailTemplate::PasswordReset,
+            Locale::English,
+            &[
+                ("name", String::from("<park>")),
+                ("password", String::from("Ir5c7y8dS3")),
+                (
+                    "link",
+                    String::from("https://darim.vip/password_reset/a1lam9cBko"),
+                ),
+            ],
+        );
+
+        assert!(email.html_body.contains("Hello &lt;park&gt; :)"));
+        assert!(email.html_body.contains(
+            "<a href=\"https://darim.vip/password_reset/a1lam9cBko\">https://darim.vip/password_reset/a1lam9cBko</a>"
+        ));
+        assert!(email.html_body.contains("Ir5c7y8dS3"));
+        assert!(email.text_body.contains("Hello <park> :)"));
+        assert!(email
+            .text_body
+            .contains("https://darim.vip/password_reset/a1lam9cBko"));
+    }
+    #[test]
+    fn test_start_email_worker() {
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+        let worker_sent_emails = sent_emails.clone();
+        let (queue, worker) = start_email_worker(move |email| {
+            worker_sent_emails.lock().unwrap().push(email.to.clone());
+            if email.to.starts_with("fail") {
+                Err(ServiceError::EmailFailure(email.to.clone()))
+            } else {
+                Ok(true)
+            }
+        });
+
+        for to in &["park@email.com", "fail@email.com", "kim@email.com"] {
+            let email = Email {
+                to: to.to_string(),
+                subject: String::from("Hello"),
+                body: String::from("World"),
+                text_body: None,
+            };
+            assert!(queue.send(email).is_ok());
+        }
+
+        drop(queue);
+        worker.join().unwrap();
+
+        assert_eq!(
+            *sent_emails.lock().unwrap(),
+            vec!["park@email.com", "fail@email.com", "kim@email.com"]
+        );
+    }
+    #[test]
+    fn test_drain_email_worker() {
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+        let worker_sent_emails = sent_emails.clone();
+        let (queue, worker) = start_email_worker(move |email| {
+            thread::sleep(Duration::from_millis(50));
+            worker_sent_emails.lock().unwrap().push(email.to.clone());
+            Ok(true)
+        });
+
+        for to in &["park@email.com", "kim@email.com", "lee@email.com"] {
+            assert!(queue
+                .send(Email {
+                    to: to.to_string(),
+                    subject: String::from("Hello"),
+                    body: String::from("World"),
+                    text_body: None,
+                })
+                .is_ok());
+        }
+
+        assert!(drain_email_worker(queue, worker, Duration::from_secs(5)));
+        assert_eq!(
+            *sent_emails.lock().unwrap(),
+            vec!["park@email.com", "kim@email.com", "lee@email.com"]
+        );
+    }
+
+    #[test]
+    fn test_drain_email_worker_after_grace_period() {
+        let (queue, worker) = start_email_worker(|_| {
+            thread::sleep(Duration::from_millis(500));
+            Ok(true)
+        });
+        assert!(queue.send(get_email()).is_ok());
+
+        assert!(!drain_email_worker(
+            queue,
+            worker,
+            Duration::from_millis(10)
+        ));
+    }
+
+    fn get_email() -> Email {
+        Email {
+            to: String::from("park <park@email.com>"),
+            subject: String::from("Hello"),
+            body: String::from("World"),
+            text_body: None,
+        }
+    }
+
+    #[test]
+    fn test_send_with_retry_until_success() {
+        let attempts = Arc::new(Mutex::new(0));
+        let transport_attempts = attempts.clone();
+
+        let mut mocked_transport = MockEmailTransport::new();
+        mocked_transport.expect_send().times(3).returning(move |_| {
+            let mut attempts = transport_attempts.lock().unwrap();
+            *attempts += 1;
+            if *attempts < 3 {
+                Err(EmailError::Unavailable(String::from("connection refused")))
+            } else {
+                Ok(())
+            }
+        });
+
+        let result = send_with_retry(&mocked_transport, &get_email(), 3, Duration::from_millis(1));
+        assert_eq!(result, Ok(()));
+        assert_eq!(*attempts.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_send_with_retry_until_giving_up() {
+        let mut mocked_transport = MockEmailTransport::new();
+        mocked_transport
+            .expect_send()
+            .times(4)
+            .returning(|_| Err(EmailError::Unavailable(String::from("connection refused"))));
+
+        let result = send_with_retry(&mocked_transport, &get_email(), 3, Duration::from_millis(1));
+        assert_eq!(
+            result,
+            Err(EmailError::Unavailable(String::from("connection refused")))
+        );
+    }
+
+    #[test]
+    fn test_send_with_retry_without_retrying_permanent_failure() {
+        let mut mocked_transport = MockEmailTransport::new();
+        mocked_transport
+            .expect_send()
+            .times(1)
+            .returning(|email| Err(EmailError::InvalidAddress(email.to.clone())));
+
+        let result = send_with_retry(&mocked_transport, &get_email(), 3, Duration::from_millis(1));
+        assert_eq!(
+            result,
+            Err(EmailError::InvalidAddress(String::from(
+                "park <park@email.com>"
+            )))
+        );
     }
 }
@@ -0,0 +1,39 @@
+use std::env;
+use std::str::FromStr;
+
+/// Returns a value of the environment variable parsed as `T`, or `default` if the variable is
+/// missing or malformed.
+///
+/// # Arguments
+///
+/// * `key` - A name of the environment variable
+/// * `default` - A fallback value
+///
+/// # Example
+///
+/// ```ignore
+/// use darim::utils::env_util::get_env_var_or;
+///
+/// let limit: u64 = get_env_var_or("LOGIN_ATTEMPT_LIMIT", 5);
+/// ```
+pub fn get_env_var_or<T: FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse::<T>().ok())
+        .unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_env_var_or() {
+        env::set_var("DARIM_TEST_ENV_UTIL_NUMBER", "10");
+        env::set_var("DARIM_TEST_ENV_UTIL_MALFORMED", "ten");
+
+        assert_eq!(get_env_var_or::<u64>("DARIM_TEST_ENV_UTIL_NUMBER", 5), 10);
+        assert_eq!(get_env_var_or::<u64>("DARIM_TEST_ENV_UTIL_MALFORMED", 5), 5);
+        assert_eq!(get_env_var_or::<u64>("DARIM_TEST_ENV_UTIL_MISSING", 5), 5);
+    }
+}
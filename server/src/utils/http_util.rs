@@ -1,13 +1,25 @@
-use actix_web::HttpResponse;
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse};
 use serde::Serialize;
 
-use crate::models::error::ServiceError;
+use crate::models::error::{FieldError, ServiceError};
 
 /// HTTP response of the API.
 #[derive(Serialize)]
 pub struct ServiceResponse<T> {
     data: Option<T>,
-    error: Option<String>,
+    error: Option<ErrorBody>,
+}
+
+/// An error in HTTP response, which has a stable code for clients and a message for humans.
+#[derive(Serialize)]
+pub struct ErrorBody {
+    code: String,
+    message: String,
+    /// Invalid fields of the request, which are given only for `ServiceError::Validation`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<FieldError>,
 }
 
 impl<T> ServiceResponse<T> {
@@ -21,14 +33,47 @@ impl<T> ServiceResponse<T> {
 
     /// Creates a response containing error.
     fn err(error: ServiceError) -> Self {
+        let code = error.code().to_string();
+        let message = format!("{}", error);
+        let fields = match error {
+            ServiceError::Validation(field_errors) => field_errors,
+            _ => vec![],
+        };
+
         ServiceResponse {
             data: None,
-            error: Some(format!("{}", error)),
+            error: Some(ErrorBody {
+                code,
+                message,
+                fields,
+            }),
         }
     }
 }
 
+/// Returns HTTP status code of the service error.
+///
+/// # Arguments
+///
+/// * `error` - An error of the service.
+fn get_status_code(error: &ServiceError) -> StatusCode {
+    match error {
+        ServiceError::NotFound(_) => StatusCode::NOT_FOUND,
+        ServiceError::InvalidArgument
+        | ServiceError::InvalidFormat
+        | ServiceError::Validation(_) => StatusCode::BAD_REQUEST,
+        ServiceError::DuplicatedKey | ServiceError::Conflict(_) => StatusCode::CONFLICT,
+        ServiceError::Unauthorized(_) | ServiceError::TotpRequired => StatusCode::UNAUTHORIZED,
+        ServiceError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+        ServiceError::Expired => StatusCode::GONE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
 /// Converts service result to HTTP response, and return it.
+/// An error is responded as `{ "data": null, "error": { "code": "NOT_FOUND", "message": "..." } }`,
+/// and internal errors are hidden from clients as `ServiceError::InternalServerError`.
+/// `ServiceError::TooManyRequests` is responded with `Retry-After` header.
 ///
 /// # Arguments
 ///
@@ -36,21 +81,179 @@ impl<T> ServiceResponse<T> {
 pub fn get_response<T: Serialize>(data: Result<T, ServiceError>) -> HttpResponse {
     match data {
         Ok(data) => HttpResponse::Ok().json(ServiceResponse::<T>::ok(data)),
-        Err(ServiceError::NotFound(key)) => {
-            HttpResponse::NotFound().json(ServiceResponse::<T>::err(ServiceError::NotFound(key)))
-        }
-        Err(ServiceError::InvalidArgument) => HttpResponse::BadRequest()
-            .json(ServiceResponse::<T>::err(ServiceError::InvalidArgument)),
-        Err(ServiceError::InvalidFormat) => {
-            HttpResponse::BadRequest().json(ServiceResponse::<T>::err(ServiceError::InvalidFormat))
-        }
-        Err(ServiceError::DuplicatedKey) => {
-            HttpResponse::Conflict().json(ServiceResponse::<T>::err(ServiceError::DuplicatedKey))
+        Err(error) => {
+            let status_code = get_status_code(&error);
+            let mut response = HttpResponse::build(status_code);
+            if let ServiceError::TooManyRequests(retry_after_seconds) = error {
+                response.header(RETRY_AFTER, retry_after_seconds.to_string());
+            }
+
+            let error = if status_code == StatusCode::INTERNAL_SERVER_ERROR {
+                ServiceError::InternalServerError
+            } else {
+                error
+            };
+            response.json(ServiceResponse::<T>::err(error))
         }
-        Err(ServiceError::Unauthorized) => {
-            HttpResponse::Unauthorized().json(ServiceResponse::<T>::err(ServiceError::Unauthorized))
+    }
+}
+
+/// Returns all values of the parameter repeated in the query string, in the given order.
+/// `web::Query` can't deserialize a repeated parameter into a field, so it is read separately.
+///
+/// # Arguments
+///
+/// * `query_string` - A query string of the request.
+/// * `key` - A name of the parameter.
+pub fn get_query_values(query_string: &str, key: &str) -> Vec<String> {
+    match web::Query::<Vec<(String, String)>>::from_query(query_string) {
+        Ok(pairs) => pairs
+            .into_inner()
+            .into_iter()
+            .filter(|(pair_key, _)| pair_key == key)
+            .map(|(_, value)| value)
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::{Body, ResponseBody};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::models::error::UnauthorizedReason;
+
+    /// Returns the JSON body of the response.
+    fn get_body(response: &HttpResponse) -> Value {
+        match response.body() {
+            ResponseBody::Body(Body::Bytes(bytes)) => serde_json::from_slice(bytes).unwrap(),
+            _ => panic!("unexpected response body"),
         }
-        _ => HttpResponse::InternalServerError()
-            .json(ServiceResponse::<T>::err(ServiceError::InternalServerError)),
+    }
+
+    #[test]
+    fn test_get_response() {
+        assert_eq!(get_response::<u64>(Ok(3)).status(), StatusCode::OK);
+        assert_eq!(
+            get_response::<u64>(Err(ServiceError::NotFound(String::from("3")))).status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            get_response::<u64>(Err(ServiceError::Unauthorized(None))).status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[test]
+    fn test_get_response_with_error_body() {
+        let response = get_response::<u64>(Err(ServiceError::Unauthorized(None)));
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            get_body(&response),
+            json!({
+                "data": null,
+                "error": { "code": "UNAUTHORIZED", "message": "unauthorized" }
+            })
+        );
+
+        let response = get_response::<u64>(Err(ServiceError::NotFound(String::from("3"))));
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            get_body(&response)["error"],
+            json!({ "code": "NOT_FOUND", "message": "data for key `3` not found" })
+        );
+
+        let response = get_response::<u64>(Err(ServiceError::InvalidArgument));
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            get_body(&response)["error"],
+            json!({ "code": "INVALID_ARGUMENT", "message": "invalid argument supplied" })
+        );
+    }
+
+    #[test]
+    fn test_get_response_with_validation_error() {
+        let response = get_response::<u64>(Err(ServiceError::Validation(vec![
+            FieldError::new("name", "must not be empty"),
+            FieldError::new("password", "password is too common"),
+        ])));
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            get_body(&response)["error"],
+            json!({
+                "code": "VALIDATION_FAILED",
+                "message": "invalid fields: name, password",
+                "fields": [
+                    { "field": "name", "reason": "must not be empty" },
+                    { "field": "password", "reason": "password is too common" }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_get_response_with_conflict() {
+        let response = get_response::<u64>(Err(ServiceError::Conflict(String::from("3"))));
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            get_body(&response)["error"],
+            json!({
+                "code": "CONFLICT",
+                "message": "data for key `3` conflicts with the current state"
+            })
+        );
+    }
+
+    #[test]
+    fn test_get_response_with_unauthorized_reason() {
+        let response = get_response::<u64>(Err(ServiceError::Unauthorized(Some(
+            UnauthorizedReason::AccountLocked(String::from("2020-08-01 12:15:00")),
+        ))));
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            get_body(&response)["error"],
+            json!({
+                "code": "UNAUTHORIZED",
+                "message": "unauthorized: account locked until `2020-08-01 12:15:00`"
+            })
+        );
+    }
+
+    #[test]
+    fn test_get_response_with_too_many_requests() {
+        let error = ServiceError::TooManyRequests(60);
+        assert_eq!(
+            format!("{}", error),
+            "too many requests, retry after 60 seconds"
+        );
+
+        let response = get_response::<u64>(Err(error));
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(RETRY_AFTER).unwrap(), "60");
+        assert_eq!(
+            get_body(&response)["error"]["code"],
+            json!("TOO_MANY_REQUESTS")
+        );
+    }
+
+    #[test]
+    fn test_get_response_hiding_internal_error() {
+        let response = get_response::<u64>(Err(ServiceError::QueryExecutionFailure));
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            get_body(&response)["error"]["code"],
+            json!("INTERNAL_SERVER_ERROR")
+        );
+    }
+
+    #[test]
+    fn test_get_query_values() {
+        assert_eq!(
+            get_query_values("tag=travel&limit=20&tag=food%20trip", "tag"),
+            vec![String::from("travel"), String::from("food trip")]
+        );
+        assert!(get_query_values("limit=20", "tag").is_empty());
     }
 }
@@ -0,0 +1,217 @@
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::Error;
+use chrono::Utc;
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tracing::Instrument;
+
+use crate::utils::token_util;
+
+/// A header having the correlation id of the request, which is echoed in the response.
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+/// A length of a generated correlation id.
+const REQUEST_ID_LENGTH: usize = 20;
+/// A maximum length of a correlation id given by the client.
+const MAX_REQUEST_ID_LENGTH: usize = 64;
+
+thread_local! {
+    /// A correlation id of the request being processed on this thread.
+    static CURRENT_REQUEST_ID: RefCell<Option<String>> = RefCell::default();
+}
+
+/// Returns the correlation id of the request being processed, if any.
+pub fn get_request_id() -> Option<String> {
+    CURRENT_REQUEST_ID.with(|request_id| request_id.borrow().clone())
+}
+
+/// Runs the function with the correlation id of the request, and restores the previous one.
+///
+/// # Arguments
+///
+/// * `request_id` - A correlation id of the request
+/// * `f` - A function processing the request
+pub fn in_scope<T, F: FnOnce() -> T>(request_id: &str, f: F) -> T {
+    let previous_request_id =
+        CURRENT_REQUEST_ID.with(|current| current.replace(Some(request_id.to_string())));
+    let result = f();
+    CURRENT_REQUEST_ID.with(|current| current.replace(previous_request_id));
+    result
+}
+
+/// A future processing a request with its correlation id. Requests are interleaved on a worker
+/// thread, so the id is set only while the future is polled.
+pub struct RequestScoped<F> {
+    request_id: String,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for RequestScoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let RequestScoped { request_id, future } = &mut *self;
+        in_scope(request_id, || future.as_mut().poll(cx))
+    }
+}
+
+/// Returns the correlation id given by `X-Request-Id` header if it is valid, or a new one.
+/// A valid id has at most 64 alphanumeric characters, hyphens and underscores.
+pub fn get_or_generate_request_id(headers: &HeaderMap) -> String {
+    let given_request_id = headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| {
+            !value.is_empty()
+                && value.len() <= MAX_REQUEST_ID_LENGTH
+                && value
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        });
+
+    match given_request_id {
+        Some(request_id) => request_id.to_string(),
+        None => token_util::get_random_string(REQUEST_ID_LENGTH),
+    }
+}
+
+/// Returns the log line of the message with the time and the correlation id of the request being
+/// processed, such as `[2020-04-12 07:43:03 UTC] [request_id=a1lam9cBko] message`.
+pub fn format_log(message: &dyn Display) -> String {
+    match get_request_id() {
+        Some(request_id) => format!("[{}] [request_id={}] {}", Utc::now(), request_id, message),
+        None => format!("[{}] {}", Utc::now(), message),
+    }
+}
+
+/// Logs the message with the correlation id of the request being processed.
+pub fn log(message: &dyn Display) {
+    println!("{}", format_log(message));
+}
+
+/// A middleware logging each request with its correlation id, method, path, status and latency.
+///
+/// The correlation id is taken from `X-Request-Id` header or generated, and echoed in the
+/// response. It is also included in logs of the request such as service errors, and recorded in
+/// the tracing span of the request.
+///
+/// # Arguments
+///
+/// * `req` - A request
+/// * `srv` - A service processing the request
+pub fn trace_request<S, B>(
+    req: ServiceRequest,
+    srv: &mut S,
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    let request_id = get_or_generate_request_id(req.headers());
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+    let span = tracing::info_span!(
+        "request",
+        request_id = request_id.as_str(),
+        method = method.as_str(),
+        path = path.as_str()
+    );
+
+    let started_at = Instant::now();
+    let response = in_scope(&request_id, || srv.call(req));
+    let header_value = HeaderValue::from_str(&request_id);
+
+    RequestScoped {
+        request_id,
+        future: Box::pin(async move {
+            let mut response = response.await?;
+            if let Ok(header_value) = header_value {
+                response
+                    .headers_mut()
+                    .insert(HeaderName::from_static("x-request-id"), header_value);
+            }
+
+            log(&format!(
+                "method={} path={} status={} latency_ms={:.3}",
+                method,
+                path,
+                response.status().as_u16(),
+                started_at.elapsed().as_secs_f64() * 1000.0,
+            ));
+            Ok(response)
+        }),
+    }
+    .instrument(span)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App, HttpResponse};
+
+    use super::*;
+    use crate::models::error::{get_service_error, ServiceError};
+
+    async fn respond_error_log() -> HttpResponse {
+        let error = get_service_error(ServiceError::InvalidArgument);
+        HttpResponse::BadRequest().body(format_log(&error))
+    }
+
+    #[test]
+    fn test_get_or_generate_request_id() {
+        let req = test::TestRequest::default()
+            .header(REQUEST_ID_HEADER, "a1lam9cBko-3")
+            .to_srv_request();
+        assert_eq!(get_or_generate_request_id(req.headers()), "a1lam9cBko-3");
+
+        let req = test::TestRequest::default()
+            .header(REQUEST_ID_HEADER, "a1lam9cBko\" injected=true")
+            .to_srv_request();
+        let request_id = get_or_generate_request_id(req.headers());
+        assert_eq!(request_id.len(), REQUEST_ID_LENGTH);
+        assert!(request_id.chars().all(|c| c.is_ascii_alphanumeric()));
+
+        let req = test::TestRequest::default().to_srv_request();
+        assert_eq!(
+            get_or_generate_request_id(req.headers()).len(),
+            REQUEST_ID_LENGTH
+        );
+    }
+
+    #[test]
+    fn test_format_log_with_request_id() {
+        let error_log = in_scope("a1lam9cBko", || {
+            format_log(&get_service_error(ServiceError::InvalidArgument))
+        });
+        assert!(error_log.ends_with("[request_id=a1lam9cBko] invalid argument supplied"));
+
+        assert_eq!(get_request_id(), None);
+        assert!(!format_log(&ServiceError::InvalidArgument).contains("request_id"));
+    }
+
+    #[actix_rt::test]
+    async fn test_trace_request() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap_fn(trace_request)
+                .route("/posts", web::get().to(respond_error_log)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/posts")
+            .header(REQUEST_ID_HEADER, "a1lam9cBko")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "a1lam9cBko");
+        let error_log = test::read_body(resp).await;
+        assert!(error_log.ends_with(b"[request_id=a1lam9cBko] invalid argument supplied"));
+
+        let req = test::TestRequest::get().uri("/posts").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let request_id = resp.headers().get(REQUEST_ID_HEADER).unwrap();
+        assert_eq!(request_id.len(), REQUEST_ID_LENGTH);
+    }
+}
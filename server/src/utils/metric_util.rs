@@ -0,0 +1,161 @@
+use lazy_static::lazy_static;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use std::sync::Once;
+
+lazy_static! {
+    /// A registry of all metrics exposed by `GET /metrics`.
+    static ref REGISTRY: Registry = Registry::new();
+
+    /// A number of logins, labeled by `result` which is `success` or `failure`.
+    static ref LOGINS: IntCounterVec = IntCounterVec::new(
+        Opts::new("darim_logins_total", "Number of logins"),
+        &["result"]
+    )
+    .unwrap();
+
+    /// A number of sign up tokens issued.
+    static ref SIGN_UP_TOKENS_ISSUED: IntCounter = IntCounter::new(
+        "darim_sign_up_tokens_issued_total",
+        "Number of sign up tokens issued"
+    )
+    .unwrap();
+
+    /// A number of posts created.
+    static ref POSTS_CREATED: IntCounter =
+        IntCounter::new("darim_posts_created_total", "Number of posts created").unwrap();
+
+    /// A number of posts deleted.
+    static ref POSTS_DELETED: IntCounter =
+        IntCounter::new("darim_posts_deleted_total", "Number of posts deleted").unwrap();
+
+    /// A number of emails sent, labeled by `result` which is `success` or `failure`.
+    static ref EMAIL_SENDS: IntCounterVec = IntCounterVec::new(
+        Opts::new("darim_email_sends_total", "Number of email sends"),
+        &["result"]
+    )
+    .unwrap();
+
+    /// A latency of HTTP requests, labeled by `method`, the route `pattern` and `status`.
+    static ref REQUEST_DURATION: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            "darim_http_request_duration_seconds",
+            "Latency of HTTP requests in seconds"
+        ),
+        &["method", "pattern", "status"]
+    )
+    .unwrap();
+}
+
+static REGISTER_METRICS: Once = Once::new();
+
+/// Registers all metrics to the registry. It is called once at startup,
+/// and does nothing if the metrics have already been registered.
+pub fn register_metrics() {
+    REGISTER_METRICS.call_once(|| {
+        REGISTRY.register(Box::new(LOGINS.clone())).unwrap();
+        REGISTRY
+            .register(Box::new(SIGN_UP_TOKENS_ISSUED.clone()))
+            .unwrap();
+        REGISTRY.register(Box::new(POSTS_CREATED.clone())).unwrap();
+        REGISTRY.register(Box::new(POSTS_DELETED.clone())).unwrap();
+        REGISTRY.register(Box::new(EMAIL_SENDS.clone())).unwrap();
+        REGISTRY
+            .register(Box::new(REQUEST_DURATION.clone()))
+            .unwrap();
+    });
+}
+
+/// Returns a label of the result of an operation.
+fn get_result_label(is_success: bool) -> &'static str {
+    if is_success {
+        "success"
+    } else {
+        "failure"
+    }
+}
+
+/// Counts a login.
+pub fn count_login(is_success: bool) {
+    LOGINS
+        .with_label_values(&[get_result_label(is_success)])
+        .inc();
+}
+
+/// Counts a sign up token issued.
+pub fn count_sign_up_token_issued() {
+    SIGN_UP_TOKENS_ISSUED.inc();
+}
+
+/// Counts a post created.
+pub fn count_post_created() {
+    POSTS_CREATED.inc();
+}
+
+/// Counts a post deleted.
+pub fn count_post_deleted() {
+    POSTS_DELETED.inc();
+}
+
+/// Counts an email send.
+pub fn count_email_send(is_success: bool) {
+    EMAIL_SENDS
+        .with_label_values(&[get_result_label(is_success)])
+        .inc();
+}
+
+/// Records the latency of an HTTP request.
+///
+/// # Arguments
+///
+/// * `method` - A method of the request
+/// * `pattern` - A pattern of the matched route such as `/posts/{id}`, not to label each id
+/// * `status` - A status code of the response
+/// * `seconds` - A time taken to respond
+pub fn observe_request_duration(method: &str, pattern: &str, status: u16, seconds: f64) {
+    REQUEST_DURATION
+        .with_label_values(&[method, pattern, &status.to_string()])
+        .observe(seconds);
+}
+
+/// Returns all registered metrics in the Prometheus text format.
+pub fn render_metrics() -> String {
+    let mut buffer = vec![];
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buffer)
+        .unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_metrics() {
+        register_metrics();
+        register_metrics();
+
+        count_login(true);
+        count_login(false);
+        count_sign_up_token_issued();
+        count_post_created();
+        count_post_deleted();
+        count_email_send(true);
+        observe_request_duration("GET", "/posts/{id}", 200, 0.01);
+
+        let metrics = render_metrics();
+        for name in &[
+            "darim_logins_total{result=\"success\"}",
+            "darim_logins_total{result=\"failure\"}",
+            "darim_sign_up_tokens_issued_total",
+            "darim_posts_created_total",
+            "darim_posts_deleted_total",
+            "darim_email_sends_total{result=\"success\"}",
+            "darim_http_request_duration_seconds_bucket",
+        ] {
+            assert!(metrics.contains(name), "{} not found", name);
+        }
+    }
+}
@@ -1,7 +1,228 @@
+use argon2::{Config, Variant, Version};
 use cfg_if::cfg_if;
-use scrypt::{scrypt_check, scrypt_simple, ScryptParams};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use scrypt::scrypt_check;
+use sha1::{Digest, Sha1};
 
-/// Returns a password that is hashed by scrypt.
+use crate::models::error::PasswordPolicyError;
+use crate::models::pwned_password::*;
+use crate::utils::{env_util, log_util};
+
+#[cfg(test)]
+use crate::models::pwned_password::MockPwnedPasswordRepositoryTrait as PwnedPasswordRepository;
+
+/// A prefix of the password hashed by Argon2id.
+const ARGON2ID_PREFIX: &str = "$argon2id$";
+/// A prefix of the legacy password hashed by scrypt.
+const SCRYPT_PREFIX: &str = "$rscrypt$";
+/// A maximum number of lanes of Argon2.
+const MAX_ARGON2_PARALLELISM: u32 = 0x00ff_ffff;
+
+/// Passwords that are too common to be used.
+const COMMON_PASSWORDS: [&str; 20] = [
+    "password",
+    "password1",
+    "password12",
+    "password123",
+    "passw0rd",
+    "qwerty",
+    "qwerty123",
+    "qwertyuiop",
+    "1q2w3e4r5t",
+    "1qaz2wsx3edc",
+    "abc123",
+    "abcdefghij",
+    "iloveyou",
+    "letmein",
+    "welcome",
+    "welcome123",
+    "admin",
+    "administrator",
+    "sunshine",
+    "football",
+];
+
+/// Parameters of Argon2id used for password hashing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PasswordHashParams {
+    /// The amount of memory in KiB.
+    pub memory_cost: u32,
+    /// The number of passes.
+    pub time_cost: u32,
+    /// The number of lanes.
+    pub parallelism: u32,
+}
+
+impl PasswordHashParams {
+    /// Reads parameters from `ARGON2_MEMORY_COST`, `ARGON2_TIME_COST`, and `ARGON2_PARALLELISM`.
+    pub fn from_env() -> Self {
+        let default_params = get_default_params_for_password_hashing();
+        Self {
+            memory_cost: env_util::get_env_var_or("ARGON2_MEMORY_COST", default_params.memory_cost),
+            time_cost: env_util::get_env_var_or("ARGON2_TIME_COST", default_params.time_cost),
+            parallelism: env_util::get_env_var_or("ARGON2_PARALLELISM", default_params.parallelism),
+        }
+    }
+
+    /// Checks whether Argon2 accepts the parameters. It is called at startup so that hashing
+    /// passwords doesn't fail on every request.
+    /// Returns the environment variable of the invalid parameter with the reason.
+    pub fn validate(&self) -> Result<(), (&'static str, String)> {
+        if self.parallelism < 1 || self.parallelism > MAX_ARGON2_PARALLELISM {
+            return Err((
+                "ARGON2_PARALLELISM",
+                format!(
+                    "it must be between 1 and {}, but {} is configured",
+                    MAX_ARGON2_PARALLELISM, self.parallelism
+                ),
+            ));
+        }
+        if self.time_cost < 1 {
+            return Err((
+                "ARGON2_TIME_COST",
+                String::from("it must be at least 1, but 0 is configured"),
+            ));
+        }
+        if self.memory_cost < 8 * self.parallelism {
+            return Err((
+                "ARGON2_MEMORY_COST",
+                format!(
+                    "it must be at least 8 KiB per lane, {} KiB, but {} is configured",
+                    8 * self.parallelism,
+                    self.memory_cost
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn get_config(&self) -> Config<'_> {
+        Config {
+            variant: Variant::Argon2id,
+            version: Version::Version13,
+            mem_cost: self.memory_cost,
+            time_cost: self.time_cost,
+            lanes: self.parallelism,
+            ..Config::default()
+        }
+    }
+}
+
+impl Default for PasswordHashParams {
+    fn default() -> Self {
+        get_default_params_for_password_hashing()
+    }
+}
+
+cfg_if! {
+    if #[cfg(test)] {
+        fn get_default_params_for_password_hashing() -> PasswordHashParams {
+            PasswordHashParams {
+                memory_cost: 64,
+                time_cost: 1,
+                parallelism: 1,
+            }
+        }
+    } else {
+        fn get_default_params_for_password_hashing() -> PasswordHashParams {
+            PasswordHashParams {
+                memory_cost: 65536,
+                time_cost: 3,
+                parallelism: 1,
+            }
+        }
+    }
+}
+
+/// Rules of password strength.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PasswordPolicy {
+    /// The minimum number of characters.
+    pub min_length: usize,
+    /// Whether to reject passwords consisting only of numbers.
+    pub reject_all_numeric: bool,
+    /// Whether to reject commonly used passwords.
+    pub reject_common: bool,
+    /// The number of previous passwords which can't be reused.
+    pub history_size: usize,
+}
+
+impl PasswordPolicy {
+    /// Reads rules from `PASSWORD_MIN_LENGTH`, `PASSWORD_REJECT_ALL_NUMERIC`, `PASSWORD_REJECT_COMMON`,
+    /// and `PASSWORD_HISTORY_SIZE`.
+    pub fn from_env() -> Self {
+        Self {
+            min_length: env_util::get_env_var_or("PASSWORD_MIN_LENGTH", 10),
+            reject_all_numeric: env_util::get_env_var_or("PASSWORD_REJECT_ALL_NUMERIC", true),
+            reject_common: env_util::get_env_var_or("PASSWORD_REJECT_COMMON", true),
+            history_size: env_util::get_env_var_or("PASSWORD_HISTORY_SIZE", 5),
+        }
+    }
+}
+
+/// Checks whether the password satisfies the password policy read from environment variables.
+///
+/// # Arguments
+///
+/// * `password` - A password to check
+pub fn validate_strength(password: &str) -> Result<(), PasswordPolicyError> {
+    validate_strength_with_policy(password, &PasswordPolicy::from_env())
+}
+
+/// Checks whether the password satisfies the given password policy.
+///
+/// # Arguments
+///
+/// * `password` - A password to check
+/// * `policy` - Rules of password strength
+pub fn validate_strength_with_policy(
+    password: &str,
+    policy: &PasswordPolicy,
+) -> Result<(), PasswordPolicyError> {
+    if password.chars().count() < policy.min_length {
+        return Err(PasswordPolicyError::TooShort(policy.min_length));
+    }
+
+    if policy.reject_all_numeric && password.chars().all(|c| c.is_ascii_digit()) {
+        return Err(PasswordPolicyError::AllNumeric);
+    }
+
+    if policy.reject_common && COMMON_PASSWORDS.contains(&password.to_lowercase().as_str()) {
+        return Err(PasswordPolicyError::Common);
+    }
+
+    Ok(())
+}
+
+/// Returns whether the password has appeared in data breaches.
+///
+/// It uses the k-anonymity model of the Pwned Passwords API:
+/// only the first 5 hex characters of the SHA-1 hash are sent, and the rest is compared locally.
+/// If the API is unreachable, it logs a warning and fails open by returning `false`.
+///
+/// # Arguments
+///
+/// * `password` - A password to check
+/// * `repository` - A repository requesting the Pwned Passwords API
+pub async fn is_breached(password: &str, repository: &PwnedPasswordRepository) -> bool {
+    let hash = format!("{:X}", Sha1::digest(password.as_bytes()));
+    let (hash_prefix, hash_suffix) = hash.split_at(5);
+
+    match repository.find_range(hash_prefix).await {
+        Ok(range) => range.lines().any(|line| match line.split(':').next() {
+            Some(breached_hash_suffix) => breached_hash_suffix.trim() == hash_suffix,
+            None => false,
+        }),
+        Err(_) => {
+            log_util::log(&"warning: failed to check breached password, allowing it");
+            false
+        }
+    }
+}
+
+/// Returns a password that is hashed by Argon2id with parameters read from environment variables.
 ///
 /// # Arguments
 ///
@@ -10,37 +231,39 @@ use scrypt::{scrypt_check, scrypt_simple, ScryptParams};
 /// # Example
 ///
 /// ```ignore
-/// use scrypt::scrypt_check;
-/// use darim::utils::password_util::get_hashed_password;
+/// use darim::utils::password_util::{check_password, get_hashed_password};
 ///
 /// let password = String::from("123");
 /// let hashed_password = get_hashed_password(&password);
 ///
-/// assert!(scrypt_check(&password, &hashed_password).is_ok());
+/// assert!(check_password(&password, &hashed_password));
 /// ```
 pub fn get_hashed_password(password: &str) -> String {
-    let params = get_params_for_password_hashing();
-    scrypt_simple(password, &params).unwrap()
+    get_hashed_password_with_params(password, &PasswordHashParams::from_env())
 }
 
-cfg_if! {
-    if #[cfg(test)] {
-        fn get_params_for_password_hashing() -> ScryptParams {
-            ScryptParams::new(7, 4, 1).unwrap()
-        }
-    } else {
-        fn get_params_for_password_hashing() -> ScryptParams {
-            ScryptParams::new(15, 8, 1).unwrap()
-        }
-    }
+/// Returns a password that is hashed by Argon2id with the given parameters.
+///
+/// # Arguments
+///
+/// * `password` - A password to be hashed
+/// * `params` - Parameters of Argon2id
+pub fn get_hashed_password_with_params(password: &str, params: &PasswordHashParams) -> String {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    argon2::hash_encoded(password.as_bytes(), &salt, &params.get_config()).unwrap()
 }
 
 /// Compares a plain-text password between hashed password
 ///
+/// Both Argon2id hashes and legacy scrypt hashes are supported.
+/// The comparison is delegated to `argon2::verify_encoded` or `scrypt_check`, which compare the derived key
+/// with the stored hash in constant time, so the result can't be inferred from the response time.
+///
 /// # Arguments
 ///
 /// * `password` - A password to compare
-/// * `hashed_password` - A hashed password returned by scrypt_simple()
+/// * `hashed_password` - A hashed password returned by get_hashed_password() or scrypt_simple()
 ///
 /// # Example
 ///
@@ -55,21 +278,57 @@ cfg_if! {
 /// assert!(check_password(&password, &hashed_password));
 /// ```
 pub fn check_password(password: &str, hashed_password: &str) -> bool {
-    scrypt_check(password, hashed_password).is_ok()
+    if hashed_password.starts_with(ARGON2ID_PREFIX) {
+        argon2::verify_encoded(hashed_password, password.as_bytes()).unwrap_or(false)
+    } else if hashed_password.starts_with(SCRYPT_PREFIX) {
+        scrypt_check(password, hashed_password).is_ok()
+    } else {
+        false
+    }
+}
+
+/// Returns whether the password matches any of the hashed passwords.
+/// Each hash is salted, so the password is verified against each of them by `check_password`.
+///
+/// # Arguments
+///
+/// * `password` - A password to compare
+/// * `hashed_passwords` - Hashed passwords used before
+pub fn is_reused(password: &str, hashed_passwords: &[String]) -> bool {
+    hashed_passwords
+        .iter()
+        .any(|hashed_password| check_password(password, hashed_password))
+}
+
+/// Returns whether the hashed password should be rehashed with the given parameters.
+/// It is true if the password is hashed by the legacy scheme or with different parameters.
+///
+/// # Arguments
+///
+/// * `hashed_password` - A hashed password
+/// * `params` - Parameters of Argon2id to be used
+pub fn needs_rehash(hashed_password: &str, params: &PasswordHashParams) -> bool {
+    let expected_prefix = format!(
+        "{}v=19$m={},t={},p={}$",
+        ARGON2ID_PREFIX, params.memory_cost, params.time_cost, params.parallelism
+    );
+    !hashed_password.starts_with(&expected_prefix)
 }
 
 #[cfg(test)]
 mod tests {
-    use scrypt::{scrypt_check, scrypt_simple, ScryptParams};
+    use scrypt::{scrypt_simple, ScryptParams};
 
     use super::*;
+    use crate::models::error::ServiceError;
 
     #[test]
     fn test_get_hashed_password() {
         let password = String::from("123");
         let hashed_password = get_hashed_password(&password);
 
-        assert!(scrypt_check(&password, &hashed_password).is_ok());
+        assert!(hashed_password.starts_with(ARGON2ID_PREFIX));
+        assert!(argon2::verify_encoded(&hashed_password, password.as_bytes()).unwrap());
     }
 
     #[test]
@@ -80,4 +339,128 @@ mod tests {
 
         assert!(check_password(&password, &hashed_password));
     }
+
+    #[test]
+    fn test_check_password_with_correct_and_incorrect_password() {
+        let password = String::from("Ir5c7y8dS3");
+        let hashed_password = get_hashed_password(&password);
+
+        assert!(check_password(&password, &hashed_password));
+        assert!(!check_password("Ir5c7y8dS4", &hashed_password));
+        assert!(!check_password("", &hashed_password));
+        assert!(!check_password(&password, "invalid hash"));
+    }
+
+    #[test]
+    fn test_is_reused() {
+        let hashed_passwords = vec![
+            get_hashed_password("Ir5c7y8dS3"),
+            get_hashed_password("Ir5c7y8dS3"),
+            get_hashed_password("9nWq2xKb7L"),
+        ];
+
+        assert_ne!(hashed_passwords[0], hashed_passwords[1]);
+        assert!(is_reused("Ir5c7y8dS3", &hashed_passwords));
+        assert!(is_reused("9nWq2xKb7L", &hashed_passwords));
+        assert!(!is_reused("Ir5c7y8dS4", &hashed_passwords));
+        assert!(!is_reused("Ir5c7y8dS3", &[]));
+    }
+
+    #[test]
+    fn test_needs_rehash() {
+        let params = PasswordHashParams::from_env();
+        let stronger_params = PasswordHashParams {
+            time_cost: params.time_cost + 1,
+            ..params.clone()
+        };
+        let legacy_hashed_password =
+            scrypt_simple("123", &ScryptParams::new(7, 4, 1).unwrap()).unwrap();
+        let hashed_password = get_hashed_password_with_params("123", &params);
+
+        assert!(needs_rehash(&legacy_hashed_password, &params));
+        assert!(!needs_rehash(&hashed_password, &params));
+        assert!(needs_rehash(&hashed_password, &stronger_params));
+    }
+
+    fn get_password_policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 10,
+            reject_all_numeric: true,
+            reject_common: true,
+            history_size: 5,
+        }
+    }
+
+    #[test]
+    fn test_validate_strength_with_too_short_password() {
+        let result = validate_strength_with_policy("Ir5c7y8", &get_password_policy());
+        assert_eq!(result, Err(PasswordPolicyError::TooShort(10)));
+    }
+
+    #[test]
+    fn test_validate_strength_with_all_numeric_password() {
+        let result = validate_strength_with_policy("0123456789", &get_password_policy());
+        assert_eq!(result, Err(PasswordPolicyError::AllNumeric));
+    }
+
+    #[test]
+    fn test_validate_strength_with_common_password() {
+        let result = validate_strength_with_policy("QwertyUIOP", &get_password_policy());
+        assert_eq!(result, Err(PasswordPolicyError::Common));
+    }
+
+    #[test]
+    fn test_validate_strength_with_acceptable_password() {
+        assert!(validate_strength_with_policy("Ir5c7y8dS3", &get_password_policy()).is_ok());
+
+        let lenient_policy = PasswordPolicy {
+            min_length: 4,
+            reject_all_numeric: false,
+            reject_common: false,
+            history_size: 0,
+        };
+        assert!(validate_strength_with_policy("1234", &lenient_policy).is_ok());
+    }
+
+    fn get_pwned_password_repository(
+        range: Result<&'static str, ServiceError>,
+    ) -> PwnedPasswordRepository {
+        let mut mocked_pwned_password_repository = PwnedPasswordRepository::new();
+        mocked_pwned_password_repository
+            .expect_find_range()
+            // SHA-1 of "password" is 5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8.
+            .withf(|hash_prefix| hash_prefix == "5BAA6")
+            .times(1)
+            .returning(move |_| {
+                let range = match &range {
+                    Ok(range) => Ok(range.to_string()),
+                    Err(_) => Err(ServiceError::QueryExecutionFailure),
+                };
+                Box::pin(async move { range })
+            });
+        mocked_pwned_password_repository
+    }
+
+    #[actix_rt::test]
+    async fn test_is_breached_with_breached_password() {
+        let repository = get_pwned_password_repository(Ok(
+            "1E4C9B93F3F0682250B6CF8331B7EE68FC7:2\r\n1E4C9B93F3F0682250B6CF8331B7EE68FD8:3861493",
+        ));
+
+        assert!(is_breached("password", &repository).await);
+    }
+
+    #[actix_rt::test]
+    async fn test_is_breached_with_clean_password() {
+        let repository = get_pwned_password_repository(Ok("1E4C9B93F3F0682250B6CF8331B7EE68FC7:2"));
+
+        assert!(!is_breached("password", &repository).await);
+    }
+
+    #[actix_rt::test]
+    async fn test_is_breached_with_unreachable_api() {
+        let repository = get_pwned_password_repository(Err(ServiceError::QueryExecutionFailure));
+
+        assert!(!is_breached("password", &repository).await);
+    }
 }
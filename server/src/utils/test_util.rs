@@ -0,0 +1,151 @@
+use chrono::Utc;
+#[cfg(feature = "hibp")]
+use sha1::{Digest, Sha1};
+use std::sync::{Arc, Mutex};
+
+use crate::models::auth::MockSessionEpochRepositoryTrait as SessionEpochRepository;
+use crate::models::password_history::{
+    MockPasswordHistoryRepositoryTrait as PasswordHistoryRepository, PasswordHistory,
+};
+use crate::models::pwned_password::MockPwnedPasswordRepositoryTrait as PwnedPasswordRepository;
+use crate::models::user::{User, UserStatus};
+use crate::models::user_key::UserKey;
+use crate::models::webhook::Webhook;
+
+/// Returns an active user named `park`, whose password is not a hash of any password.
+/// Fields are overridden by the struct update syntax, such as `User { locale, ..get_user(1, email) }`.
+pub fn get_user(id: u64, email: &str) -> User {
+    User {
+        id,
+        name: String::from("park"),
+        email: email.to_string(),
+        password: String::from("hashed password"),
+        avatar_url: None,
+        created_at: Utc::now().naive_utc(),
+        updated_at: None,
+        locked_until: None,
+        totp_secret: None,
+        totp_enabled: false,
+        has_password: true,
+        last_login_at: None,
+        previous_login_at: None,
+        timezone: None,
+        reminder_enabled: false,
+        reminder_hour: 21,
+        reminder_sent_on: None,
+        locale: None,
+        status: UserStatus::Active,
+        tos_version_accepted: None,
+    }
+}
+
+/// Returns the public key of the user, which has never been rotated.
+pub fn get_user_key(user_id: u64) -> UserKey {
+    UserKey {
+        id: 1,
+        user_id,
+        public_key: String::from("d63ee429"),
+        created_at: Utc::now().naive_utc(),
+        updated_at: None,
+        previous_public_key: None,
+        rotated_at: None,
+    }
+}
+
+/// Returns the webhook of the user to `https://example.com/hooks/darim`.
+pub fn get_webhook(id: u64, user_id: u64) -> Webhook {
+    Webhook {
+        id,
+        user_id,
+        url: String::from("https://example.com/hooks/darim"),
+        secret: String::from("It's a secret"),
+        created_at: Utc::now().naive_utc(),
+    }
+}
+
+/// Returns a session epoch repository which always finds the epoch.
+pub fn get_session_epoch_repository(epoch: u64) -> SessionEpochRepository {
+    let mut mocked_session_epoch_repository = SessionEpochRepository::default();
+    mocked_session_epoch_repository
+        .expect_find()
+        .returning(move || Ok(epoch));
+    mocked_session_epoch_repository
+}
+
+/// Returns a pwned password repository which has no breached password.
+pub fn get_clean_pwned_password_repository() -> PwnedPasswordRepository {
+    let mut mocked_pwned_password_repository = PwnedPasswordRepository::new();
+    mocked_pwned_password_repository
+        .expect_find_range()
+        .returning(|_| Box::pin(async { Ok(String::new()) }));
+    mocked_pwned_password_repository
+}
+
+/// Returns a pwned password repository which finds the password in data breaches.
+#[cfg(feature = "hibp")]
+pub fn get_breached_pwned_password_repository(password: &str) -> PwnedPasswordRepository {
+    let hash = format!("{:X}", Sha1::digest(password.as_bytes()));
+    let (hash_prefix, hash_suffix) = hash.split_at(5);
+    let hash_prefix = hash_prefix.to_string();
+    let range = format!(
+        "0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n{}:3861493",
+        hash_suffix
+    );
+
+    let mut mocked_pwned_password_repository = PwnedPasswordRepository::new();
+    mocked_pwned_password_repository
+        .expect_find_range()
+        .withf(move |prefix| prefix == hash_prefix)
+        .times(1)
+        .returning(move |_| {
+            let range = range.clone();
+            Box::pin(async move { Ok(range) })
+        });
+    mocked_pwned_password_repository
+}
+
+/// Returns a password history repository which keeps the hashed passwords in the given list
+/// from the oldest one.
+pub fn get_in_memory_password_history_repository(
+    hashed_passwords: Arc<Mutex<Vec<String>>>,
+) -> PasswordHistoryRepository {
+    let mut mocked_password_history_repository = PasswordHistoryRepository::new();
+    let found_hashed_passwords = hashed_passwords.clone();
+    mocked_password_history_repository
+        .expect_find_recent_by_user_id()
+        .returning(move |user_id, limit| {
+            Ok(found_hashed_passwords
+                .lock()
+                .unwrap()
+                .iter()
+                .rev()
+                .take(limit)
+                .enumerate()
+                .map(|(index, hashed_password)| PasswordHistory {
+                    id: index as u64,
+                    user_id,
+                    hashed_password: hashed_password.clone(),
+                    created_at: Utc::now().naive_utc(),
+                })
+                .collect())
+        });
+    let created_hashed_passwords = hashed_passwords.clone();
+    mocked_password_history_repository
+        .expect_create()
+        .returning(move |_, hashed_password| {
+            created_hashed_passwords
+                .lock()
+                .unwrap()
+                .push(hashed_password.to_string());
+            Ok(true)
+        });
+    mocked_password_history_repository
+        .expect_delete_all_except_recent()
+        .returning(move |_, count| {
+            let mut hashed_passwords = hashed_passwords.lock().unwrap();
+            let deleted_count = hashed_passwords.len().saturating_sub(count);
+            hashed_passwords.drain(..deleted_count);
+            Ok(deleted_count)
+        });
+    mocked_password_history_repository
+}
@@ -0,0 +1,131 @@
+use rand::distributions::Alphanumeric;
+use rand::rngs::OsRng;
+use rand::Rng;
+
+use crate::utils::env_util;
+
+/// A minimum length of a pin, under which it can be guessed within the lifetime of the token.
+pub const MIN_PIN_LENGTH: usize = 6;
+/// A length of the sign up pin, if it is not configured.
+pub const DEFAULT_SIGN_UP_PIN_LENGTH: usize = 8;
+/// A length of the password reset id, if it is not configured.
+pub const DEFAULT_PASSWORD_RESET_ID_LENGTH: usize = 32;
+
+/// A format of a pin sent to the user, such as the sign up pin and the password reset id.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PinFormat {
+    /// The number of characters.
+    pub length: usize,
+    /// Whether the pin consists only of numbers, which is easier to enter on mobile.
+    pub numeric_only: bool,
+}
+
+impl PinFormat {
+    /// Reads the format of the sign up pin from `SIGN_UP_PIN_LENGTH` and
+    /// `SIGN_UP_PIN_NUMERIC_ONLY`.
+    pub fn sign_up_pin_from_env() -> Self {
+        Self {
+            length: env_util::get_env_var_or("SIGN_UP_PIN_LENGTH", DEFAULT_SIGN_UP_PIN_LENGTH),
+            numeric_only: env_util::get_env_var_or("SIGN_UP_PIN_NUMERIC_ONLY", false),
+        }
+    }
+
+    /// Reads the format of the password reset id from `PASSWORD_RESET_ID_LENGTH` and
+    /// `PASSWORD_RESET_ID_NUMERIC_ONLY`.
+    pub fn password_reset_id_from_env() -> Self {
+        Self {
+            length: env_util::get_env_var_or(
+                "PASSWORD_RESET_ID_LENGTH",
+                DEFAULT_PASSWORD_RESET_ID_LENGTH,
+            ),
+            numeric_only: env_util::get_env_var_or("PASSWORD_RESET_ID_NUMERIC_ONLY", false),
+        }
+    }
+
+    /// Checks whether the pin is long enough. It is called at startup so that an insecure
+    /// configuration is rejected before serving any request.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.length < MIN_PIN_LENGTH {
+            return Err(format!(
+                "A pin must be at least {} characters, but {} is configured",
+                MIN_PIN_LENGTH, self.length
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns a random alphanumeric string used for pins, keys and temporary passwords.
+///
+/// It is generated from `OsRng`, a cryptographically secure random number generator
+/// backed by the operating system, so the result is unpredictable enough for security tokens.
+///
+/// # Arguments
+///
+/// * `length` - A length of the string
+pub fn get_random_string(length: usize) -> String {
+    OsRng.sample_iter(&Alphanumeric).take(length).collect()
+}
+
+/// Returns a random pin of the format from `OsRng`.
+///
+/// # Arguments
+///
+/// * `format` - A format of the pin
+pub fn get_random_pin(format: &PinFormat) -> String {
+    if format.numeric_only {
+        (0..format.length)
+            .map(|_| char::from(b'0' + OsRng.gen_range(0, 10)))
+            .collect()
+    } else {
+        get_random_string(format.length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_random_string() {
+        for &length in &[8, 32, 512] {
+            let random_string = get_random_string(length);
+
+            assert_eq!(random_string.len(), length);
+            assert!(random_string.chars().all(|c| c.is_ascii_alphanumeric()));
+        }
+    }
+
+    #[test]
+    fn test_get_random_string_is_not_repeated() {
+        assert_ne!(get_random_string(32), get_random_string(32));
+    }
+
+    #[test]
+    fn test_get_random_numeric_pin() {
+        let format = PinFormat {
+            length: 6,
+            numeric_only: true,
+        };
+        let pin = get_random_pin(&format);
+
+        assert_eq!(pin.len(), 6);
+        assert!(pin.chars().all(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_validate_too_short_pin_format() {
+        let format = PinFormat {
+            length: 4,
+            numeric_only: true,
+        };
+        assert!(format.validate().is_err());
+
+        let format = PinFormat {
+            length: MIN_PIN_LENGTH,
+            numeric_only: true,
+        };
+        assert!(format.validate().is_ok());
+    }
+}
@@ -0,0 +1,153 @@
+use base32::Alphabet;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_lite::{totp_custom, Sha1};
+
+/// A time step of the code in seconds.
+const STEP_SECONDS: u64 = 30;
+/// A number of digits of the code.
+const DIGITS: u32 = 6;
+/// An issuer displayed in authenticator apps.
+const ISSUER: &str = "Darim";
+
+const ALPHABET: Alphabet = Alphabet::RFC4648 { padding: false };
+
+/// Returns a new random secret encoded in base32.
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; 20];
+    OsRng.fill_bytes(&mut secret);
+    base32::encode(ALPHABET, &secret)
+}
+
+/// Percent-encodes all bytes of the text except the unreserved characters of RFC 3986.
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => {
+                let _ = write!(encoded, "%{:02X}", byte);
+            }
+        }
+    }
+    encoded
+}
+
+/// Returns an otpauth URI that can be rendered as a QR code for authenticator apps.
+/// The issuer and the account name are percent-encoded, so that characters such as `+`, `&` or
+/// `#` in the account name don't break the label or the parameters.
+///
+/// # Arguments
+///
+/// * `secret` - A secret encoded in base32
+/// * `account_name` - A name of the account (e.g. email of the user)
+pub fn get_provisioning_uri(secret: &str, account_name: &str) -> String {
+    let issuer = percent_encode(ISSUER);
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer,
+        percent_encode(account_name),
+        secret,
+        issuer,
+        DIGITS,
+        STEP_SECONDS,
+    )
+}
+
+/// Returns a code of the secret at the time, or `None` if the secret is malformed.
+///
+/// # Arguments
+///
+/// * `secret` - A secret encoded in base32
+/// * `timestamp` - A unix timestamp in seconds
+pub fn get_code(secret: &str, timestamp: u64) -> Option<String> {
+    let decoded_secret = base32::decode(ALPHABET, secret)?;
+    Some(totp_custom::<Sha1>(
+        STEP_SECONDS,
+        DIGITS,
+        &decoded_secret,
+        timestamp,
+    ))
+}
+
+/// Checks a code against the secret following RFC 6238.
+/// A code of the previous or next time step is also accepted to allow clock drift.
+///
+/// # Arguments
+///
+/// * `secret` - A secret encoded in base32
+/// * `code` - A code to check
+pub fn check_code(secret: &str, code: &str) -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    check_code_at(secret, code, now)
+}
+
+fn check_code_at(secret: &str, code: &str, timestamp: u64) -> bool {
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+
+    let timestamps = [
+        timestamp.saturating_sub(STEP_SECONDS),
+        timestamp,
+        timestamp + STEP_SECONDS,
+    ];
+
+    timestamps
+        .iter()
+        .any(|timestamp| get_code(secret, *timestamp).as_deref() == Some(code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "12345678901234567890" in base32, the secret used in RFC 6238 test vectors.
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn test_get_code() {
+        assert_eq!(get_code(RFC_SECRET, 59), Some(String::from("287082")));
+        assert_eq!(
+            get_code(RFC_SECRET, 1_111_111_109),
+            Some(String::from("081804"))
+        );
+        assert_eq!(get_code("not base32!", 59), None);
+    }
+
+    #[test]
+    fn test_check_code_at() {
+        assert!(check_code_at(RFC_SECRET, "287082", 59));
+        assert!(check_code_at(RFC_SECRET, "287082", 59 + STEP_SECONDS));
+        assert!(!check_code_at(RFC_SECRET, "287082", 59 + STEP_SECONDS * 2));
+        assert!(!check_code_at(RFC_SECRET, "28708", 59));
+    }
+
+    #[test]
+    fn test_get_provisioning_uri() {
+        assert_eq!(
+            get_provisioning_uri(RFC_SECRET, "user+tag@x.com"),
+            format!(
+                "otpauth://totp/Darim:user%2Btag%40x.com?secret={}&issuer=Darim&algorithm=SHA1&digits=6&period=30",
+                RFC_SECRET
+            )
+        );
+        assert!(get_provisioning_uri(RFC_SECRET, "a&b#c d@x.com")
+            .starts_with("otpauth://totp/Darim:a%26b%23c%20d%40x.com?"));
+    }
+
+    #[test]
+    fn test_generate_secret() {
+        let secret = generate_secret();
+
+        assert_eq!(secret.len(), 32);
+        assert!(get_code(&secret, 0).is_some());
+    }
+}
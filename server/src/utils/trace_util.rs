@@ -0,0 +1,659 @@
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use actix_web::Error;
+use hmac::{Hmac, Mac, NewMac};
+use rand::rngs::OsRng;
+use rand::Rng;
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::utils::log_util;
+
+/// A header of the W3C trace context, such as `00-<trace id>-<parent span id>-<flags>`.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+/// A name of the service in exported spans.
+const SERVICE_NAME: &str = "darim-server";
+/// An interval between exports of finished spans.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+/// A timeout of a request exporting spans.
+const EXPORT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// A maximum number of finished spans waiting to be exported. Spans over it are dropped, not to
+/// grow unbounded while the collector is unavailable.
+const MAX_PENDING_SPANS: usize = 2048;
+
+/// A kind of a span, numbered as in OTLP.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpanKind {
+    Internal = 1,
+    Server = 2,
+}
+
+/// An identity of a span in a trace.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpanContext {
+    /// A trace id of 32 lowercase hex digits.
+    pub trace_id: String,
+    /// A span id of 16 lowercase hex digits.
+    pub span_id: String,
+    /// Whether spans of the trace are recorded.
+    pub sampled: bool,
+}
+
+impl SpanContext {
+    /// Parses the value of `traceparent` header, which is `None` if it is malformed or has an
+    /// all-zero id.
+    ///
+    /// # Arguments
+    ///
+    /// * `traceparent` - A value of `traceparent` header
+    pub fn from_traceparent(traceparent: &str) -> Option<Self> {
+        let parts: Vec<&str> = traceparent.trim().split('-').collect();
+        let is_valid_hex = |value: &str, length: usize| {
+            value.len() == length
+                && value
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+        };
+        let is_all_zero = |value: &str| value.chars().all(|c| c == '0');
+
+        match parts.as_slice() {
+            [version, trace_id, span_id, flags, ..]
+                if is_valid_hex(version, 2)
+                    && *version != "ff"
+                    && (*version != "00" || parts.len() == 4)
+                    && is_valid_hex(trace_id, 32)
+                    && !is_all_zero(trace_id)
+                    && is_valid_hex(span_id, 16)
+                    && !is_all_zero(span_id)
+                    && is_valid_hex(flags, 2) =>
+            {
+                let flags = u8::from_str_radix(flags, 16).ok()?;
+                Some(Self {
+                    trace_id: trace_id.to_string(),
+                    span_id: span_id.to_string(),
+                    sampled: flags & 1 == 1,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the value of `traceparent` header of the span.
+    pub fn to_traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            self.trace_id, self.span_id, self.sampled as u8
+        )
+    }
+
+    /// Creates a context of a new sampled trace.
+    fn new_root() -> Self {
+        Self {
+            trace_id: get_random_hex(16),
+            span_id: get_random_hex(8),
+            sampled: true,
+        }
+    }
+
+    /// Creates a context of a new span in the same trace.
+    fn new_child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id.clone(),
+            span_id: get_random_hex(8),
+            sampled: self.sampled,
+        }
+    }
+}
+
+/// A finished span to be exported.
+#[derive(Clone, Debug)]
+pub struct Span {
+    pub context: SpanContext,
+    pub parent_span_id: Option<String>,
+    /// A name of the operation, such as `GET /posts/{user_id}` or `PostRepository::find`.
+    pub name: String,
+    pub kind: SpanKind,
+    pub start_time_unix_nano: u128,
+    pub end_time_unix_nano: u128,
+    pub attributes: Vec<(String, String)>,
+    pub is_error: bool,
+}
+
+impl Span {
+    /// Starts a span of the context.
+    fn start(
+        context: SpanContext,
+        parent_span_id: Option<String>,
+        name: &str,
+        kind: SpanKind,
+    ) -> Self {
+        Self {
+            context,
+            parent_span_id,
+            name: name.to_string(),
+            kind,
+            start_time_unix_nano: get_unix_nano(),
+            end_time_unix_nano: 0,
+            attributes: vec![(String::from("operation.name"), name.to_string())],
+            is_error: false,
+        }
+    }
+
+    /// Sets the attribute of the span, replacing the previous value of the key.
+    fn set_attribute(&mut self, key: &str, value: String) {
+        self.attributes
+            .retain(|(attribute_key, _)| attribute_key != key);
+        self.attributes.push((key.to_string(), value));
+    }
+
+    /// Returns the span as an OTLP JSON object.
+    fn to_otlp_json(&self) -> Value {
+        let attributes: Vec<Value> = self
+            .attributes
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+            .collect();
+
+        json!({
+            "traceId": self.context.trace_id,
+            "spanId": self.context.span_id,
+            "parentSpanId": self.parent_span_id.clone().unwrap_or_default(),
+            "name": self.name,
+            "kind": self.kind as u8,
+            "startTimeUnixNano": self.start_time_unix_nano.to_string(),
+            "endTimeUnixNano": self.end_time_unix_nano.to_string(),
+            "attributes": attributes,
+            "status": { "code": if self.is_error { 2 } else { 0 } },
+        })
+    }
+}
+
+/// A tracer collecting finished spans, which are exported via OTLP/HTTP if an endpoint is given.
+pub struct Tracer {
+    spans: Mutex<Vec<Span>>,
+    otlp_endpoint: Option<String>,
+    user_id_hash_key: Vec<u8>,
+}
+
+impl Tracer {
+    /// Creates a tracer only collecting spans, which are taken by `take_spans`.
+    /// User ids are hashed with a random key.
+    pub fn new() -> Self {
+        Self {
+            spans: Mutex::new(vec![]),
+            otlp_endpoint: None,
+            user_id_hash_key: get_random_key(),
+        }
+    }
+
+    /// Creates a tracer exporting spans to the OTLP collector in the background, or `None` if
+    /// the endpoint is not given, in which case tracing is a no-op.
+    ///
+    /// # Arguments
+    ///
+    /// * `otlp_endpoint` - A base URL of the collector, such as `http://localhost:4318`
+    /// * `user_id_hash_key` - A secret key of the hash of user ids, which is random if it is not
+    ///   given, so that hashes can't be correlated across restarts
+    pub fn with_otlp_exporter(
+        otlp_endpoint: Option<String>,
+        user_id_hash_key: Option<String>,
+    ) -> Option<Arc<Self>> {
+        let otlp_endpoint = otlp_endpoint?;
+        let tracer = Arc::new(Self {
+            spans: Mutex::new(vec![]),
+            otlp_endpoint: Some(format!("{}/v1/traces", otlp_endpoint.trim_end_matches('/'))),
+            user_id_hash_key: user_id_hash_key
+                .map(String::into_bytes)
+                .unwrap_or_else(get_random_key),
+        });
+
+        let exporting_tracer = tracer.clone();
+        thread::spawn(move || loop {
+            thread::sleep(EXPORT_INTERVAL);
+            exporting_tracer.export();
+        });
+        Some(tracer)
+    }
+
+    /// Returns the HMAC-SHA256 of the user id keyed by the secret of the tracer, not to export the
+    /// id itself. Unlike a plain hash, it can't be reversed by hashing every possible id.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - An id of the user
+    pub fn hash_user_id(&self, user_id: u64) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.user_id_hash_key)
+            .expect("HMAC can take a key of any size");
+        mac.update(user_id.to_string().as_bytes());
+
+        let mut hash = String::new();
+        for byte in mac.finalize().into_bytes().iter().take(16) {
+            let _ = write!(hash, "{:02x}", byte);
+        }
+        hash
+    }
+
+    /// Records the finished span.
+    fn record(&self, span: Span) {
+        if let Ok(mut spans) = self.spans.lock() {
+            if spans.len() < MAX_PENDING_SPANS {
+                spans.push(span);
+            }
+        }
+    }
+
+    /// Takes all finished spans which are not exported yet.
+    pub fn take_spans(&self) -> Vec<Span> {
+        match self.spans.lock() {
+            Ok(mut spans) => spans.drain(..).collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    /// Exports all finished spans to the collector. Spans are dropped if the export fails.
+    pub fn export(&self) {
+        let otlp_endpoint = match &self.otlp_endpoint {
+            Some(otlp_endpoint) => otlp_endpoint,
+            None => return,
+        };
+        let spans = self.take_spans();
+        if spans.is_empty() {
+            return;
+        }
+
+        let body = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [
+                        { "key": "service.name", "value": { "stringValue": SERVICE_NAME } },
+                    ],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": SERVICE_NAME, "version": env!("CARGO_PKG_VERSION") },
+                    "spans": spans.iter().map(Span::to_otlp_json).collect::<Vec<Value>>(),
+                }],
+            }],
+        });
+        let result = Client::builder()
+            .timeout(EXPORT_REQUEST_TIMEOUT)
+            .build()
+            .and_then(|client| {
+                client
+                    .post(otlp_endpoint)
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(body.to_string())
+                    .send()
+            });
+        if let Err(error) = result {
+            log_util::log(&format!(
+                "Failed to export {} spans: {}",
+                spans.len(),
+                error
+            ));
+        }
+    }
+}
+
+impl Default for Tracer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A span being recorded on this thread, and the tracer recording it.
+#[derive(Clone)]
+struct ActiveSpan {
+    tracer: Arc<Tracer>,
+    context: SpanContext,
+}
+
+thread_local! {
+    /// A span being recorded on this thread, which is the parent of spans started on it.
+    static CURRENT_SPAN: RefCell<Option<ActiveSpan>> = RefCell::default();
+}
+
+/// Runs the function with the span as the current one, and restores the previous one.
+fn in_span_scope<T, F: FnOnce() -> T>(active_span: Option<ActiveSpan>, f: F) -> T {
+    let previous_span = CURRENT_SPAN.with(|current| current.replace(active_span));
+    let result = f();
+    CURRENT_SPAN.with(|current| current.replace(previous_span));
+    result
+}
+
+/// A guard recording a span until it is dropped. It records nothing if there is no sampled span
+/// on this thread, such as when tracing is disabled.
+pub struct SpanGuard {
+    span: Option<(Arc<Tracer>, Span)>,
+    previous_span: Option<ActiveSpan>,
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        if let Some((tracer, mut span)) = self.span.take() {
+            span.end_time_unix_nano = get_unix_nano();
+            let previous_span = self.previous_span.take();
+            CURRENT_SPAN.with(|current| current.replace(previous_span));
+            tracer.record(span);
+        }
+    }
+}
+
+/// Starts a span of the operation as a child of the current span on this thread, and records it
+/// when the returned guard is dropped. The span is the parent of spans started until then.
+///
+/// # Arguments
+///
+/// * `name` - A name of the operation, such as `AuthService::login`
+/// * `user_id` - An id of the user, which is recorded only as a hash
+pub fn start_span(name: &str, user_id: Option<u64>) -> SpanGuard {
+    let parent_span = CURRENT_SPAN
+        .with(|current| current.borrow().clone())
+        .filter(|parent_span| parent_span.context.sampled);
+    let parent_span = match parent_span {
+        Some(parent_span) => parent_span,
+        None => {
+            return SpanGuard {
+                span: None,
+                previous_span: None,
+            }
+        }
+    };
+
+    let context = parent_span.context.new_child();
+    let mut span = Span::start(
+        context.clone(),
+        Some(parent_span.context.span_id.clone()),
+        name,
+        SpanKind::Internal,
+    );
+    if let Some(user_id) = user_id {
+        span.set_attribute("enduser.id_hash", parent_span.tracer.hash_user_id(user_id));
+    }
+
+    let previous_span = CURRENT_SPAN.with(|current| {
+        current.replace(Some(ActiveSpan {
+            tracer: parent_span.tracer.clone(),
+            context,
+        }))
+    });
+    SpanGuard {
+        span: Some((parent_span.tracer, span)),
+        previous_span,
+    }
+}
+
+/// Returns a random key of the hash of user ids.
+fn get_random_key() -> Vec<u8> {
+    let mut key = vec![0; 32];
+    OsRng.fill(&mut key[..]);
+    key
+}
+
+/// A future processing a request with its span as the current one. Requests are interleaved on a
+/// worker thread, so the span is set only while the future is polled.
+pub struct SpanScoped<F> {
+    active_span: Option<ActiveSpan>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for SpanScoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let SpanScoped {
+            active_span,
+            future,
+        } = &mut *self;
+        in_span_scope(active_span.clone(), || future.as_mut().poll(cx))
+    }
+}
+
+/// A middleware recording a span of each request, if the tracer is given.
+///
+/// The span continues the trace of `traceparent` header if it is valid, or starts a new trace.
+/// It is named after the matched route such as `GET /posts/{user_id}`, and is the parent of spans
+/// of services and repositories called by the handler. If the route has a `user_id` parameter,
+/// its hash is recorded.
+///
+/// # Arguments
+///
+/// * `req` - A request
+/// * `srv` - A service processing the request
+/// * `tracer` - A tracer recording spans, or `None` if tracing is disabled
+pub fn trace_span<S, B>(
+    req: ServiceRequest,
+    srv: &mut S,
+    tracer: Option<Arc<Tracer>>,
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    let parent_context = req
+        .headers()
+        .get(TRACEPARENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(SpanContext::from_traceparent);
+    let (context, parent_span_id) = match parent_context {
+        Some(parent_context) => (parent_context.new_child(), Some(parent_context.span_id)),
+        None => (SpanContext::new_root(), None),
+    };
+
+    let method = req.method().to_string();
+    let active_span = tracer
+        .filter(|_| context.sampled)
+        .map(|tracer| ActiveSpan { tracer, context });
+    let mut span = active_span.as_ref().map(|active_span| {
+        let mut span = Span::start(
+            active_span.context.clone(),
+            parent_span_id,
+            &method,
+            SpanKind::Server,
+        );
+        span.set_attribute("http.method", method.clone());
+        span.set_attribute("http.target", req.path().to_string());
+        if let Some(request_id) = log_util::get_request_id() {
+            span.set_attribute("request.id", request_id);
+        }
+        span
+    });
+
+    let response = in_span_scope(active_span.clone(), || srv.call(req));
+    let tracer = active_span
+        .as_ref()
+        .map(|active_span| active_span.tracer.clone());
+
+    SpanScoped {
+        active_span,
+        future: Box::pin(async move {
+            let response = response.await;
+            if let (Some(tracer), Some(mut span)) = (tracer, span.take()) {
+                match &response {
+                    Ok(response) => {
+                        let request = response.request();
+                        let route = request
+                            .match_pattern()
+                            .unwrap_or_else(|| String::from("unmatched"));
+                        span.name = format!("{} {}", method, route);
+                        span.set_attribute("operation.name", span.name.clone());
+                        span.set_attribute("http.route", route);
+                        span.set_attribute(
+                            "http.status_code",
+                            response.status().as_u16().to_string(),
+                        );
+                        if let Some(user_id) = request
+                            .match_info()
+                            .get("user_id")
+                            .and_then(|user_id| user_id.parse().ok())
+                        {
+                            span.set_attribute("enduser.id_hash", tracer.hash_user_id(user_id));
+                        }
+                        span.is_error = response.status().is_server_error();
+                    }
+                    Err(_) => span.is_error = true,
+                }
+                span.end_time_unix_nano = get_unix_nano();
+                tracer.record(span);
+            }
+            response
+        }),
+    }
+}
+
+/// Returns random bytes of the length as lowercase hex digits.
+fn get_random_hex(length: usize) -> String {
+    let mut hex = String::new();
+    for _ in 0..length {
+        let _ = write!(hex, "{:02x}", OsRng.gen::<u8>());
+    }
+    hex
+}
+
+/// Returns the current time in nanoseconds since the Unix epoch.
+fn get_unix_nano() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App, HttpResponse};
+
+    use super::*;
+
+    async fn find_post(path: web::Path<(u64, u64)>) -> HttpResponse {
+        let (user_id, _) = path.into_inner();
+        let _span = start_span("PostRepository::find", Some(user_id));
+        HttpResponse::Ok().finish()
+    }
+
+    #[test]
+    fn test_span_context_from_traceparent() {
+        let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let context = SpanContext::from_traceparent(traceparent).unwrap();
+        assert_eq!(context.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.span_id, "00f067aa0ba902b7");
+        assert!(context.sampled);
+        assert_eq!(context.to_traceparent(), traceparent);
+
+        assert!(SpanContext::from_traceparent(
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01"
+        )
+        .is_none());
+        assert!(SpanContext::from_traceparent(
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01"
+        )
+        .is_none());
+        assert!(SpanContext::from_traceparent("00-4bf92f3577b34da6-01").is_none());
+    }
+
+    #[test]
+    fn test_hash_user_id() {
+        let tracer = Tracer::with_otlp_exporter(
+            Some(String::from("http://localhost:4318")),
+            Some(String::from("Cg9Rk2WxT4bLm8Vz3HqYp6NdJs1FuE7a")),
+        )
+        .unwrap();
+        let same_key_tracer = Tracer::with_otlp_exporter(
+            Some(String::from("http://localhost:4318")),
+            Some(String::from("Cg9Rk2WxT4bLm8Vz3HqYp6NdJs1FuE7a")),
+        )
+        .unwrap();
+        let other_key_tracer = Tracer::new();
+
+        assert_eq!(tracer.hash_user_id(1).len(), 32);
+        assert_eq!(tracer.hash_user_id(1), same_key_tracer.hash_user_id(1));
+        assert_ne!(tracer.hash_user_id(1), tracer.hash_user_id(2));
+        assert_ne!(tracer.hash_user_id(1), other_key_tracer.hash_user_id(1));
+    }
+
+    #[test]
+    fn test_start_span_without_current_span() {
+        let span = start_span("PostRepository::find", Some(1));
+        assert!(span.span.is_none());
+        assert!(CURRENT_SPAN.with(|current| current.borrow().is_none()));
+    }
+
+    #[actix_rt::test]
+    async fn test_trace_span() {
+        let tracer = Arc::new(Tracer::new());
+        let middleware_tracer = tracer.clone();
+        let mut app = test::init_service(
+            App::new()
+                .wrap_fn(move |req, srv| trace_span(req, srv, Some(middleware_tracer.clone())))
+                .route("/posts/{user_id}/{id}", web::get().to(find_post)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/posts/1/2").to_request();
+        test::call_service(&mut app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/posts/1/3")
+            .header(
+                TRACEPARENT_HEADER,
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            )
+            .to_request();
+        test::call_service(&mut app, req).await;
+
+        let spans = tracer.take_spans();
+        let server_spans: Vec<&Span> = spans
+            .iter()
+            .filter(|span| span.kind == SpanKind::Server)
+            .collect();
+        assert_eq!(server_spans.len(), 2);
+        assert_eq!(spans.len(), 4);
+
+        let server_span = server_spans[1];
+        assert_eq!(server_span.name, "GET /posts/{user_id}/{id}");
+        assert_eq!(
+            server_span.context.trace_id,
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert_eq!(
+            server_span.parent_span_id,
+            Some(String::from("00f067aa0ba902b7"))
+        );
+        assert!(server_span
+            .attributes
+            .contains(&(String::from("enduser.id_hash"), tracer.hash_user_id(1))));
+        assert!(!server_span.attributes.iter().any(|(_, value)| value == "1"));
+
+        let repository_span = spans
+            .iter()
+            .find(|span| {
+                span.kind == SpanKind::Internal
+                    && span.parent_span_id == Some(server_span.context.span_id.clone())
+            })
+            .unwrap();
+        assert_eq!(repository_span.name, "PostRepository::find");
+        assert_eq!(
+            repository_span.context.trace_id,
+            server_span.context.trace_id
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_trace_span_disabled() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap_fn(|req, srv| trace_span(req, srv, None))
+                .route("/posts/{user_id}/{id}", web::get().to(find_post)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/posts/1/2").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert!(resp.status().is_success());
+    }
+}
@@ -0,0 +1,827 @@
+use chrono::{NaiveDateTime, Utc};
+use hmac::{Hmac, Mac, NewMac};
+use lazy_static::lazy_static;
+use mockall::automock;
+use reqwest::blocking::Client;
+use reqwest::header::{CONTENT_TYPE, HOST};
+use reqwest::{redirect, Url};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt::Write;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::models::webhook::{
+    Webhook, WebhookDeliveryDAO, WebhookRepository, WebhookRepositoryTrait,
+};
+use crate::utils::log_util;
+
+/// A header of a webhook request having the HMAC-SHA256 signature of the body as
+/// `sha256=<hex>`, signed with the secret of the webhook.
+pub const SIGNATURE_HEADER: &str = "X-Darim-Signature";
+/// A header of a webhook request having the name of the event.
+pub const EVENT_HEADER: &str = "X-Darim-Event";
+
+/// A maximum number of retries after a webhook request fails.
+const MAX_WEBHOOK_RETRIES: u32 = 3;
+/// A delay before the first retry, which doubles for each of the next retries.
+const WEBHOOK_RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+/// A timeout of a webhook request.
+const WEBHOOK_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Events of posts emitted to webhooks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WebhookEvent {
+    PostCreated,
+    PostUpdated,
+    PostDeleted,
+}
+
+impl WebhookEvent {
+    /// Returns the name of the event such as `post.created`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::PostCreated => "post.created",
+            WebhookEvent::PostUpdated => "post.updated",
+            WebhookEvent::PostDeleted => "post.deleted",
+        }
+    }
+}
+
+/// A body of a webhook request. It has only the metadata of the post, since the title and the
+/// content are encrypted by the client.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    pub event: String,
+    pub post_id: u64,
+    pub occurred_at: NaiveDateTime,
+}
+
+/// An event to be delivered to the webhooks of the user by the webhook worker.
+struct WebhookJob {
+    user_id: u64,
+    event: WebhookEvent,
+    body: String,
+    /// A webhook to which the event is retried, or `None` to deliver it to all webhooks of the user.
+    webhook_id: Option<u64>,
+    /// A number of failed attempts before this job.
+    retries: u32,
+}
+
+lazy_static! {
+    /// A queue of events to be delivered by the webhook worker in the background, and the worker.
+    /// It is taken on shutdown, after which events are not enqueued anymore.
+    static ref WEBHOOK_QUEUE: Mutex<Option<(Sender<WebhookJob>, JoinHandle<()>)>> =
+        Mutex::new(Some(start_webhook_worker(deliver_job)));
+}
+
+/// A dispatcher of webhook events, which is injected into services to be replaced in tests.
+/// Events are delivered in the background through the webhook queue.
+pub struct WebhookDispatcher;
+
+#[automock]
+pub trait WebhookDispatcherTrait {
+    fn new() -> Self;
+    fn dispatch(&self, user_id: u64, event: WebhookEvent, post_id: u64) -> bool;
+}
+
+impl WebhookDispatcher {
+    /// Creates a new webhook dispatcher.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Enqueues the event of the post to be delivered to the webhooks of the user, and returns
+    /// whether it is enqueued.
+    pub fn dispatch(&self, user_id: u64, event: WebhookEvent, post_id: u64) -> bool {
+        let payload = WebhookPayload {
+            event: event.name().to_string(),
+            post_id,
+            occurred_at: Utc::now().naive_utc(),
+        };
+        let body = match serde_json::to_string(&payload) {
+            Ok(body) => body,
+            Err(_) => return false,
+        };
+
+        enqueue_job(WebhookJob {
+            user_id,
+            event,
+            body,
+            webhook_id: None,
+            retries: 0,
+        })
+    }
+}
+
+impl Default for WebhookDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the signature of the body as `sha256=<hex>`, which is the HMAC-SHA256 of the body
+/// with the secret. Receivers verify it to check that the request is sent by Darim.
+///
+/// # Arguments
+///
+/// * `secret` - A secret of the webhook
+/// * `body` - A body of the webhook request
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take a key of any size");
+    mac.update(body.as_bytes());
+
+    let mut signature = String::from("sha256=");
+    for byte in mac.finalize().into_bytes() {
+        let _ = write!(signature, "{:02x}", byte);
+    }
+    signature
+}
+
+/// A resolver of the addresses of a host, which is injected into services to be replaced in tests.
+pub struct HostResolver {}
+
+#[automock]
+pub trait HostResolverTrait {
+    fn resolve(&self, host: &str, port: u16) -> Result<Vec<IpAddr>, String>;
+}
+
+impl HostResolver {
+    /// Creates a new host resolver.
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Resolves the host to its addresses with the resolver of the system.
+    pub fn resolve(&self, host: &str, port: u16) -> Result<Vec<IpAddr>, String> {
+        (host, port)
+            .to_socket_addrs()
+            .map(|addresses| addresses.map(|address| address.ip()).collect())
+            .map_err(|error| error.to_string())
+    }
+}
+
+impl HostResolverTrait for HostResolver {
+    fn resolve(&self, host: &str, port: u16) -> Result<Vec<IpAddr>, String> {
+        HostResolver::resolve(self, host, port)
+    }
+}
+
+impl Default for HostResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns whether the IPv4 address is reachable only from the host or its network: a loopback,
+/// private, link-local, shared (`100.64.0.0/10`), broadcast or unspecified address.
+fn is_internal_ipv4_address(ip: &Ipv4Addr) -> bool {
+    let octets = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_unspecified()
+        || (octets[0] == 100 && (octets[1] & 0xc0) == 64)
+}
+
+/// Returns whether the IPv6 address is reachable only from the host or its network: a loopback,
+/// unique local (`fc00::/7`), link-local (`fe80::/10`) or unspecified address, or an IPv4-mapped
+/// address of an internal IPv4 address.
+fn is_internal_ipv6_address(ip: &Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    let is_ipv4_mapped = segments[..5].iter().all(|segment| *segment == 0) && segments[5] == 0xffff;
+    if is_ipv4_mapped {
+        let [a, b] = segments[6].to_be_bytes();
+        let [c, d] = segments[7].to_be_bytes();
+        return is_internal_ipv4_address(&Ipv4Addr::new(a, b, c, d));
+    }
+
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || (segments[0] & 0xfe00) == 0xfc00
+        || (segments[0] & 0xffc0) == 0xfe80
+}
+
+/// Returns whether the address is reachable only from the host or its network, such as the
+/// address of the server itself, the private network, or the metadata service of the cloud.
+///
+/// # Arguments
+///
+/// * `ip` - An IP address
+pub fn is_internal_address(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_internal_ipv4_address(ip),
+        IpAddr::V6(ip) => is_internal_ipv6_address(ip),
+    }
+}
+
+/// Checks that the host of the URL resolves only to public addresses, so that a webhook can't
+/// reach the server itself or the internal network, and returns the first of them.
+/// An IP address host is checked as it is.
+/// Returns an error if the URL is malformed, the host can't be resolved, or any of its addresses
+/// is internal.
+///
+/// # Arguments
+///
+/// * `resolver` - A resolver of the host
+/// * `url` - A URL of the webhook
+pub fn verify_public_url<R: HostResolverTrait>(resolver: &R, url: &str) -> Result<IpAddr, String> {
+    let url = Url::parse(url).map_err(|error| error.to_string())?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| String::from("the URL has no host"))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| String::from("the URL has no port"))?;
+
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addresses = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => resolver.resolve(host, port)?,
+    };
+    if addresses.is_empty() {
+        return Err(format!("`{}` can't be resolved", host));
+    }
+
+    match addresses.iter().find(|ip| is_internal_address(ip)) {
+        Some(ip) => Err(format!("`{}` resolves to an internal address {}", host, ip)),
+        None => Ok(addresses[0]),
+    }
+}
+
+/// Returns the URL to which the request is sent, and the `Host` header of the request.
+/// An `http` URL is pinned to the verified address with the original host in the header, so that
+/// the host can't be resolved again to an internal address by DNS rebinding. An `https` URL is
+/// kept as it is, because the certificate is verified against the host, and a rebound address
+/// fails the handshake before the request is sent.
+///
+/// # Arguments
+///
+/// * `url` - A URL of the webhook
+/// * `ip` - An address of the host verified by `verify_public_url`
+fn get_pinned_url(url: &str, ip: IpAddr) -> Result<(Url, Option<String>), String> {
+    let mut url = Url::parse(url).map_err(|error| error.to_string())?;
+    if url.scheme() != "http" {
+        return Ok((url, None));
+    }
+
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err(String::from("the URL has no host")),
+    };
+    url.set_ip_host(ip)
+        .map_err(|_| String::from("the URL can't have an IP address host"))?;
+    Ok((url, Some(host)))
+}
+
+/// A transport posting webhook requests, which returns the status code of the response.
+#[automock]
+pub trait WebhookTransport {
+    fn post(&self, url: &str, event: &str, body: &str, signature: &str) -> Result<u16, String>;
+}
+
+/// A transport posting webhook requests over HTTP.
+pub struct HttpWebhookTransport;
+
+impl WebhookTransport for HttpWebhookTransport {
+    /// Posts the body as JSON with the event and signature headers.
+    /// The host is checked again before each request, as its addresses may have changed since the
+    /// webhook is registered, and the request is sent to the checked address. Redirects are not
+    /// followed, not to be redirected to an internal address.
+    fn post(&self, url: &str, event: &str, body: &str, signature: &str) -> Result<u16, String> {
+        let ip = verify_public_url(&HostResolver::new(), url)?;
+        let (url, host) = get_pinned_url(url, ip)?;
+
+        let mut request = Client::builder()
+            .timeout(WEBHOOK_REQUEST_TIMEOUT)
+            .redirect(redirect::Policy::none())
+            .build()
+            .map_err(|error| error.to_string())?
+            .post(url);
+        if let Some(host) = host {
+            request = request.header(HOST, host);
+        }
+
+        let response = request
+            .header(CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, event)
+            .header(SIGNATURE_HEADER, signature)
+            .body(body.to_string())
+            .send()
+            .map_err(|error| error.to_string())?;
+
+        Ok(response.status().as_u16())
+    }
+}
+
+/// Delivers the signed event to the webhook with the transport once, and logs the attempt as a
+/// delivery of the webhook. A failure is an error of the request or a response with a non-2xx
+/// status code. Returns whether the event is delivered.
+///
+/// # Arguments
+///
+/// * `transport` - A transport posting the request
+/// * `repository` - A repository logging the deliveries
+/// * `webhook` - A webhook to which the event is delivered
+/// * `event` - An event to be delivered
+/// * `body` - A JSON body of the request
+/// * `attempt` - A number of the attempt starting from 1
+pub fn deliver<T: WebhookTransport, R: WebhookRepositoryTrait>(
+    transport: &T,
+    repository: &R,
+    webhook: &Webhook,
+    event: WebhookEvent,
+    body: &str,
+    attempt: u32,
+) -> bool {
+    let signature = sign(&webhook.secret, body);
+    let status_code = match transport.post(&webhook.url, event.name(), body, &signature) {
+        Ok(status_code) => Some(status_code),
+        Err(error) => {
+            log_util::log(&error);
+            None
+        }
+    };
+    let is_success = matches!(status_code, Some(code) if (200..300).contains(&code));
+
+    let _ = repository.create_delivery(&WebhookDeliveryDAO {
+        webhook_id: webhook.id,
+        event: event.name().to_string(),
+        payload: body.to_string(),
+        attempt,
+        status_code,
+        is_success,
+    });
+
+    is_success
+}
+
+/// Returns the delay before retrying a failed delivery with exponential backoff, or `None` if it
+/// has already been retried `max_retries` times.
+///
+/// # Arguments
+///
+/// * `retries` - A number of failed attempts before the failed one
+/// * `max_retries` - A maximum number of retries
+/// * `base_delay` - A delay before the first retry, which doubles for each of the next retries
+fn get_retry_delay(retries: u32, max_retries: u32, base_delay: Duration) -> Option<Duration> {
+    if retries < max_retries {
+        Some(base_delay * 2u32.pow(retries))
+    } else {
+        None
+    }
+}
+
+/// Delivers the event to the webhooks of the job once, and returns the jobs to retry the failed
+/// deliveries with their delays. A webhook deleted before its retry is not retried.
+fn deliver_job(job: &WebhookJob) -> Vec<(WebhookJob, Duration)> {
+    let repository = WebhookRepository::new();
+    let webhooks = match repository.find_all_by_user_id(job.user_id) {
+        Ok(webhooks) => webhooks,
+        Err(_) => return vec![],
+    };
+
+    webhooks
+        .iter()
+        .filter(|webhook| !matches!(job.webhook_id, Some(id) if id != webhook.id))
+        .filter(|webhook| {
+            !deliver(
+                &HttpWebhookTransport,
+                &repository,
+                webhook,
+                job.event,
+                &job.body,
+                job.retries + 1,
+            )
+        })
+        .filter_map(|webhook| {
+            let delay =
+                get_retry_delay(job.retries, MAX_WEBHOOK_RETRIES, WEBHOOK_RETRY_BASE_DELAY)?;
+            let retry = WebhookJob {
+                user_id: job.user_id,
+                event: job.event,
+                body: job.body.clone(),
+                webhook_id: Some(webhook.id),
+                retries: job.retries + 1,
+            };
+            Some((retry, delay))
+        })
+        .collect()
+}
+
+/// Enqueues the event to be delivered by the webhook worker.
+fn enqueue_job(job: WebhookJob) -> bool {
+    match WEBHOOK_QUEUE.lock() {
+        Ok(queue) => match queue.as_ref() {
+            Some((sender, _)) => sender.send(job).is_ok(),
+            None => false,
+        },
+        Err(_) => false,
+    }
+}
+
+/// Stops the webhook worker after it delivers all queued events and their retries, and returns
+/// whether the queue is drained within the grace period. Events dispatched after this are not
+/// delivered.
+///
+/// # Arguments
+///
+/// * `grace_period` - A maximum time to wait for the queued events to be delivered
+pub fn shutdown_webhook_worker(grace_period: Duration) -> bool {
+    let webhook_queue = match WEBHOOK_QUEUE.lock() {
+        Ok(mut queue) => queue.take(),
+        Err(_) => None,
+    };
+
+    if let Some((sender, worker)) = webhook_queue {
+        drop(sender);
+
+        let (finished_sender, finished_receiver) = mpsc::channel::<()>();
+        thread::spawn(move || {
+            if worker.join().is_ok() {
+                let _ = finished_sender.send(());
+            }
+        });
+
+        finished_receiver.recv_timeout(grace_period).is_ok()
+    } else {
+        true
+    }
+}
+
+/// Starts the worker delivering events of the returned queue one by one in the background.
+/// Jobs returned by `deliver` are retried after their delays, while the events queued in the
+/// meantime are delivered, so that a failing webhook doesn't hold up the others.
+/// The worker stops when the queue is dropped and all retries are done.
+///
+/// # Arguments
+///
+/// * `deliver` - A function delivering an event, which returns the jobs to be retried with their
+///   delays
+fn start_webhook_worker<F>(deliver: F) -> (Sender<WebhookJob>, JoinHandle<()>)
+where
+    F: Fn(&WebhookJob) -> Vec<(WebhookJob, Duration)> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel::<WebhookJob>();
+    let worker = thread::spawn(move || {
+        let mut retries: Vec<(Instant, WebhookJob)> = vec![];
+        let mut is_queue_open = true;
+        let schedule = |retries: &mut Vec<(Instant, WebhookJob)>, job: &WebhookJob| {
+            for (retry, delay) in deliver(job) {
+                retries.push((Instant::now() + delay, retry));
+            }
+        };
+
+        while is_queue_open || !retries.is_empty() {
+            let now = Instant::now();
+            let (due_retries, pending_retries): (Vec<_>, Vec<_>) =
+                retries.into_iter().partition(|(due_at, _)| *due_at <= now);
+            retries = pending_retries;
+            for (_, job) in due_retries {
+                schedule(&mut retries, &job);
+            }
+
+            let next_due_at = retries.iter().map(|(due_at, _)| *due_at).min();
+            let job = match (is_queue_open, next_due_at) {
+                (true, Some(due_at)) => {
+                    match receiver.recv_timeout(due_at.saturating_duration_since(Instant::now())) {
+                        Ok(job) => Some(job),
+                        Err(RecvTimeoutError::Timeout) => None,
+                        Err(RecvTimeoutError::Disconnected) => {
+                            is_queue_open = false;
+                            None
+                        }
+                    }
+                }
+                (true, None) => match receiver.recv() {
+                    Ok(job) => Some(job),
+                    Err(_) => {
+                        is_queue_open = false;
+                        None
+                    }
+                },
+                (false, Some(due_at)) => {
+                    thread::sleep(due_at.saturating_duration_since(Instant::now()));
+                    None
+                }
+                (false, None) => None,
+            };
+
+            if let Some(job) = job {
+                schedule(&mut retries, &job);
+            }
+        }
+    });
+
+    (sender, worker)
+}
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate::*;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::models::webhook::MockWebhookRepositoryTrait;
+    use crate::utils::test_util::get_webhook;
+
+    #[test]
+    fn test_is_internal_address() {
+        for ip in &[
+            "127.0.0.1",
+            "127.8.8.8",
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(is_internal_address(&ip.parse().unwrap()), "{}", ip);
+        }
+
+        for ip in &["93.184.216.34", "172.32.0.1", "2606:2800:220:1::1"] {
+            assert!(!is_internal_address(&ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_verify_public_url_with_internal_address() {
+        let mut mocked_host_resolver = MockHostResolverTrait::default();
+        mocked_host_resolver.expect_resolve().times(0);
+
+        for url in &[
+            "http://127.0.0.1:8080/posts/1",
+            "http://[::1]/hooks",
+            "http://169.254.169.254/latest/meta-data",
+            "https://10.0.0.1/hooks",
+            "http://0.0.0.0:3306",
+        ] {
+            assert!(
+                verify_public_url(&mocked_host_resolver, url).is_err(),
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_public_url_with_resolved_host() {
+        let mut mocked_host_resolver = MockHostResolverTrait::default();
+        mocked_host_resolver
+            .expect_resolve()
+            .with(eq("example.com"), eq(443))
+            .times(1)
+            .returning(|_, _| Ok(vec!["93.184.216.34".parse().unwrap()]));
+        mocked_host_resolver
+            .expect_resolve()
+            .with(eq("localhost"), eq(80))
+            .times(1)
+            .returning(|_, _| Ok(vec!["127.0.0.1".parse().unwrap()]));
+        mocked_host_resolver
+            .expect_resolve()
+            .with(eq("mixed.example.com"), eq(443))
+            .times(1)
+            .returning(|_, _| {
+                Ok(vec![
+                    "93.184.216.34".parse().unwrap(),
+                    "192.168.0.10".parse().unwrap(),
+                ])
+            });
+
+        assert!(verify_public_url(&mocked_host_resolver, "https://example.com/hooks").is_ok());
+        assert!(verify_public_url(&mocked_host_resolver, "http://localhost/hooks").is_err());
+        assert!(
+            verify_public_url(&mocked_host_resolver, "https://mixed.example.com/hooks").is_err()
+        );
+    }
+
+    #[test]
+    fn test_sign() {
+        assert_eq!(
+            sign("key", "The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn test_get_pinned_url() {
+        let ip: IpAddr = "93.184.216.34".parse().unwrap();
+
+        let (url, host) = get_pinned_url("http://example.com:8080/hooks?id=1", ip).unwrap();
+        assert_eq!(url.as_str(), "http://93.184.216.34:8080/hooks?id=1");
+        assert_eq!(host, Some(String::from("example.com:8080")));
+
+        let (url, host) = get_pinned_url("http://example.com/hooks", ip).unwrap();
+        assert_eq!(url.as_str(), "http://93.184.216.34/hooks");
+        assert_eq!(host, Some(String::from("example.com")));
+
+        let (url, host) = get_pinned_url(
+            "http://example.com/hooks",
+            "2606:2800:220:1::1".parse().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(url.as_str(), "http://[2606:2800:220:1::1]/hooks");
+        assert_eq!(host, Some(String::from("example.com")));
+
+        let (url, host) = get_pinned_url("https://example.com/hooks", ip).unwrap();
+        assert_eq!(url.as_str(), "https://example.com/hooks");
+        assert_eq!(host, None);
+    }
+
+    #[test]
+    fn test_deliver_signed_delivery() {
+        let webhook = get_webhook(3, 10);
+        let body = r#"{"event":"post.created","post_id":1,"occurred_at":"2020-04-12T07:43:03"}"#;
+        let signature = sign(&webhook.secret, body);
+
+        let mut mocked_transport = MockWebhookTransport::new();
+        mocked_transport
+            .expect_post()
+            .with(
+                eq("https://example.com/hooks/darim"),
+                eq("post.created"),
+                eq(body),
+                function(move |passed_signature: &str| passed_signature == signature),
+            )
+            .times(1)
+            .returning(|_, _, _, _| Ok(200));
+
+        let mut mocked_webhook_repository = MockWebhookRepositoryTrait::new();
+        mocked_webhook_repository
+            .expect_create_delivery()
+            .with(eq(WebhookDeliveryDAO {
+                webhook_id: 3,
+                event: String::from("post.created"),
+                payload: body.to_string(),
+                attempt: 1,
+                status_code: Some(200),
+                is_success: true,
+            }))
+            .times(1)
+            .returning(|_| Ok(true));
+
+        assert!(deliver(
+            &mocked_transport,
+            &mocked_webhook_repository,
+            &webhook,
+            WebhookEvent::PostCreated,
+            body,
+            1,
+        ));
+    }
+
+    #[test]
+    fn test_deliver_failed_delivery() {
+        let mut mocked_transport = MockWebhookTransport::new();
+        mocked_transport
+            .expect_post()
+            .times(1)
+            .returning(|_, _, _, _| Err(String::from("connection refused")));
+        mocked_transport
+            .expect_post()
+            .times(1)
+            .returning(|_, _, _, _| Ok(503));
+
+        let deliveries = Arc::new(Mutex::new(vec![]));
+        let logged_deliveries = deliveries.clone();
+        let mut mocked_webhook_repository = MockWebhookRepositoryTrait::new();
+        mocked_webhook_repository
+            .expect_create_delivery()
+            .times(2)
+            .returning(move |delivery| {
+                logged_deliveries.lock().unwrap().push((
+                    delivery.attempt,
+                    delivery.status_code,
+                    delivery.is_success,
+                ));
+                Ok(true)
+            });
+
+        for attempt in 1..=2 {
+            assert!(!deliver(
+                &mocked_transport,
+                &mocked_webhook_repository,
+                &get_webhook(3, 10),
+                WebhookEvent::PostUpdated,
+                r#"{"event":"post.updated"}"#,
+                attempt,
+            ));
+        }
+        assert_eq!(
+            *deliveries.lock().unwrap(),
+            vec![(1, None, false), (2, Some(503), false)]
+        );
+    }
+
+    #[test]
+    fn test_get_retry_delay() {
+        let base_delay = Duration::from_secs(1);
+        assert_eq!(
+            get_retry_delay(0, 3, base_delay),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            get_retry_delay(1, 3, base_delay),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            get_retry_delay(2, 3, base_delay),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(get_retry_delay(3, 3, base_delay), None);
+    }
+
+    fn get_job(user_id: u64, post_id: u64) -> WebhookJob {
+        let payload = WebhookPayload {
+            event: WebhookEvent::PostCreated.name().to_string(),
+            post_id,
+            occurred_at: Utc::now().naive_utc(),
+        };
+        WebhookJob {
+            user_id,
+            event: WebhookEvent::PostCreated,
+            body: serde_json::to_string(&payload).unwrap(),
+            webhook_id: None,
+            retries: 0,
+        }
+    }
+
+    #[test]
+    fn test_start_webhook_worker() {
+        let delivered_post_ids = Arc::new(Mutex::new(vec![]));
+        let worker_delivered_post_ids = delivered_post_ids.clone();
+        let (queue, worker) = start_webhook_worker(move |job| {
+            let payload: WebhookPayload = serde_json::from_str(&job.body).unwrap();
+            worker_delivered_post_ids
+                .lock()
+                .unwrap()
+                .push((job.user_id, payload.post_id));
+            vec![]
+        });
+
+        for post_id in 1..=3 {
+            assert!(queue.send(get_job(10, post_id)).is_ok());
+        }
+
+        drop(queue);
+        assert!(worker.join().is_ok());
+        assert_eq!(
+            *delivered_post_ids.lock().unwrap(),
+            vec![(10, 1), (10, 2), (10, 3)]
+        );
+    }
+
+    #[test]
+    fn test_start_webhook_worker_with_retry() {
+        let deliveries = Arc::new(Mutex::new(vec![]));
+        let worker_deliveries = deliveries.clone();
+        let (queue, worker) = start_webhook_worker(move |job| {
+            let payload: WebhookPayload = serde_json::from_str(&job.body).unwrap();
+            worker_deliveries
+                .lock()
+                .unwrap()
+                .push((job.user_id, payload.post_id, job.retries));
+
+            // The delivery to the user 10 fails once, and is retried after a while.
+            if job.user_id == 10 && job.retries == 0 {
+                let retry = WebhookJob {
+                    user_id: job.user_id,
+                    event: job.event,
+                    body: job.body.clone(),
+                    webhook_id: Some(3),
+                    retries: 1,
+                };
+                vec![(retry, Duration::from_millis(300))]
+            } else {
+                vec![]
+            }
+        });
+
+        assert!(queue.send(get_job(10, 1)).is_ok());
+        thread::sleep(Duration::from_millis(50));
+        assert!(queue.send(get_job(11, 2)).is_ok());
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(*deliveries.lock().unwrap(), vec![(10, 1, 0), (11, 2, 0)]);
+
+        drop(queue);
+        assert!(worker.join().is_ok());
+        assert_eq!(
+            *deliveries.lock().unwrap(),
+            vec![(10, 1, 0), (11, 2, 0), (10, 1, 1)]
+        );
+    }
+}